@@ -0,0 +1,17 @@
+fn main() {
+    // Only the API server (behind `cli`) serves gRPC.
+    if std::env::var_os("CARGO_FEATURE_CLI").is_none() {
+        return;
+    }
+    println!("cargo:rerun-if-changed=proto/swiftscope.proto");
+    let protoc = protoc_bin_vendored::protoc_bin_path().expect("no vendored protoc for this host");
+    std::env::set_var("PROTOC", protoc);
+    let include = protoc_bin_vendored::include_path().expect("no vendored protobuf includes");
+    tonic_build::configure()
+        .build_client(false)
+        .compile_protos(
+            &["proto/swiftscope.proto"],
+            &[std::path::Path::new("proto"), &include],
+        )
+        .expect("failed to compile proto/swiftscope.proto");
+}
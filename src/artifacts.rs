@@ -0,0 +1,141 @@
+//! Per-session artifact directory.
+//!
+//! Actions that produce files (build logs, crash reports, traces, reports)
+//! store them here so clients can list and download them by id instead of
+//! receiving large or binary payloads inline. The default directory is a
+//! fresh temporary one that is removed when the store is dropped.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::Serialize;
+
+pub type ArtifactId = u32;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Artifact {
+    pub id: ArtifactId,
+    /// What produced it, e.g. `build-log` or `crash-report`.
+    pub kind: String,
+    pub name: String,
+    pub size: u64,
+    pub created_ms: u64,
+    #[serde(skip)]
+    pub path: PathBuf,
+}
+
+#[derive(Debug)]
+pub struct ArtifactStore {
+    dir: PathBuf,
+    /// Remove `dir` on drop; false for a directory the user chose.
+    owned: bool,
+    artifacts: Mutex<Vec<Artifact>>,
+}
+
+impl ArtifactStore {
+    /// Uses `dir` (created if missing) or a new directory under the system
+    /// temp dir.
+    pub fn create(dir: Option<&Path>) -> io::Result<Self> {
+        let (dir, owned) = match dir {
+            Some(dir) => (dir.to_path_buf(), false),
+            None => (
+                std::env::temp_dir().join(format!(
+                    "swiftscope-{}-{}",
+                    std::process::id(),
+                    now_ms()
+                )),
+                true,
+            ),
+        };
+        fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir,
+            owned,
+            artifacts: Mutex::new(Vec::new()),
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Writes `contents` as a new artifact. `name` is reduced to its file name
+    /// and prefixed with the id, so artifacts never overwrite each other.
+    pub fn add(&self, kind: &str, name: &str, contents: &[u8]) -> io::Result<Artifact> {
+        let mut artifacts = self
+            .artifacts
+            .lock()
+            .map_err(|_| io::Error::other("artifact store poisoned"))?;
+        let id = artifacts.last().map_or(1, |last| last.id + 1);
+        let name = Path::new(name)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .filter(|name| !name.is_empty())
+            .unwrap_or("artifact")
+            .to_string();
+        let path = self.dir.join(format!("{id}-{name}"));
+        fs::write(&path, contents)?;
+        let artifact = Artifact {
+            id,
+            kind: kind.to_string(),
+            name,
+            size: contents.len() as u64,
+            created_ms: now_ms(),
+            path,
+        };
+        artifacts.push(artifact.clone());
+        Ok(artifact)
+    }
+
+    pub fn list(&self) -> Vec<Artifact> {
+        self.artifacts
+            .lock()
+            .map(|artifacts| artifacts.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn get(&self, id: ArtifactId) -> Option<Artifact> {
+        self.list().into_iter().find(|artifact| artifact.id == id)
+    }
+}
+
+impl Drop for ArtifactStore {
+    fn drop(&mut self) {
+        if self.owned {
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stores_lists_and_cleans_up() {
+        let store = ArtifactStore::create(None).unwrap();
+        let dir = store.dir().to_path_buf();
+        let log = store.add("build-log", "build.log", b"ok").unwrap();
+        let report = store.add("report", "../../etc/passwd", b"{}").unwrap();
+        assert_eq!((log.id, report.id), (1, 2));
+        assert_eq!(report.name, "passwd");
+        assert!(report.path.starts_with(&dir));
+        assert_eq!(fs::read(&log.path).unwrap(), b"ok");
+        assert_eq!(store.list().len(), 2);
+        assert_eq!(store.get(2), Some(report));
+        assert_eq!(store.get(3), None);
+        drop(store);
+        assert!(!dir.exists());
+    }
+}
@@ -1,20 +1,107 @@
-use std::{collections::HashMap, fs, path::Path};
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    fmt, fs,
+    path::Path,
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use anyhow::{Context as AnyhowContext, Result as AnyResult};
+use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 
 use crate::{
-    gdb_remote::{GdbRemoteClient, StopReason, StopReply},
-    symbols::SymbolContext,
+    disasm::{self, FrameEffect},
+    dwarf::{
+        self, arm64_register_name, format_register_value, load_section_vec, FunctionIndex,
+        TypeChildren, TypeOffset, ValueKind, ValueLayout, ValueType, VariableLocation,
+    },
+    exports,
+    fault::FaultInjector,
+    formatters::FormatterRegistry,
+    gdb_remote::{
+        GdbRemoteClient, Interrupter, LoadedImage, MachException, MemoryRegion, ProcessExit,
+        RegisterInfo, StopReason, StopReply, ThreadRunState,
+    },
+    heap::{self, HeapObject},
+    memory::{le_u64, read_u64, CODE_POINTER_MASK},
+    mock::{MockFixture, MockFrame, MockTarget},
+    objc::{self, ClassDump, ClassEntry},
+    protocol_trace::ProtocolTrace,
+    swift_tasks::{self, ActorState, Task, TaskState, ThreadRegisters},
+    symbols::{self, format_uuid, LoaderFrame, SymbolContext, SymbolMatch},
 };
 use gimli::{
-    self, EndianSlice, IncompleteLineProgram, LineProgramHeader, LineRow, RunTimeEndian, SectionId,
-    Unit,
+    self, EndianSlice, IncompleteLineProgram, LineProgramHeader, LineRow, RunTimeEndian, Unit,
 };
-use object::{Object, ObjectSection};
+use object::Object;
 
 type FrameProvider = dyn Fn(i64) -> Vec<(i64, u64)> + Send + Sync;
 
+/// Upper bound on a single memory read or write, keeping payloads reasonably sized.
+pub const MAX_MEMORY_READ: usize = 64 * 1024;
+
+/// Mapped regions `memory_regions` lists before giving up on the walk.
+pub const MAX_MEMORY_REGIONS: usize = 4096;
+/// Most classes `objc_classes` reports.
+pub const MAX_OBJC_CLASSES: usize = 4096;
+
+/// Longest description `describe` reads back from the target.
+pub const MAX_DESCRIPTION_LEN: usize = 64 * 1024;
+
+/// Most elements an expanded array lists at a time.
+pub const MAX_ARRAY_ELEMENTS: u64 = 256;
+
+/// Stack the arm64 ABI lets leaf code use below `sp`, left alone by calls.
+const CALL_RED_ZONE: u64 = 128;
+
+/// How long an interrupted call gets to report its stop.
+const CALL_INTERRUPT_GRACE: Duration = Duration::from_secs(1);
+
+/// `x0`-`x7` and `d0`-`d7`: the registers arm64 passes arguments in.
+const ARGUMENT_REGISTERS: usize = 8;
+
+/// The signal a killed process reports.
+const SIGKILL: u8 = 9;
+
+/// How far into a function [`Backend::step_out`] looks for the instruction
+/// that sets the frame pointer up; prologues save at most six register pairs
+/// before it.
+const MAX_PROLOGUE_BYTES: u64 = 16 * 4;
+
+/// How far past the stop [`Backend::step_out`] looks for an epilogue.
+const EPILOGUE_SCAN_BYTES: usize = 8 * 4;
+
+const LIBOBJC: &str = "libobjc.A.dylib";
+
+/// Offset of `notification` in `dyld_all_image_infos`, after `version`,
+/// `infoArrayCount` and `infoArray`.
+const DYLD_NOTIFICATION_OFFSET: u64 = 16;
+
+/// arm64 debugserver register layout used when `qRegisterInfo` is unavailable.
+fn arm64_fallback_registers() -> Vec<RegisterInfo> {
+    let register =
+        |number: u32, name: String, alt_name: Option<&str>, generic: Option<&str>| RegisterInfo {
+            number,
+            name,
+            alt_name: alt_name.map(str::to_string),
+            generic: generic.map(str::to_string),
+            bitsize: if number == 33 { 32 } else { 64 },
+            offset: Some(number as usize * 8),
+            set: Some("General Purpose Registers".into()),
+        };
+    let mut registers: Vec<RegisterInfo> = (0..29)
+        .map(|n| register(n, format!("x{n}"), None, (n == 0).then_some("arg1")))
+        .collect();
+    registers.push(register(29, "fp".into(), Some("x29"), Some("fp")));
+    registers.push(register(30, "lr".into(), Some("x30"), Some("ra")));
+    registers.push(register(31, "sp".into(), Some("x31"), Some("sp")));
+    registers.push(register(32, "pc".into(), None, Some("pc")));
+    registers.push(register(33, "cpsr".into(), None, Some("flags")));
+    registers
+}
+
 /// Backend stub that pretends to talk to debugserver/LLDB.
 pub struct Backend {
     pub symbol_ctx: SymbolContext,
@@ -22,7 +109,69 @@ pub struct Backend {
     breakpoints: HashMap<String, Vec<i64>>,
     frame_provider: Option<Box<FrameProvider>>,
     line_index: Option<LineIndex>,
+    function_index: Option<FunctionIndex>,
     gdb_client: Option<GdbRemoteClient>,
+    faults: Option<Arc<FaultInjector>>,
+    trace: Option<Arc<ProtocolTrace>>,
+    /// Threads left stopped when the process resumes.
+    suspended_threads: BTreeSet<i64>,
+    /// Addresses of runtime functions found by [`Backend::runtime_symbol`],
+    /// keyed by `image`symbol`; valid for the current connection only.
+    runtime_symbols: HashMap<String, u64>,
+    /// The inferior's pid, once asked for on the current connection.
+    process_id: Option<u64>,
+    /// Summary providers for standard-library and Foundation types.
+    formatters: FormatterRegistry,
+    /// Scripted target served instead of debugserver; see [`Backend::new_mock`].
+    mock: Option<MockTarget>,
+    /// What the other threads do while one is stepped.
+    step_scope: StepScope,
+    /// Addresses breakpoints were planted on through [`Backend::plant_line`]
+    /// and [`Backend::plant_address`]; temporary ones leave these alone.
+    planted: HashSet<u64>,
+}
+
+/// Which threads run while [`Backend::step_over`] steps one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StepScope {
+    /// Only the stepped thread; the others stay stopped.
+    #[default]
+    Thread,
+    /// Every thread that is not suspended.
+    All,
+}
+
+impl FromStr for StepScope {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text {
+            "thread" => Ok(Self::Thread),
+            "all" => Ok(Self::All),
+            other => Err(format!(
+                "unknown step scope `{other}` (expected thread or all)"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for StepScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Thread => "thread",
+            Self::All => "all",
+        })
+    }
+}
+
+/// An argument to a function called in the target with
+/// [`Backend::call_function`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CallArgument {
+    Value(u64),
+    /// Copied below the caller's stack; the function receives its address.
+    Bytes(Vec<u8>),
 }
 
 impl Backend {
@@ -33,7 +182,17 @@ impl Backend {
             breakpoints: HashMap::new(),
             frame_provider: None,
             line_index: None,
+            function_index: None,
             gdb_client: None,
+            faults: None,
+            trace: None,
+            suspended_threads: BTreeSet::new(),
+            runtime_symbols: HashMap::new(),
+            process_id: None,
+            formatters: FormatterRegistry::swift(),
+            mock: None,
+            step_scope: StepScope::default(),
+            planted: HashSet::new(),
         }
     }
 
@@ -42,11 +201,29 @@ impl Backend {
         Self::from_symbol_context(symbol_ctx)
     }
 
-    pub fn new_from_app(app_path: &Path) -> AnyResult<Self> {
-        let symbol_ctx = SymbolContext::new(app_path)?;
+    /// Loads `app_path` for symbolication; see [`SymbolContext::new`] for how
+    /// `dsym` is used and looked for, and which slice of a universal binary
+    /// is loaded.
+    pub fn new_from_app(
+        app_path: &Path,
+        dsym: Option<&Path>,
+        arch: Option<&str>,
+    ) -> AnyResult<Self> {
+        let symbol_ctx = SymbolContext::new(app_path, dsym, arch)?;
         Ok(Self::from_symbol_context(symbol_ctx))
     }
 
+    /// A backend that plays `fixture` back instead of talking to
+    /// debugserver: connecting attaches to its threads, resuming reports its
+    /// stops in order, and anything else that needs the process fails.
+    /// `symbol_ctx` still answers symbol and line lookups.
+    pub fn new_mock(symbol_ctx: SymbolContext, fixture: MockFixture) -> Self {
+        Self {
+            mock: Some(MockTarget::new(fixture)),
+            ..Self::from_symbol_context(symbol_ctx)
+        }
+    }
+
     #[allow(dead_code)]
     pub fn set_frame_provider<F>(&mut self, provider: F)
     where
@@ -55,18 +232,51 @@ impl Backend {
         self.frame_provider = Some(Box::new(provider));
     }
 
-    #[allow(dead_code)]
     pub fn update_slide_from_remote_text_base(&mut self, remote_text_base: u64) {
         let vmaddr_text = self.symbol_ctx.main.vmaddr_text;
         let slide = remote_text_base as i64 - vmaddr_text as i64;
         self.symbol_ctx.set_slide(slide);
     }
 
+    /// Applies `faults` to the current and every later debugserver connection.
+    pub fn set_fault_injector(&mut self, faults: Arc<FaultInjector>) {
+        if let Some(client) = self.gdb_client.as_mut() {
+            client.set_fault_injector(Some(faults.clone()));
+        }
+        self.faults = Some(faults);
+    }
+
+    /// Bounds the packets of the current connection by `deadline`; `None`
+    /// lifts the bound.
+    pub fn set_deadline(&mut self, deadline: Option<Instant>) {
+        if let Some(client) = self.gdb_client.as_mut() {
+            client.set_deadline(deadline);
+        }
+    }
+
+    pub fn set_step_scope(&mut self, scope: StepScope) {
+        self.step_scope = scope;
+    }
+
+    /// Records the packets of every later connection to `trace`.
+    pub fn set_protocol_trace(&mut self, trace: Arc<ProtocolTrace>) {
+        self.trace = Some(trace);
+    }
+
     pub fn connect_debugserver(&mut self, port: u16) -> Result<(), String> {
-        match GdbRemoteClient::connect(port) {
-            Ok(client) => {
+        if let Some(mock) = self.mock.as_mut() {
+            mock.attach();
+            self.connected_port = Some(port);
+            return Ok(());
+        }
+        match GdbRemoteClient::connect_traced(port, self.trace.clone()) {
+            Ok(mut client) => {
+                client.set_fault_injector(self.faults.clone());
                 self.connected_port = Some(port);
                 self.gdb_client = Some(client);
+                self.runtime_symbols.clear();
+                self.planted.clear();
+                self.process_id = None;
                 Ok(())
             }
             Err(err) => Err(format!(
@@ -75,44 +285,137 @@ impl Backend {
         }
     }
 
-    pub fn update_breakpoints(&mut self, source_path: &str, lines: &[i64]) -> Result<(), String> {
+    /// Records `lines` as the breakpoints of `source_path` and plants them.
+    /// Returns the lines that could not be planted yet.
+    pub fn update_breakpoints(
+        &mut self,
+        source_path: &str,
+        lines: &[i64],
+    ) -> Result<Vec<i64>, String> {
         self.breakpoints
             .insert(source_path.to_string(), lines.to_vec());
 
+        self.ensure_line_index()?;
+        let mut unbound = Vec::new();
+        for &line in lines {
+            if line > 0 && !self.plant_line(source_path, line)? {
+                unbound.push(line);
+            }
+        }
+        Ok(unbound)
+    }
+
+    /// Plants a breakpoint at every address of `source_path:line`. Returns
+    /// false when the line has no code in the DWARF line tables or there is
+    /// no debugserver connection to plant it on.
+    pub fn plant_line(&mut self, source_path: &str, line: i64) -> Result<bool, String> {
         self.ensure_line_index()?;
         let Some(index) = &self.line_index else {
-            return Ok(());
+            return Ok(false);
         };
 
         let canonical = Path::new(source_path).to_string_lossy().to_string();
+        let ranges = index.lookup(&canonical, line as u64);
+        if ranges.is_empty() {
+            eprintln!("No DWARF ranges for {canonical}:{line}, skipping breakpoint placement");
+            return Ok(false);
+        }
+        let Some(client) = self.gdb_client.as_mut() else {
+            eprintln!(
+                "No gdb-remote client for breakpoint at {canonical}:{line}; call connect_debugserver first"
+            );
+            return Ok(false);
+        };
+        for range in ranges {
+            let remote_addr = self.symbol_ctx.local_to_remote(range.low);
+            client
+                .set_software_breakpoint(remote_addr)
+                .map_err(|err| format!("failed to plant breakpoint: {err}"))?;
+            self.planted.insert(remote_addr);
+        }
+        Ok(true)
+    }
 
-        for line in lines {
-            if *line <= 0 {
-                continue;
-            }
-            let ranges = index.lookup(&canonical, *line as u64);
-            if ranges.is_empty() {
-                eprintln!("No DWARF ranges for {canonical}:{line}, skipping breakpoint placement");
-                continue;
-            }
-            for range in ranges {
-                let remote_addr = self.symbol_ctx.local_to_remote(range.low);
-                if let Some(client) = self.gdb_client.as_mut() {
-                    client
-                        .set_software_breakpoint(remote_addr)
-                        .map_err(|err| format!("failed to plant breakpoint: {err}"))?;
-                } else {
-                    eprintln!(
-                        "No gdb-remote client for breakpoint at 0x{remote_addr:x}; call connect_debugserver first"
-                    );
-                }
+    /// Plants a breakpoint at the code address `address`, for runtime hooks
+    /// that have no source line.
+    pub fn plant_address(&mut self, address: u64) -> Result<(), String> {
+        self.ensure_gdb()?
+            .set_software_breakpoint(address)
+            .map_err(|err| format!("failed to plant breakpoint at 0x{address:x}: {err}"))?;
+        self.planted.insert(address);
+        Ok(())
+    }
+
+    /// Lifts a breakpoint planted with [`Self::plant_address`].
+    pub fn lift_address(&mut self, address: u64) -> Result<(), String> {
+        self.planted.remove(&address);
+        self.ensure_gdb()?
+            .clear_software_breakpoint(address)
+            .map_err(|err| format!("failed to remove breakpoint at 0x{address:x}: {err}"))
+    }
+
+    /// Forgets the breakpoint on `source_path:line` and lifts it from the target.
+    pub fn remove_breakpoint(&mut self, source_path: &str, line: i64) -> Result<(), String> {
+        if let Some(lines) = self.breakpoints.get_mut(source_path) {
+            lines.retain(|planted| *planted != line);
+        }
+        let addresses = self.line_addresses(source_path, line as u32)?;
+        if let Some(client) = self.gdb_client.as_mut() {
+            for address in addresses {
+                self.planted.remove(&address);
+                client
+                    .clear_software_breakpoint(address)
+                    .map_err(|err| format!("failed to clear breakpoint: {err}"))?;
             }
         }
-
         Ok(())
     }
 
-    pub fn threads(&self) -> Vec<Value> {
+    /// Threads as `{ id, name, queue?, run_state? }`. Without a debugserver
+    /// connection (or if the stub cannot list threads) a single stub thread
+    /// is reported.
+    pub fn threads(&mut self) -> Vec<Value> {
+        if let Some(mock) = self.attached_mock() {
+            return mock
+                .threads()
+                .iter()
+                .map(|thread| {
+                    let mut value = json!({ "id": thread.id, "name": thread.name });
+                    if let Some(queue) = &thread.queue {
+                        value["queue"] = json!(queue);
+                    }
+                    value
+                })
+                .collect();
+        }
+        if let Some(client) = self.gdb_client.as_mut() {
+            match client.threads() {
+                Ok(threads) if !threads.is_empty() => {
+                    return threads
+                        .into_iter()
+                        .map(|thread| {
+                            let mut value = json!({
+                                "id": thread.id,
+                                "name": thread.name.unwrap_or_default(),
+                            });
+                            if let Some(queue) = thread.queue {
+                                value["queue"] = json!(queue);
+                            }
+                            if let Some(state) = thread.run_state {
+                                value["run_state"] = json!(match state {
+                                    ThreadRunState::Running => "running",
+                                    ThreadRunState::Blocked => "blocked",
+                                    ThreadRunState::Suspended => "suspended",
+                                });
+                            }
+                            value
+                        })
+                        .collect();
+                }
+                Ok(_) => {}
+                Err(err) => log::warn!("listing threads failed: {err}"),
+            }
+        }
         vec![json!({
             "id": 1,
             "name": format!(
@@ -124,123 +427,1573 @@ impl Backend {
         })]
     }
 
+    /// DAP-shaped frames of `thread_id`. Code inlined at a return address
+    /// expands into one frame per inlined function, innermost first, marked
+    /// `inlined` and sharing the `id` of the physical frame they run in; the
+    /// frame they were inlined into follows at the call site.
     pub fn stack_trace(&self, thread_id: i64) -> Vec<Value> {
+        if let Some(mock) = self.attached_mock() {
+            return mock
+                .frames(thread_id)
+                .iter()
+                .enumerate()
+                .map(|(index, frame)| {
+                    mock_frame_json(thread_id * 100 + index as i64 + 1, frame, index == 0)
+                })
+                .collect();
+        }
         let raw_frames = self.backend_fetch_frames(thread_id);
         let mut out = Vec::new();
 
-        for (idx, (frame_id, pc)) in raw_frames.iter().enumerate() {
-            let frames = self.symbol_ctx.symbolize_frames(*pc).ok();
-            let top = frames.as_ref().and_then(|frames| frames.first());
-            let function_name = top
-                .and_then(|frame| frame.function.as_ref())
-                .and_then(|name| {
-                    name.demangle()
-                        .ok()
-                        .map(|cow| cow.into_owned())
-                        .or_else(|| name.raw_name().ok().map(|cow| cow.into_owned()))
+        for (frame_id, pc) in &raw_frames {
+            let frames = self.symbol_ctx.symbolize_frames(*pc).unwrap_or_default();
+            if frames.is_empty() {
+                out.push(frame_json(*frame_id, None, out.is_empty()));
+                continue;
+            }
+            let outermost = frames.len() - 1;
+            for (depth, frame) in frames.iter().enumerate() {
+                let mut value = frame_json(*frame_id, Some(frame), out.is_empty());
+                if depth < outermost {
+                    value["inlined"] = json!(true);
+                }
+                out.push(value);
+            }
+        }
+
+        out
+    }
+
+    /// Source paths recorded in the DWARF line tables, sorted and de-duplicated.
+    /// The bare file-name aliases the index adds for lookups are left out.
+    pub fn source_files(&mut self) -> Result<Vec<String>, String> {
+        self.ensure_line_index()?;
+        let mut files: Vec<String> = self
+            .line_index
+            .as_ref()
+            .map(|index| {
+                index
+                    .map
+                    .keys()
+                    .filter(|key| key.file.contains('/'))
+                    .map(|key| key.file.clone())
+                    .collect()
+            })
+            .unwrap_or_default();
+        files.sort();
+        files.dedup();
+        Ok(files)
+    }
+
+    fn ensure_line_index(&mut self) -> Result<(), String> {
+        if self.line_index.is_none() {
+            match LineIndex::from_binary(self.symbol_ctx.main.debug_path()) {
+                Ok(index) => self.line_index = Some(index),
+                Err(err) => {
+                    return Err(format!(
+                        "Failed to build DWARF line index for {:?}: {err}",
+                        self.symbol_ctx.main.debug_path()
+                    ))
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// One locals scope per frame; its reference is the frame index plus one.
+    pub fn scopes(&self, frame_index: usize) -> Vec<Value> {
+        vec![json!({
+            "name": "Locals",
+            "variablesReference": frame_index as i64 + 1,
+            "expensive": false
+        })]
+    }
+
+    pub fn variables(&self, variables_reference: i64) -> Vec<Value> {
+        vec![
+            json!({
+                "name": "var",
+                "value": format!("value-{variables_reference}"),
+                "type": "string",
+                "variablesReference": 0
+            }),
+            json!({
+                "name": "counter",
+                "value": "123",
+                "type": "int",
+                "variablesReference": 0
+            }),
+        ]
+    }
+
+    pub fn r#continue(&mut self, thread_id: i64) -> Result<Option<BackendStopEvent>, String> {
+        if let Some(stop) = self.mock_resume(thread_id) {
+            return stop;
+        }
+        self.resume_unsuspended(None)?;
+        self.ensure_gdb()?
+            .wait_for_stop()
+            .map(BackendStopEvent::from_reply)
+            .map(Some)
+            .map_err(|err| err.to_string())
+    }
+
+    /// Resumes with `signal` delivered to `thread_id` and waits for the next stop.
+    pub fn signal(
+        &mut self,
+        thread_id: i64,
+        signal: u8,
+    ) -> Result<Option<BackendStopEvent>, String> {
+        if let Some(stop) = self.mock_resume(thread_id) {
+            return stop;
+        }
+        self.resume_unsuspended(Some((thread_id, signal)))?;
+        self.ensure_gdb()?
+            .wait_for_stop()
+            .map(BackendStopEvent::from_reply)
+            .map(Some)
+            .map_err(|err| err.to_string())
+    }
+
+    pub fn step_over(&mut self, thread_id: i64) -> Result<Option<BackendStopEvent>, String> {
+        if let Some(stop) = self.mock_resume(thread_id) {
+            return stop;
+        }
+        if self.suspended_threads.contains(&thread_id) {
+            return Err(format!(
+                "thread {thread_id} is suspended; resume_thread it first"
+            ));
+        }
+        let others = match self.step_scope {
+            StepScope::Thread => Some(Vec::new()),
+            StepScope::All if self.suspended_threads.is_empty() => None,
+            StepScope::All => Some(self.unsuspended_threads()?),
+        };
+        let client = self.ensure_gdb()?;
+        client
+            .step_thread(thread_id as u64, others.as_deref())
+            .map_err(|err| err.to_string())?;
+        client
+            .wait_for_stop()
+            .map(BackendStopEvent::from_reply)
+            .map(Some)
+            .map_err(|err| err.to_string())
+    }
+
+    pub fn step_in(&mut self, thread_id: i64) -> Result<Option<BackendStopEvent>, String> {
+        self.step_over(thread_id)
+    }
+
+    /// Runs until the current function returns to its caller, then captures
+    /// the return value from the ABI registers. A temporary breakpoint goes on
+    /// the return address unless one is planted there already; stops on it
+    /// from a deeper, recursive call or another thread are run past.
+    pub fn step_out(&mut self, thread_id: i64) -> Result<Option<BackendStopEvent>, String> {
+        if let Some(stop) = self.mock_resume(thread_id) {
+            return stop;
+        }
+        let function_pc = self.read_register_u64(thread_id, "pc")?;
+        let entry_sp = self.read_register_u64(thread_id, "sp")?;
+        let return_address = self.caller_address(thread_id, function_pc)?;
+        let temporary = !self.planted.contains(&return_address);
+        if temporary {
+            self.ensure_gdb()?
+                .set_software_breakpoint(return_address)
+                .map_err(|err| format!("failed to plant step-out breakpoint: {err}"))?;
+        }
+        let returned = self.run_to_return(thread_id, return_address, entry_sp, temporary);
+        let cleared = if temporary {
+            self.ensure_gdb()?.clear_software_breakpoint(return_address)
+        } else {
+            Ok(())
+        };
+        let (mut event, stepped_out) = returned?;
+        cleared.map_err(|err| format!("failed to clear step-out breakpoint: {err}"))?;
+        if stepped_out {
+            event.reason = "step";
+            event.description = "Stepped out".to_string();
+            event.return_value = self.capture_return_value(event.thread_id, function_pc);
+        }
+        Ok(Some(event))
+    }
+
+    /// Resumes until `thread_id` reaches `return_address` with its stack
+    /// pointer at or above `entry_sp`, where it was in the callee. The frame
+    /// returning there is then the one stepped out of: a deeper call of the
+    /// same function returns with the stack lower, and a leaf function that
+    /// allocates no stack returns with it unchanged. Stops elsewhere end the
+    /// wait; so does a stop on `return_address` that is not the step's, unless
+    /// the breakpoint there is the `temporary` one.
+    fn run_to_return(
+        &mut self,
+        thread_id: i64,
+        return_address: u64,
+        entry_sp: u64,
+        temporary: bool,
+    ) -> Result<(BackendStopEvent, bool), String> {
+        loop {
+            self.resume_unsuspended(None)?;
+            let reply = self
+                .ensure_gdb()?
+                .wait_for_stop()
+                .map_err(|err| err.to_string())?;
+            let event = BackendStopEvent::from_reply(reply);
+            if self.read_register_u64(event.thread_id, "pc").ok() != Some(return_address) {
+                return Ok((event, false));
+            }
+            let sp = self.read_register_u64(event.thread_id, "sp")?;
+            if event.thread_id == thread_id && sp >= entry_sp {
+                return Ok((event, true));
+            }
+            if !temporary {
+                return Ok((event, false));
+            }
+        }
+    }
+
+    /// Where the function `thread_id` is stopped in at `pc` returns to. Once
+    /// the prologue has pointed `fp` at the frame record, and until the
+    /// epilogue reloads it, that is the `lr` saved at `[fp + 8]`: `lr` itself
+    /// is overwritten by the first call the function makes. Before that, and
+    /// in leaf functions that keep no record, it is `lr`. Either way arm64e
+    /// signs it, so the pointer authentication bits are stripped.
+    fn caller_address(&mut self, thread_id: i64, pc: u64) -> Result<u64, String> {
+        if !self.frame_record_live(pc) {
+            return self.return_address(thread_id);
+        }
+        let fp = self.read_register_u64(thread_id, "fp")?;
+        let saved = self.read_memory(fp.wrapping_add(8), 8)?;
+        Ok(le_u64(&saved) & CODE_POINTER_MASK)
+    }
+
+    /// Whether the function stopped at `pc` has its frame record set up:
+    /// something between its entry and `pc` points `fp` at the stack, and
+    /// no `ret` follows before a branch or the reload of `fp`. Without DWARF
+    /// bounds for the function only the epilogue is checked.
+    fn frame_record_live(&mut self, pc: u64) -> bool {
+        if let Some(entry) = self.function_entry(pc) {
+            let length = pc.saturating_sub(entry).min(MAX_PROLOGUE_BYTES) as usize;
+            if let Ok(prologue) = self.read_memory(entry, length) {
+                let set_up = instruction_words(&prologue)
+                    .any(|word| disasm::frame_effect(word) == FrameEffect::SetsFramePointer);
+                if !set_up {
+                    return false;
+                }
+            }
+        }
+        let Ok(ahead) = self.read_memory(pc, EPILOGUE_SCAN_BYTES) else {
+            return true;
+        };
+        for word in instruction_words(&ahead) {
+            match disasm::frame_effect(word) {
+                FrameEffect::Return => return false,
+                FrameEffect::RestoresFramePointer | FrameEffect::Branch => return true,
+                FrameEffect::SetsFramePointer | FrameEffect::None => {}
+            }
+        }
+        true
+    }
+
+    /// Remote entry address of the function containing the remote `pc`.
+    fn function_entry(&mut self, pc: u64) -> Option<u64> {
+        self.ensure_function_index().ok()?;
+        let local_pc = self.symbol_ctx.translate_remote_pc(pc);
+        let function = self.function_index.as_ref()?.lookup(local_pc)?;
+        Some(self.symbol_ctx.local_to_remote(function.low))
+    }
+
+    /// Lines of `file` a breakpoint can be planted on.
+    pub fn code_lines(&mut self, file: &str) -> Result<Vec<u32>, String> {
+        self.ensure_line_index()?;
+        Ok(self
+            .line_index
+            .as_ref()
+            .map(|index| index.lines(file))
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|line| u32::try_from(line).ok())
+            .collect())
+    }
+
+    /// Remote addresses a breakpoint on `file:line` is planted at.
+    pub fn line_addresses(&mut self, file: &str, line: u32) -> Result<Vec<u64>, String> {
+        self.ensure_line_index()?;
+        Ok(self
+            .line_index
+            .as_ref()
+            .map(|index| index.lookup(file, line as u64))
+            .unwrap_or_default()
+            .iter()
+            .map(|range| self.symbol_ctx.local_to_remote(range.low))
+            .collect())
+    }
+
+    /// Continues with one-shot breakpoints on every address of `file:line`,
+    /// removing them after the next stop wherever it happens. Addresses that
+    /// already carry a user breakpoint are left alone.
+    pub fn run_to_line(
+        &mut self,
+        file: &str,
+        line: u32,
+    ) -> Result<Option<BackendStopEvent>, String> {
+        let user_breakpoint = self
+            .breakpoints
+            .get(file)
+            .is_some_and(|lines| lines.contains(&(line as i64)));
+        let addresses = self.line_addresses(file, line)?;
+        if addresses.is_empty() {
+            return Err(format!("no code at {file}:{line}"));
+        }
+        let client = self.ensure_gdb()?;
+        let mut planted = Vec::new();
+        if !user_breakpoint {
+            for address in &addresses {
+                if let Err(err) = client.set_software_breakpoint(*address) {
+                    for address in planted {
+                        let _ = client.clear_software_breakpoint(address);
+                    }
+                    return Err(format!("failed to plant run-to-line breakpoint: {err}"));
+                }
+                planted.push(*address);
+            }
+        }
+        let resumed = client.continue_all().and_then(|_| client.wait_for_stop());
+        let cleared = planted
+            .iter()
+            .try_for_each(|address| client.clear_software_breakpoint(*address));
+        let reply = resumed.map_err(|err| err.to_string())?;
+        cleared.map_err(|err| format!("failed to clear run-to-line breakpoint: {err}"))?;
+
+        let mut event = BackendStopEvent::from_reply(reply);
+        let stop_pc = self.read_register_u64(event.thread_id, "pc").ok();
+        if stop_pc.is_some_and(|pc| addresses.contains(&pc)) {
+            event.reason = "breakpoint";
+            event.description = format!("Ran to {file}:{line}");
+        }
+        Ok(Some(event))
+    }
+
+    fn capture_return_value(&mut self, thread_id: i64, function_pc: u64) -> Option<ReturnValue> {
+        self.ensure_function_index().ok()?;
+        let local_pc = self.symbol_ctx.translate_remote_pc(function_pc);
+        let function = self.function_index.as_ref()?.lookup(local_pc)?.clone();
+        let ty = function.return_type?;
+        let integer = self.read_register_bytes(thread_id, "arg1").ok()?;
+        let float = if ty.kind == ValueKind::Float {
+            self.read_register_bytes(thread_id, "d0")
+                .or_else(|_| self.read_register_bytes(thread_id, "v0"))
+                .ok()
+        } else {
+            None
+        };
+        let value = self
+            .returned_summary(thread_id, &ty)
+            .unwrap_or_else(|| format_register_value(&ty, &integer, float.as_deref()));
+        Some(ReturnValue {
+            function: function.name.unwrap_or_else(|| "<unknown>".into()),
+            value,
+            raw: format!("0x{:x}", le_u64(&integer)),
+            ty: ty.name,
+        })
+    }
+
+    /// Summary of a value of a registered type returned in `x0`-`x3`.
+    fn returned_summary(&mut self, thread_id: i64, ty: &ValueType) -> Option<String> {
+        if !self.formatters.handles(&ty.name) {
+            return None;
+        }
+        let size = usize::try_from(ty.byte_size?).ok()?;
+        if size > 32 {
+            return None;
+        }
+        let mut bytes = Vec::with_capacity(32);
+        for register in ["x0", "x1", "x2", "x3"].into_iter().take(size.div_ceil(8)) {
+            bytes.extend_from_slice(
+                self.read_register_bytes(thread_id, register)
+                    .ok()?
+                    .get(..8)?,
+            );
+        }
+        bytes.truncate(size);
+        self.summarize(&ty.name, &bytes)
+    }
+
+    /// Summary of a `type_name` value held in `bytes` from the formatter
+    /// registry, reading any storage it points at from the target.
+    pub fn summarize(&mut self, type_name: &str, bytes: &[u8]) -> Option<String> {
+        let mut client = self.gdb_client.as_mut();
+        let mut read = |address: u64, length: usize| {
+            if length > MAX_MEMORY_READ {
+                return None;
+            }
+            client.as_mut()?.read_memory(address, length).ok()
+        };
+        self.formatters.summary(type_name, bytes, &mut read)
+    }
+
+    pub fn read_memory(&mut self, address: u64, length: usize) -> Result<Vec<u8>, String> {
+        if length > MAX_MEMORY_READ {
+            return Err(format!(
+                "read of {length} bytes exceeds the {MAX_MEMORY_READ} byte limit"
+            ));
+        }
+        self.ensure_gdb()?
+            .read_memory(address, length)
+            .map_err(|err| format!("failed to read memory at 0x{address:x}: {err}"))
+    }
+
+    pub fn write_memory(&mut self, address: u64, bytes: &[u8]) -> Result<(), String> {
+        if bytes.len() > MAX_MEMORY_READ {
+            return Err(format!(
+                "write of {} bytes exceeds the {MAX_MEMORY_READ} byte limit",
+                bytes.len()
+            ));
+        }
+        self.ensure_gdb()?
+            .write_memory(address, bytes)
+            .map_err(|err| format!("failed to write memory at 0x{address:x}: {err}"))
+    }
+
+    pub fn write_stdin(&mut self, bytes: &[u8]) -> Result<(), String> {
+        self.ensure_gdb()?
+            .write_stdin(bytes)
+            .map_err(|err| format!("failed to write stdin: {err}"))
+    }
+
+    /// Inferior stdout/stderr debugserver forwarded since the last call.
+    pub fn take_console_output(&mut self) -> Vec<u8> {
+        self.gdb_client
+            .as_mut()
+            .map(GdbRemoteClient::take_console_output)
+            .unwrap_or_default()
+    }
+
+    /// Assigns `value` to a local, parameter or `local.member` of the top frame
+    /// of `thread_id`, writing its stack slot or register. Returns the new
+    /// value and its type name as rendered from the written bytes.
+    pub fn set_variable(
+        &mut self,
+        thread_id: i64,
+        path: &str,
+        value: &str,
+    ) -> Result<(String, String), String> {
+        let pc = self.program_counter(thread_id)?;
+        let local_pc = self.symbol_ctx.translate_remote_pc(pc);
+        let info = dwarf::locate_variable(self.symbol_ctx.main.debug_path(), local_pc, path)
+            .map_err(|err| format!("failed to read variable locations: {err}"))?
+            .ok_or_else(|| format!("no writable variable `{path}` at 0x{pc:x}"))?;
+        let bytes = dwarf::encode_value(&info.ty, value)?;
+        let register_name = |register: u16| {
+            arm64_register_name(register)
+                .ok_or_else(|| format!("unsupported DWARF register {register} for `{path}`"))
+        };
+        match info.location {
+            VariableLocation::Memory { register, offset } => {
+                let base = self.read_register_u64(thread_id, &register_name(register)?)?;
+                self.write_memory(base.wrapping_add_signed(offset), &bytes)?;
+            }
+            VariableLocation::Register(register) => {
+                self.set_register(thread_id, &register_name(register)?, le_u64(&bytes))?;
+            }
+        }
+        Ok((
+            format_register_value(&info.ty, &bytes, Some(&bytes)),
+            info.ty.name,
+        ))
+    }
+
+    /// Parameters and locals of the innermost frame of `thread_id`, read
+    /// from the stack slots and registers their DWARF locations name. Outer
+    /// frames have no recovered registers to read them with.
+    pub fn frame_variables(
+        &mut self,
+        thread_id: i64,
+        frame_index: usize,
+    ) -> Result<Vec<TargetValue>, String> {
+        if let Some(mock) = self.attached_mock() {
+            let frame = mock
+                .frames(thread_id)
+                .get(frame_index)
+                .ok_or_else(|| format!("thread {thread_id} has no frame {frame_index}"))?;
+            return Ok(frame
+                .locals
+                .iter()
+                .map(|local| TargetValue {
+                    name: local.name.clone(),
+                    ty: local.ty.clone(),
+                    value: local.value.clone(),
+                    children: None,
                 })
-                .unwrap_or_else(|| "<unknown>".into());
-
-            let location = top.and_then(|frame| frame.location.as_ref());
-            let file_path = location
-                .and_then(|loc| loc.file)
-                .unwrap_or("<unknown>")
-                .to_string();
-            let line = location
-                .and_then(|loc| loc.line)
-                .map(|line| line as i64)
-                .unwrap_or(0);
-            let source_name = file_path
-                .rsplit(['/', '\\'])
-                .next()
-                .unwrap_or(&file_path)
-                .to_string();
-
-            out.push(json!({
-                "id": frame_id,
-                "name": function_name,
-                "line": line,
-                "column": 1,
-                "source": {
-                    "name": source_name,
-                    "path": file_path,
+                .collect());
+        }
+        if frame_index != 0 {
+            return Err("variables can only be read in the innermost frame".into());
+        }
+        let pc = self.program_counter(thread_id)?;
+        let local_pc = self.symbol_ctx.translate_remote_pc(pc);
+        let variables = dwarf::frame_variables(self.symbol_ctx.main.debug_path(), local_pc)
+            .map_err(|err| format!("failed to read variable locations: {err}"))?;
+        let mut values = Vec::with_capacity(variables.len());
+        for variable in variables {
+            let size = value_size(&variable.layout.ty);
+            let read = match variable.location {
+                VariableLocation::Memory { register, offset } => arm64_register_name(register)
+                    .ok_or_else(|| format!("unsupported DWARF register {register}"))
+                    .and_then(|name| self.read_register_u64(thread_id, &name))
+                    .map(|base| base.wrapping_add_signed(offset))
+                    .and_then(|address| Ok((Some(address), self.read_memory(address, size)?))),
+                VariableLocation::Register(register) => arm64_register_name(register)
+                    .ok_or_else(|| format!("unsupported DWARF register {register}"))
+                    .and_then(|name| self.read_register_bytes(thread_id, &name))
+                    .map(|mut bytes| {
+                        bytes.truncate(size);
+                        (None, bytes)
+                    }),
+            };
+            values.push(match read {
+                Ok((address, bytes)) => self.target_value(variable.layout, address, bytes),
+                Err(err) => TargetValue::unavailable(variable.layout, &err),
+            });
+        }
+        Ok(values)
+    }
+
+    /// Best-effort arguments of the function `thread_id` has just entered,
+    /// before its prologue moves them: each parameter is read from the
+    /// register the arm64 calling convention passes it in. Integers, pointers
+    /// and values of up to 16 bytes take the next one or two of `x0`-`x7`,
+    /// floating point values the next of `d0`-`d7`, larger values are passed
+    /// by address, and a trailing Swift `self` is in `x20`. Parameters past
+    /// the argument registers are on the stack and listed as unavailable.
+    pub fn entry_arguments(&mut self, thread_id: i64) -> Result<Vec<TargetValue>, String> {
+        let pc = self.program_counter(thread_id)?;
+        let local_pc = self.symbol_ctx.translate_remote_pc(pc);
+        let parameters = dwarf::function_parameters(self.symbol_ctx.main.debug_path(), local_pc)
+            .map_err(|err| format!("failed to read the function's parameters: {err}"))?;
+        let last = parameters.len().saturating_sub(1);
+        let (mut next_x, mut next_d) = (0, 0);
+        let mut values = Vec::with_capacity(parameters.len());
+        for (index, layout) in parameters.into_iter().enumerate() {
+            let size = value_size(&layout.ty);
+            let read = if index == last && layout.name == "self" {
+                self.read_register_bytes(thread_id, "x20")
+                    .map(|bytes| (None, bytes))
+            } else if layout.ty.kind == ValueKind::Float {
+                next_d += 1;
+                if next_d > ARGUMENT_REGISTERS {
+                    Err("passed on the stack".to_string())
+                } else {
+                    self.read_register_bytes(thread_id, &format!("d{}", next_d - 1))
+                        .map(|bytes| (None, bytes))
+                }
+            } else {
+                let first = next_x;
+                next_x += if size > 16 {
+                    1
+                } else {
+                    size.div_ceil(8).max(1)
+                };
+                if next_x > ARGUMENT_REGISTERS {
+                    Err("passed on the stack".to_string())
+                } else if size > 16 {
+                    self.read_register_u64(thread_id, &format!("x{first}"))
+                        .and_then(|address| Ok((Some(address), self.read_memory(address, size)?)))
+                } else {
+                    (first..next_x)
+                        .map(|register| {
+                            let bytes =
+                                self.read_register_bytes(thread_id, &format!("x{register}"))?;
+                            Ok(bytes[..bytes.len().min(8)].to_vec())
+                        })
+                        .collect::<Result<Vec<_>, String>>()
+                        .map(|words| (None, words.concat()))
+                }
+            };
+            values.push(match read {
+                Ok((address, mut bytes)) => {
+                    bytes.truncate(size);
+                    self.target_value(layout, address, bytes)
+                }
+                Err(err) => TargetValue::unavailable(layout, &err),
+            });
+        }
+        Ok(values)
+    }
+
+    /// Where the function `thread_id` has just entered returns to, with
+    /// pointer authentication bits stripped.
+    pub fn return_address(&mut self, thread_id: i64) -> Result<u64, String> {
+        Ok(self.read_register_u64(thread_id, "lr")? & CODE_POINTER_MASK)
+    }
+
+    /// The members, elements or pointee of the value `handle` was made for,
+    /// within `page`, and how many there are in all. Only the children in the
+    /// page are read, and an array lists at most [`MAX_ARRAY_ELEMENTS`] of its
+    /// elements at a time.
+    pub fn value_children(
+        &mut self,
+        handle: &ValueHandle,
+        page: ChildPage,
+    ) -> Result<(Vec<TargetValue>, usize), String> {
+        let children = dwarf::type_children(self.symbol_ctx.main.debug_path(), handle.type_offset)
+            .map_err(|err| format!("failed to read the type's layout: {err}"))?;
+        let located = |address: Option<u64>| {
+            address.ok_or_else(|| "the value is held in a register".to_string())
+        };
+        let (placed, total): (Vec<(u64, ValueLayout)>, usize) = match children {
+            TypeChildren::Members { members, indirect } => {
+                let base = if indirect {
+                    le_u64(&handle.bytes)
+                } else {
+                    located(handle.address)?
+                };
+                let total = members.len();
+                let placed = page
+                    .apply(members.into_iter())
+                    .map(|(offset, layout)| (base.wrapping_add(offset), layout))
+                    .collect();
+                (placed, total)
+            }
+            TypeChildren::Elements {
+                element,
+                stride,
+                count,
+            } => {
+                let base = located(handle.address)?;
+                let placed = page
+                    .elements(count)
+                    .map(|index| {
+                        let layout = ValueLayout {
+                            name: format!("[{index}]"),
+                            ..element.clone()
+                        };
+                        (base.wrapping_add(index * stride), layout)
+                    })
+                    .collect();
+                (placed, count as usize)
+            }
+            TypeChildren::Pointee(layout) => match le_u64(&handle.bytes) {
+                0 => (Vec::new(), 0),
+                address => (page.apply(std::iter::once((address, layout))).collect(), 1),
+            },
+            TypeChildren::None => (Vec::new(), 0),
+        };
+        let values = placed
+            .into_iter()
+            .map(
+                |(address, layout)| match self.read_memory(address, value_size(&layout.ty)) {
+                    Ok(bytes) => self.target_value(layout, Some(address), bytes),
+                    Err(err) => TargetValue::unavailable(layout, &err),
+                },
+            )
+            .collect();
+        Ok((values, total))
+    }
+
+    /// Renders `bytes` as a value of `layout`'s type: a formatter summary
+    /// when one is registered, else the scalar, pointer or `{...}`.
+    fn target_value(
+        &mut self,
+        layout: ValueLayout,
+        address: Option<u64>,
+        bytes: Vec<u8>,
+    ) -> TargetValue {
+        let ty = &layout.ty;
+        let value = self
+            .formatters
+            .handles(&ty.name)
+            .then(|| self.summarize(&ty.name, &bytes))
+            .flatten()
+            .unwrap_or_else(|| match ty.kind {
+                ValueKind::Aggregate => "{...}".into(),
+                ValueKind::Unknown if bytes.len() > 8 => "{...}".into(),
+                ValueKind::Unknown => format!("0x{:x}", le_u64(&bytes)),
+                _ => format_register_value(ty, &bytes, Some(&bytes)),
+            });
+        // Members and elements need the value's address; a pointer only its bytes.
+        let expandable = layout.expandable && (address.is_some() || ty.kind == ValueKind::Pointer);
+        TargetValue {
+            name: layout.name,
+            ty: layout.ty.name,
+            value,
+            children: expandable.then_some(ValueHandle {
+                address,
+                bytes,
+                type_offset: layout.type_offset,
+            }),
+        }
+    }
+
+    /// Images loaded in the inferior. The one matching the local program
+    /// (by UUID, or by file name when either UUID is unknown) also re-bases
+    /// symbolication on its reported load address. Images listed without
+    /// their link-time `__TEXT` address get it from their in-memory header,
+    /// so each has a slide.
+    pub fn modules(&mut self) -> Result<Vec<ModuleInfo>, String> {
+        let client = self.ensure_gdb()?;
+        let mut images = client
+            .loaded_images()
+            .map_err(|err| format!("failed to list loaded images: {err}"))?;
+        for image in images
+            .iter_mut()
+            .filter(|image| image.text_vmaddr.is_none())
+        {
+            image.text_vmaddr = exports::text_vmaddr(
+                |address, length| client.read_memory(address, length).ok(),
+                image.load_address,
+            );
+        }
+        let main = &self.symbol_ctx.main;
+        let local_uuid = main.uuid.map(|uuid| format_uuid(&uuid));
+        let local_name = main.path.file_name().map(|name| name.to_string_lossy());
+        let mut main_base = None;
+        let modules = images
+            .into_iter()
+            .map(|image| {
+                let mut module = ModuleInfo::new(&image);
+                let same_name = local_name.as_deref()
+                    == Path::new(&image.path)
+                        .file_name()
+                        .and_then(|name| name.to_str());
+                match (&local_uuid, &image.uuid) {
+                    (Some(local), Some(remote)) if local.eq_ignore_ascii_case(remote) => {
+                        module.symbols = Some(main.debug_path().display().to_string());
+                    }
+                    (Some(local), Some(remote)) if same_name => {
+                        module.warning = Some(format!(
+                            "local binary {} has UUID {local}, target image has {remote}",
+                            main.path.display()
+                        ));
+                    }
+                    _ if same_name => {
+                        module.symbols = Some(main.debug_path().display().to_string())
+                    }
+                    _ => {}
+                }
+                if module.symbols.is_some() {
+                    main_base = Some(image.load_address);
+                }
+                module
+            })
+            .collect();
+        if let Some(base) = main_base {
+            self.update_slide_from_remote_text_base(base);
+        }
+        Ok(modules)
+    }
+
+    /// The region containing `address`, or, without one, every mapped region
+    /// up to [`MAX_MEMORY_REGIONS`]; the flag tells whether the walk stopped
+    /// early.
+    pub fn memory_regions(
+        &mut self,
+        address: Option<u64>,
+    ) -> Result<(Vec<RegionInfo>, bool), String> {
+        let client = self.ensure_gdb()?;
+        let query = |client: &mut GdbRemoteClient, address: u64| {
+            client
+                .memory_region_info(address)
+                .map_err(|err| format!("failed to read memory region at 0x{address:x}: {err}"))
+        };
+        if let Some(address) = address {
+            return Ok((vec![RegionInfo::new(&query(client, address)?)], false));
+        }
+        let mut regions = Vec::new();
+        let mut next = 0u64;
+        for _ in 0..MAX_MEMORY_REGIONS * 2 {
+            let region = query(client, next)?;
+            if !region.permissions.is_empty() {
+                regions.push(RegionInfo::new(&region));
+                if regions.len() == MAX_MEMORY_REGIONS {
+                    return Ok((regions, true));
+                }
+            }
+            match region.start.checked_add(region.size) {
+                Some(end) if region.size > 0 && end > next => next = end,
+                _ => return Ok((regions, false)),
+            }
+        }
+        Ok((regions, true))
+    }
+
+    /// The region `address` falls in and, when it is readable, the Objective-C
+    /// or Swift object it points at.
+    pub fn heap_info(&mut self, address: u64) -> Result<HeapInfo, String> {
+        let mut info = HeapInfo {
+            address: format!("0x{address:x}"),
+            tagged_pointer: heap::is_tagged_pointer(address),
+            region: None,
+            object: None,
+        };
+        if info.tagged_pointer {
+            return Ok(info);
+        }
+        let client = self.ensure_gdb()?;
+        let region = client
+            .memory_region_info(address)
+            .map_err(|err| format!("failed to read memory region at 0x{address:x}: {err}"))?;
+        if region.permissions.contains('r') {
+            info.object = heap::identify(
+                |address, length| client.read_memory(address, length).ok(),
+                address,
+            );
+        }
+        info.region = Some(RegionInfo::new(&region));
+        Ok(info)
+    }
+
+    /// Swift concurrency tasks: the ones running on a thread, found through
+    /// their async frames, the ones at `handles`, and all of their children,
+    /// each with its async backtrace symbolicated.
+    pub fn swift_tasks(&mut self, handles: &[u64]) -> Result<Vec<TaskInfo>, String> {
+        let thread_ids = self
+            .ensure_gdb()?
+            .thread_ids()
+            .map_err(|err| format!("failed to list threads: {err}"))?;
+        let mut threads = Vec::with_capacity(thread_ids.len());
+        for thread_id in thread_ids {
+            let thread_id = thread_id as i64;
+            threads.push(ThreadRegisters {
+                thread_id,
+                pc: self.read_register_u64(thread_id, "pc")?,
+                fp: self.read_register_u64(thread_id, "fp")?,
+            });
+        }
+        let client = self.ensure_gdb()?;
+        let tasks = swift_tasks::collect(
+            |address, length| client.read_memory(address, length).ok(),
+            &threads,
+            handles,
+        )?;
+        Ok(tasks.into_iter().map(|task| self.task_info(task)).collect())
+    }
+
+    fn task_info(&self, task: Task) -> TaskInfo {
+        TaskInfo {
+            address: task.address.map(|address| format!("0x{address:x}")),
+            id: task.id,
+            state: task.state,
+            priority: task.priority,
+            cancelled: task.cancelled,
+            flags: task.flags,
+            parent: task.parent.map(|address| format!("0x{address:x}")),
+            thread_id: task.thread_id,
+            frames: task
+                .continuations
+                .into_iter()
+                .map(|pc| self.symbolicate(pc))
+                .collect(),
+        }
+    }
+
+    /// The status of the Swift actor at `address`. The task holding a
+    /// running actor is the running task whose innermost async frame is a
+    /// method of the actor's type; the queued tasks are waiting for it, and
+    /// any of them with a higher priority than the actor runs at is a
+    /// priority inversion.
+    pub fn actor_info(&mut self, address: u64) -> Result<ActorInfo, String> {
+        let object = self
+            .heap_info(address)?
+            .object
+            .filter(|object| object.class.language == "swift")
+            .ok_or_else(|| format!("0x{address:x} does not point at a Swift object"))?;
+        let class_address = parse_address(&object.class.address)?;
+        let class = object.class.swift_name.unwrap_or(object.class.name);
+        let client = self.ensure_gdb()?;
+        let actor = swift_tasks::read_actor(
+            |address, length| client.read_memory(address, length).ok(),
+            address,
+            class_address,
+        )
+        .ok_or_else(|| format!("0x{address:x} is a {class}, not an actor"))?;
+        let holder = if actor.state == Some(ActorState::Running) {
+            let prefix = format!("{class}.");
+            self.swift_tasks(&[])?.into_iter().find(|task| {
+                task.frames.first().is_some_and(|frame| {
+                    [&frame.function, &frame.symbol]
+                        .into_iter()
+                        .flatten()
+                        .any(|name| name.contains(&prefix))
+                })
+            })
+        } else {
+            None
+        };
+        let priority_inversion = !actor.escalated
+            && actor
+                .waiting
+                .iter()
+                .any(|task| task.priority > actor.priority);
+        Ok(ActorInfo {
+            address: format!("0x{address:x}"),
+            executor: if actor.default_actor {
+                "default"
+            } else {
+                "custom"
+            },
+            class,
+            state: actor.state,
+            priority: actor.priority,
+            escalated: actor.escalated,
+            distributed_remote: actor.distributed_remote,
+            thread_port: actor.thread_port,
+            holder,
+            waiting: actor
+                .waiting
+                .into_iter()
+                .map(|task| self.task_info(task))
+                .collect(),
+            priority_inversion,
+        })
+    }
+
+    /// Objective-C classes realized outside the shared cache, read from the
+    /// runtime's `gdb_objc_realized_classes` table and sorted by name. Only
+    /// names containing `filter` are kept; the list stops at
+    /// [`MAX_OBJC_CLASSES`] and the flag says whether it was cut.
+    pub fn objc_classes(
+        &mut self,
+        filter: Option<&str>,
+    ) -> Result<(Vec<ClassEntry>, bool), String> {
+        let table_pointer = self.runtime_symbol(LIBOBJC, "_gdb_objc_realized_classes")?;
+        let client = self.ensure_gdb()?;
+        let mut read = |address, length| client.read_memory(address, length).ok();
+        let mut classes = read_u64(&mut read, table_pointer)
+            .and_then(|table| objc::realized_classes(read, table))
+            .ok_or("the Objective-C runtime's class table is unreadable")?;
+        if let Some(filter) = filter {
+            classes.retain(|class| {
+                class.name.contains(filter)
+                    || class
+                        .swift_name
+                        .as_deref()
+                        .is_some_and(|name| name.contains(filter))
+            });
+        }
+        let truncated = classes.len() > MAX_OBJC_CLASSES;
+        classes.truncate(MAX_OBJC_CLASSES);
+        Ok((classes, truncated))
+    }
+
+    /// Ivars, properties and methods of an Objective-C (or Swift) class: the
+    /// dynamic class of the object at `object`, or the class called `name`
+    /// in the runtime's class table.
+    pub fn objc_class(
+        &mut self,
+        name: Option<&str>,
+        object: Option<u64>,
+    ) -> Result<ClassDump, String> {
+        let class = match (object, name) {
+            (Some(address), _) => {
+                let object = self.heap_info(address)?.object.ok_or_else(|| {
+                    format!("0x{address:x} does not point at an Objective-C or Swift object")
+                })?;
+                parse_address(&object.class.address)?
+            }
+            (None, Some(name)) => {
+                let (classes, _) = self.objc_classes(None)?;
+                let entry = classes
+                    .into_iter()
+                    .find(|class| class.name == name || class.swift_name.as_deref() == Some(name))
+                    .ok_or_else(|| format!("no realized Objective-C class named `{name}`"))?;
+                parse_address(&entry.address)?
+            }
+            (None, None) => return Err("pass a class `name` or an object `address`".into()),
+        };
+        let client = self.ensure_gdb()?;
+        objc::dump_class(
+            |address, length| client.read_memory(address, length).ok(),
+            class,
+        )
+        .ok_or_else(|| format!("0x{class:x} is not an Objective-C class"))
+    }
+
+    /// `debugDescription` of the Objective-C or Swift object at `address`,
+    /// the equivalent of LLDB's `po`. Sends the message from `thread_id` by
+    /// calling `objc_msgSend` in the target, then reads back the `UTF8String`
+    /// of the result; every call must finish within `timeout`.
+    pub fn describe_object(
+        &mut self,
+        thread_id: i64,
+        address: u64,
+        timeout: Duration,
+    ) -> Result<ObjectDescription, String> {
+        let deadline = Instant::now() + timeout;
+        let info = self.heap_info(address)?;
+        if !info.tagged_pointer && info.object.is_none() {
+            return Err(format!(
+                "0x{address:x} does not point at an Objective-C or Swift object"
+            ));
+        }
+        let msg_send = self.runtime_symbol(LIBOBJC, "_objc_msgSend")?;
+        let register_name = self.runtime_symbol(LIBOBJC, "_sel_registerName")?;
+        let send = |backend: &mut Self, receiver: u64, selector: &str| {
+            let name = format!("{selector}\0");
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let selector = backend.call_function(
+                thread_id,
+                register_name,
+                &[CallArgument::Bytes(name.into_bytes())],
+                remaining,
+            )?;
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            backend.call_function(
+                thread_id,
+                msg_send,
+                &[CallArgument::Value(receiver), CallArgument::Value(selector)],
+                remaining,
+            )
+        };
+        let description = send(self, address, "debugDescription")?;
+        if description == 0 {
+            return Err(format!("0x{address:x} has no description"));
+        }
+        let text = send(self, description, "UTF8String")?;
+        if text == 0 {
+            return Err(format!("the description of 0x{address:x} is not UTF-8"));
+        }
+        let (description, truncated) = self
+            .read_c_string(text, MAX_DESCRIPTION_LEN)
+            .map_err(|err| format!("failed to read the description: {err}"))?;
+        Ok(ObjectDescription {
+            address: info.address,
+            class: info
+                .object
+                .map(|object| object.class.swift_name.unwrap_or(object.class.name)),
+            description,
+            truncated,
+        })
+    }
+
+    /// The NUL-terminated string at `address`, cut at `max_len` bytes, and
+    /// whether it was cut. Invalid UTF-8 is replaced.
+    pub fn read_c_string(
+        &mut self,
+        address: u64,
+        max_len: usize,
+    ) -> Result<(String, bool), String> {
+        let client = self.ensure_gdb()?;
+        let mut bytes = Vec::new();
+        let mut truncated = true;
+        while bytes.len() < max_len {
+            let chunk = client
+                .read_memory(address + bytes.len() as u64, 1024)
+                .map_err(|err| err.to_string())?;
+            if let Some(end) = chunk.iter().position(|&byte| byte == 0) {
+                bytes.extend_from_slice(&chunk[..end]);
+                truncated = false;
+                break;
+            }
+            if chunk.is_empty() {
+                break;
+            }
+            bytes.extend_from_slice(&chunk);
+        }
+        bytes.truncate(max_len);
+        Ok((String::from_utf8_lossy(&bytes).into_owned(), truncated))
+    }
+
+    /// Calls the function at `function` on `thread_id` with up to eight
+    /// integer or pointer `args` and returns `x0`. Only that thread runs; it
+    /// returns into a breakpoint on the main image's Mach-O header, which no
+    /// code executes, and its registers are restored afterwards whether or
+    /// not the call completed. A call still running after `timeout`, or past
+    /// the connection's deadline if that comes first, is interrupted.
+    pub fn call_function(
+        &mut self,
+        thread_id: i64,
+        function: u64,
+        args: &[CallArgument],
+        timeout: Duration,
+    ) -> Result<u64, String> {
+        if args.len() > 8 {
+            return Err(format!("{} arguments do not fit in x0-x7", args.len()));
+        }
+        let deadline = Instant::now() + timeout;
+        let trap = self.call_return_address()?;
+        let client = self.ensure_gdb()?;
+        client
+            .select_thread(thread_id.max(1) as u64)
+            .map_err(|err| format!("failed to select thread {thread_id}: {err}"))?;
+        let saved = client
+            .read_all_registers()
+            .map_err(|err| format!("failed to save the registers of thread {thread_id}: {err}"))?;
+        // The call has to interrupt and clean up after itself once the
+        // deadline passes, so packets are not refused until it has.
+        let bound = client.deadline();
+        let deadline = bound.map_or(deadline, |bound| bound.min(deadline));
+        client.set_deadline(None);
+        let result = self.run_call(thread_id, function, args, trap, deadline);
+        let client = self.ensure_gdb()?;
+        let restored = client
+            .select_thread(thread_id.max(1) as u64)
+            .and_then(|_| client.write_all_registers(&saved))
+            .map_err(|err| format!("failed to restore the registers of thread {thread_id}: {err}"));
+        client.set_deadline(bound);
+        let value = result?;
+        restored?;
+        Ok(value)
+    }
+
+    fn run_call(
+        &mut self,
+        thread_id: i64,
+        function: u64,
+        args: &[CallArgument],
+        trap: u64,
+        deadline: Instant,
+    ) -> Result<u64, String> {
+        let mut stack = self.read_register_u64(thread_id, "sp")? - CALL_RED_ZONE;
+        let mut values = Vec::with_capacity(args.len());
+        for arg in args {
+            match arg {
+                CallArgument::Value(value) => values.push(*value),
+                CallArgument::Bytes(bytes) => {
+                    stack = (stack - bytes.len() as u64) & !15;
+                    self.write_memory(stack, bytes)?;
+                    values.push(stack);
+                }
+            }
+        }
+        for (index, value) in values.into_iter().enumerate() {
+            self.set_register(thread_id, &format!("x{index}"), value)?;
+        }
+        self.set_register(thread_id, "sp", (stack & !15) - 16)?;
+        self.set_register(thread_id, "ra", trap)?;
+        self.set_register(thread_id, "pc", function)?;
+
+        let client = self.ensure_gdb()?;
+        client
+            .set_software_breakpoint(trap)
+            .map_err(|err| format!("failed to plant the call's return breakpoint: {err}"))?;
+        let stop = client
+            .continue_threads(&[thread_id as u64])
+            .and_then(|_| client.wait_for_stop_until(deadline));
+        let stop = match stop {
+            Ok(Some(stop)) => Ok(stop),
+            Ok(None) => {
+                let _ = client.interrupt();
+                let _ = client.wait_for_stop_until(Instant::now() + CALL_INTERRUPT_GRACE);
+                let _ = client.clear_software_breakpoint(trap);
+                return Err(format!(
+                    "the call to 0x{function:x} did not return in time and was interrupted"
+                ));
+            }
+            Err(err) => Err(err),
+        };
+        let _ = client.clear_software_breakpoint(trap);
+        let stop = stop.map_err(|err| format!("the call to 0x{function:x} failed: {err}"))?;
+        let pc = self.read_register_u64(thread_id, "pc")?;
+        if stop.thread_id.is_some_and(|id| id != thread_id as u64) || pc != trap {
+            let event = BackendStopEvent::from_reply(stop);
+            return Err(format!(
+                "the call to 0x{function:x} stopped early ({}) at 0x{pc:x}",
+                event.description
+            ));
+        }
+        self.read_register_u64(thread_id, "x0")
+    }
+
+    /// Where called functions return to: the main image's Mach-O header,
+    /// which sits in executable `__TEXT` but is never run.
+    fn call_return_address(&mut self) -> Result<u64, String> {
+        const KEY: &str = "`header";
+        if let Some(address) = self.runtime_symbols.get(KEY) {
+            return Ok(*address);
+        }
+        let images = self
+            .ensure_gdb()?
+            .loaded_images()
+            .map_err(|err| format!("failed to list loaded images: {err}"))?;
+        let address = images
+            .first()
+            .map(|image| image.load_address)
+            .ok_or("the target reports no loaded images")?;
+        self.runtime_symbols.insert(KEY.into(), address);
+        Ok(address)
+    }
+
+    /// Address of the function dyld calls after adding or removing images,
+    /// from `dyld_all_image_infos.notification`. A breakpoint there stops
+    /// the target at every image load, `dlopen` included.
+    pub fn image_load_hook(&mut self) -> Result<u64, String> {
+        let infos = self
+            .ensure_gdb()?
+            .shlib_info_address()
+            .map_err(|err| format!("failed to locate dyld_all_image_infos: {err}"))?;
+        let bytes = self.read_memory(infos + DYLD_NOTIFICATION_OFFSET, 8)?;
+        let notifier = bytes
+            .try_into()
+            .map(u64::from_le_bytes)
+            .map_err(|_| "short read of dyld_all_image_infos".to_string())?;
+        match notifier & CODE_POINTER_MASK {
+            0 => Err("dyld has not registered an image notifier".into()),
+            hook => Ok(hook),
+        }
+    }
+
+    /// Address of `symbol` exported by the loaded image named `image`, read
+    /// from the image's export trie in target memory.
+    pub fn runtime_symbol(&mut self, image: &str, symbol: &str) -> Result<u64, String> {
+        self.exported_symbol(Some(image), symbol)?
+            .ok_or_else(|| format!("{image} does not export `{symbol}`"))
+    }
+
+    /// Address of the function `name` in the target: a symbol of the main
+    /// image, or else the first loaded library exporting `_name` (or `name`).
+    pub fn function_address(&mut self, name: &str) -> Result<u64, String> {
+        if let Ok(Some(address)) = self.symbol_ctx.symbol_address(name) {
+            return Ok(address);
+        }
+        for symbol in [format!("_{name}"), name.to_string()] {
+            if let Some(address) = self.exported_symbol(None, &symbol)? {
+                return Ok(address);
+            }
+        }
+        Err(format!(
+            "no function `{name}` in the main image or the loaded libraries"
+        ))
+    }
+
+    /// `symbol` as exported by the loaded image named `image`, or by any
+    /// loaded image, in load order, without one.
+    fn exported_symbol(
+        &mut self,
+        image: Option<&str>,
+        symbol: &str,
+    ) -> Result<Option<u64>, String> {
+        let key = format!("{}`{symbol}", image.unwrap_or("*"));
+        if let Some(address) = self.runtime_symbols.get(&key) {
+            return Ok(Some(*address));
+        }
+        let client = self.ensure_gdb()?;
+        let images = client
+            .loaded_images()
+            .map_err(|err| format!("failed to list loaded images: {err}"))?;
+        let headers: Vec<u64> = match image {
+            Some(image) => {
+                let header = images
+                    .iter()
+                    .find(|loaded| {
+                        Path::new(&loaded.path)
+                            .file_name()
+                            .and_then(|name| name.to_str())
+                            == Some(image)
+                    })
+                    .map(|loaded| loaded.load_address)
+                    .ok_or_else(|| format!("{image} is not loaded in the target"))?;
+                vec![header]
+            }
+            None => images.iter().map(|loaded| loaded.load_address).collect(),
+        };
+        let address = headers.into_iter().find_map(|header| {
+            exports::find_export(
+                |address, length| client.read_memory(address, length).ok(),
+                header,
+                symbol,
+            )
+        });
+        if let Some(address) = address {
+            self.runtime_symbols.insert(key, address);
+        }
+        Ok(address)
+    }
+
+    pub fn program_counter(&mut self, thread_id: i64) -> Result<u64, String> {
+        self.read_register_u64(thread_id, "pc")
+    }
+
+    pub fn symbol_address(&self, name: &str) -> Result<u64, String> {
+        self.symbol_ctx
+            .symbol_address(name)
+            .map_err(|err| err.to_string())?
+            .ok_or_else(|| format!("symbol `{name}` not found"))
+    }
+
+    /// Demangled name of the symbol covering `remote_pc`.
+    pub fn symbol_name(&self, remote_pc: u64) -> Option<String> {
+        self.symbol_ctx
+            .symbol_name(remote_pc)
+            .map(|raw| symbols::demangle_symbol(&raw))
+    }
+
+    /// Every address of symbols named `name` in the main image.
+    pub fn lookup_symbol(&self, name: &str) -> Result<Vec<SymbolMatch>, String> {
+        let matches = self
+            .symbol_ctx
+            .lookup_symbols(name)
+            .map_err(|err| err.to_string())?;
+        if matches.is_empty() {
+            return Err(format!("symbol `{name}` not found"));
+        }
+        Ok(matches)
+    }
+
+    /// Function, source location and inlining chain of `remote_pc`, using the
+    /// DWARF line tables and the current slide of the main image.
+    pub fn symbolicate(&self, remote_pc: u64) -> AddressInfo {
+        let frames = self
+            .symbol_ctx
+            .symbolize_frames(remote_pc)
+            .unwrap_or_default();
+        let location = frames.first().and_then(|frame| frame.location.as_ref());
+        let raw_symbol = self.symbol_ctx.symbol_name(remote_pc);
+        let symbol = raw_symbol.as_deref().map(symbols::demangle_symbol);
+        AddressInfo {
+            address: format!("0x{remote_pc:x}"),
+            function: frames.first().and_then(SymbolContext::function_name),
+            mangled_symbol: raw_symbol.filter(|raw| symbol.as_ref() != Some(raw)),
+            symbol,
+            file: location.and_then(|loc| loc.file).map(str::to_string),
+            line: location.and_then(|loc| loc.line),
+            inlined_into: frames
+                .iter()
+                .skip(1)
+                .filter_map(SymbolContext::function_name)
+                .collect(),
+        }
+    }
+
+    pub fn read_register_u64(&mut self, thread_id: i64, name: &str) -> Result<u64, String> {
+        self.read_register_bytes(thread_id, name)
+            .map(|bytes| le_u64(&bytes))
+    }
+
+    fn read_register_bytes(&mut self, thread_id: i64, name: &str) -> Result<Vec<u8>, String> {
+        let info = self.resolve_register(thread_id, name)?;
+        self.ensure_gdb()?
+            .read_register(info.number)
+            .map_err(|err| format!("failed to read register `{name}`: {err}"))
+    }
+
+    /// Selects `thread_id` and looks `name` up in the target description,
+    /// falling back to the built-in arm64 layout.
+    fn resolve_register(&mut self, thread_id: i64, name: &str) -> Result<RegisterInfo, String> {
+        let client = self.ensure_gdb()?;
+        client
+            .select_thread(thread_id.max(1) as u64)
+            .map_err(|err| format!("failed to select thread {thread_id}: {err}"))?;
+        match client.find_register(name) {
+            Ok(Some(info)) => Ok(info),
+            _ => arm64_fallback_registers()
+                .into_iter()
+                .find(|info| info.matches(name))
+                .ok_or_else(|| format!("unknown register `{name}`")),
+        }
+    }
+
+    /// Reads the general-purpose and floating-point registers of `thread_id`.
+    /// Registers covered by the `g` packet are sliced out of it; the rest are
+    /// fetched one at a time with `p`.
+    pub fn registers(&mut self, thread_id: i64) -> Result<Vec<RegisterValue>, String> {
+        let client = self.ensure_gdb()?;
+        client
+            .select_thread(thread_id.max(1) as u64)
+            .map_err(|err| format!("failed to select thread {thread_id}: {err}"))?;
+        let mut infos = client
+            .register_infos()
+            .map_err(|err| format!("failed to query register info: {err}"))?
+            .to_vec();
+        if infos.is_empty() {
+            infos = arm64_fallback_registers();
+        }
+        let file = client.read_all_registers().unwrap_or_default();
+        let mut registers = Vec::new();
+        for info in infos
+            .into_iter()
+            .filter(|info| is_listed_register_set(info.set.as_deref()))
+        {
+            let width = (info.bitsize as usize).div_ceil(8);
+            let bytes = match info
+                .offset
+                .and_then(|offset| file.get(offset..offset + width))
+            {
+                Some(bytes) => bytes.to_vec(),
+                None => match client.read_register(info.number) {
+                    Ok(bytes) => bytes,
+                    Err(_) => continue,
                 },
-                "presentationHint": if idx == 0 { "normal" } else { "subtle" },
-            }));
+            };
+            registers.push(RegisterValue::new(info, &bytes));
+        }
+        Ok(registers)
+    }
+
+    /// Writes `value` to the named register of `thread_id`, zero-extended to the register width.
+    pub fn set_register(&mut self, thread_id: i64, name: &str, value: u64) -> Result<(), String> {
+        let info = self.resolve_register(thread_id, name)?;
+        let width = (info.bitsize as usize).div_ceil(8).max(1);
+        if width < 8 && value >> (width * 8) != 0 {
+            return Err(format!(
+                "value 0x{value:x} does not fit in {}-bit register `{name}`",
+                info.bitsize
+            ));
+        }
+        let mut bytes = value.to_le_bytes().to_vec();
+        bytes.resize(width, 0);
+        self.ensure_gdb()?
+            .write_register(info.number, &bytes)
+            .map_err(|err| format!("failed to write register `{name}`: {err}"))
+    }
+
+    fn ensure_function_index(&mut self) -> Result<(), String> {
+        if self.function_index.is_none() {
+            let main = &self.symbol_ctx.main;
+            let index = FunctionIndex::from_binary(main.debug_path()).map_err(|err| {
+                format!(
+                    "Failed to build DWARF function index for {:?}: {err}",
+                    main.debug_path()
+                )
+            })?;
+            self.function_index = Some(index);
+        }
+        Ok(())
+    }
+
+    /// Keeps `thread_id` stopped whenever the process resumes.
+    pub fn suspend_thread(&mut self, thread_id: i64) -> Result<(), String> {
+        let known = self
+            .ensure_gdb()?
+            .thread_ids()
+            .map_err(|err| format!("failed to list threads: {err}"))?;
+        if !known.contains(&(thread_id as u64)) {
+            return Err(format!("no thread {thread_id}"));
+        }
+        self.suspended_threads.insert(thread_id);
+        Ok(())
+    }
+
+    /// Lets `thread_id` run again with the others on the next resume.
+    pub fn resume_thread(&mut self, thread_id: i64) -> Result<(), String> {
+        if self.suspended_threads.remove(&thread_id) {
+            Ok(())
+        } else {
+            Err(format!("thread {thread_id} is not suspended"))
         }
+    }
 
-        out
+    pub fn suspended_threads(&self) -> Vec<i64> {
+        self.suspended_threads.iter().copied().collect()
     }
 
-    fn ensure_line_index(&mut self) -> Result<(), String> {
-        if self.line_index.is_none() {
-            match LineIndex::from_binary(&self.symbol_ctx.main.path) {
-                Ok(index) => self.line_index = Some(index),
-                Err(err) => {
-                    return Err(format!(
-                        "Failed to build DWARF line index for {:?}: {err}",
-                        self.symbol_ctx.main.path
-                    ))
+    /// Resumes every thread except the suspended ones, forgetting suspended
+    /// threads that have exited, optionally delivering `(thread, signal)`.
+    fn resume_unsuspended(&mut self, signal: Option<(i64, u8)>) -> Result<(), String> {
+        if let Some((thread_id, _)) = signal {
+            if self.suspended_threads.contains(&thread_id) {
+                return Err(format!(
+                    "thread {thread_id} is suspended; resume_thread it first"
+                ));
+            }
+        }
+        if self.suspended_threads.is_empty() {
+            let client = self.ensure_gdb()?;
+            return match signal {
+                Some((thread_id, signal)) => {
+                    client.continue_with_signal(thread_id as u64, signal, None)
                 }
+                None => client.continue_all(),
             }
+            .map_err(|err| err.to_string());
         }
-        Ok(())
+        let running = self.unsuspended_threads()?;
+        if running.is_empty() {
+            return Err("every thread is suspended; resume_thread one first".into());
+        }
+        let client = self.ensure_gdb()?;
+        match signal {
+            Some((thread_id, signal)) => {
+                client.continue_with_signal(thread_id as u64, signal, Some(&running))
+            }
+            None => client.continue_threads(&running),
+        }
+        .map_err(|err| err.to_string())
     }
 
-    pub fn scopes(&self) -> Vec<Value> {
-        vec![json!({
-            "name": "Locals",
-            "variablesReference": 1,
-            "expensive": false
-        })]
+    /// The threads that are not suspended, forgetting suspended threads that
+    /// have exited.
+    fn unsuspended_threads(&mut self) -> Result<Vec<u64>, String> {
+        let threads = self
+            .ensure_gdb()?
+            .thread_ids()
+            .map_err(|err| format!("failed to list threads: {err}"))?;
+        self.suspended_threads
+            .retain(|id| threads.contains(&(*id as u64)));
+        Ok(threads
+            .into_iter()
+            .filter(|id| !self.suspended_threads.contains(&(*id as i64)))
+            .collect())
     }
 
-    pub fn variables(&self, variables_reference: i64) -> Vec<Value> {
-        vec![
-            json!({
-                "name": "var",
-                "value": format!("value-{variables_reference}"),
-                "type": "string",
-                "variablesReference": 0
-            }),
-            json!({
-                "name": "counter",
-                "value": "123",
-                "type": "int",
-                "variablesReference": 0
-            }),
-        ]
+    /// Kills the inferior and drops the debugserver connection, which ends with it.
+    pub fn kill(&mut self) -> Result<ExitStatus, String> {
+        if self.attached_mock().is_some() {
+            self.disconnect()?;
+            return Ok(ExitStatus::from(Some(ProcessExit::Signaled(SIGKILL))));
+        }
+        let exit = self
+            .ensure_gdb()?
+            .kill()
+            .map_err(|err| format!("failed to kill the process: {err}"))?;
+        self.disconnect()?;
+        Ok(ExitStatus::from(exit))
     }
 
-    pub fn r#continue(&mut self, _thread_id: i64) -> Result<Option<BackendStopEvent>, String> {
-        let client = self.ensure_gdb()?;
-        client.continue_all().map_err(|err| err.to_string())?;
-        client
-            .wait_for_stop()
-            .map(BackendStopEvent::from_reply)
-            .map(Some)
-            .map_err(|err| err.to_string())
+    pub fn detach(&mut self) -> Result<(), String> {
+        if self.attached_mock().is_some() {
+            return self.disconnect();
+        }
+        self.ensure_gdb()?
+            .detach()
+            .map_err(|err| format!("failed to detach: {err}"))?;
+        self.disconnect()
     }
 
-    pub fn step_over(&mut self, thread_id: i64) -> Result<Option<BackendStopEvent>, String> {
-        let client = self.ensure_gdb()?;
-        client
-            .step_thread(thread_id)
-            .map_err(|err| err.to_string())?;
-        client
-            .wait_for_stop()
-            .map(BackendStopEvent::from_reply)
-            .map(Some)
-            .map_err(|err| err.to_string())
+    /// See [`GdbRemoteClient::interrupter`].
+    pub fn interrupter(&self) -> Option<Interrupter> {
+        self.gdb_client.as_ref()?.interrupter().ok()
     }
 
-    pub fn step_in(&mut self, thread_id: i64) -> Result<Option<BackendStopEvent>, String> {
-        self.step_over(thread_id)
+    pub fn is_connected(&self) -> bool {
+        self.gdb_client.is_some() || self.attached_mock().is_some()
+    }
+
+    /// Whether this backend plays a fixture back; see [`Backend::new_mock`].
+    pub fn is_mock(&self) -> bool {
+        self.mock.is_some()
+    }
+
+    pub fn connected_port(&self) -> Option<u16> {
+        self.connected_port
+    }
+
+    /// The inferior's pid, asked of debugserver once per connection.
+    pub fn process_id(&mut self) -> Option<u64> {
+        if self.process_id.is_none() {
+            self.process_id = self.gdb_client.as_mut()?.process_id().ok();
+        }
+        self.process_id
+    }
+
+    /// How the inferior ended, if a resume saw it exit since the last call.
+    pub fn take_process_exit(&mut self) -> Option<ExitStatus> {
+        if let Some(mock) = self.mock.as_mut() {
+            return Some(Some(mock.take_exit()?).into());
+        }
+        let exit = self.gdb_client.as_mut()?.take_exit()?;
+        Some(Some(exit).into())
     }
 
     pub fn disconnect(&mut self) -> Result<(), String> {
+        if let Some(mock) = self.mock.as_mut() {
+            mock.detach();
+        }
         self.connected_port = None;
         self.gdb_client = None;
+        self.suspended_threads.clear();
+        self.runtime_symbols.clear();
+        self.planted.clear();
+        self.process_id = None;
         Ok(())
     }
 
@@ -255,7 +2008,36 @@ impl Backend {
         )]
     }
 
+    fn attached_mock(&self) -> Option<&MockTarget> {
+        self.mock.as_ref().filter(|mock| mock.is_attached())
+    }
+
+    /// The next stop of an attached mock target; `None` for a real one.
+    fn mock_resume(&mut self, thread_id: i64) -> Option<Result<Option<BackendStopEvent>, String>> {
+        let mock = self.mock.as_mut().filter(|mock| mock.is_attached())?;
+        Some(match mock.resume() {
+            Ok(stop) => Ok(Some(BackendStopEvent {
+                reason: stop.reason.as_str(),
+                description: stop.description(),
+                thread_id: stop.thread_id.unwrap_or(thread_id),
+                return_value: None,
+                signal: stop.signal(),
+                exception: None,
+            })),
+            Err(exit) => Err(format!("process {exit}")),
+        })
+    }
+
     fn ensure_gdb(&mut self) -> Result<&mut GdbRemoteClient, String> {
+        match &self.mock {
+            Some(mock) if mock.is_attached() => {
+                return Err("not available from the mock backend".into())
+            }
+            Some(_) => {
+                return Err("mock target not attached; call connect_debugserver first".into())
+            }
+            None => {}
+        }
         self.gdb_client
             .as_mut()
             .ok_or_else(|| "no gdb-remote connection; call connect_debugserver first".to_string())
@@ -264,6 +2046,81 @@ impl Backend {
     pub fn program_path(&self) -> &Path {
         &self.symbol_ctx.main.path
     }
+
+    /// The architecture loaded, when the program is a universal binary.
+    pub fn program_slice(&self) -> Option<&str> {
+        self.symbol_ctx
+            .main
+            .slice
+            .as_ref()
+            .map(|slice| slice.arch.as_str())
+    }
+
+    /// Where DWARF is read from: the matching dSYM, or the program itself.
+    pub fn debug_info_path(&self) -> &Path {
+        self.symbol_ctx.main.debug_path()
+    }
+}
+
+/// One DAP stack frame for `frame`, or an unknown one without symbols.
+fn frame_json(frame_id: i64, frame: Option<&LoaderFrame<'_>>, top: bool) -> Value {
+    let function_name = frame
+        .and_then(SymbolContext::function_name)
+        .unwrap_or_else(|| "<unknown>".into());
+    let mangled_name = frame.and_then(SymbolContext::mangled_function_name);
+    let location = frame.and_then(|frame| frame.location.as_ref());
+    let file_path = location
+        .and_then(|loc| loc.file)
+        .unwrap_or("<unknown>")
+        .to_string();
+    let line = location
+        .and_then(|loc| loc.line)
+        .map(|line| line as i64)
+        .unwrap_or(0);
+    let source_name = file_path
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or(&file_path)
+        .to_string();
+
+    let mut value = json!({
+        "id": frame_id,
+        "name": function_name,
+        "line": line,
+        "column": 1,
+        "source": {
+            "name": source_name,
+            "path": file_path,
+        },
+        "presentationHint": if top { "normal" } else { "subtle" },
+    });
+    if let Some(mangled_name) = mangled_name {
+        value["mangledName"] = json!(mangled_name);
+    }
+    value
+}
+
+/// The little-endian instruction words of `bytes` read from code.
+fn instruction_words(bytes: &[u8]) -> impl Iterator<Item = u32> + '_ {
+    bytes
+        .chunks_exact(4)
+        .map(|chunk| u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+}
+
+/// One DAP stack frame for a frame of a mock target.
+fn mock_frame_json(frame_id: i64, frame: &MockFrame, top: bool) -> Value {
+    let path = frame.file.as_deref().unwrap_or("<unknown>");
+    json!({
+        "id": frame_id,
+        "name": frame.function,
+        "line": frame.line,
+        "column": 1,
+        "source": {
+            "name": path.rsplit(['/', '\\']).next().unwrap_or(path),
+            "path": path,
+        },
+        "presentationHint": if top { "normal" } else { "subtle" },
+    })
 }
 
 #[derive(Hash, Eq, PartialEq, Debug, Clone)]
@@ -282,6 +2139,342 @@ pub struct BackendStopEvent {
     pub reason: &'static str,
     pub description: String,
     pub thread_id: i64,
+    pub return_value: Option<ReturnValue>,
+    /// Signal number of the stop reply.
+    pub signal: u8,
+    pub exception: Option<MachException>,
+}
+
+/// A variable read from the target, or a member or element of one.
+#[derive(Debug, Clone)]
+pub struct TargetValue {
+    pub name: String,
+    pub ty: String,
+    pub value: String,
+    /// Set when the value has children [`Backend::value_children`] can list.
+    pub children: Option<ValueHandle>,
+}
+
+impl TargetValue {
+    fn unavailable(layout: ValueLayout, err: &str) -> Self {
+        Self {
+            name: layout.name,
+            ty: layout.ty.name,
+            value: format!("<unavailable: {err}>"),
+            children: None,
+        }
+    }
+}
+
+/// The children a `variables` request pages to: `count` of them from
+/// `start`, or all of them from `start` when `count` is unset.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChildPage {
+    pub start: usize,
+    pub count: Option<usize>,
+}
+
+impl ChildPage {
+    /// The items of `items` in this page.
+    pub fn apply<T>(self, items: impl Iterator<Item = T>) -> impl Iterator<Item = T> {
+        items
+            .skip(self.start)
+            .take(self.count.unwrap_or(usize::MAX))
+    }
+
+    /// The indices of an array of `count` elements in this page, at most
+    /// [`MAX_ARRAY_ELEMENTS`] of them.
+    pub fn elements(self, count: u64) -> std::ops::Range<u64> {
+        let start = (self.start as u64).min(count);
+        let listed = self
+            .count
+            .map_or(MAX_ARRAY_ELEMENTS, |listed| listed as u64)
+            .min(MAX_ARRAY_ELEMENTS);
+        start..count.min(start.saturating_add(listed))
+    }
+}
+
+/// What [`Backend::value_children`] needs to expand a value: where it is,
+/// its bytes (for pointers) and its type. Only good until the target runs.
+#[derive(Debug, Clone)]
+pub struct ValueHandle {
+    pub(crate) address: Option<u64>,
+    pub(crate) bytes: Vec<u8>,
+    pub(crate) type_offset: TypeOffset,
+}
+
+/// Bytes read for a value of `ty`; unsized types read as a pointer.
+fn value_size(ty: &ValueType) -> usize {
+    ty.byte_size
+        .map_or(8, |size| size as usize)
+        .min(MAX_MEMORY_READ)
+}
+
+/// Value returned by the function that was just stepped out of.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReturnValue {
+    pub function: String,
+    #[serde(rename = "type")]
+    pub ty: String,
+    pub value: String,
+    pub raw: String,
+}
+
+/// How the inferior ended after `terminate`; both fields are absent when the
+/// stub closed the connection without reporting.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ExitStatus {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signal: Option<u8>,
+}
+
+impl From<Option<ProcessExit>> for ExitStatus {
+    fn from(exit: Option<ProcessExit>) -> Self {
+        match exit {
+            Some(ProcessExit::Exited(code)) => Self {
+                exit_code: Some(code),
+                signal: None,
+            },
+            Some(ProcessExit::Signaled(signal)) => Self {
+                exit_code: None,
+                signal: Some(signal),
+            },
+            None => Self::default(),
+        }
+    }
+}
+
+/// An address resolved by `symbolicate`; unknown parts are left out.
+#[derive(Debug, Clone, Serialize)]
+pub struct AddressInfo {
+    pub address: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub function: Option<String>,
+    /// Symbol-table name, which survives when DWARF is missing.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub symbol: Option<String>,
+    /// Raw symbol-table name, when `symbol` is its demangled form.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mangled_symbol: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<u32>,
+    /// Functions the code at `address` was inlined into, innermost first.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub inlined_into: Vec<String>,
+}
+
+/// A loaded image, as reported by `modules`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ModuleInfo {
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uuid: Option<String>,
+    pub load_address: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub slide: Option<String>,
+    /// Local binary used to symbolize this image, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub symbols: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warning: Option<String>,
+}
+
+impl ModuleInfo {
+    fn new(image: &LoadedImage) -> Self {
+        Self {
+            path: image.path.clone(),
+            uuid: image.uuid.clone(),
+            load_address: format!("0x{:x}", image.load_address),
+            slide: image.text_vmaddr.map(|vmaddr| {
+                let slide = image.load_address as i64 - vmaddr as i64;
+                if slide < 0 {
+                    format!("-0x{:x}", slide.unsigned_abs())
+                } else {
+                    format!("0x{slide:x}")
+                }
+            }),
+            symbols: None,
+            warning: None,
+        }
+    }
+}
+
+/// What a pointer points at, as reported by `heap_info`.
+#[derive(Debug, Clone, Serialize)]
+pub struct HeapInfo {
+    pub address: String,
+    /// Objective-C tagged pointers hold their value inline; nothing is read.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub tagged_pointer: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub region: Option<RegionInfo>,
+    #[serde(flatten)]
+    pub object: Option<HeapObject>,
+}
+
+/// A Swift concurrency task, as reported by `tasks`.
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskInfo {
+    /// Absent for an async chain whose task could not be located.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<u32>,
+    pub state: TaskState,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority: Option<u8>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub cancelled: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub flags: Vec<&'static str>,
+    /// Address of the task that created this child task.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+    /// The thread a running task is executing on.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thread_id: Option<i64>,
+    /// The async backtrace, innermost first.
+    pub frames: Vec<AddressInfo>,
+}
+
+/// A Swift actor's status, as reported by `actors`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ActorInfo {
+    pub address: String,
+    pub class: String,
+    /// `default` for actors on the shared executor, `custom` for those
+    /// providing their own (`MainActor`, `unownedExecutor` overrides), whose
+    /// status is not known.
+    pub executor: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state: Option<ActorState>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority: Option<u8>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub escalated: bool,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub distributed_remote: bool,
+    /// Mach port of the thread running the actor.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thread_port: Option<u32>,
+    /// The running task holding the actor's isolation, when found.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub holder: Option<TaskInfo>,
+    /// Tasks queued on the actor.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub waiting: Vec<TaskInfo>,
+    /// A queued task has a higher priority than the actor runs at and the
+    /// actor was not escalated.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub priority_inversion: bool,
+}
+
+/// An object's description, as reported by `describe`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ObjectDescription {
+    pub address: String,
+    /// Class name, demangled for Swift classes; absent for tagged pointers.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub class: Option<String>,
+    pub description: String,
+    /// The description was cut at [`MAX_DESCRIPTION_LEN`] bytes.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+}
+
+/// A mapped region of the target, as reported by `memory_regions`.
+#[derive(Debug, Clone, Serialize)]
+pub struct RegionInfo {
+    pub start: String,
+    pub end: String,
+    pub size: u64,
+    pub permissions: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mapped_file: Option<String>,
+    /// `stack`, `heap`, `image` (a mapped file), `unmapped` or `other`.
+    pub kind: &'static str,
+}
+
+impl RegionInfo {
+    fn new(region: &MemoryRegion) -> Self {
+        let region_type = region.region_type.as_deref().unwrap_or_default();
+        let kind = if region.permissions.is_empty() {
+            "unmapped"
+        } else if region_type.contains("stack") {
+            "stack"
+        } else if region_type.contains("heap") || region_type.contains("malloc") {
+            "heap"
+        } else if region.name.is_some() {
+            "image"
+        } else {
+            "other"
+        };
+        Self {
+            start: format!("0x{:x}", region.start),
+            end: format!("0x{:x}", region.start.saturating_add(region.size)),
+            size: region.size,
+            permissions: region.permissions.clone(),
+            mapped_file: region.name.clone(),
+            kind,
+        }
+    }
+}
+
+/// The value of a `0x`-prefixed hex address as this module formats them.
+fn parse_address(text: &str) -> Result<u64, String> {
+    u64::from_str_radix(text.trim_start_matches("0x"), 16)
+        .map_err(|err| format!("bad address `{text}`: {err}"))
+}
+
+/// A register of the selected thread, as reported by `registers`.
+#[derive(Debug, Clone, Serialize)]
+pub struct RegisterValue {
+    pub name: String,
+    pub number: u32,
+    pub value: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub float: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub set: Option<String>,
+}
+
+impl RegisterValue {
+    fn new(info: RegisterInfo, bytes: &[u8]) -> Self {
+        let hex: String = bytes
+            .iter()
+            .rev()
+            .map(|byte| format!("{byte:02x}"))
+            .collect();
+        let floating = info
+            .set
+            .as_deref()
+            .is_some_and(|set| set.contains("Floating Point"));
+        let float = match (floating, bytes.len()) {
+            (true, 4) => bytes
+                .try_into()
+                .ok()
+                .map(|raw| f32::from_le_bytes(raw) as f64),
+            (true, 8) => bytes.try_into().ok().map(f64::from_le_bytes),
+            _ => None,
+        };
+        Self {
+            name: info.name,
+            number: info.number,
+            value: format!("0x{hex}"),
+            float,
+            set: info.set,
+        }
+    }
+}
+
+/// Keeps the sets `registers` reports: general-purpose (including fp/lr/sp/pc)
+/// and floating-point. Targets that do not group registers report everything.
+fn is_listed_register_set(set: Option<&str>) -> bool {
+    set.is_none_or(|set| set.contains("General Purpose") || set.contains("Floating Point"))
 }
 
 impl BackendStopEvent {
@@ -291,12 +2484,18 @@ impl BackendStopEvent {
             StopReason::Breakpoint => ("breakpoint", "Breakpoint hit".to_string()),
             StopReason::Step => ("step", "Step completed".to_string()),
             StopReason::Signal => ("signal", format!("Signal {}", reply.signal)),
-            StopReason::Unknown(text) => ("stopped", text),
+            StopReason::Unknown(text) => match &reply.exception {
+                Some(exception) if text == "exception" => ("stopped", exception.to_string()),
+                _ => ("stopped", text),
+            },
         };
         Self {
             reason,
             description,
             thread_id,
+            return_value: None,
+            signal: reply.signal,
+            exception: reply.exception,
         }
     }
 }
@@ -338,6 +2537,31 @@ impl LineIndex {
         Ok(index)
     }
 
+    /// Lines of `file` that have code, in order; like [`lookup`](Self::lookup),
+    /// falls back to the file name when the path itself is not recorded.
+    pub fn lines(&self, file: &str) -> Vec<u64> {
+        let name = Path::new(file).file_name().and_then(|n| n.to_str());
+        let mut lines: Vec<u64> = self
+            .map
+            .keys()
+            .filter(|key| key.file == file)
+            .map(|key| key.line)
+            .collect();
+        if lines.is_empty() {
+            if let Some(name) = name.filter(|name| *name != file) {
+                lines.extend(
+                    self.map
+                        .keys()
+                        .filter(|key| key.file == name)
+                        .map(|key| key.line),
+                );
+            }
+        }
+        lines.sort_unstable();
+        lines.dedup();
+        lines
+    }
+
     pub fn lookup(&self, file: &str, line: u64) -> Vec<AddressRange> {
         let mut results = Vec::new();
         let key = FileLine {
@@ -389,8 +2613,8 @@ impl LineIndex {
                 continue;
             }
 
-            let file_path = line_file_path(dwarf, unit, header, &row)
-                .unwrap_or_else(|| "<unknown>".to_string());
+            let file_path =
+                line_file_path(dwarf, unit, header, row).unwrap_or_else(|| "<unknown>".to_string());
             let line = row.line().map(|value| value.get()).unwrap_or(0);
             let address = row.address();
 
@@ -434,7 +2658,7 @@ impl LineIndex {
 
 pub fn binary_has_dwarf_line_info(path: &Path) -> bool {
     match fs::read(path) {
-        Ok(data) => match object::File::parse(&*data) {
+        Ok(data) => match object::File::parse(symbols::preferred_slice(&data)) {
             Ok(file) => {
                 let endian = if file.is_little_endian() {
                     RunTimeEndian::Little
@@ -464,18 +2688,6 @@ pub fn binary_has_dwarf_line_info(path: &Path) -> bool {
     }
 }
 
-fn load_section_vec(
-    file: &object::File<'_>,
-    id: SectionId,
-) -> Result<Vec<u8>, object::read::Error> {
-    if let Some(section) = file.section_by_name(id.name()) {
-        let data = section.uncompressed_data()?;
-        Ok(data.into_owned())
-    } else {
-        Ok(Vec::new())
-    }
-}
-
 fn line_file_path(
     dwarf: &gimli::Dwarf<EndianSlice<'_, RunTimeEndian>>,
     unit: &Unit<EndianSlice<'_, RunTimeEndian>>,
@@ -511,9 +2723,20 @@ mod tests {
         std::hint::black_box(());
     }
 
+    #[inline(always)]
+    fn backend_inlined_test_leaf(value: u64) -> u64 {
+        std::hint::black_box(value).wrapping_mul(3)
+    }
+
+    #[no_mangle]
+    #[inline(never)]
+    pub extern "C" fn backend_inline_test_caller(value: u64) -> u64 {
+        backend_inlined_test_leaf(value) + 1
+    }
+
     #[test]
     fn threads_have_id_and_name() {
-        let backend = test_backend();
+        let mut backend = test_backend();
         let threads = backend.threads();
         assert!(!threads.is_empty(), "expected at least one thread");
         let thread = threads.first().unwrap();
@@ -521,6 +2744,42 @@ mod tests {
         assert!(thread.get("name").is_some());
     }
 
+    #[test]
+    fn module_info_formats_uuid_and_slide() {
+        let uuid = [
+            0x8b, 0x5a, 0x9d, 0x3c, 0x1f, 0x2e, 0x4a, 0x6b, 0x9c, 0x0d, 0x11, 0x22, 0x33, 0x44,
+            0x55, 0x66,
+        ];
+        assert_eq!(format_uuid(&uuid), "8B5A9D3C-1F2E-4A6B-9C0D-112233445566");
+        let module = ModuleInfo::new(&LoadedImage {
+            path: "/usr/lib/dyld".into(),
+            load_address: 0x1_0400_0000,
+            uuid: None,
+            text_vmaddr: Some(0x1_0000_0000),
+        });
+        assert_eq!(module.load_address, "0x104000000");
+        assert_eq!(module.slide.as_deref(), Some("0x4000000"));
+    }
+
+    #[test]
+    fn region_info_classifies_regions() {
+        let region = |permissions: &str, name: Option<&str>, region_type: Option<&str>| {
+            RegionInfo::new(&MemoryRegion {
+                start: 0x1000,
+                size: 0x2000,
+                permissions: permissions.into(),
+                name: name.map(Into::into),
+                region_type: region_type.map(Into::into),
+            })
+        };
+        let stack = region("rw", None, Some("stack"));
+        assert_eq!((stack.kind, stack.end.as_str()), ("stack", "0x3000"));
+        assert_eq!(region("rw", None, Some("heap")).kind, "heap");
+        assert_eq!(region("rx", Some("/usr/lib/dyld"), None).kind, "image");
+        assert_eq!(region("", None, None).kind, "unmapped");
+        assert_eq!(region("r", None, None).kind, "other");
+    }
+
     #[test]
     fn update_slide_tracks_remote_base() {
         let mut backend = test_backend_with_vmaddr(0x1000);
@@ -583,6 +2842,99 @@ mod tests {
         assert_eq!(frame.get("line").unwrap().as_i64().unwrap(), 0);
     }
 
+    #[test]
+    fn stack_trace_expands_inlined_frames() {
+        let backend = test_backend();
+        backend_inline_test_caller(2);
+        let caller = find_symbol_address("backend_inline_test_caller");
+        // Some address in the caller runs the inlined body.
+        let Some(pc) = (caller..caller + 0x100).step_by(4).find(|&pc| {
+            backend
+                .symbol_ctx
+                .symbolize_frames(pc)
+                .unwrap_or_default()
+                .len()
+                > 1
+        }) else {
+            eprintln!("skipping stack_trace_expands_inlined_frames: nothing was inlined");
+            return;
+        };
+        let mut backend = backend;
+        backend.set_frame_provider(move |_thread_id| vec![(42, pc)]);
+
+        let frames = backend.stack_trace(1);
+        assert_eq!(frames.len(), 2);
+        assert!(frames[0]["name"]
+            .as_str()
+            .unwrap()
+            .contains("backend_inlined_test_leaf"));
+        assert_eq!(frames[0]["inlined"], true);
+        assert!(frames[1]["name"]
+            .as_str()
+            .unwrap()
+            .contains("backend_inline_test_caller"));
+        assert!(frames[1].get("inlined").is_none());
+        assert!(frames.iter().all(|frame| frame["id"] == 42));
+    }
+
+    #[test]
+    fn target_values_render_by_kind_and_expand_when_located() {
+        let mut backend = test_backend();
+        let layout = |name: &str, kind, byte_size, expandable| ValueLayout {
+            name: name.into(),
+            ty: ValueType {
+                name: format!("{name}_type"),
+                byte_size: Some(byte_size),
+                kind,
+            },
+            type_offset: 0x40,
+            expandable,
+        };
+
+        let count = backend.target_value(
+            layout("count", ValueKind::Signed, 4, false),
+            Some(0x1000),
+            (-3i32).to_le_bytes().to_vec(),
+        );
+        assert_eq!(count.value, "-3");
+        assert!(count.children.is_none());
+
+        let point = backend.target_value(
+            layout("point", ValueKind::Aggregate, 8, true),
+            Some(0x1000),
+            vec![0; 8],
+        );
+        assert_eq!(point.value, "{...}");
+        assert_eq!(point.children.as_ref().unwrap().address, Some(0x1000));
+        // Members of a struct held in registers have no address to read from.
+        let in_register = backend.target_value(
+            layout("point", ValueKind::Aggregate, 8, true),
+            None,
+            vec![0; 8],
+        );
+        assert!(in_register.children.is_none());
+
+        let pointer = backend.target_value(
+            layout("next", ValueKind::Pointer, 8, true),
+            None,
+            0x2000u64.to_le_bytes().to_vec(),
+        );
+        assert_eq!(pointer.value, "0x2000");
+        assert_eq!(pointer.children.unwrap().bytes, 0x2000u64.to_le_bytes());
+    }
+
+    #[test]
+    fn child_pages_reach_every_array_element() {
+        let page = |start, count| ChildPage { start, count };
+        assert_eq!(ChildPage::default().elements(1000), 0..MAX_ARRAY_ELEMENTS);
+        assert_eq!(page(256, None).elements(1000), 256..512);
+        assert_eq!(page(990, Some(50)).elements(1000), 990..1000);
+        assert_eq!(page(10, Some(5000)).elements(1000), 10..266);
+        assert_eq!(page(2000, None).elements(1000), 1000..1000);
+        let members = page(1, Some(2)).apply(["a", "b", "c", "d"].into_iter());
+        assert_eq!(members.collect::<Vec<_>>(), ["b", "c"]);
+    }
+
     #[test]
     fn line_index_lookup_returns_ranges() {
         let mut map = HashMap::new();
@@ -611,10 +2963,66 @@ mod tests {
     #[test]
     fn backend_from_app_uses_symbol_context() {
         let exe = std::env::current_exe().unwrap();
-        let backend = Backend::new_from_app(&exe).unwrap();
+        let backend = Backend::new_from_app(&exe, None, None).unwrap();
         assert_eq!(backend.symbol_ctx.main.path, exe);
     }
 
+    #[test]
+    fn register_value_formats_integer_and_float_registers() {
+        let mut fallback = arm64_fallback_registers();
+        assert!(fallback.iter().any(|info| info.matches("ra")));
+        let pc = fallback.remove(32);
+        let value = RegisterValue::new(pc, &0x1_0000_4000u64.to_le_bytes());
+        assert_eq!(value.name, "pc");
+        assert_eq!(value.value, "0x0000000100004000");
+        assert!(value.float.is_none());
+
+        let d0 = RegisterInfo {
+            number: 66,
+            name: "d0".into(),
+            alt_name: None,
+            generic: None,
+            bitsize: 64,
+            offset: None,
+            set: Some("Floating Point Registers".into()),
+        };
+        let value = RegisterValue::new(d0, &1.5f64.to_le_bytes());
+        assert_eq!(value.float, Some(1.5));
+        assert!(!is_listed_register_set(Some("Exception State Registers")));
+    }
+
+    #[test]
+    fn symbol_address_resolves_by_name() {
+        let backend = test_backend();
+        backend_symbol_test_function();
+        let expected = find_symbol_address("backend_symbol_test_function");
+        assert_eq!(
+            backend.symbol_address("backend_symbol_test_function"),
+            Ok(expected)
+        );
+        assert!(backend.symbol_address("no_such_symbol_anywhere").is_err());
+    }
+
+    #[test]
+    fn symbolicate_and_lookup_round_trip() {
+        let backend = test_backend();
+        backend_symbol_test_function();
+        let expected = find_symbol_address("backend_symbol_test_function");
+        let matches = backend
+            .lookup_symbol("backend_symbol_test_function")
+            .unwrap();
+        assert!(matches.iter().any(|symbol| symbol.address == expected));
+
+        let info = backend.symbolicate(expected);
+        assert_eq!(info.address, format!("0x{expected:x}"));
+        assert!(info
+            .function
+            .is_some_and(|name| name.contains("backend_symbol_test_function")));
+        assert!(info.file.is_some_and(|file| file.ends_with("backend.rs")));
+        assert!(info.line.is_some());
+        assert!(backend.lookup_symbol("no_such_symbol_anywhere").is_err());
+    }
+
     #[test]
     fn update_breakpoints_succeeds_without_gdb_client() {
         let mut backend = test_backend();
@@ -631,7 +3039,34 @@ mod tests {
                 }],
             )]),
         });
-        assert!(backend.update_breakpoints("/tmp/foo.rs", &[42]).is_ok());
+        assert_eq!(
+            backend.update_breakpoints("/tmp/foo.rs", &[42, 7]),
+            Ok(vec![42, 7])
+        );
+    }
+
+    #[test]
+    fn line_index_lists_lines_with_code() {
+        let entry = |file: &str, line| {
+            (
+                FileLine {
+                    file: file.into(),
+                    line,
+                },
+                vec![AddressRange { low: 0, high: 4 }],
+            )
+        };
+        let index = LineIndex {
+            map: HashMap::from([
+                entry("/src/App.swift", 45),
+                entry("/src/App.swift", 40),
+                entry("/src/Other.swift", 41),
+                entry("Loose.swift", 7),
+            ]),
+        };
+        assert_eq!(index.lines("/src/App.swift"), vec![40, 45]);
+        assert_eq!(index.lines("/elsewhere/Loose.swift"), vec![7]);
+        assert!(index.lines("/src/Missing.swift").is_empty());
     }
 
     #[test]
@@ -660,6 +3095,118 @@ mod tests {
         assert!(binary_has_dwarf_line_info(&exe));
     }
 
+    /// A debugserver answering each packet from `reply`, acking every one,
+    /// that records what it was sent.
+    fn fake_debugserver<F>(mut reply: F) -> (u16, Arc<std::sync::Mutex<Vec<String>>>)
+    where
+        F: FnMut(&str) -> Option<String> + Send + 'static,
+    {
+        use std::io::{Read, Write};
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let sent = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let log = sent.clone();
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut byte = [0u8; 1];
+            while stream.read_exact(&mut byte).is_ok() {
+                if byte[0] != b'$' {
+                    continue;
+                }
+                let mut payload = Vec::new();
+                while stream.read_exact(&mut byte).is_ok() && byte[0] != b'#' {
+                    payload.push(byte[0]);
+                }
+                let mut checksum = [0u8; 2];
+                let _ = stream.read_exact(&mut checksum);
+                let payload = String::from_utf8_lossy(&payload).into_owned();
+                let _ = stream.write_all(b"+");
+                let body = reply(&payload);
+                log.lock().unwrap().push(payload);
+                if let Some(body) = body {
+                    let sum = body.bytes().fold(0u8, |acc, b| acc.wrapping_add(b));
+                    let _ = write!(stream, "${body}#{sum:02x}");
+                }
+            }
+        });
+        (port, sent)
+    }
+
+    fn le_hex(value: u64) -> String {
+        value
+            .to_le_bytes()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
+
+    /// Thread 1 stopped in a function that has made a call, so `lr` points
+    /// back into it. The first return to the caller's address is a deeper
+    /// recursive call, with the stack below where it was; the second is the
+    /// frame being stepped out of.
+    fn recursive_step_out_target() -> (u16, Arc<std::sync::Mutex<Vec<String>>>) {
+        const PC: u64 = 0x1_0000_4100;
+        const RETURN: u64 = 0x1_0000_5000;
+        let mut resumes = 0;
+        fake_debugserver(move |payload| {
+            let reply = match payload {
+                p if p.starts_with("qSupported") => "PacketSize=20000".into(),
+                "?" => "S05".into(),
+                "Hg1" => "OK".into(),
+                "p20" => le_hex(if resumes == 0 { PC } else { RETURN }),
+                "p1f" => le_hex([0x16f000, 0x16e000, 0x16f040][resumes.min(2)]),
+                "p1d" => le_hex(0x16f020),
+                "p1e" => le_hex((PC + 0x10) | 0x0045_0000_0000_0000),
+                // Signed, as arm64e saves it.
+                "m16f028,8" => le_hex(RETURN | 0x0021_8000_0000_0000),
+                // A `bl` right after the stop: the frame record is live.
+                "m100004100,20" => format!("10000094{}", "00".repeat(28)),
+                "vCont;c" => {
+                    resumes += 1;
+                    "T05thread:1;".into()
+                }
+                p if p.starts_with("Z0,") || p.starts_with("z0,") => "OK".into(),
+                _ => String::new(),
+            };
+            Some(reply)
+        })
+    }
+
+    #[test]
+    fn step_out_returns_through_the_frame_record_past_recursive_calls() {
+        let (port, sent) = recursive_step_out_target();
+        let mut backend = test_backend();
+        backend.connect_debugserver(port).unwrap();
+        let event = backend.step_out(1).unwrap().unwrap();
+        assert_eq!((event.reason, event.thread_id), ("step", 1));
+        assert_eq!(event.description, "Stepped out");
+        let sent = sent.lock().unwrap();
+        let breakpoints: Vec<_> = sent
+            .iter()
+            .filter(|packet| packet.starts_with("Z0") || packet.starts_with("z0"))
+            .collect();
+        assert_eq!(breakpoints, ["Z0,100005000,1", "z0,100005000,1"]);
+        assert_eq!(sent.iter().filter(|packet| *packet == "vCont;c").count(), 2);
+    }
+
+    #[test]
+    fn step_out_leaves_a_breakpoint_it_did_not_plant() {
+        let (port, sent) = recursive_step_out_target();
+        let mut backend = test_backend();
+        backend.connect_debugserver(port).unwrap();
+        backend.plant_address(0x1_0000_5000).unwrap();
+        // The deeper call stops on the user's breakpoint and is reported.
+        let event = backend.step_out(1).unwrap().unwrap();
+        assert_ne!(event.description, "Stepped out");
+        let sent = sent.lock().unwrap();
+        assert_eq!(
+            sent.iter()
+                .filter(|packet| packet.starts_with("Z0") || packet.starts_with("z0"))
+                .collect::<Vec<_>>(),
+            ["Z0,100005000,1"]
+        );
+    }
+
     fn test_backend() -> Backend {
         test_backend_with_vmaddr(0x0)
     }
@@ -669,8 +3216,10 @@ mod tests {
         let loader = Loader::new(&exe).unwrap();
         let image = Image {
             name: "test".into(),
-            path: exe.into(),
+            path: exe,
             uuid: None,
+            dsym: None,
+            slice: None,
             vmaddr_text,
             slide: 0,
             dwarf: loader,
@@ -695,9 +3244,7 @@ mod tests {
 
     fn symbol_matches(name: &str, symbol_name: &str) -> bool {
         name == symbol_name
-            || name
-                .strip_prefix('_')
-                .map_or(false, |rest| rest == symbol_name)
+            || name.strip_prefix('_') == Some(symbol_name)
             || name.contains(symbol_name)
     }
 }
@@ -19,8 +19,14 @@ struct Args {
     #[arg(long)]
     device: String,
     /// Bundle identifier to start (devicectl --start-stopped).
-    #[arg(long)]
-    bundle_id: String,
+    #[arg(long, required_unless_present_any = ["attach_pid", "attach_name"])]
+    bundle_id: Option<String>,
+    /// Attach to this already-running process instead of launching the app.
+    #[arg(long, conflicts_with_all = ["bundle_id", "attach_name"])]
+    attach_pid: Option<i64>,
+    /// Attach to the running process whose executable has this name.
+    #[arg(long, conflicts_with = "bundle_id")]
+    attach_name: Option<String>,
     /// Optional .app path to install before launching.
     #[arg(long)]
     install_app: Option<PathBuf>,
@@ -39,6 +45,12 @@ struct Args {
     /// Additional arguments forwarded to `devicectl device process launch` (app launch step).
     #[arg(long = "launch-arg")]
     launch_arg: Vec<String>,
+    /// Argument passed to the launched app (repeat flag).
+    #[arg(long = "app-arg", allow_hyphen_values = true)]
+    app_arg: Vec<String>,
+    /// Environment variable for the launched app, as `KEY=VALUE` (repeat flag).
+    #[arg(long = "app-env")]
+    app_env: Vec<String>,
     /// Path to a state file that records the last launch metadata.
     #[arg(long)]
     state_file: Option<PathBuf>,
@@ -53,14 +65,28 @@ struct LaunchResult {
 fn main() -> Result<()> {
     env_logger::builder().format_timestamp(None).init();
     let args = Args::parse();
-    if let Some(app) = &args.install_app {
-        install_app(&args, app)?;
-    }
-    let launch = launch_app_waiting(&args)?;
-    println!(
-        "Process {} for bundle {} is suspended and awaiting debugserver",
-        launch.pid, args.bundle_id
-    );
+    let launch = if let Some(pid) = args.attach_pid {
+        println!("Attaching to running process {pid}");
+        LaunchResult {
+            pid,
+            app_binary: None,
+        }
+    } else if let Some(name) = &args.attach_name {
+        let found = find_running_process(&args, name)?;
+        println!("Attaching to running process {} ({name})", found.pid);
+        found
+    } else {
+        let bundle_id = args.bundle_id.as_deref().unwrap_or_default();
+        if let Some(app) = &args.install_app {
+            install_app(&args, app)?;
+        }
+        let launch = launch_app_waiting(&args, bundle_id)?;
+        println!(
+            "Process {} for bundle {bundle_id} is suspended and awaiting debugserver",
+            launch.pid
+        );
+        launch
+    };
     if let Some(binary) = &launch.app_binary {
         println!("Detected app binary at {}", binary.display());
     } else {
@@ -91,6 +117,7 @@ fn write_state_file(args: &Args, launch: &LaunchResult) -> Result<()> {
         "device": args.device,
         "bundle_id": args.bundle_id,
         "listen_port": args.listen_port,
+        "pid": launch.pid,
         "app_binary": canonical_app.as_ref().map(|p| p.display().to_string()),
     });
     fs::write(&path, serde_json::to_string_pretty(&state)?)
@@ -117,7 +144,50 @@ fn install_app(args: &Args, app: &Path) -> Result<()> {
     Ok(())
 }
 
-fn launch_app_waiting(args: &Args) -> Result<LaunchResult> {
+/// Looks `name` up in `devicectl device info processes`.
+fn find_running_process(args: &Args, name: &str) -> Result<LaunchResult> {
+    let json_path = temp_json_path("processes");
+    let mut cmd = base_command(args);
+    cmd.args(["device", "info", "processes", "--device", &args.device]);
+    cmd.args([
+        "-j",
+        json_path
+            .to_str()
+            .ok_or_else(|| anyhow!("json path is not valid utf-8"))?,
+    ]);
+    let status = cmd
+        .status()
+        .context("failed to run devicectl process listing")?;
+    if !status.success() {
+        return Err(anyhow!("devicectl process listing failed: {status}"));
+    }
+    let data = fs::read_to_string(&json_path)
+        .with_context(|| format!("failed to read json output {}", json_path.display()))?;
+    let value: Value = serde_json::from_str(&data).context("invalid devicectl json")?;
+    fs::remove_file(&json_path).ok();
+    find_process_by_name(&value, name)
+        .ok_or_else(|| anyhow!("no running process named `{name}` on {}", args.device))
+}
+
+/// Matches `name` against the last component of each process's executable URL.
+fn find_process_by_name(value: &Value, name: &str) -> Option<LaunchResult> {
+    let processes = value
+        .pointer("/result/runningProcesses")
+        .and_then(Value::as_array)?;
+    processes.iter().find_map(|process| {
+        let executable = process.get("executable").and_then(Value::as_str)?;
+        let path = executable.strip_prefix("file://").unwrap_or(executable);
+        if Path::new(path).file_name()?.to_str()? != name {
+            return None;
+        }
+        Some(LaunchResult {
+            pid: process.get("processIdentifier")?.as_i64()?,
+            app_binary: Some(PathBuf::from(path)),
+        })
+    })
+}
+
+fn launch_app_waiting(args: &Args, bundle_id: &str) -> Result<LaunchResult> {
     let json_path = temp_json_path("launch");
     let mut cmd = base_command(args);
     cmd.args([
@@ -132,13 +202,19 @@ fn launch_app_waiting(args: &Args) -> Result<LaunchResult> {
     for extra in &args.launch_arg {
         cmd.arg(extra);
     }
+    if !args.app_env.is_empty() {
+        cmd.arg("--environment-variables");
+        cmd.arg(environment_json(&args.app_env)?.to_string());
+    }
     cmd.args([
         "-j",
         json_path
             .to_str()
             .ok_or_else(|| anyhow!("json path is not valid utf-8"))?,
-        &args.bundle_id,
+        bundle_id,
     ]);
+    // devicectl passes everything after the bundle id to the app.
+    cmd.args(&args.app_arg);
     let status = cmd.status().context("failed to run devicectl launch")?;
     if !status.success() {
         return Err(anyhow!("devicectl launch failed: {status}"));
@@ -153,6 +229,20 @@ fn launch_app_waiting(args: &Args) -> Result<LaunchResult> {
     Ok(LaunchResult { pid, app_binary })
 }
 
+/// `KEY=VALUE` entries as the JSON object `--environment-variables` takes.
+fn environment_json(entries: &[String]) -> Result<Value> {
+    let mut env = serde_json::Map::new();
+    for entry in entries {
+        match entry.split_once('=') {
+            Some((key, value)) if !key.is_empty() => {
+                env.insert(key.to_string(), json!(value));
+            }
+            _ => return Err(anyhow!("--app-env expects KEY=VALUE, got `{entry}`")),
+        }
+    }
+    Ok(Value::Object(env))
+}
+
 fn spawn_debugserver(args: &Args, pid: i64) -> Result<Child> {
     println!("Spawning debugserver for pid {pid}");
     let mut cmd = base_command(args);
@@ -290,12 +380,56 @@ mod tests {
         assert_eq!(extract_process_identifier(&value), Some(4242));
     }
 
+    #[test]
+    fn app_arguments_and_environment_parse() {
+        let args = Args::try_parse_from([
+            "ios-llm-devicectl",
+            "--device",
+            "DEVICE",
+            "--bundle-id",
+            "com.example.app",
+            "--app-arg=-AppleLanguages",
+            "--app-arg",
+            "(fr)",
+            "--app-env",
+            "MOCK_API=1",
+            "--app-env",
+            "EMPTY=",
+        ])
+        .unwrap();
+        assert_eq!(args.app_arg, ["-AppleLanguages", "(fr)"]);
+        assert_eq!(
+            environment_json(&args.app_env).unwrap(),
+            serde_json::json!({ "MOCK_API": "1", "EMPTY": "" })
+        );
+        assert!(environment_json(&["NOEQUALS".into()]).is_err());
+    }
+
     #[test]
     fn fallback_to_pid_key() {
         let value = serde_json::json!({ "pid": 1337 });
         assert_eq!(extract_process_identifier(&value), Some(1337));
     }
 
+    #[test]
+    fn finds_running_process_by_executable_name() {
+        let value = serde_json::json!({
+            "result": {
+                "runningProcesses": [
+                    { "executable": "file:///usr/libexec/backboardd", "processIdentifier": 60 },
+                    {
+                        "executable": "file:///private/var/containers/Bundle/Application/ABC/MyApp.app/MyApp",
+                        "processIdentifier": 812
+                    }
+                ]
+            }
+        });
+        let found = find_process_by_name(&value, "MyApp").expect("MyApp not found");
+        assert_eq!(found.pid, 812);
+        assert!(found.app_binary.unwrap().ends_with("MyApp.app/MyApp"));
+        assert!(find_process_by_name(&value, "Other").is_none());
+    }
+
     #[test]
     fn extracts_app_binary_from_json() {
         let value = serde_json::json!({
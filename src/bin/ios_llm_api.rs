@@ -3,40 +3,61 @@
 // curl -s -X POST http://127.0.0.1:4000/command -d '{"action":"set_breakpoint","file":"ViewController.swift","line":42}' -H 'Content-Type: application/json'
 
 use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap, VecDeque},
+    hash::{DefaultHasher, Hash, Hasher},
     net::SocketAddr,
     path::{Path, PathBuf},
     pin::Pin,
     process::Stdio,
     sync::Arc,
     task::{Context as TaskContext, Poll},
-    time::Duration,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
 use anyhow::{bail, Context};
 use axum::{
-    extract::State,
-    http::StatusCode,
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        ConnectInfo, FromRequestParts, Path as RoutePath, Query, Request, State,
+    },
+    http::{header, request::Parts, HeaderMap, StatusCode, Uri},
+    middleware::{self, Next},
     response::{
         sse::{Event, KeepAlive, Sse},
-        IntoResponse,
+        Html, IntoResponse, Response,
     },
-    routing::{get, post},
-    Json, Router,
+    routing::{delete, get, post},
+    Extension, Json, Router,
 };
+use axum_server::tls_rustls::RustlsConfig;
 use clap::Parser;
 use futures_core::Stream;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
 use swiftscope::{
-    backend,
-    debug_session::{self, DebugSession, DebugSessionError},
+    artifacts::{ArtifactId, ArtifactStore},
+    backend::{self, ChildPage, StepScope},
+    breakpoint_file,
+    debug_session::{
+        self, Advance, DebugSession, DebugSessionError, ErrorCode, Language, PathMatch,
+    },
+    fault::{FaultInjector, FaultPlan},
+    gdb_remote::Interrupter,
+    mock::MockFixture,
+    protocol_trace::ProtocolTrace,
+    redaction::Redactor,
+    response_format::{self, ResponseFormat},
+    truncation::{self, Limits},
+    value_format::{self, IntegerFormat, ValueFormat},
+    verbosity::{self, Verbosity},
 };
-use serde::Deserialize;
-use serde_json::{json, Value};
 use tokio::{
-    io::{AsyncBufReadExt, AsyncRead, BufReader},
+    io::{AsyncBufReadExt, AsyncRead, AsyncWriteExt, BufReader},
     net::{TcpListener, TcpStream},
     process::Command as TokioCommand,
-    sync::{broadcast, mpsc, Mutex},
-    task::JoinHandle,
+    sync::{broadcast, mpsc, watch, Mutex, Notify},
+    task::{AbortHandle, JoinHandle},
     time::sleep,
 };
 
@@ -44,17 +65,37 @@ use tokio::{
 #[command(about = "Start the iOS LLM Debug API server")]
 struct Args {
     /// Port where debugserver is listening.
-    #[arg(long)]
-    debugserver_port: u16,
+    #[arg(long, required_unless_present = "mock")]
+    debugserver_port: Option<u16>,
+    /// Play a fixture of threads, frames, locals and stops back instead of
+    /// debugging a device (see docs/claude_tool.md); `--program` still
+    /// provides the symbols.
+    #[arg(long, value_name = "FIXTURE", conflicts_with_all = ["manage_bridge", "prewarm"])]
+    mock: Option<PathBuf>,
     /// Optional Mach-O path to use for symbolication (falls back to IOS_LLDB_DAP_CONFIG env).
     #[arg(long)]
     program: Option<PathBuf>,
+    /// dSYM bundle (or the DWARF file inside one) holding the debug information
+    /// of a stripped --program; must match its UUID. Without it, a dSYM next
+    /// to the binary or its .app, or one Spotlight knows, is used.
+    #[arg(long, value_name = "PATH", requires = "program")]
+    dsym: Option<PathBuf>,
+    /// Slice of a universal (fat) --program to debug, e.g. arm64 or arm64e.
+    /// Without it arm64e is picked, else arm64, else the first slice.
+    #[arg(long, value_name = "ARCH", requires = "program")]
+    arch: Option<String>,
     /// Host interface for the HTTP server.
     #[arg(long, default_value = "127.0.0.1")]
     host: String,
     /// HTTP port for the LLM API.
     #[arg(long, default_value_t = 4000)]
     port: u16,
+    /// PEM certificate chain; with `--tls-key`, serve HTTPS instead of HTTP.
+    #[arg(long, requires = "tls_key")]
+    tls_cert: Option<PathBuf>,
+    /// PEM private key for `--tls-cert`.
+    #[arg(long, requires = "tls_cert")]
+    tls_key: Option<PathBuf>,
     /// Optional device identifier for restart/log commands.
     #[arg(long)]
     device: Option<String>,
@@ -79,6 +120,19 @@ struct Args {
     /// Optional .app bundle to install when launching.
     #[arg(long)]
     app_bundle: Option<PathBuf>,
+    /// Have the managed bridge attach to this running pid instead of launching.
+    #[arg(long, conflicts_with = "attach_name")]
+    attach_pid: Option<i64>,
+    /// Have the managed bridge attach to the running process with this name.
+    #[arg(long)]
+    attach_name: Option<String>,
+    /// Argument passed to the app when the managed bridge launches it (repeat flag).
+    #[arg(long = "launch-args", allow_hyphen_values = true)]
+    launch_args: Vec<String>,
+    /// Environment variable for the app the managed bridge launches, as
+    /// `KEY=VALUE` (repeat flag).
+    #[arg(long = "launch-env")]
+    launch_env: Vec<String>,
     /// Enable devicectl log streaming.
     #[arg(long)]
     enable_log_stream: bool,
@@ -91,6 +145,97 @@ struct Args {
     /// Build command to run when the LLM requests a rebuild (repeat flag).
     #[arg(long = "build-cmd")]
     build_cmd: Vec<String>,
+    /// Include a one-line summary of the top frame's variables in every stop event.
+    #[arg(long)]
+    stop_summaries: bool,
+    /// Start serving immediately and bring up the bridge (app launched suspended)
+    /// and the debugserver connection in the background; commands wait for it.
+    #[arg(long)]
+    prewarm: bool,
+    /// Remap a build-machine source prefix to a local one, as `FROM=TO` (repeat flag).
+    #[arg(long = "source-map")]
+    source_map: Vec<String>,
+    /// Local checkout for relative breakpoint paths (default: the working
+    /// directory). Source maps are derived from it when it holds an
+    /// `.xcodeproj`, `.xcworkspace` or `Package.swift`.
+    #[arg(long)]
+    project_root: Option<PathBuf>,
+    /// Require `Authorization: Bearer TOKEN` and attribute requests to TENANT,
+    /// as `TENANT=TOKEN`, or a bare `TOKEN` for the `default` tenant (repeat flag).
+    #[arg(long = "api-token")]
+    api_token: Vec<String>,
+    /// File with one `--api-token` entry per line, keeping tokens out of the process list.
+    #[arg(long)]
+    api_tokens_file: Option<PathBuf>,
+    /// Serve `/health` without a token, for load balancers and readiness probes.
+    #[arg(long)]
+    public_health: bool,
+    /// Commands a minute each client (its tenant, or its address without
+    /// tokens) may send, in bursts of up to that many. Unlimited when unset.
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    rate_limit: Option<u32>,
+    /// Commands that may wait for the session at once; more are refused
+    /// with 429 instead of piling up.
+    #[arg(long, default_value_t = 32)]
+    max_queued: usize,
+    /// Developer mode: serve `/admin/faults` to inject debugserver disconnects,
+    /// slow packets and devicectl failures.
+    #[arg(long)]
+    fault_inject: bool,
+    /// Speak the Model Context Protocol over stdin/stdout instead of serving
+    /// HTTP: every action is a tool and target events are notifications.
+    #[arg(long)]
+    mcp: bool,
+    /// Append every gdb-remote packet, with timestamp and direction, to this file.
+    #[arg(long)]
+    protocol_trace: Option<PathBuf>,
+    /// Also append every command `/history` records to this file, one JSON
+    /// object per line.
+    #[arg(long)]
+    history_file: Option<PathBuf>,
+    /// Seconds between `[status]` lines on `/logs` while the target runs (0 disables).
+    #[arg(long, default_value_t = 10)]
+    status_interval: u64,
+    /// Milliseconds an `evaluate` may take before it is abandoned, unless the
+    /// request sets `timeout_ms`.
+    #[arg(long, default_value_t = debug_session::DEFAULT_EVAL_TIMEOUT.as_millis() as u64)]
+    eval_timeout_ms: u64,
+    /// Keep artifacts (build logs, reports, ...) here instead of a temporary
+    /// directory that is removed on exit.
+    #[arg(long)]
+    artifacts_dir: Option<PathBuf>,
+    /// How breakpoint file arguments match DWARF paths: exact, suffix or basename.
+    #[arg(long, default_value = "suffix")]
+    path_match: PathMatch,
+    /// Threads that run while `next`/`step_in` step the selected one: thread
+    /// (only it; the others stay stopped) or all (every unsuspended thread).
+    #[arg(long, default_value = "thread")]
+    step_scope: StepScope,
+    /// Detail level of inspection payloads: minimal, normal or full.
+    #[arg(long, default_value = "normal")]
+    verbosity: Verbosity,
+    /// Cut strings in responses longer than this many bytes, ending them in
+    /// `…(+N more bytes)`; requests may set their own `max_string_len`.
+    #[arg(long)]
+    max_string_len: Option<usize>,
+    /// Keep at most this many items of each array in responses.
+    #[arg(long)]
+    max_children: Option<usize>,
+    /// Keep at most this many frames of each stack trace in responses.
+    #[arg(long)]
+    max_frames: Option<usize>,
+    /// Redact values of variables and keys whose name contains this, case
+    /// insensitively (repeat flag; adds to the built-in list).
+    #[arg(long = "redact-field")]
+    redact_field: Vec<String>,
+    /// Redact matches of this regular expression in responses and logs
+    /// (repeat flag; adds to the built-in patterns).
+    #[arg(long = "redact-pattern")]
+    redact_pattern: Vec<String>,
+    /// Drop the built-in redaction rules, keeping only --redact-field and
+    /// --redact-pattern.
+    #[arg(long)]
+    no_default_redaction: bool,
 }
 
 #[derive(Clone, Debug)]
@@ -98,7 +243,14 @@ struct Config {
     host: String,
     port: u16,
     debugserver_port: u16,
+    /// Fixture played back instead of a device.
+    mock: Option<PathBuf>,
     program: PathBuf,
+    dsym: Option<PathBuf>,
+    /// Slice of a universal program asked for with `--arch`.
+    arch: Option<String>,
+    /// Slice of a universal program that was loaded.
+    slice: Option<String>,
     device: Option<String>,
     bundle_id: Option<String>,
     require_dwarf: bool,
@@ -107,19 +259,59 @@ struct Config {
     ios_llm_devicectl_args: Vec<String>,
     state_file: Option<PathBuf>,
     app_bundle: Option<PathBuf>,
+    attach: Option<AttachTarget>,
+    launch: LaunchOptions,
     enable_log_stream: bool,
     devicectl_bin: String,
     devicectl_subcommand: String,
     build_command: Option<Vec<String>>,
+    stop_summaries: bool,
+    prewarm: bool,
+    fault_inject: bool,
+    protocol_trace: Option<PathBuf>,
+    history_file: Option<PathBuf>,
+    artifacts_dir: Option<PathBuf>,
+    status_interval: Option<Duration>,
+    eval_timeout: Duration,
+    source_maps: Vec<(String, String)>,
+    path_match: PathMatch,
+    step_scope: StepScope,
+    project_root: Option<PathBuf>,
+    verbosity: Verbosity,
+    /// Response size limits for requests that set none.
+    limits: Limits,
+    redact_fields: Vec<String>,
+    redact_patterns: Vec<String>,
+    default_redaction: bool,
+    /// Token to tenant name; empty leaves the API open.
+    api_tokens: HashMap<String, String>,
+    public_health: bool,
+    rate_limit: Option<u32>,
+    max_queued: usize,
 }
 
 impl Config {
-    fn from_args(args: &Args, program: PathBuf) -> Self {
-        Self {
+    fn from_args(args: &Args, program: PathBuf) -> anyhow::Result<Self> {
+        let mut token_lines = args.api_token.clone();
+        if let Some(path) = &args.api_tokens_file {
+            let text = std::fs::read_to_string(path)
+                .with_context(|| format!("failed to read {}", path.display()))?;
+            token_lines.extend(
+                text.lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                    .map(str::to_string),
+            );
+        }
+        Ok(Self {
             host: args.host.clone(),
             port: args.port,
-            debugserver_port: args.debugserver_port,
+            debugserver_port: args.debugserver_port.unwrap_or_default(),
+            mock: args.mock.clone(),
             program,
+            dsym: args.dsym.clone(),
+            arch: args.arch.clone(),
+            slice: None,
             device: args.device.clone(),
             bundle_id: args.bundle_id.clone(),
             require_dwarf: args.require_dwarf,
@@ -128,6 +320,15 @@ impl Config {
             ios_llm_devicectl_args: args.ios_llm_devicectl_arg.clone(),
             state_file: args.state_file.clone(),
             app_bundle: args.app_bundle.clone(),
+            attach: match (args.attach_pid, &args.attach_name) {
+                (Some(pid), _) => Some(AttachTarget::Pid(pid)),
+                (None, Some(name)) => Some(AttachTarget::Name(name.clone())),
+                (None, None) => None,
+            },
+            launch: LaunchOptions {
+                args: args.launch_args.clone(),
+                env: parse_launch_env(&args.launch_env)?,
+            },
             enable_log_stream: args.enable_log_stream,
             devicectl_bin: args.devicectl_bin.clone(),
             devicectl_subcommand: args.devicectl_subcommand.clone(),
@@ -136,594 +337,7119 @@ impl Config {
             } else {
                 Some(args.build_cmd.clone())
             },
+            stop_summaries: args.stop_summaries,
+            prewarm: args.prewarm,
+            fault_inject: args.fault_inject,
+            protocol_trace: args.protocol_trace.clone(),
+            history_file: args.history_file.clone(),
+            artifacts_dir: args.artifacts_dir.clone(),
+            status_interval: (args.status_interval > 0)
+                .then(|| Duration::from_secs(args.status_interval)),
+            eval_timeout: Duration::from_millis(args.eval_timeout_ms),
+            source_maps: args
+                .source_map
+                .iter()
+                .filter_map(|mapping| mapping.split_once('='))
+                .map(|(from, to)| (from.to_string(), to.to_string()))
+                .collect(),
+            path_match: args.path_match,
+            step_scope: args.step_scope,
+            project_root: args
+                .project_root
+                .clone()
+                .or_else(|| std::env::current_dir().ok()),
+            verbosity: args.verbosity,
+            limits: Limits {
+                max_string_len: args.max_string_len,
+                max_children: args.max_children,
+                max_frames: args.max_frames,
+            },
+            redact_fields: args.redact_field.clone(),
+            redact_patterns: args.redact_pattern.clone(),
+            default_redaction: !args.no_default_redaction,
+            api_tokens: parse_api_tokens(&token_lines)?,
+            public_health: args.public_health,
+            rate_limit: args.rate_limit,
+            max_queued: args.max_queued,
+        })
+    }
+
+    fn redactor(&self) -> Result<Redactor, String> {
+        Redactor::new(
+            &self.redact_fields,
+            &self.redact_patterns,
+            self.default_redaction,
+        )
+    }
+
+    /// Checks prerequisites that would otherwise fail one at a time inside
+    /// handlers, reporting all of them at once.
+    fn validate(&self) -> anyhow::Result<()> {
+        let problems = self.problems();
+        if problems.is_empty() {
+            return Ok(());
+        }
+        bail!(
+            "invalid configuration:\n{}",
+            problems
+                .iter()
+                .map(|problem| format!("  - {problem}"))
+                .collect::<Vec<_>>()
+                .join("\n")
+        )
+    }
+
+    fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if self.port == self.debugserver_port {
+            problems.push(format!(
+                "--port {} is also the --debugserver-port; pick a different HTTP port",
+                self.port
+            ));
+        }
+        if self.manage_bridge {
+            if self.device.is_none() {
+                problems.push("--device is required with --manage-bridge".into());
+            }
+            if self.bundle_id.is_none() && self.attach.is_none() {
+                problems.push(
+                    "--bundle-id (or --attach-pid/--attach-name) is required with --manage-bridge"
+                        .into(),
+                );
+            }
+            if !executable_exists(&self.ios_llm_devicectl) {
+                problems.push(format!(
+                    "--ios-llm-devicectl `{}` was not found on PATH",
+                    self.ios_llm_devicectl
+                ));
+            }
+        }
+        if let Some(bundle) = &self.app_bundle {
+            if !bundle.is_dir() {
+                problems.push(format!(
+                    "--app-bundle {} is not an existing .app directory",
+                    bundle.display()
+                ));
+            }
+        }
+        if self.enable_log_stream {
+            if self.device.is_none() {
+                problems.push("--device is required with --enable-log-stream".into());
+            }
+            if !executable_exists(&self.devicectl_bin) {
+                problems.push(format!(
+                    "--devicectl-bin `{}` was not found on PATH",
+                    self.devicectl_bin
+                ));
+            }
+        }
+        if let Some(program) = self.build_command.as_ref().and_then(|cmd| cmd.first()) {
+            if !executable_exists(program) {
+                problems.push(format!(
+                    "build command `{program}` (--build-cmd) was not found on PATH"
+                ));
+            }
+        }
+        if let Some(root) = &self.project_root {
+            if !root.is_dir() {
+                problems.push(format!(
+                    "--project-root {} is not a directory",
+                    root.display()
+                ));
+            }
+        }
+        if let Some(dir) = &self.artifacts_dir {
+            if dir.exists() && !dir.is_dir() {
+                problems.push(format!(
+                    "--artifacts-dir {} is not a directory",
+                    dir.display()
+                ));
+            }
+        }
+        if let Err(err) = self.redactor() {
+            problems.push(format!("--redact-pattern: {err}"));
+        }
+        for (flag, file) in [
+            ("--state-file", &self.state_file),
+            ("--protocol-trace", &self.protocol_trace),
+            ("--history-file", &self.history_file),
+        ] {
+            let parent = file
+                .as_deref()
+                .and_then(Path::parent)
+                .filter(|parent| !parent.as_os_str().is_empty());
+            if let Some(parent) = parent.filter(|parent| !parent.is_dir()) {
+                problems.push(format!(
+                    "{flag} directory {} does not exist",
+                    parent.display()
+                ));
+            }
         }
+        problems
     }
+}
 
-    fn program(&self) -> &Path {
-        &self.program
+/// Whether `program` names an existing file, directly when it contains a
+/// path separator and through `PATH` otherwise.
+fn executable_exists(program: &str) -> bool {
+    if program.contains(std::path::MAIN_SEPARATOR) || program.contains('/') {
+        return Path::new(program).is_file();
     }
+    std::env::var_os("PATH")
+        .is_some_and(|paths| std::env::split_paths(&paths).any(|dir| dir.join(program).is_file()))
 }
 
 #[derive(Clone)]
 struct AppState {
+    /// [`DEFAULT_SESSION`] or the id `POST /sessions` gave it.
+    session_id: String,
     session: Arc<Mutex<DebugSession>>,
     config: Config,
     bridge: Option<Arc<Mutex<BridgeController>>>,
     log_tx: broadcast::Sender<String>,
+    /// The tail of `log_tx`, for `context`.
+    recent_logs: Arc<RecentLogs>,
+    /// Target events for `/events`, from the session's event sink.
+    event_tx: broadcast::Sender<Value>,
     build_runner: Option<Arc<BuildRunner>>,
+    /// Tenant that claimed the session; other tenants cannot see it.
+    owner: Arc<Mutex<Option<String>>>,
+    /// Present with `--fault-inject`.
+    faults: Option<Arc<FaultInjector>>,
+    /// Present with `--protocol-trace`.
+    trace: Option<Arc<ProtocolTrace>>,
+    artifacts: Arc<ArtifactStore>,
+    running: Arc<RunTracker>,
+    timeline: Arc<Timeline>,
+    /// Every stop of the target, for `/timeline`.
+    stops: Arc<StopTimeline>,
+    /// Every command of every session, for `/history`.
+    history: Arc<CommandHistory>,
+    queue: Arc<CommandQueue>,
+    /// Applied to every response body and `/logs` line.
+    redactor: Arc<Redactor>,
+    jobs: Arc<JobTable>,
+    replays: Arc<ReplayCache>,
+    /// When the server started, for `/status` uptime.
+    started: Instant,
+    /// Present with `--rate-limit`.
+    rate_limiter: Option<Arc<RateLimiter>>,
+    sessions: Arc<SessionRegistry>,
+    /// Background work of this session, stopped when it is removed.
+    tasks: Arc<Vec<AbortHandle>>,
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(tag = "action")]
-enum LlmCommand {
-    #[serde(rename = "stacktrace")]
-    Stacktrace,
-    #[serde(rename = "threads")]
-    Threads,
-    #[serde(rename = "continue")]
-    Continue,
-    #[serde(rename = "next")]
-    Next,
-    #[serde(rename = "step_in")]
-    StepIn,
-    #[serde(rename = "set_breakpoint")]
-    SetBreakpoint { file: String, line: u32 },
-    #[serde(rename = "locals")]
-    Locals,
-    #[serde(rename = "scopes")]
-    Scopes,
-    #[serde(rename = "variables")]
-    Variables {
-        #[serde(rename = "variablesReference", default)]
-        reference: Option<i64>,
-    },
-    #[serde(rename = "evaluate")]
-    Evaluate { expression: String },
-    #[serde(rename = "evaluate_swift")]
-    EvaluateSwift { expression: String },
-    #[serde(rename = "watch_expr")]
-    WatchExpression { expression: String },
-    #[serde(rename = "disconnect")]
-    Disconnect,
-    #[serde(rename = "restart")]
-    Restart,
-    #[serde(rename = "launch")]
-    Launch,
-    #[serde(rename = "build")]
-    Build,
-    #[serde(rename = "select_thread")]
-    SelectThread { thread_id: i64 },
+/// Per-client token buckets for `--rate-limit`: a client may send a minute's
+/// allowance at once, then one command every `60 / per_minute` seconds.
+#[derive(Debug)]
+struct RateLimiter {
+    per_minute: u32,
+    /// Client to tokens left and when they were last topped up.
+    clients: std::sync::Mutex<HashMap<String, (f64, Instant)>>,
 }
 
-#[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    let _ = env_logger::builder().format_timestamp(None).try_init();
-    let args = Args::parse();
-    let listen_host = args.host.clone();
-    let listen_port = args.port;
-    let backend = if let Some(program) = args.program.as_deref() {
-        debug_session::backend_from_program(program)?
-    } else {
-        debug_session::init_backend()?
-    };
-
-    let mut session = DebugSession::new(backend);
-    let config = Config::from_args(&args, session.program_path().to_path_buf());
-    let (log_tx, _log_rx) = broadcast::channel(1024);
-    let bridge = if config.manage_bridge {
-        Some(Arc::new(Mutex::new(BridgeController::new(
-            &config,
-            log_tx.clone(),
-        )?)))
-    } else {
-        None
-    };
-
-    if let Some(controller) = &bridge {
-        controller.lock().await.ensure_running().await?;
+impl RateLimiter {
+    fn new(per_minute: u32) -> Self {
+        Self {
+            per_minute,
+            clients: Default::default(),
+        }
     }
 
-    if config.enable_log_stream {
-        if let Err(err) = spawn_log_stream(&config, log_tx.clone()).await {
-            log::warn!("log streaming failed to start: {err}");
+    /// Spends one of `client`'s tokens, or says how long until it has one.
+    fn check(&self, client: &str, now: Instant) -> Result<(), Duration> {
+        let capacity = f64::from(self.per_minute);
+        let per_second = capacity / 60.0;
+        let mut clients = self.clients.lock().unwrap_or_else(|err| err.into_inner());
+        let (tokens, topped_up) = clients.entry(client.to_string()).or_insert((capacity, now));
+        let elapsed = now.saturating_duration_since(*topped_up).as_secs_f64();
+        *tokens = (*tokens + elapsed * per_second).min(capacity);
+        *topped_up = now;
+        if *tokens >= 1.0 {
+            *tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - *tokens) / per_second))
         }
     }
-
-    session.connect_debugserver(config.debugserver_port)?;
-    warn_if_missing_dwarf(&config)?;
-
-    let build_runner = config
-        .build_command
-        .as_ref()
-        .map(|cmd| Arc::new(BuildRunner::new(cmd.clone())));
-
-    let state = AppState {
-        session: Arc::new(Mutex::new(session)),
-        config: config.clone(),
-        bridge,
-        log_tx: log_tx.clone(),
-        build_runner,
-    };
-    let app = Router::new()
-        .route("/command", post(handle_command))
-        .route("/health", get(health_check))
-        .route("/logs", get(stream_logs))
-        .with_state(state);
-
-    let addr: SocketAddr = format!("{}:{}", listen_host, listen_port).parse()?;
-    let listener = TcpListener::bind(addr).await?;
-    println!(
-        "LLM Debug API listening on http://{}",
-        listener.local_addr()?
-    );
-    axum::serve(listener, app.into_make_service()).await?;
-    Ok(())
 }
 
-async fn handle_command(
-    State(state): State<AppState>,
-    Json(command): Json<LlmCommand>,
-) -> (StatusCode, Json<serde_json::Value>) {
-    let response = match command {
-        LlmCommand::Restart => handle_launch_request(&state, true)
-            .await
-            .map_err(|err| DebugSessionError::Backend(err.to_string())),
-        LlmCommand::Launch => handle_launch_request(&state, false)
-            .await
-            .map_err(|err| DebugSessionError::Backend(err.to_string())),
-        LlmCommand::Build => handle_build_request(&state)
-            .await
-            .map_err(|err| DebugSessionError::Backend(err.to_string())),
-        other => {
-            let mut session = state.session.lock().await;
-            execute_command(&mut session, other)
-        }
-    };
+/// A command turned away by [`admit`].
+#[derive(Debug)]
+struct Throttled {
+    code: &'static str,
+    message: String,
+    retry_after: Duration,
+}
 
-    match response {
-        Ok(body) => (StatusCode::OK, Json(body)),
-        Err(err) => (
-            StatusCode::BAD_REQUEST,
-            Json(json!({ "ok": false, "error": err.to_string() })),
-        ),
+impl Throttled {
+    fn body(&self) -> Value {
+        let Json(mut body) = api_error(self.code, &self.message);
+        body["error"]["retry_after_ms"] = json!(self.retry_after.as_millis() as u64);
+        body
     }
 }
 
-async fn health_check(State(state): State<AppState>) -> Json<serde_json::Value> {
-    Json(json!({
-        "ok": true,
-        "program": state.config.program.display().to_string(),
-        "debugserverPort": state.config.debugserver_port,
-        "device": state.config.device,
-        "bundleId": state.config.bundle_id,
-        "host": state.config.host.clone(),
-        "port": state.config.port
-    }))
+impl IntoResponse for Throttled {
+    fn into_response(self) -> Response {
+        let seconds = self.retry_after.as_secs_f64().ceil().max(1.0) as u64;
+        (
+            StatusCode::TOO_MANY_REQUESTS,
+            [(header::RETRY_AFTER, seconds.to_string())],
+            Json(self.body()),
+        )
+            .into_response()
+    }
 }
 
-fn warn_if_missing_dwarf(config: &Config) -> anyhow::Result<()> {
-    if backend::binary_has_dwarf_line_info(config.program()) {
+/// Applies `--max-queued` and `--rate-limit` before `command` is queued.
+/// `cancel` is never refused: it is how a client unwedges the queue.
+fn admit(state: &AppState, client: &str, command: &LlmCommand) -> Result<(), Throttled> {
+    if let LlmCommand::Cancel = command {
         return Ok(());
     }
-    log::warn!(
-        "DWARF line info missing for {}. Breakpoints may fail; rebuild the app with a Debug configuration or pass -g.",
-        config.program().display()
-    );
-    if config.require_dwarf {
-        bail!(
-            "DWARF data missing for {}; refusing to start because --require-dwarf was set",
-            config.program().display()
-        );
+    let waiting = state.queue.waiting();
+    if waiting >= state.config.max_queued {
+        return Err(Throttled {
+            code: "QUEUE_FULL",
+            message: format!("{waiting} commands are already waiting for the session"),
+            retry_after: Duration::from_secs(1),
+        });
+    }
+    if let Some(limiter) = &state.rate_limiter {
+        limiter
+            .check(client, Instant::now())
+            .map_err(|retry_after| Throttled {
+                code: "RATE_LIMITED",
+                message: format!("more than {} commands a minute", limiter.per_minute),
+                retry_after,
+            })?;
     }
     Ok(())
 }
 
-async fn handle_launch_request(state: &AppState, restart: bool) -> anyhow::Result<Value> {
-    let bridge = state
-        .bridge
-        .as_ref()
-        .ok_or_else(|| anyhow::anyhow!("restart/launch requires --manage-bridge"))?;
-    let mut controller = bridge.lock().await;
-    if restart {
-        controller.restart().await?;
-    } else {
-        controller.ensure_running().await?;
+/// Who [`RateLimiter`] counts a request against.
+fn client_key(tenant: Option<&Tenant>, peer: SocketAddr) -> String {
+    match tenant {
+        Some(Tenant(name)) => name.clone(),
+        None => peer.ip().to_string(),
     }
-    drop(controller);
-    let mut session = state.session.lock().await;
-    session
-        .connect_debugserver(state.config.debugserver_port)
-        .map_err(|err: DebugSessionError| anyhow::anyhow!(err))?;
-    Ok(json!({ "ok": true }))
 }
 
-async fn handle_build_request(state: &AppState) -> anyhow::Result<Value> {
-    let runner = state
-        .build_runner
-        .as_ref()
-        .ok_or_else(|| anyhow::anyhow!("build command not configured"))?;
-    let output = runner.run().await?;
-    Ok(json!({
-        "ok": output.success,
-        "exitCode": output.exit_code,
-        "stdout": output.stdout,
-        "stderr": output.stderr
-    }))
-}
+/// `/logs` lines `RecentLogs` keeps.
+const RECENT_LOG_CAPACITY: usize = 500;
 
-async fn stream_logs(State(state): State<AppState>) -> impl IntoResponse {
-    let mut receiver = state.log_tx.subscribe();
-    let (tx, rx) = mpsc::unbounded_channel();
-    tokio::spawn(async move {
-        while let Ok(line) = receiver.recv().await {
-            if tx.send(line).is_err() {
-                break;
+/// The last [`RECENT_LOG_CAPACITY`] `/logs` lines, without `[status]` ones.
+#[derive(Debug, Default)]
+struct RecentLogs(std::sync::Mutex<VecDeque<String>>);
+
+impl RecentLogs {
+    fn push(&self, line: String) {
+        if let Ok(mut lines) = self.0.lock() {
+            if lines.len() == RECENT_LOG_CAPACITY {
+                lines.pop_front();
             }
+            lines.push_back(line);
         }
-    });
-    Sse::new(LogSseStream { receiver: rx })
-        .keep_alive(KeepAlive::new().interval(Duration::from_secs(5)))
+    }
+
+    /// The newest `count` lines, oldest first.
+    fn last(&self, count: usize) -> Vec<String> {
+        let Ok(lines) = self.0.lock() else {
+            return Vec::new();
+        };
+        lines
+            .iter()
+            .skip(lines.len().saturating_sub(count))
+            .cloned()
+            .collect()
+    }
 }
 
-async fn spawn_log_stream(
-    config: &Config,
-    log_tx: broadcast::Sender<String>,
-) -> anyhow::Result<()> {
-    let device = config
-        .device
-        .clone()
-        .ok_or_else(|| anyhow::anyhow!("--device required for log streaming"))?;
-    let bin = config.devicectl_bin.clone();
-    let subcommand = config.devicectl_subcommand.clone();
+/// Copies every line sent to `log_tx` into `recent`.
+fn spawn_log_recorder(
+    log_tx: &broadcast::Sender<String>,
+    recent: Arc<RecentLogs>,
+) -> JoinHandle<()> {
+    let mut lines = log_tx.subscribe();
     tokio::spawn(async move {
-        if let Err(err) = run_log_stream(bin, subcommand, device, log_tx.clone()).await {
-            let _ = log_tx.send(format!("log stream exited: {err}"));
+        loop {
+            match lines.recv().await {
+                Ok(line) if !line.starts_with(STATUS_TAG) => recent.push(line),
+                Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
         }
-    });
-    Ok(())
+    })
 }
 
-async fn run_log_stream(
-    bin: String,
-    subcommand: String,
-    device: String,
-    log_tx: broadcast::Sender<String>,
-) -> anyhow::Result<()> {
-    let mut cmd = TokioCommand::new(&bin);
-    if !subcommand.is_empty() {
-        cmd.arg(&subcommand);
-    }
-    cmd.args(["device", "log", "stream", "--device", &device]);
-    cmd.stdout(Stdio::piped());
-    cmd.stderr(Stdio::piped());
-    cmd.kill_on_drop(true);
-    let mut child = cmd
-        .spawn()
-        .with_context(|| format!("failed to spawn {bin} {subcommand} log stream"))?;
-    if let Some(stdout) = child.stdout.take() {
-        spawn_log_task(stdout, log_tx.clone(), "log");
-    }
-    if let Some(stderr) = child.stderr.take() {
-        spawn_log_task(stderr, log_tx.clone(), "log-err");
-    }
-    let status = child.wait().await?;
-    let _ = log_tx.send(format!("log stream status: {status}"));
-    Ok(())
+/// The resuming command in flight, if any, for `[status]` lines, and the
+/// `request_id` of whichever command holds the session, for its events.
+#[derive(Debug, Default)]
+struct RunTracker {
+    current: std::sync::Mutex<Option<(&'static str, Instant)>>,
+    request_id: std::sync::Mutex<Option<String>>,
+    /// Stops the target for `cancel` and `/jobs/{id}/cancel`, with the job
+    /// the command runs as, if any.
+    interrupter: std::sync::Mutex<Option<(Option<u64>, Interrupter)>>,
 }
 
-fn spawn_log_task<R>(
-    reader: R,
-    log_tx: broadcast::Sender<String>,
-    tag: &'static str,
-) -> JoinHandle<()>
-where
-    R: AsyncRead + Send + Unpin + 'static,
-{
-    tokio::spawn(async move {
-        let mut lines = BufReader::new(reader).lines();
-        while let Ok(Some(line)) = lines.next_line().await {
-            let _ = log_tx.send(format!("[{tag}] {line}"));
+impl RunTracker {
+    fn start(&self, action: &'static str) {
+        if let Ok(mut running) = self.current.lock() {
+            *running = Some((action, Instant::now()));
         }
-    })
-}
+    }
 
-struct BridgeController {
-    bin: String,
-    args: Vec<String>,
-    port: u16,
-    log_tx: broadcast::Sender<String>,
-    handle: Option<BridgeChild>,
-}
-
-struct BridgeChild {
-    child: tokio::process::Child,
-    tasks: Vec<JoinHandle<()>>,
-}
+    /// Lets [`interrupt`](Self::interrupt) stop the target until the command
+    /// finishes.
+    fn arm(&self, job: Option<u64>, interrupter: Interrupter) {
+        if let Ok(mut armed) = self.interrupter.lock() {
+            *armed = Some((job, interrupter));
+        }
+    }
 
-impl BridgeController {
-    fn new(config: &Config, log_tx: broadcast::Sender<String>) -> anyhow::Result<Self> {
-        let device = config
-            .device
-            .clone()
-            .ok_or_else(|| anyhow::anyhow!("--device is required when managing the bridge"))?;
-        let bundle = config
-            .bundle_id
-            .clone()
-            .ok_or_else(|| anyhow::anyhow!("--bundle-id is required when managing the bridge"))?;
-        let mut args = config.ios_llm_devicectl_args.clone();
-        args.push("--device".into());
-        args.push(device);
-        args.push("--bundle-id".into());
-        args.push(bundle);
-        args.push("--listen-port".into());
-        args.push(config.debugserver_port.to_string());
-        if let Some(app_bundle) = &config.app_bundle {
-            args.push("--install-app".into());
-            args.push(app_bundle.display().to_string());
+    fn finish(&self) {
+        if let Ok(mut running) = self.current.lock() {
+            *running = None;
         }
-        if let Some(state) = &config.state_file {
-            args.push("--state-file".into());
-            args.push(state.display().to_string());
+        if let Ok(mut armed) = self.interrupter.lock() {
+            *armed = None;
         }
-        Ok(Self {
-            bin: config.ios_llm_devicectl.clone(),
-            args,
-            port: config.debugserver_port,
-            log_tx,
-            handle: None,
-        })
     }
 
-    async fn ensure_running(&mut self) -> anyhow::Result<()> {
-        if self.handle.is_none() {
-            self.spawn_child().await?;
+    fn current(&self) -> Option<(&'static str, Instant)> {
+        self.current.lock().ok().and_then(|running| *running)
+    }
+
+    fn set_request_id(&self, request_id: Option<String>) {
+        if let Ok(mut current) = self.request_id.lock() {
+            *current = request_id;
         }
-        Ok(())
     }
 
-    async fn restart(&mut self) -> anyhow::Result<()> {
-        self.stop_current().await?;
-        self.spawn_child().await
+    fn request_id(&self) -> Option<String> {
+        self.request_id
+            .lock()
+            .ok()
+            .and_then(|current| current.clone())
     }
 
-    async fn spawn_child(&mut self) -> anyhow::Result<()> {
-        let mut cmd = TokioCommand::new(&self.bin);
-        cmd.args(&self.args);
-        cmd.stdout(Stdio::piped());
-        cmd.stderr(Stdio::piped());
-        cmd.kill_on_drop(true);
-        let mut child = cmd
-            .spawn()
-            .context("failed to spawn ios-llm-devicectl bridge")?;
-        let mut tasks = Vec::new();
-        if let Some(stdout) = child.stdout.take() {
-            tasks.push(spawn_log_task(stdout, self.log_tx.clone(), "bridge"));
-        }
-        if let Some(stderr) = child.stderr.take() {
-            tasks.push(spawn_log_task(stderr, self.log_tx.clone(), "bridge-err"));
+    /// Sends `^C` if `job` is the resuming command in flight.
+    fn interrupt(&self, job: u64) -> bool {
+        let Ok(armed) = self.interrupter.lock() else {
+            return false;
+        };
+        match armed.as_ref() {
+            Some((armed_job, interrupter)) if *armed_job == Some(job) => {
+                interrupter.interrupt().is_ok()
+            }
+            _ => false,
         }
-        wait_for_port(self.port).await?;
-        self.handle = Some(BridgeChild { child, tasks });
-        Ok(())
     }
 
-    async fn stop_current(&mut self) -> anyhow::Result<()> {
-        if let Some(mut handle) = self.handle.take() {
-            if handle.child.id().is_some() {
-                let _ = handle.child.start_kill();
-                let _ = handle.child.wait().await;
-            }
-            for task in handle.tasks {
-                task.abort();
-            }
+    /// Sends `^C` to whatever resuming command is in flight, returning its
+    /// action and job. The job is marked cancelled first so it ends that way
+    /// however quickly the command returns.
+    fn interrupt_current(&self, jobs: &JobTable) -> Option<(&'static str, Option<u64>)> {
+        let (action, _) = self.current()?;
+        let armed = self.interrupter.lock().ok()?;
+        let (job, interrupter) = armed.as_ref()?;
+        if let Some(job) = *job {
+            jobs.request_cancel(job);
         }
-        Ok(())
+        interrupter.interrupt().ok()?;
+        Some((action, *job))
     }
 }
 
-async fn wait_for_port(port: u16) -> anyhow::Result<()> {
-    let mut attempts = 0;
-    loop {
-        match TcpStream::connect(("127.0.0.1", port)).await {
-            Ok(stream) => {
-                drop(stream);
-                return Ok(());
-            }
-            Err(err) => {
-                attempts += 1;
-                if attempts > 50 {
-                    return Err(anyhow::anyhow!(
-                        "timed out waiting for bridge on port {port}: {err}"
-                    ));
-                }
-                sleep(Duration::from_millis(100)).await;
-            }
+/// `request_id`s [`ReplayCache`] remembers per session.
+const REPLAY_CAPACITY: usize = 256;
+
+type Reply = Option<(StatusCode, Value)>;
+
+/// Replies of side-effectful commands by `request_id`, so that a retried
+/// request gets the first one's reply, waiting for it if need be, instead
+/// of launching or writing twice. Each reply is kept with a hash of the
+/// command that produced it.
+#[derive(Debug, Default)]
+struct ReplayCache(std::sync::Mutex<VecDeque<(String, u64, watch::Receiver<Reply>)>>);
+
+impl ReplayCache {
+    /// The reply to wait for, with the sender to complete it when this is
+    /// the first request with `request_id`; `None` when `request_id` was
+    /// first used for a command other than `command`.
+    fn claim(
+        &self,
+        request_id: &str,
+        command: &LlmCommand,
+    ) -> Option<(Option<watch::Sender<Reply>>, watch::Receiver<Reply>)> {
+        let mut hasher = DefaultHasher::new();
+        serde_json::to_string(command)
+            .unwrap_or_default()
+            .hash(&mut hasher);
+        let fingerprint = hasher.finish();
+        let mut replies = self.0.lock().unwrap_or_else(|err| err.into_inner());
+        if let Some((_, first, reply)) = replies.iter().find(|(id, _, _)| id == request_id) {
+            return (*first == fingerprint).then(|| (None, reply.clone()));
+        }
+        let (sender, reply) = watch::channel(None);
+        if replies.len() == REPLAY_CAPACITY {
+            replies.pop_front();
         }
+        replies.push_back((request_id.to_string(), fingerprint, reply.clone()));
+        Some((Some(sender), reply))
     }
 }
 
-struct BuildRunner {
-    command: Vec<String>,
+/// Finished jobs kept for `/jobs/{id}`; running ones are never dropped.
+const JOB_CAPACITY: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum JobStatus {
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
 }
 
-impl BuildRunner {
-    fn new(command: Vec<String>) -> Self {
-        Self { command }
+#[derive(Debug)]
+struct Job {
+    id: u64,
+    action: &'static str,
+    started: Instant,
+    elapsed: Option<Duration>,
+    status: JobStatus,
+    cancel_requested: bool,
+    /// HTTP status and body the command would have answered with.
+    response: Option<(StatusCode, Value)>,
+    abort: Option<AbortHandle>,
+}
+
+impl Job {
+    fn view(&self) -> Value {
+        let mut view = json!({
+            "id": self.id,
+            "action": self.action,
+            "status": self.status,
+            "elapsed_ms": self.elapsed.unwrap_or_else(|| self.started.elapsed()).as_millis() as u64,
+        });
+        if let Some((status, body)) = &self.response {
+            view["http_status"] = json!(status.as_u16());
+            view["result"] = body.clone();
+        }
+        view
     }
+}
 
-    async fn run(&self) -> anyhow::Result<BuildResult> {
-        let mut parts = self.command.iter();
-        let program = parts
-            .next()
-            .ok_or_else(|| anyhow::anyhow!("build command requires at least one argument"))?;
-        let mut cmd = TokioCommand::new(program);
-        for arg in parts {
-            cmd.arg(arg);
+/// Commands sent with `?async=true`, which answer with a job id at once and
+/// run in the background; see `/jobs/{id}`.
+#[derive(Debug, Default)]
+struct JobTable(std::sync::Mutex<(u64, VecDeque<Job>)>);
+
+impl JobTable {
+    fn create(&self, action: &'static str) -> u64 {
+        let mut guard = self.0.lock().unwrap_or_else(|err| err.into_inner());
+        let (next_id, jobs) = &mut *guard;
+        *next_id += 1;
+        while jobs.len() >= JOB_CAPACITY {
+            match jobs.iter().position(|job| job.status != JobStatus::Running) {
+                Some(index) => jobs.remove(index),
+                None => break,
+            };
         }
-        cmd.stdout(Stdio::piped());
-        cmd.stderr(Stdio::piped());
-        let output = cmd
-            .spawn()
-            .context("failed to spawn build command")?
-            .wait_with_output()
-            .await
-            .context("failed to run build command")?;
-        Ok(BuildResult {
-            success: output.status.success(),
-            exit_code: output.status.code().unwrap_or_default(),
-            stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
-            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        jobs.push_back(Job {
+            id: *next_id,
+            action,
+            started: Instant::now(),
+            elapsed: None,
+            status: JobStatus::Running,
+            cancel_requested: false,
+            response: None,
+            abort: None,
+        });
+        *next_id
+    }
+
+    fn with_job<T>(&self, id: u64, f: impl FnOnce(&mut Job) -> T) -> Option<T> {
+        let mut guard = self.0.lock().unwrap_or_else(|err| err.into_inner());
+        guard.1.iter_mut().find(|job| job.id == id).map(f)
+    }
+
+    fn set_abort(&self, id: u64, abort: AbortHandle) {
+        self.with_job(id, |job| {
+            if job.status == JobStatus::Running {
+                job.abort = Some(abort);
+            }
+        });
+    }
+
+    /// Records the command's response; a job whose cancellation was
+    /// requested ends `cancelled` even when the command completed.
+    fn finish(&self, id: u64, status: StatusCode, body: Value) {
+        self.with_job(id, |job| {
+            if job.status != JobStatus::Running {
+                return;
+            }
+            job.status = if job.cancel_requested {
+                JobStatus::Cancelled
+            } else if status.is_success() && body["ok"] != json!(false) {
+                JobStatus::Succeeded
+            } else {
+                JobStatus::Failed
+            };
+            job.elapsed = Some(job.started.elapsed());
+            job.response = Some((status, body));
+            job.abort = None;
+        });
+    }
+
+    fn view(&self, id: u64) -> Option<Value> {
+        self.with_job(id, |job| job.view())
+    }
+
+    /// Marks `id` as being cancelled and hands back what is needed to stop
+    /// it, or the status it already finished with.
+    fn request_cancel(&self, id: u64) -> Option<Result<Option<AbortHandle>, JobStatus>> {
+        self.with_job(id, |job| {
+            if job.status != JobStatus::Running {
+                return Err(job.status);
+            }
+            job.cancel_requested = true;
+            Ok(job.abort.clone())
         })
     }
+
+    /// Ends a job whose task was aborted before it could respond.
+    fn cancelled(&self, id: u64) {
+        self.with_job(id, |job| {
+            if job.status == JobStatus::Running {
+                job.status = JobStatus::Cancelled;
+                job.elapsed = Some(job.started.elapsed());
+                job.abort = None;
+            }
+        });
+    }
 }
 
-struct BuildResult {
-    success: bool,
-    exit_code: i32,
-    stdout: String,
-    stderr: String,
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Priority {
+    #[default]
+    Normal,
+    /// Runs before every queued normal command; inspection commands only.
+    High,
 }
 
-struct LogSseStream {
-    receiver: mpsc::UnboundedReceiver<String>,
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum QueueState {
+    Queued,
+    Running,
 }
 
-impl Stream for LogSseStream {
-    type Item = Result<Event, std::convert::Infallible>;
+#[derive(Debug)]
+struct QueuedCommand {
+    id: u64,
+    action: &'static str,
+    priority: Priority,
+    state: QueueState,
+    /// When it was queued, or started running.
+    since: Instant,
+}
 
-    fn poll_next(mut self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<Option<Self::Item>> {
-        match Pin::new(&mut self.receiver).poll_recv(cx) {
-            Poll::Ready(Some(line)) => Poll::Ready(Some(Ok(Event::default().data(line)))),
-            Poll::Ready(None) => Poll::Ready(None),
-            Poll::Pending => Poll::Pending,
-        }
-    }
+/// `/command` requests waiting for, or holding, the session. One runs at a
+/// time; among the waiting ones high priority goes first, then arrival order.
+#[derive(Debug, Default)]
+struct CommandQueue {
+    commands: std::sync::Mutex<(u64, Vec<QueuedCommand>)>,
+    changed: Notify,
 }
 
-fn execute_command(
-    session: &mut DebugSession,
-    command: LlmCommand,
-) -> Result<Value, DebugSessionError> {
-    match command {
-        LlmCommand::Stacktrace => Ok(json!({ "ok": true, "stacktrace": session.stacktrace() })),
-        LlmCommand::Threads => Ok(json!({ "ok": true, "threads": session.threads() })),
-        LlmCommand::Continue => session.continue_execution().map(|stop| {
-            if let Some(stop) = stop {
-                json!({ "ok": true, "stop": stop })
-            } else {
-                json!({ "ok": true })
+/// A place in the [`CommandQueue`], given up on drop, including when the
+/// client goes away while it is still waiting.
+struct QueueTicket {
+    queue: Arc<CommandQueue>,
+    id: u64,
+}
+
+impl CommandQueue {
+    /// Waits until it is `action`'s turn.
+    async fn enter(self: &Arc<Self>, action: &'static str, priority: Priority) -> QueueTicket {
+        let id = {
+            let mut guard = self.commands.lock().unwrap_or_else(|err| err.into_inner());
+            let (next_id, commands) = &mut *guard;
+            *next_id += 1;
+            commands.push(QueuedCommand {
+                id: *next_id,
+                action,
+                priority,
+                state: QueueState::Queued,
+                since: Instant::now(),
+            });
+            *next_id
+        };
+        let ticket = QueueTicket {
+            queue: self.clone(),
+            id,
+        };
+        loop {
+            // Created before the check, so a change in between still wakes it.
+            let changed = self.changed.notified();
+            if self.try_start(id) {
+                return ticket;
             }
-        }),
-        LlmCommand::Next => session.next().map(|stop| match stop {
-            Some(stop) => json!({ "ok": true, "stop": stop }),
-            None => json!({ "ok": true }),
-        }),
-        LlmCommand::StepIn => session.step_in().map(|stop| match stop {
-            Some(stop) => json!({ "ok": true, "stop": stop }),
-            None => json!({ "ok": true }),
-        }),
-        LlmCommand::SetBreakpoint { file, line } => session
-            .set_breakpoint(&file, line)
-            .map(|bp| json!({ "ok": true, "breakpoint_id": bp.id })),
-        LlmCommand::Locals => Ok(json!({ "ok": true, "locals": session.locals() })),
-        LlmCommand::Scopes => Ok(json!({ "ok": true, "scopes": session.scopes() })),
-        LlmCommand::Variables { reference } => {
-            let reference = reference.unwrap_or(1);
-            Ok(json!({ "ok": true, "variables": session.variables_for_reference(reference) }))
-        }
-        LlmCommand::Evaluate { expression } => session
-            .evaluate(&expression)
-            .map(|result| json!({ "ok": true, "result": result.result, "type": result.ty })),
-        LlmCommand::EvaluateSwift { expression } => session
-            .evaluate_swift(&expression)
-            .map(|result| json!({ "ok": true, "result": result.result, "type": result.ty })),
-        LlmCommand::WatchExpression { expression } => session
-            .add_watch_expression(&expression)
-            .map(|values| json!({ "ok": true, "watch": values })),
-        LlmCommand::SelectThread { thread_id } => {
-            session.select_thread(thread_id);
-            Ok(json!({ "ok": true, "threadId": thread_id }))
+            changed.await;
         }
-        LlmCommand::Disconnect => {
-            session.disconnect()?;
-            Ok(json!({ "ok": true }))
+    }
+
+    fn try_start(&self, id: u64) -> bool {
+        let mut guard = self.commands.lock().unwrap_or_else(|err| err.into_inner());
+        let commands = &mut guard.1;
+        if commands
+            .iter()
+            .any(|command| command.state == QueueState::Running)
+        {
+            return false;
         }
-        LlmCommand::Restart | LlmCommand::Launch | LlmCommand::Build => {
-            unreachable!("managed by handle_command")
+        let next = commands
+            .iter_mut()
+            .filter(|command| command.state == QueueState::Queued)
+            .min_by_key(|command| (std::cmp::Reverse(command.priority), command.id));
+        match next {
+            Some(command) if command.id == id => {
+                command.state = QueueState::Running;
+                command.since = Instant::now();
+                true
+            }
+            _ => false,
         }
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
 
-    fn sample_session() -> DebugSession {
-        let exe = std::env::current_exe().expect("current exe");
-        let backend = debug_session::backend_from_program(&exe).expect("backend");
-        DebugSession::new(backend)
+    /// How many commands are waiting for their turn.
+    fn waiting(&self) -> usize {
+        let guard = self.commands.lock().unwrap_or_else(|err| err.into_inner());
+        guard
+            .1
+            .iter()
+            .filter(|command| command.state == QueueState::Queued)
+            .count()
     }
 
-    #[test]
-    fn stacktrace_returns_frames() {
-        let mut session = sample_session();
-        let value = execute_command(&mut session, LlmCommand::Stacktrace).unwrap();
-        assert!(value.get("stacktrace").is_some());
+    /// The running command, then the queued ones in the order they will run.
+    fn snapshot(&self) -> Vec<Value> {
+        let guard = self.commands.lock().unwrap_or_else(|err| err.into_inner());
+        let mut commands: Vec<&QueuedCommand> = guard.1.iter().collect();
+        commands.sort_by_key(|command| {
+            (
+                command.state == QueueState::Queued,
+                std::cmp::Reverse(command.priority),
+                command.id,
+            )
+        });
+        commands
+            .into_iter()
+            .map(|command| {
+                json!({
+                    "id": command.id,
+                    "action": command.action,
+                    "priority": command.priority,
+                    "state": command.state,
+                    "since_ms": command.since.elapsed().as_millis() as u64,
+                })
+            })
+            .collect()
     }
+}
 
-    #[test]
-    fn variables_defaults_to_locals_reference() {
-        let mut session = sample_session();
-        let value =
-            execute_command(&mut session, LlmCommand::Variables { reference: None }).unwrap();
-        assert!(value.get("variables").is_some());
+impl Drop for QueueTicket {
+    fn drop(&mut self) {
+        let mut guard = self
+            .queue
+            .commands
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        guard.1.retain(|command| command.id != self.id);
+        drop(guard);
+        self.queue.changed.notify_waiters();
     }
+}
 
-    #[test]
-    fn evaluate_rejects_empty_input() {
-        let mut session = sample_session();
-        let err = execute_command(
-            &mut session,
-            LlmCommand::Evaluate {
-                expression: "".into(),
-            },
-        )
-        .unwrap_err();
-        assert!(err.to_string().contains("not supported"));
-    }
+/// `/command` requests kept for the dashboard.
+const TIMELINE_CAPACITY: usize = 200;
 
-    #[test]
-    fn threads_command_returns_payload() {
-        let mut session = sample_session();
-        let value = execute_command(&mut session, LlmCommand::Threads).unwrap();
-        assert!(value.get("threads").is_some());
+#[derive(Debug, Clone, Serialize)]
+struct TimelineEntry {
+    at_ms: u64,
+    action: &'static str,
+    ok: bool,
+    elapsed_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    /// Description of the stop the command ended in.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop: Option<String>,
+}
+
+/// Recent `/command` requests, oldest first.
+#[derive(Debug, Default)]
+struct Timeline(std::sync::Mutex<VecDeque<TimelineEntry>>);
+
+impl Timeline {
+    fn record(
+        &self,
+        action: &'static str,
+        started: Instant,
+        response: &Result<Value, DebugSessionError>,
+    ) {
+        let entry = TimelineEntry {
+            at_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_millis() as u64)
+                .unwrap_or_default(),
+            action,
+            ok: response.is_ok(),
+            elapsed_ms: started.elapsed().as_millis() as u64,
+            error: response.as_ref().err().map(ToString::to_string),
+            stop: response
+                .as_ref()
+                .ok()
+                .and_then(|body| body["stop"]["description"].as_str())
+                .map(str::to_string),
+        };
+        if let Ok(mut entries) = self.0.lock() {
+            if entries.len() == TIMELINE_CAPACITY {
+                entries.pop_front();
+            }
+            entries.push_back(entry);
+        }
     }
 
-    #[test]
-    fn locals_command_returns_payload() {
-        let mut session = sample_session();
-        let value = execute_command(&mut session, LlmCommand::Locals).unwrap();
-        assert!(value.get("locals").is_some());
+    fn entries(&self) -> Vec<TimelineEntry> {
+        self.0
+            .lock()
+            .map(|entries| entries.iter().cloned().collect())
+            .unwrap_or_default()
     }
+}
 
-    #[test]
-    fn scopes_command_returns_payload() {
-        let mut session = sample_session();
-        let value = execute_command(&mut session, LlmCommand::Scopes).unwrap();
-        assert!(value.get("scopes").is_some());
+/// Stops `/timeline` keeps; older ones are dropped, but `index` keeps counting.
+const STOP_TIMELINE_CAPACITY: usize = 1000;
+
+/// A `stopped` event as `/timeline` lists it.
+#[derive(Debug, Clone, Serialize)]
+struct TimelineStop {
+    /// Counts the session's stops from 1.
+    index: u64,
+    at_ms: u64,
+    /// The resuming command that ended in this stop.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    action: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request_id: Option<String>,
+    /// The event's `reason`, `description`, `thread_id`, top frame, locals
+    /// `summary`, ...
+    #[serde(flatten)]
+    stop: serde_json::Map<String, Value>,
+}
+
+/// Every stop of one session, oldest first.
+#[derive(Debug, Default)]
+struct StopTimeline(std::sync::Mutex<(u64, VecDeque<TimelineStop>)>);
+
+impl StopTimeline {
+    /// Records `event` when it is a `stopped` event.
+    fn record(&self, event: &Value, action: Option<&'static str>, request_id: Option<String>) {
+        if event["event"] != "stopped" {
+            return;
+        }
+        let Some(mut stop) = event.as_object().cloned() else {
+            return;
+        };
+        stop.remove("event");
+        stop.remove("request_id");
+        let Ok(mut guard) = self.0.lock() else {
+            return;
+        };
+        let (count, stops) = &mut *guard;
+        *count += 1;
+        if stops.len() == STOP_TIMELINE_CAPACITY {
+            stops.pop_front();
+        }
+        stops.push_back(TimelineStop {
+            index: *count,
+            at_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_millis() as u64)
+                .unwrap_or_default(),
+            action,
+            request_id,
+            stop,
+        });
     }
 
-    #[test]
-    fn disconnect_command_succeeds_even_without_remote() {
-        let mut session = sample_session();
-        let value = execute_command(&mut session, LlmCommand::Disconnect).unwrap();
-        assert_eq!(value.get("ok").and_then(Value::as_bool), Some(true));
+    /// The stops after `since`, or all that are kept.
+    fn since(&self, since: Option<u64>) -> Vec<TimelineStop> {
+        let Ok(guard) = self.0.lock() else {
+            return Vec::new();
+        };
+        guard
+            .1
+            .iter()
+            .filter(|stop| since.is_none_or(|since| stop.index > since))
+            .cloned()
+            .collect()
     }
+}
 
-    #[test]
+/// Commands `/history` keeps in memory.
+const HISTORY_CAPACITY: usize = 1000;
+
+#[derive(Debug, Clone, Serialize)]
+struct HistoryEntry {
+    id: u64,
+    at_ms: u64,
+    session_id: String,
+    /// Tenant that owned the session when the command ran.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tenant: Option<String>,
+    action: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request_id: Option<String>,
+    /// The command's fields, redacted like responses.
+    params: Value,
+    status: u16,
+    ok: bool,
+    elapsed_ms: u64,
+    /// The response body.
+    result: Value,
+}
+
+/// Audit log of the commands every session ran, for reviewing what an agent
+/// did to the target. The newest [`HISTORY_CAPACITY`] stay in memory; with
+/// `--history-file` all of them are also appended there as JSON lines.
+#[derive(Debug, Default)]
+struct CommandHistory {
+    entries: std::sync::Mutex<(u64, VecDeque<HistoryEntry>)>,
+    file: Option<std::sync::Mutex<std::fs::File>>,
+}
+
+impl CommandHistory {
+    fn create(path: Option<&Path>) -> std::io::Result<Self> {
+        let file = match path {
+            Some(path) => Some(std::sync::Mutex::new(
+                std::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)?,
+            )),
+            None => None,
+        };
+        Ok(Self {
+            file,
+            ..Self::default()
+        })
+    }
+
+    /// Stores `entry` under the next id.
+    fn record(&self, mut entry: HistoryEntry) {
+        let Ok(mut guard) = self.entries.lock() else {
+            return;
+        };
+        guard.0 += 1;
+        entry.id = guard.0;
+        if let Some(file) = &self.file {
+            if let Ok(mut file) = file.lock() {
+                let line = format!("{}\n", json!(entry));
+                if let Err(err) = std::io::Write::write_all(&mut *file, line.as_bytes()) {
+                    log::warn!("failed to append to the history file: {err}");
+                }
+            }
+        }
+        if guard.1.len() == HISTORY_CAPACITY {
+            guard.1.pop_front();
+        }
+        guard.1.push_back(entry);
+    }
+
+    /// The newest `query.limit` entries matching `query`, oldest first.
+    fn query(&self, query: &HistoryQuery, tenant: Option<&str>) -> Vec<HistoryEntry> {
+        let Ok(guard) = self.entries.lock() else {
+            return Vec::new();
+        };
+        let mut entries: Vec<_> = guard
+            .1
+            .iter()
+            .rev()
+            .filter(|entry| tenant.is_none_or(|tenant| entry.tenant.as_deref() == Some(tenant)))
+            .filter(|entry| query.since.is_none_or(|since| entry.id > since))
+            .filter(|entry| {
+                query
+                    .session
+                    .as_ref()
+                    .is_none_or(|session| &entry.session_id == session)
+            })
+            .filter(|entry| {
+                query
+                    .action
+                    .as_ref()
+                    .is_none_or(|action| entry.action == action)
+            })
+            .filter(|entry| query.ok.is_none_or(|ok| entry.ok == ok))
+            .take(query.limit)
+            .cloned()
+            .collect();
+        entries.reverse();
+        entries
+    }
+}
+
+/// Adds a finished command to `state.history`.
+async fn record_history(
+    state: &AppState,
+    action: &'static str,
+    mut params: Value,
+    request_id: Option<String>,
+    started: Instant,
+    status: StatusCode,
+    result: &Value,
+) {
+    if let Some(fields) = params.as_object_mut() {
+        fields.remove("action");
+    }
+    state.redactor.apply(&mut params);
+    state.history.record(HistoryEntry {
+        id: 0,
+        at_ms: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis() as u64)
+            .unwrap_or_default(),
+        session_id: state.session_id.clone(),
+        tenant: state.owner.lock().await.clone(),
+        action,
+        request_id,
+        params,
+        status: status.as_u16(),
+        ok: status.is_success(),
+        elapsed_ms: started.elapsed().as_millis() as u64,
+        result: result.clone(),
+    });
+}
+
+const STATUS_TAG: &str = "[status] ";
+
+/// Sends a `status` event to `/events`, and a `[status]` line to `/logs`,
+/// every `interval` while a resuming command is in flight, so a long silent
+/// `continue` can be told apart from a wedged server.
+fn spawn_status_events(
+    log_tx: broadcast::Sender<String>,
+    event_tx: broadcast::Sender<Value>,
+    running: Arc<RunTracker>,
+    interval: Duration,
+) -> JoinHandle<()> {
+    let mut lines = log_tx.subscribe();
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        let mut last_log: Option<Instant> = None;
+        loop {
+            tokio::select! {
+                line = lines.recv() => match line {
+                    Ok(line) if !line.starts_with(STATUS_TAG) => last_log = Some(Instant::now()),
+                    Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+                _ = ticker.tick() => {
+                    let Some((action, since)) = running.current() else {
+                        continue;
+                    };
+                    let status = json!({
+                        "state": "running",
+                        "action": action,
+                        "running_ms": since.elapsed().as_millis() as u64,
+                        "last_log_ms_ago": last_log.map(|at| at.elapsed().as_millis() as u64),
+                    });
+                    let _ = log_tx.send(format!("{STATUS_TAG}{status}"));
+                    let mut event = status;
+                    event["event"] = json!("status");
+                    if let Some(request_id) = running.request_id() {
+                        event["request_id"] = json!(request_id);
+                    }
+                    let _ = event_tx.send(event);
+                }
+            }
+        }
+    })
+}
+
+/// Tenant name attached to a request by [`authorize`].
+#[derive(Clone, Debug)]
+struct Tenant(String);
+
+/// Parses `--launch-env KEY=VALUE` entries into the launched app's environment.
+fn parse_launch_env(entries: &[String]) -> anyhow::Result<BTreeMap<String, String>> {
+    entries
+        .iter()
+        .map(|entry| match entry.split_once('=') {
+            Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+            _ => bail!("launch environment entries must look like KEY=VALUE, got `{entry}`"),
+        })
+        .collect()
+}
+
+/// Tenant that bare `--api-token TOKEN` entries authenticate as.
+const DEFAULT_TENANT: &str = "default";
+
+/// Maps each token to its tenant. An entry is `TENANT=TOKEN`, split on the
+/// first `=`, or a bare `TOKEN` for [`DEFAULT_TENANT`]. Tenants are made of
+/// ASCII letters, digits, `-`, `_` and `.`; entries whose token half is empty
+/// or starts with `=` (such as a padded `abc==`) are refused rather than
+/// guessed at, so write them as `default=abc==`.
+fn parse_api_tokens(entries: &[String]) -> anyhow::Result<HashMap<String, String>> {
+    let mut tokens = HashMap::new();
+    for entry in entries {
+        let (tenant, token) = match entry.split_once('=') {
+            Some((tenant, token)) => (tenant.trim(), token.trim()),
+            None => (DEFAULT_TENANT, entry.trim()),
+        };
+        if token.is_empty() || token.starts_with('=') {
+            bail!("ambiguous API token entry; write it as TENANT=TOKEN (`{DEFAULT_TENANT}=` for the default tenant)");
+        }
+        if tenant.is_empty()
+            || !tenant
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        {
+            bail!("API token tenant `{tenant}` must be letters, digits, `-`, `_` or `.`");
+        }
+        if tokens
+            .insert(token.to_string(), tenant.to_string())
+            .is_some()
+        {
+            bail!("API token for `{tenant}` is shared with another tenant");
+        }
+    }
+    Ok(tokens)
+}
+
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+        .map(str::trim)
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenQuery {
+    token: Option<String>,
+}
+
+/// `?token=` for the dashboard, whose page loads and `EventSource` cannot
+/// send headers, and for browser WebSockets, which cannot either. The
+/// dashboard percent-encodes it, so it is decoded here.
+fn query_token(uri: &Uri) -> Option<String> {
+    Query::<TokenQuery>::try_from_uri(uri).ok()?.0.token
+}
+
+/// Whether `path` is served without a token even when tokens are configured.
+fn is_public_route(path: &str, public_health: bool) -> bool {
+    public_health && path == "/health"
+}
+
+/// Rejects requests without a known token when tokens are configured.
+async fn authorize(State(state): State<AppState>, mut request: Request, next: Next) -> Response {
+    if state.config.api_tokens.is_empty()
+        || is_public_route(request.uri().path(), state.config.public_health)
+    {
+        return next.run(request).await;
+    }
+    let tenant = bearer_token(request.headers())
+        .map(str::to_string)
+        .or_else(|| query_token(request.uri()))
+        .and_then(|token| state.config.api_tokens.get(&token))
+        .cloned();
+    match tenant {
+        Some(tenant) => {
+            request.extensions_mut().insert(Tenant(tenant));
+            next.run(request).await
+        }
+        None => (
+            StatusCode::UNAUTHORIZED,
+            api_error("UNAUTHORIZED", "missing or invalid API token"),
+        )
+            .into_response(),
+    }
+}
+
+/// The first tenant to use the session claims it until it disconnects.
+fn claim_session(owner: &mut Option<String>, tenant: &str) -> bool {
+    match owner {
+        Some(current) => current == tenant,
+        None => {
+            *owner = Some(tenant.to_string());
+            true
+        }
+    }
+}
+
+/// Same 404 for every tenant that does not own the session, so tenants
+/// cannot tell whether someone else is debugging.
+async fn check_session_access(
+    state: &AppState,
+    tenant: Option<&Tenant>,
+) -> Result<(), (StatusCode, Json<Value>)> {
+    let Some(Tenant(tenant)) = tenant else {
+        return Ok(());
+    };
+    if claim_session(&mut *state.owner.lock().await, tenant) {
+        Ok(())
+    } else {
+        Err((
+            StatusCode::NOT_FOUND,
+            api_error("SESSION_NOT_FOUND", "no debug session for this token"),
+        ))
+    }
+}
+
+/// Output of `export_breakpoints`.
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum BreakpointFormat {
+    #[default]
+    Json,
+    Lldb,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(tag = "action")]
+enum LlmCommand {
+    #[serde(rename = "stacktrace")]
+    Stacktrace,
+    #[serde(rename = "threads")]
+    Threads,
+    #[serde(rename = "all_stacktraces", alias = "stacktrace_all")]
+    AllStacktraces {
+        #[serde(default)]
+        max_frames: Option<usize>,
+        /// Per-thread overrides of `max_frames`, keyed by thread id.
+        #[serde(default)]
+        thread_max_frames: HashMap<String, usize>,
+    },
+    #[serde(rename = "source")]
+    Source {
+        #[serde(default)]
+        file: Option<String>,
+        #[serde(default)]
+        line: Option<u32>,
+        #[serde(default)]
+        context: Option<usize>,
+    },
+    #[serde(rename = "detect_deadlock")]
+    DetectDeadlock,
+    #[serde(rename = "stack_diff")]
+    StackDiff,
+    #[serde(rename = "continue")]
+    Continue,
+    #[serde(rename = "next")]
+    Next,
+    #[serde(rename = "step_in")]
+    StepIn,
+    #[serde(rename = "step_out")]
+    StepOut,
+    #[serde(rename = "run_to_line")]
+    RunToLine { file: String, line: u32 },
+    #[serde(rename = "continue_until_changed")]
+    ContinueUntilChanged {
+        expression: String,
+        /// `continue` (default) or `next` between evaluations.
+        #[serde(default)]
+        mode: Advance,
+        #[serde(default)]
+        max_iterations: Option<usize>,
+    },
+    #[serde(rename = "signal")]
+    Signal {
+        /// `SIGUSR1`, `USR1` or the Darwin number.
+        #[serde(deserialize_with = "deserialize_scalar_text")]
+        signal: String,
+        /// Thread to deliver to; defaults to the selected thread.
+        #[serde(default)]
+        thread_id: Option<i64>,
+    },
+    #[serde(rename = "set_breakpoint")]
+    SetBreakpoint {
+        file: String,
+        line: u32,
+        /// `exact`, `suffix` or `basename`; defaults to `--path-match`.
+        #[serde(default, rename = "match")]
+        path_match: Option<PathMatch>,
+        /// Delete the breakpoint after its first hit.
+        #[serde(default)]
+        temporary: bool,
+    },
+    #[serde(rename = "set_logpoint")]
+    SetLogpoint {
+        file: String,
+        line: u32,
+        /// Template such as `"count is {count}"`; `{{`/`}}` are literal braces.
+        message: String,
+    },
+    #[serde(rename = "export_breakpoints")]
+    ExportBreakpoints {
+        /// `json` (default) or `lldb` for `breakpoint read`.
+        #[serde(default)]
+        format: Option<BreakpointFormat>,
+    },
+    #[serde(rename = "import_breakpoints")]
+    ImportBreakpoints {
+        /// Breakpoint list on the server: native JSON, LLDB JSON or `.xcbkptlist`.
+        #[serde(default)]
+        path: Option<PathBuf>,
+        /// The same, inline.
+        #[serde(default)]
+        contents: Option<String>,
+    },
+    #[serde(rename = "locals")]
+    Locals {
+        #[serde(default)]
+        format: Option<ValueFormat>,
+    },
+    #[serde(rename = "scopes")]
+    Scopes,
+    #[serde(rename = "variables")]
+    Variables {
+        #[serde(rename = "variablesReference", default)]
+        reference: Option<i64>,
+        /// First child returned, for paging through large collections.
+        #[serde(default)]
+        start: Option<usize>,
+        /// Children returned from `start`; absent or 0 returns the rest.
+        #[serde(default)]
+        count: Option<usize>,
+        #[serde(default)]
+        format: Option<ValueFormat>,
+    },
+    #[serde(rename = "dump_variable")]
+    DumpVariable {
+        /// Local of the selected frame, optionally with `.member`/`[index]` steps.
+        #[serde(default)]
+        name: Option<String>,
+        #[serde(rename = "variablesReference", default)]
+        reference: Option<i64>,
+        #[serde(default)]
+        depth: Option<usize>,
+        #[serde(default)]
+        format: Option<ValueFormat>,
+    },
+    #[serde(rename = "snapshot")]
+    Snapshot {
+        /// Defaults to `snapshot-<n>`; an older snapshot of the same name is replaced.
+        #[serde(default)]
+        name: Option<String>,
+        #[serde(default)]
+        depth: Option<usize>,
+        #[serde(default)]
+        memory: Vec<MemoryRange>,
+    },
+    #[serde(rename = "diff_snapshot")]
+    DiffSnapshot {
+        from: String,
+        /// Defaults to the target as it is now.
+        #[serde(default)]
+        to: Option<String>,
+    },
+    #[serde(rename = "evaluate")]
+    Evaluate {
+        expression: String,
+        #[serde(default)]
+        language: Option<Language>,
+        #[serde(default)]
+        format: Option<ValueFormat>,
+        /// Overrides `--eval-timeout-ms` for this evaluation.
+        #[serde(default)]
+        timeout_ms: Option<u64>,
+        /// Lets `function(args)` run code in the target.
+        #[serde(default)]
+        allow_side_effects: bool,
+    },
+    #[serde(rename = "evaluate_swift")]
+    EvaluateSwift {
+        expression: String,
+        #[serde(default)]
+        format: Option<ValueFormat>,
+        #[serde(default)]
+        timeout_ms: Option<u64>,
+        #[serde(default)]
+        allow_side_effects: bool,
+    },
+    #[serde(rename = "set_variable")]
+    SetVariable {
+        name: String,
+        #[serde(deserialize_with = "deserialize_scalar_text")]
+        value: String,
+    },
+    #[serde(rename = "watch_expr")]
+    WatchExpression {
+        expression: String,
+        #[serde(default)]
+        format: Option<ValueFormat>,
+    },
+    #[serde(rename = "disconnect")]
+    Disconnect,
+    #[serde(rename = "detach")]
+    Detach,
+    #[serde(rename = "restart")]
+    Restart,
+    #[serde(rename = "launch")]
+    Launch {
+        /// Replaces `--launch-args` for this launch.
+        #[serde(default)]
+        args: Option<Vec<String>>,
+        /// Replaces `--launch-env` for this launch.
+        #[serde(default)]
+        env: Option<BTreeMap<String, String>>,
+    },
+    #[serde(rename = "build")]
+    Build,
+    #[serde(rename = "prewarm")]
+    Prewarm,
+    /// The stop, top frames with source, locals, watches and recent logs
+    /// in one response.
+    #[serde(rename = "context")]
+    Context {
+        #[serde(default)]
+        frames: Option<usize>,
+        /// Source lines either side of each frame's line.
+        #[serde(default)]
+        context: Option<usize>,
+        #[serde(default)]
+        log_lines: Option<usize>,
+    },
+    #[serde(rename = "attach")]
+    Attach {
+        #[serde(default)]
+        pid: Option<i64>,
+        #[serde(default)]
+        name: Option<String>,
+    },
+    #[serde(rename = "terminate")]
+    Terminate {
+        /// Also ask devicectl to terminate the app by the pid the bridge recorded.
+        #[serde(default)]
+        devicectl: bool,
+    },
+    /// Interrupts the resuming command in flight; never queued.
+    #[serde(rename = "cancel")]
+    Cancel,
+    #[serde(rename = "select_thread")]
+    SelectThread { thread_id: i64 },
+    #[serde(rename = "suspend_thread")]
+    SuspendThread { thread_id: i64 },
+    #[serde(rename = "resume_thread")]
+    ResumeThread { thread_id: i64 },
+    #[serde(rename = "select_frame")]
+    SelectFrame { frame_index: usize },
+    #[serde(rename = "read_memory")]
+    ReadMemory {
+        #[serde(deserialize_with = "deserialize_address")]
+        address: u64,
+        length: usize,
+    },
+    #[serde(rename = "write_memory")]
+    WriteMemory {
+        #[serde(deserialize_with = "deserialize_address")]
+        address: u64,
+        data: String,
+        #[serde(default = "default_byte_encoding")]
+        encoding: String,
+        #[serde(default)]
+        confirm: bool,
+    },
+    #[serde(rename = "write_stdin")]
+    WriteStdin {
+        data: String,
+        /// `text` (default), `hex` or `base64`.
+        #[serde(default = "default_stdin_encoding")]
+        encoding: String,
+    },
+    #[serde(rename = "disassemble")]
+    Disassemble {
+        #[serde(default, deserialize_with = "deserialize_optional_address")]
+        address: Option<u64>,
+        #[serde(default)]
+        symbol: Option<String>,
+        #[serde(default)]
+        count: Option<usize>,
+    },
+    #[serde(rename = "modules")]
+    Modules,
+    #[serde(rename = "memory_regions")]
+    MemoryRegions {
+        /// Only the region containing this address.
+        #[serde(default, deserialize_with = "deserialize_optional_address")]
+        address: Option<u64>,
+    },
+    #[serde(rename = "heap_info")]
+    HeapInfo {
+        #[serde(deserialize_with = "deserialize_address")]
+        address: u64,
+    },
+    /// Objective-C classes realized outside the shared cache.
+    #[serde(rename = "objc_classes")]
+    ObjcClasses {
+        /// Only classes whose name contains this.
+        #[serde(default)]
+        filter: Option<String>,
+    },
+    /// Ivars, properties and methods of a class, or of an object's dynamic
+    /// class.
+    #[serde(rename = "objc_class")]
+    ObjcClass {
+        #[serde(default)]
+        name: Option<String>,
+        #[serde(default, deserialize_with = "deserialize_optional_address")]
+        address: Option<u64>,
+    },
+    /// Stop when the Main Thread Checker reports a UI API called off the
+    /// main thread.
+    #[serde(rename = "main_thread_checker")]
+    MainThreadChecker {
+        #[serde(default = "default_true")]
+        enabled: bool,
+    },
+    /// Log calls to a function without stopping; `enabled: false` stops.
+    #[serde(rename = "trace_function")]
+    TraceFunction {
+        name: String,
+        #[serde(default = "default_true")]
+        enabled: bool,
+    },
+    /// Executor, state, holder and queue of Swift actors.
+    #[serde(rename = "actors")]
+    Actors {
+        #[serde(deserialize_with = "deserialize_addresses")]
+        addresses: Vec<u64>,
+    },
+    /// Swift concurrency tasks and their async backtraces.
+    #[serde(rename = "tasks")]
+    Tasks {
+        /// Task handles to include besides the running tasks.
+        #[serde(default, deserialize_with = "deserialize_addresses")]
+        addresses: Vec<u64>,
+    },
+    /// LLDB's `po`: runs `debugDescription` on the object in the target.
+    #[serde(rename = "describe")]
+    Describe {
+        #[serde(default, deserialize_with = "deserialize_optional_address")]
+        address: Option<u64>,
+        /// Evaluated to the object pointer when no `address` is given.
+        #[serde(default)]
+        expression: Option<String>,
+        #[serde(default)]
+        timeout_ms: Option<u64>,
+    },
+    #[serde(rename = "symbolicate")]
+    Symbolicate {
+        #[serde(deserialize_with = "deserialize_addresses")]
+        addresses: Vec<u64>,
+    },
+    #[serde(rename = "lookup_symbol")]
+    LookupSymbol { name: String },
+    #[serde(rename = "registers")]
+    Registers,
+    #[serde(rename = "set_register")]
+    SetRegister {
+        name: String,
+        #[serde(deserialize_with = "deserialize_address")]
+        value: u64,
+    },
+    #[serde(rename = "set_verbosity")]
+    SetVerbosity { level: Verbosity },
+    /// Session default for the `format` option of the value actions.
+    #[serde(rename = "set_value_format")]
+    SetValueFormat {
+        #[serde(default)]
+        integers: Option<IntegerFormat>,
+        #[serde(default)]
+        float_precision: Option<usize>,
+        #[serde(default)]
+        raw: Option<bool>,
+    },
+    /// Runs `commands` in order, with nothing else in between.
+    #[serde(rename = "batch")]
+    Batch {
+        commands: Vec<LlmCommand>,
+        /// Keep going after a command fails instead of skipping the rest.
+        #[serde(default)]
+        continue_on_error: bool,
+    },
+}
+
+fn default_byte_encoding() -> String {
+    "hex".into()
+}
+
+/// A range of target memory recorded by `snapshot`.
+#[derive(Debug, Deserialize, Serialize)]
+struct MemoryRange {
+    #[serde(deserialize_with = "deserialize_address")]
+    address: u64,
+    length: usize,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_stdin_encoding() -> String {
+    "text".into()
+}
+
+/// Accepts addresses (and register values) as JSON numbers or as `"0x..."`/decimal strings.
+fn deserialize_address<'de, D>(deserializer: D) -> Result<u64, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    match Value::deserialize(deserializer)? {
+        Value::Number(number) => number
+            .as_u64()
+            .ok_or_else(|| serde::de::Error::custom("address must be a positive integer")),
+        Value::String(text) => debug_session::parse_address(&text)
+            .ok_or_else(|| serde::de::Error::custom(format!("invalid address `{text}`"))),
+        other => Err(serde::de::Error::custom(format!(
+            "invalid address `{other}`"
+        ))),
+    }
+}
+
+/// Accepts new variable values as strings, numbers or booleans.
+fn deserialize_scalar_text<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    match Value::deserialize(deserializer)? {
+        Value::String(text) => Ok(text),
+        Value::Number(number) => Ok(number.to_string()),
+        Value::Bool(flag) => Ok(flag.to_string()),
+        other => Err(serde::de::Error::custom(format!(
+            "value must be a scalar, got `{other}`"
+        ))),
+    }
+}
+
+/// Accepts one address or a list, each in any form [`deserialize_address`] takes.
+fn deserialize_addresses<'de, D>(deserializer: D) -> Result<Vec<u64>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let values = match Value::deserialize(deserializer)? {
+        Value::Array(values) => values,
+        single => vec![single],
+    };
+    values
+        .into_iter()
+        .map(|value| deserialize_address(value).map_err(serde::de::Error::custom))
+        .collect()
+}
+
+fn deserialize_optional_address<'de, D>(deserializer: D) -> Result<Option<u64>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    deserialize_address(deserializer).map(Some)
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let _ = env_logger::builder().format_timestamp(None).try_init();
+    let args = Args::parse();
+    let listen_host = args.host.clone();
+    let listen_port = args.port;
+    let tls = match (&args.tls_cert, &args.tls_key) {
+        (Some(cert), Some(key)) => Some(load_tls_config(cert, key).await?),
+        _ => None,
+    };
+    let mut backend = if let Some(program) = args.program.as_deref() {
+        debug_session::backend_from_program(program, args.dsym.as_deref(), args.arch.as_deref())?
+    } else {
+        debug_session::init_backend()?
+    };
+    if let Some(fixture) = &args.mock {
+        backend = backend::Backend::new_mock(backend.symbol_ctx, MockFixture::load(fixture)?);
+    }
+
+    let session = DebugSession::new(backend);
+    let mut config = Config::from_args(&args, session.program_path().to_path_buf())?;
+    config.slice = session.program_slice().map(str::to_string);
+    config.validate()?;
+    let faults = config
+        .fault_inject
+        .then(|| Arc::new(FaultInjector::default()));
+    if faults.is_some() {
+        log::warn!("fault injection enabled; arm faults with POST /admin/faults");
+    }
+    let trace = match &config.protocol_trace {
+        Some(path) => {
+            let trace = ProtocolTrace::create(path, DEFAULT_SESSION)
+                .with_context(|| format!("failed to open protocol trace {}", path.display()))?;
+            Some(Arc::new(trace))
+        }
+        None => None,
+    };
+    let history = CommandHistory::create(config.history_file.as_deref()).with_context(|| {
+        format!(
+            "failed to open history file {}",
+            config
+                .history_file
+                .as_deref()
+                .unwrap_or(Path::new(""))
+                .display()
+        )
+    })?;
+    let artifacts = ArtifactStore::create(config.artifacts_dir.as_deref())
+        .context("failed to create the artifacts directory")?;
+    log::info!("artifacts are stored in {}", artifacts.dir().display());
+    let parts = open_session(session, &config, faults.as_ref(), trace, artifacts).await?;
+
+    let build_runner = config
+        .build_command
+        .as_ref()
+        .map(|cmd| Arc::new(BuildRunner::new(cmd.clone())));
+
+    let state = AppState {
+        session_id: DEFAULT_SESSION.into(),
+        session: Arc::new(Mutex::new(parts.session)),
+        config: config.clone(),
+        bridge: parts.bridge,
+        log_tx: parts.log_tx,
+        recent_logs: parts.recent_logs,
+        event_tx: parts.event_tx,
+        build_runner,
+        owner: Arc::new(Mutex::new(None)),
+        faults,
+        trace: parts.trace,
+        artifacts: parts.artifacts,
+        running: parts.running,
+        timeline: Arc::default(),
+        stops: parts.stops,
+        history: Arc::new(history),
+        queue: Arc::default(),
+        redactor: Arc::new(config.redactor().map_err(anyhow::Error::msg)?),
+        jobs: Arc::default(),
+        replays: Arc::default(),
+        started: Instant::now(),
+        rate_limiter: config
+            .rate_limit
+            .map(|limit| Arc::new(RateLimiter::new(limit))),
+        sessions: Arc::default(),
+        tasks: Arc::new(parts.tasks),
+    };
+    state.sessions.insert(state.clone());
+    if config.prewarm {
+        spawn_prewarm(&state).await;
+    }
+    if args.mcp {
+        return serve_mcp(state).await;
+    }
+    let app = router(state);
+
+    let addr: SocketAddr = format!("{}:{}", listen_host, listen_port).parse()?;
+    let listener = TcpListener::bind(addr).await?;
+    let scheme = if tls.is_some() { "https" } else { "http" };
+    println!(
+        "LLM Debug API listening on {scheme}://{}",
+        listener.local_addr()?
+    );
+    match tls {
+        Some(tls) => {
+            axum_server::from_tcp_rustls(listener.into_std()?, tls)
+                .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+                .await?
+        }
+        None => {
+            axum::serve(
+                listener,
+                app.into_make_service_with_connect_info::<SocketAddr>(),
+            )
+            .await?
+        }
+    }
+    Ok(())
+}
+
+fn router(state: AppState) -> Router {
+    // Served at the top level for the default session and under
+    // `/sessions/{session_id}` for every session.
+    let session_routes = Router::new()
+        .route("/status", get(session_status))
+        .route("/queue", get(command_queue))
+        .route("/command", post(handle_command))
+        .route("/jobs/:id", get(job_status).delete(cancel_job))
+        .route("/jobs/:id/cancel", post(cancel_job))
+        .route("/health", get(health_check))
+        .route("/logs", get(stream_logs))
+        .route("/events", get(stream_events))
+        .route("/timeline", get(stop_timeline))
+        .route("/protocol-trace/tail", get(protocol_trace_tail))
+        .route("/artifacts", get(list_artifacts))
+        .route("/artifacts/:id", get(download_artifact))
+        .route("/ws", get(command_socket))
+        .route("/rpc", get(rpc_socket).post(handle_rpc));
+    Router::new()
+        .route("/", get(dashboard))
+        .merge(session_routes.clone())
+        .nest(
+            "/sessions/:session_id",
+            session_routes.route_layer(middleware::from_fn_with_state(
+                state.clone(),
+                select_session,
+            )),
+        )
+        .route("/sessions", get(list_sessions).post(create_session))
+        .route("/sessions/:session_id", delete(remove_session))
+        .route("/history", get(command_history))
+        .route("/admin/faults", get(get_faults).post(set_faults))
+        .route_layer(middleware::from_fn_with_state(state.clone(), authorize))
+        .route("/openapi.json", get(openapi_spec))
+        .route("/schema", get(command_schema_spec))
+        .with_state(state.clone())
+        .merge(
+            tonic::service::Routes::new(proto::debugger_server::DebuggerServer::new(GrpcDebugger(
+                state,
+            )))
+            .into_axum_router(),
+        )
+}
+
+/// Reads the certificate and key up front so a bad path fails at startup
+/// rather than on the first handshake.
+async fn load_tls_config(cert: &Path, key: &Path) -> anyhow::Result<RustlsConfig> {
+    // rustls is built without a default provider; ring is the one compiled in.
+    let _ = rustls::crypto::ring::default_provider().install_default();
+    RustlsConfig::from_pem_file(cert, key)
+        .await
+        .with_context(|| {
+            format!(
+                "failed to load TLS certificate {} and key {}",
+                cert.display(),
+                key.display()
+            )
+        })
+}
+
+/// The session the server starts with, also served at the top-level routes.
+const DEFAULT_SESSION: &str = "default";
+
+/// The fields of [`AppState`] that belong to one session.
+struct SessionParts {
+    session: DebugSession,
+    bridge: Option<Arc<Mutex<BridgeController>>>,
+    log_tx: broadcast::Sender<String>,
+    recent_logs: Arc<RecentLogs>,
+    event_tx: broadcast::Sender<Value>,
+    running: Arc<RunTracker>,
+    stops: Arc<StopTimeline>,
+    tasks: Vec<AbortHandle>,
+    trace: Option<Arc<ProtocolTrace>>,
+    artifacts: Arc<ArtifactStore>,
+}
+
+impl SessionParts {
+    /// A session `id` sharing everything else with `shared`.
+    fn into_state(self, id: String, config: Config, shared: &AppState) -> AppState {
+        AppState {
+            session_id: id,
+            session: Arc::new(Mutex::new(self.session)),
+            config,
+            bridge: self.bridge,
+            log_tx: self.log_tx,
+            recent_logs: self.recent_logs,
+            event_tx: self.event_tx,
+            owner: Arc::default(),
+            running: self.running,
+            timeline: Arc::default(),
+            stops: self.stops,
+            trace: self.trace,
+            artifacts: self.artifacts,
+            queue: Arc::default(),
+            jobs: Arc::default(),
+            replays: Arc::default(),
+            started: Instant::now(),
+            tasks: Arc::new(self.tasks),
+            ..shared.clone()
+        }
+    }
+}
+
+/// Configures `session` for `config`, starts its bridge and log stream and,
+/// unless it is prewarmed later, connects it to debugserver. `trace` and
+/// `artifacts` are the session's own.
+async fn open_session(
+    mut session: DebugSession,
+    config: &Config,
+    faults: Option<&Arc<FaultInjector>>,
+    trace: Option<Arc<ProtocolTrace>>,
+    artifacts: ArtifactStore,
+) -> anyhow::Result<SessionParts> {
+    session.set_stop_summaries(config.stop_summaries);
+    for (from, to) in &config.source_maps {
+        session.add_source_map(from, to);
+    }
+    if let Some(root) = &config.project_root {
+        for (from, to) in session.set_project_root(root) {
+            log::info!("derived source map {from} -> {to}");
+        }
+    }
+    session.set_verbosity(config.verbosity);
+    session.set_eval_timeout(config.eval_timeout);
+    session.set_path_match(config.path_match);
+    session.set_step_scope(config.step_scope);
+    if let Some(faults) = faults {
+        session.set_fault_injector(faults.clone());
+    }
+    if let Some(trace) = &trace {
+        session.set_protocol_trace(trace.clone());
+    }
+    let (log_tx, _log_rx) = broadcast::channel(1024);
+    let logpoint_tx = log_tx.clone();
+    session.set_log_sink(Box::new(move |message| {
+        let _ = logpoint_tx.send(message);
+    }));
+    let (event_tx, _event_rx) = broadcast::channel(1024);
+    let target_event_tx = event_tx.clone();
+    let running = Arc::new(RunTracker::default());
+    let event_running = running.clone();
+    let stops = Arc::new(StopTimeline::default());
+    let event_stops = stops.clone();
+    session.set_event_sink(Box::new(move |event| {
+        if let Ok(mut event) = serde_json::to_value(event) {
+            let request_id = event_running.request_id();
+            if let Some(request_id) = &request_id {
+                event["request_id"] = json!(request_id);
+            }
+            let action = event_running.current().map(|(action, _)| action);
+            event_stops.record(&event, action, request_id);
+            let _ = target_event_tx.send(event);
+        }
+    }));
+    let bridge = if config.manage_bridge {
+        Some(Arc::new(Mutex::new(BridgeController::new(
+            config,
+            log_tx.clone(),
+            faults.cloned(),
+        )?)))
+    } else {
+        None
+    };
+
+    if !config.prewarm {
+        if let Some(controller) = &bridge {
+            controller.lock().await.ensure_running().await?;
+        }
+    }
+
+    let recent_logs = Arc::new(RecentLogs::default());
+    let mut tasks = vec![spawn_log_recorder(&log_tx, recent_logs.clone()).abort_handle()];
+    if config.enable_log_stream {
+        match spawn_log_stream(config, log_tx.clone()).await {
+            Ok(task) => tasks.push(task.abort_handle()),
+            Err(err) => log::warn!("log streaming failed to start: {err}"),
+        }
+    }
+
+    if !config.prewarm {
+        connect_debugserver(&mut session, config.debugserver_port).await?;
+    }
+    warn_if_missing_dwarf(config, session.debug_info_path())?;
+
+    if let Some(interval) = config.status_interval {
+        let task = spawn_status_events(log_tx.clone(), event_tx.clone(), running.clone(), interval);
+        tasks.push(task.abort_handle());
+    }
+    Ok(SessionParts {
+        session,
+        bridge,
+        log_tx,
+        recent_logs,
+        event_tx,
+        running,
+        stops,
+        tasks,
+        trace,
+        artifacts: Arc::new(artifacts),
+    })
+}
+
+/// Sessions by id, including [`DEFAULT_SESSION`].
+#[derive(Default)]
+struct SessionRegistry(std::sync::Mutex<(u64, BTreeMap<String, AppState>)>);
+
+impl SessionRegistry {
+    fn next_id(&self) -> String {
+        let mut guard = self.0.lock().unwrap_or_else(|err| err.into_inner());
+        guard.0 += 1;
+        guard.0.to_string()
+    }
+
+    fn insert(&self, state: AppState) {
+        let mut guard = self.0.lock().unwrap_or_else(|err| err.into_inner());
+        guard.1.insert(state.session_id.clone(), state);
+    }
+
+    fn get(&self, id: &str) -> Option<AppState> {
+        let guard = self.0.lock().unwrap_or_else(|err| err.into_inner());
+        guard.1.get(id).cloned()
+    }
+
+    fn remove(&self, id: &str) -> Option<AppState> {
+        let mut guard = self.0.lock().unwrap_or_else(|err| err.into_inner());
+        guard.1.remove(id)
+    }
+
+    fn list(&self) -> Vec<AppState> {
+        let guard = self.0.lock().unwrap_or_else(|err| err.into_inner());
+        guard.1.values().cloned().collect()
+    }
+}
+
+/// The session a request is for: the one `/sessions/{session_id}/...`
+/// names, otherwise the default one.
+struct Scoped(AppState);
+
+#[axum::async_trait]
+impl FromRequestParts<AppState> for Scoped {
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        Ok(Self(
+            parts
+                .extensions
+                .get::<AppState>()
+                .cloned()
+                .unwrap_or_else(|| state.clone()),
+        ))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct SessionPath {
+    session_id: String,
+}
+
+/// Looks up `{session_id}` for the routes under `/sessions/{session_id}`.
+async fn select_session(
+    State(state): State<AppState>,
+    RoutePath(SessionPath { session_id }): RoutePath<SessionPath>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    match state.sessions.get(&session_id) {
+        Some(session) => {
+            request.extensions_mut().insert(session);
+            next.run(request).await
+        }
+        None => (
+            StatusCode::NOT_FOUND,
+            api_error("SESSION_NOT_FOUND", format!("no session {session_id}")),
+        )
+            .into_response(),
+    }
+}
+
+/// `POST /sessions`: another target to debug next to the default one.
+#[derive(Debug, Deserialize)]
+struct NewSession {
+    debugserver_port: u16,
+    #[serde(default)]
+    program: Option<PathBuf>,
+    #[serde(default)]
+    dsym: Option<PathBuf>,
+    #[serde(default)]
+    device: Option<String>,
+    #[serde(default)]
+    bundle_id: Option<String>,
+    #[serde(default)]
+    app_bundle: Option<PathBuf>,
+}
+
+impl Config {
+    /// This configuration pointed at the target `request` describes. The
+    /// bridge state file and `--prewarm` stay with the default session.
+    fn for_session(&self, request: &NewSession) -> Self {
+        let mut config = self.clone();
+        config.debugserver_port = request.debugserver_port;
+        if let Some(program) = &request.program {
+            config.program = program.clone();
+            config.dsym = request.dsym.clone();
+        }
+        if request.device.is_some() {
+            config.device = request.device.clone();
+        }
+        if request.bundle_id.is_some() {
+            config.bundle_id = request.bundle_id.clone();
+            config.attach = None;
+        }
+        if request.app_bundle.is_some() {
+            config.app_bundle = request.app_bundle.clone();
+        }
+        config.state_file = None;
+        config.prewarm = false;
+        config
+    }
+}
+
+fn session_summary(state: &AppState) -> Value {
+    json!({
+        "id": state.session_id,
+        "program": state.config.program.display().to_string(),
+        "device": state.config.device,
+        "bundleId": state.config.bundle_id,
+        "debugserverPort": state.config.debugserver_port,
+        "uptime_ms": state.started.elapsed().as_millis() as u64,
+        "running": state.running.current().map(|(action, _)| action),
+    })
+}
+
+/// Sessions the tenant may use: its own and unclaimed ones.
+async fn list_sessions(
+    State(state): State<AppState>,
+    tenant: Option<Extension<Tenant>>,
+) -> (StatusCode, Json<Value>) {
+    let mut sessions = Vec::new();
+    for session in state.sessions.list() {
+        let visible = match (tenant.as_deref(), &*session.owner.lock().await) {
+            (Some(Tenant(tenant)), Some(owner)) => owner == tenant,
+            _ => true,
+        };
+        if visible {
+            sessions.push(session_summary(&session));
+        }
+    }
+    (
+        StatusCode::OK,
+        Json(json!({ "ok": true, "sessions": sessions })),
+    )
+}
+
+async fn create_session(
+    State(state): State<AppState>,
+    tenant: Option<Extension<Tenant>>,
+    Json(request): Json<NewSession>,
+) -> (StatusCode, Json<Value>) {
+    if state
+        .sessions
+        .list()
+        .iter()
+        .any(|session| session.config.debugserver_port == request.debugserver_port)
+    {
+        // Which session holds it may be another tenant's business.
+        return (
+            StatusCode::CONFLICT,
+            api_error(
+                "INVALID_ARGUMENT",
+                format!(
+                    "debugserver port {} is already in use",
+                    request.debugserver_port
+                ),
+            ),
+        );
+    }
+    let id = state.sessions.next_id();
+    let config = state.config.for_session(&request);
+    let opened = async {
+        config.validate()?;
+        let artifacts = ArtifactStore::create(
+            config
+                .artifacts_dir
+                .as_ref()
+                .map(|dir| dir.join(format!("session-{id}")))
+                .as_deref(),
+        )
+        .context("failed to create the session's artifacts directory")?;
+        let session = DebugSession::new(debug_session::backend_from_program(
+            &config.program,
+            config.dsym.as_deref(),
+            config.arch.as_deref(),
+        )?);
+        let config = Config {
+            program: session.program_path().to_path_buf(),
+            slice: session.program_slice().map(str::to_string),
+            ..config
+        };
+        let trace = state
+            .trace
+            .as_ref()
+            .map(|trace| Arc::new(trace.for_session(&id)));
+        let parts = open_session(session, &config, state.faults.as_ref(), trace, artifacts).await?;
+        anyhow::Ok((parts, config))
+    }
+    .await;
+    match opened {
+        Ok((parts, config)) => {
+            let session = parts.into_state(id, config, &state);
+            if let Some(Extension(Tenant(tenant))) = tenant {
+                *session.owner.lock().await = Some(tenant);
+            }
+            state.sessions.insert(session.clone());
+            (
+                StatusCode::CREATED,
+                Json(json!({ "ok": true, "session": session_summary(&session) })),
+            )
+        }
+        Err(err) => (
+            StatusCode::BAD_REQUEST,
+            api_error("BACKEND", format!("failed to open the session: {err:#}")),
+        ),
+    }
+}
+
+/// Disconnects a session, stops its bridge and forgets it. The default
+/// session stays; send it `disconnect` instead.
+async fn remove_session(
+    State(state): State<AppState>,
+    tenant: Option<Extension<Tenant>>,
+    RoutePath(SessionPath { session_id }): RoutePath<SessionPath>,
+) -> (StatusCode, Json<Value>) {
+    if session_id == DEFAULT_SESSION {
+        return (
+            StatusCode::CONFLICT,
+            api_error(
+                "INVALID_ARGUMENT",
+                "the default session cannot be removed; send it `disconnect` instead",
+            ),
+        );
+    }
+    let Some(session) = state.sessions.get(&session_id) else {
+        return (
+            StatusCode::NOT_FOUND,
+            api_error("SESSION_NOT_FOUND", format!("no session {session_id}")),
+        );
+    };
+    if let Err(rejection) = check_session_access(&session, tenant.as_deref()).await {
+        return rejection;
+    }
+    state.sessions.remove(&session_id);
+    close_session(&session).await;
+    (
+        StatusCode::OK,
+        Json(json!({ "ok": true, "removed": session_id })),
+    )
+}
+
+async fn close_session(state: &AppState) {
+    for task in state.tasks.iter() {
+        task.abort();
+    }
+    // Lets a `continue` in flight return so the session can be taken.
+    state.running.interrupt_current(&state.jobs);
+    let mut session = state.session.lock().await;
+    if session.is_connected() {
+        if let Err(err) = session.disconnect() {
+            log::warn!(
+                "session {} did not disconnect cleanly: {err}",
+                state.session_id
+            );
+        }
+    }
+    if let Some(bridge) = &state.bridge {
+        if let Err(err) = bridge.lock().await.stop_current().await {
+            log::warn!("session {} bridge did not stop: {err}", state.session_id);
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct CommandQuery {
+    #[serde(default)]
+    priority: Priority,
+    /// Answer with a job id at once and run the command in the background.
+    #[serde(default, rename = "async")]
+    background: bool,
+    /// Echoed in the reply and the events the command causes; a retry with
+    /// the same id of a command that changes the target gets the first
+    /// reply instead of running it again.
+    request_id: Option<String>,
+    /// Override `--max-string-len`, `--max-children` and `--max-frames`.
+    max_string_len: Option<usize>,
+    max_children: Option<usize>,
+    max_frames: Option<usize>,
+    /// `json` (default), `compact` or `markdown`.
+    response_format: Option<ResponseFormat>,
+}
+
+impl CommandQuery {
+    fn limits(&self, defaults: Limits) -> Limits {
+        Limits {
+            max_string_len: self.max_string_len,
+            max_children: self.max_children,
+            max_frames: self.max_frames,
+        }
+        .or(defaults)
+    }
+}
+
+/// A `/command` body: the command plus an optional `request_id`.
+#[derive(Debug, Deserialize)]
+struct CommandBody {
+    request_id: Option<String>,
+    #[serde(flatten)]
+    command: LlmCommand,
+}
+
+async fn handle_command(
+    Scoped(state): Scoped,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    tenant: Option<Extension<Tenant>>,
+    headers: HeaderMap,
+    Query(mut query): Query<CommandQuery>,
+    Json(CommandBody {
+        request_id,
+        command,
+    }): Json<CommandBody>,
+) -> Response {
+    query.request_id = request_id.or(query.request_id).or_else(|| {
+        headers
+            .get("idempotency-key")
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string)
+    });
+    if let Err(rejection) = check_session_access(&state, tenant.as_deref()).await {
+        return rejection.into_response();
+    }
+    if let Err(throttled) = admit(&state, &client_key(tenant.as_deref(), peer), &command) {
+        return throttled.into_response();
+    }
+    match dispatch_command(&state, command, query).await {
+        (status, Json(Value::String(markdown))) => (
+            status,
+            [(header::CONTENT_TYPE, "text/markdown; charset=utf-8")],
+            markdown,
+        )
+            .into_response(),
+        reply => reply.into_response(),
+    }
+}
+
+/// Runs `command` as `/command` does once access has been checked and
+/// renders the reply in the requested `response_format`; a markdown reply
+/// is a JSON string.
+async fn dispatch_command(
+    state: &AppState,
+    command: LlmCommand,
+    query: CommandQuery,
+) -> (StatusCode, Json<Value>) {
+    let format = query.response_format.unwrap_or_default();
+    let (status, Json(body)) = dispatch_tracked(state, command, query).await;
+    (status, Json(response_format::render(body, format)))
+}
+
+/// Runs `command`, echoing its `request_id` and replaying retries.
+async fn dispatch_tracked(
+    state: &AppState,
+    command: LlmCommand,
+    query: CommandQuery,
+) -> (StatusCode, Json<Value>) {
+    let Some(request_id) = query.request_id.clone() else {
+        return start_command(state, command, query).await;
+    };
+    let (status, mut body) = if command.is_inspection() || matches!(command, LlmCommand::Cancel) {
+        let (status, Json(body)) = start_command(state, command, query).await;
+        (status, body)
+    } else {
+        let Some((first, mut reply)) = state.replays.claim(&request_id, &command) else {
+            let Json(mut body) = api_error(
+                "REQUEST_ID_CONFLICT",
+                format!("request_id `{request_id}` was already used for a different command"),
+            );
+            body["request_id"] = json!(request_id);
+            return (StatusCode::CONFLICT, Json(body));
+        };
+        let replayed = first.is_none();
+        if let Some(sender) = first {
+            // Runs to the end even if this request goes away, so that
+            // its retry gets the outcome.
+            let state = state.clone();
+            tokio::spawn(async move {
+                let (status, Json(body)) = start_command(&state, command, query).await;
+                let _ = sender.send(Some((status, body)));
+            });
+        }
+        let outcome = reply
+            .wait_for(Option::is_some)
+            .await
+            .ok()
+            .and_then(|reply| reply.clone());
+        match outcome {
+            Some((status, mut body)) => {
+                if replayed {
+                    body["replayed"] = json!(true);
+                }
+                (status, body)
+            }
+            None => {
+                let Json(body) =
+                    api_error("INTERNAL", "the first request with this request_id failed");
+                (StatusCode::INTERNAL_SERVER_ERROR, body)
+            }
+        }
+    };
+    if body.is_object() {
+        body["request_id"] = json!(request_id);
+    }
+    (status, Json(body))
+}
+
+async fn start_command(
+    state: &AppState,
+    mut command: LlmCommand,
+    query: CommandQuery,
+) -> (StatusCode, Json<Value>) {
+    // In `/ws` and `/rpc` messages the query options share the command's
+    // object, so they take `all_stacktraces`' own `max_frames`.
+    if let LlmCommand::AllStacktraces { max_frames, .. } = &mut command {
+        *max_frames = max_frames.or(query.max_frames);
+    }
+    let action = command.action();
+    if let LlmCommand::Cancel = command {
+        let started = Instant::now();
+        let (status, Json(body)) = cancel_running(state);
+        let request_id = query.request_id;
+        record_history(state, action, json!({}), request_id, started, status, &body).await;
+        return (status, Json(body));
+    }
+    if query.priority == Priority::High && !command.is_inspection() {
+        return (
+            StatusCode::BAD_REQUEST,
+            api_error(
+                "INVALID_ARGUMENT",
+                format!("`{action}` changes the target and cannot run at high priority"),
+            ),
+        );
+    }
+    if query.background {
+        let id = state.jobs.create(action);
+        let job_state = state.clone();
+        let task = tokio::spawn(async move {
+            let (status, Json(body)) = run_command(&job_state, command, query, Some(id)).await;
+            job_state.jobs.finish(id, status, body);
+        });
+        state.jobs.set_abort(id, task.abort_handle());
+        return (
+            StatusCode::ACCEPTED,
+            Json(
+                json!({ "ok": true, "job_id": id, "action": action, "status": JobStatus::Running }),
+            ),
+        );
+    }
+    run_command(state, command, query, None).await
+}
+
+/// A `/ws` message: a `/command` body plus an optional `id` echoed in the
+/// reply and the `/command` query options.
+#[derive(Debug, Deserialize)]
+struct SocketCommand {
+    #[serde(flatten)]
+    query: CommandQuery,
+    #[serde(flatten)]
+    command: LlmCommand,
+}
+
+/// `/ws`: `/command` and `/events` over one connection. Replies come back as
+/// `{"type": "response", "id", "status", "body"}` in the order commands
+/// finish, interleaved with `{"type": "event", ...}` messages.
+async fn command_socket(
+    Scoped(state): Scoped,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    tenant: Option<Extension<Tenant>>,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    if let Err(rejection) = check_session_access(&state, tenant.as_deref()).await {
+        return rejection.into_response();
+    }
+    let tenant = tenant.map(|Extension(tenant)| tenant);
+    let client = client_key(tenant.as_ref(), peer);
+    upgrade.on_upgrade(move |socket| {
+        serve_socket(state, tenant, client, SocketProtocol::Commands, socket)
+    })
+}
+
+/// How a socket frames its messages: `/ws` messages or JSON-RPC 2.0.
+#[derive(Debug, Clone, Copy)]
+enum SocketProtocol {
+    Commands,
+    JsonRpc,
+}
+
+async fn serve_socket(
+    state: AppState,
+    tenant: Option<Tenant>,
+    client: String,
+    protocol: SocketProtocol,
+    mut socket: WebSocket,
+) {
+    let mut events = state.event_tx.subscribe();
+    let (reply_tx, mut replies) = mpsc::unbounded_channel();
+    loop {
+        let outgoing: Value = tokio::select! {
+            message = socket.recv() => match message {
+                Some(Ok(Message::Text(text))) => {
+                    // Commands queue like HTTP ones; run them off the socket
+                    // loop so events keep flowing meanwhile.
+                    let state = state.clone();
+                    let tenant = tenant.clone();
+                    let client = client.clone();
+                    let reply_tx = reply_tx.clone();
+                    tokio::spawn(async move {
+                        let reply = match protocol {
+                            SocketProtocol::Commands => {
+                                Some(socket_reply(&state, tenant.as_ref(), &client, &text).await)
+                            }
+                            SocketProtocol::JsonRpc => {
+                                rpc_payload(&state, tenant.as_ref(), &client, &text).await
+                            }
+                        };
+                        if let Some(reply) = reply {
+                            let _ = reply_tx.send(reply);
+                        }
+                    });
+                    continue;
+                }
+                Some(Ok(Message::Close(_)) | Err(_)) | None => break,
+                Some(Ok(_)) => continue,
+            },
+            Some(reply) = replies.recv() => reply,
+            event = events.recv() => match event {
+                Ok(mut event) => {
+                    state.redactor.apply(&mut event);
+                    match protocol {
+                        SocketProtocol::Commands => {
+                            event["type"] = json!("event");
+                            event
+                        }
+                        SocketProtocol::JsonRpc => {
+                            json!({ "jsonrpc": "2.0", "method": "event", "params": event })
+                        }
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+        };
+        if socket
+            .send(Message::Text(outgoing.to_string()))
+            .await
+            .is_err()
+        {
+            break;
+        }
+    }
+}
+
+/// Runs one `/ws` message and wraps its result as a `response` message.
+async fn socket_reply(
+    state: &AppState,
+    tenant: Option<&Tenant>,
+    client: &str,
+    text: &str,
+) -> Value {
+    let (id, (status, Json(body))) = match serde_json::from_str::<Value>(text) {
+        Err(err) => (
+            Value::Null,
+            (
+                StatusCode::BAD_REQUEST,
+                api_error("INVALID_COMMAND", format!("invalid JSON: {err}")),
+            ),
+        ),
+        Ok(message) => {
+            let id = message.get("id").cloned().unwrap_or(Value::Null);
+            let result = match serde_json::from_value::<SocketCommand>(message) {
+                Err(err) => (
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    api_error("INVALID_COMMAND", format!("invalid command: {err}")),
+                ),
+                Ok(SocketCommand { query, command }) => {
+                    match check_session_access(state, tenant).await {
+                        Err(rejection) => rejection,
+                        Ok(()) => match admit(state, client, &command) {
+                            Ok(()) => dispatch_command(state, command, query).await,
+                            Err(throttled) => {
+                                (StatusCode::TOO_MANY_REQUESTS, Json(throttled.body()))
+                            }
+                        },
+                    }
+                }
+            };
+            (id, result)
+        }
+    };
+    json!({ "type": "response", "id": id, "status": status.as_u16(), "body": body })
+}
+
+/// `/rpc`: JSON-RPC 2.0 over HTTP. The method is the action and `params` its
+/// fields plus the `/command` query options; batches run in order. A payload
+/// of notifications only is answered with 204.
+async fn handle_rpc(
+    Scoped(state): Scoped,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    tenant: Option<Extension<Tenant>>,
+    body: String,
+) -> Response {
+    let client = client_key(tenant.as_deref(), peer);
+    match rpc_payload(&state, tenant.as_deref(), &client, &body).await {
+        Some(reply) => Json(reply).into_response(),
+        None => StatusCode::NO_CONTENT.into_response(),
+    }
+}
+
+/// `/rpc` upgraded to a WebSocket: JSON-RPC payloads in, their responses and
+/// `event` notifications out.
+async fn rpc_socket(
+    Scoped(state): Scoped,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    tenant: Option<Extension<Tenant>>,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    if let Err(rejection) = check_session_access(&state, tenant.as_deref()).await {
+        return rejection.into_response();
+    }
+    let tenant = tenant.map(|Extension(tenant)| tenant);
+    let client = client_key(tenant.as_ref(), peer);
+    upgrade.on_upgrade(move |socket| {
+        serve_socket(state, tenant, client, SocketProtocol::JsonRpc, socket)
+    })
+}
+
+fn rpc_error(id: Value, code: i64, message: impl std::fmt::Display, data: Option<Value>) -> Value {
+    let mut error = json!({ "code": code, "message": message.to_string() });
+    if let Some(data) = data {
+        error["data"] = data;
+    }
+    json!({ "jsonrpc": "2.0", "id": id, "error": error })
+}
+
+/// Answers one JSON-RPC payload, a request or a batch of them; `None` when
+/// there is nothing to send back.
+async fn rpc_payload(
+    state: &AppState,
+    tenant: Option<&Tenant>,
+    client: &str,
+    text: &str,
+) -> Option<Value> {
+    let payload: Value = match serde_json::from_str(text) {
+        Ok(payload) => payload,
+        Err(err) => {
+            return Some(rpc_error(
+                Value::Null,
+                -32700,
+                format!("parse error: {err}"),
+                None,
+            ))
+        }
+    };
+    let Value::Array(calls) = payload else {
+        return rpc_call(state, tenant, client, payload).await;
+    };
+    if calls.is_empty() {
+        return Some(rpc_error(Value::Null, -32600, "empty batch", None));
+    }
+    let mut replies = Vec::new();
+    for call in calls {
+        replies.extend(rpc_call(state, tenant, client, call).await);
+    }
+    (!replies.is_empty()).then_some(Value::Array(replies))
+}
+
+/// Runs one JSON-RPC request as `/command` would. Failed commands become
+/// error objects carrying the `/command` envelope as `data`.
+async fn rpc_call(
+    state: &AppState,
+    tenant: Option<&Tenant>,
+    client: &str,
+    call: Value,
+) -> Option<Value> {
+    let id = call.get("id").cloned();
+    let reply_id = id.clone().unwrap_or(Value::Null);
+    let (Some("2.0"), Some(method)) = (call["jsonrpc"].as_str(), call["method"].as_str()) else {
+        return Some(rpc_error(
+            reply_id,
+            -32600,
+            "not a JSON-RPC 2.0 request",
+            None,
+        ));
+    };
+    let reply = if !ACTIONS.iter().any(|spec| spec.action == method) {
+        rpc_error(reply_id, -32601, format!("unknown method `{method}`"), None)
+    } else {
+        let mut params = match call.get("params") {
+            Some(Value::Object(params)) => params.clone(),
+            None => serde_json::Map::new(),
+            Some(_) => {
+                let message = "params must be an object of the action's fields";
+                return id.map(|id| rpc_error(id, -32602, message, None));
+            }
+        };
+        params.insert("action".into(), json!(method));
+        match serde_json::from_value::<SocketCommand>(Value::Object(params)) {
+            Err(err) => rpc_error(
+                reply_id,
+                -32602,
+                format!("invalid params for `{method}`: {err}"),
+                None,
+            ),
+            Ok(SocketCommand { query, command }) => {
+                let (status, Json(body)) = match check_session_access(state, tenant).await {
+                    Err(rejection) => rejection,
+                    Ok(()) => match admit(state, client, &command) {
+                        Ok(()) => dispatch_command(state, command, query).await,
+                        Err(throttled) => (StatusCode::TOO_MANY_REQUESTS, Json(throttled.body())),
+                    },
+                };
+                if status.is_success() {
+                    json!({ "jsonrpc": "2.0", "id": reply_id, "result": body })
+                } else {
+                    let message = body["error"]["message"]
+                        .as_str()
+                        .unwrap_or("command failed")
+                        .to_string();
+                    let mut data = body;
+                    data["status"] = json!(status.as_u16());
+                    rpc_error(reply_id, -32000, message, Some(data))
+                }
+            }
+        }
+    };
+    // Notifications still run but are never answered.
+    id.map(|_| reply)
+}
+
+/// `cancel`: stops the resuming command in flight without waiting in the
+/// queue behind it, so that command returns with the stop the interrupt
+/// causes.
+fn cancel_running(state: &AppState) -> (StatusCode, Json<Value>) {
+    match state.running.interrupt_current(&state.jobs) {
+        Some((action, job)) => (
+            StatusCode::OK,
+            Json(json!({ "ok": true, "interrupted": action, "job_id": job })),
+        ),
+        None => (
+            StatusCode::CONFLICT,
+            api_error("NOT_RUNNING", "no running command to cancel"),
+        ),
+    }
+}
+
+/// Queues `command` and runs it; `job` is set for `?async=true` requests.
+async fn run_command(
+    state: &AppState,
+    command: LlmCommand,
+    query: CommandQuery,
+    job: Option<u64>,
+) -> (StatusCode, Json<Value>) {
+    let state = state.clone();
+    let limits = query.limits(state.config.limits);
+    let CommandQuery {
+        priority,
+        request_id,
+        ..
+    } = query;
+    let action = command.action();
+    let _ticket = state.queue.enter(action, priority).await;
+    state.running.set_request_id(request_id.clone());
+    let disconnecting = command.is_disconnect();
+    let params = serde_json::to_value(&command).unwrap_or_default();
+    let started = Instant::now();
+    let response = match command {
+        LlmCommand::Restart => handle_launch_request(&state, None)
+            .await
+            .map_err(command_error),
+        LlmCommand::Launch { args, env } => {
+            let launch = LaunchOptions {
+                args: args.unwrap_or_else(|| state.config.launch.args.clone()),
+                env: env.unwrap_or_else(|| state.config.launch.env.clone()),
+            };
+            handle_launch_request(&state, Some(launch))
+                .await
+                .map_err(command_error)
+        }
+        LlmCommand::Build => handle_build_request(&state).await.map_err(command_error),
+        LlmCommand::Attach { pid, name } => handle_attach_request(&state, pid, name)
+            .await
+            .map_err(command_error),
+        LlmCommand::Terminate { devicectl } => handle_terminate_request(&state, devicectl)
+            .await
+            .map_err(command_error),
+        LlmCommand::Prewarm => {
+            let mut session = state.session.lock().await;
+            prewarm(&state, &mut session).await.map_err(command_error)
+        }
+        LlmCommand::Context {
+            frames,
+            context,
+            log_lines,
+        } => {
+            let mut session = state.session.lock().await;
+            let mut body = context_bundle(&mut session, frames, context);
+            let log_lines = log_lines.unwrap_or(DEFAULT_CONTEXT_LOG_LINES);
+            body["logs"] = json!(state.recent_logs.last(log_lines));
+            Ok(body)
+        }
+        LlmCommand::Batch {
+            commands,
+            continue_on_error,
+        } => run_batch(&state, commands, continue_on_error, job).await,
+        other => run_session_command(&state, other, job).await,
+    };
+
+    let response = redact_response(&state.redactor, response);
+    state.timeline.record(action, started, &response);
+    let (status, mut body) = match response {
+        Ok(body) => (StatusCode::OK, body),
+        Err(err) => {
+            let status = match err {
+                DebugSessionError::Timeout(_) => StatusCode::GATEWAY_TIMEOUT,
+                _ if err.code() == ErrorCode::Protocol => StatusCode::BAD_GATEWAY,
+                _ => StatusCode::BAD_REQUEST,
+            };
+            (status, error_body(&err))
+        }
+    };
+    truncation::truncate(&mut body, &limits);
+    state.running.set_request_id(None);
+    record_history(&state, action, params, request_id, started, status, &body).await;
+    if disconnecting && status.is_success() {
+        *state.owner.lock().await = None;
+    }
+    (status, Json(body))
+}
+
+/// Runs a command that only needs the session: evaluations through
+/// [`evaluate_bounded`], and resuming commands registered as running so that
+/// `cancel` can interrupt them.
+async fn run_session_command(
+    state: &AppState,
+    command: LlmCommand,
+    job: Option<u64>,
+) -> Result<Value, DebugSessionError> {
+    if matches!(
+        command,
+        LlmCommand::Evaluate { .. }
+            | LlmCommand::EvaluateSwift { .. }
+            | LlmCommand::Describe { .. }
+    ) {
+        return evaluate_bounded(state, command).await;
+    }
+    let mut session = state.session.lock().await;
+    let level = session.verbosity();
+    let resuming = match &command {
+        LlmCommand::Continue => Some("continue"),
+        LlmCommand::Next => Some("next"),
+        LlmCommand::StepIn => Some("step_in"),
+        LlmCommand::StepOut => Some("step_out"),
+        LlmCommand::RunToLine { .. } => Some("run_to_line"),
+        LlmCommand::ContinueUntilChanged { .. } => Some("continue_until_changed"),
+        LlmCommand::Signal { .. } => Some("signal"),
+        _ => None,
+    };
+    if let Some(action) = resuming {
+        state.running.start(action);
+        if let Some(interrupter) = session.interrupter() {
+            state.running.arm(job, interrupter);
+        }
+    }
+    let response = execute_command(&mut session, command).map(|mut body| {
+        verbosity::shape(&mut body, level);
+        body
+    });
+    state.running.finish();
+    response
+}
+
+/// Runs `commands` in order, each as [`run_session_command`] runs it alone,
+/// so a `continue` in a batch can be cancelled and an `evaluate` stays
+/// bounded. The batch keeps its turn in the command queue throughout, so no
+/// other command runs between them.
+async fn run_batch(
+    state: &AppState,
+    commands: Vec<LlmCommand>,
+    continue_on_error: bool,
+    job: Option<u64>,
+) -> Result<Value, DebugSessionError> {
+    if commands.is_empty() {
+        return Err(DebugSessionError::InvalidArgument(
+            "batch needs at least one command".into(),
+        ));
+    }
+    if let Some(command) = commands.iter().find(|command| !command.is_batchable()) {
+        return Err(DebugSessionError::InvalidArgument(format!(
+            "`{}` cannot run inside a batch",
+            command.action()
+        )));
+    }
+    let total = commands.len();
+    let mut results = Vec::with_capacity(total);
+    let mut ok = true;
+    for command in commands {
+        let action = command.action();
+        match run_session_command(state, command, job).await {
+            Ok(mut body) => {
+                if let Value::Object(map) = &mut body {
+                    map.insert("action".into(), json!(action));
+                }
+                results.push(body);
+            }
+            Err(err) => {
+                ok = false;
+                let mut body = error_body(&err);
+                body["action"] = json!(action);
+                results.push(body);
+                if !continue_on_error {
+                    break;
+                }
+            }
+        }
+    }
+    let skipped = total - results.len();
+    Ok(json!({ "ok": ok, "results": results, "skipped": skipped }))
+}
+
+/// The error a command the server runs itself (`launch`, `build`, ...)
+/// reports, keeping the code of a [`DebugSessionError`] raised inside it.
+fn command_error(err: anyhow::Error) -> DebugSessionError {
+    err.downcast()
+        .unwrap_or_else(|err| DebugSessionError::Backend(err.to_string()))
+}
+
+/// A command that needs a server option this server was started without.
+fn not_configured(message: &str) -> anyhow::Error {
+    DebugSessionError::coded(ErrorCode::NotConfigured, message).into()
+}
+
+/// `{ "ok": false, "error": { "code", "message", "hint"? } }` for a failed
+/// command; timeouts also carry `timed_out` and `timeout_ms`.
+fn error_body(err: &DebugSessionError) -> Value {
+    let mut error = json!({ "code": err.code(), "message": err.to_string() });
+    if let Some(hint) = err.hint() {
+        error["hint"] = json!(hint);
+    }
+    let mut body = json!({ "ok": false, "error": error });
+    if let DebugSessionError::Timeout(timeout_ms) = err {
+        body["timed_out"] = json!(true);
+        body["timeout_ms"] = json!(timeout_ms);
+    }
+    body
+}
+
+/// The same envelope for failures of the server itself rather than of a
+/// command.
+fn api_error(code: &str, message: impl std::fmt::Display) -> Json<Value> {
+    Json(json!({
+        "ok": false,
+        "error": { "code": code, "message": message.to_string() },
+    }))
+}
+
+/// Extra time past an evaluation's own deadline before the handler stops
+/// waiting for it, so the session's timeout error normally wins.
+const EVAL_TIMEOUT_GRACE: Duration = Duration::from_millis(250);
+
+/// Runs an `evaluate`/`evaluate_swift`/`describe` off the async runtime and answers with
+/// a timeout error when it outlives its deadline, even if the backend call is
+/// stuck somewhere that does not observe the deadline. The session stays
+/// locked until that call returns.
+async fn evaluate_bounded(
+    state: &AppState,
+    command: LlmCommand,
+) -> Result<Value, DebugSessionError> {
+    let mut session = state.session.clone().lock_owned().await;
+    let timeout = match &command {
+        LlmCommand::Evaluate { timeout_ms, .. }
+        | LlmCommand::EvaluateSwift { timeout_ms, .. }
+        | LlmCommand::Describe { timeout_ms, .. } => timeout_ms.map(Duration::from_millis),
+        _ => None,
+    }
+    .unwrap_or(session.eval_timeout());
+    let level = session.verbosity();
+    let task = tokio::task::spawn_blocking(move || {
+        execute_command(&mut session, command).map(|mut body| {
+            verbosity::shape(&mut body, level);
+            body
+        })
+    });
+    match tokio::time::timeout(timeout + EVAL_TIMEOUT_GRACE, task).await {
+        Ok(Ok(response)) => response,
+        Ok(Err(err)) => Err(DebugSessionError::Backend(format!(
+            "evaluation failed: {err}"
+        ))),
+        Err(_) => Err(DebugSessionError::Timeout(timeout.as_millis() as u64)),
+    }
+}
+
+async fn get_faults(State(state): State<AppState>) -> (StatusCode, Json<Value>) {
+    match &state.faults {
+        Some(faults) => (
+            StatusCode::OK,
+            Json(json!({ "ok": true, "faults": faults.plan() })),
+        ),
+        None => fault_injection_disabled(),
+    }
+}
+
+/// Replaces the armed faults; `{}` disarms everything.
+async fn set_faults(
+    State(state): State<AppState>,
+    Json(plan): Json<FaultPlan>,
+) -> (StatusCode, Json<Value>) {
+    match &state.faults {
+        Some(faults) => {
+            faults.configure(&plan);
+            log::warn!("fault injection armed: {plan:?}");
+            (
+                StatusCode::OK,
+                Json(json!({ "ok": true, "faults": faults.plan() })),
+            )
+        }
+        None => fault_injection_disabled(),
+    }
+}
+
+fn fault_injection_disabled() -> (StatusCode, Json<Value>) {
+    (
+        StatusCode::NOT_FOUND,
+        api_error("NOT_CONFIGURED", "fault injection requires --fault-inject"),
+    )
+}
+
+#[derive(Debug, Deserialize)]
+struct HistoryQuery {
+    /// Only this session's commands.
+    session: Option<String>,
+    action: Option<String>,
+    ok: Option<bool>,
+    /// Only entries with a larger id, to poll for new ones.
+    since: Option<u64>,
+    #[serde(default = "default_history_limit")]
+    limit: usize,
+}
+
+fn default_history_limit() -> usize {
+    100
+}
+
+/// Recorded commands, oldest first. A tenant only sees the commands run on
+/// sessions it owned.
+async fn command_history(
+    State(state): State<AppState>,
+    tenant: Option<Extension<Tenant>>,
+    Query(query): Query<HistoryQuery>,
+) -> Json<Value> {
+    let tenant = tenant.as_deref().map(|Tenant(tenant)| tenant.as_str());
+    Json(json!({ "ok": true, "commands": state.history.query(&query, tenant) }))
+}
+
+#[derive(Debug, Deserialize)]
+struct TimelineQuery {
+    /// Only stops with a larger `index`, to poll for new ones.
+    since: Option<u64>,
+    /// `json` (default) or `jsonl`, one stop per line for saving to a file.
+    format: Option<String>,
+}
+
+/// The session's stops, oldest first, as a JSON body or as JSON lines.
+async fn stop_timeline(
+    Scoped(state): Scoped,
+    tenant: Option<Extension<Tenant>>,
+    Query(query): Query<TimelineQuery>,
+) -> Response {
+    if let Err(rejection) = check_session_access(&state, tenant.as_deref()).await {
+        return rejection.into_response();
+    }
+    let mut stops: Vec<Value> = state
+        .stops
+        .since(query.since)
+        .into_iter()
+        .filter_map(|stop| serde_json::to_value(stop).ok())
+        .collect();
+    for stop in &mut stops {
+        state.redactor.apply(stop);
+    }
+    match query.format.as_deref() {
+        None | Some("json") => Json(json!({ "ok": true, "stops": stops })).into_response(),
+        Some("jsonl") => {
+            let lines: String = stops.iter().map(|stop| format!("{stop}\n")).collect();
+            ([(header::CONTENT_TYPE, "application/x-ndjson")], lines).into_response()
+        }
+        Some(other) => (
+            StatusCode::BAD_REQUEST,
+            api_error(
+                "INVALID_ARGUMENT",
+                format!("unknown timeline format `{other}` (expected json or jsonl)"),
+            ),
+        )
+            .into_response(),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct TailQuery {
+    #[serde(default = "default_trace_tail")]
+    lines: usize,
+}
+
+fn default_trace_tail() -> usize {
+    100
+}
+
+/// Most recent gdb-remote packets of the session, oldest first.
+async fn protocol_trace_tail(
+    Scoped(state): Scoped,
+    tenant: Option<Extension<Tenant>>,
+    Query(query): Query<TailQuery>,
+) -> (StatusCode, Json<Value>) {
+    if let Err(rejection) = check_session_access(&state, tenant.as_deref()).await {
+        return rejection;
+    }
+    match &state.trace {
+        Some(trace) => (
+            StatusCode::OK,
+            Json(json!({ "ok": true, "packets": trace.tail(query.lines) })),
+        ),
+        None => (
+            StatusCode::NOT_FOUND,
+            api_error(
+                "NOT_CONFIGURED",
+                "protocol tracing requires --protocol-trace",
+            ),
+        ),
+    }
+}
+
+async fn dashboard() -> Html<&'static str> {
+    Html(include_str!("../dashboard.html"))
+}
+
+/// What the dashboard polls. The session is only inspected when no command
+/// holds it, so a long `continue` shows up as `running` instead of stalling
+/// the page.
+async fn session_status(
+    Scoped(state): Scoped,
+    tenant: Option<Extension<Tenant>>,
+) -> (StatusCode, Json<Value>) {
+    if let Err(rejection) = check_session_access(&state, tenant.as_deref()).await {
+        return rejection;
+    }
+    let running = state.running.current();
+    let mut body = json!({
+        "ok": true,
+        "session_id": state.session_id,
+        "program": state.config.program.display().to_string(),
+        "device": state.config.device,
+        "bundle_id": state.config.bundle_id,
+        "uptime_ms": state.started.elapsed().as_millis() as u64,
+        "running": running.map(|(action, since)| json!({
+            "action": action,
+            "running_ms": since.elapsed().as_millis() as u64,
+        })),
+        "timeline": state.timeline.entries(),
+    });
+    match state.session.try_lock() {
+        Ok(mut session) => {
+            let connected = session.is_connected();
+            let breakpoints = session.export_breakpoints();
+            body["busy"] = json!(false);
+            body["state"] = json!(if connected { "stopped" } else { "disconnected" });
+            body["connected"] = json!(connected);
+            body["debugserver_port"] = json!(session.debugserver_port());
+            body["pid"] = json!(session.process_id());
+            body["stop"] = json!(session.last_stop());
+            body["thread_id"] = json!(session.selected_thread());
+            body["frame_index"] = json!(session.selected_frame());
+            body["breakpoint_count"] = json!(breakpoints.len());
+            body["breakpoints"] = json!(breakpoints);
+        }
+        Err(_) => {
+            // A command holds the session; only a resuming one means the
+            // target itself is running.
+            body["busy"] = json!(true);
+            body["state"] = json!(if running.is_some() { "running" } else { "busy" });
+        }
+    }
+    // The stop carries return and watch values, redacted like everywhere else.
+    state.redactor.apply(&mut body);
+    (StatusCode::OK, Json(body))
+}
+
+async fn command_queue(
+    Scoped(state): Scoped,
+    tenant: Option<Extension<Tenant>>,
+) -> (StatusCode, Json<Value>) {
+    if let Err(rejection) = check_session_access(&state, tenant.as_deref()).await {
+        return rejection;
+    }
+    (
+        StatusCode::OK,
+        Json(json!({ "ok": true, "commands": state.queue.snapshot() })),
+    )
+}
+
+#[derive(Debug, Deserialize)]
+struct JobPath {
+    id: u64,
+}
+
+async fn job_status(
+    Scoped(state): Scoped,
+    tenant: Option<Extension<Tenant>>,
+    RoutePath(JobPath { id }): RoutePath<JobPath>,
+) -> (StatusCode, Json<Value>) {
+    if let Err(rejection) = check_session_access(&state, tenant.as_deref()).await {
+        return rejection;
+    }
+    match state.jobs.view(id) {
+        Some(job) => (StatusCode::OK, Json(json!({ "ok": true, "job": job }))),
+        None => (
+            StatusCode::NOT_FOUND,
+            api_error("JOB_NOT_FOUND", format!("no job {id}")),
+        ),
+    }
+}
+
+/// Stops a job: a resuming command is interrupted so it returns with the
+/// stop that causes, anything else still queued or awaiting is aborted.
+async fn cancel_job(
+    Scoped(state): Scoped,
+    tenant: Option<Extension<Tenant>>,
+    RoutePath(JobPath { id }): RoutePath<JobPath>,
+) -> (StatusCode, Json<Value>) {
+    if let Err(rejection) = check_session_access(&state, tenant.as_deref()).await {
+        return rejection;
+    }
+    match state.jobs.request_cancel(id) {
+        None => (
+            StatusCode::NOT_FOUND,
+            api_error("JOB_NOT_FOUND", format!("no job {id}")),
+        ),
+        Some(Err(status)) => {
+            let Json(mut body) = api_error("JOB_FINISHED", format!("job {id} already finished"));
+            body["status"] = json!(status);
+            (StatusCode::CONFLICT, Json(body))
+        }
+        Some(Ok(abort)) => {
+            if !state.running.interrupt(id) {
+                if let Some(abort) = abort {
+                    abort.abort();
+                }
+                state.jobs.cancelled(id);
+            }
+            let job = state.jobs.view(id).unwrap_or_default();
+            (StatusCode::OK, Json(json!({ "ok": true, "job": job })))
+        }
+    }
+}
+
+async fn health_check(Scoped(state): Scoped) -> Json<serde_json::Value> {
+    Json(json!({
+        "ok": true,
+        "program": state.config.program.display().to_string(),
+        "slice": state.config.slice,
+        "debugserverPort": state.config.debugserver_port,
+        "mock": state.config.mock.as_ref().map(|path| path.display().to_string()),
+        "device": state.config.device,
+        "bundleId": state.config.bundle_id,
+        "host": state.config.host.clone(),
+        "port": state.config.port
+    }))
+}
+
+impl LlmCommand {
+    /// The `action` tag; a new command fails to compile here until it is
+    /// named, as a reminder to extend [`ACTIONS`] too.
+    fn action(&self) -> &'static str {
+        match self {
+            Self::Batch { .. } => "batch",
+            Self::Stacktrace => "stacktrace",
+            Self::Threads => "threads",
+            Self::AllStacktraces { .. } => "all_stacktraces",
+            Self::Source { .. } => "source",
+            Self::DetectDeadlock => "detect_deadlock",
+            Self::StackDiff => "stack_diff",
+            Self::Continue => "continue",
+            Self::Next => "next",
+            Self::StepIn => "step_in",
+            Self::StepOut => "step_out",
+            Self::RunToLine { .. } => "run_to_line",
+            Self::ContinueUntilChanged { .. } => "continue_until_changed",
+            Self::Signal { .. } => "signal",
+            Self::SetBreakpoint { .. } => "set_breakpoint",
+            Self::SetLogpoint { .. } => "set_logpoint",
+            Self::ExportBreakpoints { .. } => "export_breakpoints",
+            Self::ImportBreakpoints { .. } => "import_breakpoints",
+            Self::Locals { .. } => "locals",
+            Self::Scopes => "scopes",
+            Self::Variables { .. } => "variables",
+            Self::DumpVariable { .. } => "dump_variable",
+            Self::Snapshot { .. } => "snapshot",
+            Self::DiffSnapshot { .. } => "diff_snapshot",
+            Self::Evaluate { .. } => "evaluate",
+            Self::EvaluateSwift { .. } => "evaluate_swift",
+            Self::SetVariable { .. } => "set_variable",
+            Self::WatchExpression { .. } => "watch_expr",
+            Self::Disconnect => "disconnect",
+            Self::Detach => "detach",
+            Self::Restart => "restart",
+            Self::Launch { .. } => "launch",
+            Self::Build => "build",
+            Self::Prewarm => "prewarm",
+            Self::Context { .. } => "context",
+            Self::Attach { .. } => "attach",
+            Self::Terminate { .. } => "terminate",
+            Self::Cancel => "cancel",
+            Self::SelectThread { .. } => "select_thread",
+            Self::SuspendThread { .. } => "suspend_thread",
+            Self::ResumeThread { .. } => "resume_thread",
+            Self::SelectFrame { .. } => "select_frame",
+            Self::ReadMemory { .. } => "read_memory",
+            Self::WriteMemory { .. } => "write_memory",
+            Self::WriteStdin { .. } => "write_stdin",
+            Self::Disassemble { .. } => "disassemble",
+            Self::Modules => "modules",
+            Self::MemoryRegions { .. } => "memory_regions",
+            Self::HeapInfo { .. } => "heap_info",
+            Self::Tasks { .. } => "tasks",
+            Self::Actors { .. } => "actors",
+            Self::ObjcClasses { .. } => "objc_classes",
+            Self::ObjcClass { .. } => "objc_class",
+            Self::Describe { .. } => "describe",
+            Self::Symbolicate { .. } => "symbolicate",
+            Self::LookupSymbol { .. } => "lookup_symbol",
+            Self::Registers => "registers",
+            Self::SetRegister { .. } => "set_register",
+            Self::SetVerbosity { .. } => "set_verbosity",
+            Self::SetValueFormat { .. } => "set_value_format",
+            Self::MainThreadChecker { .. } => "main_thread_checker",
+            Self::TraceFunction { .. } => "trace_function",
+        }
+    }
+
+    /// Read-only commands, which may jump the queue with `?priority=high`.
+    /// A batch is one when every command in it is.
+    fn is_inspection(&self) -> bool {
+        if let Self::Batch { commands, .. } = self {
+            return !commands.is_empty() && commands.iter().all(Self::is_inspection);
+        }
+        matches!(
+            self,
+            Self::Stacktrace
+                | Self::Threads
+                | Self::AllStacktraces { .. }
+                | Self::Source { .. }
+                | Self::Context { .. }
+                | Self::DetectDeadlock
+                | Self::StackDiff
+                | Self::ExportBreakpoints { .. }
+                | Self::Locals { .. }
+                | Self::Scopes
+                | Self::Variables { .. }
+                | Self::DumpVariable { .. }
+                | Self::DiffSnapshot { .. }
+                | Self::ReadMemory { .. }
+                | Self::Disassemble { .. }
+                | Self::Modules
+                | Self::MemoryRegions { .. }
+                | Self::HeapInfo { .. }
+                | Self::Tasks { .. }
+                | Self::Actors { .. }
+                | Self::ObjcClasses { .. }
+                | Self::ObjcClass { .. }
+                | Self::Symbolicate { .. }
+                | Self::LookupSymbol { .. }
+                | Self::Registers
+        )
+    }
+
+    /// Whether the command can run inside a `batch`: the ones
+    /// `handle_command` manages itself need the app state, not just the
+    /// session.
+    fn is_batchable(&self) -> bool {
+        !matches!(
+            self,
+            Self::Restart
+                | Self::Launch { .. }
+                | Self::Build
+                | Self::Prewarm
+                | Self::Context { .. }
+                | Self::Attach { .. }
+                | Self::Terminate { .. }
+                | Self::Cancel
+                | Self::Batch { .. }
+        )
+    }
+
+    fn is_disconnect(&self) -> bool {
+        match self {
+            Self::Disconnect | Self::Detach => true,
+            Self::Batch { commands, .. } => commands.iter().any(Self::is_disconnect),
+            _ => false,
+        }
+    }
+}
+
+/// Schema of a `/command` request field.
+#[derive(Clone, Copy)]
+enum FieldKind {
+    String,
+    Integer,
+    Boolean,
+    /// Number or `"0x..."`/decimal string, see `deserialize_address`.
+    Address,
+    /// One address or a list of them, see `deserialize_addresses`.
+    Addresses,
+    /// String, number or boolean, see `deserialize_scalar_text`.
+    Scalar,
+    Enum(&'static [&'static str]),
+    /// Object mapping thread ids to non-negative integers.
+    ThreadCounts,
+    /// `{ integers?, float_precision?, raw? }`, see [`ValueFormat`].
+    ValueFormat,
+    Strings,
+    /// Object with string values.
+    StringMap,
+    /// List of `/command` requests.
+    Commands,
+    /// List of `{ address, length }` objects.
+    MemoryRanges,
+}
+
+struct ActionSpec {
+    action: &'static str,
+    summary: &'static str,
+    /// `(name, kind, required)`
+    fields: &'static [(&'static str, FieldKind, bool)],
+}
+
+/// Every `LlmCommand` variant with its request fields; the source of `/openapi.json`.
+const ACTIONS: &[ActionSpec] = &[
+    ActionSpec {
+        action: "stacktrace",
+        summary: "Frames of the selected thread",
+        fields: &[],
+    },
+    ActionSpec {
+        action: "threads",
+        summary: "List threads",
+        fields: &[],
+    },
+    ActionSpec {
+        action: "all_stacktraces",
+        summary:
+            "Backtraces of every thread with shared frames de-duplicated (alias: stacktrace_all)",
+        fields: &[
+            ("max_frames", FieldKind::Integer, false),
+            ("thread_max_frames", FieldKind::ThreadCounts, false),
+        ],
+    },
+    ActionSpec {
+        action: "source",
+        summary: "Source lines around a location, defaulting to the current stop",
+        fields: &[
+            ("file", FieldKind::String, false),
+            ("line", FieldKind::Integer, false),
+            ("context", FieldKind::Integer, false),
+        ],
+    },
+    ActionSpec {
+        action: "context",
+        summary: "The stop, top frames with source, locals, watch values and recent logs at once",
+        fields: &[
+            ("frames", FieldKind::Integer, false),
+            ("context", FieldKind::Integer, false),
+            ("log_lines", FieldKind::Integer, false),
+        ],
+    },
+    ActionSpec {
+        action: "detect_deadlock",
+        summary: "Wait-for analysis across all threads",
+        fields: &[],
+    },
+    ActionSpec {
+        action: "stack_diff",
+        summary: "Compare the stack at the last stop with the previous stop's",
+        fields: &[],
+    },
+    ActionSpec {
+        action: "continue",
+        summary: "Resume the target",
+        fields: &[],
+    },
+    ActionSpec {
+        action: "next",
+        summary: "Step over",
+        fields: &[],
+    },
+    ActionSpec {
+        action: "step_in",
+        summary: "Step into",
+        fields: &[],
+    },
+    ActionSpec {
+        action: "step_out",
+        summary: "Step out, reporting the return value",
+        fields: &[],
+    },
+    ActionSpec {
+        action: "run_to_line",
+        summary: "Continue to a source line through a one-shot breakpoint",
+        fields: &[
+            ("file", FieldKind::String, true),
+            ("line", FieldKind::Integer, true),
+        ],
+    },
+    ActionSpec {
+        action: "continue_until_changed",
+        summary: "Continue or step until an expression's value changes",
+        fields: &[
+            ("expression", FieldKind::String, true),
+            ("mode", FieldKind::Enum(&["continue", "next"]), false),
+            ("max_iterations", FieldKind::Integer, false),
+        ],
+    },
+    ActionSpec {
+        action: "signal",
+        summary: "Deliver a UNIX signal to the inferior and report the next stop",
+        fields: &[
+            ("signal", FieldKind::Scalar, true),
+            ("thread_id", FieldKind::Integer, false),
+        ],
+    },
+    ActionSpec {
+        action: "set_breakpoint",
+        summary: "Set a breakpoint at a source line",
+        fields: &[
+            ("file", FieldKind::String, true),
+            ("line", FieldKind::Integer, true),
+            (
+                "match",
+                FieldKind::Enum(&["exact", "suffix", "basename"]),
+                false,
+            ),
+            ("temporary", FieldKind::Boolean, false),
+        ],
+    },
+    ActionSpec {
+        action: "set_logpoint",
+        summary: "Log a message template to /logs when a line is hit, without stopping",
+        fields: &[
+            ("file", FieldKind::String, true),
+            ("line", FieldKind::Integer, true),
+            ("message", FieldKind::String, true),
+        ],
+    },
+    ActionSpec {
+        action: "export_breakpoints",
+        summary: "Breakpoints and logpoints as a list import_breakpoints (or LLDB) can read",
+        fields: &[("format", FieldKind::Enum(&["json", "lldb"]), false)],
+    },
+    ActionSpec {
+        action: "import_breakpoints",
+        summary: "Set breakpoints from a native, LLDB or Xcode .xcbkptlist breakpoint list",
+        fields: &[
+            ("path", FieldKind::String, false),
+            ("contents", FieldKind::String, false),
+        ],
+    },
+    ActionSpec {
+        action: "locals",
+        summary: "Locals of the selected frame",
+        fields: &[("format", FieldKind::ValueFormat, false)],
+    },
+    ActionSpec {
+        action: "scopes",
+        summary: "Scopes of the selected frame",
+        fields: &[],
+    },
+    ActionSpec {
+        action: "variables",
+        summary: "Children of a variables reference",
+        fields: &[
+            ("variablesReference", FieldKind::Integer, false),
+            ("start", FieldKind::Integer, false),
+            ("count", FieldKind::Integer, false),
+            ("format", FieldKind::ValueFormat, false),
+        ],
+    },
+    ActionSpec {
+        action: "dump_variable",
+        summary: "A variable expanded into a nested tree, several levels deep",
+        fields: &[
+            ("name", FieldKind::String, false),
+            ("variablesReference", FieldKind::Integer, false),
+            ("depth", FieldKind::Integer, false),
+            ("format", FieldKind::ValueFormat, false),
+        ],
+    },
+    ActionSpec {
+        action: "snapshot",
+        summary: "Record the locals, watch values and memory ranges at this stop for diff_snapshot",
+        fields: &[
+            ("name", FieldKind::String, false),
+            ("depth", FieldKind::Integer, false),
+            ("memory", FieldKind::MemoryRanges, false),
+        ],
+    },
+    ActionSpec {
+        action: "diff_snapshot",
+        summary: "What changed between two snapshots, or between one and now",
+        fields: &[
+            ("from", FieldKind::String, true),
+            ("to", FieldKind::String, false),
+        ],
+    },
+    ActionSpec {
+        action: "evaluate",
+        summary: "Evaluate an expression in the selected frame",
+        fields: &[
+            ("expression", FieldKind::String, true),
+            (
+                "language",
+                FieldKind::Enum(&["swift", "objc", "objc++", "c"]),
+                false,
+            ),
+            ("format", FieldKind::ValueFormat, false),
+            ("timeout_ms", FieldKind::Integer, false),
+            ("allow_side_effects", FieldKind::Boolean, false),
+        ],
+    },
+    ActionSpec {
+        action: "evaluate_swift",
+        summary: "Evaluate a Swift expression",
+        fields: &[
+            ("expression", FieldKind::String, true),
+            ("format", FieldKind::ValueFormat, false),
+            ("timeout_ms", FieldKind::Integer, false),
+            ("allow_side_effects", FieldKind::Boolean, false),
+        ],
+    },
+    ActionSpec {
+        action: "set_variable",
+        summary: "Assign a scalar local or struct member",
+        fields: &[
+            ("name", FieldKind::String, true),
+            ("value", FieldKind::Scalar, true),
+        ],
+    },
+    ActionSpec {
+        action: "watch_expr",
+        summary: "Add a watch expression and return all watch values",
+        fields: &[
+            ("expression", FieldKind::String, true),
+            ("format", FieldKind::ValueFormat, false),
+        ],
+    },
+    ActionSpec {
+        action: "disconnect",
+        summary: "Drop the debugserver connection, leaving the target as it is",
+        fields: &[],
+    },
+    ActionSpec {
+        action: "detach",
+        summary: "Detach debugserver and let the app keep running",
+        fields: &[],
+    },
+    ActionSpec {
+        action: "restart",
+        summary: "Relaunch the app (requires --manage-bridge)",
+        fields: &[],
+    },
+    ActionSpec {
+        action: "launch",
+        summary: "Launch the app (requires --manage-bridge)",
+        fields: &[
+            ("args", FieldKind::Strings, false),
+            ("env", FieldKind::StringMap, false),
+        ],
+    },
+    ActionSpec {
+        action: "build",
+        summary: "Run the registered --build-cmd",
+        fields: &[],
+    },
+    ActionSpec {
+        action: "prewarm",
+        summary: "Bring up the bridge and debugserver connection if they are down",
+        fields: &[],
+    },
+    ActionSpec {
+        action: "attach",
+        summary: "Attach to a running process by pid or name without relaunching",
+        fields: &[
+            ("pid", FieldKind::Integer, false),
+            ("name", FieldKind::String, false),
+        ],
+    },
+    ActionSpec {
+        action: "terminate",
+        summary: "Kill the inferior and report its exit status",
+        fields: &[("devicectl", FieldKind::Boolean, false)],
+    },
+    ActionSpec {
+        action: "cancel",
+        summary: "Interrupt the running continue or step without queueing behind it",
+        fields: &[],
+    },
+    ActionSpec {
+        action: "select_thread",
+        summary: "Select the thread used by inspection and stepping",
+        fields: &[("thread_id", FieldKind::Integer, true)],
+    },
+    ActionSpec {
+        action: "suspend_thread",
+        summary: "Keep a thread stopped while the others run",
+        fields: &[("thread_id", FieldKind::Integer, true)],
+    },
+    ActionSpec {
+        action: "resume_thread",
+        summary: "Let a suspended thread run again on the next resume",
+        fields: &[("thread_id", FieldKind::Integer, true)],
+    },
+    ActionSpec {
+        action: "select_frame",
+        summary: "Select the frame used by locals and evaluation",
+        fields: &[("frame_index", FieldKind::Integer, true)],
+    },
+    ActionSpec {
+        action: "read_memory",
+        summary: "Read target memory",
+        fields: &[
+            ("address", FieldKind::Address, true),
+            ("length", FieldKind::Integer, true),
+        ],
+    },
+    ActionSpec {
+        action: "write_memory",
+        summary: "Write target memory",
+        fields: &[
+            ("address", FieldKind::Address, true),
+            ("data", FieldKind::String, true),
+            ("encoding", FieldKind::Enum(&["hex", "base64"]), false),
+            ("confirm", FieldKind::Boolean, false),
+        ],
+    },
+    ActionSpec {
+        action: "write_stdin",
+        summary: "Queue bytes on the inferior's standard input",
+        fields: &[
+            ("data", FieldKind::String, true),
+            (
+                "encoding",
+                FieldKind::Enum(&["text", "hex", "base64"]),
+                false,
+            ),
+        ],
+    },
+    ActionSpec {
+        action: "disassemble",
+        summary: "Disassemble around an address, a symbol or the PC",
+        fields: &[
+            ("address", FieldKind::Address, false),
+            ("symbol", FieldKind::String, false),
+            ("count", FieldKind::Integer, false),
+        ],
+    },
+    ActionSpec {
+        action: "modules",
+        summary: "Loaded images with UUIDs and load addresses",
+        fields: &[],
+    },
+    ActionSpec {
+        action: "memory_regions",
+        summary: "Mapped memory regions, or the one containing an address",
+        fields: &[("address", FieldKind::Address, false)],
+    },
+    ActionSpec {
+        action: "heap_info",
+        summary: "Region, class and malloc block size of the object a pointer points at",
+        fields: &[("address", FieldKind::Address, true)],
+    },
+    ActionSpec {
+        action: "tasks",
+        summary: "Swift concurrency tasks with their states and async backtraces",
+        fields: &[("addresses", FieldKind::Addresses, false)],
+    },
+    ActionSpec {
+        action: "actors",
+        summary: "Executor, state, holding task and queued tasks of Swift actors",
+        fields: &[("addresses", FieldKind::Addresses, true)],
+    },
+    ActionSpec {
+        action: "objc_classes",
+        summary: "Objective-C classes the runtime has realized outside the shared cache",
+        fields: &[("filter", FieldKind::String, false)],
+    },
+    ActionSpec {
+        action: "objc_class",
+        summary: "Ivars, properties and methods of a class, or of an object's dynamic class",
+        fields: &[
+            ("name", FieldKind::String, false),
+            ("address", FieldKind::Address, false),
+        ],
+    },
+    ActionSpec {
+        action: "describe",
+        summary: "debugDescription of an object, computed in the target like LLDB's po",
+        fields: &[
+            ("address", FieldKind::Address, false),
+            ("expression", FieldKind::String, false),
+            ("timeout_ms", FieldKind::Integer, false),
+        ],
+    },
+    ActionSpec {
+        action: "symbolicate",
+        summary: "Function, file and line for each address",
+        fields: &[("addresses", FieldKind::Addresses, true)],
+    },
+    ActionSpec {
+        action: "lookup_symbol",
+        summary: "Addresses of a symbol in the main image",
+        fields: &[("name", FieldKind::String, true)],
+    },
+    ActionSpec {
+        action: "registers",
+        summary: "Registers of the selected thread",
+        fields: &[],
+    },
+    ActionSpec {
+        action: "set_register",
+        summary: "Write a register of the selected thread",
+        fields: &[
+            ("name", FieldKind::String, true),
+            ("value", FieldKind::Address, true),
+        ],
+    },
+    ActionSpec {
+        action: "set_verbosity",
+        summary: "Change the detail level of responses",
+        fields: &[(
+            "level",
+            FieldKind::Enum(&["minimal", "normal", "full"]),
+            true,
+        )],
+    },
+    ActionSpec {
+        action: "set_value_format",
+        summary: "Default number formatting for locals, variables, evaluate and watch_expr",
+        fields: &[
+            ("integers", FieldKind::Enum(&["decimal", "hex"]), false),
+            ("float_precision", FieldKind::Integer, false),
+            ("raw", FieldKind::Boolean, false),
+        ],
+    },
+    ActionSpec {
+        action: "main_thread_checker",
+        summary: "Stop with the offending backtrace when a UI API is called off the main thread",
+        fields: &[("enabled", FieldKind::Boolean, false)],
+    },
+    ActionSpec {
+        action: "trace_function",
+        summary: "Log each call to a function with its arguments and caller, without stopping",
+        fields: &[
+            ("name", FieldKind::String, true),
+            ("enabled", FieldKind::Boolean, false),
+        ],
+    },
+    ActionSpec {
+        action: "batch",
+        summary: "Run several commands in order in one request",
+        fields: &[
+            ("commands", FieldKind::Commands, true),
+            ("continue_on_error", FieldKind::Boolean, false),
+        ],
+    },
+];
+
+impl FieldKind {
+    /// JSON Schema of the field; `refs` locates the shared schemas, such as
+    /// `Command`, in the enclosing document.
+    fn schema(self, refs: &str) -> Value {
+        match self {
+            Self::String => json!({ "type": "string" }),
+            Self::Integer => json!({ "type": "integer" }),
+            Self::Boolean => json!({ "type": "boolean" }),
+            Self::Address => json!({
+                "oneOf": [
+                    { "type": "integer", "minimum": 0 },
+                    { "type": "string", "pattern": "^(0[xX][0-9a-fA-F]+|[0-9]+)$" }
+                ]
+            }),
+            Self::Addresses => json!({
+                "oneOf": [
+                    Self::Address.schema(refs),
+                    { "type": "array", "items": Self::Address.schema(refs) }
+                ]
+            }),
+            Self::Scalar => json!({ "type": ["string", "number", "boolean"] }),
+            Self::Enum(values) => json!({ "type": "string", "enum": values }),
+            Self::ThreadCounts => json!({
+                "type": "object",
+                "propertyNames": { "pattern": "^[0-9]+$" },
+                "additionalProperties": { "type": "integer", "minimum": 0 }
+            }),
+            Self::ValueFormat => json!({
+                "type": "object",
+                "properties": {
+                    "integers": { "type": "string", "enum": ["decimal", "hex"] },
+                    "float_precision": { "type": "integer", "minimum": 0 },
+                    "raw": { "type": "boolean" }
+                },
+                "additionalProperties": false
+            }),
+            Self::Strings => json!({ "type": "array", "items": { "type": "string" } }),
+            Self::StringMap => json!({
+                "type": "object",
+                "additionalProperties": { "type": "string" }
+            }),
+            Self::Commands => json!({
+                "type": "array",
+                "minItems": 1,
+                "items": { "$ref": format!("{refs}/Command") }
+            }),
+            Self::MemoryRanges => json!({
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "address": Self::Address.schema(refs),
+                        "length": { "type": "integer", "minimum": 0 }
+                    },
+                    "required": ["address", "length"]
+                }
+            }),
+        }
+    }
+}
+
+fn schema_name(action: &str) -> String {
+    let pascal: String = action
+        .split('_')
+        .map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_ascii_uppercase().to_string() + chars.as_str())
+                .unwrap_or_default()
+        })
+        .collect();
+    format!("{pascal}Command")
+}
+
+/// OpenAPI 3.1 description of the HTTP surface, built from [`ACTIONS`].
+/// Request schema of every action keyed by [`schema_name`], plus `Command`,
+/// the `oneOf` of them all; `refs` is where they are placed in the document.
+fn action_schemas(refs: &str) -> serde_json::Map<String, Value> {
+    let mut schemas = serde_json::Map::new();
+    let mut variants = Vec::new();
+    for spec in ACTIONS {
+        let name = schema_name(spec.action);
+        let mut properties = serde_json::Map::new();
+        properties.insert("action".into(), json!({ "const": spec.action }));
+        let mut required = vec!["action"];
+        for (field, kind, is_required) in spec.fields {
+            properties.insert(field.to_string(), kind.schema(refs));
+            if *is_required {
+                required.push(field);
+            }
+        }
+        variants.push(json!({ "$ref": format!("{refs}/{name}") }));
+        schemas.insert(
+            name,
+            json!({
+                "type": "object",
+                "description": spec.summary,
+                "properties": properties,
+                "required": required,
+            }),
+        );
+    }
+    schemas.insert("Command".into(), json!({ "oneOf": variants }));
+    schemas
+}
+
+fn openapi_document() -> Value {
+    let mut schemas = action_schemas("#/components/schemas");
+    let mapping: serde_json::Map<String, Value> = ACTIONS
+        .iter()
+        .map(|spec| {
+            let path = format!("#/components/schemas/{}", schema_name(spec.action));
+            (spec.action.to_string(), json!(path))
+        })
+        .collect();
+    schemas["Command"]["discriminator"] = json!({ "propertyName": "action", "mapping": mapping });
+    schemas.insert(
+        "Success".into(),
+        json!({
+            "type": "object",
+            "description": "`ok` plus action-specific fields; see docs/claude_tool.md",
+            "properties": { "ok": { "type": "boolean" } },
+            "required": ["ok"],
+            "additionalProperties": true,
+        }),
+    );
+    schemas.insert(
+        "Error".into(),
+        json!({
+            "type": "object",
+            "properties": {
+                "ok": { "const": false },
+                "error": {
+                    "type": "object",
+                    "properties": {
+                        "code": { "type": "string", "description": "Stable, e.g. NOT_CONNECTED or NO_CODE_AT_LINE" },
+                        "message": { "type": "string" },
+                        "hint": { "type": "string" },
+                        "retry_after_ms": { "type": "integer", "description": "With QUEUE_FULL and RATE_LIMITED" },
+                    },
+                    "required": ["code", "message"],
+                },
+                "timed_out": { "type": "boolean" },
+                "timeout_ms": { "type": "integer" },
+            },
+            "required": ["ok", "error"],
+        }),
+    );
+    schemas.insert(
+        "Health".into(),
+        json!({
+            "type": "object",
+            "properties": {
+                "ok": { "type": "boolean" },
+                "program": { "type": "string" },
+                "debugserverPort": { "type": "integer" },
+                "device": { "type": ["string", "null"] },
+                "bundleId": { "type": ["string", "null"] },
+                "host": { "type": "string" },
+                "port": { "type": "integer" },
+            },
+        }),
+    );
+    schemas.insert(
+        "NewSession".into(),
+        json!({
+            "type": "object",
+            "properties": {
+                "debugserver_port": { "type": "integer" },
+                "program": { "type": "string" },
+                "dsym": { "type": "string" },
+                "device": { "type": "string" },
+                "bundle_id": { "type": "string" },
+                "app_bundle": { "type": "string" },
+            },
+            "required": ["debugserver_port"],
+        }),
+    );
+    let json_body = |schema: &str| json!({ "application/json": { "schema": { "$ref": format!("#/components/schemas/{schema}") } } });
+    json!({
+        "openapi": "3.1.0",
+        "info": {
+            "title": "SwiftScope LLM Debug API",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "paths": {
+            "/command": {
+                "post": {
+                    "operationId": "command",
+                    "summary": "Run one debugger action",
+                    "parameters": [
+                        { "name": "priority", "in": "query", "schema": { "type": "string", "enum": ["normal", "high"] } },
+                        { "name": "async", "in": "query", "schema": { "type": "boolean" } },
+                        { "name": "request_id", "in": "query", "description": "Same as the body's `request_id`", "schema": { "type": "string" } },
+                        { "name": "Idempotency-Key", "in": "header", "description": "Same as `request_id`", "schema": { "type": "string" } },
+                        { "name": "max_string_len", "in": "query", "description": "Cut longer strings, overriding --max-string-len", "schema": { "type": "integer" } },
+                        { "name": "max_children", "in": "query", "description": "Keep this many items per array, overriding --max-children", "schema": { "type": "integer" } },
+                        { "name": "max_frames", "in": "query", "description": "Keep this many frames per stack, overriding --max-frames", "schema": { "type": "integer" } },
+                        { "name": "response_format", "in": "query", "description": "`compact` shortens keys; `markdown` answers text/markdown", "schema": { "type": "string", "enum": ["json", "compact", "markdown"] } },
+                    ],
+                    "requestBody": { "required": true, "content": json_body("Command") },
+                    "responses": {
+                        "200": { "description": "Action result", "content": json_body("Success") },
+                        "202": { "description": "Started in the background with `?async=true`; poll `/jobs/{id}`" },
+                        "400": { "description": "Invalid request or failed action", "content": json_body("Error") },
+                        "422": { "description": "Body does not match any action" },
+                        "429": { "description": "`QUEUE_FULL` or `RATE_LIMITED`; retry after the `Retry-After` seconds", "content": json_body("Error") },
+                    },
+                },
+            },
+            "/health": {
+                "get": {
+                    "operationId": "health",
+                    "responses": { "200": { "description": "Server configuration", "content": json_body("Health") } },
+                },
+            },
+            "/logs": {
+                "get": {
+                    "operationId": "logs",
+                    "summary": "Device log lines as server-sent events",
+                    "responses": {
+                        "200": {
+                            "description": "One `data:` event per log line",
+                            "content": { "text/event-stream": { "schema": { "type": "string" } } },
+                        },
+                    },
+                },
+            },
+            "/events": {
+                "get": {
+                    "operationId": "events",
+                    "summary": "Target events (stops, threads, images, exits) as server-sent events",
+                    "responses": {
+                        "200": {
+                            "description": "One `data:` event per JSON event object",
+                            "content": { "text/event-stream": { "schema": { "type": "string" } } },
+                        },
+                    },
+                },
+            },
+            "/timeline": {
+                "get": {
+                    "operationId": "timeline",
+                    "summary": "Every stop of the session with its cause, location and locals summary, oldest first",
+                    "parameters": [
+                        { "name": "since", "in": "query", "description": "Only stops with a larger index", "schema": { "type": "integer" } },
+                        { "name": "format", "in": "query", "description": "`jsonl` answers one stop per line", "schema": { "type": "string", "enum": ["json", "jsonl"] } },
+                    ],
+                    "responses": {
+                        "200": {
+                            "description": "`{ok, stops: [{index, at_ms, action?, request_id?, reason, description, thread_id, function?, file?, line?, summary?, exception?, diagnosis?}]}`",
+                            "content": { "application/x-ndjson": { "schema": { "type": "string" } } },
+                        },
+                        "400": { "description": "Unknown format", "content": json_body("Error") },
+                    },
+                },
+            },
+            "/ws": {
+                "get": {
+                    "operationId": "socket",
+                    "summary": "WebSocket carrying Command messages, their responses and target events",
+                    "responses": { "101": { "description": "Switching to the WebSocket protocol" } },
+                },
+            },
+            "/rpc": {
+                "post": {
+                    "operationId": "rpc",
+                    "summary": "JSON-RPC 2.0: method is the action, params its fields; batches run in order",
+                    "requestBody": { "required": true, "content": { "application/json": { "schema": { "type": ["object", "array"] } } } },
+                    "responses": {
+                        "200": { "description": "JSON-RPC response or batch of responses; failed commands are error objects with the Envelope as `data`" },
+                        "204": { "description": "Only notifications were sent" },
+                    },
+                },
+                "get": {
+                    "operationId": "rpcSocket",
+                    "summary": "WebSocket carrying JSON-RPC payloads, their responses and `event` notifications",
+                    "responses": { "101": { "description": "Switching to the WebSocket protocol" } },
+                },
+            },
+            "/sessions": {
+                "get": {
+                    "operationId": "listSessions",
+                    "summary": "Debug sessions; each serves /command, /status, /events, ... under /sessions/{session_id}",
+                    "responses": { "200": { "description": "`{ok, sessions: [{id, program, device, debugserverPort, ...}]}`" } },
+                },
+                "post": {
+                    "operationId": "createSession",
+                    "summary": "Open a session on another debugserver port, device or program",
+                    "requestBody": { "required": true, "content": json_body("NewSession") },
+                    "responses": {
+                        "201": { "description": "`{ok, session}`" },
+                        "400": { "description": "The session could not be opened", "content": json_body("Error") },
+                        "409": { "description": "The debugserver port is taken", "content": json_body("Error") },
+                    },
+                },
+            },
+            "/sessions/{session_id}": {
+                "delete": {
+                    "operationId": "removeSession",
+                    "summary": "Disconnect a session and stop its bridge",
+                    "parameters": [{ "name": "session_id", "in": "path", "required": true, "schema": { "type": "string" } }],
+                    "responses": {
+                        "200": { "description": "`{ok, removed}`" },
+                        "404": { "description": "No such session", "content": json_body("Error") },
+                        "409": { "description": "The default session cannot be removed", "content": json_body("Error") },
+                    },
+                },
+            },
+            "/history": {
+                "get": {
+                    "operationId": "history",
+                    "summary": "Audit log of the commands every session ran, oldest first",
+                    "parameters": [
+                        { "name": "session", "in": "query", "schema": { "type": "string" } },
+                        { "name": "action", "in": "query", "schema": { "type": "string" } },
+                        { "name": "ok", "in": "query", "schema": { "type": "boolean" } },
+                        { "name": "since", "in": "query", "description": "Only entries with a larger id", "schema": { "type": "integer" } },
+                        { "name": "limit", "in": "query", "schema": { "type": "integer", "default": 100 } },
+                    ],
+                    "responses": { "200": { "description": "`{ok, commands: [{id, at_ms, session_id, tenant?, action, params, status, ok, elapsed_ms, result}]}`" } },
+                },
+            },
+            "/schema": {
+                "get": {
+                    "operationId": "schema",
+                    "summary": "JSON Schema of Command, or with `?format=tool` a tool definition around it",
+                    "responses": { "200": { "description": "The schema" } },
+                },
+            },
+            "/openapi.json": {
+                "get": {
+                    "operationId": "openapi",
+                    "responses": { "200": { "description": "This document" } },
+                },
+            },
+        },
+        "components": { "schemas": schemas },
+    })
+}
+
+async fn openapi_spec() -> Json<Value> {
+    Json(openapi_document())
+}
+
+/// Name of the single tool an agent is given; see docs/claude_tool.md.
+const TOOL_NAME: &str = "ios_debug_command";
+
+/// Standalone JSON Schema of a `/command` body, for tool-calling frameworks
+/// that take one rather than an OpenAPI document.
+fn command_schema() -> Value {
+    let defs = action_schemas("#/$defs");
+    let actions: Vec<&str> = ACTIONS.iter().map(|spec| spec.action).collect();
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": TOOL_NAME,
+        // Tool definitions must be objects at the top level, so the action
+        // is spelled out here as well as in each branch.
+        "type": "object",
+        "properties": { "action": { "type": "string", "enum": actions } },
+        "required": ["action"],
+        "oneOf": defs["Command"]["oneOf"],
+        "$defs": defs,
+    })
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct SchemaQuery {
+    #[serde(default)]
+    format: SchemaFormat,
+}
+
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum SchemaFormat {
+    /// The bare JSON Schema.
+    #[default]
+    JsonSchema,
+    /// A tool definition (`name`, `description`, `input_schema`) wrapping it.
+    Tool,
+}
+
+async fn command_schema_spec(Query(query): Query<SchemaQuery>) -> Json<Value> {
+    let schema = command_schema();
+    Json(match query.format {
+        SchemaFormat::JsonSchema => schema,
+        SchemaFormat::Tool => json!({
+            "name": TOOL_NAME,
+            "description": "Drive the iOS debug session: pick an `action` and pass its fields. Responses are `{ \"ok\": true, ... }` or `{ \"ok\": false, \"error\" }`.",
+            "input_schema": schema,
+        }),
+    })
+}
+
+/// MCP revisions `--mcp` speaks, newest first; a client asking for another
+/// one is offered the first.
+const MCP_PROTOCOL_VERSIONS: &[&str] = &["2025-06-18", "2025-03-26", "2024-11-05"];
+
+/// `--mcp`: newline-delimited JSON-RPC 2.0 on stdin/stdout. Requests run
+/// concurrently and queue like `/command`; replies and event notifications
+/// share stdout, so nothing else may print there.
+async fn serve_mcp(state: AppState) -> anyhow::Result<()> {
+    let (out_tx, mut out_rx) = mpsc::unbounded_channel::<Value>();
+    let writer = tokio::spawn(async move {
+        let mut stdout = tokio::io::stdout();
+        while let Some(message) = out_rx.recv().await {
+            let line = format!("{message}\n");
+            if stdout.write_all(line.as_bytes()).await.is_err() || stdout.flush().await.is_err() {
+                break;
+            }
+        }
+    });
+    let mut events = state.event_tx.subscribe();
+    let (event_out, redactor) = (out_tx.clone(), state.redactor.clone());
+    let forwarder = tokio::spawn(async move {
+        loop {
+            match events.recv().await {
+                Ok(mut event) => {
+                    redactor.apply(&mut event);
+                    let notification = json!({
+                        "jsonrpc": "2.0",
+                        "method": "notifications/message",
+                        "params": { "level": "info", "logger": "events", "data": event },
+                    });
+                    if event_out.send(notification).is_err() {
+                        break;
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    });
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (state, out_tx) = (state.clone(), out_tx.clone());
+        tokio::spawn(async move {
+            if let Some(reply) = mcp_reply(&state, &line).await {
+                let _ = out_tx.send(reply);
+            }
+        });
+    }
+    forwarder.abort();
+    drop(out_tx);
+    let _ = writer.await;
+    Ok(())
+}
+
+/// Answers one JSON-RPC message; notifications get `None`.
+async fn mcp_reply(state: &AppState, line: &str) -> Option<Value> {
+    let message: Value = match serde_json::from_str(line) {
+        Ok(message) => message,
+        Err(err) => {
+            return Some(json!({
+                "jsonrpc": "2.0",
+                "id": null,
+                "error": { "code": -32700, "message": format!("parse error: {err}") },
+            }))
+        }
+    };
+    let id = message.get("id").cloned();
+    let method = message["method"].as_str().unwrap_or_default();
+    let params = message.get("params").cloned().unwrap_or(Value::Null);
+    let result = match method {
+        "initialize" => {
+            let requested = params["protocolVersion"].as_str();
+            let version = MCP_PROTOCOL_VERSIONS
+                .iter()
+                .find(|version| Some(**version) == requested)
+                .unwrap_or(&MCP_PROTOCOL_VERSIONS[0]);
+            Ok(json!({
+                "protocolVersion": version,
+                "capabilities": { "tools": {}, "logging": {} },
+                "serverInfo": { "name": "swiftscope", "version": env!("CARGO_PKG_VERSION") },
+            }))
+        }
+        "ping" | "logging/setLevel" => Ok(json!({})),
+        "tools/list" => Ok(json!({ "tools": mcp_tools() })),
+        "tools/call" => mcp_call(state, &params).await,
+        _ if id.is_none() => return None,
+        _ => Err((-32601, format!("unknown method `{method}`"))),
+    };
+    Some(match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id?, "result": result }),
+        Err((code, message)) => json!({
+            "jsonrpc": "2.0",
+            "id": id?,
+            "error": { "code": code, "message": message },
+        }),
+    })
+}
+
+/// One tool per action, named after it, taking the action's fields.
+fn mcp_tools() -> Vec<Value> {
+    let defs = action_schemas("#/$defs");
+    ACTIONS
+        .iter()
+        .map(|spec| {
+            let mut schema = defs[&schema_name(spec.action)].clone();
+            if let Some(schema) = schema.as_object_mut() {
+                schema.remove("description");
+            }
+            if let Some(properties) = schema["properties"].as_object_mut() {
+                properties.remove("action");
+            }
+            if let Some(required) = schema["required"].as_array_mut() {
+                required.retain(|field| field != "action");
+            }
+            if spec
+                .fields
+                .iter()
+                .any(|(_, kind, _)| matches!(kind, FieldKind::Commands))
+            {
+                schema["$defs"] = Value::Object(defs.clone());
+            }
+            json!({ "name": spec.action, "description": spec.summary, "inputSchema": schema })
+        })
+        .collect()
+}
+
+/// Runs a tool as the `/command` body `{"action": name, ...arguments}`. The
+/// response envelope is the text content; failures set `isError`.
+async fn mcp_call(state: &AppState, params: &Value) -> Result<Value, (i64, String)> {
+    let name = params["name"].as_str().unwrap_or_default();
+    if !ACTIONS.iter().any(|spec| spec.action == name) {
+        return Err((-32602, format!("unknown tool `{name}`")));
+    }
+    let mut body = match params.get("arguments") {
+        Some(Value::Object(arguments)) => arguments.clone(),
+        _ => serde_json::Map::new(),
+    };
+    body.insert("action".into(), json!(name));
+    let (status, Json(body)) = match serde_json::from_value::<LlmCommand>(Value::Object(body)) {
+        Ok(command) => match admit(state, "mcp", &command) {
+            Ok(()) => dispatch_command(state, command, CommandQuery::default()).await,
+            Err(throttled) => (StatusCode::TOO_MANY_REQUESTS, Json(throttled.body())),
+        },
+        Err(err) => (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            api_error(
+                "INVALID_COMMAND",
+                format!("invalid arguments for `{name}`: {err}"),
+            ),
+        ),
+    };
+    Ok(json!({
+        "content": [{ "type": "text", "text": body.to_string() }],
+        "isError": !status.is_success(),
+    }))
+}
+
+mod proto {
+    tonic::include_proto!("swiftscope.v1");
+}
+
+/// The gRPC `Debugger` service (`proto/swiftscope.proto`), served next to
+/// the HTTP routes. It holds the default session and finds the others in
+/// its registry.
+struct GrpcDebugger(AppState);
+
+#[tonic::async_trait]
+impl proto::debugger_server::Debugger for GrpcDebugger {
+    async fn command(
+        &self,
+        request: tonic::Request<proto::CommandRequest>,
+    ) -> Result<tonic::Response<proto::CommandReply>, tonic::Status> {
+        let tenant =
+            grpc_tenant(&self.0, request.metadata()).map_err(tonic::Status::unauthenticated)?;
+        let client = grpc_client(tenant.as_ref(), request.extensions());
+        let command = request.into_inner();
+        let state = grpc_session(&self.0, tenant.as_ref(), &command.session_id).await?;
+        Ok(tonic::Response::new(
+            grpc_reply(&state, &client, command).await,
+        ))
+    }
+
+    type EventsStream = ChannelStream<proto::Event>;
+
+    async fn events(
+        &self,
+        request: tonic::Request<proto::EventsRequest>,
+    ) -> Result<tonic::Response<Self::EventsStream>, tonic::Status> {
+        let tenant =
+            grpc_tenant(&self.0, request.metadata()).map_err(tonic::Status::unauthenticated)?;
+        let state = grpc_session(&self.0, tenant.as_ref(), &request.get_ref().session_id).await?;
+        let (tx, rx) = mpsc::unbounded_channel();
+        let mut events = state.event_tx.subscribe();
+        tokio::spawn(async move {
+            loop {
+                let event = tokio::select! {
+                    event = events.recv() => event,
+                    _ = tx.closed() => break,
+                };
+                match event {
+                    Ok(mut event) => {
+                        state.redactor.apply(&mut event);
+                        if tx.send(Ok(grpc_event(event))).is_err() {
+                            break;
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+        Ok(tonic::Response::new(ChannelStream(rx)))
+    }
+
+    type SessionStream = ChannelStream<proto::SessionMessage>;
+
+    async fn session(
+        &self,
+        request: tonic::Request<tonic::Streaming<proto::CommandRequest>>,
+    ) -> Result<tonic::Response<Self::SessionStream>, tonic::Status> {
+        let tenant =
+            grpc_tenant(&self.0, request.metadata()).map_err(tonic::Status::unauthenticated)?;
+        let client = grpc_client(tenant.as_ref(), request.extensions());
+        let mut inbound = request.into_inner();
+        let (tx, rx) = mpsc::unbounded_channel();
+        let service = self.0.clone();
+        tokio::spawn(async move {
+            // Events follow the session of the first command.
+            let mut events: Option<(AppState, broadcast::Receiver<Value>)> = None;
+            loop {
+                let next_event = async {
+                    match events.as_mut() {
+                        Some((_, receiver)) => receiver.recv().await,
+                        None => std::future::pending().await,
+                    }
+                };
+                tokio::select! {
+                    message = inbound.message() => {
+                        let Ok(Some(command)) = message else { break };
+                        let state = match grpc_session(&service, tenant.as_ref(), &command.session_id).await {
+                            Ok(state) => state,
+                            Err(status) => {
+                                let _ = tx.send(Err(status));
+                                break;
+                            }
+                        };
+                        if events.is_none() {
+                            events = Some((state.clone(), state.event_tx.subscribe()));
+                        }
+                        // Run off the stream loop so events keep flowing meanwhile.
+                        let (tx, client) = (tx.clone(), client.clone());
+                        tokio::spawn(async move {
+                            let reply = grpc_reply(&state, &client, command).await;
+                            let message = proto::session_message::Message::Reply(reply);
+                            let _ = tx.send(Ok(proto::SessionMessage { message: Some(message) }));
+                        });
+                    }
+                    event = next_event => match event {
+                        Ok(mut event) => {
+                            if let Some((state, _)) = &events {
+                                state.redactor.apply(&mut event);
+                            }
+                            let message = proto::session_message::Message::Event(grpc_event(event));
+                            if tx.send(Ok(proto::SessionMessage { message: Some(message) })).is_err() {
+                                break;
+                            }
+                        }
+                        Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    },
+                    _ = tx.closed() => break,
+                }
+            }
+        });
+        Ok(tonic::Response::new(ChannelStream(rx)))
+    }
+}
+
+/// A gRPC response stream fed by a channel.
+struct ChannelStream<T>(mpsc::UnboundedReceiver<Result<T, tonic::Status>>);
+
+impl<T> Stream for ChannelStream<T> {
+    type Item = Result<T, tonic::Status>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<Option<Self::Item>> {
+        self.0.poll_recv(cx)
+    }
+}
+
+/// The tenant of the `authorization: Bearer` token, as `authorize` finds it
+/// for HTTP requests.
+fn grpc_tenant(
+    state: &AppState,
+    metadata: &tonic::metadata::MetadataMap,
+) -> Result<Option<Tenant>, &'static str> {
+    if state.config.api_tokens.is_empty() {
+        return Ok(None);
+    }
+    metadata
+        .get("authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .and_then(|token| state.config.api_tokens.get(token.trim()))
+        .map(|tenant| Some(Tenant(tenant.clone())))
+        .ok_or("missing or invalid API token")
+}
+
+fn grpc_client(tenant: Option<&Tenant>, extensions: &axum::http::Extensions) -> String {
+    let peer = extensions
+        .get::<ConnectInfo<SocketAddr>>()
+        .map_or(SocketAddr::from(([0, 0, 0, 0], 0)), |info| info.0);
+    client_key(tenant, peer)
+}
+
+/// The session `session_id` names (the default one when empty), if the
+/// tenant may use it.
+async fn grpc_session(
+    default: &AppState,
+    tenant: Option<&Tenant>,
+    session_id: &str,
+) -> Result<AppState, tonic::Status> {
+    let session_id = if session_id.is_empty() {
+        DEFAULT_SESSION
+    } else {
+        session_id
+    };
+    let state = default
+        .sessions
+        .get(session_id)
+        .ok_or_else(|| tonic::Status::not_found(format!("no session `{session_id}`")))?;
+    check_session_access(&state, tenant)
+        .await
+        .map_err(|_| tonic::Status::not_found("no debug session for this token"))?;
+    Ok(state)
+}
+
+/// Runs a `CommandRequest` as the `/command` body `{"action", ...arguments}`.
+async fn grpc_reply(
+    state: &AppState,
+    client: &str,
+    request: proto::CommandRequest,
+) -> proto::CommandReply {
+    let priority = match request.priority() {
+        proto::Priority::Normal => Priority::Normal,
+        proto::Priority::High => Priority::High,
+    };
+    let mut body = request.arguments.map(json_object).unwrap_or_default();
+    body.insert("action".into(), json!(request.action));
+    let (status, Json(body)) = match serde_json::from_value::<LlmCommand>(Value::Object(body)) {
+        Ok(command) => match admit(state, client, &command) {
+            Ok(()) => {
+                let query = CommandQuery {
+                    priority,
+                    background: request.background,
+                    request_id: (!request.request_id.is_empty())
+                        .then(|| request.request_id.clone()),
+                    max_string_len: request.max_string_len.map(|max| max as usize),
+                    max_children: request.max_children.map(|max| max as usize),
+                    max_frames: request.max_frames.map(|max| max as usize),
+                    response_format: None,
+                };
+                dispatch_command(state, command, query).await
+            }
+            Err(throttled) => (StatusCode::TOO_MANY_REQUESTS, Json(throttled.body())),
+        },
+        Err(err) => (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            api_error("INVALID_COMMAND", format!("invalid command: {err}")),
+        ),
+    };
+    let text = |value: &Value| value.as_str().unwrap_or_default().to_string();
+    proto::CommandReply {
+        id: request.id,
+        status: status.as_u16().into(),
+        ok: body["ok"].as_bool().unwrap_or(status.is_success()),
+        error_code: text(&body["error"]["code"]),
+        error_message: text(&body["error"]["message"]),
+        body: match body {
+            Value::Object(fields) => Some(prost_struct(fields)),
+            _ => None,
+        },
+    }
+}
+
+/// A `/events` object as a typed `Event`, keeping the object in `fields`.
+fn grpc_event(event: Value) -> proto::Event {
+    let text = |key: &str| event[key].as_str().unwrap_or_default().to_string();
+    let small = |key: &str| event[key].as_i64().map(|value| value as i32);
+    proto::Event {
+        event: text("event"),
+        thread_id: event["thread_id"].as_i64().unwrap_or_default(),
+        reason: text("reason"),
+        description: text("description"),
+        file: text("file"),
+        line: event["line"].as_u64().unwrap_or_default() as u32,
+        name: text("name"),
+        path: text("path"),
+        load_address: text("load_address"),
+        exit_code: small("exit_code"),
+        signal: small("signal"),
+        fields: match event {
+            Value::Object(fields) => Some(prost_struct(fields)),
+            _ => None,
+        },
+    }
+}
+
+fn prost_struct(fields: serde_json::Map<String, Value>) -> prost_types::Struct {
+    prost_types::Struct {
+        fields: fields
+            .into_iter()
+            .map(|(key, value)| (key, prost_value(value)))
+            .collect(),
+    }
+}
+
+fn prost_value(value: Value) -> prost_types::Value {
+    use prost_types::value::Kind;
+    let kind = match value {
+        Value::Null => Kind::NullValue(prost_types::NullValue::NullValue.into()),
+        Value::Bool(value) => Kind::BoolValue(value),
+        Value::Number(value) => Kind::NumberValue(value.as_f64().unwrap_or_default()),
+        Value::String(value) => Kind::StringValue(value),
+        Value::Array(values) => Kind::ListValue(prost_types::ListValue {
+            values: values.into_iter().map(prost_value).collect(),
+        }),
+        Value::Object(fields) => Kind::StructValue(prost_struct(fields)),
+    };
+    prost_types::Value { kind: Some(kind) }
+}
+
+fn json_object(fields: prost_types::Struct) -> serde_json::Map<String, Value> {
+    fields
+        .fields
+        .into_iter()
+        .map(|(key, value)| (key, json_value(value)))
+        .collect()
+}
+
+/// Structs only carry doubles, so whole numbers come back as integers for the
+/// fields (`line`, `count`, ...) that expect one.
+fn json_value(value: prost_types::Value) -> Value {
+    use prost_types::value::Kind;
+    match value.kind {
+        None | Some(Kind::NullValue(_)) => Value::Null,
+        Some(Kind::BoolValue(value)) => json!(value),
+        Some(Kind::NumberValue(value)) if value.fract() == 0.0 && value.abs() < 9.0e15 => {
+            json!(value as i64)
+        }
+        Some(Kind::NumberValue(value)) => json!(value),
+        Some(Kind::StringValue(value)) => json!(value),
+        Some(Kind::ListValue(list)) => list.values.into_iter().map(json_value).collect(),
+        Some(Kind::StructValue(fields)) => Value::Object(json_object(fields)),
+    }
+}
+
+/// Checks the file debug information is read from: `debug_info`, the
+/// program or its dSYM.
+fn warn_if_missing_dwarf(config: &Config, debug_info: &Path) -> anyhow::Result<()> {
+    if backend::binary_has_dwarf_line_info(debug_info) {
+        return Ok(());
+    }
+    log::warn!(
+        "DWARF line info missing for {}. Breakpoints may fail; rebuild the app with a Debug configuration or pass -g, or point --dsym at its dSYM.",
+        debug_info.display()
+    );
+    if config.require_dwarf {
+        bail!(
+            "DWARF data missing for {}; refusing to start because --require-dwarf was set",
+            debug_info.display()
+        );
+    }
+    Ok(())
+}
+
+/// Launches the app through the bridge with `launch`'s argv and environment,
+/// or relaunches it with the previous ones when `launch` is `None` (`restart`).
+/// Restarting a `--mock` target plays its fixture from the start.
+async fn handle_launch_request(
+    state: &AppState,
+    launch: Option<LaunchOptions>,
+) -> anyhow::Result<Value> {
+    if state.config.mock.is_some() && launch.is_none() {
+        let mut session = state.session.lock().await;
+        connect_debugserver(&mut session, state.config.debugserver_port).await?;
+        return Ok(json!({ "ok": true }));
+    }
+    let bridge = state
+        .bridge
+        .as_ref()
+        .ok_or_else(|| not_configured("restart/launch requires --manage-bridge"))?;
+    let mut controller = bridge.lock().await;
+    controller.set_attach(None)?;
+    match launch {
+        Some(launch) => {
+            controller.set_launch(launch);
+            controller.ensure_running().await?;
+        }
+        None => controller.restart().await?,
+    }
+    drop(controller);
+    let mut session = state.session.lock().await;
+    connect_debugserver(&mut session, state.config.debugserver_port).await?;
+    Ok(json!({ "ok": true }))
+}
+
+/// Restarts the bridge attached to a running process and reconnects
+/// debugserver; the module list is refreshed so the slide matches the
+/// process as it is already loaded.
+async fn handle_attach_request(
+    state: &AppState,
+    pid: Option<i64>,
+    name: Option<String>,
+) -> anyhow::Result<Value> {
+    let target = match (pid, name) {
+        (Some(pid), None) => AttachTarget::Pid(pid),
+        (None, Some(name)) if !name.is_empty() => AttachTarget::Name(name),
+        _ => {
+            return Err(DebugSessionError::InvalidArgument(
+                "attach needs exactly one of `pid` or `name`".into(),
+            )
+            .into())
+        }
+    };
+    let bridge = state
+        .bridge
+        .as_ref()
+        .ok_or_else(|| not_configured("attach requires --manage-bridge"))?;
+    let mut controller = bridge.lock().await;
+    controller.set_attach(Some(target.clone()))?;
+    controller.restart().await?;
+    drop(controller);
+    let mut session = state.session.lock().await;
+    connect_debugserver(&mut session, state.config.debugserver_port).await?;
+    let mut body = json!({ "ok": true, "attached": target });
+    if let Err(err) = session.modules() {
+        body["modulesError"] = json!(err.to_string());
+    }
+    Ok(body)
+}
+
+/// [`DebugSession::connect_debugserver`] for async handlers, which hold the
+/// session lock: the backoff between attempts sleeps on the runtime instead of
+/// blocking one of its worker threads.
+async fn connect_debugserver(session: &mut DebugSession, port: u16) -> anyhow::Result<()> {
+    let mut attempt = 1;
+    while let Some(backoff) = session.connect_attempt(port, attempt)? {
+        tokio::time::sleep(backoff).await;
+        attempt += 1;
+    }
+    Ok(())
+}
+
+/// Runs [`prewarm`] in the background for `--prewarm`. The session is taken
+/// before this returns, so commands that arrive first queue behind the warm-up.
+async fn spawn_prewarm(state: &AppState) -> tokio::task::JoinHandle<()> {
+    let mut session = state.session.clone().lock_owned().await;
+    let state = state.clone();
+    tokio::spawn(async move {
+        match prewarm(&state, &mut session).await {
+            Ok(report) => log::info!("prewarm finished: {report}"),
+            Err(err) => log::warn!("prewarm failed: {err}"),
+        }
+    })
+}
+
+/// Brings up whatever is down of the bridge (which launches the app
+/// suspended) and the debugserver connection, so later commands start warm.
+async fn prewarm(state: &AppState, session: &mut DebugSession) -> anyhow::Result<Value> {
+    let started = Instant::now();
+    let already_warm = session.is_connected();
+    if !already_warm {
+        if let Some(bridge) = &state.bridge {
+            bridge.lock().await.ensure_running().await?;
+        }
+        connect_debugserver(session, state.config.debugserver_port).await?;
+    }
+    Ok(json!({
+        "ok": true,
+        "alreadyWarm": already_warm,
+        "elapsedMs": started.elapsed().as_millis() as u64,
+    }))
+}
+
+/// Kills the inferior over gdb-remote and stops the managed bridge. With
+/// `devicectl`, the app is also terminated on the device, which still works
+/// when the debugserver connection is already gone.
+async fn handle_terminate_request(state: &AppState, devicectl: bool) -> anyhow::Result<Value> {
+    let mut body = json!({ "ok": true });
+    match state.session.lock().await.terminate() {
+        Ok(exit) => body["exit"] = json!(exit),
+        Err(err) if devicectl => body["killError"] = json!(err.to_string()),
+        Err(err) => return Err(err.into()),
+    }
+    if devicectl {
+        body["devicectlPid"] = json!(terminate_with_devicectl(&state.config).await?);
+    }
+    if let Some(bridge) = &state.bridge {
+        bridge.lock().await.stop_current().await?;
+    }
+    Ok(body)
+}
+
+/// Runs `devicectl device process terminate` for the pid in the bridge state file.
+async fn terminate_with_devicectl(config: &Config) -> anyhow::Result<i64> {
+    let path = config
+        .state_file
+        .clone()
+        .unwrap_or_else(|| PathBuf::from(".zed/ios-llm-state.json"));
+    let text = std::fs::read_to_string(&path)
+        .with_context(|| format!("failed to read bridge state {}", path.display()))?;
+    let recorded: Value = serde_json::from_str(&text)
+        .with_context(|| format!("invalid bridge state {}", path.display()))?;
+    let pid = recorded
+        .get("pid")
+        .and_then(Value::as_i64)
+        .ok_or_else(|| anyhow::anyhow!("{} does not record a pid", path.display()))?;
+    let device = config
+        .device
+        .clone()
+        .or_else(|| {
+            recorded
+                .get("device")
+                .and_then(Value::as_str)
+                .map(str::to_string)
+        })
+        .ok_or_else(|| anyhow::anyhow!("--device required to terminate via devicectl"))?;
+    let mut cmd = TokioCommand::new(&config.devicectl_bin);
+    if !config.devicectl_subcommand.is_empty() {
+        cmd.arg(&config.devicectl_subcommand);
+    }
+    cmd.args([
+        "device",
+        "process",
+        "terminate",
+        "--device",
+        &device,
+        "--pid",
+    ]);
+    cmd.arg(pid.to_string());
+    let output = cmd
+        .output()
+        .await
+        .with_context(|| format!("failed to run {} process terminate", config.devicectl_bin))?;
+    if !output.status.success() {
+        bail!(
+            "devicectl terminate failed ({}): {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(pid)
+}
+
+async fn handle_build_request(state: &AppState) -> anyhow::Result<Value> {
+    let runner = state
+        .build_runner
+        .as_ref()
+        .ok_or_else(|| not_configured("build command not configured"))?;
+    let output = runner.run().await?;
+    let log = format!(
+        "$ {}\n--- stdout ---\n{}\n--- stderr ---\n{}",
+        runner.command.join(" "),
+        output.stdout,
+        output.stderr
+    );
+    let artifact = state
+        .artifacts
+        .add("build-log", "build.log", log.as_bytes())?;
+    Ok(json!({
+        "ok": output.success,
+        "exitCode": output.exit_code,
+        "stdout": output.stdout,
+        "stderr": output.stderr,
+        "artifact": artifact.id,
+    }))
+}
+
+async fn list_artifacts(
+    Scoped(state): Scoped,
+    tenant: Option<Extension<Tenant>>,
+) -> (StatusCode, Json<Value>) {
+    if let Err(rejection) = check_session_access(&state, tenant.as_deref()).await {
+        return rejection;
+    }
+    (
+        StatusCode::OK,
+        Json(json!({ "ok": true, "artifacts": state.artifacts.list() })),
+    )
+}
+
+#[derive(Debug, Deserialize)]
+struct ArtifactPath {
+    id: ArtifactId,
+}
+
+async fn download_artifact(
+    Scoped(state): Scoped,
+    tenant: Option<Extension<Tenant>>,
+    RoutePath(ArtifactPath { id }): RoutePath<ArtifactPath>,
+) -> Response {
+    if let Err(rejection) = check_session_access(&state, tenant.as_deref()).await {
+        return rejection.into_response();
+    }
+    let Some(artifact) = state.artifacts.get(id) else {
+        return (
+            StatusCode::NOT_FOUND,
+            api_error("ARTIFACT_NOT_FOUND", format!("no artifact {id}")),
+        )
+            .into_response();
+    };
+    match std::fs::read(&artifact.path) {
+        Ok(bytes) => (
+            [
+                (
+                    header::CONTENT_TYPE,
+                    content_type(&artifact.name).to_string(),
+                ),
+                (
+                    header::CONTENT_DISPOSITION,
+                    format!("attachment; filename=\"{}\"", artifact.name),
+                ),
+            ],
+            bytes,
+        )
+            .into_response(),
+        Err(err) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            api_error("INTERNAL", format!("failed to read artifact {id}: {err}")),
+        )
+            .into_response(),
+    }
+}
+
+fn content_type(name: &str) -> &'static str {
+    match Path::new(name).extension().and_then(|ext| ext.to_str()) {
+        Some("json") => "application/json",
+        Some("log" | "txt" | "ips" | "crash") => "text/plain; charset=utf-8",
+        Some("png") => "image/png",
+        _ => "application/octet-stream",
+    }
+}
+
+async fn stream_logs(Scoped(state): Scoped, tenant: Option<Extension<Tenant>>) -> Response {
+    if let Err(rejection) = check_session_access(&state, tenant.as_deref()).await {
+        return rejection.into_response();
+    }
+    let mut receiver = state.log_tx.subscribe();
+    let (tx, rx) = mpsc::unbounded_channel();
+    let redactor = state.redactor.clone();
+    tokio::spawn(async move {
+        while let Ok(line) = receiver.recv().await {
+            if tx.send(redactor.redact_text(&line).into_owned()).is_err() {
+                break;
+            }
+        }
+    });
+    Sse::new(LogSseStream { receiver: rx })
+        .keep_alive(KeepAlive::new().interval(Duration::from_secs(5)))
+        .into_response()
+}
+
+/// Target events (stops, thread and image changes, exits) as JSON, one
+/// per SSE `data:` line.
+async fn stream_events(Scoped(state): Scoped, tenant: Option<Extension<Tenant>>) -> Response {
+    if let Err(rejection) = check_session_access(&state, tenant.as_deref()).await {
+        return rejection.into_response();
+    }
+    let mut receiver = state.event_tx.subscribe();
+    let (tx, rx) = mpsc::unbounded_channel();
+    let redactor = state.redactor.clone();
+    tokio::spawn(async move {
+        while let Ok(mut event) = receiver.recv().await {
+            redactor.apply(&mut event);
+            if tx.send(event.to_string()).is_err() {
+                break;
+            }
+        }
+    });
+    Sse::new(LogSseStream { receiver: rx })
+        .keep_alive(KeepAlive::new().interval(Duration::from_secs(5)))
+        .into_response()
+}
+
+async fn spawn_log_stream(
+    config: &Config,
+    log_tx: broadcast::Sender<String>,
+) -> anyhow::Result<JoinHandle<()>> {
+    let device = config
+        .device
+        .clone()
+        .ok_or_else(|| anyhow::anyhow!("--device required for log streaming"))?;
+    let bin = config.devicectl_bin.clone();
+    let subcommand = config.devicectl_subcommand.clone();
+    Ok(tokio::spawn(async move {
+        if let Err(err) = run_log_stream(bin, subcommand, device, log_tx.clone()).await {
+            let _ = log_tx.send(format!("log stream exited: {err}"));
+        }
+    }))
+}
+
+async fn run_log_stream(
+    bin: String,
+    subcommand: String,
+    device: String,
+    log_tx: broadcast::Sender<String>,
+) -> anyhow::Result<()> {
+    let mut cmd = TokioCommand::new(&bin);
+    if !subcommand.is_empty() {
+        cmd.arg(&subcommand);
+    }
+    cmd.args(["device", "log", "stream", "--device", &device]);
+    cmd.stdout(Stdio::piped());
+    cmd.stderr(Stdio::piped());
+    cmd.kill_on_drop(true);
+    let mut child = cmd
+        .spawn()
+        .with_context(|| format!("failed to spawn {bin} {subcommand} log stream"))?;
+    if let Some(stdout) = child.stdout.take() {
+        spawn_log_task(stdout, log_tx.clone(), "log");
+    }
+    if let Some(stderr) = child.stderr.take() {
+        spawn_log_task(stderr, log_tx.clone(), "log-err");
+    }
+    let status = child.wait().await?;
+    let _ = log_tx.send(format!("log stream status: {status}"));
+    Ok(())
+}
+
+fn spawn_log_task<R>(
+    reader: R,
+    log_tx: broadcast::Sender<String>,
+    tag: &'static str,
+) -> JoinHandle<()>
+where
+    R: AsyncRead + Send + Unpin + 'static,
+{
+    tokio::spawn(async move {
+        let mut lines = BufReader::new(reader).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            let _ = log_tx.send(format!("[{tag}] {line}"));
+        }
+    })
+}
+
+/// Running process the managed bridge attaches to instead of launching.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum AttachTarget {
+    Pid(i64),
+    Name(String),
+}
+
+/// argv and environment for the launched app.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct LaunchOptions {
+    args: Vec<String>,
+    env: BTreeMap<String, String>,
+}
+
+struct BridgeController {
+    bin: String,
+    args: Vec<String>,
+    bundle_id: Option<String>,
+    app_bundle: Option<PathBuf>,
+    attach: Option<AttachTarget>,
+    launch: LaunchOptions,
+    port: u16,
+    log_tx: broadcast::Sender<String>,
+    handle: Option<BridgeChild>,
+    /// The running bridge was started in a different launch/attach mode.
+    stale: bool,
+    faults: Option<Arc<FaultInjector>>,
+}
+
+struct BridgeChild {
+    child: tokio::process::Child,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl BridgeController {
+    fn new(
+        config: &Config,
+        log_tx: broadcast::Sender<String>,
+        faults: Option<Arc<FaultInjector>>,
+    ) -> anyhow::Result<Self> {
+        let device = config
+            .device
+            .clone()
+            .ok_or_else(|| anyhow::anyhow!("--device is required when managing the bridge"))?;
+        if config.bundle_id.is_none() && config.attach.is_none() {
+            bail!(
+                "--bundle-id (or --attach-pid/--attach-name) is required when managing the bridge"
+            );
+        }
+        let mut args = config.ios_llm_devicectl_args.clone();
+        args.push("--device".into());
+        args.push(device);
+        args.push("--listen-port".into());
+        args.push(config.debugserver_port.to_string());
+        if let Some(state) = &config.state_file {
+            args.push("--state-file".into());
+            args.push(state.display().to_string());
+        }
+        Ok(Self {
+            bin: config.ios_llm_devicectl.clone(),
+            args,
+            bundle_id: config.bundle_id.clone(),
+            app_bundle: config.app_bundle.clone(),
+            attach: config.attach.clone(),
+            launch: config.launch.clone(),
+            port: config.debugserver_port,
+            log_tx,
+            handle: None,
+            stale: false,
+            faults,
+        })
+    }
+
+    /// Switches between attaching to `attach` and launching the bundle; takes
+    /// effect on the next (re)start.
+    fn set_attach(&mut self, attach: Option<AttachTarget>) -> anyhow::Result<()> {
+        if attach.is_none() && self.bundle_id.is_none() {
+            return Err(not_configured("launching requires --bundle-id"));
+        }
+        if attach != self.attach {
+            self.attach = attach;
+            // A bridge started in the other mode has to be replaced.
+            self.stale = self.handle.is_some();
+        }
+        Ok(())
+    }
+
+    /// Sets the launched app's argv and environment; takes effect on the
+    /// next (re)start.
+    fn set_launch(&mut self, launch: LaunchOptions) {
+        if launch != self.launch {
+            self.launch = launch;
+            self.stale = self.handle.is_some() && self.attach.is_none();
+        }
+    }
+
+    fn spawn_args(&self) -> Vec<String> {
+        let mut args = self.args.clone();
+        match (&self.attach, &self.bundle_id) {
+            (Some(AttachTarget::Pid(pid)), _) => {
+                args.push("--attach-pid".into());
+                args.push(pid.to_string());
+            }
+            (Some(AttachTarget::Name(name)), _) => {
+                args.push("--attach-name".into());
+                args.push(name.clone());
+            }
+            (None, Some(bundle_id)) => {
+                args.push("--bundle-id".into());
+                args.push(bundle_id.clone());
+                if let Some(app_bundle) = &self.app_bundle {
+                    args.push("--install-app".into());
+                    args.push(app_bundle.display().to_string());
+                }
+                for (key, value) in &self.launch.env {
+                    args.push("--app-env".into());
+                    args.push(format!("{key}={value}"));
+                }
+                // `=` keeps app arguments that start with `-` from being read
+                // as bridge flags.
+                args.extend(
+                    self.launch
+                        .args
+                        .iter()
+                        .map(|arg| format!("--app-arg={arg}")),
+                );
+            }
+            (None, None) => {}
+        }
+        args
+    }
+
+    async fn ensure_running(&mut self) -> anyhow::Result<()> {
+        if self.stale {
+            self.stop_current().await?;
+        }
+        if self.handle.is_none() {
+            self.spawn_child().await?;
+        }
+        Ok(())
+    }
+
+    async fn restart(&mut self) -> anyhow::Result<()> {
+        self.stop_current().await?;
+        self.spawn_child().await
+    }
+
+    async fn spawn_child(&mut self) -> anyhow::Result<()> {
+        if self
+            .faults
+            .as_ref()
+            .is_some_and(|faults| faults.take_devicectl_failure())
+        {
+            bail!("injected devicectl failure");
+        }
+        let mut cmd = TokioCommand::new(&self.bin);
+        cmd.args(self.spawn_args());
+        cmd.stdout(Stdio::piped());
+        cmd.stderr(Stdio::piped());
+        cmd.kill_on_drop(true);
+        let mut child = cmd
+            .spawn()
+            .context("failed to spawn ios-llm-devicectl bridge")?;
+        let mut tasks = Vec::new();
+        if let Some(stdout) = child.stdout.take() {
+            tasks.push(spawn_log_task(stdout, self.log_tx.clone(), "bridge"));
+        }
+        if let Some(stderr) = child.stderr.take() {
+            tasks.push(spawn_log_task(stderr, self.log_tx.clone(), "bridge-err"));
+        }
+        wait_for_port(self.port).await?;
+        self.handle = Some(BridgeChild { child, tasks });
+        Ok(())
+    }
+
+    async fn stop_current(&mut self) -> anyhow::Result<()> {
+        self.stale = false;
+        if let Some(mut handle) = self.handle.take() {
+            if handle.child.id().is_some() {
+                let _ = handle.child.start_kill();
+                let _ = handle.child.wait().await;
+            }
+            for task in handle.tasks {
+                task.abort();
+            }
+        }
+        Ok(())
+    }
+}
+
+async fn wait_for_port(port: u16) -> anyhow::Result<()> {
+    let mut attempts = 0;
+    loop {
+        match TcpStream::connect(("127.0.0.1", port)).await {
+            Ok(stream) => {
+                drop(stream);
+                return Ok(());
+            }
+            Err(err) => {
+                attempts += 1;
+                if attempts > 50 {
+                    return Err(anyhow::anyhow!(
+                        "timed out waiting for bridge on port {port}: {err}"
+                    ));
+                }
+                sleep(Duration::from_millis(100)).await;
+            }
+        }
+    }
+}
+
+struct BuildRunner {
+    command: Vec<String>,
+}
+
+impl BuildRunner {
+    fn new(command: Vec<String>) -> Self {
+        Self { command }
+    }
+
+    async fn run(&self) -> anyhow::Result<BuildResult> {
+        let mut parts = self.command.iter();
+        let program = parts
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("build command requires at least one argument"))?;
+        let mut cmd = TokioCommand::new(program);
+        for arg in parts {
+            cmd.arg(arg);
+        }
+        cmd.stdout(Stdio::piped());
+        cmd.stderr(Stdio::piped());
+        let output = cmd
+            .spawn()
+            .context("failed to spawn build command")?
+            .wait_with_output()
+            .await
+            .context("failed to run build command")?;
+        Ok(BuildResult {
+            success: output.status.success(),
+            exit_code: output.status.code().unwrap_or_default(),
+            stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        })
+    }
+}
+
+struct BuildResult {
+    success: bool,
+    exit_code: i32,
+    stdout: String,
+    stderr: String,
+}
+
+struct LogSseStream {
+    receiver: mpsc::UnboundedReceiver<String>,
+}
+
+impl Stream for LogSseStream {
+    type Item = Result<Event, std::convert::Infallible>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<Option<Self::Item>> {
+        match Pin::new(&mut self.receiver).poll_recv(cx) {
+            Poll::Ready(Some(line)) => Poll::Ready(Some(Ok(Event::default().data(line)))),
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// Redacts a command's body, or its error message, before it is returned or
+/// kept in the timeline.
+fn redact_response(
+    redactor: &Redactor,
+    response: Result<Value, DebugSessionError>,
+) -> Result<Value, DebugSessionError> {
+    match response {
+        Ok(mut body) => {
+            redactor.apply(&mut body);
+            Ok(body)
+        }
+        Err(err) => {
+            let message = err.to_string();
+            match redactor.redact_text(&message) {
+                Cow::Borrowed(_) => Err(err),
+                Cow::Owned(redacted) => Err(DebugSessionError::Coded {
+                    code: err.code(),
+                    message: redacted,
+                    hint: err.hint(),
+                }),
+            }
+        }
+    }
+}
+
+/// Applies the request's value `format` over the session default.
+/// Frames `context` returns by default.
+const DEFAULT_CONTEXT_FRAMES: usize = 5;
+/// `/logs` lines `context` returns by default.
+const DEFAULT_CONTEXT_LOG_LINES: usize = 20;
+
+/// Everything `context` returns but the logs: the last stop, the top
+/// `frames` frames of the selected thread with `context` lines of source
+/// around each, the top frame's locals and the watch values.
+fn context_bundle(
+    session: &mut DebugSession,
+    frames: Option<usize>,
+    context: Option<usize>,
+) -> Value {
+    let frames: Vec<Value> = session
+        .stacktrace()
+        .into_iter()
+        .take(frames.unwrap_or(DEFAULT_CONTEXT_FRAMES))
+        .map(|frame| {
+            let source = (frame.file != "<unknown>")
+                .then(|| {
+                    session
+                        .source(Some(&frame.file), Some(frame.line), context)
+                        .ok()
+                })
+                .flatten();
+            let mut value = json!(frame);
+            value["source"] = json!(source);
+            value
+        })
+        .collect();
+    let (watch, watch_error) = match session.evaluate_watch_expressions() {
+        Ok(values) => (values, None),
+        Err(err) => (Vec::new(), Some(err.to_string())),
+    };
+    let mut body = json!({
+        "ok": true,
+        "stop": session.last_stop(),
+        "frames": frames,
+        "locals": session.locals(),
+        "watch": watch,
+    });
+    if let Some(error) = watch_error {
+        body["watch_error"] = json!(error);
+    }
+    formatted(body, None, session.value_format())
+}
+
+fn formatted(mut body: Value, format: Option<ValueFormat>, default: ValueFormat) -> Value {
+    value_format::apply(&mut body, &format.unwrap_or_default().or(default));
+    body
+}
+
+fn execute_command(
+    session: &mut DebugSession,
+    command: LlmCommand,
+) -> Result<Value, DebugSessionError> {
+    let default_format = session.value_format();
+    match command {
+        LlmCommand::Stacktrace => Ok(json!({ "ok": true, "stacktrace": session.stacktrace() })),
+        LlmCommand::Threads => Ok(json!({ "ok": true, "threads": session.threads() })),
+        LlmCommand::Source {
+            file,
+            line,
+            context,
+        } => session
+            .source(file.as_deref(), line, context)
+            .map(|source| json!({ "ok": true, "source": source })),
+        LlmCommand::DetectDeadlock => {
+            Ok(json!({ "ok": true, "deadlock": session.detect_deadlock() }))
+        }
+        LlmCommand::StackDiff => session
+            .stack_diff()
+            .map(|diff| json!({ "ok": true, "diff": diff })),
+        LlmCommand::AllStacktraces {
+            max_frames,
+            thread_max_frames,
+        } => {
+            let thread_max_frames = thread_max_frames
+                .into_iter()
+                .map(|(thread, limit)| match thread.parse::<i64>() {
+                    Ok(thread) => Ok((thread, limit)),
+                    Err(_) => Err(DebugSessionError::InvalidArgument(format!(
+                        "thread_max_frames keys must be thread ids, got `{thread}`"
+                    ))),
+                })
+                .collect::<Result<HashMap<_, _>, _>>()?;
+            let all = session.all_stacktraces(max_frames, &thread_max_frames);
+            Ok(json!({ "ok": true, "frames": all.frames, "threads": all.threads }))
+        }
+        LlmCommand::Continue => session.continue_execution().map(|stop| {
+            if let Some(stop) = stop {
+                json!({ "ok": true, "stop": stop })
+            } else {
+                json!({ "ok": true })
+            }
+        }),
+        LlmCommand::Next => session.next().map(|stop| match stop {
+            Some(stop) => json!({ "ok": true, "stop": stop }),
+            None => json!({ "ok": true }),
+        }),
+        LlmCommand::StepIn => session.step_in().map(|stop| match stop {
+            Some(stop) => json!({ "ok": true, "stop": stop }),
+            None => json!({ "ok": true }),
+        }),
+        LlmCommand::StepOut => session.step_out().map(|stop| match stop {
+            Some(stop) => json!({ "ok": true, "stop": stop }),
+            None => json!({ "ok": true }),
+        }),
+        LlmCommand::RunToLine { file, line } => {
+            session.run_to_line(&file, line).map(|stop| match stop {
+                Some(stop) => json!({ "ok": true, "stop": stop }),
+                None => json!({ "ok": true }),
+            })
+        }
+        LlmCommand::ContinueUntilChanged {
+            expression,
+            mode,
+            max_iterations,
+        } => session
+            .continue_until_changed(&expression, mode, max_iterations)
+            .map(|change| {
+                let mut body = json!(change);
+                body["ok"] = json!(true);
+                body
+            }),
+        LlmCommand::Signal { signal, thread_id } => {
+            let number = debug_session::parse_signal(&signal).ok_or_else(|| {
+                DebugSessionError::InvalidArgument(format!(
+                    "unknown signal `{signal}` (expected a name like SIGUSR1 or 1-31)"
+                ))
+            })?;
+            session
+                .send_signal(number, thread_id)
+                .map(|stop| match stop {
+                    Some(stop) => json!({ "ok": true, "signal": number, "stop": stop }),
+                    None => json!({ "ok": true, "signal": number }),
+                })
+        }
+        LlmCommand::SetBreakpoint {
+            file,
+            line,
+            path_match,
+            temporary,
+        } => if temporary {
+            session.set_temporary_breakpoint(&file, line, path_match)
+        } else {
+            session.set_breakpoint(&file, line, path_match)
+        }
+        .map(|bp| {
+            json!({
+                "ok": true,
+                "breakpoint_id": bp.id,
+                "resolved_file": bp.resolved_file,
+                "requestedLine": bp.requested_line,
+                "actualLine": bp.line,
+                "match": bp.matched,
+                "temporary": bp.temporary,
+                "verified": bp.verified,
+            })
+        }),
+        LlmCommand::SetLogpoint {
+            file,
+            line,
+            message,
+        } => session
+            .set_logpoint(&file, line, &message)
+            .map(|bp| json!({ "ok": true, "breakpoint_id": bp.id, "verified": bp.verified })),
+        LlmCommand::ExportBreakpoints { format } => {
+            let breakpoints = session.export_breakpoints();
+            Ok(match format.unwrap_or_default() {
+                BreakpointFormat::Json => {
+                    json!({ "ok": true, "format": "json", "breakpoints": breakpoints })
+                }
+                BreakpointFormat::Lldb => json!({
+                    "ok": true,
+                    "format": "lldb",
+                    "breakpoints": breakpoint_file::to_lldb(&breakpoints),
+                }),
+            })
+        }
+        LlmCommand::ImportBreakpoints { path, contents } => {
+            let text = match (path, contents) {
+                (Some(path), None) => std::fs::read_to_string(&path).map_err(|err| {
+                    DebugSessionError::InvalidArgument(format!(
+                        "failed to read {}: {err}",
+                        path.display()
+                    ))
+                })?,
+                (None, Some(contents)) => contents,
+                _ => {
+                    return Err(DebugSessionError::InvalidArgument(
+                        "pass exactly one of `path` or `contents`".into(),
+                    ))
+                }
+            };
+            let parsed =
+                breakpoint_file::parse(&text).map_err(DebugSessionError::InvalidArgument)?;
+            let (set, failed) = session.import_breakpoints(&parsed.breakpoints);
+            let set: Vec<Value> = set
+                .iter()
+                .map(|bp| {
+                    json!({
+                        "breakpoint_id": bp.id,
+                        "file": bp.file,
+                        "line": bp.line,
+                        "resolved_file": bp.resolved_file,
+                    })
+                })
+                .collect();
+            Ok(json!({
+                "ok": true,
+                "breakpoints": set,
+                "failed": failed,
+                "skipped": parsed.skipped,
+            }))
+        }
+        LlmCommand::Locals { format } => Ok(formatted(
+            json!({ "ok": true, "locals": session.locals() }),
+            format,
+            default_format,
+        )),
+        LlmCommand::Scopes => Ok(json!({ "ok": true, "scopes": session.scopes() })),
+        LlmCommand::Variables {
+            reference,
+            start,
+            count,
+            format,
+        } => {
+            let reference = reference.unwrap_or_else(|| session.locals_reference());
+            let (page, total) = session.variables_page(
+                reference,
+                ChildPage {
+                    start: start.unwrap_or(0),
+                    count: count.filter(|&count| count > 0),
+                },
+            );
+            Ok(formatted(
+                json!({ "ok": true, "variables": page, "totalCount": total }),
+                format,
+                default_format,
+            ))
+        }
+        LlmCommand::DumpVariable {
+            name,
+            reference,
+            depth,
+            format,
+        } => session
+            .dump_variable(name.as_deref(), reference, depth)
+            .map(|variable| {
+                formatted(
+                    json!({ "ok": true, "variable": variable }),
+                    format,
+                    default_format,
+                )
+            }),
+        LlmCommand::Snapshot {
+            name,
+            depth,
+            memory,
+        } => {
+            let ranges: Vec<(u64, usize)> = memory
+                .iter()
+                .map(|range| (range.address, range.length))
+                .collect();
+            session
+                .snapshot(name.as_deref(), depth, &ranges)
+                .map(|snapshot| json!({ "ok": true, "snapshot": snapshot }))
+        }
+        LlmCommand::DiffSnapshot { from, to } => session
+            .diff_snapshot(&from, to.as_deref())
+            .map(|diff| json!({ "ok": true, "diff": diff })),
+        LlmCommand::Evaluate {
+            expression,
+            language,
+            format,
+            timeout_ms,
+            allow_side_effects,
+        } => session
+            .evaluate_within(
+                &expression,
+                language,
+                timeout_ms.map(Duration::from_millis),
+                allow_side_effects,
+            )
+            .map(|result| {
+            formatted(
+                json!({
+                    "ok": true,
+                    "result": result.result,
+                    "type": result.ty,
+                    "variablesReference": result.variables_reference,
+                    "language": result.language,
+                }),
+                format,
+                default_format,
+            )
+        }),
+        LlmCommand::EvaluateSwift {
+            expression,
+            format,
+            timeout_ms,
+            allow_side_effects,
+        } => {
+            session
+                .evaluate_within(
+                    &expression,
+                    Some(Language::Swift),
+                    timeout_ms.map(Duration::from_millis),
+                    allow_side_effects,
+                )
+                .map(|result| {
+                formatted(
+                    json!({
+                        "ok": true,
+                        "result": result.result,
+                        "type": result.ty,
+                        "variablesReference": result.variables_reference,
+                        "language": result.language,
+                    }),
+                    format,
+                    default_format,
+                )
+            })
+        }
+        LlmCommand::SetVariable { name, value } => session
+            .set_variable(&name, &value)
+            .map(|variable| json!({ "ok": true, "variable": variable })),
+        LlmCommand::WatchExpression { expression, format } => {
+            session.add_watch_expression(&expression).map(|values| {
+                formatted(
+                    json!({ "ok": true, "watch": values }),
+                    format,
+                    default_format,
+                )
+            })
+        }
+        LlmCommand::SelectThread { thread_id } => {
+            session.select_thread(thread_id);
+            Ok(json!({ "ok": true, "threadId": thread_id }))
+        }
+        LlmCommand::SuspendThread { thread_id } => session
+            .suspend_thread(thread_id)
+            .map(|suspended| json!({ "ok": true, "suspended_threads": suspended })),
+        LlmCommand::ResumeThread { thread_id } => session
+            .resume_thread(thread_id)
+            .map(|suspended| json!({ "ok": true, "suspended_threads": suspended })),
+        LlmCommand::SelectFrame { frame_index } => session
+            .select_frame(frame_index)
+            .map(|frame| json!({ "ok": true, "frame": frame })),
+        LlmCommand::Disassemble {
+            address,
+            symbol,
+            count,
+        } => session
+            .disassemble(address, symbol.as_deref(), count)
+            .map(|disassembly| json!({ "ok": true, "disassembly": disassembly })),
+        LlmCommand::Symbolicate { addresses } => Ok(json!({
+            "ok": true,
+            "symbols": session.symbolicate(&addresses),
+        })),
+        LlmCommand::LookupSymbol { name } => session
+            .lookup_symbol(&name)
+            .map(|symbols| json!({ "ok": true, "symbols": symbols })),
+        LlmCommand::Modules => session
+            .modules()
+            .map(|modules| json!({ "ok": true, "modules": modules })),
+        LlmCommand::MemoryRegions { address } => {
+            session
+                .memory_regions(address)
+                .map(|(regions, truncated)| {
+                    json!({ "ok": true, "regions": regions, "truncated": truncated })
+                })
+        }
+        LlmCommand::HeapInfo { address } => session
+            .heap_info(address)
+            .map(|heap| json!({ "ok": true, "heap": heap })),
+        LlmCommand::Tasks { addresses } => session
+            .tasks(&addresses)
+            .map(|tasks| json!({ "ok": true, "tasks": tasks })),
+        LlmCommand::ObjcClasses { filter } => session
+            .objc_classes(filter.as_deref())
+            .map(|(classes, truncated)| {
+                json!({ "ok": true, "classes": classes, "truncated": truncated })
+            }),
+        LlmCommand::ObjcClass { name, address } => session
+            .objc_class(name.as_deref(), address)
+            .map(|class| json!({ "ok": true, "class": class })),
+        LlmCommand::Actors { addresses } => session
+            .actors(&addresses)
+            .map(|actors| json!({ "ok": true, "actors": actors })),
+        LlmCommand::Describe {
+            address,
+            expression,
+            timeout_ms,
+        } => session
+            .describe(
+                address,
+                expression.as_deref(),
+                timeout_ms.map(Duration::from_millis),
+            )
+            .map(|object| json!({ "ok": true, "object": object })),
+        LlmCommand::Registers => session
+            .registers()
+            .map(|registers| json!({ "ok": true, "registers": registers })),
+        LlmCommand::SetRegister { name, value } => session
+            .set_register(&name, value)
+            .map(|_| json!({ "ok": true, "register": name, "value": format!("0x{value:x}") })),
+        LlmCommand::SetVerbosity { level } => {
+            session.set_verbosity(level);
+            Ok(json!({ "ok": true, "verbosity": level }))
+        }
+        LlmCommand::SetValueFormat {
+            integers,
+            float_precision,
+            raw,
+        } => {
+            let format = ValueFormat {
+                integers,
+                float_precision,
+                raw,
+            };
+            session.set_value_format(format);
+            Ok(json!({ "ok": true, "format": format }))
+        }
+        LlmCommand::MainThreadChecker { enabled } => session
+            .set_main_thread_checker(enabled)
+            .map(|hook| {
+                json!({
+                    "ok": true,
+                    "enabled": hook.is_some(),
+                    "hook": hook.map(|hook| format!("0x{hook:x}")),
+                })
+            }),
+        LlmCommand::TraceFunction { name, enabled } => session
+            .trace_function(&name, enabled)
+            .map(|symbols| json!({ "ok": true, "enabled": enabled, "functions": symbols })),
+        LlmCommand::ReadMemory { address, length } => session
+            .read_memory(address, length)
+            .map(|memory| json!({ "ok": true, "memory": memory })),
+        LlmCommand::WriteMemory {
+            address,
+            data,
+            encoding,
+            confirm,
+        } => {
+            let bytes = debug_session::decode_bytes(&data, &encoding)?;
+            session
+                .write_memory(address, &bytes, confirm)
+                .map(|written| json!({ "ok": true, "bytesWritten": written }))
+        }
+        LlmCommand::WriteStdin { data, encoding } => {
+            let bytes = match encoding.as_str() {
+                "text" => data.into_bytes(),
+                "hex" | "base64" => debug_session::decode_bytes(&data, &encoding)?,
+                other => {
+                    return Err(DebugSessionError::InvalidArgument(format!(
+                        "unknown encoding `{other}`; expected `text`, `hex` or `base64`"
+                    )))
+                }
+            };
+            session
+                .write_stdin(&bytes)
+                .map(|written| json!({ "ok": true, "bytesWritten": written }))
+        }
+        LlmCommand::Disconnect => {
+            session.disconnect()?;
+            Ok(json!({ "ok": true }))
+        }
+        LlmCommand::Detach => {
+            session.detach()?;
+            Ok(json!({ "ok": true, "detached": true }))
+        }
+        LlmCommand::Restart
+        | LlmCommand::Launch { .. }
+        | LlmCommand::Build
+        | LlmCommand::Prewarm
+        | LlmCommand::Context { .. }
+        | LlmCommand::Attach { .. }
+        | LlmCommand::Terminate { .. }
+        | LlmCommand::Cancel
+        | LlmCommand::Batch { .. } => {
+            unreachable!("managed by handle_command")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use addr2line::Loader;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use swiftscope::{
+        backend::Backend,
+        protocol_trace::Direction,
+        symbols::{Image, SymbolContext},
+    };
+
+    fn sample_session() -> DebugSession {
+        let exe = std::env::current_exe().expect("current exe");
+        let loader = Loader::new(&exe).expect("loader");
+        let image = Image {
+            name: "test".into(),
+            path: exe,
+            uuid: None,
+            dsym: None,
+            slice: None,
+            vmaddr_text: 0,
+            slide: 0,
+            dwarf: loader,
+        };
+        let backend = Backend::new_for_testing(SymbolContext::for_testing(image));
+        DebugSession::new(backend)
+    }
+
+    #[test]
+    fn stacktrace_returns_frames() {
+        let mut session = sample_session();
+        let value = execute_command(&mut session, LlmCommand::Stacktrace).unwrap();
+        assert!(value.get("stacktrace").is_some());
+    }
+
+    #[test]
+    fn variables_defaults_to_locals_reference() {
+        let mut session = sample_session();
+        let value = execute_command(
+            &mut session,
+            LlmCommand::Variables {
+                reference: None,
+                start: None,
+                count: None,
+                format: None,
+            },
+        )
+        .unwrap();
+        assert!(value.get("variables").is_some());
+    }
+
+    #[test]
+    fn variables_page_through_children() {
+        let mut session = sample_session();
+        let command: LlmCommand = serde_json::from_value(json!({
+            "action": "variables",
+            "variablesReference": 9,
+            "start": 1,
+            "count": 5
+        }))
+        .unwrap();
+        let value = execute_command(&mut session, command).unwrap();
+        assert_eq!(value["totalCount"], 2);
+        assert_eq!(value["variables"].as_array().unwrap().len(), 1);
+        assert_eq!(value["variables"][0]["name"], "counter");
+
+        let command: LlmCommand = serde_json::from_value(
+            json!({ "action": "variables", "variablesReference": 9, "start": 5 }),
+        )
+        .unwrap();
+        let value = execute_command(&mut session, command).unwrap();
+        assert_eq!(value["variables"], json!([]));
+        assert_eq!(value["totalCount"], 2);
+    }
+
+    #[test]
+    fn dump_variable_expands_locals() {
+        let mut session = sample_session();
+        let command: LlmCommand = serde_json::from_value(
+            json!({ "action": "dump_variable", "name": "counter", "depth": 2 }),
+        )
+        .unwrap();
+        let value = execute_command(&mut session, command).unwrap();
+        assert_eq!(value["variable"]["name"], "counter");
+        assert_eq!(value["variable"]["value"], "123");
+
+        let value = execute_command(
+            &mut session,
+            LlmCommand::DumpVariable {
+                name: None,
+                reference: Some(5),
+                depth: None,
+                format: None,
+            },
+        )
+        .unwrap();
+        assert_eq!(value["variable"]["children"][0]["value"], "value-5");
+
+        let err = execute_command(
+            &mut session,
+            LlmCommand::DumpVariable {
+                name: Some("missing".into()),
+                reference: None,
+                depth: None,
+                format: None,
+            },
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("no variable `missing`"), "{err}");
+    }
+
+    #[test]
+    fn evaluate_rejects_empty_input() {
+        let mut session = sample_session();
+        let err = execute_command(
+            &mut session,
+            LlmCommand::Evaluate {
+                expression: "".into(),
+                language: None,
+                format: None,
+                timeout_ms: None,
+                allow_side_effects: false,
+            },
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("not supported"));
+    }
+
+    #[tokio::test]
+    async fn batch_runs_commands_in_order_and_stops_at_errors() {
+        let state = sample_state(sample_config(4001));
+        let batch = |continue_on_error| {
+            json!({
+                "action": "batch",
+                "continue_on_error": continue_on_error,
+                "commands": [
+                    { "action": "stacktrace" },
+                    { "action": "stack_diff" },
+                    { "action": "threads" }
+                ]
+            })
+        };
+        assert!(serde_json::from_value::<LlmCommand>(batch(false))
+            .unwrap()
+            .is_inspection());
+        let (_, value) = run(&state, batch(false)).await;
+        assert_eq!(value["ok"], json!(false));
+        assert_eq!(value["skipped"], json!(1));
+        let results = value["results"].as_array().unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0]["action"], "stacktrace");
+        assert!(results[0]["stacktrace"].is_array());
+        assert_eq!(results[1]["action"], "stack_diff");
+        assert_eq!(results[1]["ok"], json!(false));
+        assert_eq!(results[1]["error"]["code"], "NOT_STOPPED");
+
+        let (_, value) = run(&state, batch(true)).await;
+        assert_eq!(value["results"][2]["action"], "threads");
+        assert_eq!(value["skipped"], json!(0));
+
+        let nested = json!({
+            "action": "batch",
+            "commands": [{ "action": "threads" }, { "action": "restart" }]
+        });
+        let (status, body) = run(&state, nested).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let message = body["error"]["message"].as_str().unwrap();
+        assert!(
+            message.contains("`restart` cannot run inside a batch"),
+            "{body}"
+        );
+    }
+
+    #[tokio::test]
+    async fn batched_commands_run_as_they_would_alone() {
+        let state = mock_state().await;
+        run(&state, json!({ "action": "prewarm" })).await;
+        let batch = json!({
+            "action": "batch",
+            "commands": [
+                { "action": "continue" },
+                { "action": "evaluate", "expression": "1 + 1", "timeout_ms": 5000 }
+            ]
+        });
+        let (status, body) = run(&state, batch).await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+        assert_eq!(body["results"][0]["stop"]["reason"], "breakpoint", "{body}");
+        assert_eq!(body["results"][1]["result"], "2", "{body}");
+        assert!(state.running.current().is_none());
+    }
+
+    #[test]
+    fn function_calls_need_allow_side_effects() {
+        let mut session = sample_session();
+        let call = |allow_side_effects| LlmCommand::Evaluate {
+            expression: r#"strlen("abc")"#.into(),
+            language: None,
+            format: None,
+            timeout_ms: None,
+            allow_side_effects,
+        };
+        let err = execute_command(&mut session, call(false)).unwrap_err();
+        assert!(err.to_string().contains("allow_side_effects"), "{err}");
+        let err = execute_command(&mut session, call(true)).unwrap_err();
+        assert!(
+            err.to_string().contains("no gdb-remote connection"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn evaluate_failing_past_its_deadline_times_out() {
+        let mut session = sample_session();
+        let command: LlmCommand = serde_json::from_value(json!({
+            "action": "evaluate",
+            "expression": "missing",
+            "timeout_ms": 0
+        }))
+        .unwrap();
+        let err = execute_command(&mut session, command).unwrap_err();
+        assert!(matches!(err, DebugSessionError::Timeout(0)), "{err}");
+        assert_eq!(err.to_string(), "evaluation timed out after 0 ms");
+
+        let command: LlmCommand = serde_json::from_value(
+            json!({ "action": "evaluate_swift", "expression": "counter", "timeout_ms": 0 }),
+        )
+        .unwrap();
+        let value = execute_command(&mut session, command).unwrap();
+        assert_eq!(value["result"], "123");
+    }
+
+    #[test]
+    fn evaluate_accepts_language_field() {
+        let cmd: LlmCommand = serde_json::from_value(json!({
+            "action": "evaluate",
+            "expression": "self->counter",
+            "language": "objc++",
+        }))
+        .unwrap();
+        assert!(matches!(
+            cmd,
+            LlmCommand::Evaluate {
+                language: Some(Language::ObjCPlusPlus),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn convenience_variables_persist_until_resume() {
+        let mut session = sample_session();
+        let declare = LlmCommand::Evaluate {
+            expression: "let $saved = counter".into(),
+            language: None,
+            format: None,
+            timeout_ms: None,
+            allow_side_effects: false,
+        };
+        let declared = execute_command(&mut session, declare).unwrap();
+        let recalled = execute_command(
+            &mut session,
+            LlmCommand::Evaluate {
+                expression: "$saved".into(),
+                language: None,
+                format: None,
+                timeout_ms: None,
+                allow_side_effects: false,
+            },
+        )
+        .unwrap();
+        assert_eq!(recalled.get("result"), declared.get("result"));
+        let bad = LlmCommand::Evaluate {
+            expression: "let saved = counter".into(),
+            language: None,
+            format: None,
+            timeout_ms: None,
+            allow_side_effects: false,
+        };
+        assert!(execute_command(&mut session, bad).is_err());
+    }
+
+    #[test]
+    fn value_format_applies_per_request_over_session_default() {
+        let mut session = sample_session();
+        let hex: LlmCommand = serde_json::from_value(json!({
+            "action": "evaluate",
+            "expression": "counter",
+            "format": { "integers": "hex" }
+        }))
+        .unwrap();
+        assert_eq!(
+            execute_command(&mut session, hex).unwrap()["result"],
+            "0x7b"
+        );
+
+        let set: LlmCommand =
+            serde_json::from_value(json!({ "action": "set_value_format", "integers": "hex" }))
+                .unwrap();
+        execute_command(&mut session, set).unwrap();
+        let locals = execute_command(&mut session, LlmCommand::Locals { format: None }).unwrap();
+        assert_eq!(locals["locals"][1]["value"], "0x7b");
+        let raw: LlmCommand = serde_json::from_value(json!({
+            "action": "locals",
+            "format": { "raw": true }
+        }))
+        .unwrap();
+        let locals = execute_command(&mut session, raw).unwrap();
+        assert_eq!(locals["locals"][1]["value"], "123");
+    }
+
+    #[test]
+    fn evaluate_reports_variables_reference() {
+        let mut session = sample_session();
+        let value = execute_command(
+            &mut session,
+            LlmCommand::Evaluate {
+                expression: "counter".into(),
+                language: None,
+                format: None,
+                timeout_ms: None,
+                allow_side_effects: false,
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            value.get("variablesReference").and_then(Value::as_i64),
+            Some(0)
+        );
+    }
+
+    #[test]
+    fn all_stacktraces_share_frames_and_trim() {
+        let mut session = sample_session();
+        // The sample frame may expand into inlined frames.
+        let depth = session.stacktrace().len();
+        let value = execute_command(
+            &mut session,
+            LlmCommand::AllStacktraces {
+                max_frames: None,
+                thread_max_frames: HashMap::new(),
+            },
+        )
+        .unwrap();
+        let threads = value.get("threads").and_then(Value::as_array).unwrap();
+        let frames = value.get("frames").and_then(Value::as_array).unwrap();
+        assert!(!threads.is_empty());
+        assert_eq!(threads[0]["frames"], json!((0..depth).collect::<Vec<_>>()));
+        assert_eq!(frames.len(), depth);
+
+        let value = execute_command(
+            &mut session,
+            LlmCommand::AllStacktraces {
+                max_frames: Some(0),
+                thread_max_frames: HashMap::new(),
+            },
+        )
+        .unwrap();
+        assert_eq!(value["threads"][0]["truncated"], json!(true));
+        assert_eq!(value["frames"], json!([]));
+
+        let command: LlmCommand = serde_json::from_value(json!({
+            "action": "stacktrace_all",
+            "max_frames": 0,
+            "thread_max_frames": { "1": 8 },
+        }))
+        .unwrap();
+        let value = execute_command(&mut session, command).unwrap();
+        assert_eq!(
+            value["threads"][0]["frames"],
+            json!((0..depth).collect::<Vec<_>>())
+        );
+        assert_eq!(value["threads"][0]["truncated"], json!(false));
+    }
+
+    #[test]
+    fn set_variable_accepts_numeric_values() {
+        let command: LlmCommand = serde_json::from_value(json!({
+            "action": "set_variable",
+            "name": "point.x",
+            "value": 5
+        }))
+        .unwrap();
+        assert!(matches!(
+            &command,
+            LlmCommand::SetVariable { name, value } if name == "point.x" && value == "5"
+        ));
+        let mut session = sample_session();
+        assert!(execute_command(&mut session, command).is_err());
+    }
+
+    #[test]
+    fn detect_deadlock_reports_running_threads() {
+        let mut session = sample_session();
+        let value = execute_command(&mut session, LlmCommand::DetectDeadlock).unwrap();
+        assert_eq!(value["deadlock"]["deadlocked"], json!(false));
+        assert_eq!(value["deadlock"]["threads"][0]["state"], json!("running"));
+    }
+
+    #[test]
+    fn memory_regions_require_debugserver() {
+        let mut session = sample_session();
+        let command: LlmCommand =
+            serde_json::from_value(json!({ "action": "memory_regions", "address": "0x1000" }))
+                .unwrap();
+        assert!(matches!(
+            command,
+            LlmCommand::MemoryRegions {
+                address: Some(0x1000)
+            }
+        ));
+        let err = execute_command(&mut session, command).unwrap_err();
+        assert!(
+            err.to_string().contains("no gdb-remote connection"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn runtime_inspection_commands_validate_their_arguments() {
+        let command: LlmCommand =
+            serde_json::from_value(json!({ "action": "tasks", "addresses": "0x4000" })).unwrap();
+        assert!(matches!(&command, LlmCommand::Tasks { addresses } if addresses == &[0x4000]));
+        assert!(command.is_inspection());
+        let command: LlmCommand = serde_json::from_value(json!({ "action": "tasks" })).unwrap();
+        assert!(matches!(&command, LlmCommand::Tasks { addresses } if addresses.is_empty()));
+        assert!(serde_json::from_value::<LlmCommand>(json!({ "action": "actors" })).is_err());
+
+        let err = execute_command(
+            &mut sample_session(),
+            LlmCommand::ObjcClass {
+                name: None,
+                address: None,
+            },
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("class `name`"), "{err}");
+
+        let mut session = sample_session();
+        let err = execute_command(&mut session, command).unwrap_err();
+        assert!(
+            err.to_string().contains("no gdb-remote connection"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn heap_info_reports_tagged_pointers_without_reading() {
+        let mut session = sample_session();
+        let command: LlmCommand = serde_json::from_value(
+            json!({ "action": "heap_info", "address": "0x8000000000000a27" }),
+        )
+        .unwrap();
+        let value = execute_command(&mut session, command).unwrap();
+        assert_eq!(value["heap"]["tagged_pointer"], json!(true));
+        assert!(value["heap"].get("region").is_none());
+
+        let err =
+            execute_command(&mut session, LlmCommand::HeapInfo { address: 0x1000 }).unwrap_err();
+        assert!(
+            err.to_string().contains("no gdb-remote connection"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn describe_needs_one_target_and_a_connection() {
+        let mut session = sample_session();
+        let describe = |address, expression: Option<&str>| LlmCommand::Describe {
+            address,
+            expression: expression.map(str::to_string),
+            timeout_ms: None,
+        };
+        let err = execute_command(&mut session, describe(None, None)).unwrap_err();
+        assert!(err.to_string().contains("either an address"), "{err}");
+        let err = execute_command(&mut session, describe(Some(0), None)).unwrap_err();
+        assert!(err.to_string().contains("null pointer"), "{err}");
+        let err = execute_command(&mut session, describe(Some(0x1000), None)).unwrap_err();
+        assert!(
+            err.to_string().contains("no gdb-remote connection"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn stack_diff_needs_two_stops() {
+        let mut session = sample_session();
+        let err = execute_command(&mut session, LlmCommand::StackDiff).unwrap_err();
+        assert!(err.to_string().contains("needs two stops"), "{err}");
+    }
+
+    #[test]
+    fn source_lists_lines_of_files_in_the_debug_info() {
+        let mut session = sample_session();
+        let value = execute_command(
+            &mut session,
+            LlmCommand::Source {
+                file: Some("src/bin/ios_llm_api.rs".into()),
+                line: Some(3),
+                context: Some(1),
+            },
+        )
+        .unwrap();
+        let lines = value["source"]["lines"].as_array().unwrap();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[1]["number"], json!(3));
+        assert_eq!(lines[1]["current"], json!(true));
+
+        let last = execute_command(
+            &mut session,
+            LlmCommand::Source {
+                file: Some("src/bin/ios_llm_api.rs".into()),
+                line: Some(u32::MAX),
+                context: Some(5),
+            },
+        )
+        .unwrap();
+        assert!(last["source"]["lines"].as_array().unwrap().is_empty());
+
+        for outside in ["Cargo.toml", "/etc/passwd", "/build/elsewhere/None.swift"] {
+            let err = execute_command(
+                &mut session,
+                LlmCommand::Source {
+                    file: Some(outside.into()),
+                    line: Some(1),
+                    context: None,
+                },
+            )
+            .unwrap_err();
+            assert!(
+                err.to_string().contains("not in the loaded debug info"),
+                "{err}"
+            );
+        }
+    }
+
+    #[test]
+    fn threads_command_returns_payload() {
+        let mut session = sample_session();
+        let value = execute_command(&mut session, LlmCommand::Threads).unwrap();
+        let thread = &value["threads"][0];
+        assert_eq!(thread["id"], json!(1));
+        assert!(thread["name"].is_string());
+        assert_eq!(thread["state"], json!("unknown"));
+        assert!(thread["top_frame"]["function"].is_string());
+        assert!(thread.get("stop_reason").is_none());
+    }
+
+    #[test]
+    fn locals_command_returns_payload() {
+        let mut session = sample_session();
+        let value = execute_command(&mut session, LlmCommand::Locals { format: None }).unwrap();
+        assert!(value.get("locals").is_some());
+    }
+
+    #[test]
+    fn scopes_command_returns_payload() {
+        let mut session = sample_session();
+        let value = execute_command(&mut session, LlmCommand::Scopes).unwrap();
+        assert!(value.get("scopes").is_some());
+    }
+
+    #[test]
+    fn disconnect_command_succeeds_even_without_remote() {
+        let mut session = sample_session();
+        let value = execute_command(&mut session, LlmCommand::Disconnect).unwrap();
+        assert_eq!(value.get("ok").and_then(Value::as_bool), Some(true));
+    }
+
+    #[test]
+    fn set_breakpoint_echoes_path_resolution() {
+        let mut session = sample_session();
+        let command: LlmCommand = serde_json::from_value(json!({
+            "action": "set_breakpoint",
+            "file": "bin/ios_llm_api.rs",
+            "line": 1,
+        }))
+        .unwrap();
+        let value = execute_command(&mut session, command).unwrap();
+        assert_eq!(value["match"], json!("suffix"));
+        let resolved = value["resolved_file"].as_str().unwrap();
+        assert!(resolved.ends_with("src/bin/ios_llm_api.rs"), "{resolved}");
+        assert_eq!(value["temporary"], json!(false));
+        // Without debugserver it waits to be planted.
+        assert_eq!(value["verified"], json!(false));
+
+        let command: LlmCommand = serde_json::from_value(json!({
+            "action": "set_breakpoint",
+            "file": "bin/ios_llm_api.rs",
+            "line": 2,
+            "temporary": true,
+        }))
+        .unwrap();
+        let value = execute_command(&mut session, command).unwrap();
+        assert_eq!(value["temporary"], json!(true));
+        assert_eq!(value["breakpoint_id"], json!(2));
+
+        let exact = LlmCommand::SetBreakpoint {
+            file: "bin/ios_llm_api.rs".into(),
+            line: 1,
+            path_match: Some(PathMatch::Exact),
+            temporary: false,
+        };
+        let err = execute_command(&mut session, exact).unwrap_err();
+        assert!(err.to_string().contains("(match: exact)"), "{err}");
+
+        let ambiguous = LlmCommand::SetBreakpoint {
+            file: "lib.rs".into(),
+            line: 1,
+            path_match: Some(PathMatch::Basename),
+            temporary: false,
+        };
+        let err = execute_command(&mut session, ambiguous).unwrap_err();
+        assert!(err.to_string().contains("pass more of the path"), "{err}");
+    }
+
+    #[test]
+    fn breakpoints_export_and_import_round_trip() {
+        let mut session = sample_session();
+        session
+            .set_breakpoint("bin/ios_llm_api.rs", 1, None)
+            .unwrap();
+        let exported =
+            execute_command(&mut session, LlmCommand::ExportBreakpoints { format: None }).unwrap();
+        let file = exported["breakpoints"][0]["file"].as_str().unwrap();
+        assert!(file.ends_with("src/bin/ios_llm_api.rs"), "{file}");
+
+        let mut fresh = sample_session();
+        let contents = json!({
+            "breakpoints": [
+                exported["breakpoints"][0],
+                { "file": "NoSuchFile.swift", "line": 3 }
+            ]
+        })
+        .to_string();
+        let imported = execute_command(
+            &mut fresh,
+            LlmCommand::ImportBreakpoints {
+                path: None,
+                contents: Some(contents),
+            },
+        )
+        .unwrap();
+        assert_eq!(imported["breakpoints"][0]["breakpoint_id"], json!(1));
+        assert_eq!(imported["failed"].as_array().map(Vec::len), Some(1));
+        assert_eq!(
+            fresh.export_breakpoints(),
+            session.export_breakpoints(),
+            "import reproduces the exported list"
+        );
+        let neither = LlmCommand::ImportBreakpoints {
+            path: None,
+            contents: None,
+        };
+        assert!(execute_command(&mut fresh, neither).is_err());
+    }
+
+    #[test]
+    fn set_logpoint_validates_message_and_line() {
+        let mut session = sample_session();
+        let empty = LlmCommand::SetLogpoint {
+            file: "Missing.swift".into(),
+            line: 3,
+            message: " ".into(),
+        };
+        let err = execute_command(&mut session, empty).unwrap_err();
+        assert!(err.to_string().contains("must not be empty"), "{err}");
+        let nowhere = LlmCommand::SetLogpoint {
+            file: "Missing.swift".into(),
+            line: 3,
+            message: "count is {counter}".into(),
+        };
+        let err = execute_command(&mut session, nowhere).unwrap_err();
+        assert!(err.to_string().contains("matches `Missing.swift`"), "{err}");
+        let unbalanced = LlmCommand::SetLogpoint {
+            file: "Missing.swift".into(),
+            line: 3,
+            message: "count is {counter".into(),
+        };
+        let err = execute_command(&mut session, unbalanced).unwrap_err();
+        assert!(err.to_string().contains("missing its closing `}`"), "{err}");
+    }
+
+    #[test]
+    fn detach_requires_debugserver() {
+        let mut session = sample_session();
+        let err = execute_command(&mut session, LlmCommand::Detach).unwrap_err();
+        assert!(
+            err.to_string().contains("no gdb-remote connection"),
+            "{err}"
+        );
+    }
+
+    #[test]
     fn continue_command_errors_without_debugserver() {
         let mut session = sample_session();
-        assert!(execute_command(&mut session, LlmCommand::Continue).is_err());
+        assert!(execute_command(&mut session, LlmCommand::Continue).is_err());
+    }
+
+    #[test]
+    fn connect_reports_attempts_when_giving_up() {
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let mut session = sample_session();
+        session.set_connect_attempts(2);
+        let err = session.connect_debugserver(port).unwrap_err();
+        assert!(
+            err.to_string().contains("gave up after 2 attempts"),
+            "{err}"
+        );
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn connect_backoff_leaves_the_runtime_free() {
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let mut session = sample_session();
+        session.set_connect_attempts(3);
+        let ticks = Arc::new(AtomicUsize::new(0));
+        let ticker = tokio::spawn({
+            let ticks = ticks.clone();
+            async move {
+                loop {
+                    ticks.fetch_add(1, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+            }
+        });
+        let err = connect_debugserver(&mut session, port).await.unwrap_err();
+        ticker.abort();
+        assert!(
+            err.to_string().contains("gave up after 3 attempts"),
+            "{err}"
+        );
+        // The 300ms of backoff ran on the runtime, so the ticker kept going.
+        assert!(ticks.load(Ordering::SeqCst) > 5);
+    }
+
+    #[test]
+    fn terminate_requires_debugserver() {
+        let command: LlmCommand =
+            serde_json::from_value(json!({ "action": "terminate", "devicectl": true })).unwrap();
+        assert!(matches!(command, LlmCommand::Terminate { devicectl: true }));
+        let mut session = sample_session();
+        assert!(session.terminate().is_err());
+    }
+
+    #[test]
+    fn launch_arguments_and_environment_reach_the_bridge() {
+        let args = Args::try_parse_from([
+            "ios_llm_api",
+            "--debugserver-port",
+            "1234",
+            "--device",
+            "DEVICE",
+            "--bundle-id",
+            "com.example.app",
+            "--launch-args",
+            "-AppleLanguages",
+            "--launch-args",
+            "(fr)",
+            "--launch-env",
+            "MOCK_API=1",
+        ])
+        .unwrap();
+        let config = Config::from_args(&args, PathBuf::from("MyApp")).unwrap();
+        let (log_tx, _) = broadcast::channel(4);
+        let mut bridge = BridgeController::new(&config, log_tx, None).unwrap();
+        let spawned = bridge.spawn_args();
+        assert!(spawned
+            .windows(2)
+            .any(|pair| pair == ["--app-env", "MOCK_API=1"]));
+        assert!(spawned.ends_with(&["--app-arg=-AppleLanguages".into(), "--app-arg=(fr)".into()]));
+
+        let command: LlmCommand = serde_json::from_value(json!({
+            "action": "launch",
+            "env": { "MOCK_API": "0" }
+        }))
+        .unwrap();
+        let LlmCommand::Launch { args, env } = command else {
+            panic!("expected launch");
+        };
+        assert_eq!(args, None);
+        bridge.set_launch(LaunchOptions {
+            args: Vec::new(),
+            env: env.unwrap(),
+        });
+        let spawned = bridge.spawn_args();
+        assert!(spawned
+            .windows(2)
+            .any(|pair| pair == ["--app-env", "MOCK_API=0"]));
+        assert!(!spawned.iter().any(|arg| arg.starts_with("--app-arg")));
+
+        let bad = Args::try_parse_from([
+            "ios_llm_api",
+            "--debugserver-port",
+            "1234",
+            "--launch-env",
+            "NOEQUALS",
+        ])
+        .unwrap();
+        assert!(Config::from_args(&bad, PathBuf::from("MyApp")).is_err());
+    }
+
+    #[test]
+    fn responses_and_errors_are_redacted_by_default() {
+        let args = Args::try_parse_from([
+            "ios_llm_api",
+            "--debugserver-port",
+            "1234",
+            "--redact-field",
+            "pin",
+        ])
+        .unwrap();
+        let redactor = Config::from_args(&args, PathBuf::from("MyApp"))
+            .unwrap()
+            .redactor()
+            .unwrap();
+        let body = json!({
+            "ok": true,
+            "locals": [
+                { "name": "password", "value": "hunter2" },
+                { "name": "cardPin", "value": "1234" },
+                { "name": "count", "value": "3" }
+            ]
+        });
+        let body = redact_response(&redactor, Ok(body)).unwrap();
+        assert_eq!(body["locals"][0]["value"], "<redacted>");
+        assert_eq!(body["locals"][1]["value"], "<redacted>");
+        assert_eq!(body["locals"][2]["value"], "3");
+        let err = redact_response(
+            &redactor,
+            Err(DebugSessionError::Backend(
+                "login failed for jane@example.com".into(),
+            )),
+        )
+        .unwrap_err();
+        assert_eq!(err.to_string(), "login failed for <redacted>");
+        let err = redact_response(
+            &redactor,
+            Err(DebugSessionError::Backend(
+                "no gdb-remote connection for jane@example.com".into(),
+            )),
+        )
+        .unwrap_err();
+        assert_eq!(err.code(), debug_session::ErrorCode::NotConnected);
+
+        let args = Args::try_parse_from([
+            "ios_llm_api",
+            "--debugserver-port",
+            "1234",
+            "--no-default-redaction",
+            "--redact-pattern",
+            "[",
+        ])
+        .unwrap();
+        let config = Config::from_args(&args, PathBuf::from("MyApp")).unwrap();
+        assert!(config
+            .validate()
+            .unwrap_err()
+            .to_string()
+            .contains("--redact-pattern"));
+    }
+
+    #[test]
+    fn config_validation_reports_every_problem() {
+        let args = Args::try_parse_from([
+            "ios_llm_api",
+            "--debugserver-port",
+            "4000",
+            "--manage-bridge",
+            "--ios-llm-devicectl",
+            "/nonexistent/ios-llm-devicectl",
+            "--app-bundle",
+            "/nonexistent/MyApp.app",
+            "--build-cmd",
+            "swiftscope-missing-build-tool",
+        ])
+        .unwrap();
+        let config = Config::from_args(&args, PathBuf::from("MyApp")).unwrap();
+        let err = config.validate().unwrap_err().to_string();
+        for expected in [
+            "--port 4000 is also the --debugserver-port",
+            "--device is required",
+            "--bundle-id (or --attach-pid/--attach-name) is required",
+            "`/nonexistent/ios-llm-devicectl` was not found",
+            "--app-bundle /nonexistent/MyApp.app",
+            "`swiftscope-missing-build-tool` (--build-cmd)",
+        ] {
+            assert!(err.contains(expected), "missing {expected:?} in {err}");
+        }
+        assert_eq!(config.problems().len(), 6);
+
+        let args = Args::try_parse_from(["ios_llm_api", "--debugserver-port", "1234"]).unwrap();
+        let config = Config::from_args(&args, PathBuf::from("MyApp")).unwrap();
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn attach_target_replaces_launch_arguments() {
+        let args = Args::try_parse_from([
+            "ios_llm_api",
+            "--debugserver-port",
+            "1234",
+            "--device",
+            "DEVICE",
+            "--attach-name",
+            "MyApp",
+        ])
+        .unwrap();
+        let config = Config::from_args(&args, PathBuf::from("MyApp")).unwrap();
+        let (log_tx, _) = broadcast::channel(4);
+        let mut bridge = BridgeController::new(&config, log_tx, None).unwrap();
+        let spawned = bridge.spawn_args();
+        assert!(spawned
+            .windows(2)
+            .any(|pair| pair == ["--attach-name", "MyApp"]));
+        assert!(!spawned.iter().any(|arg| arg == "--bundle-id"));
+        assert!(bridge.set_attach(None).is_err());
+        bridge.set_attach(Some(AttachTarget::Pid(812))).unwrap();
+        assert!(bridge
+            .spawn_args()
+            .windows(2)
+            .any(|pair| pair == ["--attach-pid", "812"]));
+
+        assert!(Args::try_parse_from([
+            "ios_llm_api",
+            "--debugserver-port",
+            "1234",
+            "--attach-pid",
+            "1",
+            "--attach-name",
+            "MyApp",
+        ])
+        .is_err());
+        let command: LlmCommand =
+            serde_json::from_value(json!({ "action": "attach", "pid": 812 })).unwrap();
+        assert!(matches!(
+            command,
+            LlmCommand::Attach {
+                pid: Some(812),
+                name: None
+            }
+        ));
+    }
+
+    #[test]
+    fn run_to_line_errors_without_code_at_line() {
+        let mut session = sample_session();
+        let command = LlmCommand::RunToLine {
+            file: "/no/such/File.swift".into(),
+            line: 12,
+        };
+        let err = execute_command(&mut session, command).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("no code at /no/such/File.swift:12"));
+    }
+
+    #[test]
+    fn suspend_thread_requires_debugserver() {
+        let mut session = sample_session();
+        let command: LlmCommand =
+            serde_json::from_value(json!({ "action": "suspend_thread", "thread_id": 2 })).unwrap();
+        let err = execute_command(&mut session, command).unwrap_err();
+        assert!(
+            err.to_string().contains("no gdb-remote connection"),
+            "{err}"
+        );
+        let err =
+            execute_command(&mut session, LlmCommand::ResumeThread { thread_id: 2 }).unwrap_err();
+        assert!(err.to_string().contains("not suspended"), "{err}");
+    }
+
+    #[test]
+    fn signal_parses_names_and_requires_debugserver() {
+        let mut session = sample_session();
+        let command: LlmCommand =
+            serde_json::from_value(json!({ "action": "signal", "signal": "SIGUSR1" })).unwrap();
+        let err = execute_command(&mut session, command).unwrap_err();
+        assert!(
+            err.to_string().contains("no gdb-remote connection"),
+            "{err}"
+        );
+        let command: LlmCommand =
+            serde_json::from_value(json!({ "action": "signal", "signal": 99 })).unwrap();
+        let err = execute_command(&mut session, command).unwrap_err();
+        assert!(err.to_string().contains("unknown signal"), "{err}");
+    }
+
+    #[test]
+    fn step_out_command_errors_without_debugserver() {
+        let mut session = sample_session();
+        assert!(execute_command(&mut session, LlmCommand::StepOut).is_err());
+    }
+
+    #[test]
+    fn register_commands_parse_and_require_debugserver() {
+        let command: LlmCommand = serde_json::from_value(json!({
+            "action": "set_register",
+            "name": "pc",
+            "value": "0x100004000"
+        }))
+        .unwrap();
+        assert!(matches!(
+            &command,
+            LlmCommand::SetRegister { name, value: 0x1_0000_4000 } if name == "pc"
+        ));
+        let mut session = sample_session();
+        assert!(execute_command(&mut session, command).is_err());
+        assert!(execute_command(&mut session, LlmCommand::Registers).is_err());
+    }
+
+    #[test]
+    fn api_tokens_map_to_tenants_and_claim_the_session() {
+        let tokens = parse_api_tokens(&["alice=secret-a".into(), "bob = secret-b".into()]).unwrap();
+        assert_eq!(tokens.get("secret-b").map(String::as_str), Some("bob"));
+        assert!(parse_api_tokens(&["a=same".into(), "b=same".into()]).is_err());
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, "Bearer secret-a".parse().unwrap());
+        assert_eq!(bearer_token(&headers), Some("secret-a"));
+        let uri: Uri = "/logs?since=3&token=secret-b".parse().unwrap();
+        assert_eq!(query_token(&uri).as_deref(), Some("secret-b"));
+        let uri: Uri = "/events?token=a%2Bb%3Dc%26d".parse().unwrap();
+        assert_eq!(query_token(&uri).as_deref(), Some("a+b=c&d"));
+        assert_eq!(query_token(&"/logs".parse().unwrap()), None);
+        assert!(is_public_route("/health", true));
+        assert!(!is_public_route("/health", false));
+        assert!(!is_public_route("/status", true));
+
+        let mut owner = None;
+        assert!(claim_session(&mut owner, "alice"));
+        assert!(claim_session(&mut owner, "alice"));
+        assert!(!claim_session(&mut owner, "bob"));
+    }
+
+    #[test]
+    fn api_token_entries_split_on_the_tenant_delimiter() {
+        let tokens = parse_api_tokens(&[
+            "bare-token".into(),
+            "alice=padded==".into(),
+            "default=abc==".into(),
+        ])
+        .unwrap();
+        assert_eq!(
+            tokens.get("bare-token").map(String::as_str),
+            Some("default")
+        );
+        assert_eq!(tokens.get("padded==").map(String::as_str), Some("alice"));
+        assert_eq!(tokens.get("abc==").map(String::as_str), Some("default"));
+
+        for ambiguous in ["abc==", "abc=", "=token", "a b=token", ""] {
+            assert!(
+                parse_api_tokens(&[ambiguous.into()]).is_err(),
+                "{ambiguous}"
+            );
+        }
+    }
+
+    #[test]
+    fn timeline_keeps_recent_commands_with_outcomes() {
+        let timeline = Timeline::default();
+        let started = Instant::now();
+        timeline.record(
+            "continue",
+            started,
+            &Ok(json!({ "ok": true, "stop": { "description": "Breakpoint 1" } })),
+        );
+        timeline.record(
+            "evaluate",
+            started,
+            &Err(DebugSessionError::InvalidArgument("bad".into())),
+        );
+        let entries = timeline.entries();
+        assert_eq!(entries[0].stop.as_deref(), Some("Breakpoint 1"));
+        assert!(!entries[1].ok);
+        assert!(entries[1].error.as_deref().unwrap().contains("bad"));
+
+        for _ in 0..TIMELINE_CAPACITY {
+            timeline.record("threads", started, &Ok(json!({ "ok": true })));
+        }
+        let entries = timeline.entries();
+        assert_eq!(entries.len(), TIMELINE_CAPACITY);
+        assert!(entries.iter().all(|entry| entry.action == "threads"));
+    }
+
+    #[test]
+    fn stop_timeline_numbers_stops_and_keeps_their_command() {
+        let stops = StopTimeline::default();
+        stops.record(&json!({ "event": "continued", "thread_id": 1 }), None, None);
+        stops.record(
+            &json!({
+                "event": "stopped",
+                "reason": "breakpoint",
+                "thread_id": 1,
+                "function": "MyApp.load() -> ()",
+                "summary": "count=3",
+                "request_id": "r1",
+            }),
+            Some("continue"),
+            Some("r1".into()),
+        );
+        stops.record(
+            &json!({ "event": "stopped", "reason": "step", "thread_id": 1 }),
+            Some("next"),
+            None,
+        );
+        let all = stops.since(None);
+        assert_eq!(all.len(), 2, "only stopped events are recorded");
+        let first = serde_json::to_value(&all[0]).unwrap();
+        assert_eq!(first["index"], 1);
+        assert_eq!(first["action"], "continue");
+        assert_eq!(first["request_id"], "r1");
+        assert_eq!(first["summary"], "count=3");
+        assert!(first.get("event").is_none());
+        assert_eq!(stops.since(Some(1))[0].index, 2);
+
+        for _ in 0..STOP_TIMELINE_CAPACITY {
+            stops.record(&json!({ "event": "stopped" }), None, None);
+        }
+        let kept = stops.since(None);
+        assert_eq!(kept.len(), STOP_TIMELINE_CAPACITY);
+        assert_eq!(kept[0].index, 3, "indices keep counting past dropped stops");
+    }
+
+    #[test]
+    fn symbolicate_accepts_address_lists() {
+        let command: LlmCommand = serde_json::from_value(json!({
+            "action": "symbolicate",
+            "addresses": ["0x10", 32]
+        }))
+        .unwrap();
+        assert!(
+            matches!(&command, LlmCommand::Symbolicate { addresses } if addresses == &[0x10, 32])
+        );
+        let mut session = sample_session();
+        let value = execute_command(&mut session, command).unwrap();
+        assert_eq!(value["symbols"][0]["address"], "0x10");
+        assert_eq!(value["symbols"][1]["address"], "0x20");
+        let missing = LlmCommand::LookupSymbol {
+            name: "no_such_symbol_anywhere".into(),
+        };
+        assert!(execute_command(&mut session, missing).is_err());
+    }
+
+    #[test]
+    fn modules_command_requires_debugserver() {
+        let command: LlmCommand = serde_json::from_value(json!({ "action": "modules" })).unwrap();
+        let mut session = sample_session();
+        assert!(execute_command(&mut session, command).is_err());
+    }
+
+    #[test]
+    fn set_verbosity_updates_session_level() {
+        let command: LlmCommand = serde_json::from_value(json!({
+            "action": "set_verbosity",
+            "level": "minimal"
+        }))
+        .unwrap();
+        let mut session = sample_session();
+        let value = execute_command(&mut session, command).unwrap();
+        assert_eq!(value["verbosity"], "minimal");
+        assert_eq!(session.verbosity(), Verbosity::Minimal);
+        assert!(serde_json::from_value::<LlmCommand>(json!({
+            "action": "set_verbosity",
+            "level": "loud"
+        }))
+        .is_err());
+    }
+
+    #[test]
+    fn disassemble_accepts_optional_target() {
+        let command: LlmCommand = serde_json::from_value(json!({
+            "action": "disassemble",
+            "address": "0x100004000",
+            "count": 8
+        }))
+        .unwrap();
+        assert!(matches!(
+            command,
+            LlmCommand::Disassemble {
+                address: Some(0x1_0000_4000),
+                symbol: None,
+                count: Some(8)
+            }
+        ));
+        let command: LlmCommand =
+            serde_json::from_value(json!({ "action": "disassemble" })).unwrap();
+        let mut session = sample_session();
+        assert!(execute_command(&mut session, command).is_err());
+    }
+
+    #[test]
+    fn read_memory_accepts_hex_string_addresses() {
+        let command: LlmCommand = serde_json::from_value(json!({
+            "action": "read_memory",
+            "address": "0x1000",
+            "length": 16
+        }))
+        .unwrap();
+        assert!(matches!(
+            command,
+            LlmCommand::ReadMemory {
+                address: 0x1000,
+                length: 16
+            }
+        ));
+    }
+
+    #[test]
+    fn write_memory_requires_confirmation() {
+        let mut session = sample_session();
+        let err = execute_command(
+            &mut session,
+            LlmCommand::WriteMemory {
+                address: 0x1000,
+                data: "00".into(),
+                encoding: "hex".into(),
+                confirm: false,
+            },
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("confirm"));
+    }
+
+    #[test]
+    fn write_stdin_decodes_and_requires_debugserver() {
+        let mut session = sample_session();
+        let command: LlmCommand =
+            serde_json::from_value(json!({ "action": "write_stdin", "data": "quit\n" })).unwrap();
+        let err = execute_command(&mut session, command).unwrap_err();
+        assert!(
+            err.to_string().contains("no gdb-remote connection"),
+            "{err}"
+        );
+        let command = LlmCommand::WriteStdin {
+            data: "".into(),
+            encoding: "text".into(),
+        };
+        let err = execute_command(&mut session, command).unwrap_err();
+        assert!(err.to_string().contains("must not be empty"), "{err}");
+        let command = LlmCommand::WriteStdin {
+            data: "71".into(),
+            encoding: "utf16".into(),
+        };
+        let err = execute_command(&mut session, command).unwrap_err();
+        assert!(err.to_string().contains("expected `text`"), "{err}");
     }
 
     #[test]
@@ -733,6 +7459,7 @@ mod tests {
             &mut session,
             LlmCommand::WatchExpression {
                 expression: "var".into(),
+                format: None,
             },
         )
         .unwrap();
@@ -747,6 +7474,1075 @@ mod tests {
         assert_eq!(value.get("threadId").and_then(Value::as_i64), Some(3));
     }
 
+    #[test]
+    fn openapi_actions_match_commands() {
+        for spec in ACTIONS {
+            let mut request = json!({ "action": spec.action });
+            for (field, kind, _) in spec.fields {
+                request[*field] = match kind {
+                    FieldKind::String => json!("00"),
+                    FieldKind::Integer => json!(1),
+                    FieldKind::Boolean => json!(true),
+                    FieldKind::Address => json!("0x1000"),
+                    FieldKind::Addresses => json!(["0x1000", 4096]),
+                    FieldKind::Scalar => json!(5),
+                    FieldKind::Enum(values) => json!(values[0]),
+                    FieldKind::ThreadCounts => json!({ "1": 8 }),
+                    FieldKind::ValueFormat => json!({ "integers": "hex", "float_precision": 2 }),
+                    FieldKind::Strings => json!(["-AppleLanguages", "(en)"]),
+                    FieldKind::StringMap => json!({ "MOCK_API": "1" }),
+                    FieldKind::Commands => json!([{ "action": "threads" }]),
+                    FieldKind::MemoryRanges => json!([{ "address": "0x1000", "length": 16 }]),
+                };
+            }
+            let command: LlmCommand = serde_json::from_value(request.clone())
+                .unwrap_or_else(|err| panic!("{request}: {err}"));
+            assert_eq!(command.action(), spec.action);
+        }
+        let document = openapi_document();
+        assert_eq!(document["openapi"], "3.1.0");
+        let mapping = &document["components"]["schemas"]["Command"]["discriminator"]["mapping"];
+        assert_eq!(
+            mapping.as_object().map(|map| map.len()),
+            Some(ACTIONS.len())
+        );
+        assert_eq!(
+            document["components"]["schemas"]["SetBreakpointCommand"]["required"],
+            json!(["action", "file", "line"])
+        );
+    }
+
+    #[test]
+    fn command_schema_refs_resolve() {
+        fn refs<'a>(value: &'a Value, out: &mut Vec<&'a str>) {
+            match value {
+                Value::Object(map) => {
+                    if let Some(Value::String(path)) = map.get("$ref") {
+                        out.push(path);
+                    }
+                    map.values().for_each(|value| refs(value, out));
+                }
+                Value::Array(items) => items.iter().for_each(|value| refs(value, out)),
+                _ => {}
+            }
+        }
+        let schema = command_schema();
+        assert_eq!(schema["type"], "object");
+        assert_eq!(
+            schema["oneOf"].as_array().map(Vec::len),
+            Some(ACTIONS.len())
+        );
+        assert_eq!(
+            schema["properties"]["action"]["enum"]
+                .as_array()
+                .map(Vec::len),
+            Some(ACTIONS.len())
+        );
+        let mut found = Vec::new();
+        refs(&schema, &mut found);
+        assert!(
+            found.contains(&"#/$defs/Command"),
+            "batch items refer to Command"
+        );
+        for path in found {
+            let name = path.strip_prefix("#/$defs/").unwrap();
+            assert!(
+                schema["$defs"].get(name).is_some(),
+                "{path} does not resolve"
+            );
+        }
+        assert_eq!(
+            schema["$defs"]["SetBreakpointCommand"]["required"],
+            json!(["action", "file", "line"])
+        );
+    }
+
+    #[test]
+    fn select_frame_scopes_locals_per_thread() {
+        let exe = std::env::current_exe().expect("current exe");
+        let image = Image {
+            name: "test".into(),
+            path: exe.clone(),
+            uuid: None,
+            dsym: None,
+            slice: None,
+            vmaddr_text: 0,
+            slide: 0,
+            dwarf: Loader::new(&exe).expect("loader"),
+        };
+        let mut backend = Backend::new_for_testing(SymbolContext::for_testing(image));
+        backend.set_frame_provider(|thread_id| {
+            // Addresses without symbols, so neither expands into inlined frames.
+            vec![
+                (thread_id * 100 + 1, 0xDEAD_BEEF),
+                (thread_id * 100 + 2, 0xDEAD_BEEF),
+            ]
+        });
+        let mut session = DebugSession::new(backend);
+
+        let command: LlmCommand =
+            serde_json::from_value(json!({ "action": "select_frame", "frame_index": 1 })).unwrap();
+        let value = execute_command(&mut session, command).unwrap();
+        assert_eq!(value["frame"]["frame_index"], 1);
+        let locals = execute_command(&mut session, LlmCommand::Locals { format: None }).unwrap();
+        assert_eq!(locals["locals"][0]["value"], "value-2");
+        assert!(execute_command(
+            &mut session,
+            LlmCommand::SetVariable {
+                name: "counter".into(),
+                value: "1".into()
+            }
+        )
+        .is_err());
+
+        session.select_thread(2);
+        assert_eq!(session.selected_frame(), 0);
+        assert!(execute_command(&mut session, LlmCommand::SelectFrame { frame_index: 2 }).is_err());
+        session.select_thread(1);
+        assert_eq!(session.selected_frame(), 1);
+    }
+
+    fn sample_config(debugserver_port: u16) -> Config {
+        let port = debugserver_port.to_string();
+        let args = Args::try_parse_from(["ios_llm_api", "--debugserver-port", &port]).unwrap();
+        Config::from_args(&args, PathBuf::from("MyApp")).unwrap()
+    }
+
+    fn sample_parts() -> SessionParts {
+        SessionParts {
+            session: sample_session(),
+            bridge: None,
+            log_tx: broadcast::channel(4).0,
+            recent_logs: Arc::default(),
+            event_tx: broadcast::channel(4).0,
+            running: Arc::default(),
+            stops: Arc::default(),
+            tasks: Vec::new(),
+            trace: None,
+            artifacts: Arc::new(ArtifactStore::create(None).unwrap()),
+        }
+    }
+
+    /// A registered default session over [`sample_session`].
+    fn sample_state(config: Config) -> AppState {
+        let shared = AppState {
+            session_id: String::new(),
+            session: Arc::new(Mutex::new(sample_session())),
+            config: config.clone(),
+            bridge: None,
+            log_tx: broadcast::channel(4).0,
+            recent_logs: Arc::default(),
+            event_tx: broadcast::channel(4).0,
+            build_runner: None,
+            owner: Arc::default(),
+            faults: None,
+            trace: None,
+            artifacts: Arc::new(ArtifactStore::create(None).unwrap()),
+            running: Arc::default(),
+            timeline: Arc::default(),
+            stops: Arc::default(),
+            history: Arc::new(CommandHistory::create(config.history_file.as_deref()).unwrap()),
+            queue: Arc::default(),
+            redactor: Arc::new(config.redactor().unwrap()),
+            jobs: Arc::default(),
+            replays: Arc::default(),
+            started: Instant::now(),
+            rate_limiter: None,
+            sessions: Arc::default(),
+            tasks: Arc::default(),
+        };
+        let state = sample_parts().into_state(DEFAULT_SESSION.into(), config, &shared);
+        state.sessions.insert(state.clone());
+        state
+    }
+
+    #[tokio::test]
+    async fn mcp_exposes_actions_as_tools() {
+        let state = sample_state(sample_config(4001));
+        let reply = |line: &'static str| mcp_reply(&state, line);
+
+        let init = reply(r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{"protocolVersion":"2024-11-05"}}"#).await.unwrap();
+        assert_eq!(init["result"]["protocolVersion"], "2024-11-05");
+        assert_eq!(init["result"]["serverInfo"]["name"], "swiftscope");
+        assert!(
+            reply(r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#)
+                .await
+                .is_none()
+        );
+
+        let list = reply(r#"{"jsonrpc":"2.0","id":2,"method":"tools/list"}"#)
+            .await
+            .unwrap();
+        let tools = list["result"]["tools"].as_array().unwrap();
+        assert_eq!(tools.len(), ACTIONS.len());
+        let tool = |name: &str| tools.iter().find(|tool| tool["name"] == name).unwrap();
+        assert_eq!(
+            tool("set_breakpoint")["inputSchema"]["required"],
+            json!(["file", "line"])
+        );
+        assert!(tool("stacktrace")["inputSchema"]["properties"]
+            .get("action")
+            .is_none());
+        assert!(tool("batch")["inputSchema"]["$defs"]["Command"].is_object());
+
+        let call = reply(
+            r#"{"jsonrpc":"2.0","id":3,"method":"tools/call","params":{"name":"stacktrace"}}"#,
+        )
+        .await
+        .unwrap();
+        assert_eq!(call["result"]["isError"], false);
+        let text: Value =
+            serde_json::from_str(call["result"]["content"][0]["text"].as_str().unwrap()).unwrap();
+        assert!(text["stacktrace"].is_array(), "{text}");
+        let call = reply(r#"{"jsonrpc":"2.0","id":4,"method":"tools/call","params":{"name":"set_breakpoint","arguments":{"line":"x"}}}"#).await.unwrap();
+        assert_eq!(call["result"]["isError"], true);
+
+        let unknown =
+            reply(r#"{"jsonrpc":"2.0","id":5,"method":"tools/call","params":{"name":"nope"}}"#)
+                .await
+                .unwrap();
+        assert_eq!(unknown["error"]["code"], -32602);
+        let unknown = reply(r#"{"jsonrpc":"2.0","id":6,"method":"resources/list"}"#)
+            .await
+            .unwrap();
+        assert_eq!(unknown["error"]["code"], -32601);
+        assert_eq!(reply("{").await.unwrap()["error"]["code"], -32700);
+    }
+
+    #[tokio::test]
+    async fn rpc_correlates_ids_and_batches() {
+        let state = sample_state(sample_config(4001));
+        let rpc = |text: &'static str| rpc_payload(&state, None, "rpc", text);
+
+        let single = rpc(r#"{"jsonrpc":"2.0","id":"a","method":"stacktrace"}"#)
+            .await
+            .unwrap();
+        assert_eq!(single["id"], "a");
+        assert!(single["result"]["stacktrace"].is_array(), "{single}");
+        assert!(rpc(r#"{"jsonrpc":"2.0","method":"stacktrace"}"#)
+            .await
+            .is_none());
+
+        let batch = rpc(r#"[
+            {"jsonrpc":"2.0","id":1,"method":"set_breakpoint","params":{"line":"x"}},
+            {"jsonrpc":"2.0","id":2,"method":"nope"},
+            {"jsonrpc":"2.0","method":"stacktrace"},
+            {"jsonrpc":"2.0","id":3,"method":"next"},
+            {"id":4,"method":"stacktrace"}
+        ]"#)
+        .await
+        .unwrap();
+        let batch = batch.as_array().unwrap();
+        let ids: Vec<_> = batch.iter().map(|reply| reply["id"].clone()).collect();
+        assert_eq!(ids, [json!(1), json!(2), json!(3), json!(4)]);
+        let codes: Vec<_> = batch
+            .iter()
+            .map(|reply| reply["error"]["code"].clone())
+            .collect();
+        assert_eq!(
+            codes,
+            [json!(-32602), json!(-32601), json!(-32000), json!(-32600)]
+        );
+        assert_eq!(batch[2]["error"]["data"]["ok"], false, "{}", batch[2]);
+        assert!(batch[2]["error"]["data"]["status"].is_u64());
+
+        assert_eq!(rpc("[]").await.unwrap()["error"]["code"], -32600);
+        assert_eq!(rpc("{").await.unwrap()["error"]["code"], -32700);
+    }
+
+    #[tokio::test]
+    async fn grpc_commands_mirror_the_http_ones() {
+        use proto::debugger_server::Debugger;
+
+        let service = GrpcDebugger(sample_state(sample_config(4001)));
+        let command = |action: &str, arguments: Value| proto::CommandRequest {
+            id: "7".into(),
+            action: action.into(),
+            arguments: arguments.as_object().cloned().map(prost_struct),
+            ..Default::default()
+        };
+        let reply = service
+            .command(tonic::Request::new(command("stacktrace", json!({}))))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(
+            (reply.id.as_str(), reply.status, reply.ok),
+            ("7", 200, true)
+        );
+        let body = Value::Object(json_object(reply.body.unwrap()));
+        assert!(body["stacktrace"].is_array(), "{body}");
+
+        let reply = service
+            .command(tonic::Request::new(command(
+                "set_breakpoint",
+                json!({ "file": "App.swift", "line": "x" }),
+            )))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!((reply.status, reply.ok), (422, false));
+        assert_eq!(reply.error_code, "INVALID_COMMAND");
+
+        let mut request = command("stacktrace", json!({}));
+        request.session_id = "nope".into();
+        let status = service
+            .command(tonic::Request::new(request))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::NotFound);
+
+        let mut config = sample_config(4001);
+        config.api_tokens = parse_api_tokens(&["alice=secret-a".into()]).unwrap();
+        let service = GrpcDebugger(sample_state(config));
+        let status = service
+            .command(tonic::Request::new(command("stacktrace", json!({}))))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unauthenticated);
+        let mut request = tonic::Request::new(command("stacktrace", json!({})));
+        request
+            .metadata_mut()
+            .insert("authorization", "Bearer secret-a".parse().unwrap());
+        assert!(service.command(request).await.unwrap().into_inner().ok);
+    }
+
+    #[test]
+    fn grpc_events_and_structs_round_trip() {
+        let event = grpc_event(json!({
+            "event": "process_exited",
+            "exit_code": 3,
+        }));
+        assert_eq!(event.event, "process_exited");
+        assert_eq!((event.exit_code, event.signal), (Some(3), None));
+        let fields =
+            json!({ "line": 42, "ratio": 0.5, "tags": ["a", null], "nested": { "ok": true } });
+        let round_trip = json_object(prost_struct(fields.as_object().unwrap().clone()));
+        assert_eq!(Value::Object(round_trip), fields);
+    }
+
+    /// Sends one request to `addr` and reads the status and JSON body.
+    async fn http(addr: SocketAddr, method: &str, path: &str) -> (u16, Value) {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let request =
+            format!("{method} {path} HTTP/1.1\r\nHost: test\r\nConnection: close\r\n\r\n");
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        tokio::io::AsyncReadExt::read_to_string(&mut stream, &mut response)
+            .await
+            .unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        let status = head.split(' ').nth(1).unwrap().parse().unwrap();
+        (status, serde_json::from_str(body).unwrap_or(Value::Null))
+    }
+
+    #[tokio::test]
+    async fn sessions_are_addressed_by_id() {
+        let config = sample_config(4001);
+        let default = sample_state(config.clone());
+        let second = config.for_session(&NewSession {
+            debugserver_port: 4002,
+            program: None,
+            dsym: None,
+            device: Some("OTHER".into()),
+            bundle_id: None,
+            app_bundle: None,
+        });
+        let id = default.sessions.next_id();
+        default
+            .sessions
+            .insert(sample_parts().into_state(id.clone(), second, &default));
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = router(default).into_make_service_with_connect_info::<SocketAddr>();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let (status, body) = http(addr, "GET", "/sessions").await;
+        assert_eq!(status, 200);
+        assert_eq!(body["sessions"].as_array().unwrap().len(), 2);
+        let (_, body) = http(addr, "GET", "/health").await;
+        assert_eq!(body["debugserverPort"], 4001);
+        let (_, body) = http(addr, "GET", &format!("/sessions/{id}/health")).await;
+        assert_eq!(
+            (body["debugserverPort"].clone(), body["device"].clone()),
+            (json!(4002), json!("OTHER"))
+        );
+        let (status, body) = http(addr, "GET", &format!("/sessions/{id}/jobs/7")).await;
+        assert_eq!(
+            (status, body["error"]["code"].clone()),
+            (404, json!("JOB_NOT_FOUND"))
+        );
+        let (status, body) = http(addr, "GET", "/sessions/nope/status").await;
+        assert_eq!(
+            (status, body["error"]["code"].clone()),
+            (404, json!("SESSION_NOT_FOUND"))
+        );
+
+        assert_eq!(http(addr, "DELETE", "/sessions/default").await.0, 409);
+        assert_eq!(
+            http(addr, "DELETE", &format!("/sessions/{id}")).await.0,
+            200
+        );
+        assert_eq!(
+            http(addr, "GET", &format!("/sessions/{id}/health")).await.0,
+            404
+        );
+        let (_, body) = http(addr, "GET", "/sessions").await;
+        assert_eq!(body["sessions"][0]["id"], DEFAULT_SESSION);
+    }
+
+    #[tokio::test]
+    async fn artifacts_and_protocol_traces_are_private_to_their_session() {
+        let mut config = sample_config(4001);
+        config.api_tokens =
+            parse_api_tokens(&["alice=secret-a".into(), "bob=secret-b".into()]).unwrap();
+        let default = sample_state(config.clone());
+        let path =
+            std::env::temp_dir().join(format!("swiftscope-trace-{}.log", std::process::id()));
+        let trace = ProtocolTrace::create(&path, DEFAULT_SESSION).unwrap();
+        let id = default.sessions.next_id();
+        let session_trace = Arc::new(trace.for_session(&id));
+        session_trace.record(Direction::Send, b"m1000,8");
+        let parts = SessionParts {
+            trace: Some(session_trace),
+            ..sample_parts()
+        };
+        let second = config.for_session(&NewSession {
+            debugserver_port: 4002,
+            program: None,
+            dsym: None,
+            device: None,
+            bundle_id: None,
+            app_bundle: None,
+        });
+        let session = parts.into_state(id.clone(), second, &default);
+        *session.owner.lock().await = Some("alice".into());
+        session
+            .artifacts
+            .add("report", "report.json", b"{}")
+            .unwrap();
+        default.sessions.insert(session);
+
+        let (status, body) = create_session(
+            State(default.clone()),
+            None,
+            Json(NewSession {
+                debugserver_port: 4002,
+                program: None,
+                dsym: None,
+                device: None,
+                bundle_id: None,
+                app_bundle: None,
+            }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        let message = body["error"]["message"].as_str().unwrap();
+        assert_eq!(message, "debugserver port 4002 is already in use");
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = router(default).into_make_service_with_connect_info::<SocketAddr>();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let alice = |route: &str| format!("/sessions/{id}{route}?token=secret-a");
+        let bob = |route: &str| format!("/sessions/{id}{route}?token=secret-b");
+        let (status, body) = http(addr, "GET", &alice("/artifacts")).await;
+        assert_eq!(
+            (status, body["artifacts"][0]["name"].clone()),
+            (200, json!("report.json"))
+        );
+        assert_eq!(http(addr, "GET", &alice("/artifacts/1")).await.0, 200);
+        let (status, body) = http(addr, "GET", &alice("/protocol-trace/tail")).await;
+        assert_eq!(
+            (status, body["packets"][0]["packet"].clone()),
+            (200, json!("m1000,8"))
+        );
+        for route in ["/artifacts", "/artifacts/1", "/protocol-trace/tail"] {
+            let (status, body) = http(addr, "GET", &bob(route)).await;
+            assert_eq!(
+                (status, body["error"]["code"].clone()),
+                (404, json!("SESSION_NOT_FOUND")),
+                "{route}"
+            );
+        }
+        // Bob's own session has neither Alice's artifact nor her packets.
+        let (_, body) = http(addr, "GET", "/artifacts?token=secret-b").await;
+        assert_eq!(body["artifacts"], json!([]));
+        let (status, _) = http(addr, "GET", "/protocol-trace/tail?token=secret-b").await;
+        assert_eq!(status, 404, "the default session was not given a trace");
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[tokio::test]
+    async fn history_records_commands_and_appends_them_to_the_file() {
+        let path =
+            std::env::temp_dir().join(format!("swiftscope-history-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut config = sample_config(4001);
+        config.history_file = Some(path.clone());
+        let state = sample_state(config);
+        let run = |command: Value| {
+            let command = serde_json::from_value(command).unwrap();
+            dispatch_command(&state, command, CommandQuery::default())
+        };
+        let _ = run(json!({ "action": "all_stacktraces", "max_frames": 2 })).await;
+        let _ = run(json!({ "action": "select_frame", "frame_index": 99 })).await;
+        let _ = run(json!({ "action": "cancel" })).await;
+
+        let all = state
+            .history
+            .query(&serde_json::from_value(json!({})).unwrap(), None);
+        let actions: Vec<_> = all.iter().map(|entry| entry.action).collect();
+        assert_eq!(actions, ["all_stacktraces", "select_frame", "cancel"]);
+        assert_eq!(all[0].params["max_frames"], 2);
+        assert!(all[0].params.get("action").is_none());
+        assert!(all[0].ok && !all[1].ok);
+        assert_eq!(all[1].result["ok"], false);
+        assert_eq!(all[0].session_id, DEFAULT_SESSION);
+
+        let failed: HistoryQuery = serde_json::from_value(json!({ "ok": false })).unwrap();
+        let failed = state.history.query(&failed, None);
+        // Nothing was running, so the cancel failed too.
+        let ids: Vec<_> = failed.iter().map(|entry| entry.id).collect();
+        assert_eq!(ids, [2, 3]);
+        let newest: HistoryQuery =
+            serde_json::from_value(json!({ "since": 1, "limit": 1 })).unwrap();
+        assert_eq!(state.history.query(&newest, None)[0].action, "cancel");
+        assert!(state.history.query(&newest, Some("alice")).is_empty());
+
+        let lines = std::fs::read_to_string(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        let logged: Vec<Value> = lines
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(logged.len(), 3);
+        assert_eq!(logged[2]["action"], "cancel");
+    }
+
+    #[tokio::test]
+    async fn retried_request_ids_replay_the_first_reply() {
+        let state = sample_state(sample_config(4001));
+        let run = |command: Value, request_id: &str| {
+            let command = serde_json::from_value(command).unwrap();
+            let query = CommandQuery {
+                request_id: Some(request_id.into()),
+                ..CommandQuery::default()
+            };
+            dispatch_command(&state, command, query)
+        };
+        let set = json!({ "action": "set_breakpoint", "file": "bin/ios_llm_api.rs", "line": 1 });
+        let (status, Json(first)) = run(set.clone(), "bp-1").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(first["request_id"], "bp-1");
+        assert!(first.get("replayed").is_none());
+        let (status, Json(retry)) = run(set, "bp-1").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(retry["replayed"], true);
+        assert_eq!(retry["breakpoint_id"], first["breakpoint_id"]);
+        // The same id on another command is refused rather than answered
+        // with the first command's reply.
+        let other = json!({ "action": "set_breakpoint", "file": "bin/ios_llm_api.rs", "line": 2 });
+        let (status, Json(conflict)) = run(other, "bp-1").await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(conflict["error"]["code"], "REQUEST_ID_CONFLICT");
+        assert_eq!(conflict["request_id"], "bp-1");
+
+        // Read-only commands just run again.
+        let (_, Json(stack)) = run(json!({ "action": "stacktrace" }), "st").await;
+        let (_, Json(again)) = run(json!({ "action": "stacktrace" }), "st").await;
+        assert_eq!(stack["request_id"], "st");
+        assert!(again.get("replayed").is_none());
+
+        let history = state
+            .history
+            .query(&serde_json::from_value(json!({})).unwrap(), None);
+        let ran: Vec<_> = history
+            .iter()
+            .map(|entry| (entry.action, entry.request_id.as_deref()))
+            .collect();
+        assert_eq!(
+            ran,
+            [
+                ("set_breakpoint", Some("bp-1")),
+                ("stacktrace", Some("st")),
+                ("stacktrace", Some("st")),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn responses_are_truncated_to_the_request_limits() {
+        let mut config = sample_config(4001);
+        config.limits.max_string_len = Some(4);
+        let state = sample_state(config);
+        let command = |value: Value| serde_json::from_value::<LlmCommand>(value).unwrap();
+        let query = CommandQuery {
+            max_children: Some(0),
+            ..CommandQuery::default()
+        };
+        for line in [1, 2] {
+            let set =
+                json!({ "action": "set_breakpoint", "file": "bin/ios_llm_api.rs", "line": line });
+            let _ = dispatch_command(&state, command(set), CommandQuery::default()).await;
+        }
+        let (_, Json(body)) = dispatch_command(
+            &state,
+            command(json!({ "action": "export_breakpoints" })),
+            query,
+        )
+        .await;
+        assert_eq!(body["breakpoints"], json!(["…(+2 more items)"]), "{body}");
+        let (_, Json(body)) = dispatch_command(
+            &state,
+            command(json!({ "action": "select_frame", "frame_index": 99 })),
+            CommandQuery::default(),
+        )
+        .await;
+        let message = body["error"]["message"].as_str().unwrap();
+        assert!(message.ends_with("more bytes)"), "{message}");
+        assert!(!body["error"]["code"].as_str().unwrap().contains('…'));
+
+        let message: SocketCommand = serde_json::from_value(json!({
+            "action": "all_stacktraces",
+            "max_frames": 3,
+            "max_string_len": 10,
+        }))
+        .unwrap();
+        assert_eq!(message.query.max_frames, Some(3));
+        assert_eq!(message.query.max_string_len, Some(10));
+    }
+
+    #[tokio::test]
+    async fn replies_follow_the_requested_response_format() {
+        let state = sample_state(sample_config(4001));
+        let message: SocketCommand = serde_json::from_value(json!({
+            "action": "stacktrace",
+            "response_format": "compact",
+        }))
+        .unwrap();
+        let (_, Json(body)) = dispatch_command(&state, message.command, message.query).await;
+        assert!(body.get("st").is_some(), "{body}");
+
+        let query = CommandQuery {
+            response_format: Some(ResponseFormat::Markdown),
+            ..CommandQuery::default()
+        };
+        let (status, Json(body)) = dispatch_command(
+            &state,
+            serde_json::from_value(json!({ "action": "select_frame", "frame_index": 99 })).unwrap(),
+            query,
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body.as_str().unwrap().starts_with("**Error** `"), "{body}");
+    }
+
+    /// [`sample_state`] over a mock target that is not attached yet: one
+    /// thread stopped at line 12, which a breakpoint (described with an email
+    /// address) and then a step move on.
+    async fn mock_state() -> AppState {
+        let state = sample_state(sample_config(4001));
+        let exe = std::env::current_exe().expect("current exe");
+        let image = Image {
+            name: "test".into(),
+            path: exe.clone(),
+            uuid: None,
+            dsym: None,
+            slice: None,
+            vmaddr_text: 0,
+            slide: 0,
+            dwarf: Loader::new(&exe).expect("loader"),
+        };
+        let frame = |line| json!({ "function": "ContentView.increment()", "line": line });
+        let fixture: MockFixture = serde_json::from_value(json!({
+            "threads": [{ "id": 1, "name": "main", "frames": [frame(12)] }],
+            "stops": [
+                { "reason": "breakpoint", "description": "signed in as ada@example.com" },
+                { "reason": "step", "threads": [{ "id": 1, "frames": [frame(13)] }] }
+            ]
+        }))
+        .unwrap();
+        *state.session.lock().await = DebugSession::new(Backend::new_mock(
+            SymbolContext::for_testing(image),
+            fixture,
+        ));
+        state
+    }
+
+    async fn run(state: &AppState, command: Value) -> (StatusCode, Value) {
+        let command = serde_json::from_value(command).unwrap();
+        let (status, Json(body)) = dispatch_command(state, command, CommandQuery::default()).await;
+        (status, body)
+    }
+
+    #[tokio::test]
+    async fn commands_reuse_the_prewarmed_session() {
+        let state = mock_state().await;
+        let warmup = spawn_prewarm(&state).await;
+        // Queued behind the warm-up, so it finds the target attached.
+        let (status, body) = run(&state, json!({ "action": "threads" })).await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+        assert_eq!(body["threads"][0]["id"], 1, "{body}");
+        warmup.await.unwrap();
+
+        let (status, body) = run(&state, json!({ "action": "continue" })).await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+        let (status, body) = run(&state, json!({ "action": "prewarm" })).await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+        assert_eq!(body["alreadyWarm"], true, "{body}");
+        // Reconnecting would have started the fixture over at its first stop.
+        let (_, body) = run(&state, json!({ "action": "continue" })).await;
+        assert_eq!(body["stop"]["reason"], "step", "{body}");
+    }
+
+    #[tokio::test]
+    async fn server_commands_report_missing_options_by_code() {
+        let state = mock_state().await;
+        for command in [
+            json!({ "action": "build" }),
+            json!({ "action": "restart" }),
+            json!({ "action": "attach", "pid": 42 }),
+        ] {
+            let (_, body) = run(&state, command).await;
+            assert_eq!(body["error"]["code"], "NOT_CONFIGURED", "{body}");
+            assert!(body["error"]["hint"].is_string(), "{body}");
+        }
+        let (_, body) = run(&state, json!({ "action": "attach" })).await;
+        assert_eq!(body["error"]["code"], "INVALID_ARGUMENT", "{body}");
+    }
+
+    #[tokio::test]
+    async fn status_redacts_the_stop_and_uses_snake_case() {
+        let state = mock_state().await;
+        run(&state, json!({ "action": "prewarm" })).await;
+        run(&state, json!({ "action": "continue" })).await;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = router(state).into_make_service_with_connect_info::<SocketAddr>();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let (status, body) = http(addr, "GET", "/status").await;
+        assert_eq!(status, 200, "{body}");
+        assert_eq!(
+            body["stop"]["description"], "signed in as <redacted>",
+            "{body}"
+        );
+        assert_eq!(body["debugserver_port"], 4001, "{body}");
+        assert!(body.get("debugserverPort").is_none() && body.get("bundleId").is_none());
+    }
+
+    #[tokio::test]
+    async fn failed_prewarm_is_reported_and_can_be_retried() {
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let state = sample_state(sample_config(port));
+        state.session.lock().await.set_connect_attempts(1);
+
+        let (status, body) = run(&state, json!({ "action": "prewarm" })).await;
+        assert_ne!(status, StatusCode::OK, "{body}");
+        assert_eq!(body["ok"], false, "{body}");
+        let message = body["error"]["message"].as_str().unwrap_or_default();
+        assert!(message.contains("gave up after 1 attempts"), "{body}");
+        assert!(!state.session.lock().await.is_connected());
+
+        let (_, again) = run(&state, json!({ "action": "prewarm" })).await;
+        assert_eq!(
+            again["ok"], false,
+            "a failed prewarm is not remembered as warm"
+        );
+    }
+
+    #[tokio::test]
+    async fn context_bundles_the_stop_and_the_log_tail() {
+        let state = sample_state(sample_config(4001));
+        for line in 0..RECENT_LOG_CAPACITY + 3 {
+            state.recent_logs.push(format!("[log] line {line}"));
+        }
+        let command = json!({ "action": "context", "frames": 1, "log_lines": 2 });
+        let (status, Json(body)) = dispatch_command(
+            &state,
+            serde_json::from_value(command).unwrap(),
+            CommandQuery::default(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+        assert_eq!(
+            body["logs"],
+            json!(["[log] line 501", "[log] line 502"]),
+            "{body}"
+        );
+        assert!(body["stop"].is_null());
+        assert!(body["frames"].as_array().unwrap().len() <= 1);
+        assert!(body["locals"].is_array() || body["locals"].is_object());
+        assert_eq!(body["watch"], json!([]));
+        assert_eq!(
+            state.recent_logs.last(usize::MAX).len(),
+            RECENT_LOG_CAPACITY
+        );
+    }
+
+    #[test]
+    fn command_bodies_carry_an_optional_request_id() {
+        let body: CommandBody =
+            serde_json::from_value(json!({ "action": "launch", "request_id": "r1" })).unwrap();
+        assert_eq!(body.request_id.as_deref(), Some("r1"));
+        assert_eq!(body.command.action(), "launch");
+    }
+
+    #[tokio::test]
+    async fn tls_config_errors_name_the_files() {
+        let dir = std::env::temp_dir();
+        let cert = dir.join("swiftscope-missing-cert.pem");
+        let err = load_tls_config(&cert, &dir.join("swiftscope-missing-key.pem"))
+            .await
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("swiftscope-missing-cert.pem"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn rate_limits_are_per_client_and_refill() {
+        let limiter = RateLimiter::new(2);
+        let start = Instant::now();
+        assert!(limiter.check("alice", start).is_ok());
+        assert!(limiter.check("alice", start).is_ok());
+        let wait = limiter.check("alice", start).unwrap_err();
+        assert_eq!(wait, Duration::from_secs(30));
+        assert!(limiter.check("bob", start).is_ok());
+        assert!(limiter.check("alice", start + wait).is_ok());
+
+        let response = Throttled {
+            code: "RATE_LIMITED",
+            message: "more than 2 commands a minute".into(),
+            retry_after: Duration::from_millis(1500),
+        }
+        .into_response();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "2");
+
+        let peer: SocketAddr = "10.0.0.7:51000".parse().unwrap();
+        assert_eq!(client_key(None, peer), "10.0.0.7");
+        assert_eq!(client_key(Some(&Tenant("alice".into())), peer), "alice");
+    }
+
+    #[tokio::test]
+    async fn high_priority_commands_jump_the_queue() {
+        let queue = Arc::new(CommandQueue::default());
+        let running = queue.enter("continue", Priority::Normal).await;
+
+        let (order_tx, mut order) = mpsc::unbounded_channel();
+        let mut waiters = Vec::new();
+        for (action, priority) in [("build", Priority::Normal), ("threads", Priority::High)] {
+            let waiting = queue.clone();
+            let order_tx = order_tx.clone();
+            waiters.push(tokio::spawn(async move {
+                let _ticket = waiting.enter(action, priority).await;
+                order_tx.send(action).unwrap();
+            }));
+            // Queue them in a fixed order.
+            while queue.snapshot().len() < waiters.len() + 1 {
+                tokio::task::yield_now().await;
+            }
+        }
+        let snapshot = queue.snapshot();
+        let listed: Vec<_> = snapshot
+            .iter()
+            .map(|command| (command["action"].clone(), command["state"].clone()))
+            .collect();
+        assert_eq!(
+            listed,
+            [
+                (json!("continue"), json!("running")),
+                (json!("threads"), json!("queued")),
+                (json!("build"), json!("queued")),
+            ]
+        );
+
+        // A waiter that gives up leaves the queue.
+        let abandoned = {
+            let queue = queue.clone();
+            tokio::spawn(async move { queue.enter("modules", Priority::Normal).await })
+        };
+        while queue.snapshot().len() < 4 {
+            tokio::task::yield_now().await;
+        }
+        abandoned.abort();
+        let _ = abandoned.await;
+        assert_eq!(queue.snapshot().len(), 3);
+        assert_eq!(queue.waiting(), 2);
+
+        drop(running);
+        for waiter in waiters {
+            waiter.await.unwrap();
+        }
+        assert_eq!(order.recv().await, Some("threads"));
+        assert_eq!(order.recv().await, Some("build"));
+        assert!(queue.snapshot().is_empty());
+
+        assert!(LlmCommand::Registers.is_inspection());
+        assert!(!LlmCommand::Continue.is_inspection());
+    }
+
+    #[tokio::test]
+    async fn status_events_only_flow_while_running() {
+        let (log_tx, mut lines) = broadcast::channel(16);
+        let (event_tx, mut events) = broadcast::channel(16);
+        let running = Arc::new(RunTracker::default());
+        let task = spawn_status_events(
+            log_tx.clone(),
+            event_tx,
+            running.clone(),
+            Duration::from_millis(10),
+        );
+        sleep(Duration::from_millis(30)).await;
+        assert!(lines.try_recv().is_err(), "no status while stopped");
+        assert!(events.try_recv().is_err(), "no status event while stopped");
+
+        running.start("continue");
+        log_tx.send("[log] tick".into()).unwrap();
+        // The first tick may race the log line; wait for one that saw it.
+        let status = loop {
+            let line = lines.recv().await.unwrap();
+            let Some(status) = line.strip_prefix(STATUS_TAG) else {
+                continue;
+            };
+            let status = serde_json::from_str::<Value>(status).unwrap();
+            if status["last_log_ms_ago"].is_u64() {
+                break status;
+            }
+        };
+        assert_eq!(status["action"], "continue");
+        assert_eq!(status["state"], "running");
+
+        let event = events.recv().await.unwrap();
+        assert_eq!(event["event"], "status");
+        assert_eq!(event["action"], "continue");
+        assert!(event["running_ms"].is_u64());
+        task.abort();
+    }
+
+    #[test]
+    fn jobs_record_results_and_cancellation() {
+        let jobs = JobTable::default();
+        let done = jobs.create("continue");
+        assert_eq!(jobs.view(done).unwrap()["status"], "running");
+        jobs.finish(done, StatusCode::OK, json!({ "ok": true }));
+        let view = jobs.view(done).unwrap();
+        assert_eq!(view["status"], "succeeded");
+        assert_eq!(view["http_status"], 200);
+        assert_eq!(view["result"]["ok"], true);
+        assert!(matches!(
+            jobs.request_cancel(done),
+            Some(Err(JobStatus::Succeeded))
+        ));
+
+        let failed = jobs.create("build");
+        jobs.finish(failed, StatusCode::BAD_REQUEST, json!({ "ok": false }));
+        assert_eq!(jobs.view(failed).unwrap()["status"], "failed");
+
+        // An interrupted command still reports the stop it returned with.
+        let interrupted = jobs.create("continue");
+        assert!(jobs.request_cancel(interrupted).unwrap().is_ok());
+        jobs.finish(interrupted, StatusCode::OK, json!({ "ok": true }));
+        let view = jobs.view(interrupted).unwrap();
+        assert_eq!(view["status"], "cancelled");
+        assert_eq!(view["result"]["ok"], true);
+
+        let aborted = jobs.create("launch");
+        jobs.cancelled(aborted);
+        jobs.finish(aborted, StatusCode::OK, json!({ "ok": true }));
+        let view = jobs.view(aborted).unwrap();
+        assert_eq!(view["status"], "cancelled");
+        assert!(view.get("result").is_none());
+        assert!(jobs.view(aborted + 1).is_none());
+    }
+
+    #[test]
+    fn cancel_needs_an_interruptible_command() {
+        let command: LlmCommand = serde_json::from_value(json!({ "action": "cancel" })).unwrap();
+        assert!(matches!(command, LlmCommand::Cancel));
+        assert!(!command.is_batchable());
+        assert!(!command.is_inspection());
+
+        let running = RunTracker::default();
+        let jobs = JobTable::default();
+        let job = jobs.create("continue");
+        assert_eq!(running.interrupt_current(&jobs), None);
+        // Without a gdb-remote connection there is nothing to interrupt.
+        running.start("continue");
+        assert_eq!(running.interrupt_current(&jobs), None);
+        assert!(!running.interrupt(job));
+        assert!(jobs.request_cancel(job).unwrap().is_ok());
+    }
+
+    #[test]
+    fn socket_messages_carry_command_options() {
+        let message: SocketCommand = serde_json::from_value(json!({
+            "id": 7,
+            "action": "threads",
+            "priority": "high",
+        }))
+        .unwrap();
+        assert!(matches!(message.command, LlmCommand::Threads));
+        assert_eq!(message.query.priority, Priority::High);
+        assert!(!message.query.background);
+
+        let message: SocketCommand = serde_json::from_value(json!({
+            "action": "locals",
+            "async": true,
+        }))
+        .unwrap();
+        assert!(matches!(message.command, LlmCommand::Locals { .. }));
+        assert!(message.query.background);
+        assert!(serde_json::from_value::<SocketCommand>(json!({ "id": 1 })).is_err());
+    }
+
+    #[test]
+    fn failures_carry_a_code_and_hint() {
+        let mut session = sample_session();
+        let err = execute_command(&mut session, LlmCommand::Registers).unwrap_err();
+        let body = error_body(&err);
+        assert_eq!(body["ok"], json!(false));
+        assert_eq!(body["error"]["code"], "NOT_CONNECTED");
+        assert!(body["error"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("no gdb-remote connection"));
+        assert!(body["error"]["hint"].is_string());
+
+        let body = error_body(&DebugSessionError::Timeout(250));
+        assert_eq!(body["error"]["code"], "TIMEOUT");
+        assert_eq!(body["timed_out"], json!(true));
+        assert_eq!(body["timeout_ms"], json!(250));
+
+        let body = error_body(&DebugSessionError::InvalidArgument("bad".into()));
+        assert_eq!(
+            body["error"],
+            json!({ "code": "INVALID_ARGUMENT", "message": "bad" })
+        );
+    }
+
+    #[test]
+    fn jobs_evict_only_finished_entries() {
+        let jobs = JobTable::default();
+        let running = jobs.create("continue");
+        for _ in 0..JOB_CAPACITY + 5 {
+            let id = jobs.create("threads");
+            jobs.finish(id, StatusCode::OK, json!({ "ok": true }));
+        }
+        assert_eq!(jobs.view(running).unwrap()["status"], "running");
+        assert!(jobs.view(running + 1).is_none());
+    }
+
     #[cfg(target_family = "unix")]
     #[tokio::test]
     async fn build_runner_executes_command() {
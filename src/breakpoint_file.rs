@@ -0,0 +1,248 @@
+//! Breakpoint lists for `export_breakpoints` and `import_breakpoints`.
+//!
+//! The native format is `{ "breakpoints": [{ file, line, message?, temporary? }] }`.
+//! Imports also accept LLDB `breakpoint write` JSON and, best effort, Xcode's
+//! `Breakpoints_v2.xcbkptlist`: enabled file breakpoints are kept, one whose
+//! log action continues becomes a logpoint, and anything without a file and
+//! line (symbolic, exception, disabled) is counted as skipped.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BreakpointSpec {
+    pub file: String,
+    pub line: u32,
+    /// Logpoint message template; the breakpoint does not stop.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub temporary: bool,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ParsedBreakpoints {
+    pub breakpoints: Vec<BreakpointSpec>,
+    /// Entries with no file-and-line equivalent.
+    pub skipped: usize,
+}
+
+/// Reads a native, LLDB or Xcode breakpoint list, telling them apart by shape.
+pub fn parse(text: &str) -> Result<ParsedBreakpoints, String> {
+    let text = text.trim_start();
+    if text.starts_with('<') {
+        return Ok(parse_xcode(text));
+    }
+    let value: Value =
+        serde_json::from_str(text).map_err(|err| format!("not a breakpoint list: {err}"))?;
+    let native = |list: Value| {
+        serde_json::from_value(list)
+            .map(|breakpoints| ParsedBreakpoints {
+                breakpoints,
+                skipped: 0,
+            })
+            .map_err(|err| format!("invalid breakpoint list: {err}"))
+    };
+    match value {
+        Value::Array(items) if items.iter().all(|item| item.get("Breakpoint").is_some()) => {
+            Ok(parse_lldb(&items))
+        }
+        Value::Array(_) => native(value),
+        Value::Object(mut map) if map.contains_key("breakpoints") => {
+            native(map.remove("breakpoints").unwrap_or_default())
+        }
+        _ => Err(
+            "expected `{ \"breakpoints\": [...] }`, LLDB `breakpoint write` JSON or an .xcbkptlist"
+                .into(),
+        ),
+    }
+}
+
+/// Renders `breakpoints` as LLDB `breakpoint read` input. LLDB has no
+/// logpoint equivalent, so messages are dropped.
+pub fn to_lldb(breakpoints: &[BreakpointSpec]) -> Value {
+    breakpoints
+        .iter()
+        .map(|breakpoint| {
+            json!({
+                "Breakpoint": {
+                    "BKPTOptions": {
+                        "AutoContinue": false,
+                        "ConditionText": "",
+                        "EnabledState": true,
+                        "IgnoreCount": 0,
+                        "OneShotState": breakpoint.temporary,
+                    },
+                    "BKPTResolver": {
+                        "Options": {
+                            "Column": 0,
+                            "Exact": false,
+                            "FileName": breakpoint.file,
+                            "Inlines": true,
+                            "LineNumber": breakpoint.line,
+                            "Offset": 0,
+                            "SkipPrologue": true,
+                        },
+                        "Type": "FileAndLine",
+                    },
+                    "Hardware": false,
+                    "SearchFilter": { "Options": {}, "Type": "Unconstrained" },
+                }
+            })
+        })
+        .collect()
+}
+
+fn parse_lldb(items: &[Value]) -> ParsedBreakpoints {
+    let mut parsed = ParsedBreakpoints::default();
+    for item in items {
+        let breakpoint = &item["Breakpoint"];
+        let options = &breakpoint["BKPTOptions"];
+        let resolver = &breakpoint["BKPTResolver"];
+        let file = resolver["Options"]["FileName"].as_str();
+        let line = resolver["Options"]["LineNumber"].as_u64();
+        match (resolver["Type"].as_str(), file, line) {
+            (Some("FileAndLine"), Some(file), Some(line))
+                if options["EnabledState"].as_bool() != Some(false) =>
+            {
+                parsed.breakpoints.push(BreakpointSpec {
+                    file: file.to_string(),
+                    line: line as u32,
+                    message: None,
+                    temporary: options["OneShotState"].as_bool().unwrap_or(false),
+                });
+            }
+            _ => parsed.skipped += 1,
+        }
+    }
+    parsed
+}
+
+fn parse_xcode(xml: &str) -> ParsedBreakpoints {
+    let mut parsed = ParsedBreakpoints::default();
+    for proxy in xml.split("<BreakpointProxy").skip(1) {
+        let file = attribute(proxy, "filePath");
+        let line = attribute(proxy, "startingLineNumber").and_then(|line| line.parse().ok());
+        let (Some(file), Some(line)) = (file, line) else {
+            parsed.skipped += 1;
+            continue;
+        };
+        if !proxy.contains("Xcode.Breakpoint.FileBreakpoint")
+            || attribute(proxy, "shouldBeEnabled").as_deref() == Some("No")
+        {
+            parsed.skipped += 1;
+            continue;
+        }
+        let continues = attribute(proxy, "continueAfterRunningActions").as_deref() == Some("Yes");
+        let message = proxy
+            .split("<BreakpointActionProxy")
+            .skip(1)
+            .filter(|action| action.contains("Xcode.BreakpointAction.Log"))
+            .find_map(|action| attribute(action, "message"))
+            .filter(|_| continues)
+            .map(|message| xcode_log_template(&message));
+        // Xcode stores paths relative to the project; `../` would defeat
+        // suffix matching.
+        let mut file = file.as_str();
+        while let Some(rest) = file.strip_prefix("../") {
+            file = rest;
+        }
+        parsed.breakpoints.push(BreakpointSpec {
+            file: file.to_string(),
+            line,
+            message,
+            temporary: false,
+        });
+    }
+    parsed
+}
+
+/// Xcode writes expressions as `@expr@`; logpoints use `{expr}`.
+fn xcode_log_template(message: &str) -> String {
+    let mut out = String::with_capacity(message.len());
+    for (index, part) in message.split('@').enumerate() {
+        if index % 2 == 1 {
+            out.push('{');
+            out.push_str(part);
+            out.push('}');
+        } else {
+            out.push_str(&part.replace('{', "{{").replace('}', "}}"));
+        }
+    }
+    out
+}
+
+/// Xcode writes attributes as `name = "value"`.
+fn attribute(element: &str, name: &str) -> Option<String> {
+    let key = format!(" {name}");
+    let value = element.match_indices(&key).find_map(|(at, _)| {
+        let rest = element[at + key.len()..].trim_start().strip_prefix('=')?;
+        rest.trim_start().strip_prefix('"')
+    })?;
+    let end = value.find('"')?;
+    Some(
+        value[..end]
+            .replace("&quot;", "\"")
+            .replace("&apos;", "'")
+            .replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&amp;", "&"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_xcode_lists_and_round_trips_lldb() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Bucket uuid="A" type="1" version="2.0">
+   <Breakpoints>
+      <BreakpointProxy BreakpointExtensionID = "Xcode.Breakpoint.FileBreakpoint">
+         <BreakpointContent uuid="B" shouldBeEnabled = "Yes" continueAfterRunningActions = "Yes"
+            filePath = "../MyApp/ViewController.swift" startingLineNumber = "42" endingLineNumber = "42">
+            <Actions>
+               <BreakpointActionProxy ActionExtensionID = "Xcode.BreakpointAction.Log">
+                  <ActionContent message = "count=@count@ &quot;{raw}&quot;" conveyanceType = "0">
+                  </ActionContent>
+               </BreakpointActionProxy>
+            </Actions>
+         </BreakpointContent>
+      </BreakpointProxy>
+      <BreakpointProxy BreakpointExtensionID = "Xcode.Breakpoint.FileBreakpoint">
+         <BreakpointContent uuid="C" shouldBeEnabled = "No"
+            filePath = "MyApp/Model.swift" startingLineNumber = "7" endingLineNumber = "7">
+         </BreakpointContent>
+      </BreakpointProxy>
+      <BreakpointProxy BreakpointExtensionID = "Xcode.Breakpoint.SymbolicBreakpoint">
+         <BreakpointContent uuid="D" shouldBeEnabled = "Yes" symbolName = "objc_exception_throw">
+         </BreakpointContent>
+      </BreakpointProxy>
+   </Breakpoints>
+</Bucket>"#;
+        let parsed = parse(xml).unwrap();
+        assert_eq!(parsed.skipped, 2);
+        assert_eq!(
+            parsed.breakpoints,
+            [BreakpointSpec {
+                file: "MyApp/ViewController.swift".into(),
+                line: 42,
+                message: Some("count={count} \"{{raw}}\"".into()),
+                temporary: false,
+            }]
+        );
+
+        let specs = [BreakpointSpec {
+            file: "/src/App.swift".into(),
+            line: 3,
+            message: None,
+            temporary: true,
+        }];
+        let lldb = to_lldb(&specs).to_string();
+        assert_eq!(parse(&lldb).unwrap().breakpoints, specs);
+        let native = json!({ "breakpoints": specs }).to_string();
+        assert_eq!(parse(&native).unwrap().breakpoints, specs);
+        assert!(parse("{}").is_err());
+    }
+}
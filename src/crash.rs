@@ -0,0 +1,318 @@
+//! Heuristics that turn a crash stop into a one-line hypothesis, backing the
+//! `diagnosis` of stops.
+//!
+//! Each rule looks at the Mach exception, the crashing thread's function
+//! names, the other threads' stack pointers and the console output the app
+//! wrote just before it died. The first rule that matches wins, so the more
+//! specific ones come first.
+
+use crate::gdb_remote::MachException;
+
+/// Faults below this address are taken as null pointer dereferences: a field
+/// read through `nil` faults at the field's offset, which stays small.
+const NULL_PAGE_END: u64 = 0x4000;
+/// How far from a thread's stack pointer a fault still counts as hitting the
+/// guard page below its stack; one frame may reserve a lot at once.
+const GUARD_DISTANCE: u64 = 64 * 1024;
+/// Highest user-space address on arm64; anything above is a corrupt pointer
+/// or one whose pointer authentication code did not check out.
+const USER_ADDRESS_END: u64 = 0x0000_8000_0000_0000;
+/// How many frames from the top are searched for a runtime failure function.
+const FAILURE_FRAME_WINDOW: usize = 12;
+
+const EXC_BAD_INSTRUCTION: u32 = 2;
+const EXC_ARITHMETIC: u32 = 3;
+/// How debugserver reports a plain Unix signal (`EXC_SOFT_SIGNAL`).
+const EXC_SOFTWARE: u32 = 5;
+const EXC_BREAKPOINT: u32 = 6;
+const EXC_RESOURCE: u32 = 11;
+const EXC_GUARD: u32 = 12;
+/// `KERN_PROTECTION_FAILURE`: the page is mapped, the access is not allowed.
+const KERN_PROTECTION_FAILURE: u64 = 2;
+const SIGABRT: u8 = 6;
+/// `SIGILL`, `SIGTRAP`, `SIGABRT`, `SIGFPE`, `SIGBUS`, `SIGSEGV` and `SIGSYS`;
+/// other signals, such as the `SIGSTOP` of a pause, are not crashes.
+const CRASH_SIGNALS: &[u8] = &[4, 5, 6, 8, 10, 11, 12];
+
+/// Lines the Swift runtime prints before trapping.
+const SWIFT_FAILURE_MARKERS: &[&str] =
+    &["Fatal error:", "Precondition failed:", "Assertion failed:"];
+const OBJC_EXCEPTION_MARKER: &str = "Terminating app due to uncaught exception";
+
+/// What is known about a crash when the target stops.
+#[derive(Debug, Default)]
+pub struct Crash<'a> {
+    pub exception: Option<&'a MachException>,
+    pub signal: u8,
+    pub thread_id: i64,
+    /// Function names of the crashing thread, innermost first.
+    pub functions: &'a [String],
+    /// Every thread's stack pointer.
+    pub stack_pointers: &'a [(i64, u64)],
+    /// Permissions of the region holding the fault address; empty when it is
+    /// unmapped, `None` when it could not be read.
+    pub fault_region: Option<&'a str>,
+    /// The app's console output since the previous stop.
+    pub console: &'a str,
+}
+
+/// A plain-language guess at what went wrong, e.g. `null pointer dereference
+/// (address 0x8)`.
+pub fn diagnose(crash: &Crash) -> Option<String> {
+    let signal_only = crash
+        .exception
+        .is_none_or(|exception| exception.exception_type == EXC_SOFTWARE);
+    if signal_only && !CRASH_SIGNALS.contains(&crash.signal) {
+        return None;
+    }
+    let top: Vec<&str> = crash
+        .functions
+        .iter()
+        .take(FAILURE_FRAME_WINDOW)
+        .map(String::as_str)
+        .collect();
+    let in_top = |names: &[&str]| top.iter().any(|f| names.iter().any(|n| f.contains(n)));
+
+    if in_top(&["objc_exception_throw", "objc_exception_rethrow"]) {
+        return Some(
+            match console_line(crash.console, &[OBJC_EXCEPTION_MARKER]) {
+                Some(line) => format!("uncaught Objective-C exception — {line}"),
+                None => "uncaught Objective-C exception".into(),
+            },
+        );
+    }
+    if let Some(message) = console_message(crash.console, SWIFT_FAILURE_MARKERS) {
+        return Some(format!("Swift runtime fatalError — message: {message}"));
+    }
+    if in_top(&["_assertionFailure", "fatalError", "preconditionFailure"]) {
+        return Some("Swift runtime fatalError (the message was not captured)".into());
+    }
+
+    let Some(exception) = crash.exception else {
+        return (crash.signal == SIGABRT).then(|| abort(&top));
+    };
+    match exception.exception_type {
+        MachException::BAD_ACCESS => Some(bad_access(crash, exception)),
+        EXC_BREAKPOINT => Some(format!(
+            "Swift runtime trap in {} — likely a force-unwrapped nil, an out-of-range \
+             index or an arithmetic overflow",
+            top.first().copied().unwrap_or("<unknown>")
+        )),
+        EXC_BAD_INSTRUCTION => Some("illegal instruction".into()),
+        EXC_ARITHMETIC => Some("arithmetic exception, e.g. integer division by zero".into()),
+        EXC_RESOURCE => Some("resource limit exceeded (CPU, memory or wakeups)".into()),
+        EXC_GUARD => Some("guarded resource misused, e.g. a guarded file descriptor closed".into()),
+        _ if crash.signal == SIGABRT => Some(abort(&top)),
+        _ => None,
+    }
+}
+
+fn bad_access(crash: &Crash, exception: &MachException) -> String {
+    let Some(address) = exception.fault_address() else {
+        return "bad memory access".into();
+    };
+    if address < NULL_PAGE_END {
+        return format!("null pointer dereference (address {address:#x})");
+    }
+    if address >= USER_ADDRESS_END {
+        return format!(
+            "corrupt or unauthenticated pointer ({address:#x} is outside the address space)"
+        );
+    }
+    let guarded = crash.fault_region.is_none_or(|perms| !perms.contains('w'));
+    let overflowed = crash
+        .stack_pointers
+        .iter()
+        // The crashing thread is the likeliest owner of a nearby guard page.
+        .filter(|(_, sp)| sp.abs_diff(address) <= GUARD_DISTANCE)
+        .min_by_key(|(thread_id, sp)| (*thread_id != crash.thread_id, sp.abs_diff(address)));
+    if let (true, Some((thread_id, _))) = (guarded, overflowed) {
+        return format!(
+            "stack overflow (fault address {address:#x} within guard page of thread \
+             {thread_id}'s stack)"
+        );
+    }
+    match crash.fault_region {
+        Some("") => format!(
+            "access to unmapped memory at {address:#x}, likely a dangling or corrupt pointer"
+        ),
+        Some(perms) if exception.code() == Some(KERN_PROTECTION_FAILURE) => {
+            format!("access not allowed by the page at {address:#x} (permissions `{perms}`)")
+        }
+        _ => format!("bad memory access at {address:#x}"),
+    }
+}
+
+/// Names the first frame above `abort` and the signal machinery under it.
+fn abort(top: &[&str]) -> String {
+    let caller = top.iter().find(|function| {
+        !["abort", "pthread_kill", "<unknown>"].contains(&function.trim_start_matches('_'))
+    });
+    match caller {
+        Some(caller) => format!("abort() called from {caller}"),
+        None => "abort() called".into(),
+    }
+}
+
+/// The last console line containing one of `markers`, trimmed.
+fn console_line<'a>(console: &'a str, markers: &[&str]) -> Option<&'a str> {
+    console
+        .lines()
+        .rev()
+        .find(|line| markers.iter().any(|marker| line.contains(marker)))
+        .map(str::trim)
+}
+
+/// What follows the marker on the last line carrying one, e.g. `Index out of
+/// range` from `Swift/Array.swift:418: Fatal error: Index out of range`.
+fn console_message(console: &str, markers: &[&str]) -> Option<String> {
+    let line = console_line(console, markers)?;
+    let (at, marker) = markers
+        .iter()
+        .filter_map(|marker| Some((line.find(marker)?, marker)))
+        .min()?;
+    let message = line[at + marker.len()..].trim();
+    Some(if message.is_empty() {
+        line.to_string()
+    } else {
+        message.to_string()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exception(exception_type: u32, data: &[u64]) -> MachException {
+        MachException {
+            exception_type,
+            data: data.to_vec(),
+        }
+    }
+
+    #[test]
+    fn bad_access_distinguishes_null_overflow_and_corrupt_pointers() {
+        let null = exception(1, &[1, 0x8]);
+        let crash = Crash {
+            exception: Some(&null),
+            signal: 11,
+            fault_region: Some(""),
+            ..Crash::default()
+        };
+        assert_eq!(
+            diagnose(&crash).unwrap(),
+            "null pointer dereference (address 0x8)"
+        );
+
+        let guard = exception(1, &[2, 0x1_6f5f_bff0]);
+        let stacks = [(1, 0x1_6fe0_0000), (5, 0x1_6f5f_c010)];
+        let crash = Crash {
+            exception: Some(&guard),
+            signal: 11,
+            thread_id: 5,
+            stack_pointers: &stacks,
+            fault_region: Some(""),
+            ..Crash::default()
+        };
+        assert_eq!(
+            diagnose(&crash).unwrap(),
+            "stack overflow (fault address 0x16f5fbff0 within guard page of thread 5's stack)"
+        );
+
+        let corrupt = exception(1, &[1, 0x2000_0001_0400_8000]);
+        let crash = Crash {
+            exception: Some(&corrupt),
+            signal: 11,
+            ..Crash::default()
+        };
+        assert!(diagnose(&crash)
+            .unwrap()
+            .starts_with("corrupt or unauthenticated pointer"));
+
+        let dangling = exception(1, &[1, 0x1_0b00_0000]);
+        let crash = Crash {
+            exception: Some(&dangling),
+            signal: 11,
+            fault_region: Some(""),
+            ..Crash::default()
+        };
+        assert_eq!(
+            diagnose(&crash).unwrap(),
+            "access to unmapped memory at 0x10b000000, likely a dangling or corrupt pointer"
+        );
+    }
+
+    #[test]
+    fn runtime_failures_quote_the_console() {
+        let trap = exception(6, &[1, 0x1_0000_4f2c]);
+        let functions = vec![
+            "Swift._assertionFailure(_: Swift.StaticString, _: Swift.String) -> Swift.Never"
+                .to_string(),
+            "MyApp.load() -> ()".to_string(),
+        ];
+        let crash = Crash {
+            exception: Some(&trap),
+            signal: 5,
+            functions: &functions,
+            console: "loading\nMyApp/Store.swift:12: Fatal error: Index out of range\n",
+            ..Crash::default()
+        };
+        assert_eq!(
+            diagnose(&crash).unwrap(),
+            "Swift runtime fatalError — message: Index out of range"
+        );
+
+        let functions = vec!["MyApp.load() -> ()".to_string()];
+        let crash = Crash {
+            exception: Some(&trap),
+            signal: 5,
+            functions: &functions,
+            ..Crash::default()
+        };
+        assert!(diagnose(&crash)
+            .unwrap()
+            .starts_with("Swift runtime trap in MyApp.load() -> ()"));
+
+        let functions = vec![
+            "__pthread_kill".to_string(),
+            "abort".to_string(),
+            "objc_exception_throw".to_string(),
+        ];
+        let crash = Crash {
+            signal: 6,
+            functions: &functions,
+            console: "*** Terminating app due to uncaught exception 'NSRangeException', \
+                      reason: 'index 3 beyond bounds'",
+            ..Crash::default()
+        };
+        assert_eq!(
+            diagnose(&crash).unwrap(),
+            "uncaught Objective-C exception — *** Terminating app due to uncaught exception \
+             'NSRangeException', reason: 'index 3 beyond bounds'"
+        );
+
+        let functions = vec![
+            "__pthread_kill".to_string(),
+            "abort".to_string(),
+            "MyApp.fail() -> ()".to_string(),
+        ];
+        let crash = Crash {
+            signal: 6,
+            functions: &functions,
+            ..Crash::default()
+        };
+        assert_eq!(
+            diagnose(&crash).unwrap(),
+            "abort() called from MyApp.fail() -> ()"
+        );
+
+        let pause = exception(5, &[0x10003, 17]);
+        let crash = Crash {
+            exception: Some(&pause),
+            signal: 17,
+            console: "Assertion failed: logged by the app",
+            ..Crash::default()
+        };
+        assert_eq!(diagnose(&crash), None);
+    }
+}
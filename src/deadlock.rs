@@ -0,0 +1,303 @@
+//! Wait-for analysis over thread backtraces, backing `detect_deadlock`.
+//!
+//! Each thread is classified by the blocking primitive near the top of its
+//! stack. Where the kernel wait call carries the owner (the pthread mutex
+//! owner's thread id is the fourth argument of `__psynch_mutexwait`), an edge
+//! is added from waiter to owner; cycles in that graph are deadlocks.
+
+use serde::Serialize;
+
+/// How many frames from the top are searched for a blocking call.
+const WAIT_FRAME_WINDOW: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WaitKind {
+    Mutex,
+    UnfairLock,
+    DispatchQueue,
+    Semaphore,
+    Condition,
+    Running,
+}
+
+impl WaitKind {
+    fn describe(self) -> &'static str {
+        match self {
+            Self::Mutex => "mutex",
+            Self::UnfairLock => "os_unfair_lock",
+            Self::DispatchQueue => "dispatch queue",
+            Self::Semaphore => "semaphore",
+            Self::Condition => "condition variable",
+            Self::Running => "nothing",
+        }
+    }
+}
+
+/// Recognizes the blocking primitive from a thread's function names, innermost first.
+pub fn classify_wait<S: AsRef<str>>(functions: &[S]) -> WaitKind {
+    let top: Vec<&str> = functions
+        .iter()
+        .take(WAIT_FRAME_WINDOW)
+        .map(|name| name.as_ref().trim_start_matches('_'))
+        .collect();
+    let any = |names: &[&str]| top.iter().any(|frame| names.contains(frame));
+    // dispatch_sync parks in __ulock_wait too, so queues are checked first.
+    if any(&[
+        "dispatch_sync_f_slow",
+        "DISPATCH_WAIT_FOR_QUEUE__",
+        "dispatch_sync_wait",
+        "dispatch_sync",
+        "dispatch_sync_f",
+    ]) {
+        WaitKind::DispatchQueue
+    } else if any(&[
+        "psynch_mutexwait",
+        "pthread_mutex_firstfit_lock_wait",
+        "pthread_mutex_firstfit_lock_slow",
+        "pthread_mutex_lock",
+    ]) {
+        WaitKind::Mutex
+    } else if any(&["os_unfair_lock_lock_slow", "os_unfair_lock_lock"]) {
+        WaitKind::UnfairLock
+    } else if any(&[
+        "semaphore_wait_trap",
+        "dispatch_semaphore_wait_slow",
+        "dispatch_semaphore_wait",
+    ]) {
+        WaitKind::Semaphore
+    } else if any(&["psynch_cvwait", "pthread_cond_wait"]) {
+        WaitKind::Condition
+    } else {
+        WaitKind::Running
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ThreadWait {
+    pub thread_id: i64,
+    pub name: String,
+    pub state: WaitKind,
+    /// Innermost symbolized frame, for orientation.
+    pub function: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resource: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owner: Option<i64>,
+}
+
+impl ThreadWait {
+    /// Builds the wait record for one thread. `register` reads the thread's
+    /// top-frame registers, which hold the arguments of the kernel wait call.
+    pub fn new(
+        thread_id: i64,
+        name: String,
+        functions: &[String],
+        mut register: impl FnMut(&str) -> Option<u64>,
+    ) -> Self {
+        let state = classify_wait(functions);
+        let top = functions.first().map(|name| name.trim_start_matches('_'));
+        let (resource, owner) = match (state, top) {
+            // __psynch_mutexwait(mutex, mgen, ugen, owner_tid, flags)
+            (WaitKind::Mutex, Some("psynch_mutexwait")) => (
+                register("x0").map(|mutex| format!("0x{mutex:x}")),
+                register("x3").filter(|tid| *tid != 0).map(|tid| tid as i64),
+            ),
+            // __ulock_wait(operation, address, value, timeout)
+            (
+                WaitKind::UnfairLock | WaitKind::DispatchQueue,
+                Some("ulock_wait" | "ulock_wait2"),
+            ) => (register("x1").map(|lock| format!("0x{lock:x}")), None),
+            (WaitKind::Running, _) => (None, None),
+            _ => (register("x0").map(|object| format!("0x{object:x}")), None),
+        };
+        Self {
+            thread_id,
+            name,
+            state,
+            function: functions
+                .iter()
+                .find(|name| name.as_str() != "<unknown>")
+                .cloned()
+                .unwrap_or_else(|| "<unknown>".into()),
+            resource,
+            owner,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WaitEdge {
+    pub waiter: i64,
+    pub owner: i64,
+    pub resource: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DeadlockReport {
+    pub deadlocked: bool,
+    pub threads: Vec<ThreadWait>,
+    pub edges: Vec<WaitEdge>,
+    /// Each cycle lists thread ids in wait order, starting from the lowest id.
+    pub cycles: Vec<Vec<i64>>,
+    /// One plain sentence per wait and per cycle.
+    pub findings: Vec<String>,
+}
+
+impl DeadlockReport {
+    pub fn analyze(threads: Vec<ThreadWait>) -> Self {
+        let edges: Vec<WaitEdge> = threads
+            .iter()
+            .filter_map(|thread| {
+                Some(WaitEdge {
+                    waiter: thread.thread_id,
+                    owner: thread.owner?,
+                    resource: thread.resource.clone().unwrap_or_default(),
+                })
+            })
+            .collect();
+        let cycles = find_cycles(&edges);
+        let mut findings: Vec<String> = threads
+            .iter()
+            .filter(|thread| thread.state != WaitKind::Running)
+            .map(|thread| {
+                let mut line = format!(
+                    "thread {} waits on {}",
+                    thread.thread_id,
+                    thread.state.describe()
+                );
+                if let Some(resource) = &thread.resource {
+                    line.push_str(&format!(" {resource}"));
+                }
+                match thread.owner {
+                    Some(owner) => line.push_str(&format!(" owned by thread {owner}")),
+                    None => line.push_str(" (owner unknown)"),
+                }
+                line
+            })
+            .collect();
+        for cycle in &cycles {
+            let path: Vec<String> = cycle
+                .iter()
+                .chain(cycle.first())
+                .map(|thread| format!("thread {thread}"))
+                .collect();
+            findings.push(format!("deadlock: {}", path.join(" -> ")));
+        }
+        Self {
+            deadlocked: !cycles.is_empty(),
+            threads,
+            edges,
+            cycles,
+            findings,
+        }
+    }
+}
+
+/// Every thread waits on at most one owner, so cycles are found by walking
+/// each chain until it repeats.
+fn find_cycles(edges: &[WaitEdge]) -> Vec<Vec<i64>> {
+    let next = |thread: i64| {
+        edges
+            .iter()
+            .find(|edge| edge.waiter == thread)
+            .map(|edge| edge.owner)
+    };
+    let mut cycles: Vec<Vec<i64>> = Vec::new();
+    for edge in edges {
+        let mut path = vec![edge.waiter];
+        let mut current = edge.owner;
+        loop {
+            if let Some(start) = path.iter().position(|thread| *thread == current) {
+                let mut cycle = path[start..].to_vec();
+                let lowest = (0..cycle.len()).min_by_key(|idx| cycle[*idx]).unwrap_or(0);
+                cycle.rotate_left(lowest);
+                if !cycles.contains(&cycle) {
+                    cycles.push(cycle);
+                }
+                break;
+            }
+            path.push(current);
+            match next(current) {
+                Some(owner) => current = owner,
+                None => break,
+            }
+        }
+    }
+    cycles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frames(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn classify_wait_prefers_dispatch_over_ulock() {
+        assert_eq!(
+            classify_wait(&["__ulock_wait", "_dispatch_sync_f_slow", "main"]),
+            WaitKind::DispatchQueue
+        );
+        assert_eq!(
+            classify_wait(&["__psynch_mutexwait", "_pthread_mutex_firstfit_lock_wait"]),
+            WaitKind::Mutex
+        );
+        assert_eq!(classify_wait(&["main"]), WaitKind::Running);
+    }
+
+    #[test]
+    fn analyze_reports_mutex_cycle() {
+        let registers = |mutex: u64, owner: u64| {
+            move |name: &str| match name {
+                "x0" => Some(mutex),
+                "x3" => Some(owner),
+                _ => None,
+            }
+        };
+        let waits = vec![
+            ThreadWait::new(
+                1,
+                "main".into(),
+                &frames(&["__psynch_mutexwait", "worker"]),
+                registers(0x1000, 3),
+            ),
+            ThreadWait::new(
+                3,
+                "bg".into(),
+                &frames(&["__psynch_mutexwait", "other"]),
+                registers(0x2000, 1),
+            ),
+            ThreadWait::new(4, "idle".into(), &frames(&["run"]), |_| None),
+        ];
+        let report = DeadlockReport::analyze(waits);
+        assert!(report.deadlocked);
+        assert_eq!(report.cycles, vec![vec![1, 3]]);
+        assert_eq!(report.edges.len(), 2);
+        assert!(report
+            .findings
+            .contains(&"thread 3 waits on mutex 0x2000 owned by thread 1".to_string()));
+        assert!(report
+            .findings
+            .contains(&"deadlock: thread 1 -> thread 3 -> thread 1".to_string()));
+    }
+
+    #[test]
+    fn analyze_without_cycle_is_not_deadlocked() {
+        let edges = vec![
+            WaitEdge {
+                waiter: 1,
+                owner: 2,
+                resource: String::new(),
+            },
+            WaitEdge {
+                waiter: 2,
+                owner: 3,
+                resource: String::new(),
+            },
+        ];
+        assert!(find_cycles(&edges).is_empty());
+    }
+}
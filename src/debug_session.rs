@@ -1,29 +1,184 @@
 use std::{
-    collections::{BTreeSet, HashMap},
-    env, io,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
+    env, fmt, fs, io,
     path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
-use serde::Serialize;
-use serde_json::Value;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
 use thiserror::Error;
 
 use crate::{
-    backend::{Backend, BackendStopEvent},
+    backend::{
+        ActorInfo, AddressInfo, Backend, BackendStopEvent, CallArgument, ChildPage, ExitStatus,
+        HeapInfo, ModuleInfo, ObjectDescription, RegionInfo, RegisterValue, ReturnValue, StepScope,
+        TargetValue, TaskInfo, ValueHandle,
+    },
+    breakpoint_file::BreakpointSpec,
+    crash::{self, Crash},
+    deadlock::{DeadlockReport, ThreadWait},
+    disasm,
+    expression::{self, EvalError, Scope},
+    fault::FaultInjector,
+    gdb_remote::{self, Interrupter, MachException},
+    objc::{ClassDump, ClassEntry},
+    protocol_trace::ProtocolTrace,
+    symbols,
+    value_format::ValueFormat,
+    verbosity::Verbosity,
     CONFIG_ENV_VAR,
 };
 
 const DEFAULT_THREAD_ID: i64 = 1;
+/// Connection attempts while a bridge finishes attaching; backoff doubles up to the cap.
+const DEFAULT_CONNECT_ATTEMPTS: u32 = 8;
+const CONNECT_INITIAL_BACKOFF: Duration = Duration::from_millis(100);
+const CONNECT_MAX_BACKOFF: Duration = Duration::from_secs(2);
 const LOCALS_REFERENCE: i64 = 1;
+/// References handed out for expandable values start here, above the
+/// per-frame locals references.
+const FIRST_VALUE_REFERENCE: i64 = 1 << 20;
+pub const DEFAULT_EVAL_TIMEOUT: Duration = Duration::from_secs(5);
+const SUMMARY_VALUE_LIMIT: usize = 32;
+const DEFAULT_DISASSEMBLY_COUNT: usize = 16;
+const MAX_DISASSEMBLY_COUNT: usize = 512;
+const DEFAULT_SOURCE_CONTEXT: usize = 5;
+const MAX_SOURCE_CONTEXT: usize = 200;
+pub const DEFAULT_DUMP_DEPTH: usize = 3;
+const MAX_DUMP_DEPTH: usize = 10;
+/// Resumes `continue_until_changed` makes before giving up, by default and
+/// at most.
+const DEFAULT_CHANGE_ITERATIONS: usize = 100;
+const MAX_CHANGE_ITERATIONS: usize = 10_000;
+/// How deep `snapshot` expands locals by default.
+const DEFAULT_SNAPSHOT_DEPTH: usize = 2;
+/// Snapshots kept for `diff_snapshot`; taking another drops the oldest.
+const MAX_SNAPSHOTS: usize = 32;
+/// Variables expanded by one `dump_variable` before the rest is truncated.
+const MAX_DUMP_NODES: usize = 1000;
+/// The library Xcode injects for the Main Thread Checker, and the function
+/// it calls with each report so that a debugger can stop there.
+const MAIN_THREAD_CHECKER: &str = "libMainThreadChecker.dylib";
+const MAIN_THREAD_CHECKER_HOOK: &str = "___main_thread_checker_on_report";
+/// Where the library is on a device with the developer disk image mounted.
+const MAIN_THREAD_CHECKER_PATH: &str = "/Developer/usr/lib/libMainThreadChecker.dylib";
+/// Longest Main Thread Checker report read.
+const MAX_REPORT_LEN: usize = 4096;
 
 pub type BreakpointId = u32;
 
+/// Receives rendered logpoint messages.
+pub type LogSink = dyn Fn(String) + Send;
+
+/// Receives target events; see [`DebugEvent`].
+pub type EventSink = dyn Fn(DebugEvent) + Send;
+
 pub struct DebugSession {
     backend: Backend,
     thread_id: i64,
+    /// Frame chosen with `select_frame`, per thread; cleared whenever the target resumes.
+    selected_frames: HashMap<i64, usize>,
     next_breakpoint_id: BreakpointId,
     file_breakpoints: HashMap<String, BTreeSet<i64>>,
     watch_expressions: Vec<String>,
+    /// Each watch expression's value at the last stop, `None` where it could
+    /// not be evaluated, which the next stop's [`WatchDelta`]s compare with.
+    watch_values: HashMap<String, Option<String>>,
+    /// Taken with `snapshot`, oldest first.
+    snapshots: VecDeque<Snapshot>,
+    next_snapshot: u32,
+    /// `$name` results declared with `let`/`var`; cleared whenever the target resumes.
+    convenience_variables: HashMap<String, EvalResult>,
+    /// Expandable values behind the `variablesReference`s handed out since
+    /// the target last ran. Numbers are never reused, so a stale reference
+    /// lists nothing rather than some other value's children.
+    value_refs: HashMap<i64, ValueHandle>,
+    next_value_ref: i64,
+    /// Build-machine path prefixes and their local replacements, tried in order.
+    source_maps: Vec<(String, String)>,
+    /// Local checkout that relative breakpoint paths are resolved against.
+    project_root: Option<PathBuf>,
+    path_match: PathMatch,
+    stop_summaries: bool,
+    verbosity: Verbosity,
+    value_format: ValueFormat,
+    connect_attempts: u32,
+    /// Bound on `evaluate` calls that do not set their own.
+    eval_timeout: Duration,
+    /// Most recent stop, reported on the thread that caused it.
+    last_stop: Option<SessionStop>,
+    /// Stop thread and its frames at the last two stops, for `stack_diff`.
+    stop_stacks: [Option<(i64, Vec<Frame>)>; 2],
+    logpoints: Vec<Logpoint>,
+    traced_functions: Vec<TracedFunction>,
+    temporary_breakpoints: Vec<TemporaryBreakpoint>,
+    pending_breakpoints: Vec<PendingBreakpoint>,
+    /// Images loaded when pending breakpoints were last retried.
+    loaded_images: Option<usize>,
+    log_sink: Option<Box<LogSink>>,
+    event_sink: Option<Box<EventSink>>,
+    /// Threads and image load addresses at the last stop, which the next
+    /// one is compared against for events. Only tracked with an event sink.
+    known_threads: Option<BTreeSet<i64>>,
+    known_images: Option<HashMap<String, ModuleInfo>>,
+    /// Address of the Main Thread Checker's report hook while stops there
+    /// are reported as violations.
+    main_thread_checker: Option<u64>,
+    /// dyld's image notifier, planted while breakpoints are pending so they
+    /// are retried as soon as an image loads.
+    image_load_hook: Option<u64>,
+}
+
+/// Breakpoint that could not be planted yet, because there was no
+/// debugserver connection or the target had not loaded code for the line.
+/// Retried whenever the target's image list changes.
+struct PendingBreakpoint {
+    id: BreakpointId,
+    file: String,
+    dwarf_path: String,
+    line: u32,
+}
+
+/// Breakpoint removed again by the first stop at one of its addresses.
+struct TemporaryBreakpoint {
+    id: BreakpointId,
+    dwarf_path: String,
+    line: u32,
+    addresses: Vec<u64>,
+    /// A regular breakpoint already covered the line, so it stays planted.
+    shared: bool,
+}
+
+/// Breakpoint that logs a rendered message and resumes instead of stopping.
+struct Logpoint {
+    id: BreakpointId,
+    file: String,
+    dwarf_path: String,
+    line: u32,
+    message: String,
+    /// `message` split by [`parse_log_template`].
+    parts: Vec<LogPart>,
+    addresses: Vec<u64>,
+}
+
+/// A piece of a logpoint message: literal text or an `{expression}`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum LogPart {
+    Text(String),
+    Expression(String),
+}
+
+/// Entry of a function whose calls are logged and resumed.
+struct TracedFunction {
+    /// The name `trace_function` was given.
+    request: String,
+    symbol: SymbolAddress,
+    address: u64,
 }
 
 impl DebugSession {
@@ -31,294 +186,4615 @@ impl DebugSession {
         Self {
             backend,
             thread_id: DEFAULT_THREAD_ID,
+            selected_frames: HashMap::new(),
             next_breakpoint_id: 1,
             file_breakpoints: HashMap::new(),
             watch_expressions: Vec::new(),
+            watch_values: HashMap::new(),
+            snapshots: VecDeque::new(),
+            next_snapshot: 1,
+            convenience_variables: HashMap::new(),
+            value_refs: HashMap::new(),
+            next_value_ref: FIRST_VALUE_REFERENCE,
+            source_maps: Vec::new(),
+            project_root: None,
+            path_match: PathMatch::default(),
+            stop_summaries: false,
+            verbosity: Verbosity::default(),
+            value_format: ValueFormat::default(),
+            connect_attempts: DEFAULT_CONNECT_ATTEMPTS,
+            eval_timeout: DEFAULT_EVAL_TIMEOUT,
+            last_stop: None,
+            stop_stacks: [None, None],
+            logpoints: Vec::new(),
+            traced_functions: Vec::new(),
+            temporary_breakpoints: Vec::new(),
+            pending_breakpoints: Vec::new(),
+            loaded_images: None,
+            log_sink: None,
+            event_sink: None,
+            known_threads: None,
+            known_images: None,
+            main_thread_checker: None,
+            image_load_hook: None,
         }
     }
 
+    /// Connects and validates the gdb-remote handshake, retrying with
+    /// exponential backoff so a bridge that is still attaching is waited for
+    /// instead of being mistaken for a ready debugserver.
     pub fn connect_debugserver(&mut self, port: u16) -> Result<(), DebugSessionError> {
-        self.backend
-            .connect_debugserver(port)
-            .map_err(DebugSessionError::Backend)
+        let mut attempt = 1;
+        while let Some(backoff) = self.connect_attempt(port, attempt)? {
+            thread::sleep(backoff);
+            attempt += 1;
+        }
+        Ok(())
     }
 
-    pub fn stacktrace(&self) -> Vec<Frame> {
-        self.backend
-            .stack_trace(self.thread_id)
-            .into_iter()
-            .enumerate()
-            .map(|(idx, value)| Frame::from_backend_value(idx, &value))
-            .collect()
+    /// Makes attempt `attempt` (from 1) of [`Self::connect_debugserver`] and
+    /// returns how long to back off before the next one when it failed with
+    /// attempts to spare, so async callers can wait without blocking a thread.
+    pub fn connect_attempt(
+        &mut self,
+        port: u16,
+        attempt: u32,
+    ) -> Result<Option<Duration>, DebugSessionError> {
+        match self.backend.connect_debugserver(port) {
+            Ok(()) => {
+                self.loaded_images = None;
+                self.main_thread_checker = None;
+                self.image_load_hook = None;
+                self.traced_functions.clear();
+                self.forget_known_target();
+                self.retry_pending_breakpoints();
+                self.sync_image_load_hook();
+                Ok(None)
+            }
+            Err(err) if attempt >= self.connect_attempts => Err(DebugSessionError::Backend(
+                format!("{err} (gave up after {attempt} attempts)"),
+            )),
+            Err(err) => {
+                let backoff = CONNECT_INITIAL_BACKOFF
+                    .saturating_mul(1 << attempt.saturating_sub(1).min(16))
+                    .min(CONNECT_MAX_BACKOFF);
+                log::debug!("connect attempt {attempt} failed: {err}; retrying in {backoff:?}");
+                Ok(Some(backoff))
+            }
+        }
     }
 
-    pub fn threads(&self) -> Vec<Value> {
-        self.backend.threads()
+    /// How many times `connect_debugserver` tries before giving up (at least once).
+    pub fn set_connect_attempts(&mut self, attempts: u32) {
+        self.connect_attempts = attempts.max(1);
     }
 
-    pub fn scopes(&self) -> Vec<Value> {
-        self.backend.scopes()
+    pub fn set_fault_injector(&mut self, faults: Arc<FaultInjector>) {
+        self.backend.set_fault_injector(faults);
     }
 
-    pub fn continue_execution(&mut self) -> Result<Option<SessionStop>, DebugSessionError> {
-        self.backend
-            .r#continue(self.thread_id)
-            .map(|maybe_event| maybe_event.map(SessionStop::from))
-            .map_err(DebugSessionError::Backend)
+    pub fn set_eval_timeout(&mut self, timeout: Duration) {
+        self.eval_timeout = timeout;
     }
 
-    pub fn next(&mut self) -> Result<Option<SessionStop>, DebugSessionError> {
-        self.backend
-            .step_over(self.thread_id)
-            .map(|maybe_event| maybe_event.map(SessionStop::from))
-            .map_err(DebugSessionError::Backend)
+    pub fn eval_timeout(&self) -> Duration {
+        self.eval_timeout
     }
 
-    pub fn step_in(&mut self) -> Result<Option<SessionStop>, DebugSessionError> {
-        self.backend
-            .step_in(self.thread_id)
-            .map(|maybe_event| maybe_event.map(SessionStop::from))
-            .map_err(DebugSessionError::Backend)
+    pub fn set_protocol_trace(&mut self, trace: Arc<ProtocolTrace>) {
+        self.backend.set_protocol_trace(trace);
     }
 
-    pub fn disconnect(&mut self) -> Result<(), DebugSessionError> {
-        self.backend
-            .disconnect()
-            .map_err(DebugSessionError::Backend)
+    /// Which threads run while `next` and `step_in` step the selected one.
+    pub fn set_step_scope(&mut self, scope: StepScope) {
+        self.backend.set_step_scope(scope);
     }
 
-    pub fn set_breakpoint(
-        &mut self,
-        file: &str,
-        line: u32,
-    ) -> Result<Breakpoint, DebugSessionError> {
-        let entry = self
-            .file_breakpoints
-            .entry(file.to_string())
-            .or_insert_with(BTreeSet::new);
-        entry.insert(line as i64);
-        let current_lines: Vec<i64> = entry.iter().copied().collect();
-        self.backend
-            .update_breakpoints(file, &current_lines)
-            .map_err(DebugSessionError::Backend)?;
+    pub fn is_connected(&self) -> bool {
+        self.backend.is_connected()
+    }
 
-        let id = self.next_breakpoint_id;
-        self.next_breakpoint_id = self.next_breakpoint_id.saturating_add(1);
-        Ok(Breakpoint {
-            id,
-            file: file.to_string(),
-            line,
-        })
+    /// Stops the target from another thread while a resuming call such as
+    /// [`continue_execution`](Self::continue_execution) is waiting on the session.
+    pub fn interrupter(&self) -> Option<Interrupter> {
+        self.backend.interrupter()
     }
 
-    pub fn locals(&self) -> Vec<Variable> {
-        self.variables_for_reference(LOCALS_REFERENCE)
+    pub fn stacktrace(&self) -> Vec<Frame> {
+        self.stacktrace_for(self.thread_id)
     }
 
-    pub fn variables_for_reference(&self, reference: i64) -> Vec<Variable> {
+    pub fn stacktrace_for(&self, thread_id: i64) -> Vec<Frame> {
         self.backend
-            .variables(reference)
+            .stack_trace(thread_id)
             .into_iter()
-            .map(Variable::from_backend_value)
+            .enumerate()
+            .map(|(idx, value)| Frame::from_backend_value(idx, &value))
             .collect()
     }
 
-    pub fn evaluate(&self, expression: &str) -> Result<EvalResult, DebugSessionError> {
-        let trimmed = expression.trim();
-        if trimmed.is_empty() {
-            return Err(DebugSessionError::UnsupportedExpression(
-                expression.to_string(),
-            ));
-        }
-        let locals = self.locals();
-        if let Some(variable) = locals.iter().find(|var| var.name == trimmed) {
-            return Ok(EvalResult {
-                result: variable.value.clone(),
-                ty: variable.ty.clone(),
+    /// Every thread with its top frame. debugserver runs in all-stop mode, so
+    /// while connected every thread is stopped; `stop_reason` is only set on
+    /// the thread that caused the last stop, and `run_state` is what the
+    /// kernel reported for the thread when it stopped.
+    pub fn threads(&mut self) -> Vec<ThreadInfo> {
+        let state = if self.is_connected() {
+            ThreadState::Stopped
+        } else {
+            ThreadState::Unknown
+        };
+        self.backend
+            .threads()
+            .iter()
+            .filter_map(|value| {
+                let id = value.get("id").and_then(Value::as_i64)?;
+                let text = |key: &str| value.get(key).and_then(Value::as_str).map(str::to_string);
+                Some(ThreadInfo {
+                    id,
+                    name: text("name").unwrap_or_default(),
+                    queue: text("queue"),
+                    state,
+                    run_state: text("run_state").and_then(|state| state.parse().ok()),
+                    stop_reason: self
+                        .last_stop
+                        .as_ref()
+                        .filter(|stop| stop.thread_id == id)
+                        .map(|stop| stop.reason.clone()),
+                    top_frame: self.stacktrace_for(id).into_iter().next(),
+                })
+            })
+            .collect()
+    }
+
+    /// Backtraces of every thread, optionally trimmed to `max_frames` each;
+    /// `thread_max_frames` overrides the limit for individual threads.
+    /// Identical frames are stored once in `frames` and referenced by index.
+    pub fn all_stacktraces(
+        &mut self,
+        max_frames: Option<usize>,
+        thread_max_frames: &HashMap<i64, usize>,
+    ) -> AllStacktraces {
+        let mut frames: Vec<SharedFrame> = Vec::new();
+        let mut threads = Vec::new();
+        for ThreadInfo { id, name, .. } in self.threads() {
+            let stack = self.stacktrace_for(id);
+            let limit = thread_max_frames
+                .get(&id)
+                .copied()
+                .or(max_frames)
+                .unwrap_or(stack.len())
+                .min(stack.len());
+            let indices = stack[..limit]
+                .iter()
+                .map(|frame| {
+                    let shared = SharedFrame::from(frame);
+                    frames
+                        .iter()
+                        .position(|existing| *existing == shared)
+                        .unwrap_or_else(|| {
+                            frames.push(shared);
+                            frames.len() - 1
+                        })
+                })
+                .collect();
+            threads.push(ThreadStack {
+                thread_id: id,
+                name,
+                frames: indices,
+                truncated: limit < stack.len(),
             });
         }
-        Err(DebugSessionError::UnsupportedExpression(
-            expression.to_string(),
-        ))
+        AllStacktraces { frames, threads }
     }
 
-    pub fn evaluate_swift(&self, expression: &str) -> Result<EvalResult, DebugSessionError> {
-        self.evaluate(expression)
+    /// Compares the stack at the last stop with the one at the stop before.
+    pub fn stack_diff(&self) -> Result<StackDiff, DebugSessionError> {
+        match &self.stop_stacks {
+            [Some((previous_thread, previous)), Some((current_thread, current))] => {
+                let mut diff = diff_stacks(previous, current);
+                diff.previous_thread = *previous_thread;
+                diff.current_thread = *current_thread;
+                Ok(diff)
+            }
+            _ => Err(DebugSessionError::coded(
+                ErrorCode::NotStopped,
+                "stack_diff needs two stops; step or continue first",
+            )),
+        }
     }
 
-    pub fn add_watch_expression(
-        &mut self,
-        expression: &str,
-    ) -> Result<Vec<WatchValue>, DebugSessionError> {
-        let trimmed = expression.trim();
-        if trimmed.is_empty() {
-            return Err(DebugSessionError::UnsupportedExpression(
-                expression.to_string(),
-            ));
-        }
-        if !self
-            .watch_expressions
-            .iter()
-            .any(|existing| existing == trimmed)
+    /// Builds a wait-for graph across all threads and reports any cycles.
+    pub fn detect_deadlock(&mut self) -> DeadlockReport {
+        let mut waits = Vec::new();
+        for ThreadInfo {
+            id: thread_id,
+            name,
+            ..
+        } in self.threads()
         {
-            self.watch_expressions.push(trimmed.to_string());
+            let functions: Vec<String> = self
+                .stacktrace_for(thread_id)
+                .into_iter()
+                .map(|frame| frame.function)
+                .collect();
+            let backend = &mut self.backend;
+            waits.push(ThreadWait::new(thread_id, name, &functions, |register| {
+                backend.read_register_u64(thread_id, register).ok()
+            }));
         }
-        self.evaluate_watch_expressions()
+        DeadlockReport::analyze(waits)
     }
 
-    pub fn evaluate_watch_expressions(&self) -> Result<Vec<WatchValue>, DebugSessionError> {
-        self.watch_expressions
-            .iter()
-            .map(|expr| {
-                self.evaluate(expr).map(|result| WatchValue {
-                    expression: expr.clone(),
-                    result,
-                })
-            })
-            .collect()
+    pub fn scopes(&self) -> Vec<Value> {
+        self.backend.scopes(self.selected_frame())
     }
 
-    pub fn select_thread(&mut self, thread_id: i64) {
-        self.thread_id = thread_id.max(1);
+    /// Continues until a stop the target does not resume from on its own;
+    /// see [`Self::passes_through`].
+    pub fn continue_execution(&mut self) -> Result<Option<SessionStop>, DebugSessionError> {
+        self.resuming(self.thread_id);
+        self.continue_to_stop()
     }
 
-    pub fn program_path(&self) -> &Path {
-        self.backend.program_path()
+    fn continue_to_stop(&mut self) -> Result<Option<SessionStop>, DebugSessionError> {
+        loop {
+            let event = self
+                .backend
+                .r#continue(self.thread_id)
+                .map_err(|err| self.resume_failed(err))?;
+            if !event
+                .as_ref()
+                .is_some_and(|event| self.passes_through(event))
+            {
+                return Ok(self.finish_stop(event));
+            }
+        }
     }
-}
 
-#[derive(Debug, Error)]
-pub enum DebugSessionError {
-    #[error("{0}")]
-    Backend(String),
-    #[error("expression `{0}` is not supported")]
-    UnsupportedExpression(String),
-}
+    /// Whether the target is resumed from `event` without reporting a stop:
+    /// dyld's image notifier, the entry of a traced function and logpoints,
+    /// whose calls and messages go to the log sink instead.
+    fn passes_through(&mut self, event: &BackendStopEvent) -> bool {
+        if self.image_load_hit(event) {
+            return true;
+        }
+        if let Some(call) = self.trace_hit(event) {
+            match serde_json::to_string(&call) {
+                Ok(line) => self.emit_log(format!("[trace] {line}")),
+                Err(err) => log::warn!("rendering a traced call: {err}"),
+            }
+            self.emit_event(DebugEvent::FunctionCalled(call));
+            return true;
+        }
+        match self.logpoint_hit(event) {
+            Some(message) => {
+                self.emit_log(message);
+                true
+            }
+            None => false,
+        }
+    }
 
-#[derive(Debug, Clone, Serialize)]
-pub struct Frame {
-    pub frame_index: usize,
-    pub function: String,
-    pub file: String,
-    pub line: u32,
-}
+    /// Whether `event` is a stop at dyld's image notifier, which only retries
+    /// the pending breakpoints and reports the image changes before the
+    /// target is resumed.
+    fn image_load_hit(&mut self, event: &BackendStopEvent) -> bool {
+        let Some(hook) = self.image_load_hook else {
+            return false;
+        };
+        if event.reason != "breakpoint"
+            || self.backend.read_register_u64(event.thread_id, "pc").ok() != Some(hook)
+        {
+            return false;
+        }
+        self.retry_pending_breakpoints();
+        if self.event_sink.is_some() {
+            self.emit_module_changes();
+        }
+        self.sync_image_load_hook();
+        true
+    }
 
-impl Frame {
-    fn from_backend_value(index: usize, value: &Value) -> Self {
-        let function = value
-            .get("name")
-            .and_then(Value::as_str)
-            .unwrap_or("<unknown>")
-            .to_string();
-        let file = value
-            .get("source")
-            .and_then(|src| src.get("path"))
-            .and_then(Value::as_str)
-            .unwrap_or("<unknown>")
-            .to_string();
-        let line = value
-            .get("line")
-            .and_then(Value::as_i64)
-            .unwrap_or_default()
-            .max(0) as u32;
-        Self {
-            frame_index: index,
+    /// Plants dyld's image notifier while breakpoints are pending or events
+    /// are pushed, and lifts it once neither is.
+    fn sync_image_load_hook(&mut self) {
+        if !self.backend.is_connected() {
+            return;
+        }
+        let wanted = !self.pending_breakpoints.is_empty() || self.event_sink.is_some();
+        match (wanted, self.image_load_hook) {
+            (true, None) => {
+                match self
+                    .backend
+                    .image_load_hook()
+                    .and_then(|hook| self.backend.plant_address(hook).map(|()| hook))
+                {
+                    Ok(hook) => self.image_load_hook = Some(hook),
+                    Err(err) => log::debug!("image loads are only seen at stops: {err}"),
+                }
+            }
+            (false, Some(hook)) => {
+                if let Err(err) = self.backend.lift_address(hook) {
+                    log::warn!("lifting the image load breakpoint: {err}");
+                }
+                self.image_load_hook = None;
+            }
+            _ => {}
+        }
+    }
+
+    /// The call `event` stopped at the entry of, when it is a traced function.
+    fn trace_hit(&mut self, event: &BackendStopEvent) -> Option<FunctionCall> {
+        if event.reason != "breakpoint" || self.traced_functions.is_empty() {
+            return None;
+        }
+        let pc = self.backend.read_register_u64(event.thread_id, "pc").ok()?;
+        let function = self
+            .traced_functions
+            .iter()
+            .find(|traced| traced.address == pc)?
+            .symbol
+            .name
+            .clone();
+        let arguments = match self.backend.entry_arguments(event.thread_id) {
+            Ok(values) => values
+                .into_iter()
+                .map(|value| TracedArgument {
+                    name: value.name,
+                    ty: value.ty,
+                    value: value.value,
+                })
+                .collect(),
+            Err(err) => {
+                log::debug!("arguments of a traced call to {function}: {err}");
+                Vec::new()
+            }
+        };
+        let caller = self
+            .backend
+            .return_address(event.thread_id)
+            .ok()
+            .map(|address| self.describe_caller(address));
+        Some(FunctionCall {
+            timestamp_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_millis() as u64)
+                .unwrap_or_default(),
+            thread_id: event.thread_id,
             function,
-            file,
-            line,
+            arguments,
+            caller,
+        })
+    }
+
+    /// `function at file:line` of the call that returns to `return_address`.
+    fn describe_caller(&self, return_address: u64) -> String {
+        let info = self.backend.symbolicate(return_address.saturating_sub(4));
+        let name = info.function.or(info.symbol).unwrap_or(info.address);
+        match (info.file, info.line) {
+            (Some(file), Some(line)) => format!("{name} at {}:{line}", file_name_of(&file)),
+            _ => name,
         }
     }
-}
 
-#[derive(Debug, Clone, Serialize)]
-pub struct Variable {
-    pub name: String,
-    #[serde(rename = "type")]
-    pub ty: String,
-    pub value: String,
-}
+    /// Renders the logpoint `event` stopped at, if any.
+    fn logpoint_hit(&mut self, event: &BackendStopEvent) -> Option<String> {
+        if event.reason != "breakpoint" || self.logpoints.is_empty() {
+            return None;
+        }
+        let pc = self.backend.read_register_u64(event.thread_id, "pc").ok()?;
+        let index = self
+            .logpoints
+            .iter()
+            .position(|logpoint| logpoint.addresses.contains(&pc))?;
+        let previous_thread = self.thread_id;
+        self.thread_id = event.thread_id;
+        self.selected_frames.clear();
+        let logpoint = &self.logpoints[index];
+        let prefix = format!(
+            "[logpoint {}] {}:{}: ",
+            logpoint.id, logpoint.file, logpoint.line
+        );
+        let parts = logpoint.parts.clone();
+        let message = self.render_log_message(&parts);
+        self.thread_id = previous_thread;
+        Some(prefix + &message)
+    }
 
-impl Variable {
-    fn from_backend_value(value: Value) -> Self {
-        let name = value
-            .get("name")
-            .and_then(Value::as_str)
-            .unwrap_or("<unnamed>")
-            .to_string();
-        let ty = value
-            .get("type")
-            .and_then(Value::as_str)
-            .unwrap_or("<unknown>")
-            .to_string();
-        let val = value
-            .get("value")
-            .and_then(Value::as_str)
-            .unwrap_or_default()
-            .to_string();
-        Self {
-            name,
-            ty,
-            value: val,
+    /// Joins `parts`, each expression replaced by its value or, when it
+    /// fails, by `<` and the error message and `>`.
+    fn render_log_message(&mut self, parts: &[LogPart]) -> String {
+        let mut out = String::new();
+        for part in parts {
+            match part {
+                LogPart::Text(text) => out.push_str(text),
+                LogPart::Expression(expression) => match self.evaluate_value(expression, None) {
+                    Ok(value) => out.push_str(&value.result),
+                    Err(err) => out.push_str(&format!("<{err}>")),
+                },
+            }
         }
+        out
     }
-}
 
-#[derive(Debug, Clone, Serialize)]
-pub struct EvalResult {
-    pub result: String,
-    #[serde(rename = "type")]
-    pub ty: String,
-}
+    fn emit_log(&self, message: String) {
+        match &self.log_sink {
+            Some(sink) => sink(message),
+            None => log::info!("{message}"),
+        }
+    }
 
-#[derive(Debug, Clone, Serialize)]
-pub struct WatchValue {
-    pub expression: String,
-    pub result: EvalResult,
-}
+    /// Sends the inferior's console output since the last stop to the log
+    /// sink, one `[stdout]` line per line of output, and returns it.
+    fn emit_console_output(&mut self) -> String {
+        let output = String::from_utf8_lossy(&self.backend.take_console_output()).into_owned();
+        for line in output.lines() {
+            self.emit_log(format!("[stdout] {line}"));
+        }
+        output
+    }
 
-#[derive(Debug, Clone, Serialize)]
-pub struct Breakpoint {
-    pub id: BreakpointId,
-    pub file: String,
-    pub line: u32,
-}
+    /// Queues `bytes` on the inferior's stdin; the process reads them once it
+    /// runs. Returns the number of bytes written.
+    pub fn write_stdin(&mut self, bytes: &[u8]) -> Result<usize, DebugSessionError> {
+        if bytes.is_empty() {
+            return Err(DebugSessionError::InvalidArgument(
+                "stdin data must not be empty".into(),
+            ));
+        }
+        self.backend
+            .write_stdin(bytes)
+            .map_err(DebugSessionError::Backend)?;
+        Ok(bytes.len())
+    }
 
-#[derive(Debug, Clone, Serialize)]
-pub struct SessionStop {
-    pub reason: String,
-    pub description: String,
-    pub thread_id: i64,
-}
+    /// Where logpoint messages go; without a sink they are logged at info level.
+    pub fn set_log_sink(&mut self, sink: Box<LogSink>) {
+        self.log_sink = Some(sink);
+    }
 
-impl From<BackendStopEvent> for SessionStop {
-    fn from(value: BackendStopEvent) -> Self {
-        Self {
-            reason: value.reason.to_string(),
-            description: value.description,
-            thread_id: value.thread_id,
+    /// Where target events go. Without a sink none are tracked, which saves
+    /// listing threads and images at every stop.
+    pub fn set_event_sink(&mut self, sink: Box<EventSink>) {
+        self.event_sink = Some(sink);
+        self.sync_image_load_hook();
+    }
+
+    fn emit_event(&self, event: DebugEvent) {
+        if let Some(sink) = &self.event_sink {
+            sink(event);
         }
     }
-}
 
-pub fn init_backend() -> io::Result<Backend> {
-    if let Ok(raw) = env::var(CONFIG_ENV_VAR) {
-        if let Some(program) = parse_program_from_config(&raw)? {
-            return backend_from_program(&program);
+    /// Announces that the target is about to run, first recording the
+    /// threads and images the next stop is compared against. Value
+    /// references stop being valid here.
+    fn resuming(&mut self, thread_id: i64) {
+        self.value_refs.clear();
+        if self.event_sink.is_none() {
+            return;
+        }
+        if self.known_threads.is_none() {
+            self.known_threads = Some(self.thread_names().into_keys().collect());
         }
+        if self.known_images.is_none() {
+            self.known_images = self.image_addresses();
+        }
+        self.emit_event(DebugEvent::Continued { thread_id });
     }
-    let exe = env::current_exe()?;
-    backend_from_program(&exe)
-}
 
-pub fn backend_from_program(program: &Path) -> io::Result<Backend> {
-    Backend::new_from_app(program).map_err(|err| io::Error::new(io::ErrorKind::Other, err))
-}
+    /// Wraps a failed resume, announcing the exit when the process ended.
+    fn resume_failed(&mut self, err: String) -> DebugSessionError {
+        if let Some(status) = self.backend.take_process_exit() {
+            self.forget_known_target();
+            self.emit_event(DebugEvent::ProcessExited { status });
+        }
+        DebugSessionError::Backend(err)
+    }
 
-pub fn parse_program_from_config(raw: &str) -> io::Result<Option<PathBuf>> {
-    let value: Value =
-        serde_json::from_str(raw).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
-    Ok(value
-        .get("program")
-        .and_then(Value::as_str)
-        .map(PathBuf::from))
+    /// Reports the threads and images that came or went while the target
+    /// ran, then the stop itself.
+    fn emit_stop_events(&mut self, stop: &SessionStop, summary: Option<String>) {
+        if self.event_sink.is_none() {
+            return;
+        }
+        let threads = self.thread_names();
+        let known = self
+            .known_threads
+            .replace(threads.keys().copied().collect());
+        if let Some(known) = known {
+            for (&thread_id, name) in &threads {
+                if !known.contains(&thread_id) {
+                    self.emit_event(DebugEvent::ThreadCreated {
+                        thread_id,
+                        name: name.clone(),
+                    });
+                }
+            }
+            for &thread_id in known.iter().filter(|id| !threads.contains_key(id)) {
+                self.emit_event(DebugEvent::ThreadExited { thread_id });
+            }
+        }
+        self.emit_module_changes();
+        let top = self.stop_stacks[1]
+            .as_ref()
+            .and_then(|(_, frames)| frames.first())
+            .cloned();
+        let located = top.as_ref().filter(|frame| frame.file != "<unknown>");
+        if stop.reason == "breakpoint" {
+            self.emit_event(DebugEvent::BreakpointHit {
+                thread_id: stop.thread_id,
+                file: located.map(|frame| frame.file.clone()),
+                line: located.map(|frame| frame.line),
+            });
+        }
+        if let Some(violation) = &stop.main_thread_violation {
+            self.emit_event(DebugEvent::MainThreadViolation(violation.clone()));
+        }
+        self.emit_event(DebugEvent::Stopped {
+            reason: stop.reason.clone(),
+            description: stop.description.clone(),
+            thread_id: stop.thread_id,
+            exception: stop.exception.clone(),
+            diagnosis: stop.diagnosis.clone(),
+            function: top.as_ref().map(|frame| frame.function.clone()),
+            file: located.map(|frame| frame.file.clone()),
+            line: located.map(|frame| frame.line),
+            summary,
+        });
+    }
+
+    fn thread_names(&mut self) -> BTreeMap<i64, String> {
+        self.backend
+            .threads()
+            .iter()
+            .filter_map(|value| {
+                let id = value.get("id").and_then(Value::as_i64)?;
+                let name = value
+                    .get("name")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default();
+                Some((id, name.to_string()))
+            })
+            .collect()
+    }
+
+    fn image_addresses(&mut self) -> Option<HashMap<String, ModuleInfo>> {
+        let modules = self.backend.modules().ok()?;
+        Some(
+            modules
+                .into_iter()
+                .map(|module| (module.load_address.clone(), module))
+                .collect(),
+        )
+    }
+
+    /// Reports the images loaded and unloaded since the last look.
+    fn emit_module_changes(&mut self) {
+        let Some(current) = self.image_addresses() else {
+            return;
+        };
+        let Some(mut known) = self.known_images.replace(current.clone()) else {
+            return;
+        };
+        for (address, module) in current {
+            if known.remove(&address).is_none() {
+                self.emit_event(DebugEvent::ModuleLoaded(module));
+            }
+        }
+        for (load_address, module) in known {
+            self.emit_event(DebugEvent::ModuleUnloaded {
+                path: module.path,
+                load_address,
+            });
+        }
+    }
+
+    /// Drops the thread and image baseline after the process changed.
+    fn forget_known_target(&mut self) {
+        self.known_threads = None;
+        self.known_images = None;
+    }
+
+    /// Resumes with `signal` delivered to `thread_id` (the selected thread by
+    /// default) and waits for the next stop, like `continue`.
+    pub fn send_signal(
+        &mut self,
+        signal: u8,
+        thread_id: Option<i64>,
+    ) -> Result<Option<SessionStop>, DebugSessionError> {
+        let thread_id = thread_id.unwrap_or(self.thread_id);
+        self.resuming(thread_id);
+        let event = self
+            .backend
+            .signal(thread_id, signal)
+            .map_err(|err| self.resume_failed(err))?;
+        if event
+            .as_ref()
+            .is_some_and(|event| self.passes_through(event))
+        {
+            return self.continue_to_stop();
+        }
+        Ok(self.finish_stop(event))
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Result<Option<SessionStop>, DebugSessionError> {
+        self.resuming(self.thread_id);
+        match self.backend.step_over(self.thread_id) {
+            Ok(event) => Ok(self.finish_stop(event)),
+            Err(err) => Err(self.resume_failed(err)),
+        }
+    }
+
+    pub fn step_in(&mut self) -> Result<Option<SessionStop>, DebugSessionError> {
+        self.resuming(self.thread_id);
+        match self.backend.step_in(self.thread_id) {
+            Ok(event) => Ok(self.finish_stop(event)),
+            Err(err) => Err(self.resume_failed(err)),
+        }
+    }
+
+    pub fn step_out(&mut self) -> Result<Option<SessionStop>, DebugSessionError> {
+        self.resuming(self.thread_id);
+        match self.backend.step_out(self.thread_id) {
+            Ok(event) => Ok(self.finish_stop(event)),
+            Err(err) => Err(self.resume_failed(err)),
+        }
+    }
+
+    /// Continues to `file:line` through a temporary breakpoint that is removed
+    /// after the stop, even when the target stops somewhere else first.
+    pub fn run_to_line(
+        &mut self,
+        file: &str,
+        line: u32,
+    ) -> Result<Option<SessionStop>, DebugSessionError> {
+        if self
+            .backend
+            .line_addresses(file, line)
+            .is_ok_and(|addresses| addresses.is_empty())
+        {
+            return Err(self.no_code_at(file, file, line));
+        }
+        self.resuming(self.thread_id);
+        match self.backend.run_to_line(file, line) {
+            Ok(event) => Ok(self.finish_stop(event)),
+            Err(err) => Err(self.resume_failed(err)),
+        }
+    }
+
+    /// Resumes the target with `advance` until `expression`, evaluated in the
+    /// stopped thread's top frame, no longer has the value it has now. Gives
+    /// up after `max_iterations` resumes, and early at a stop that is not a
+    /// breakpoint or step (a crash, a pause) or when the process exits. A stop
+    /// where the expression cannot be evaluated, e.g. out of its scope, does
+    /// not count as a change.
+    pub fn continue_until_changed(
+        &mut self,
+        expression: &str,
+        advance: Advance,
+        max_iterations: Option<usize>,
+    ) -> Result<ValueChange, DebugSessionError> {
+        let max_iterations = max_iterations
+            .unwrap_or(DEFAULT_CHANGE_ITERATIONS)
+            .clamp(1, MAX_CHANGE_ITERATIONS);
+        let previous = self.evaluate_value(expression, None)?.result;
+        let mut change = ValueChange {
+            expression: expression.to_string(),
+            changed: false,
+            value: Some(previous.clone()),
+            previous,
+            error: None,
+            iterations: 0,
+            stop: None,
+        };
+        while change.iterations < max_iterations {
+            change.iterations += 1;
+            let stop = match advance {
+                Advance::Continue => self.continue_execution()?,
+                Advance::Next => self.next()?,
+            };
+            let Some(stop) = stop else {
+                break;
+            };
+            (change.value, change.error) = match self.evaluate_value(expression, None) {
+                Ok(result) => (Some(result.result), None),
+                Err(err) => (None, Some(err.to_string())),
+            };
+            let ordinary = matches!(stop.reason.as_str(), "breakpoint" | "step");
+            change.stop = Some(stop);
+            change.changed = change
+                .value
+                .as_ref()
+                .is_some_and(|value| *value != change.previous);
+            if change.changed || !ordinary {
+                break;
+            }
+        }
+        Ok(change)
+    }
+
+    /// Logs every call to the functions named `name` (see
+    /// [`Self::lookup_symbol`]) without stopping: a breakpoint on each entry
+    /// sends a [`FunctionCall`] to the log sink and the event sink, then
+    /// resumes. With `enabled` false the breakpoints are lifted again.
+    /// Returns the entries traced or untraced. Connecting to a new process
+    /// drops every trace.
+    pub fn trace_function(
+        &mut self,
+        name: &str,
+        enabled: bool,
+    ) -> Result<Vec<SymbolAddress>, DebugSessionError> {
+        if !enabled {
+            let (untraced, kept) = std::mem::take(&mut self.traced_functions)
+                .into_iter()
+                .partition(|traced| traced.request == name || traced.symbol.name == name);
+            self.traced_functions = kept;
+            let mut symbols = Vec::new();
+            for traced in untraced {
+                self.backend
+                    .lift_address(traced.address)
+                    .map_err(DebugSessionError::Backend)?;
+                symbols.push(traced.symbol);
+            }
+            return Ok(symbols);
+        }
+        let matches = self
+            .backend
+            .lookup_symbol(name)
+            .map_err(|err| DebugSessionError::Coded {
+                code: ErrorCode::UnknownSymbol,
+                message: err,
+                hint: Some("use lookup_symbol to find the function's exact name".into()),
+            })?;
+        let mut symbols = Vec::with_capacity(matches.len());
+        for symbol in matches {
+            let demangled = symbols::demangle_symbol(&symbol.name);
+            let entry = SymbolAddress {
+                mangled_name: (demangled != symbol.name).then_some(symbol.name),
+                name: demangled,
+                address: format!("0x{:x}", symbol.address),
+            };
+            if !self
+                .traced_functions
+                .iter()
+                .any(|traced| traced.address == symbol.address)
+            {
+                self.backend
+                    .plant_address(symbol.address)
+                    .map_err(DebugSessionError::Backend)?;
+                self.traced_functions.push(TracedFunction {
+                    request: name.to_string(),
+                    symbol: entry.clone(),
+                    address: symbol.address,
+                });
+            }
+            symbols.push(entry);
+        }
+        Ok(symbols)
+    }
+
+    /// Stops the target whenever the Main Thread Checker reports a UI API
+    /// called off the main thread, by planting a breakpoint on its report
+    /// hook; the stop carries a [`MainThreadViolation`]. The checker must
+    /// have been injected into the app, as Xcode does. Returns the hook's
+    /// address while enabled; connecting to a new process disables it.
+    pub fn set_main_thread_checker(
+        &mut self,
+        enabled: bool,
+    ) -> Result<Option<u64>, DebugSessionError> {
+        if !enabled {
+            if let Some(hook) = self.main_thread_checker.take() {
+                self.backend
+                    .lift_address(hook)
+                    .map_err(DebugSessionError::Backend)?;
+            }
+            return Ok(None);
+        }
+        if let Some(hook) = self.main_thread_checker {
+            return Ok(Some(hook));
+        }
+        let hook = self
+            .backend
+            .runtime_symbol(MAIN_THREAD_CHECKER, MAIN_THREAD_CHECKER_HOOK)
+            .map_err(|err| DebugSessionError::Coded {
+                code: ErrorCode::UnknownSymbol,
+                message: format!("the Main Thread Checker is not available: {err}"),
+                hint: Some(format!(
+                    "launch with env DYLD_INSERT_LIBRARIES={MAIN_THREAD_CHECKER_PATH}, \
+                     which is how Xcode enables it"
+                )),
+            })?;
+        self.backend
+            .plant_address(hook)
+            .map_err(DebugSessionError::Backend)?;
+        self.main_thread_checker = Some(hook);
+        Ok(Some(hook))
+    }
+
+    /// The violation a breakpoint stop on `thread_id` reports, when it is at
+    /// the Main Thread Checker's hook: the report passed as its argument and
+    /// the backtrace of the offending call.
+    fn main_thread_violation(&mut self, thread_id: i64) -> Option<MainThreadViolation> {
+        let hook = self.main_thread_checker?;
+        if self.backend.read_register_u64(thread_id, "pc").ok()? != hook {
+            return None;
+        }
+        let message = self
+            .backend
+            .read_register_u64(thread_id, "x0")
+            .and_then(|report| self.backend.read_c_string(report, MAX_REPORT_LEN))
+            .map(|(message, _)| message)
+            .unwrap_or_default();
+        let frames = self
+            .stacktrace_for(thread_id)
+            .into_iter()
+            .skip_while(|frame| frame.function.contains("main_thread_checker"))
+            .collect();
+        Some(MainThreadViolation {
+            thread_id,
+            api: reported_api(&message),
+            message,
+            frames,
+        })
+    }
+
+    /// Attaches a one-line summary of the top frame's variables to every stop.
+    pub fn set_stop_summaries(&mut self, enabled: bool) {
+        self.stop_summaries = enabled;
+    }
+
+    /// How much detail inspection payloads carry; see [`crate::verbosity`].
+    pub fn set_verbosity(&mut self, verbosity: Verbosity) {
+        self.verbosity = verbosity;
+    }
+
+    pub fn verbosity(&self) -> Verbosity {
+        self.verbosity
+    }
+
+    /// Default number formatting for variables and evaluation results; see
+    /// [`crate::value_format`].
+    pub fn set_value_format(&mut self, format: ValueFormat) {
+        self.value_format = format;
+    }
+
+    pub fn value_format(&self) -> ValueFormat {
+        self.value_format
+    }
+
+    fn finish_stop(&mut self, event: Option<BackendStopEvent>) -> Option<SessionStop> {
+        self.convenience_variables.clear();
+        self.selected_frames.clear();
+        let exception = event.as_ref().and_then(|event| event.exception.clone());
+        self.last_stop = event.map(SessionStop::from);
+        let breakpoint_thread = self
+            .last_stop
+            .as_ref()
+            .filter(|stop| stop.reason == "breakpoint")
+            .map(|stop| stop.thread_id);
+        if let Some(violation) = breakpoint_thread.and_then(|id| self.main_thread_violation(id)) {
+            if let Some(stop) = self.last_stop.as_mut() {
+                stop.reason = "main_thread_checker".into();
+                stop.description = violation.message.clone();
+                stop.main_thread_violation = Some(violation);
+            }
+        }
+        if let Some(thread_id) = self.last_stop.as_ref().map(|stop| stop.thread_id) {
+            let stack = (thread_id, self.stacktrace_for(thread_id));
+            self.stop_stacks = [self.stop_stacks[1].take(), Some(stack)];
+        }
+        let console = self.emit_console_output();
+        let diagnosis = self
+            .last_stop
+            .clone()
+            .filter(|stop| stop.exception.is_some())
+            .and_then(|stop| self.diagnose_crash(&stop, exception.as_ref(), &console));
+        if let Some(stop) = self.last_stop.as_mut() {
+            stop.diagnosis = diagnosis;
+        }
+        let removed = self.reap_temporary_breakpoint();
+        let verified = self.retry_pending_breakpoints();
+        self.sync_image_load_hook();
+        let mut stop = self.last_stop.clone()?;
+        stop.removed_breakpoint = removed;
+        stop.verified_breakpoints = verified;
+        // Event consumers get the summary whether or not stops carry it.
+        let summary = (self.stop_summaries || self.event_sink.is_some())
+            .then(|| summarize_variables(&self.locals()));
+        if self.stop_summaries {
+            stop.summary = summary.clone();
+        }
+        stop.watches = self.watch_deltas();
+        self.emit_stop_events(&stop, summary);
+        Some(stop)
+    }
+
+    /// Gathers what [`crash::diagnose`] looks at for a crash stop: the
+    /// crashing thread's frames, every thread's stack pointer and the region
+    /// of the fault address.
+    fn diagnose_crash(
+        &mut self,
+        stop: &SessionStop,
+        exception: Option<&MachException>,
+        console: &str,
+    ) -> Option<String> {
+        let functions: Vec<String> = self.stop_stacks[1]
+            .as_ref()
+            .map(|(_, frames)| frames.iter().map(|frame| frame.function.clone()).collect())
+            .unwrap_or_default();
+        let fault_address = exception.and_then(MachException::fault_address);
+        let (stack_pointers, fault_region) = match fault_address {
+            Some(address) => {
+                let thread_ids: Vec<i64> = self.threads().iter().map(|thread| thread.id).collect();
+                let stack_pointers: Vec<(i64, u64)> = thread_ids
+                    .into_iter()
+                    .filter_map(|id| Some((id, self.backend.read_register_u64(id, "sp").ok()?)))
+                    .collect();
+                let region = self
+                    .backend
+                    .memory_regions(Some(address))
+                    .ok()
+                    .and_then(|(regions, _)| regions.into_iter().next())
+                    .map(|region| region.permissions);
+                (stack_pointers, region)
+            }
+            None => (Vec::new(), None),
+        };
+        crash::diagnose(&Crash {
+            exception,
+            signal: stop.exception.as_ref().map_or(0, |e| e.signal),
+            thread_id: stop.thread_id,
+            functions: &functions,
+            stack_pointers: &stack_pointers,
+            fault_region: fault_region.as_deref(),
+            console,
+        })
+    }
+
+    /// Rewrites source paths starting with `from` to start with `to` instead.
+    pub fn add_source_map(&mut self, from: &str, to: &str) {
+        self.source_maps.push((from.to_string(), to.to_string()));
+    }
+
+    /// Resolves relative breakpoint paths against `root`. When `root` is an
+    /// Xcode or SwiftPM checkout, source maps from the build paths in the
+    /// DWARF line tables to `root` are derived as well; returns those added.
+    pub fn set_project_root(&mut self, root: &Path) -> Vec<(String, String)> {
+        self.project_root = Some(root.to_path_buf());
+        if !is_project_root(root) {
+            return Vec::new();
+        }
+        let Ok(files) = self.backend.source_files() else {
+            return Vec::new();
+        };
+        let derived: Vec<_> = derive_source_maps(&files, root)
+            .into_iter()
+            .filter(|(from, _)| !self.source_maps.iter().any(|(known, _)| known == from))
+            .collect();
+        self.source_maps.extend(derived.iter().cloned());
+        derived
+    }
+
+    /// Translates a local or project-relative path into the path the DWARF
+    /// line tables record for it, by running the source maps backwards.
+    fn dwarf_path_for(&mut self, file: &str) -> String {
+        let local = match &self.project_root {
+            Some(root) if Path::new(file).is_relative() => root.join(file),
+            _ => PathBuf::from(file),
+        };
+        let local = local.to_string_lossy();
+        let Ok(known) = self.backend.source_files() else {
+            return file.to_string();
+        };
+        self.source_maps
+            .iter()
+            .filter_map(|(from, to)| {
+                let rest = local.strip_prefix(to.trim_end_matches('/'))?;
+                rest.starts_with('/').then(|| format!("{from}{rest}"))
+            })
+            .find(|candidate| known.binary_search(candidate).is_ok())
+            .unwrap_or_else(|| file.to_string())
+    }
+
+    /// Lists `context` lines either side of `line` in `file`, defaulting to
+    /// the top frame's location.
+    pub fn source(
+        &mut self,
+        file: Option<&str>,
+        line: Option<u32>,
+        context: Option<usize>,
+    ) -> Result<SourceListing, DebugSessionError> {
+        let top = self.stacktrace().into_iter().next();
+        let (file, line) = match (file, top) {
+            (Some(file), top) => (
+                file.to_string(),
+                line.or(top.map(|frame| frame.line)).unwrap_or(1),
+            ),
+            (None, Some(frame)) if frame.file != "<unknown>" => (frame.file, frame.line),
+            (None, _) => {
+                return Err(DebugSessionError::InvalidArgument(
+                    "the current stop has no source location; pass `file` and `line`".into(),
+                ))
+            }
+        };
+        let path = self.resolve_source_path(&file)?;
+        let text = fs::read_to_string(&path).map_err(|err| {
+            DebugSessionError::Backend(format!("failed to read {}: {err}", path.display()))
+        })?;
+        let context = context
+            .unwrap_or(DEFAULT_SOURCE_CONTEXT)
+            .min(MAX_SOURCE_CONTEXT);
+        let line = line.max(1);
+        let first = line.saturating_sub(context as u32).max(1);
+        let lines = text
+            .lines()
+            .enumerate()
+            .map(|(idx, text)| (idx as u32 + 1, text))
+            .skip_while(|(number, _)| *number < first)
+            .take_while(|(number, _)| *number <= line.saturating_add(context as u32))
+            .map(|(number, text)| SourceLine {
+                number,
+                text: text.to_string(),
+                current: number == line,
+            })
+            .collect();
+        Ok(SourceListing {
+            path: path.display().to_string(),
+            line,
+            lines,
+        })
+    }
+
+    /// Where the source file DWARF records as `file` is on this machine, if
+    /// it can be found.
+    pub fn local_source_path(&mut self, file: &str) -> Option<PathBuf> {
+        self.resolve_source_path(file).ok()
+    }
+
+    /// Finds `file` on this machine: matched against the DWARF line tables,
+    /// which also complete a trailing path, then as recorded or through the
+    /// source maps. Files the debug info does not name are refused, so
+    /// `source` cannot be used to read arbitrary local files.
+    fn resolve_source_path(&mut self, file: &str) -> Result<PathBuf, DebugSessionError> {
+        let known = self
+            .backend
+            .source_files()
+            .map_err(DebugSessionError::Backend)?;
+        let suffix = format!("/{}", file.trim_start_matches('/'));
+        let candidates: Vec<String> = known
+            .into_iter()
+            .filter(|known| known == file || known.ends_with(&suffix))
+            .collect();
+        if candidates.is_empty() {
+            return Err(DebugSessionError::InvalidArgument(format!(
+                "source file `{file}` is not in the loaded debug info"
+            )));
+        }
+        candidates
+            .iter()
+            .flat_map(|candidate| {
+                let mapped = self.source_maps.iter().filter_map(|(from, to)| {
+                    candidate
+                        .strip_prefix(from.as_str())
+                        .map(|rest| format!("{}{rest}", to.trim_end_matches('/')))
+                });
+                mapped
+                    .chain(std::iter::once(candidate.clone()))
+                    .collect::<Vec<_>>()
+            })
+            .map(PathBuf::from)
+            .find(|path| path.is_file())
+            .ok_or_else(|| {
+                DebugSessionError::coded(
+                    ErrorCode::SourceNotFound,
+                    format!(
+                        "source file `{file}` not found; add a --source-map for its build path"
+                    ),
+                )
+            })
+    }
+
+    /// Detaches debugserver and lets the app run free; unlike `disconnect`,
+    /// the process is resumed rather than left stopped.
+    pub fn detach(&mut self) -> Result<(), DebugSessionError> {
+        self.backend.detach().map_err(DebugSessionError::Backend)?;
+        self.forget_known_target();
+        self.convenience_variables.clear();
+        self.selected_frames.clear();
+        self.last_stop = None;
+        Ok(())
+    }
+
+    /// Kills the inferior; the debugserver connection ends with it.
+    pub fn terminate(&mut self) -> Result<ExitStatus, DebugSessionError> {
+        let status = self.backend.kill().map_err(DebugSessionError::Backend)?;
+        self.convenience_variables.clear();
+        self.selected_frames.clear();
+        self.last_stop = None;
+        self.forget_known_target();
+        self.emit_event(DebugEvent::ProcessExited {
+            status: status.clone(),
+        });
+        Ok(status)
+    }
+
+    pub fn disconnect(&mut self) -> Result<(), DebugSessionError> {
+        self.last_stop = None;
+        self.main_thread_checker = None;
+        self.image_load_hook = None;
+        self.traced_functions.clear();
+        self.forget_known_target();
+        self.backend
+            .disconnect()
+            .map_err(DebugSessionError::Backend)
+    }
+
+    /// How `set_breakpoint` matches file arguments when none is given.
+    pub fn set_path_match(&mut self, path_match: PathMatch) {
+        self.path_match = path_match;
+    }
+
+    /// Sets a breakpoint, matching `file` against the DWARF source paths with
+    /// `path_match` (the session default when `None`). A line without code
+    /// moves to the nearest one with some; see [`Self::breakpoint_line`].
+    pub fn set_breakpoint(
+        &mut self,
+        file: &str,
+        requested_line: u32,
+        path_match: Option<PathMatch>,
+    ) -> Result<Breakpoint, DebugSessionError> {
+        let (dwarf_path, matched) =
+            self.resolve_breakpoint_file(file, path_match.unwrap_or(self.path_match))?;
+        let line = self.breakpoint_line(&dwarf_path, requested_line);
+        let entry = self.file_breakpoints.entry(dwarf_path.clone()).or_default();
+        entry.insert(line as i64);
+        let current_lines: Vec<i64> = entry.iter().copied().collect();
+        let unbound = self
+            .backend
+            .update_breakpoints(&dwarf_path, &current_lines)
+            .map_err(DebugSessionError::Backend)?;
+
+        let id = self.next_breakpoint_id;
+        self.next_breakpoint_id = self.next_breakpoint_id.saturating_add(1);
+        let verified = !unbound.contains(&(line as i64));
+        if !verified {
+            self.pending_breakpoints.push(PendingBreakpoint {
+                id,
+                file: file.to_string(),
+                dwarf_path: dwarf_path.clone(),
+                line,
+            });
+            self.sync_image_load_hook();
+        }
+        Ok(Breakpoint {
+            id,
+            file: file.to_string(),
+            line,
+            requested_line,
+            resolved_file: dwarf_path,
+            matched,
+            temporary: false,
+            verified,
+        })
+    }
+
+    /// Makes `lines` the only breakpoints in `file`, as DAP `setBreakpoints`
+    /// does. Logpoints and temporary breakpoints on other lines of the file
+    /// are lifted with the rest.
+    pub fn replace_file_breakpoints(
+        &mut self,
+        file: &str,
+        lines: &[u32],
+    ) -> Result<Vec<Breakpoint>, DebugSessionError> {
+        let (dwarf_path, matched) = self.resolve_breakpoint_file(file, self.path_match)?;
+        let placed: Vec<(u32, u32)> = lines
+            .iter()
+            .map(|&line| (line, self.breakpoint_line(&dwarf_path, line)))
+            .collect();
+        let wanted: BTreeSet<i64> = placed.iter().map(|&(_, line)| line as i64).collect();
+        let previous = self
+            .file_breakpoints
+            .remove(&dwarf_path)
+            .unwrap_or_default();
+        for &line in previous.difference(&wanted) {
+            self.backend
+                .remove_breakpoint(&dwarf_path, line)
+                .map_err(DebugSessionError::Backend)?;
+        }
+        self.logpoints.retain(|logpoint| {
+            logpoint.dwarf_path != dwarf_path || wanted.contains(&(logpoint.line as i64))
+        });
+        self.temporary_breakpoints
+            .retain(|temporary| temporary.dwarf_path != dwarf_path);
+        self.pending_breakpoints
+            .retain(|pending| pending.dwarf_path != dwarf_path);
+        let current: Vec<i64> = wanted.iter().copied().collect();
+        let unbound = self
+            .backend
+            .update_breakpoints(&dwarf_path, &current)
+            .map_err(DebugSessionError::Backend)?;
+        if !wanted.is_empty() {
+            self.file_breakpoints.insert(dwarf_path.clone(), wanted);
+        }
+
+        let mut breakpoints = Vec::new();
+        for (requested_line, line) in placed {
+            let id = self.next_breakpoint_id;
+            self.next_breakpoint_id = self.next_breakpoint_id.saturating_add(1);
+            let verified = !unbound.contains(&(line as i64));
+            if !verified {
+                self.pending_breakpoints.push(PendingBreakpoint {
+                    id,
+                    file: file.to_string(),
+                    dwarf_path: dwarf_path.clone(),
+                    line,
+                });
+            }
+            breakpoints.push(Breakpoint {
+                id,
+                file: file.to_string(),
+                line,
+                requested_line,
+                resolved_file: dwarf_path.clone(),
+                matched,
+                temporary: false,
+                verified,
+            });
+        }
+        self.sync_image_load_hook();
+        Ok(breakpoints)
+    }
+
+    /// Finds the DWARF path `file` refers to: exactly (after the source maps),
+    /// then, as `mode` allows, as a path suffix, then by file name alone. Each
+    /// step must be unambiguous. Without DWARF line tables `file` is used as is.
+    fn resolve_breakpoint_file(
+        &mut self,
+        file: &str,
+        mode: PathMatch,
+    ) -> Result<(String, Option<PathMatch>), DebugSessionError> {
+        let mapped = self.dwarf_path_for(file);
+        let Ok(known) = self.backend.source_files() else {
+            return Ok((mapped, None));
+        };
+        if known.binary_search(&mapped).is_ok() {
+            return Ok((mapped, Some(PathMatch::Exact)));
+        }
+        let trimmed = file.trim_start_matches("./").trim_start_matches('/');
+        let suffix = format!("/{trimmed}");
+        let name = file_name_of(file);
+        for strategy in [PathMatch::Suffix, PathMatch::Basename] {
+            if strategy > mode {
+                break;
+            }
+            let found: Vec<&String> = known
+                .iter()
+                .filter(|known| match strategy {
+                    PathMatch::Suffix => known.ends_with(&suffix),
+                    _ => file_name_of(known) == name,
+                })
+                .collect();
+            match found.as_slice() {
+                [] => continue,
+                [only] => return Ok(((*only).clone(), Some(strategy))),
+                many => {
+                    let shown: Vec<&str> = many.iter().take(5).map(|path| path.as_str()).collect();
+                    return Err(DebugSessionError::coded(
+                        ErrorCode::AmbiguousFile,
+                        format!(
+                            "`{file}` matches {} source files ({}{}); pass more of the path",
+                            many.len(),
+                            shown.join(", "),
+                            if many.len() > shown.len() {
+                                ", ..."
+                            } else {
+                                ""
+                            }
+                        ),
+                    ));
+                }
+            }
+        }
+        Err(DebugSessionError::coded(
+            ErrorCode::SourceNotFound,
+            format!("no source file in the DWARF line tables matches `{file}` (match: {mode})"),
+        ))
+    }
+
+    /// Plants a breakpoint on `file:line` that, instead of stopping, sends
+    /// `message` with each `{expression}` replaced by its value to the log
+    /// sink and resumes.
+    pub fn set_logpoint(
+        &mut self,
+        file: &str,
+        line: u32,
+        message: &str,
+    ) -> Result<Breakpoint, DebugSessionError> {
+        if message.trim().is_empty() {
+            return Err(DebugSessionError::InvalidArgument(
+                "logpoint message must not be empty".into(),
+            ));
+        }
+        let parts = parse_log_template(message).map_err(|err| {
+            DebugSessionError::InvalidArgument(format!("logpoint message `{message}`: {err}"))
+        })?;
+        let (dwarf_path, _) = self.resolve_breakpoint_file(file, self.path_match)?;
+        let addresses = self
+            .backend
+            .line_addresses(&dwarf_path, line)
+            .map_err(DebugSessionError::Backend)?;
+        if addresses.is_empty() {
+            return Err(self.no_code_at(file, &dwarf_path, line));
+        }
+        let breakpoint = self.set_breakpoint(file, line, None)?;
+        self.logpoints.push(Logpoint {
+            id: breakpoint.id,
+            file: file.to_string(),
+            dwarf_path,
+            line,
+            message: message.to_string(),
+            parts,
+            addresses,
+        });
+        Ok(breakpoint)
+    }
+
+    /// The line a breakpoint requested on `line` of `dwarf_path` lands on;
+    /// `line` itself when the file has no line tables to check it against.
+    fn breakpoint_line(&mut self, dwarf_path: &str, line: u32) -> u32 {
+        let lines = self.backend.code_lines(dwarf_path).unwrap_or_default();
+        nearest_code_line(&lines, line).unwrap_or(line)
+    }
+
+    /// The error for a line without code, naming the closest lines that
+    /// have some.
+    fn no_code_at(&mut self, file: &str, dwarf_path: &str, line: u32) -> DebugSessionError {
+        let lines = self.backend.code_lines(dwarf_path).unwrap_or_default();
+        let split = lines.partition_point(|&candidate| candidate < line);
+        let before = &lines[split.saturating_sub(2)..split];
+        let after = &lines[split..(split + 2).min(lines.len())];
+        let near: Vec<String> = before.iter().chain(after).map(u32::to_string).collect();
+        DebugSessionError::Coded {
+            code: ErrorCode::NoCodeAtLine,
+            message: format!("no code at {file}:{line}"),
+            hint: (!near.is_empty())
+                .then(|| format!("lines with code near {line}: {}", near.join(", "))),
+        }
+    }
+
+    /// Like `set_breakpoint`, but the breakpoint is deleted by the first stop
+    /// at it.
+    pub fn set_temporary_breakpoint(
+        &mut self,
+        file: &str,
+        line: u32,
+        path_match: Option<PathMatch>,
+    ) -> Result<Breakpoint, DebugSessionError> {
+        let (dwarf_path, _) =
+            self.resolve_breakpoint_file(file, path_match.unwrap_or(self.path_match))?;
+        let actual_line = self.breakpoint_line(&dwarf_path, line);
+        let shared = self
+            .file_breakpoints
+            .get(&dwarf_path)
+            .is_some_and(|lines| lines.contains(&(actual_line as i64)));
+        let addresses = self
+            .backend
+            .line_addresses(&dwarf_path, actual_line)
+            .map_err(DebugSessionError::Backend)?;
+        let mut breakpoint = self.set_breakpoint(file, line, path_match)?;
+        breakpoint.temporary = true;
+        self.temporary_breakpoints.push(TemporaryBreakpoint {
+            id: breakpoint.id,
+            dwarf_path,
+            line: actual_line,
+            addresses,
+            shared,
+        });
+        Ok(breakpoint)
+    }
+
+    /// Planted breakpoints and logpoints by DWARF path, in the form
+    /// [`DebugSession::import_breakpoints`] takes back.
+    pub fn export_breakpoints(&self) -> Vec<BreakpointSpec> {
+        let mut files: Vec<_> = self.file_breakpoints.iter().collect();
+        files.sort();
+        let mut specs = Vec::new();
+        for (path, lines) in files {
+            for &line in lines {
+                let line = line as u32;
+                let message = self
+                    .logpoints
+                    .iter()
+                    .find(|logpoint| &logpoint.dwarf_path == path && logpoint.line == line)
+                    .map(|logpoint| logpoint.message.clone());
+                let temporary = message.is_none()
+                    && self.temporary_breakpoints.iter().any(|temporary| {
+                        &temporary.dwarf_path == path && temporary.line == line && !temporary.shared
+                    });
+                specs.push(BreakpointSpec {
+                    file: path.clone(),
+                    line,
+                    message,
+                    temporary,
+                });
+            }
+        }
+        specs
+    }
+
+    /// Sets every breakpoint in `specs`. An entry that fails (no such file,
+    /// no code at the line) is reported and does not stop the others.
+    pub fn import_breakpoints(
+        &mut self,
+        specs: &[BreakpointSpec],
+    ) -> (Vec<Breakpoint>, Vec<String>) {
+        let mut set = Vec::new();
+        let mut failed = Vec::new();
+        for spec in specs {
+            let result = match &spec.message {
+                Some(message) => self.set_logpoint(&spec.file, spec.line, message),
+                None if spec.temporary => {
+                    self.set_temporary_breakpoint(&spec.file, spec.line, None)
+                }
+                None => self.set_breakpoint(&spec.file, spec.line, None),
+            };
+            match result {
+                Ok(breakpoint) => set.push(breakpoint),
+                Err(err) => failed.push(format!("{}:{}: {err}", spec.file, spec.line)),
+            }
+        }
+        (set, failed)
+    }
+
+    /// Plants pending breakpoints again if the target's image list changed
+    /// since the last try, sending a `[breakpoint]` line to the log sink for
+    /// each one that is now planted. Returns their ids.
+    fn retry_pending_breakpoints(&mut self) -> Vec<BreakpointId> {
+        if self.pending_breakpoints.is_empty() || !self.backend.is_connected() {
+            return Vec::new();
+        }
+        // Listing the modules also refreshes the main image's slide.
+        let Ok(modules) = self.backend.modules() else {
+            return Vec::new();
+        };
+        if self.loaded_images == Some(modules.len()) {
+            return Vec::new();
+        }
+        self.loaded_images = Some(modules.len());
+        let mut verified = Vec::new();
+        for pending in std::mem::take(&mut self.pending_breakpoints) {
+            match self
+                .backend
+                .plant_line(&pending.dwarf_path, pending.line as i64)
+            {
+                Ok(true) => {
+                    self.emit_log(format!(
+                        "[breakpoint] {}",
+                        json!({
+                            "id": pending.id,
+                            "file": pending.file,
+                            "line": pending.line,
+                            "verified": true,
+                        })
+                    ));
+                    self.emit_event(DebugEvent::BreakpointResolved {
+                        id: pending.id,
+                        file: pending.file,
+                        line: pending.line,
+                    });
+                    verified.push(pending.id);
+                }
+                Ok(false) => self.pending_breakpoints.push(pending),
+                Err(err) => {
+                    log::warn!("retrying breakpoint {}: {err}", pending.id);
+                    self.pending_breakpoints.push(pending);
+                }
+            }
+        }
+        verified
+    }
+
+    /// Deletes the temporary breakpoint the last stop hit, if any, and
+    /// returns its id.
+    fn reap_temporary_breakpoint(&mut self) -> Option<BreakpointId> {
+        let stop = self.last_stop.as_ref()?;
+        if stop.reason != "breakpoint" || self.temporary_breakpoints.is_empty() {
+            return None;
+        }
+        let pc = self.backend.read_register_u64(stop.thread_id, "pc").ok()?;
+        let index = self
+            .temporary_breakpoints
+            .iter()
+            .position(|temporary| temporary.addresses.contains(&pc))?;
+        let temporary = self.temporary_breakpoints.remove(index);
+        let still_wanted = temporary.shared
+            || self.temporary_breakpoints.iter().any(|other| {
+                other.dwarf_path == temporary.dwarf_path && other.line == temporary.line
+            });
+        if !still_wanted {
+            if let Some(lines) = self.file_breakpoints.get_mut(&temporary.dwarf_path) {
+                lines.remove(&(temporary.line as i64));
+            }
+            if let Err(err) = self
+                .backend
+                .remove_breakpoint(&temporary.dwarf_path, temporary.line as i64)
+            {
+                log::warn!("temporary breakpoint {}: {err}", temporary.id);
+            }
+        }
+        Some(temporary.id)
+    }
+
+    /// Locals of the selected frame of the selected thread.
+    pub fn locals(&mut self) -> Vec<Variable> {
+        self.variables_for_reference(self.locals_reference())
+    }
+
+    /// The reference [`Self::variables_page`] lists the selected frame's locals under.
+    pub fn locals_reference(&self) -> i64 {
+        LOCALS_REFERENCE + self.selected_frame() as i64
+    }
+
+    /// The locals of a frame (`LOCALS_REFERENCE` plus its index) or the
+    /// children of a value listed earlier. Children are read from the target
+    /// only when asked for, and each expandable one gets a new reference.
+    /// Without a debugserver connection the backend's placeholder variables
+    /// are listed.
+    pub fn variables_for_reference(&mut self, reference: i64) -> Vec<Variable> {
+        self.variables_page(reference, ChildPage::default()).0
+    }
+
+    /// The variables of `reference` within `page`, and how many it has in
+    /// all. Only the children in the page are read and given references.
+    pub fn variables_page(&mut self, reference: i64, page: ChildPage) -> (Vec<Variable>, usize) {
+        if !self.backend.is_connected() {
+            let variables = self.backend.variables(reference);
+            let total = variables.len();
+            return (
+                page.apply(variables.into_iter())
+                    .map(Variable::from_backend_value)
+                    .collect(),
+                total,
+            );
+        }
+        let values = if reference >= FIRST_VALUE_REFERENCE {
+            match self.value_refs.get(&reference) {
+                Some(handle) => self.backend.value_children(&handle.clone(), page),
+                None => Ok((Vec::new(), 0)),
+            }
+        } else {
+            let frame_index = (reference - LOCALS_REFERENCE).max(0) as usize;
+            self.backend
+                .frame_variables(self.thread_id, frame_index)
+                .map(|values| {
+                    let total = values.len();
+                    (page.apply(values.into_iter()).collect(), total)
+                })
+        };
+        match values {
+            Ok((values, total)) => (
+                values
+                    .into_iter()
+                    .map(|value| self.variable_for(value))
+                    .collect(),
+                total,
+            ),
+            Err(err) => {
+                log::debug!("variables for reference {reference}: {err}");
+                (Vec::new(), 0)
+            }
+        }
+    }
+
+    /// `value` as a [`Variable`], with a new reference when it can be expanded.
+    fn variable_for(&mut self, value: TargetValue) -> Variable {
+        let variables_reference = match value.children {
+            Some(handle) => {
+                let reference = self.next_value_ref;
+                self.next_value_ref += 1;
+                self.value_refs.insert(reference, handle);
+                reference
+            }
+            None => 0,
+        };
+        Variable {
+            name: value.name,
+            ty: value.ty,
+            value: value.value,
+            variables_reference,
+        }
+    }
+
+    /// Expands `path` (`user.address`, `items[0]`), a local of the selected
+    /// frame, or the children of `reference`, `depth` levels deep.
+    pub fn dump_variable(
+        &mut self,
+        path: Option<&str>,
+        reference: Option<i64>,
+        depth: Option<usize>,
+    ) -> Result<VariableTree, DebugSessionError> {
+        let depth = depth.unwrap_or(DEFAULT_DUMP_DEPTH).min(MAX_DUMP_DEPTH);
+        let root = match (path, reference) {
+            (Some(path), _) => {
+                let locals = self.locals();
+                resolve_variable_path(locals, path, |reference| {
+                    self.variables_for_reference(reference)
+                })?
+            }
+            (None, Some(reference)) => Variable {
+                name: format!("<{reference}>"),
+                ty: String::new(),
+                value: String::new(),
+                variables_reference: reference,
+            },
+            (None, None) => {
+                return Err(DebugSessionError::InvalidArgument(
+                    "dump_variable requires `name` or `variablesReference`".into(),
+                ))
+            }
+        };
+        let mut budget = MAX_DUMP_NODES;
+        Ok(build_variable_tree(
+            root,
+            depth,
+            &mut |reference| self.variables_for_reference(reference),
+            &mut budget,
+            &mut HashSet::new(),
+        ))
+    }
+
+    /// [`evaluate_in`](Self::evaluate_in) bounded by `timeout` (the session
+    /// default when `None`): debugserver packets are refused once it passes,
+    /// and a failure after that point is reported as a timeout. With
+    /// `allow_side_effects`, `function(args)` calls into the target.
+    pub fn evaluate_within(
+        &mut self,
+        expression: &str,
+        language: Option<Language>,
+        timeout: Option<Duration>,
+        allow_side_effects: bool,
+    ) -> Result<EvalResult, DebugSessionError> {
+        let timeout = timeout.unwrap_or(self.eval_timeout);
+        let deadline = Instant::now() + timeout;
+        self.backend.set_deadline(Some(deadline));
+        let calls = allow_side_effects.then_some(timeout);
+        let result = self.evaluate_with(expression, language, calls);
+        self.backend.set_deadline(None);
+        match result {
+            Err(_) if Instant::now() >= deadline => {
+                Err(DebugSessionError::Timeout(timeout.as_millis() as u64))
+            }
+            result => result,
+        }
+    }
+
+    pub fn evaluate(&mut self, expression: &str) -> Result<EvalResult, DebugSessionError> {
+        self.evaluate_in(expression, None)
+    }
+
+    pub fn evaluate_swift(&mut self, expression: &str) -> Result<EvalResult, DebugSessionError> {
+        self.evaluate_in(expression, Some(Language::Swift))
+    }
+
+    /// Evaluates `expression` against the selected frame, in the given language
+    /// or in the language of that frame's source file when none is given.
+    /// Never runs code in the target.
+    ///
+    /// `let $name = <expr>` (or `var`) stores the result as a convenience
+    /// variable that later evaluations can refer to until the target resumes.
+    pub fn evaluate_in(
+        &mut self,
+        expression: &str,
+        language: Option<Language>,
+    ) -> Result<EvalResult, DebugSessionError> {
+        self.evaluate_with(expression, language, None)
+    }
+
+    /// `calls` is how long a `function(args)` call may run in the target, or
+    /// `None` when calls are not allowed.
+    fn evaluate_with(
+        &mut self,
+        expression: &str,
+        language: Option<Language>,
+        calls: Option<Duration>,
+    ) -> Result<EvalResult, DebugSessionError> {
+        let trimmed = expression.trim();
+        if let Some((name, value)) = parse_declaration(trimmed) {
+            if !is_convenience_name(name) {
+                return Err(DebugSessionError::InvalidArgument(format!(
+                    "convenience variable `{name}` must start with `$`"
+                )));
+            }
+            let result = self.evaluate_with(value, language, calls)?;
+            self.convenience_variables
+                .insert(name.to_string(), result.clone());
+            return Ok(result);
+        }
+        if let Some((function, args)) = parse_call(trimmed) {
+            let Some(timeout) = calls else {
+                return Err(DebugSessionError::coded(
+                    ErrorCode::ConfirmationRequired,
+                    format!(
+                        "`{trimmed}` calls `{function}` in the target; pass allow_side_effects: true to run it"
+                    ),
+                ));
+            };
+            return self.evaluate_call(function, &args, language, timeout);
+        }
+        self.evaluate_value(expression, language)
+    }
+
+    /// Calls `function` on the selected thread and returns its raw `x0`.
+    /// Each argument is an integer, `true`/`false`/`nil`, a string literal
+    /// (passed as a C string) or a local or convenience variable holding an
+    /// integer, boolean or pointer. The call is abandoned after `timeout`.
+    fn evaluate_call(
+        &mut self,
+        function: &str,
+        args: &[&str],
+        language: Option<Language>,
+        timeout: Duration,
+    ) -> Result<EvalResult, DebugSessionError> {
+        let language = language.unwrap_or_else(|| self.frame_language());
+        let args = args
+            .iter()
+            .map(|arg| self.call_argument(arg, language))
+            .collect::<Result<Vec<_>, _>>()?;
+        let address = self
+            .backend
+            .function_address(function)
+            .map_err(DebugSessionError::Backend)?;
+        let value = self
+            .backend
+            .call_function(self.thread_id, address, &args, timeout)
+            .map_err(DebugSessionError::Backend)?;
+        Ok(EvalResult {
+            result: (value as i64).to_string(),
+            ty: "Int".into(),
+            variables_reference: 0,
+            language,
+        })
+    }
+
+    fn call_argument(
+        &mut self,
+        text: &str,
+        language: Language,
+    ) -> Result<CallArgument, DebugSessionError> {
+        let text = text.trim();
+        if let Some(literal) = text
+            .strip_prefix('"')
+            .and_then(|rest| rest.strip_suffix('"'))
+        {
+            let mut bytes = unescape_string_literal(literal).into_bytes();
+            bytes.push(0);
+            return Ok(CallArgument::Bytes(bytes));
+        }
+        if let Some(value) = scalar_argument(text) {
+            return Ok(CallArgument::Value(value));
+        }
+        let value = self.evaluate_value(text, Some(language))?;
+        scalar_argument(&value.result)
+            .or_else(|| object_pointer(&value.result))
+            .map(CallArgument::Value)
+            .ok_or_else(|| {
+                DebugSessionError::InvalidArgument(format!(
+                    "`{text}` is `{}`; only integers, booleans, pointers and string literals can be passed to a function",
+                    value.result
+                ))
+            })
+    }
+
+    /// Evaluates `expression` with [`expression::evaluate`] against the
+    /// selected frame's locals and the convenience variables.
+    fn evaluate_value(
+        &mut self,
+        expression: &str,
+        language: Option<Language>,
+    ) -> Result<EvalResult, DebugSessionError> {
+        let trimmed = expression.trim();
+        if trimmed.is_empty() {
+            return Err(DebugSessionError::UnsupportedExpression(
+                expression.to_string(),
+            ));
+        }
+        if let Some(result) = self.convenience_variables.get(trimmed) {
+            return Ok(result.clone());
+        }
+        let language = language.unwrap_or_else(|| self.frame_language());
+        let mut scope = EvalScope {
+            locals: self.locals(),
+            session: self,
+        };
+        match expression::evaluate(trimmed, language, &mut scope) {
+            Ok(variable) => Ok(EvalResult {
+                result: variable.value,
+                ty: variable.ty,
+                variables_reference: variable.variables_reference,
+                language,
+            }),
+            Err(EvalError::Syntax(_)) => Err(DebugSessionError::UnsupportedExpression(
+                expression.to_string(),
+            )),
+            Err(EvalError::Unknown(message)) => Err(DebugSessionError::coded(
+                ErrorCode::UnknownVariable,
+                message,
+            )),
+            Err(EvalError::Failed(message)) => Err(DebugSessionError::InvalidArgument(message)),
+        }
+    }
+
+    fn frame_language(&self) -> Language {
+        self.stacktrace()
+            .get(self.selected_frame())
+            .and_then(|frame| Language::for_source(&frame.file))
+            .unwrap_or_default()
+    }
+
+    pub fn add_watch_expression(
+        &mut self,
+        expression: &str,
+    ) -> Result<Vec<WatchValue>, DebugSessionError> {
+        let trimmed = expression.trim();
+        if trimmed.is_empty() {
+            return Err(DebugSessionError::UnsupportedExpression(
+                expression.to_string(),
+            ));
+        }
+        if !self
+            .watch_expressions
+            .iter()
+            .any(|existing| existing == trimmed)
+        {
+            self.watch_expressions.push(trimmed.to_string());
+        }
+        let values = self.evaluate_watch_expressions();
+        // A new watch is compared with its value now at the next stop.
+        for watch in values.iter().flatten() {
+            self.watch_values
+                .entry(watch.expression.clone())
+                .or_insert_with(|| Some(watch.result.result.clone()));
+        }
+        values
+    }
+
+    pub fn evaluate_watch_expressions(&mut self) -> Result<Vec<WatchValue>, DebugSessionError> {
+        self.watch_expressions
+            .clone()
+            .iter()
+            .map(|expr| {
+                self.evaluate_value(expr, None).map(|result| WatchValue {
+                    expression: expr.clone(),
+                    result,
+                })
+            })
+            .collect()
+    }
+
+    /// Re-evaluates every watch expression at a stop, each compared with its
+    /// value at the previous one.
+    fn watch_deltas(&mut self) -> Vec<WatchDelta> {
+        let mut deltas = Vec::with_capacity(self.watch_expressions.len());
+        for expression in self.watch_expressions.clone() {
+            let (value, error) = match self.evaluate_value(&expression, None) {
+                Ok(result) => (Some(result.result), None),
+                Err(err) => (None, Some(err.to_string())),
+            };
+            let previous = self.watch_values.insert(expression.clone(), value.clone());
+            deltas.push(WatchDelta {
+                changed: previous.as_ref().is_some_and(|previous| *previous != value),
+                previous: previous.flatten(),
+                expression,
+                value,
+                error,
+            });
+        }
+        deltas
+    }
+
+    /// Records the selected frame's locals, expanded `depth` levels deep
+    /// (default 2), every watch expression and the `memory` ranges, so
+    /// [`Self::diff_snapshot`] can later tell what changed. The snapshot is
+    /// kept under `name`, replacing an older one of that name, or under a
+    /// generated `snapshot-<n>`.
+    pub fn snapshot(
+        &mut self,
+        name: Option<&str>,
+        depth: Option<usize>,
+        memory: &[(u64, usize)],
+    ) -> Result<Snapshot, DebugSessionError> {
+        let id = match name.map(str::trim) {
+            Some("") => {
+                return Err(DebugSessionError::InvalidArgument(
+                    "snapshot name must not be empty".into(),
+                ))
+            }
+            Some(name) => name.to_string(),
+            None => {
+                self.next_snapshot += 1;
+                format!("snapshot-{}", self.next_snapshot - 1)
+            }
+        };
+        let depth = depth.unwrap_or(DEFAULT_SNAPSHOT_DEPTH).min(MAX_DUMP_DEPTH);
+        let snapshot = self.capture_snapshot(id, depth, memory);
+        self.snapshots.retain(|kept| kept.id != snapshot.id);
+        if self.snapshots.len() == MAX_SNAPSHOTS {
+            self.snapshots.pop_front();
+        }
+        self.snapshots.push_back(snapshot.clone());
+        Ok(snapshot)
+    }
+
+    /// What changed between the snapshots `from` and `to`, or between `from`
+    /// and the target as it is now, captured the same way.
+    pub fn diff_snapshot(
+        &mut self,
+        from: &str,
+        to: Option<&str>,
+    ) -> Result<SnapshotDiff, DebugSessionError> {
+        let find = |id: &str| {
+            self.snapshots
+                .iter()
+                .find(|snapshot| snapshot.id == id)
+                .cloned()
+                .ok_or_else(|| {
+                    DebugSessionError::InvalidArgument(format!(
+                        "no snapshot `{id}` (snapshots: {})",
+                        self.snapshots
+                            .iter()
+                            .map(|snapshot| snapshot.id.as_str())
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ))
+                })
+        };
+        let before = find(from)?;
+        let after = match to {
+            Some(to) => find(to)?,
+            None => {
+                let ranges: Vec<(u64, usize)> = before
+                    .memory
+                    .iter()
+                    .map(|range| (range.start, range.length))
+                    .collect();
+                self.capture_snapshot("current".into(), before.depth, &ranges)
+            }
+        };
+        Ok(diff_snapshots(&before, &after))
+    }
+
+    fn capture_snapshot(&mut self, id: String, depth: usize, memory: &[(u64, usize)]) -> Snapshot {
+        let frame = self.stacktrace().into_iter().nth(self.selected_frame());
+        let mut locals = BTreeMap::new();
+        let mut budget = MAX_DUMP_NODES;
+        for local in self.locals() {
+            let path = local.name.clone();
+            let tree = build_variable_tree(
+                local,
+                depth,
+                &mut |reference| self.variables_for_reference(reference),
+                &mut budget,
+                &mut HashSet::new(),
+            );
+            flatten_variable_tree(tree, path, &mut locals);
+        }
+        let mut watches = BTreeMap::new();
+        for expression in self.watch_expressions.clone() {
+            let value = match self.evaluate_value(&expression, None) {
+                Ok(result) => result.result,
+                Err(err) => format!("<{err}>"),
+            };
+            watches.insert(expression, value);
+        }
+        let memory = memory
+            .iter()
+            .map(|&(start, length)| {
+                let read = self.backend.read_memory(start, length);
+                MemorySnapshot {
+                    address: format!("0x{start:x}"),
+                    start,
+                    length,
+                    bytes: read
+                        .as_ref()
+                        .ok()
+                        .map(|bytes| bytes.iter().map(|byte| format!("{byte:02x}")).collect()),
+                    error: read.err(),
+                }
+            })
+            .collect();
+        Snapshot {
+            id,
+            thread_id: self.thread_id,
+            function: frame.as_ref().map(|frame| frame.function.clone()),
+            location: frame
+                .filter(|frame| frame.line > 0)
+                .map(|frame| format!("{}:{}", file_name_of(&frame.file), frame.line)),
+            locals,
+            watches,
+            memory,
+            depth,
+        }
+    }
+
+    pub fn read_memory(
+        &mut self,
+        address: u64,
+        length: usize,
+    ) -> Result<MemoryDump, DebugSessionError> {
+        let bytes = self
+            .backend
+            .read_memory(address, length)
+            .map_err(DebugSessionError::Backend)?;
+        Ok(MemoryDump {
+            address: format!("0x{address:x}"),
+            length: bytes.len(),
+            base64: BASE64.encode(&bytes),
+            hex_dump: hex_dump(address, &bytes),
+        })
+    }
+
+    /// Disassembles `count` instructions starting at `address` or `symbol`.
+    /// With neither, the window surrounds the selected thread's PC.
+    pub fn disassemble(
+        &mut self,
+        address: Option<u64>,
+        symbol: Option<&str>,
+        count: Option<usize>,
+    ) -> Result<Disassembly, DebugSessionError> {
+        let count = count
+            .unwrap_or(DEFAULT_DISASSEMBLY_COUNT)
+            .clamp(1, MAX_DISASSEMBLY_COUNT);
+        let pc = self.backend.program_counter(self.thread_id);
+        let start = match (address, symbol) {
+            (Some(address), _) => address,
+            (None, Some(symbol)) => self
+                .backend
+                .symbol_address(symbol)
+                .map_err(DebugSessionError::Backend)?,
+            (None, None) => {
+                let pc = pc.clone().map_err(DebugSessionError::Backend)?;
+                pc.saturating_sub(4 * (count / 4) as u64)
+            }
+        } & !3;
+        let pc = pc.ok();
+        let bytes = self
+            .backend
+            .read_memory(start, count * 4)
+            .map_err(DebugSessionError::Backend)?;
+        let instructions = disasm::disassemble(start, &bytes)
+            .into_iter()
+            .map(|instruction| DisassemblyLine {
+                address: format!("0x{:x}", instruction.address),
+                opcode: format!("0x{:08x}", instruction.word),
+                text: instruction.text(),
+                symbol: instruction
+                    .target
+                    .and_then(|target| self.backend.symbol_name(target)),
+                current: pc == Some(instruction.address),
+            })
+            .collect();
+        Ok(Disassembly {
+            address: format!("0x{start:x}"),
+            function: self.backend.symbol_name(start),
+            pc: pc.map(|pc| format!("0x{pc:x}")),
+            instructions,
+        })
+    }
+
+    /// Assigns a new value to a local or `local.member` of the selected thread's top frame.
+    pub fn set_variable(&mut self, name: &str, value: &str) -> Result<Variable, DebugSessionError> {
+        if self.selected_frame() != 0 {
+            return Err(DebugSessionError::InvalidArgument(
+                "set_variable only writes the innermost frame; select_frame 0 first".into(),
+            ));
+        }
+        let (value, ty) = self
+            .backend
+            .set_variable(self.thread_id, name, value)
+            .map_err(DebugSessionError::Backend)?;
+        self.convenience_variables.clear();
+        Ok(Variable {
+            name: name.to_string(),
+            ty,
+            value,
+            variables_reference: 0,
+        })
+    }
+
+    pub fn symbolicate(&self, addresses: &[u64]) -> Vec<AddressInfo> {
+        addresses
+            .iter()
+            .map(|address| self.backend.symbolicate(*address))
+            .collect()
+    }
+
+    pub fn lookup_symbol(&self, name: &str) -> Result<Vec<SymbolAddress>, DebugSessionError> {
+        let matches = self
+            .backend
+            .lookup_symbol(name)
+            .map_err(DebugSessionError::Backend)?;
+        Ok(matches
+            .into_iter()
+            .map(|symbol| {
+                let name = symbols::demangle_symbol(&symbol.name);
+                SymbolAddress {
+                    mangled_name: (name != symbol.name).then_some(symbol.name),
+                    name,
+                    address: format!("0x{:x}", symbol.address),
+                }
+            })
+            .collect())
+    }
+
+    pub fn modules(&mut self) -> Result<Vec<ModuleInfo>, DebugSessionError> {
+        self.backend.modules().map_err(DebugSessionError::Backend)
+    }
+
+    /// The region containing `address`, or every mapped region; the flag is
+    /// set when the list was cut short.
+    pub fn memory_regions(
+        &mut self,
+        address: Option<u64>,
+    ) -> Result<(Vec<RegionInfo>, bool), DebugSessionError> {
+        self.backend
+            .memory_regions(address)
+            .map_err(DebugSessionError::Backend)
+    }
+
+    pub fn heap_info(&mut self, address: u64) -> Result<HeapInfo, DebugSessionError> {
+        self.backend
+            .heap_info(address)
+            .map_err(DebugSessionError::Backend)
+    }
+
+    /// Objective-C classes the runtime has realized outside the shared cache,
+    /// optionally only those whose name contains `filter`, and whether the
+    /// list was cut.
+    pub fn objc_classes(
+        &mut self,
+        filter: Option<&str>,
+    ) -> Result<(Vec<ClassEntry>, bool), DebugSessionError> {
+        self.backend
+            .objc_classes(filter)
+            .map_err(DebugSessionError::Backend)
+    }
+
+    /// Ivars, properties and methods of the class called `name`, or of the
+    /// dynamic class of the object at `address`.
+    pub fn objc_class(
+        &mut self,
+        name: Option<&str>,
+        address: Option<u64>,
+    ) -> Result<ClassDump, DebugSessionError> {
+        if name.is_none() && address.is_none() {
+            return Err(DebugSessionError::InvalidArgument(
+                "objc_class needs a class `name` or an object `address`".into(),
+            ));
+        }
+        self.backend
+            .objc_class(name, address)
+            .map_err(DebugSessionError::Backend)
+    }
+
+    /// Swift concurrency tasks with their async backtraces: the running
+    /// ones, the ones at the task `handles`, and their children.
+    pub fn tasks(&mut self, handles: &[u64]) -> Result<Vec<TaskInfo>, DebugSessionError> {
+        self.backend
+            .swift_tasks(handles)
+            .map_err(DebugSessionError::Backend)
+    }
+
+    /// Status of the Swift actors at `addresses`: executor, state, the task
+    /// holding each and the tasks queued on it.
+    pub fn actors(&mut self, addresses: &[u64]) -> Result<Vec<ActorInfo>, DebugSessionError> {
+        addresses
+            .iter()
+            .map(|&address| {
+                self.backend
+                    .actor_info(address)
+                    .map_err(DebugSessionError::Backend)
+            })
+            .collect()
+    }
+
+    /// LLDB's `po`: the `debugDescription` of the object at `address`, or at
+    /// the pointer `expression` evaluates to, computed by calling into the
+    /// Objective-C runtime on the selected thread. The calls share the
+    /// evaluation timeout unless `timeout` is given.
+    pub fn describe(
+        &mut self,
+        address: Option<u64>,
+        expression: Option<&str>,
+        timeout: Option<Duration>,
+    ) -> Result<ObjectDescription, DebugSessionError> {
+        let address = match (address, expression) {
+            (Some(address), None) => address,
+            (None, Some(expression)) => {
+                let value = self.evaluate(expression)?;
+                object_pointer(&value.result).ok_or_else(|| {
+                    DebugSessionError::InvalidArgument(format!(
+                        "`{expression}` is `{}`, not an object pointer",
+                        value.result
+                    ))
+                })?
+            }
+            _ => {
+                return Err(DebugSessionError::InvalidArgument(
+                    "describe takes either an address or an expression".into(),
+                ))
+            }
+        };
+        if address == 0 {
+            return Err(DebugSessionError::InvalidArgument(
+                "cannot describe a null pointer".into(),
+            ));
+        }
+        let timeout = timeout.unwrap_or(self.eval_timeout);
+        let deadline = Instant::now() + timeout;
+        match self
+            .backend
+            .describe_object(self.thread_id, address, timeout)
+        {
+            Err(_) if Instant::now() >= deadline => {
+                Err(DebugSessionError::Timeout(timeout.as_millis() as u64))
+            }
+            result => result.map_err(DebugSessionError::Backend),
+        }
+    }
+
+    pub fn registers(&mut self) -> Result<Vec<RegisterValue>, DebugSessionError> {
+        self.backend
+            .registers(self.thread_id)
+            .map_err(DebugSessionError::Backend)
+    }
+
+    pub fn set_register(&mut self, name: &str, value: u64) -> Result<(), DebugSessionError> {
+        self.backend
+            .set_register(self.thread_id, name, value)
+            .map_err(DebugSessionError::Backend)
+    }
+
+    /// Patches target memory. `confirm` must be set explicitly so a stray call
+    /// cannot corrupt the inferior.
+    pub fn write_memory(
+        &mut self,
+        address: u64,
+        bytes: &[u8],
+        confirm: bool,
+    ) -> Result<usize, DebugSessionError> {
+        if !confirm {
+            return Err(DebugSessionError::coded(
+                ErrorCode::ConfirmationRequired,
+                "write_memory modifies the target; resend with \"confirm\": true",
+            ));
+        }
+        if bytes.is_empty() {
+            return Err(DebugSessionError::InvalidArgument(
+                "write_memory requires at least one byte".into(),
+            ));
+        }
+        self.backend
+            .write_memory(address, bytes)
+            .map_err(DebugSessionError::Backend)?;
+        Ok(bytes.len())
+    }
+
+    pub fn select_thread(&mut self, thread_id: i64) {
+        self.thread_id = thread_id.max(1);
+    }
+
+    /// Freezes `thread_id` while the other threads run, e.g. to widen a race
+    /// window; returns every suspended thread.
+    pub fn suspend_thread(&mut self, thread_id: i64) -> Result<Vec<i64>, DebugSessionError> {
+        self.backend
+            .suspend_thread(thread_id)
+            .map_err(DebugSessionError::Backend)?;
+        Ok(self.backend.suspended_threads())
+    }
+
+    /// Undoes [`DebugSession::suspend_thread`]; returns the threads still suspended.
+    pub fn resume_thread(&mut self, thread_id: i64) -> Result<Vec<i64>, DebugSessionError> {
+        self.backend
+            .resume_thread(thread_id)
+            .map_err(DebugSessionError::InvalidArgument)?;
+        Ok(self.backend.suspended_threads())
+    }
+
+    /// Makes frame `index` (0 = innermost) of the selected thread the scope of
+    /// `locals`, `scopes`, `evaluate` and `watch_expr`.
+    pub fn select_frame(&mut self, index: usize) -> Result<Frame, DebugSessionError> {
+        let frames = self.stacktrace();
+        let count = frames.len();
+        let frame = frames.into_iter().nth(index).ok_or_else(|| {
+            DebugSessionError::coded(
+                ErrorCode::UnknownFrame,
+                format!(
+                    "frame {index} is out of range; thread {} has {count} frame(s)",
+                    self.thread_id
+                ),
+            )
+        })?;
+        self.selected_frames.insert(self.thread_id, index);
+        Ok(frame)
+    }
+
+    /// Thread used by inspection and stepping, from `select_thread` or the
+    /// last stop.
+    pub fn selected_thread(&self) -> i64 {
+        self.thread_id
+    }
+
+    /// The stop the target is sitting at, if it is stopped.
+    pub fn last_stop(&self) -> Option<&SessionStop> {
+        self.last_stop.as_ref()
+    }
+
+    pub fn debugserver_port(&self) -> Option<u16> {
+        self.backend.connected_port()
+    }
+
+    pub fn process_id(&mut self) -> Option<u64> {
+        self.backend.process_id()
+    }
+
+    pub fn selected_frame(&self) -> usize {
+        self.selected_frames
+            .get(&self.thread_id)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn program_path(&self) -> &Path {
+        self.backend.program_path()
+    }
+
+    /// The architecture loaded, when the program is a universal binary.
+    pub fn program_slice(&self) -> Option<&str> {
+        self.backend.program_slice()
+    }
+
+    pub fn debug_info_path(&self) -> &Path {
+        self.backend.debug_info_path()
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum DebugSessionError {
+    #[error("{0}")]
+    Backend(String),
+    #[error("expression `{0}` is not supported")]
+    UnsupportedExpression(String),
+    #[error("{0}")]
+    InvalidArgument(String),
+    #[error("evaluation timed out after {0} ms")]
+    Timeout(u64),
+    /// A failure whose code and hint are known where it happens rather than
+    /// derived from the message.
+    #[error("{message}")]
+    Coded {
+        code: ErrorCode,
+        message: String,
+        hint: Option<String>,
+    },
+}
+
+impl DebugSessionError {
+    /// A failure of a known kind, with that kind's usual hint.
+    pub fn coded(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self::Coded {
+            code,
+            message: message.into(),
+            hint: None,
+        }
+    }
+
+    /// What kind of failure this is, for callers to branch on. Errors built
+    /// without a code, such as the backend's plain strings, are recognized
+    /// by their message.
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Self::Coded { code, .. } => *code,
+            Self::UnsupportedExpression(_) => ErrorCode::UnsupportedExpression,
+            Self::Timeout(_) => ErrorCode::Timeout,
+            Self::InvalidArgument(_) => ErrorCode::InvalidArgument,
+            Self::Backend(message) => ErrorCode::classify(message).unwrap_or(ErrorCode::Backend),
+        }
+    }
+
+    /// What the caller can do about it, when there is something to do.
+    pub fn hint(&self) -> Option<String> {
+        match self {
+            Self::Coded { code, hint, .. } => {
+                hint.clone().or_else(|| code.hint().map(str::to_string))
+            }
+            other => other.code().hint().map(str::to_string),
+        }
+    }
+}
+
+/// Stable, machine-readable kind of a [`DebugSessionError`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    NotConnected,
+    ProcessExited,
+    /// The command needs a stop the target has not made yet.
+    NotStopped,
+    NoDwarf,
+    SourceNotFound,
+    AmbiguousFile,
+    NoCodeAtLine,
+    UnknownThread,
+    UnknownFrame,
+    UnknownVariable,
+    UnknownSymbol,
+    /// The command changes the target and must be asked for explicitly.
+    ConfirmationRequired,
+    /// The server was started without the option the command needs.
+    NotConfigured,
+    UnsupportedExpression,
+    InvalidArgument,
+    Timeout,
+    /// The exchange with debugserver itself broke down (corrupted or
+    /// refused packets), as opposed to it rejecting the command.
+    Protocol,
+    Backend,
+}
+
+impl ErrorCode {
+    /// Recognizes the messages of well-known failures the backend and
+    /// debugserver raise as plain strings, which carry no code of their own.
+    fn classify(message: &str) -> Option<Self> {
+        const FRAGMENTS: &[(&str, ErrorCode)] = &[
+            ("no gdb-remote connection", ErrorCode::NotConnected),
+            ("failed to connect to debugserver", ErrorCode::NotConnected),
+            ("process exited", ErrorCode::ProcessExited),
+            ("process terminated by signal", ErrorCode::ProcessExited),
+            ("Failed to build DWARF", ErrorCode::NoDwarf),
+            ("no code at ", ErrorCode::NoCodeAtLine),
+            ("no thread ", ErrorCode::UnknownThread),
+            ("symbol `", ErrorCode::UnknownSymbol),
+            (gdb_remote::PROTOCOL_ERROR, ErrorCode::Protocol),
+        ];
+        FRAGMENTS
+            .iter()
+            .find(|(fragment, _)| message.contains(fragment))
+            .map(|(_, code)| *code)
+    }
+
+    fn hint(self) -> Option<&'static str> {
+        Some(match self {
+            Self::NotConnected => "start debugserver (or `restart` with --manage-bridge) and retry",
+            Self::ProcessExited => "`launch` or `restart` the app",
+            Self::NotStopped => "`continue` or step until the target stops, then retry",
+            Self::NoDwarf => {
+                "rebuild with a Debug configuration (-g) so the binary has DWARF line info"
+            }
+            Self::SourceNotFound => "check the path, or add a --source-map from the build path",
+            Self::AmbiguousFile => "pass more of the path, or `match: exact`",
+            Self::NoCodeAtLine => "pick a line with an executable statement",
+            Self::UnknownThread => "list thread ids with `threads`",
+            Self::UnknownFrame => "list frames with `stacktrace`",
+            Self::UnknownVariable => "list names with `locals`",
+            Self::UnknownSymbol => "check the name with `lookup_symbol`",
+            Self::NotConfigured => "restart the server with the option named in the message",
+            Self::UnsupportedExpression => "inspect `locals`, or try `evaluate_swift`",
+            Self::Timeout => "pass a larger `timeout_ms`, or break the work up",
+            Self::Protocol => "check the device link; `threads` shows whether the command ran",
+            Self::ConfirmationRequired | Self::InvalidArgument | Self::Backend => return None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SymbolAddress {
+    pub name: String,
+    /// Raw symbol-table name, when `name` is its demangled form.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mangled_name: Option<String>,
+    pub address: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThreadState {
+    Stopped,
+    /// Not connected to debugserver, so the thread list may be stale.
+    Unknown,
+}
+
+/// Kernel scheduling state of a thread at the last stop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RunState {
+    Running,
+    /// Waiting on a lock, I/O or a Mach message.
+    Blocked,
+    Suspended,
+}
+
+impl FromStr for RunState {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text {
+            "running" => Ok(Self::Running),
+            "blocked" => Ok(Self::Blocked),
+            "suspended" => Ok(Self::Suspended),
+            other => Err(format!("unknown run state `{other}`")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ThreadInfo {
+    pub id: i64,
+    pub name: String,
+    /// Dispatch queue label, when the backend reports one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub queue: Option<String>,
+    pub state: ThreadState,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub run_state: Option<RunState>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_reason: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_frame: Option<Frame>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Frame {
+    pub frame_index: usize,
+    pub function: String,
+    /// Linkage name of `function`, when it is demangled.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mangled_name: Option<String>,
+    pub file: String,
+    pub line: u32,
+    /// Set on a function the compiler inlined into the next frame, whose
+    /// `line` is then the call site.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub inlined: bool,
+}
+
+impl Frame {
+    fn from_backend_value(index: usize, value: &Value) -> Self {
+        let function = value
+            .get("name")
+            .and_then(Value::as_str)
+            .unwrap_or("<unknown>")
+            .to_string();
+        let mangled_name = value
+            .get("mangledName")
+            .and_then(Value::as_str)
+            .map(str::to_string);
+        let file = value
+            .get("source")
+            .and_then(|src| src.get("path"))
+            .and_then(Value::as_str)
+            .unwrap_or("<unknown>")
+            .to_string();
+        let line = value
+            .get("line")
+            .and_then(Value::as_i64)
+            .unwrap_or_default()
+            .max(0) as u32;
+        let inlined = value
+            .get("inlined")
+            .and_then(Value::as_bool)
+            .unwrap_or_default();
+        Self {
+            frame_index: index,
+            function,
+            mangled_name,
+            file,
+            line,
+            inlined,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Variable {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: String,
+    pub value: String,
+    #[serde(rename = "variablesReference")]
+    pub variables_reference: i64,
+}
+
+impl Variable {
+    fn from_backend_value(value: Value) -> Self {
+        let name = value
+            .get("name")
+            .and_then(Value::as_str)
+            .unwrap_or("<unnamed>")
+            .to_string();
+        let ty = value
+            .get("type")
+            .and_then(Value::as_str)
+            .unwrap_or("<unknown>")
+            .to_string();
+        let val = value
+            .get("value")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string();
+        let variables_reference = value
+            .get("variablesReference")
+            .and_then(Value::as_i64)
+            .unwrap_or_default();
+        Self {
+            name,
+            ty,
+            value: val,
+            variables_reference,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EvalResult {
+    pub result: String,
+    #[serde(rename = "type")]
+    pub ty: String,
+    /// Non-zero when the result has children that `variables` can expand.
+    #[serde(rename = "variablesReference")]
+    pub variables_reference: i64,
+    pub language: Language,
+}
+
+/// The selected frame as [`expression::evaluate`] sees it.
+struct EvalScope<'a> {
+    /// Read once per evaluation rather than per name.
+    locals: Vec<Variable>,
+    session: &'a mut DebugSession,
+}
+
+impl Scope for EvalScope<'_> {
+    fn variable(&self, name: &str) -> Option<Variable> {
+        if let Some(result) = self.session.convenience_variables.get(name) {
+            return Some(Variable {
+                name: name.to_string(),
+                ty: result.ty.clone(),
+                value: result.result.clone(),
+                variables_reference: result.variables_reference,
+            });
+        }
+        self.locals.iter().find(|local| local.name == name).cloned()
+    }
+
+    fn children(&mut self, reference: i64) -> Vec<Variable> {
+        self.session.variables_for_reference(reference)
+    }
+
+    fn read_memory(&mut self, address: u64, length: usize) -> Result<Vec<u8>, String> {
+        self.session.backend.read_memory(address, length)
+    }
+}
+
+/// Source language an expression is evaluated in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Language {
+    #[default]
+    #[serde(rename = "swift")]
+    Swift,
+    #[serde(rename = "objc")]
+    ObjC,
+    #[serde(rename = "objc++")]
+    ObjCPlusPlus,
+    #[serde(rename = "c")]
+    C,
+}
+
+impl Language {
+    /// Infers the language from a source file extension.
+    pub fn for_source(path: &str) -> Option<Self> {
+        let extension = Path::new(path).extension()?.to_str()?;
+        match extension {
+            "swift" => Some(Self::Swift),
+            "m" => Some(Self::ObjC),
+            "mm" | "cpp" | "cc" | "cxx" | "hpp" => Some(Self::ObjCPlusPlus),
+            "c" | "h" => Some(Self::C),
+            _ => None,
+        }
+    }
+
+    /// This language's names for the implicit receiver, whose members are
+    /// recorded as locals when it is not a local itself.
+    pub fn implicit_receivers(&self) -> &'static [&'static str] {
+        match self {
+            Self::Swift | Self::ObjC => &["self"],
+            Self::ObjCPlusPlus => &["self", "this"],
+            Self::C => &[],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WatchValue {
+    pub expression: String,
+    pub result: EvalResult,
+}
+
+/// A watch expression at a stop, next to its value at the stop before.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct WatchDelta {
+    pub expression: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    /// Why the expression could not be evaluated at this stop.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Whether `value` differs from the previous stop's, counting a failed
+    /// evaluation as a value. A watch seen for the first time is unchanged.
+    pub changed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous: Option<String>,
+}
+
+/// The state of a stop recorded by [`DebugSession::snapshot`].
+#[derive(Debug, Clone, Serialize)]
+pub struct Snapshot {
+    pub id: String,
+    pub thread_id: i64,
+    /// The selected frame's function and `file:line`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub function: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    /// Locals and their children by path, e.g. `point.x` or `items[0]`.
+    pub locals: BTreeMap<String, String>,
+    /// Watch expressions and their values, `<error>` where they failed.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub watches: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub memory: Vec<MemorySnapshot>,
+    /// Expansion depth of `locals`, reused for a diff against the present.
+    #[serde(skip)]
+    depth: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MemorySnapshot {
+    pub address: String,
+    #[serde(skip)]
+    start: u64,
+    pub length: usize,
+    /// The bytes in hex, when they could be read.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bytes: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// What [`DebugSession::diff_snapshot`] found changed between two snapshots.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct SnapshotDiff {
+    pub from: String,
+    pub to: String,
+    /// Whether anything below differs.
+    pub changed: bool,
+    #[serde(skip_serializing_if = "ValuesDiff::is_empty")]
+    pub locals: ValuesDiff,
+    #[serde(skip_serializing_if = "ValuesDiff::is_empty")]
+    pub watches: ValuesDiff,
+    /// Ranges whose bytes differ; ranges not read both times are left out.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub memory: Vec<MemoryDiff>,
+}
+
+/// Paths only in the later snapshot, only in the earlier one, and in both
+/// with different values.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ValuesDiff {
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub added: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub removed: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub changed: Vec<ChangedValue>,
+}
+
+impl ValuesDiff {
+    fn between(before: &BTreeMap<String, String>, after: &BTreeMap<String, String>) -> Self {
+        let mut diff = Self::default();
+        for (path, value) in before {
+            match after.get(path) {
+                None => {
+                    diff.removed.insert(path.clone(), value.clone());
+                }
+                Some(now) if now != value => diff.changed.push(ChangedValue {
+                    path: path.clone(),
+                    before: value.clone(),
+                    after: now.clone(),
+                }),
+                Some(_) => {}
+            }
+        }
+        for (path, value) in after {
+            if !before.contains_key(path) {
+                diff.added.insert(path.clone(), value.clone());
+            }
+        }
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ChangedValue {
+    pub path: String,
+    pub before: String,
+    pub after: String,
+}
+
+/// The runs of differing bytes in one snapshotted memory range.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MemoryDiff {
+    pub address: String,
+    pub changes: Vec<ChangedValue>,
+}
+
+/// One call to a function traced with [`DebugSession::trace_function`].
+#[derive(Debug, Clone, Serialize)]
+pub struct FunctionCall {
+    /// Milliseconds since the Unix epoch.
+    pub timestamp_ms: u64,
+    pub thread_id: i64,
+    pub function: String,
+    /// Best-effort values read from the argument registers at entry; see
+    /// [`Backend::entry_arguments`].
+    pub arguments: Vec<TracedArgument>,
+    /// `function at file:line` of the call site, from the return address.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub caller: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TracedArgument {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: String,
+    pub value: String,
+}
+
+/// How [`DebugSession::continue_until_changed`] moves the target between
+/// evaluations.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Advance {
+    /// To the next breakpoint or other stop.
+    #[default]
+    Continue,
+    /// One source line, over calls.
+    Next,
+}
+
+/// Where [`DebugSession::continue_until_changed`] stopped.
+#[derive(Debug, Clone, Serialize)]
+pub struct ValueChange {
+    pub expression: String,
+    /// Whether `value` differs from `previous`; false when the iteration limit,
+    /// a crash or the process exit ended the search.
+    pub changed: bool,
+    /// The value before the first resume.
+    pub previous: String,
+    /// The value at the last stop, when it could be evaluated there.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Resumes made.
+    pub iterations: usize,
+    /// The last stop; absent when the process exited.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop: Option<SessionStop>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MemoryDump {
+    pub address: String,
+    pub length: usize,
+    pub base64: String,
+    pub hex_dump: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AllStacktraces {
+    pub frames: Vec<SharedFrame>,
+    pub threads: Vec<ThreadStack>,
+}
+
+/// A frame location shared by every thread whose backtrace passes through it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SharedFrame {
+    pub function: String,
+    pub file: String,
+    pub line: u32,
+}
+
+impl From<&Frame> for SharedFrame {
+    fn from(frame: &Frame) -> Self {
+        Self {
+            function: frame.function.clone(),
+            file: frame.file.clone(),
+            line: frame.line,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ThreadStack {
+    pub thread_id: i64,
+    pub name: String,
+    /// Indices into [`AllStacktraces::frames`], innermost first.
+    pub frames: Vec<usize>,
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SourceListing {
+    pub path: String,
+    pub line: u32,
+    pub lines: Vec<SourceLine>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SourceLine {
+    pub number: u32,
+    pub text: String,
+    /// Marks the requested (or stopped-at) line.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub current: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Disassembly {
+    pub address: String,
+    pub function: Option<String>,
+    pub pc: Option<String>,
+    pub instructions: Vec<DisassemblyLine>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DisassemblyLine {
+    pub address: String,
+    pub opcode: String,
+    pub text: String,
+    /// Symbol at the branch or literal target, when it resolves.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub symbol: Option<String>,
+    /// Marks the instruction at the selected thread's PC.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub current: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Breakpoint {
+    pub id: BreakpointId,
+    pub file: String,
+    /// Line the breakpoint is set on.
+    pub line: u32,
+    /// Line that was asked for; differs from `line` when it had no code.
+    pub requested_line: u32,
+    /// DWARF path `file` was matched to.
+    pub resolved_file: String,
+    /// Strategy that matched; absent when the binary has no line tables.
+    #[serde(rename = "match", skip_serializing_if = "Option::is_none")]
+    pub matched: Option<PathMatch>,
+    /// Deleted by its first hit.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub temporary: bool,
+    /// Planted in the target. Pending breakpoints are retried as images load.
+    pub verified: bool,
+}
+
+/// How loosely a breakpoint's file argument may match a DWARF source path.
+/// Each mode also tries the stricter ones first.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PathMatch {
+    /// The full path, after source maps.
+    Exact,
+    /// Trailing path components, e.g. `App/ViewController.swift`.
+    #[default]
+    Suffix,
+    /// The file name alone, ignoring directories.
+    Basename,
+}
+
+impl FromStr for PathMatch {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text {
+            "exact" => Ok(Self::Exact),
+            "suffix" => Ok(Self::Suffix),
+            "basename" => Ok(Self::Basename),
+            other => Err(format!(
+                "unknown path match `{other}` (expected exact, suffix or basename)"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for PathMatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Exact => "exact",
+            Self::Suffix => "suffix",
+            Self::Basename => "basename",
+        })
+    }
+}
+
+/// `line` when `lines` (sorted lines with code) has it, else the next line
+/// with code, else the last one before; `None` when `lines` is empty.
+fn nearest_code_line(lines: &[u32], line: u32) -> Option<u32> {
+    let split = lines.partition_point(|&candidate| candidate < line);
+    lines.get(split).or(lines.last()).copied()
+}
+
+fn file_name_of(path: &str) -> &str {
+    path.rsplit(['/', '\\']).next().unwrap_or(path)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionStop {
+    pub reason: String,
+    pub description: String,
+    pub thread_id: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub return_value: Option<ReturnValue>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+    /// Temporary breakpoint this stop hit and deleted.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub removed_breakpoint: Option<BreakpointId>,
+    /// Pending breakpoints planted because new images were loaded.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub verified_breakpoints: Vec<BreakpointId>,
+    /// What the Main Thread Checker reported, for `main_thread_checker` stops.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub main_thread_violation: Option<MainThreadViolation>,
+    /// The Mach exception and signal behind a `signal` or exception stop.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exception: Option<StopException>,
+    /// A guess at the cause of a crash, e.g. `null pointer dereference
+    /// (address 0x0)`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub diagnosis: Option<String>,
+    /// Every watch expression re-evaluated at this stop.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub watches: Vec<WatchDelta>,
+}
+
+/// A decoded Mach exception, so a crash can be triaged without parsing
+/// `description`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StopException {
+    /// `EXC_BAD_ACCESS`, `EXC_BREAKPOINT`, ...
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exception: Option<String>,
+    /// The raw `exception_type_t`, also for types without a name.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exception_type: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subcode: Option<String>,
+    /// The address an `EXC_BAD_ACCESS` could not access.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+    pub signal: u8,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signal_name: Option<String>,
+}
+
+impl StopException {
+    /// Decodes the exception of a stop that is not a breakpoint or step;
+    /// those carry a trap exception that says nothing the reason does not.
+    fn of(stop: &BackendStopEvent) -> Option<Self> {
+        if stop.exception.is_none() && stop.reason != "signal" {
+            return None;
+        }
+        if matches!(stop.reason, "breakpoint" | "step") {
+            return None;
+        }
+        let exception = stop.exception.as_ref();
+        Some(Self {
+            exception: exception.and_then(|e| e.name()).map(str::to_string),
+            exception_type: exception.map(|e| e.exception_type),
+            code: exception.and_then(|e| e.code()),
+            subcode: exception
+                .filter(|e| e.fault_address().is_none())
+                .and_then(|e| e.subcode())
+                .map(|subcode| format!("{subcode:#x}")),
+            address: exception
+                .and_then(|e| e.fault_address())
+                .map(|address| format!("{address:#x}")),
+            signal: stop.signal,
+            signal_name: signal_name(stop.signal),
+        })
+    }
+}
+
+/// A UI API called off the main thread, as reported by the Main Thread
+/// Checker.
+#[derive(Debug, Clone, Serialize)]
+pub struct MainThreadViolation {
+    pub thread_id: i64,
+    /// The checker's report, e.g. `Main Thread Checker: UI API called on a
+    /// background thread: -[UIView setNeedsLayout]`.
+    pub message: String,
+    /// The method named in the report.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api: Option<String>,
+    /// The offending thread's frames, from the checked call outwards.
+    pub frames: Vec<Frame>,
+}
+
+/// The `-[Class selector]` or `+[Class selector]` a Main Thread Checker
+/// report names.
+fn reported_api(message: &str) -> Option<String> {
+    let start = message.find("-[").or_else(|| message.find("+["))?;
+    let end = start + message[start..].find(']')?;
+    Some(message[start..=end].to_string())
+}
+
+/// Something that happened to the target, as sent to the event sink.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum DebugEvent {
+    /// The target was resumed; `thread_id` is the thread that steps, or the
+    /// selected one for `continue`.
+    Continued {
+        thread_id: i64,
+    },
+    Stopped {
+        reason: String,
+        description: String,
+        thread_id: i64,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        exception: Option<StopException>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        diagnosis: Option<String>,
+        /// The stopped thread's top frame.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        function: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        file: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        line: Option<u32>,
+        /// `name=value` pairs of the top frame's locals, as in stop summaries.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        summary: Option<String>,
+    },
+    /// Sent before the `stopped` event of a breakpoint stop, with the source
+    /// location when it is known.
+    BreakpointHit {
+        thread_id: i64,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        file: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        line: Option<u32>,
+    },
+    /// Sent before the `stopped` event of a `main_thread_checker` stop.
+    MainThreadViolation(MainThreadViolation),
+    /// A pending breakpoint was planted, at a stop or as an image loaded.
+    BreakpointResolved {
+        id: BreakpointId,
+        file: String,
+        line: u32,
+    },
+    ThreadCreated {
+        thread_id: i64,
+        name: String,
+    },
+    ThreadExited {
+        thread_id: i64,
+    },
+    /// Carries the image's `uuid` and `slide` when known.
+    ModuleLoaded(ModuleInfo),
+    /// A traced function was entered; the target went on running.
+    FunctionCalled(FunctionCall),
+    ModuleUnloaded {
+        path: String,
+        load_address: String,
+    },
+    ProcessExited {
+        #[serde(flatten)]
+        status: ExitStatus,
+    },
+}
+
+impl From<BackendStopEvent> for SessionStop {
+    fn from(value: BackendStopEvent) -> Self {
+        let exception = StopException::of(&value);
+        Self {
+            reason: value.reason.to_string(),
+            description: value.description,
+            thread_id: value.thread_id,
+            return_value: value.return_value,
+            summary: None,
+            removed_breakpoint: None,
+            verified_breakpoints: Vec::new(),
+            main_thread_violation: None,
+            exception,
+            diagnosis: None,
+            watches: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StackDiff {
+    pub previous_thread: i64,
+    pub current_thread: i64,
+    /// Outermost frames that are the same (function, file and line) in both.
+    pub common_frames: usize,
+    /// Frames below the common ones, innermost first.
+    pub previous_only: Vec<Frame>,
+    pub current_only: Vec<Frame>,
+    /// Same functions at every depth; only lines moved, e.g. another
+    /// iteration of the same loop.
+    pub same_path: bool,
+}
+
+fn diff_stacks(previous: &[Frame], current: &[Frame]) -> StackDiff {
+    let same_frame =
+        |a: &Frame, b: &Frame| a.function == b.function && a.file == b.file && a.line == b.line;
+    let common_frames = previous
+        .iter()
+        .rev()
+        .zip(current.iter().rev())
+        .take_while(|(a, b)| same_frame(a, b))
+        .count();
+    StackDiff {
+        previous_thread: 0,
+        current_thread: 0,
+        common_frames,
+        previous_only: previous[..previous.len() - common_frames].to_vec(),
+        current_only: current[..current.len() - common_frames].to_vec(),
+        same_path: previous.len() == current.len()
+            && previous
+                .iter()
+                .zip(current)
+                .all(|(a, b)| a.function == b.function),
+    }
+}
+
+/// A variable with its children expanded, as returned by `dump_variable`.
+#[derive(Debug, Clone, Serialize)]
+pub struct VariableTree {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: String,
+    pub value: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<VariableTree>,
+    /// Set when children were left unexpanded (depth or size limit, or a
+    /// reference already shown above); `variables` can continue from here.
+    #[serde(rename = "variablesReference", skip_serializing_if = "Option::is_none")]
+    pub unexpanded: Option<i64>,
+}
+
+/// Finds `path` among `locals`, descending through children for each
+/// `.member` or `[index]` segment.
+fn resolve_variable_path<F>(
+    locals: Vec<Variable>,
+    path: &str,
+    mut fetch: F,
+) -> Result<Variable, DebugSessionError>
+where
+    F: FnMut(i64) -> Vec<Variable>,
+{
+    let mut segments = Vec::new();
+    for part in path.trim().split('.') {
+        let mut rest = part;
+        while let Some(open) = rest.find('[') {
+            if open > 0 {
+                segments.push(&rest[..open]);
+            }
+            let close = rest[open..]
+                .find(']')
+                .map_or(rest.len(), |end| open + end + 1);
+            segments.push(&rest[open..close]);
+            rest = &rest[close..];
+        }
+        if !rest.is_empty() {
+            segments.push(rest);
+        }
+    }
+    let (first, children) = segments.split_first().ok_or_else(|| {
+        DebugSessionError::InvalidArgument("variable name must not be empty".into())
+    })?;
+    let mut current = locals
+        .into_iter()
+        .find(|var| var.name == *first)
+        .ok_or_else(|| {
+            DebugSessionError::coded(
+                ErrorCode::UnknownVariable,
+                format!("no variable `{first}` in the selected frame"),
+            )
+        })?;
+    for segment in children {
+        let children = match current.variables_reference {
+            0 => Vec::new(),
+            reference => fetch(reference),
+        };
+        let parent = current.name;
+        current = children
+            .into_iter()
+            .find(|var| var.name == *segment)
+            .ok_or_else(|| {
+                DebugSessionError::coded(
+                    ErrorCode::UnknownVariable,
+                    format!("`{parent}` has no child `{segment}`"),
+                )
+            })?;
+    }
+    Ok(current)
+}
+
+fn build_variable_tree<F>(
+    variable: Variable,
+    depth: usize,
+    fetch: &mut F,
+    budget: &mut usize,
+    seen: &mut HashSet<i64>,
+) -> VariableTree
+where
+    F: FnMut(i64) -> Vec<Variable>,
+{
+    let reference = variable.variables_reference;
+    let mut tree = VariableTree {
+        name: variable.name,
+        ty: variable.ty,
+        value: variable.value,
+        children: Vec::new(),
+        unexpanded: None,
+    };
+    if reference == 0 {
+        return tree;
+    }
+    if depth == 0 || *budget == 0 || !seen.insert(reference) {
+        tree.unexpanded = Some(reference);
+        return tree;
+    }
+    for child in fetch(reference) {
+        if *budget == 0 {
+            tree.unexpanded = Some(reference);
+            break;
+        }
+        *budget -= 1;
+        tree.children
+            .push(build_variable_tree(child, depth - 1, fetch, budget, seen));
+    }
+    tree
+}
+
+/// Adds `tree` and its expanded children to `out` by path: `point.x` for a
+/// member, `items[0]` for an element.
+fn flatten_variable_tree(tree: VariableTree, path: String, out: &mut BTreeMap<String, String>) {
+    for child in tree.children {
+        let child_path = if child.name.starts_with('[') {
+            format!("{path}{}", child.name)
+        } else {
+            format!("{path}.{}", child.name)
+        };
+        flatten_variable_tree(child, child_path, out);
+    }
+    out.insert(path, tree.value);
+}
+
+fn diff_snapshots(before: &Snapshot, after: &Snapshot) -> SnapshotDiff {
+    let mut diff = SnapshotDiff {
+        from: before.id.clone(),
+        to: after.id.clone(),
+        locals: ValuesDiff::between(&before.locals, &after.locals),
+        watches: ValuesDiff::between(&before.watches, &after.watches),
+        ..SnapshotDiff::default()
+    };
+    for range in &before.memory {
+        let now = after
+            .memory
+            .iter()
+            .find(|now| now.start == range.start && now.length == range.length);
+        let (Some(old), Some(new)) = (&range.bytes, now.and_then(|now| now.bytes.as_ref())) else {
+            continue;
+        };
+        let changes = changed_byte_runs(range.start, old, new);
+        if !changes.is_empty() {
+            diff.memory.push(MemoryDiff {
+                address: range.address.clone(),
+                changes,
+            });
+        }
+    }
+    diff.changed = !diff.locals.is_empty() || !diff.watches.is_empty() || !diff.memory.is_empty();
+    diff
+}
+
+/// The runs of bytes that differ between the hex strings `old` and `new`,
+/// read at `start`, each at the address of its first byte.
+fn changed_byte_runs(start: u64, old: &str, new: &str) -> Vec<ChangedValue> {
+    let old: Vec<&str> = hex_bytes(old).collect();
+    let new: Vec<&str> = hex_bytes(new).collect();
+    let mut runs = Vec::new();
+    let mut offset = 0;
+    while offset < old.len().min(new.len()) {
+        if old[offset] == new[offset] {
+            offset += 1;
+            continue;
+        }
+        let run_start = offset;
+        while offset < old.len().min(new.len()) && old[offset] != new[offset] {
+            offset += 1;
+        }
+        runs.push(ChangedValue {
+            path: format!("0x{:x}", start + run_start as u64),
+            before: old[run_start..offset].concat(),
+            after: new[run_start..offset].concat(),
+        });
+    }
+    runs
+}
+
+fn hex_bytes(hex: &str) -> impl Iterator<Item = &str> {
+    (0..hex.len() / 2).map(move |index| &hex[index * 2..index * 2 + 2])
+}
+
+/// Splits a logpoint message into text and `{expression}` parts. `{{` and
+/// `}}` stand for literal braces; any other brace must open or close an
+/// expression.
+fn parse_log_template(template: &str) -> Result<Vec<LogPart>, String> {
+    let mut parts = Vec::new();
+    let mut text = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                text.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                text.push('}');
+            }
+            '{' => {
+                let mut expression = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some('{') => {
+                            return Err(
+                                "`{` inside an expression; write `{{` for a literal brace".into()
+                            )
+                        }
+                        Some(ch) => expression.push(ch),
+                        None => {
+                            return Err(format!("`{{{expression}` is missing its closing `}}`"))
+                        }
+                    }
+                }
+                if expression.trim().is_empty() {
+                    return Err("`{}` has no expression in it".into());
+                }
+                if !text.is_empty() {
+                    parts.push(LogPart::Text(std::mem::take(&mut text)));
+                }
+                parts.push(LogPart::Expression(expression));
+            }
+            '}' => return Err("unmatched `}`; write `}}` for a literal brace".into()),
+            other => text.push(other),
+        }
+    }
+    if !text.is_empty() {
+        parts.push(LogPart::Text(text));
+    }
+    Ok(parts)
+}
+
+/// Splits `let $name = value` / `var $name = value` into name and value.
+fn parse_declaration(expression: &str) -> Option<(&str, &str)> {
+    let rest = expression
+        .strip_prefix("let ")
+        .or_else(|| expression.strip_prefix("var "))?;
+    let (name, value) = rest.split_once('=')?;
+    Some((name.trim(), value.trim()))
+}
+
+fn is_convenience_name(name: &str) -> bool {
+    name.strip_prefix('$').is_some_and(|ident| {
+        !ident.is_empty()
+            && ident
+                .chars()
+                .all(|ch| ch.is_ascii_alphanumeric() || ch == '_')
+    })
+}
+
+/// Renders variables as `name=value, ...`, clipping long values so the line stays short.
+pub fn summarize_variables(variables: &[Variable]) -> String {
+    variables
+        .iter()
+        .map(|var| {
+            if var.value.chars().count() > SUMMARY_VALUE_LIMIT {
+                let clipped: String = var.value.chars().take(SUMMARY_VALUE_LIMIT).collect();
+                format!("{}={clipped}…", var.name)
+            } else {
+                format!("{}={}", var.name, var.value)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Renders bytes as `xxd`-style lines: address, 16 hex bytes, printable ASCII.
+pub fn hex_dump(address: u64, bytes: &[u8]) -> String {
+    bytes
+        .chunks(16)
+        .enumerate()
+        .map(|(idx, chunk)| {
+            let hex = chunk
+                .iter()
+                .map(|byte| format!("{byte:02x}"))
+                .collect::<Vec<_>>()
+                .join(" ");
+            let ascii: String = chunk
+                .iter()
+                .map(|byte| {
+                    if byte.is_ascii_graphic() || *byte == b' ' {
+                        *byte as char
+                    } else {
+                        '.'
+                    }
+                })
+                .collect();
+            let line_address = address.wrapping_add((idx * 16) as u64);
+            format!("0x{line_address:016x}: {hex:<47}  |{ascii}|")
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Decodes a `hex` (whitespace tolerated) or `base64` byte payload.
+pub fn decode_bytes(data: &str, encoding: &str) -> Result<Vec<u8>, DebugSessionError> {
+    match encoding {
+        "hex" => {
+            let compact: String = data.chars().filter(|c| !c.is_whitespace()).collect();
+            let compact = compact.strip_prefix("0x").unwrap_or(&compact);
+            crate::gdb_remote::decode_hex(compact).map_err(|_| {
+                DebugSessionError::InvalidArgument(format!("invalid hex data `{data}`"))
+            })
+        }
+        "base64" => BASE64.decode(data.trim()).map_err(|err| {
+            DebugSessionError::InvalidArgument(format!("invalid base64 data: {err}"))
+        }),
+        other => Err(DebugSessionError::InvalidArgument(format!(
+            "unknown encoding `{other}`; expected `hex` or `base64`"
+        ))),
+    }
+}
+
+/// Parses an address given as `0x`-prefixed hex or plain decimal.
+pub fn parse_address(text: &str) -> Option<u64> {
+    let text = text.trim();
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+/// The pointer in a rendered value: the value itself when it is a bare
+/// address, otherwise the first `0x` address inside it, as in
+/// `<MyApp.Cart: 0x600000c04000>`.
+fn object_pointer(value: &str) -> Option<u64> {
+    parse_address(value).or_else(|| {
+        let hex = &value[value.find("0x")? + 2..];
+        let end = hex
+            .find(|c: char| !c.is_ascii_hexdigit())
+            .unwrap_or(hex.len());
+        u64::from_str_radix(&hex[..end], 16).ok()
+    })
+}
+
+/// `name(arg, ...)` split into the function name and its top-level
+/// arguments; anything else, including method calls, is not a call.
+fn parse_call(expression: &str) -> Option<(&str, Vec<&str>)> {
+    let open = expression.find('(')?;
+    let name = expression[..open].trim();
+    let inner = expression[open + 1..].strip_suffix(')')?;
+    let identifier = name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$');
+    if name.is_empty() || !identifier || name.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    let mut args = Vec::new();
+    let (mut depth, mut quoted, mut escaped, mut start) = (0usize, false, false, 0);
+    for (index, c) in inner.char_indices() {
+        if quoted {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => quoted = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => quoted = true,
+            '(' => depth += 1,
+            ')' => depth = depth.checked_sub(1)?,
+            ',' if depth == 0 => {
+                args.push(inner[start..index].trim());
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    if quoted || depth != 0 {
+        return None;
+    }
+    let last = inner[start..].trim();
+    if !last.is_empty() || !args.is_empty() {
+        args.push(last);
+    }
+    Some((name, args))
+}
+
+/// An integer literal (decimal, possibly negative, or `0x` hex) or a
+/// boolean/null keyword, as the bits a function argument register holds.
+fn scalar_argument(text: &str) -> Option<u64> {
+    match text.trim() {
+        "true" | "YES" => Some(1),
+        "false" | "NO" | "nil" | "NULL" | "nullptr" => Some(0),
+        text => text
+            .parse::<i64>()
+            .map(|value| value as u64)
+            .ok()
+            .or_else(|| parse_address(text)),
+    }
+}
+
+/// The text of a string literal's body with `\n`, `\t`, `\0`, `\"` and `\\`
+/// escapes resolved.
+fn unescape_string_literal(literal: &str) -> String {
+    let mut text = String::with_capacity(literal.len());
+    let mut chars = literal.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            text.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => text.push('\n'),
+            Some('t') => text.push('\t'),
+            Some('0') => text.push('\0'),
+            Some(other) => text.push(other),
+            None => text.push('\\'),
+        }
+    }
+    text
+}
+
+/// Darwin signal numbers, as debugserver expects them.
+const SIGNALS: &[(&str, u8)] = &[
+    ("HUP", 1),
+    ("INT", 2),
+    ("QUIT", 3),
+    ("ILL", 4),
+    ("TRAP", 5),
+    ("ABRT", 6),
+    ("EMT", 7),
+    ("FPE", 8),
+    ("KILL", 9),
+    ("BUS", 10),
+    ("SEGV", 11),
+    ("SYS", 12),
+    ("PIPE", 13),
+    ("ALRM", 14),
+    ("TERM", 15),
+    ("URG", 16),
+    ("STOP", 17),
+    ("TSTP", 18),
+    ("CONT", 19),
+    ("CHLD", 20),
+    ("TTIN", 21),
+    ("TTOU", 22),
+    ("IO", 23),
+    ("XCPU", 24),
+    ("XFSZ", 25),
+    ("VTALRM", 26),
+    ("PROF", 27),
+    ("WINCH", 28),
+    ("INFO", 29),
+    ("USR1", 30),
+    ("USR2", 31),
+];
+
+/// `SIGSEGV` for 11, and so on.
+pub fn signal_name(number: u8) -> Option<String> {
+    SIGNALS
+        .iter()
+        .find(|(_, known)| *known == number)
+        .map(|(name, _)| format!("SIG{name}"))
+}
+
+/// Parses `SIGUSR1`, `usr1` or `30` into a Darwin signal number.
+pub fn parse_signal(text: &str) -> Option<u8> {
+    let text = text.trim();
+    if let Ok(number) = text.parse::<u8>() {
+        return (1..=31).contains(&number).then_some(number);
+    }
+    let upper = text.to_ascii_uppercase();
+    let name = upper.strip_prefix("SIG").unwrap_or(&upper);
+    SIGNALS
+        .iter()
+        .find(|(known, _)| *known == name)
+        .map(|(_, number)| *number)
+}
+
+/// Whether `root` holds an `.xcodeproj`, `.xcworkspace` or `Package.swift`.
+fn is_project_root(root: &Path) -> bool {
+    let Ok(entries) = fs::read_dir(root) else {
+        return false;
+    };
+    entries.flatten().any(|entry| {
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        name == "Package.swift" || name.ends_with(".xcodeproj") || name.ends_with(".xcworkspace")
+    })
+}
+
+/// Maps each build-machine prefix in `files` to `root` when the rest of the
+/// path exists under `root`. At least two trailing components must match so a
+/// lone file name (or an SDK header) cannot produce a bogus mapping; the
+/// longest matching tail wins. The most common prefixes come first.
+fn derive_source_maps(files: &[String], root: &Path) -> Vec<(String, String)> {
+    let to = root.to_string_lossy().trim_end_matches('/').to_string();
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for file in files.iter().filter(|file| file.starts_with('/')) {
+        let components: Vec<&str> = file.split('/').collect();
+        let found = (1..components.len().saturating_sub(1))
+            .find(|&split| root.join(components[split..].join("/")).is_file());
+        if let Some(split) = found {
+            let from = components[..split].join("/");
+            if !from.is_empty() && from != to {
+                *counts.entry(from).or_default() += 1;
+            }
+        }
+    }
+    let mut maps: Vec<_> = counts.into_iter().collect();
+    maps.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+    maps.into_iter()
+        .map(|(from, _)| (from, to.clone()))
+        .collect()
+}
+
+pub fn init_backend() -> io::Result<Backend> {
+    if let Ok(raw) = env::var(CONFIG_ENV_VAR) {
+        if let Some(program) = parse_program_from_config(&raw)? {
+            return backend_from_program(&program, None, None);
+        }
+    }
+    let exe = env::current_exe()?;
+    backend_from_program(&exe, None, None)
+}
+
+/// A backend for `program` (its `arch` slice, if it is universal), with
+/// debug information from `dsym` when given and from a discovered dSYM
+/// otherwise.
+pub fn backend_from_program(
+    program: &Path,
+    dsym: Option<&Path>,
+    arch: Option<&str>,
+) -> io::Result<Backend> {
+    Backend::new_from_app(program, dsym, arch).map_err(io::Error::other)
+}
+
+pub fn parse_program_from_config(raw: &str) -> io::Result<Option<PathBuf>> {
+    let value: Value =
+        serde_json::from_str(raw).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    Ok(value
+        .get("program")
+        .and_then(Value::as_str)
+        .map(PathBuf::from))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        mock::MockFixture,
+        symbols::{Image, SymbolContext},
+    };
+
+    #[test]
+    fn variable_trees_follow_paths_and_stop_at_limits() {
+        let var = |name: &str, value: &str, reference: i64| Variable {
+            name: name.into(),
+            ty: "T".into(),
+            value: value.into(),
+            variables_reference: reference,
+        };
+        // 10: user { name, address -> 11, friend -> 10 }; 11: address { city }
+        let mut fetch = |reference: i64| match reference {
+            10 => vec![
+                var("name", "\"Ann\"", 0),
+                var("address", "Address", 11),
+                var("friend", "User", 10),
+            ],
+            11 => vec![var("city", "\"Oslo\"", 0)],
+            12 => vec![var("[0]", "User", 10)],
+            _ => Vec::new(),
+        };
+        let locals = vec![var("user", "User", 10), var("users", "[User]", 12)];
+
+        let city = resolve_variable_path(locals.clone(), "users[0].address.city", fetch).unwrap();
+        assert_eq!(city.value, "\"Oslo\"");
+        let err = resolve_variable_path(locals.clone(), "user.age", fetch).unwrap_err();
+        assert!(
+            err.to_string().contains("`user` has no child `age`"),
+            "{err}"
+        );
+        assert!(resolve_variable_path(locals.clone(), "nobody", fetch).is_err());
+
+        let mut budget = MAX_DUMP_NODES;
+        let tree = build_variable_tree(
+            locals[0].clone(),
+            1,
+            &mut fetch,
+            &mut budget,
+            &mut HashSet::new(),
+        );
+        assert_eq!(tree.children.len(), 3);
+        assert_eq!(tree.children[1].unexpanded, Some(11));
+        assert!(tree.children[1].children.is_empty());
+
+        let tree = build_variable_tree(
+            locals[0].clone(),
+            5,
+            &mut fetch,
+            &mut budget,
+            &mut HashSet::new(),
+        );
+        assert_eq!(tree.children[1].children[0].name, "city");
+        // The self-reference is not expanded again.
+        assert_eq!(tree.children[2].unexpanded, Some(10));
+
+        let mut budget = 2;
+        let tree = build_variable_tree(
+            locals[0].clone(),
+            5,
+            &mut fetch,
+            &mut budget,
+            &mut HashSet::new(),
+        );
+        assert_eq!(tree.children.len(), 2);
+        assert_eq!(tree.unexpanded, Some(10));
+    }
+
+    #[test]
+    fn language_is_inferred_from_source_extension() {
+        assert_eq!(
+            Language::for_source("/src/View.swift"),
+            Some(Language::Swift)
+        );
+        assert_eq!(Language::for_source("AppDelegate.m"), Some(Language::ObjC));
+        assert_eq!(
+            Language::for_source("Bridge.mm"),
+            Some(Language::ObjCPlusPlus)
+        );
+        assert_eq!(Language::for_source("util.c"), Some(Language::C));
+        assert_eq!(Language::for_source("<unknown>"), None);
+    }
+
+    #[test]
+    fn source_maps_are_derived_from_dwarf_paths() {
+        let root = env::temp_dir().join(format!("swiftscope-root-{}", std::process::id()));
+        fs::create_dir_all(root.join("Sources/App")).unwrap();
+        fs::write(root.join("Sources/App/Foo.swift"), "").unwrap();
+        fs::write(root.join("Sources/App/Bar.swift"), "").unwrap();
+        fs::write(root.join("main.swift"), "").unwrap();
+        assert!(!is_project_root(&root));
+        fs::write(root.join("Package.swift"), "").unwrap();
+        assert!(is_project_root(&root));
+
+        let files = [
+            "/ci/work/App/Sources/App/Foo.swift",
+            "/ci/work/App/Sources/App/Bar.swift",
+            "/tmp/other/Sources/App/Foo.swift",
+            "/ci/work/App/main.swift",
+            "/SDK/usr/include/stdio.h",
+            "Foo.swift",
+        ]
+        .map(String::from);
+        let maps = derive_source_maps(&files, &root);
+        fs::remove_dir_all(&root).ok();
+        let to = root.display().to_string();
+        assert_eq!(
+            maps,
+            vec![
+                ("/ci/work/App".to_string(), to.clone()),
+                ("/tmp/other".to_string(), to),
+            ]
+        );
+    }
+
+    #[test]
+    fn log_messages_interpolate_expressions() {
+        let exe = env::current_exe().unwrap();
+        let image = Image {
+            name: "test".into(),
+            path: exe.clone(),
+            uuid: None,
+            dsym: None,
+            slice: None,
+            vmaddr_text: 0,
+            slide: 0,
+            dwarf: addr2line::Loader::new(&exe).unwrap(),
+        };
+        let mut session =
+            DebugSession::new(Backend::new_for_testing(SymbolContext::for_testing(image)));
+        let parts = parse_log_template("count is {counter}, {{literal}}").unwrap();
+        assert_eq!(
+            session.render_log_message(&parts),
+            "count is 123, {literal}"
+        );
+        let parts = [
+            LogPart::Text("[".into()),
+            LogPart::Expression("counter +".into()),
+            LogPart::Text("]".into()),
+        ];
+        assert_eq!(
+            session.render_log_message(&parts),
+            "[<expression `counter +` is not supported>]"
+        );
+    }
+
+    #[test]
+    fn snapshots_diff_locals_watches_and_memory() {
+        let exe = env::current_exe().unwrap();
+        let image = Image {
+            name: "test".into(),
+            path: exe.clone(),
+            uuid: None,
+            dsym: None,
+            slice: None,
+            vmaddr_text: 0,
+            slide: 0,
+            dwarf: addr2line::Loader::new(&exe).unwrap(),
+        };
+        let mut session =
+            DebugSession::new(Backend::new_for_testing(SymbolContext::for_testing(image)));
+        session.add_watch_expression("counter").unwrap();
+        let first = session.snapshot(None, None, &[(0x1000, 4)]).unwrap();
+        assert_eq!(first.id, "snapshot-1");
+        assert_eq!(first.locals["counter"], "123");
+        assert_eq!(first.watches["counter"], "123");
+        assert!(first.memory[0].bytes.is_none() && first.memory[0].error.is_some());
+
+        let unchanged = session.diff_snapshot("snapshot-1", None).unwrap();
+        assert_eq!(unchanged.to, "current");
+        assert!(!unchanged.changed, "{unchanged:?}");
+        assert!(session.diff_snapshot("missing", None).is_err());
+
+        let mut before = first.clone();
+        before.locals.insert("gone".into(), "1".into());
+        before.locals.insert("counter".into(), "7".into());
+        before.memory[0].bytes = Some("00112233".into());
+        let mut after = first;
+        after.id = "after".into();
+        after.locals.insert("fresh".into(), "2".into());
+        after.memory[0].bytes = Some("00aabb33".into());
+        let diff = diff_snapshots(&before, &after);
+        assert!(diff.changed);
+        assert_eq!(diff.locals.removed["gone"], "1");
+        assert_eq!(diff.locals.added["fresh"], "2");
+        assert_eq!(
+            diff.locals.changed,
+            [ChangedValue {
+                path: "counter".into(),
+                before: "7".into(),
+                after: "123".into(),
+            }]
+        );
+        assert!(diff.watches.is_empty());
+        assert_eq!(
+            diff.memory[0].changes,
+            [ChangedValue {
+                path: "0x1001".into(),
+                before: "1122".into(),
+                after: "aabb".into(),
+            }]
+        );
+    }
+
+    #[test]
+    fn trace_function_needs_a_known_symbol_and_debugserver() {
+        let exe = env::current_exe().unwrap();
+        let image = Image {
+            name: "test".into(),
+            path: exe.clone(),
+            uuid: None,
+            dsym: None,
+            slice: None,
+            vmaddr_text: 0,
+            slide: 0,
+            dwarf: addr2line::Loader::new(&exe).unwrap(),
+        };
+        let mut session =
+            DebugSession::new(Backend::new_for_testing(SymbolContext::for_testing(image)));
+        let err = session
+            .trace_function("no_such_function_to_trace", true)
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::UnknownSymbol);
+
+        let err = session
+            .trace_function("dwarf_children_test_function", true)
+            .unwrap_err();
+        assert!(matches!(err, DebugSessionError::Backend(_)), "{err:?}");
+        assert!(
+            session.traced_functions.is_empty(),
+            "an entry that could not be planted is not traced"
+        );
+        assert!(session
+            .trace_function("dwarf_children_test_function", false)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn mock_backend_plays_its_fixture_back() {
+        let exe = env::current_exe().unwrap();
+        let image = Image {
+            name: "test".into(),
+            path: exe.clone(),
+            uuid: None,
+            dsym: None,
+            slice: None,
+            vmaddr_text: 0,
+            slide: 0,
+            dwarf: addr2line::Loader::new(&exe).unwrap(),
+        };
+        let fixture: MockFixture = serde_json::from_value(json!({
+            "threads": [{
+                "id": 1,
+                "name": "main",
+                "queue": "com.apple.main-thread",
+                "frames": [
+                    {
+                        "function": "ContentView.increment()",
+                        "file": "/src/App/ContentView.swift",
+                        "line": 12,
+                        "locals": [{ "name": "count", "type": "Int", "value": "1" }]
+                    },
+                    { "function": "main" }
+                ]
+            }],
+            "stops": [
+                { "reason": "breakpoint" },
+                {
+                    "reason": "step",
+                    "threads": [{
+                        "id": 1,
+                        "name": "main",
+                        "frames": [{
+                            "function": "ContentView.increment()",
+                            "file": "/src/App/ContentView.swift",
+                            "line": 13,
+                            "locals": [{ "name": "count", "type": "Int", "value": "2" }]
+                        }]
+                    }]
+                }
+            ],
+            "exit_code": 3
+        }))
+        .unwrap();
+        let mut session = DebugSession::new(Backend::new_mock(
+            SymbolContext::for_testing(image),
+            fixture,
+        ));
+        assert!(!session.is_connected());
+        session.connect_debugserver(0).unwrap();
+
+        let threads = session.threads();
+        assert_eq!(threads.len(), 1);
+        assert_eq!(threads[0].queue.as_deref(), Some("com.apple.main-thread"));
+        let frames = session.stacktrace();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].file, "/src/App/ContentView.swift");
+        assert_eq!(frames[0].line, 12);
+        assert_eq!(session.locals()[0].value, "1");
+
+        let stop = session.continue_execution().unwrap().unwrap();
+        assert_eq!(stop.reason, "breakpoint");
+        assert_eq!(stop.description, "Breakpoint hit");
+        let stop = session.next().unwrap().unwrap();
+        assert_eq!(stop.reason, "step");
+        assert_eq!(session.stacktrace()[0].line, 13);
+        assert_eq!(session.locals()[0].value, "2");
+        assert!(session.read_memory(0x1000, 8).is_err());
+
+        let err = session.continue_execution().unwrap_err();
+        assert!(err.to_string().contains("exited with status 3"), "{err}");
+        assert!(!session.is_connected());
+
+        session.connect_debugserver(0).unwrap();
+        assert_eq!(session.stacktrace()[0].line, 12, "attaching starts over");
+    }
+
+    #[test]
+    fn continue_until_changed_checks_the_expression_before_resuming() {
+        let exe = env::current_exe().unwrap();
+        let image = Image {
+            name: "test".into(),
+            path: exe.clone(),
+            uuid: None,
+            dsym: None,
+            slice: None,
+            vmaddr_text: 0,
+            slide: 0,
+            dwarf: addr2line::Loader::new(&exe).unwrap(),
+        };
+        let mut session =
+            DebugSession::new(Backend::new_for_testing(SymbolContext::for_testing(image)));
+        let err = session
+            .continue_until_changed("missing", Advance::Continue, None)
+            .unwrap_err();
+        assert!(
+            !matches!(err, DebugSessionError::Backend(_)),
+            "an unknown expression fails before the target resumes: {err:?}"
+        );
+        let err = session
+            .continue_until_changed("counter", Advance::Next, Some(0))
+            .unwrap_err();
+        assert!(
+            matches!(err, DebugSessionError::Backend(_)),
+            "a known expression resumes at least once: {err:?}"
+        );
+    }
+
+    #[test]
+    fn watch_deltas_compare_with_the_previous_stop() {
+        let exe = env::current_exe().unwrap();
+        let image = Image {
+            name: "test".into(),
+            path: exe.clone(),
+            uuid: None,
+            dsym: None,
+            slice: None,
+            vmaddr_text: 0,
+            slide: 0,
+            dwarf: addr2line::Loader::new(&exe).unwrap(),
+        };
+        let mut session =
+            DebugSession::new(Backend::new_for_testing(SymbolContext::for_testing(image)));
+        session.add_watch_expression("counter").unwrap();
+        session.watch_expressions.push("missing".into());
+
+        let deltas = session.watch_deltas();
+        assert_eq!(
+            deltas[0],
+            WatchDelta {
+                expression: "counter".into(),
+                value: Some("123".into()),
+                error: None,
+                changed: false,
+                previous: Some("123".into()),
+            }
+        );
+        assert!(deltas[1].error.is_some() && !deltas[1].changed);
+
+        session
+            .watch_values
+            .insert("counter".into(), Some("7".into()));
+        let deltas = session.watch_deltas();
+        assert!(deltas[0].changed);
+        assert_eq!(deltas[0].previous.as_deref(), Some("7"));
+        assert!(
+            !deltas[1].changed,
+            "a watch that keeps failing is unchanged"
+        );
+    }
+
+    #[test]
+    fn value_references_are_dropped_when_the_target_resumes() {
+        let exe = env::current_exe().unwrap();
+        let image = Image {
+            name: "test".into(),
+            path: exe.clone(),
+            uuid: None,
+            dsym: None,
+            slice: None,
+            vmaddr_text: 0,
+            slide: 0,
+            dwarf: addr2line::Loader::new(&exe).unwrap(),
+        };
+        let mut session =
+            DebugSession::new(Backend::new_for_testing(SymbolContext::for_testing(image)));
+        let value = |name: &str, children| TargetValue {
+            name: name.into(),
+            ty: "Point".into(),
+            value: "{...}".into(),
+            children,
+        };
+        let handle = ValueHandle {
+            address: Some(0x1000),
+            bytes: vec![0; 8],
+            type_offset: 0x40,
+        };
+
+        let first = session.variable_for(value("a", Some(handle.clone())));
+        let second = session.variable_for(value("b", Some(handle.clone())));
+        let leaf = session.variable_for(value("c", None));
+        assert_eq!(first.variables_reference, FIRST_VALUE_REFERENCE);
+        assert_eq!(second.variables_reference, FIRST_VALUE_REFERENCE + 1);
+        assert_eq!(leaf.variables_reference, 0);
+        assert_eq!(session.value_refs.len(), 2);
+
+        session.resuming(1);
+        assert!(session.value_refs.is_empty());
+        let after = session.variable_for(value("a", Some(handle)));
+        assert_eq!(after.variables_reference, FIRST_VALUE_REFERENCE + 2);
+    }
+
+    #[test]
+    fn replacing_file_breakpoints_drops_the_old_lines() {
+        let exe = env::current_exe().unwrap();
+        let image = Image {
+            name: "test".into(),
+            path: exe.clone(),
+            uuid: None,
+            dsym: None,
+            slice: None,
+            vmaddr_text: 0,
+            slide: 0,
+            dwarf: addr2line::Loader::new(&exe).unwrap(),
+        };
+        let mut session =
+            DebugSession::new(Backend::new_for_testing(SymbolContext::for_testing(image)));
+        let file = "src/debug_session.rs";
+        session.set_breakpoint(file, 10, None).unwrap();
+        let lines = |session: &DebugSession| -> Vec<u32> {
+            session
+                .export_breakpoints()
+                .iter()
+                .map(|spec| spec.line)
+                .collect()
+        };
+
+        let set = session.replace_file_breakpoints(file, &[30, 20]).unwrap();
+        assert_eq!(
+            set.iter().map(|bp| bp.requested_line).collect::<Vec<_>>(),
+            [30, 20]
+        );
+        // Both are `use` lines, so they move to the first line with code.
+        assert!(set.iter().all(|bp| bp.line > bp.requested_line));
+        let actual: BTreeSet<u32> = set.iter().map(|bp| bp.line).collect();
+        assert_eq!(lines(&session), actual.into_iter().collect::<Vec<_>>());
+        assert!(session
+            .replace_file_breakpoints(file, &[])
+            .unwrap()
+            .is_empty());
+        assert!(lines(&session).is_empty());
+        assert!(session.pending_breakpoints.is_empty());
+    }
+
+    #[test]
+    fn language_strips_implicit_receiver() {
+        let exe = env::current_exe().unwrap();
+        let image = Image {
+            name: "test".into(),
+            path: exe.clone(),
+            uuid: None,
+            dsym: None,
+            slice: None,
+            vmaddr_text: 0,
+            slide: 0,
+            dwarf: addr2line::Loader::new(&exe).unwrap(),
+        };
+        let mut session =
+            DebugSession::new(Backend::new_for_testing(SymbolContext::for_testing(image)));
+        for (language, expression) in [
+            (Language::Swift, "self.counter"),
+            (Language::ObjC, "self->counter"),
+            (Language::ObjCPlusPlus, "this->counter"),
+        ] {
+            let value = session.evaluate_in(expression, Some(language)).unwrap();
+            assert_eq!(value.result, "123", "{expression}");
+        }
+        assert!(session
+            .evaluate_in("self.counter", Some(Language::C))
+            .is_err());
+    }
+
+    #[test]
+    fn log_templates_split_into_text_and_expressions() {
+        assert_eq!(
+            parse_log_template("count {{is}} {counter}!").unwrap(),
+            vec![
+                LogPart::Text("count {is} ".into()),
+                LogPart::Expression("counter".into()),
+                LogPart::Text("!".into()),
+            ]
+        );
+        let err = parse_log_template("count is {counter").unwrap_err();
+        assert!(err.contains("missing its closing"), "{err}");
+        assert!(parse_log_template("count is counter}").is_err());
+        assert!(parse_log_template("{a{b}}").is_err());
+        assert!(parse_log_template("{ }").is_err());
+    }
+
+    #[test]
+    fn parse_declaration_splits_convenience_assignments() {
+        assert_eq!(
+            parse_declaration("let $tmp = counter"),
+            Some(("$tmp", "counter"))
+        );
+        assert_eq!(parse_declaration("var $x=1"), Some(("$x", "1")));
+        assert_eq!(parse_declaration("counter"), None);
+        assert!(is_convenience_name("$tmp_1"));
+        assert!(!is_convenience_name("tmp"));
+        assert!(!is_convenience_name("$"));
+    }
+
+    #[test]
+    fn variable_keeps_backend_reference_for_drill_down() {
+        let variable = Variable::from_backend_value(json!({
+            "name": "user",
+            "value": "User",
+            "type": "User",
+            "variablesReference": 7
+        }));
+        assert_eq!(variable.variables_reference, 7);
+    }
+
+    #[test]
+    fn hex_dump_renders_offsets_and_ascii() {
+        let dump = hex_dump(0x1000, b"Hello, world!\x00\x01\x02xyz");
+        let lines: Vec<_> = dump.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("0x0000000000001000: 48 65 6c 6c"));
+        assert!(lines[0].ends_with("|Hello, world!...|"));
+        assert!(lines[1].starts_with("0x0000000000001010: 78 79 7a"));
+    }
+
+    #[test]
+    fn decode_bytes_handles_hex_and_base64() {
+        assert_eq!(
+            decode_bytes("de ad be ef", "hex").unwrap(),
+            vec![0xde, 0xad, 0xbe, 0xef]
+        );
+        assert_eq!(
+            decode_bytes("3q2+7w==", "base64").unwrap(),
+            vec![0xde, 0xad, 0xbe, 0xef]
+        );
+        assert!(decode_bytes("00", "octal").is_err());
+    }
+
+    #[test]
+    fn error_codes_come_from_known_messages() {
+        let backend = |message: &str| DebugSessionError::Backend(message.into());
+        assert_eq!(
+            backend("no gdb-remote connection; call connect_debugserver first").code(),
+            ErrorCode::NotConnected
+        );
+        assert_eq!(
+            backend("process exited with status 0").code(),
+            ErrorCode::ProcessExited
+        );
+        assert_eq!(backend("no thread 9").code(), ErrorCode::UnknownThread);
+        assert_eq!(backend("E08").code(), ErrorCode::Backend);
+        assert_eq!(backend("E08").hint(), None);
+        assert_eq!(
+            backend("protocol error: invalid packet checksum").code(),
+            ErrorCode::Protocol
+        );
+        // Only plain backend strings are classified by their message.
+        let invalid =
+            DebugSessionError::InvalidArgument("no variable `x` in the selected frame".into());
+        assert_eq!(invalid.code(), ErrorCode::InvalidArgument);
+        let unknown = DebugSessionError::coded(
+            ErrorCode::UnknownVariable,
+            "no variable `x` in the selected frame",
+        );
+        assert_eq!(unknown.code(), ErrorCode::UnknownVariable);
+        assert_eq!(unknown.hint().as_deref(), Some("list names with `locals`"));
+        assert_eq!(
+            serde_json::to_value(ErrorCode::NoCodeAtLine).unwrap(),
+            json!("NO_CODE_AT_LINE")
+        );
+        let coded = DebugSessionError::Coded {
+            code: ErrorCode::NoCodeAtLine,
+            message: "no code at App.swift:42".into(),
+            hint: Some("lines with code near 42: 40, 45".into()),
+        };
+        assert_eq!(coded.to_string(), "no code at App.swift:42");
+        assert_eq!(
+            coded.hint().as_deref(),
+            Some("lines with code near 42: 40, 45")
+        );
+    }
+
+    #[test]
+    fn debug_events_are_tagged_by_kind() {
+        let hit = DebugEvent::BreakpointHit {
+            thread_id: 3,
+            file: Some("/src/App.swift".into()),
+            line: Some(12),
+        };
+        assert_eq!(
+            serde_json::to_value(hit).unwrap(),
+            json!({ "event": "breakpoint_hit", "thread_id": 3, "file": "/src/App.swift", "line": 12 })
+        );
+        let exited = DebugEvent::ProcessExited {
+            status: ExitStatus {
+                exit_code: Some(0),
+                signal: None,
+            },
+        };
+        assert_eq!(
+            serde_json::to_value(exited).unwrap(),
+            json!({ "event": "process_exited", "exit_code": 0 })
+        );
+    }
+
+    #[test]
+    fn parse_address_accepts_hex_and_decimal() {
+        assert_eq!(parse_address("0x10"), Some(16));
+        assert_eq!(parse_address("42"), Some(42));
+        assert_eq!(parse_address("zz"), None);
+        assert_eq!(
+            object_pointer("<MyApp.Cart: 0x600000c04000>"),
+            Some(0x6000_00c0_4000)
+        );
+        assert_eq!(object_pointer("0x10"), Some(16));
+        assert_eq!(object_pointer("\"hello\""), None);
+    }
+
+    #[test]
+    fn parse_call_splits_top_level_arguments() {
+        assert_eq!(
+            parse_call(r#"printf("%d, %d\n", count, 0x10)"#),
+            Some(("printf", vec![r#""%d, %d\n""#, "count", "0x10"]))
+        );
+        assert_eq!(parse_call("getpid()"), Some(("getpid", vec![])));
+        assert_eq!(
+            parse_call("f(g(1, 2), 3)"),
+            Some(("f", vec!["g(1, 2)", "3"]))
+        );
+        assert_eq!(parse_call("counter"), None);
+        assert_eq!(parse_call("self.reload()"), None);
+        assert_eq!(parse_call(r#"f("unterminated)"#), None);
+        assert_eq!(scalar_argument("-1"), Some(u64::MAX));
+        assert_eq!(scalar_argument("nil"), Some(0));
+        assert_eq!(unescape_string_literal(r#"a\"b\n"#), "a\"b\n");
+    }
+
+    #[test]
+    fn stack_diff_splits_at_the_common_outer_frames() {
+        let frame = |function: &str, line: u32| Frame {
+            frame_index: 0,
+            function: function.into(),
+            mangled_name: None,
+            file: "/src/App.swift".into(),
+            line,
+            inlined: false,
+        };
+        let previous = [frame("step", 10), frame("loop", 20), frame("main", 5)];
+        let current = [frame("step", 11), frame("loop", 20), frame("main", 5)];
+        let diff = diff_stacks(&previous, &current);
+        assert_eq!(diff.common_frames, 2);
+        assert_eq!(diff.previous_only.len(), 1);
+        assert_eq!(diff.current_only[0].line, 11);
+        assert!(diff.same_path);
+
+        let deeper = [frame("helper", 3), frame("loop", 21), frame("main", 5)];
+        let diff = diff_stacks(&current, &deeper);
+        assert_eq!(diff.common_frames, 1);
+        assert_eq!(diff.current_only[0].function, "helper");
+        assert!(!diff.same_path);
+    }
+
+    #[test]
+    fn breakpoints_move_to_the_nearest_line_with_code() {
+        let lines = [10, 12, 20];
+        assert_eq!(nearest_code_line(&lines, 12), Some(12));
+        assert_eq!(nearest_code_line(&lines, 13), Some(20));
+        assert_eq!(nearest_code_line(&lines, 1), Some(10));
+        assert_eq!(nearest_code_line(&lines, 25), Some(20));
+        assert_eq!(nearest_code_line(&[], 7), None);
+    }
+
+    #[test]
+    fn parse_signal_accepts_names_and_numbers() {
+        assert_eq!(parse_signal("SIGUSR1"), Some(30));
+        assert_eq!(parse_signal("term"), Some(15));
+        assert_eq!(parse_signal("31"), Some(31));
+        assert_eq!(parse_signal("0"), None);
+        assert_eq!(parse_signal("SIGNOPE"), None);
+        assert_eq!(signal_name(11).as_deref(), Some("SIGSEGV"));
+        assert_eq!(signal_name(0), None);
+    }
+
+    #[test]
+    fn stop_exceptions_are_decoded_for_crashes_only() {
+        let stop = |reason: &'static str, exception| BackendStopEvent {
+            reason,
+            description: String::new(),
+            thread_id: 1,
+            return_value: None,
+            signal: 11,
+            exception,
+        };
+        let bad_access = crate::gdb_remote::MachException {
+            exception_type: 1,
+            data: vec![2, 0x1_0400_0000],
+        };
+        let decoded = SessionStop::from(stop("stopped", Some(bad_access.clone()))).exception;
+        assert_eq!(
+            decoded,
+            Some(StopException {
+                exception: Some("EXC_BAD_ACCESS".into()),
+                exception_type: Some(1),
+                code: Some(2),
+                subcode: None,
+                address: Some("0x104000000".into()),
+                signal: 11,
+                signal_name: Some("SIGSEGV".into()),
+            })
+        );
+        let signal = SessionStop::from(stop("signal", None)).exception.unwrap();
+        assert_eq!(signal.exception, None);
+        assert_eq!(signal.signal_name.as_deref(), Some("SIGSEGV"));
+        assert!(SessionStop::from(stop("breakpoint", Some(bad_access)))
+            .exception
+            .is_none());
+        assert!(SessionStop::from(stop("stopped", None)).exception.is_none());
+    }
+
+    #[test]
+    fn summarize_variables_clips_long_values() {
+        let variables = vec![
+            Variable {
+                name: "count".into(),
+                ty: "Int".into(),
+                value: "5".into(),
+                variables_reference: 0,
+            },
+            Variable {
+                name: "title".into(),
+                ty: "String".into(),
+                value: "x".repeat(40),
+                variables_reference: 0,
+            },
+        ];
+        let summary = summarize_variables(&variables);
+        assert_eq!(summary, format!("count=5, title={}…", "x".repeat(32)));
+    }
+
+    #[test]
+    fn reported_api_finds_the_offending_method() {
+        let message = "Main Thread Checker: UI API called on a background thread: \
+                       -[UIView setNeedsLayout]\nPID: 412, TID: 9011, Thread name: (none)";
+        assert_eq!(
+            reported_api(message).as_deref(),
+            Some("-[UIView setNeedsLayout]")
+        );
+        assert_eq!(
+            reported_api("UI API called on a background thread: +[UIColor redColor]").as_deref(),
+            Some("+[UIColor redColor]")
+        );
+        assert_eq!(reported_api("UI API called on a background thread"), None);
+    }
 }
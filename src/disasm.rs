@@ -0,0 +1,792 @@
+//! Small A64 decoder backing the `disassemble` command.
+//!
+//! It covers the instruction classes that dominate compiled Swift and
+//! Objective-C: branches, loads/stores, integer arithmetic, logical and
+//! bitfield operations, moves, and the pointer-authentication hints used in
+//! arm64e prologues. Anything else is rendered as `.long 0x...` so listings
+//! stay aligned with memory.
+
+const CONDITIONS: [&str; 16] = [
+    "eq", "ne", "hs", "lo", "mi", "pl", "vs", "vc", "hi", "ls", "ge", "lt", "gt", "le", "al", "nv",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Instruction {
+    pub address: u64,
+    pub word: u32,
+    pub mnemonic: String,
+    pub operands: String,
+    /// Absolute destination of PC-relative branches, literals and `adr`/`adrp`.
+    pub target: Option<u64>,
+}
+
+impl Instruction {
+    /// Renders the instruction as `mnemonic operands`, mnemonic padded like LLDB.
+    pub fn text(&self) -> String {
+        if self.operands.is_empty() {
+            self.mnemonic.clone()
+        } else {
+            format!("{:<7} {}", self.mnemonic, self.operands)
+        }
+    }
+}
+
+/// Decodes `bytes` (little-endian, as read from memory) starting at `address`.
+/// Trailing bytes that do not form a whole instruction are ignored.
+pub fn disassemble(address: u64, bytes: &[u8]) -> Vec<Instruction> {
+    bytes
+        .chunks_exact(4)
+        .enumerate()
+        .map(|(idx, chunk)| {
+            let word = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+            decode(word, address.wrapping_add(idx as u64 * 4))
+        })
+        .collect()
+}
+
+pub fn decode(word: u32, pc: u64) -> Instruction {
+    let (mnemonic, operands, target) =
+        decode_fields(word, pc).unwrap_or_else(|| (".long".into(), format!("0x{word:08x}"), None));
+    Instruction {
+        address: pc,
+        word,
+        mnemonic,
+        operands,
+        target,
+    }
+}
+
+/// What an instruction does to the frame record (`fp`, `lr` saved at `[fp]`)
+/// that arm64 functions keep, for finding a return address without unwind info.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameEffect {
+    /// `mov fp, sp` or `add fp, sp, #n`: the prologue has set the record up.
+    SetsFramePointer,
+    /// Loads `fp` from memory, as epilogues do with `ldp fp, lr, [sp], #n`.
+    RestoresFramePointer,
+    /// `ret`, `retaa` or `retab`.
+    Return,
+    /// Any other branch, direct or through a register.
+    Branch,
+    None,
+}
+
+pub fn frame_effect(word: u32) -> FrameEffect {
+    const FP: u32 = 29;
+    if word & 0xff80_03ff == 0x9100_03fd {
+        // ADD (immediate), 64-bit, Rd = fp, Rn = sp.
+        return FrameEffect::SetsFramePointer;
+    }
+    if word & 0xfc40_0000 == 0xa840_0000 && (word & 0x1f == FP || (word >> 10) & 0x1f == FP) {
+        // LDP/LDNP, 64-bit, with fp as either destination.
+        return FrameEffect::RestoresFramePointer;
+    }
+    if word & 0xfffffc1f == 0xd65f_0000 || matches!(word, 0xd65f0bff | 0xd65f0fff) {
+        return FrameEffect::Return;
+    }
+    let branch = word & 0x7c00_0000 == 0x1400_0000 // B, BL
+        || word & 0xff00_0010 == 0x5400_0000 // B.cond
+        || word & 0x7c00_0000 == 0x3400_0000 // CBZ, CBNZ, TBZ, TBNZ
+        || word & 0xfe00_0000 == 0xd600_0000; // BR, BLR and their authenticated forms
+    if branch {
+        FrameEffect::Branch
+    } else {
+        FrameEffect::None
+    }
+}
+
+type Decoded = (String, String, Option<u64>);
+
+fn plain(mnemonic: &str, operands: String) -> Option<Decoded> {
+    Some((mnemonic.to_string(), operands, None))
+}
+
+fn decode_fields(w: u32, pc: u64) -> Option<Decoded> {
+    match w {
+        0xd503201f => return plain("nop", String::new()),
+        0xd503233f => return plain("paciasp", String::new()),
+        0xd50323bf => return plain("autiasp", String::new()),
+        0xd503237f => return plain("pacibsp", String::new()),
+        0xd50323ff => return plain("autibsp", String::new()),
+        0xd65f0bff => return plain("retaa", String::new()),
+        0xd65f0fff => return plain("retab", String::new()),
+        _ => {}
+    }
+    let rd = w & 0x1f;
+    let rn = field(w, 5, 5);
+    let sf = w >> 31 == 1;
+
+    if w & 0xfffffc1f == 0xd65f0000 {
+        let operands = if rn == 30 {
+            String::new()
+        } else {
+            reg(rn, true)
+        };
+        return plain("ret", operands);
+    }
+    if w & 0xfffffc1f == 0xd61f0000 {
+        return plain("br", reg(rn, true));
+    }
+    if w & 0xfffffc1f == 0xd63f0000 {
+        return plain("blr", reg(rn, true));
+    }
+    if w & 0xffe0001f == 0xd4200000 {
+        return plain("brk", imm(field(w, 5, 16) as i64));
+    }
+    if w & 0xffe0001f == 0xd4000001 {
+        return plain("svc", imm(field(w, 5, 16) as i64));
+    }
+    if w & 0xfffff01f == 0xd503201f {
+        return plain("hint", format!("#{}", field(w, 5, 7)));
+    }
+    if w & 0x7c000000 == 0x14000000 {
+        let target = relative(pc, field(w, 0, 26), 26, 2);
+        let mnemonic = if sf { "bl" } else { "b" };
+        return Some((mnemonic.into(), format!("0x{target:x}"), Some(target)));
+    }
+    if w & 0xff000010 == 0x54000000 {
+        let target = relative(pc, field(w, 5, 19), 19, 2);
+        let mnemonic = format!("b.{}", CONDITIONS[rd as usize & 0xf]);
+        return Some((mnemonic, format!("0x{target:x}"), Some(target)));
+    }
+    if w & 0x7e000000 == 0x34000000 {
+        let target = relative(pc, field(w, 5, 19), 19, 2);
+        let mnemonic = if field(w, 24, 1) == 1 { "cbnz" } else { "cbz" };
+        let operands = format!("{}, 0x{target:x}", reg(rd, sf));
+        return Some((mnemonic.into(), operands, Some(target)));
+    }
+    if w & 0x7e000000 == 0x36000000 {
+        let target = relative(pc, field(w, 5, 14), 14, 2);
+        let bit = (field(w, 31, 1) << 5) | field(w, 19, 5);
+        let mnemonic = if field(w, 24, 1) == 1 { "tbnz" } else { "tbz" };
+        let operands = format!("{}, #{bit}, 0x{target:x}", reg(rd, sf));
+        return Some((mnemonic.into(), operands, Some(target)));
+    }
+    if w & 0x1f000000 == 0x10000000 {
+        let offset = sign_extend(((field(w, 5, 19) << 2) | field(w, 29, 2)) as u64, 21);
+        let (mnemonic, target) = if sf {
+            ("adrp", (pc & !0xfff).wrapping_add_signed(offset << 12))
+        } else {
+            ("adr", pc.wrapping_add_signed(offset))
+        };
+        let operands = format!("{}, 0x{target:x}", reg(rd, true));
+        return Some((mnemonic.into(), operands, Some(target)));
+    }
+    if w & 0x1f800000 == 0x11000000 {
+        return add_sub_immediate(w, sf, rd, rn);
+    }
+    if w & 0x1f200000 == 0x0b000000 {
+        return add_sub_shifted(w, sf, rd, rn);
+    }
+    if w & 0x1f200000 == 0x0b200000 {
+        return add_sub_extended(w, sf, rd, rn);
+    }
+    if w & 0x1f800000 == 0x12000000 {
+        return logical_immediate(w, sf, rd, rn);
+    }
+    if w & 0x1f000000 == 0x0a000000 {
+        return logical_shifted(w, sf, rd, rn);
+    }
+    if w & 0x1f800000 == 0x12800000 {
+        return move_wide(w, sf, rd);
+    }
+    if w & 0x1f800000 == 0x13000000 {
+        return bitfield(w, sf, rd, rn);
+    }
+    if w & 0x3fe00000 == 0x1a800000 && field(w, 11, 1) == 0 {
+        return conditional_select(w, sf, rd, rn);
+    }
+    if w & 0x7f000000 == 0x1b000000 {
+        return multiply(w, sf, rd, rn);
+    }
+    if w & 0x5fe00000 == 0x1ac00000 {
+        let mnemonic = match field(w, 10, 6) {
+            0b000010 => "udiv",
+            0b000011 => "sdiv",
+            0b001000 => "lsl",
+            0b001001 => "lsr",
+            0b001010 => "asr",
+            0b001011 => "ror",
+            _ => return None,
+        };
+        let rm = field(w, 16, 5);
+        return plain(
+            mnemonic,
+            format!("{}, {}, {}", reg(rd, sf), reg(rn, sf), reg(rm, sf)),
+        );
+    }
+    if w & 0x3a000000 == 0x28000000 {
+        return load_store_pair(w, rd, rn);
+    }
+    if w & 0x3b000000 == 0x18000000 {
+        let target = relative(pc, field(w, 5, 19), 19, 2);
+        let (mnemonic, register) = match (field(w, 26, 1), field(w, 30, 2)) {
+            (0, 0) => ("ldr", reg(rd, false)),
+            (0, 1) => ("ldr", reg(rd, true)),
+            (0, 2) => ("ldrsw", reg(rd, true)),
+            (1, size @ 0..=2) => ("ldr", format!("{}{rd}", ["s", "d", "q"][size as usize])),
+            _ => return None,
+        };
+        return Some((
+            mnemonic.into(),
+            format!("{register}, 0x{target:x}"),
+            Some(target),
+        ));
+    }
+    if w & 0x3b000000 == 0x39000000 {
+        let (mnemonic, register, scale) = load_store_kind(w, rd)?;
+        let offset = (field(w, 10, 12) as i64) << scale;
+        return plain(mnemonic, format!("{register}, {}", address(rn, offset)));
+    }
+    if w & 0x3b200c00 == 0x38200800 {
+        let (mnemonic, register, scale) = load_store_kind(w, rd)?;
+        let rm = field(w, 16, 5);
+        let option = field(w, 13, 3);
+        let amount = if field(w, 12, 1) == 1 { scale } else { 0 };
+        let extend = match option {
+            0b010 => "uxtw",
+            0b011 => "lsl",
+            0b110 => "sxtw",
+            0b111 => "sxtx",
+            _ => return None,
+        };
+        let index = reg(rm, option & 1 == 1);
+        let index = match (extend, amount) {
+            ("lsl", 0) => index,
+            (_, 0) => format!("{index}, {extend}"),
+            _ => format!("{index}, {extend} #{amount}"),
+        };
+        return plain(
+            mnemonic,
+            format!("{register}, [{}, {index}]", reg_sp(rn, true)),
+        );
+    }
+    if w & 0x3b200000 == 0x38000000 {
+        let (mnemonic, register, _) = load_store_kind(w, rd)?;
+        let offset = sign_extend(field(w, 12, 9) as u64, 9);
+        let base = reg_sp(rn, true);
+        return match field(w, 10, 2) {
+            0b00 => plain(
+                &infix(mnemonic, 'u'),
+                format!("{register}, {}", address(rn, offset)),
+            ),
+            0b01 => plain(mnemonic, format!("{register}, [{base}], {}", imm(offset))),
+            0b10 => plain(
+                &infix(mnemonic, 't'),
+                format!("{register}, {}", address(rn, offset)),
+            ),
+            _ => plain(mnemonic, format!("{register}, [{base}, {}]!", imm(offset))),
+        };
+    }
+    None
+}
+
+fn add_sub_immediate(w: u32, sf: bool, rd: u32, rn: u32) -> Option<Decoded> {
+    let sub = field(w, 30, 1) == 1;
+    let set_flags = field(w, 29, 1) == 1;
+    let shifted = field(w, 22, 1) == 1;
+    let value = field(w, 10, 12) as i64;
+    let operand = if shifted {
+        format!("{}, lsl #12", imm(value))
+    } else {
+        imm(value)
+    };
+    if set_flags && rd == 31 {
+        let mnemonic = if sub { "cmp" } else { "cmn" };
+        return plain(mnemonic, format!("{}, {operand}", reg_sp(rn, sf)));
+    }
+    if !sub && !set_flags && value == 0 && !shifted && (rd == 31 || rn == 31) {
+        return plain("mov", format!("{}, {}", reg_sp(rd, sf), reg_sp(rn, sf)));
+    }
+    let mnemonic = ["add", "adds", "sub", "subs"][(sub as usize) * 2 + set_flags as usize];
+    let dest = if set_flags {
+        reg(rd, sf)
+    } else {
+        reg_sp(rd, sf)
+    };
+    plain(mnemonic, format!("{dest}, {}, {operand}", reg_sp(rn, sf)))
+}
+
+fn add_sub_shifted(w: u32, sf: bool, rd: u32, rn: u32) -> Option<Decoded> {
+    let sub = field(w, 30, 1) == 1;
+    let set_flags = field(w, 29, 1) == 1;
+    let shift = field(w, 22, 2);
+    if shift == 0b11 {
+        return None;
+    }
+    let rm = shifted_register(field(w, 16, 5), sf, shift, field(w, 10, 6));
+    if set_flags && rd == 31 {
+        let mnemonic = if sub { "cmp" } else { "cmn" };
+        return plain(mnemonic, format!("{}, {rm}", reg(rn, sf)));
+    }
+    if sub && rn == 31 {
+        let mnemonic = if set_flags { "negs" } else { "neg" };
+        return plain(mnemonic, format!("{}, {rm}", reg(rd, sf)));
+    }
+    let mnemonic = ["add", "adds", "sub", "subs"][(sub as usize) * 2 + set_flags as usize];
+    plain(mnemonic, format!("{}, {}, {rm}", reg(rd, sf), reg(rn, sf)))
+}
+
+fn add_sub_extended(w: u32, sf: bool, rd: u32, rn: u32) -> Option<Decoded> {
+    let sub = field(w, 30, 1) == 1;
+    let set_flags = field(w, 29, 1) == 1;
+    let option = field(w, 13, 3);
+    let amount = field(w, 10, 3);
+    if amount > 4 {
+        return None;
+    }
+    let rm = reg(field(w, 16, 5), option & 0b11 == 0b11);
+    let uses_sp = rd == 31 || rn == 31;
+    let extend = if uses_sp && option == if sf { 0b011 } else { 0b010 } {
+        "lsl"
+    } else {
+        [
+            "uxtb", "uxth", "uxtw", "uxtx", "sxtb", "sxth", "sxtw", "sxtx",
+        ][option as usize]
+    };
+    let operand = match (extend, amount) {
+        ("lsl", 0) => rm,
+        (_, 0) => format!("{rm}, {extend}"),
+        _ => format!("{rm}, {extend} #{amount}"),
+    };
+    if set_flags && rd == 31 {
+        let mnemonic = if sub { "cmp" } else { "cmn" };
+        return plain(mnemonic, format!("{}, {operand}", reg_sp(rn, sf)));
+    }
+    let mnemonic = ["add", "adds", "sub", "subs"][(sub as usize) * 2 + set_flags as usize];
+    let dest = if set_flags {
+        reg(rd, sf)
+    } else {
+        reg_sp(rd, sf)
+    };
+    plain(mnemonic, format!("{dest}, {}, {operand}", reg_sp(rn, sf)))
+}
+
+fn logical_immediate(w: u32, sf: bool, rd: u32, rn: u32) -> Option<Decoded> {
+    let value = decode_bit_mask(field(w, 22, 1), field(w, 10, 6), field(w, 16, 6), sf)?;
+    let operand = format!("#0x{value:x}");
+    let opc = field(w, 29, 2);
+    if opc == 0b01 && rn == 31 {
+        return plain("mov", format!("{}, {operand}", reg_sp(rd, sf)));
+    }
+    if opc == 0b11 && rd == 31 {
+        return plain("tst", format!("{}, {operand}", reg(rn, sf)));
+    }
+    let mnemonic = ["and", "orr", "eor", "ands"][opc as usize];
+    let dest = if opc == 0b11 {
+        reg(rd, sf)
+    } else {
+        reg_sp(rd, sf)
+    };
+    plain(mnemonic, format!("{dest}, {}, {operand}", reg(rn, sf)))
+}
+
+fn logical_shifted(w: u32, sf: bool, rd: u32, rn: u32) -> Option<Decoded> {
+    let opc = field(w, 29, 2);
+    let negate = field(w, 21, 1);
+    let shift = field(w, 22, 2);
+    let amount = field(w, 10, 6);
+    let rm_number = field(w, 16, 5);
+    let rm = shifted_register(rm_number, sf, shift, amount);
+    match (opc, negate) {
+        (0b01, 0) if rn == 31 && amount == 0 => {
+            return plain("mov", format!("{}, {}", reg(rd, sf), reg(rm_number, sf)));
+        }
+        (0b01, 1) if rn == 31 => return plain("mvn", format!("{}, {rm}", reg(rd, sf))),
+        (0b11, 0) if rd == 31 => return plain("tst", format!("{}, {rm}", reg(rn, sf))),
+        _ => {}
+    }
+    let mnemonic =
+        ["and", "bic", "orr", "orn", "eor", "eon", "ands", "bics"][(opc * 2 + negate) as usize];
+    plain(mnemonic, format!("{}, {}, {rm}", reg(rd, sf), reg(rn, sf)))
+}
+
+fn move_wide(w: u32, sf: bool, rd: u32) -> Option<Decoded> {
+    let hw = field(w, 21, 2);
+    if !sf && hw > 1 {
+        return None;
+    }
+    let shift = hw * 16;
+    let value = (field(w, 5, 16) as u64) << shift;
+    match field(w, 29, 2) {
+        0b00 => {
+            let inverted = if sf { !value } else { !value & 0xffff_ffff };
+            plain("mov", format!("{}, #0x{inverted:x}", reg(rd, sf)))
+        }
+        0b10 => plain("mov", format!("{}, #0x{value:x}", reg(rd, sf))),
+        0b11 => {
+            let operands = if shift == 0 {
+                format!("{}, #0x{:x}", reg(rd, sf), value)
+            } else {
+                format!("{}, #0x{:x}, lsl #{shift}", reg(rd, sf), value >> shift)
+            };
+            plain("movk", operands)
+        }
+        _ => None,
+    }
+}
+
+fn bitfield(w: u32, sf: bool, rd: u32, rn: u32) -> Option<Decoded> {
+    let immr = field(w, 16, 6);
+    let imms = field(w, 10, 6);
+    let width = if sf { 64 } else { 32 };
+    let (dst, src) = (reg(rd, sf), reg(rn, sf));
+    match field(w, 29, 2) {
+        0b00 => {
+            if imms == width - 1 {
+                plain("asr", format!("{dst}, {src}, #{immr}"))
+            } else if immr == 0 && matches!(imms, 7 | 15 | 31) {
+                let mnemonic = match imms {
+                    7 => "sxtb",
+                    15 => "sxth",
+                    _ => "sxtw",
+                };
+                plain(mnemonic, format!("{dst}, {}", reg(rn, false)))
+            } else if imms < immr {
+                let operands = format!("{dst}, {src}, #{}, #{}", width - immr, imms + 1);
+                plain("sbfiz", operands)
+            } else {
+                let operands = format!("{dst}, {src}, #{immr}, #{}", imms - immr + 1);
+                plain("sbfx", operands)
+            }
+        }
+        0b01 => {
+            if imms < immr {
+                let operands = format!("{dst}, {src}, #{}, #{}", width - immr, imms + 1);
+                plain("bfi", operands)
+            } else {
+                let operands = format!("{dst}, {src}, #{immr}, #{}", imms - immr + 1);
+                plain("bfxil", operands)
+            }
+        }
+        0b10 => {
+            if imms == width - 1 {
+                plain("lsr", format!("{dst}, {src}, #{immr}"))
+            } else if imms + 1 == immr {
+                plain("lsl", format!("{dst}, {src}, #{}", width - 1 - imms))
+            } else if !sf && immr == 0 && matches!(imms, 7 | 15) {
+                let mnemonic = if imms == 7 { "uxtb" } else { "uxth" };
+                plain(mnemonic, format!("{dst}, {src}"))
+            } else if imms < immr {
+                let operands = format!("{dst}, {src}, #{}, #{}", width - immr, imms + 1);
+                plain("ubfiz", operands)
+            } else {
+                let operands = format!("{dst}, {src}, #{immr}, #{}", imms - immr + 1);
+                plain("ubfx", operands)
+            }
+        }
+        _ => None,
+    }
+}
+
+fn conditional_select(w: u32, sf: bool, rd: u32, rn: u32) -> Option<Decoded> {
+    let rm = field(w, 16, 5);
+    let cond = field(w, 12, 4);
+    let invert = field(w, 30, 1);
+    let increment = field(w, 10, 1);
+    let inverted_cond = CONDITIONS[(cond ^ 1) as usize];
+    if cond < 0b1110 && rn == rm {
+        match (invert, increment) {
+            (0, 1) if rn == 31 => {
+                return plain("cset", format!("{}, {inverted_cond}", reg(rd, sf)))
+            }
+            (1, 0) if rn == 31 => {
+                return plain("csetm", format!("{}, {inverted_cond}", reg(rd, sf)));
+            }
+            (0, 1) => {
+                let operands = format!("{}, {}, {inverted_cond}", reg(rd, sf), reg(rn, sf));
+                return plain("cinc", operands);
+            }
+            _ => {}
+        }
+    }
+    let mnemonic = ["csel", "csinc", "csinv", "csneg"][(invert * 2 + increment) as usize];
+    plain(
+        mnemonic,
+        format!(
+            "{}, {}, {}, {}",
+            reg(rd, sf),
+            reg(rn, sf),
+            reg(rm, sf),
+            CONDITIONS[cond as usize]
+        ),
+    )
+}
+
+fn multiply(w: u32, sf: bool, rd: u32, rn: u32) -> Option<Decoded> {
+    let rm = field(w, 16, 5);
+    let ra = field(w, 10, 5);
+    let subtract = field(w, 15, 1) == 1;
+    match (field(w, 21, 3), sf) {
+        (0b000, _) => {
+            let (short, long) = if subtract {
+                ("mneg", "msub")
+            } else {
+                ("mul", "madd")
+            };
+            let operands = format!("{}, {}, {}", reg(rd, sf), reg(rn, sf), reg(rm, sf));
+            if ra == 31 {
+                plain(short, operands)
+            } else {
+                plain(long, format!("{operands}, {}", reg(ra, sf)))
+            }
+        }
+        (0b001 | 0b101, true) if !subtract && ra == 31 => {
+            let mnemonic = if field(w, 23, 1) == 1 {
+                "umull"
+            } else {
+                "smull"
+            };
+            let operands = format!("{}, {}, {}", reg(rd, true), reg(rn, false), reg(rm, false));
+            plain(mnemonic, operands)
+        }
+        (0b010 | 0b110, true) => {
+            let mnemonic = if field(w, 23, 1) == 1 {
+                "umulh"
+            } else {
+                "smulh"
+            };
+            let operands = format!("{}, {}, {}", reg(rd, true), reg(rn, true), reg(rm, true));
+            plain(mnemonic, operands)
+        }
+        _ => None,
+    }
+}
+
+fn load_store_pair(w: u32, rt: u32, rn: u32) -> Option<Decoded> {
+    let opc = field(w, 30, 2);
+    let vector = field(w, 26, 1) == 1;
+    let load = field(w, 22, 1) == 1;
+    let rt2 = field(w, 10, 5);
+    let (scale, bank) = match (vector, opc) {
+        (false, 0b00) => (2, "w"),
+        (false, 0b01) if load => (2, "x"),
+        (false, 0b10) => (3, "x"),
+        (true, 0b00) => (2, "s"),
+        (true, 0b01) => (3, "d"),
+        (true, 0b10) => (4, "q"),
+        _ => return None,
+    };
+    let name = |number: u32| match bank {
+        "w" | "x" => reg(number, bank == "x"),
+        _ => format!("{bank}{number}"),
+    };
+    let offset = sign_extend(field(w, 15, 7) as u64, 7) << scale;
+    let base = reg_sp(rn, true);
+    let mode = field(w, 23, 2);
+    let mnemonic = match (load, mode, !vector && opc == 0b01) {
+        (true, _, true) => "ldpsw",
+        (true, 0b00, _) => "ldnp",
+        (false, 0b00, _) => "stnp",
+        (true, ..) => "ldp",
+        (false, ..) => "stp",
+    };
+    let addressing = match mode {
+        0b01 => format!("[{base}], {}", imm(offset)),
+        0b11 => format!("[{base}, {}]!", imm(offset)),
+        _ => address(rn, offset),
+    };
+    plain(
+        mnemonic,
+        format!("{}, {}, {addressing}", name(rt), name(rt2)),
+    )
+}
+
+/// Mnemonic, transfer register and access-size scale for single-register loads/stores.
+fn load_store_kind(w: u32, rt: u32) -> Option<(&'static str, String, u32)> {
+    let size = field(w, 30, 2);
+    let opc = field(w, 22, 2);
+    if field(w, 26, 1) == 1 {
+        let mnemonic = if opc & 1 == 1 { "ldr" } else { "str" };
+        return match (opc >> 1, size) {
+            (0, _) => Some((
+                mnemonic,
+                format!("{}{rt}", ["b", "h", "s", "d"][size as usize]),
+                size,
+            )),
+            (1, 0) => Some((mnemonic, format!("q{rt}"), 4)),
+            _ => None,
+        };
+    }
+    let (mnemonic, wide) = match (opc, size) {
+        (0b00, 0) => ("strb", false),
+        (0b00, 1) => ("strh", false),
+        (0b00, 2) => ("str", false),
+        (0b00, _) => ("str", true),
+        (0b01, 0) => ("ldrb", false),
+        (0b01, 1) => ("ldrh", false),
+        (0b01, 2) => ("ldr", false),
+        (0b01, _) => ("ldr", true),
+        (0b10, 0) => ("ldrsb", true),
+        (0b10, 1) => ("ldrsh", true),
+        (0b10, 2) => ("ldrsw", true),
+        (0b11, 0) => ("ldrsb", false),
+        (0b11, 1) => ("ldrsh", false),
+        _ => return None,
+    };
+    Some((mnemonic, reg(rt, wide), size))
+}
+
+/// `ldr` → `ldur`/`ldtr`, `strb` → `sturb`/`sttrb`.
+fn infix(mnemonic: &str, letter: char) -> String {
+    format!("{}{letter}{}", &mnemonic[..2], &mnemonic[2..])
+}
+
+fn address(rn: u32, offset: i64) -> String {
+    if offset == 0 {
+        format!("[{}]", reg_sp(rn, true))
+    } else {
+        format!("[{}, {}]", reg_sp(rn, true), imm(offset))
+    }
+}
+
+fn shifted_register(rm: u32, sf: bool, shift: u32, amount: u32) -> String {
+    if amount == 0 {
+        reg(rm, sf)
+    } else {
+        let kind = ["lsl", "lsr", "asr", "ror"][shift as usize];
+        format!("{}, {kind} #{amount}", reg(rm, sf))
+    }
+}
+
+/// Register operand where number 31 is the zero register.
+fn reg(number: u32, wide: bool) -> String {
+    match (number, wide) {
+        (31, true) => "xzr".into(),
+        (31, false) => "wzr".into(),
+        (29, true) => "fp".into(),
+        (30, true) => "lr".into(),
+        (n, true) => format!("x{n}"),
+        (n, false) => format!("w{n}"),
+    }
+}
+
+/// Register operand where number 31 is the stack pointer.
+fn reg_sp(number: u32, wide: bool) -> String {
+    match (number, wide) {
+        (31, true) => "sp".into(),
+        (31, false) => "wsp".into(),
+        _ => reg(number, wide),
+    }
+}
+
+fn imm(value: i64) -> String {
+    if value < 0 {
+        format!("#-0x{:x}", value.unsigned_abs())
+    } else {
+        format!("#0x{value:x}")
+    }
+}
+
+fn field(word: u32, low: u32, width: u32) -> u32 {
+    (word >> low) & ((1 << width) - 1)
+}
+
+fn sign_extend(value: u64, bits: u32) -> i64 {
+    let shift = 64 - bits;
+    ((value << shift) as i64) >> shift
+}
+
+fn relative(pc: u64, imm: u32, bits: u32, scale: u32) -> u64 {
+    pc.wrapping_add_signed(sign_extend(imm as u64, bits) << scale)
+}
+
+/// `DecodeBitMasks` from the Arm ARM, returning the immediate of a logical instruction.
+fn decode_bit_mask(n: u32, imms: u32, immr: u32, sf: bool) -> Option<u64> {
+    if !sf && n == 1 {
+        return None;
+    }
+    let combined = (n << 6) | (!imms & 0x3f);
+    if combined < 2 {
+        return None;
+    }
+    let len = 31 - combined.leading_zeros();
+    let size = 1u32 << len;
+    let levels = size - 1;
+    let (s, r) = (imms & levels, immr & levels);
+    if s == levels {
+        return None;
+    }
+    let mask = if size == 64 {
+        u64::MAX
+    } else {
+        (1u64 << size) - 1
+    };
+    let ones = (1u64 << (s + 1)) - 1;
+    let element = if r == 0 {
+        ones
+    } else {
+        ((ones >> r) | (ones << (size - r))) & mask
+    };
+    let mut value = 0u64;
+    let mut shift = 0;
+    while shift < 64 {
+        value |= element << shift;
+        shift += size;
+    }
+    Some(if sf { value } else { value & 0xffff_ffff })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(word: u32, pc: u64) -> String {
+        decode(word, pc).text()
+    }
+
+    #[test]
+    fn decodes_common_prologue_and_epilogue() {
+        assert_eq!(text(0xa9bf7bfd, 0), "stp     fp, lr, [sp, #-0x10]!");
+        assert_eq!(text(0x910003fd, 0), "mov     fp, sp");
+        assert_eq!(text(0xd10083ff, 0), "sub     sp, sp, #0x20");
+        assert_eq!(text(0xf9400900, 0), "ldr     x0, [x8, #0x10]");
+        assert_eq!(text(0x52800540, 0), "mov     w0, #0x2a");
+        assert_eq!(text(0xaa0103e0, 0), "mov     x0, x1");
+        assert_eq!(text(0x7100041f, 0), "cmp     w0, #0x1");
+        assert_eq!(text(0x927cec00, 0), "and     x0, x0, #0xfffffffffffffff0");
+        assert_eq!(text(0xa8c17bfd, 0), "ldp     fp, lr, [sp], #0x10");
+        assert_eq!(text(0xd65f03c0, 0), "ret");
+        assert_eq!(text(0xd503237f, 0), "pacibsp");
+    }
+
+    #[test]
+    fn classifies_frame_record_setup_and_teardown() {
+        assert_eq!(frame_effect(0x910003fd), FrameEffect::SetsFramePointer);
+        assert_eq!(frame_effect(0x910043fd), FrameEffect::SetsFramePointer);
+        assert_eq!(frame_effect(0xa9bf7bfd), FrameEffect::None);
+        assert_eq!(frame_effect(0xa8c17bfd), FrameEffect::RestoresFramePointer);
+        assert_eq!(frame_effect(0xa9417bfd), FrameEffect::RestoresFramePointer);
+        assert_eq!(frame_effect(0xd65f03c0), FrameEffect::Return);
+        assert_eq!(frame_effect(0xd65f0fff), FrameEffect::Return);
+        assert_eq!(frame_effect(0x94000004), FrameEffect::Branch);
+        assert_eq!(frame_effect(0x54000041), FrameEffect::Branch);
+        assert_eq!(frame_effect(0xd63f0100), FrameEffect::Branch);
+        assert_eq!(frame_effect(0xd10083ff), FrameEffect::None);
+    }
+
+    #[test]
+    fn resolves_pc_relative_targets() {
+        let call = decode(0x94000004, 0x1000);
+        assert_eq!(call.text(), "bl      0x1010");
+        assert_eq!(call.target, Some(0x1010));
+        assert_eq!(decode(0x54000041, 0x2000).text(), "b.ne    0x2008");
+        assert_eq!(decode(0x17ffffff, 0x2000).target, Some(0x1ffc));
+        assert_eq!(
+            decode(0x90000008, 0x1_0000_3abc).target,
+            Some(0x1_0000_3000)
+        );
+    }
+
+    #[test]
+    fn unknown_words_render_as_data() {
+        let listing = disassemble(
+            0x4000,
+            &[0x00, 0x00, 0x00, 0x00, 0x1f, 0x20, 0x03, 0xd5, 0xff],
+        );
+        assert_eq!(listing.len(), 2);
+        assert_eq!(listing[0].text(), ".long   0x00000000");
+        assert_eq!(listing[1].address, 0x4004);
+        assert_eq!(listing[1].text(), "nop");
+    }
+}
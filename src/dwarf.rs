@@ -0,0 +1,1212 @@
+use std::{fs, path::Path};
+
+use anyhow::{Context as AnyhowContext, Result as AnyResult};
+use gimli::{
+    AttributeValue, DebugInfoOffset, DebuggingInformationEntry, Dwarf, EndianSlice,
+    EntriesTreeNode, Expression, Operation, RunTimeEndian, SectionId, Unit, UnitOffset,
+};
+use object::{Object, ObjectSection};
+
+use crate::memory::le_u64;
+
+type Reader<'a> = EndianSlice<'a, RunTimeEndian>;
+
+/// Coarse classification of a DWARF type, enough to render a raw register value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueKind {
+    Signed,
+    Unsigned,
+    Bool,
+    Float,
+    Pointer,
+    Aggregate,
+    Unknown,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValueType {
+    pub name: String,
+    pub byte_size: Option<u64>,
+    pub kind: ValueKind,
+}
+
+#[derive(Debug, Clone)]
+pub struct FunctionInfo {
+    pub low: u64,
+    pub high: u64,
+    pub name: Option<String>,
+    pub return_type: Option<ValueType>,
+}
+
+/// Where a variable lives in the stopped frame, in DWARF register numbers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VariableLocation {
+    /// In memory at the value of `register` plus `offset`.
+    Memory { register: u16, offset: i64 },
+    /// Held entirely in `register`.
+    Register(u16),
+}
+
+#[derive(Debug, Clone)]
+pub struct VariableInfo {
+    pub location: VariableLocation,
+    pub ty: ValueType,
+}
+
+/// A type by its offset in `.debug_info`, which stays valid across loads of
+/// the same binary.
+pub type TypeOffset = usize;
+
+/// A named value of some type: a local, or a member or element of one.
+#[derive(Debug, Clone)]
+pub struct ValueLayout {
+    pub name: String,
+    pub ty: ValueType,
+    /// The type as declared, for [`type_children`].
+    pub type_offset: TypeOffset,
+    /// Whether values of the type have children to expand.
+    pub expandable: bool,
+}
+
+/// A parameter or local in scope at a pc.
+#[derive(Debug, Clone)]
+pub struct FrameVariable {
+    pub layout: ValueLayout,
+    pub location: VariableLocation,
+}
+
+/// Where the children of a value of some type are.
+#[derive(Debug, Clone)]
+pub enum TypeChildren {
+    /// Members at byte offsets into the value, or into the object it points
+    /// at when `indirect` (a Swift class reference).
+    Members {
+        members: Vec<(u64, ValueLayout)>,
+        indirect: bool,
+    },
+    /// `count` values of `element`, `stride` bytes apart.
+    Elements {
+        element: ValueLayout,
+        stride: u64,
+        count: u64,
+    },
+    /// What a pointer or reference points at.
+    Pointee(ValueLayout),
+    None,
+}
+
+/// Address-sorted table of `DW_TAG_subprogram` ranges and their return types.
+pub struct FunctionIndex {
+    functions: Vec<FunctionInfo>,
+}
+
+impl FunctionIndex {
+    pub fn from_binary(path: &Path) -> AnyResult<Self> {
+        let data = fs::read(path).with_context(|| {
+            format!(
+                "failed to read Mach-O for function index: {}",
+                path.display()
+            )
+        })?;
+        let file =
+            object::File::parse(&*data).context("failed to parse Mach-O for function index")?;
+        let endian = if file.is_little_endian() {
+            RunTimeEndian::Little
+        } else {
+            RunTimeEndian::Big
+        };
+        let dwarf_sections = gimli::DwarfSections::load(|id| load_section_vec(&file, id))?;
+        let dwarf = dwarf_sections.borrow(|section| EndianSlice::new(section, endian));
+        Self::new_from_dwarf(&dwarf)
+    }
+
+    pub fn new_from_dwarf(dwarf: &Dwarf<Reader<'_>>) -> AnyResult<Self> {
+        let mut functions = Vec::new();
+        let mut units = dwarf.units();
+        while let Some(header) = units.next()? {
+            let unit = dwarf.unit(header)?;
+            let mut entries = unit.entries();
+            while let Some((_, entry)) = entries.next_dfs()? {
+                if entry.tag() != gimli::DW_TAG_subprogram {
+                    continue;
+                }
+                let mut ranges = match dwarf.die_ranges(&unit, entry) {
+                    Ok(ranges) => ranges,
+                    Err(_) => continue,
+                };
+                let name = function_name(dwarf, &unit, entry);
+                let return_type = function_return_type(dwarf, &unit, entry);
+                while let Some(range) = ranges.next()? {
+                    if range.end > range.begin {
+                        functions.push(FunctionInfo {
+                            low: range.begin,
+                            high: range.end,
+                            name: name.clone(),
+                            return_type: return_type.clone(),
+                        });
+                    }
+                }
+            }
+        }
+        functions.sort_by_key(|function| function.low);
+        Ok(Self { functions })
+    }
+
+    /// Returns the innermost function whose range contains `address`.
+    pub fn lookup(&self, address: u64) -> Option<&FunctionInfo> {
+        let end = self
+            .functions
+            .partition_point(|function| function.low <= address);
+        self.functions[..end]
+            .iter()
+            .rev()
+            .filter(|function| address < function.high)
+            .min_by_key(|function| function.high - function.low)
+    }
+}
+
+/// Locates `path` — a parameter or local, optionally followed by `.member`
+/// components — in the function containing the local address `pc`.
+///
+/// Only single-operation locations (`DW_OP_fbreg`, `DW_OP_bregN`, `DW_OP_regN`)
+/// are understood; anything more involved yields `None`.
+pub fn locate_variable(binary: &Path, pc: u64, path: &str) -> AnyResult<Option<VariableInfo>> {
+    with_dwarf(binary, |dwarf| locate_variable_in(dwarf, pc, path))
+}
+
+fn locate_variable_in(
+    dwarf: &Dwarf<Reader<'_>>,
+    pc: u64,
+    path: &str,
+) -> AnyResult<Option<VariableInfo>> {
+    let mut components = path.split('.').map(str::trim);
+    let Some(name) = components.next().filter(|name| !name.is_empty()) else {
+        return Ok(None);
+    };
+    let members: Vec<&str> = components.collect();
+
+    let mut units = dwarf.units();
+    while let Some(header) = units.next()? {
+        let unit = dwarf.unit(header)?;
+        let Some((function, frame_base)) = containing_function(dwarf, &unit, pc)? else {
+            continue;
+        };
+        let mut tree = unit.entries_tree(Some(function))?;
+        let Some(variable) = find_variable(dwarf, &unit, tree.root()?, pc, name)? else {
+            return Ok(None);
+        };
+        let entry = unit.entry(variable)?;
+        let Some(mut location) = variable_location(dwarf, &unit, &entry, pc, frame_base)? else {
+            return Ok(None);
+        };
+        let Some(AttributeValue::UnitRef(mut ty)) = variable_type(&unit, &entry) else {
+            return Ok(None);
+        };
+        for member in &members {
+            let Some((offset, member_ty)) = find_member(dwarf, &unit, ty, member)? else {
+                return Ok(None);
+            };
+            location = match location {
+                VariableLocation::Memory {
+                    register,
+                    offset: base,
+                } => VariableLocation::Memory {
+                    register,
+                    offset: base + offset as i64,
+                },
+                VariableLocation::Register(_) => return Ok(None),
+            };
+            ty = member_ty;
+        }
+        return Ok(resolve_type(dwarf, &unit, ty).map(|ty| VariableInfo { location, ty }));
+    }
+    Ok(None)
+}
+
+/// The parameters and locals with a location at the local address `pc`, in
+/// declaration order, from the scopes of its function that cover it.
+pub fn frame_variables(binary: &Path, pc: u64) -> AnyResult<Vec<FrameVariable>> {
+    with_dwarf(binary, |dwarf| {
+        let mut units = dwarf.units();
+        while let Some(header) = units.next()? {
+            let unit = dwarf.unit(header)?;
+            let Some((function, frame_base)) = containing_function(dwarf, &unit, pc)? else {
+                continue;
+            };
+            let mut tree = unit.entries_tree(Some(function))?;
+            let mut variables = Vec::new();
+            collect_variables(dwarf, &unit, tree.root()?, pc, frame_base, &mut variables)?;
+            return Ok(variables);
+        }
+        Ok(Vec::new())
+    })
+}
+
+/// The formal parameters of the function containing the local address `pc`,
+/// in declaration order, whether or not they have a location there.
+pub fn function_parameters(binary: &Path, pc: u64) -> AnyResult<Vec<ValueLayout>> {
+    with_dwarf(binary, |dwarf| {
+        let mut units = dwarf.units();
+        while let Some(header) = units.next()? {
+            let unit = dwarf.unit(header)?;
+            let Some((function, _)) = containing_function(dwarf, &unit, pc)? else {
+                continue;
+            };
+            let mut tree = unit.entries_tree(Some(function))?;
+            let root = tree.root()?;
+            let mut children = root.children();
+            let mut parameters = Vec::new();
+            while let Some(child) = children.next()? {
+                let entry = child.entry();
+                if entry.tag() != gimli::DW_TAG_formal_parameter {
+                    continue;
+                }
+                let (Some(name), Some(AttributeValue::UnitRef(ty))) = (
+                    function_name(dwarf, &unit, entry),
+                    variable_type(&unit, entry),
+                ) else {
+                    continue;
+                };
+                parameters.extend(value_layout(dwarf, &unit, name, ty));
+            }
+            return Ok(parameters);
+        }
+        Ok(Vec::new())
+    })
+}
+
+/// The members, elements or pointee of values of the type at `type_offset`.
+pub fn type_children(binary: &Path, type_offset: TypeOffset) -> AnyResult<TypeChildren> {
+    with_dwarf(binary, |dwarf| {
+        let mut units = dwarf.units();
+        while let Some(header) = units.next()? {
+            let Some(offset) = DebugInfoOffset(type_offset).to_unit_offset(&header) else {
+                continue;
+            };
+            let unit = dwarf.unit(header)?;
+            return children_of(dwarf, &unit, offset);
+        }
+        Ok(TypeChildren::None)
+    })
+}
+
+/// Runs `f` over the DWARF of `binary`.
+fn with_dwarf<T>(
+    binary: &Path,
+    f: impl FnOnce(&Dwarf<Reader<'_>>) -> AnyResult<T>,
+) -> AnyResult<T> {
+    let data = fs::read(binary)
+        .with_context(|| format!("failed to read binary for variables: {}", binary.display()))?;
+    let file = object::File::parse(&*data).context("failed to parse binary for variables")?;
+    let endian = if file.is_little_endian() {
+        RunTimeEndian::Little
+    } else {
+        RunTimeEndian::Big
+    };
+    let dwarf_sections = gimli::DwarfSections::load(|id| load_section_vec(&file, id))?;
+    let dwarf = dwarf_sections.borrow(|section| EndianSlice::new(section, endian));
+    f(&dwarf)
+}
+
+fn collect_variables(
+    dwarf: &Dwarf<Reader<'_>>,
+    unit: &Unit<Reader<'_>>,
+    node: EntriesTreeNode<'_, '_, '_, Reader<'_>>,
+    pc: u64,
+    frame_base: Option<VariableLocation>,
+    out: &mut Vec<FrameVariable>,
+) -> AnyResult<()> {
+    let mut children = node.children();
+    while let Some(child) = children.next()? {
+        let entry = child.entry();
+        match entry.tag() {
+            gimli::DW_TAG_variable | gimli::DW_TAG_formal_parameter => {
+                let Some(name) = function_name(dwarf, unit, entry) else {
+                    continue;
+                };
+                let Some(location) = variable_location(dwarf, unit, entry, pc, frame_base)? else {
+                    continue;
+                };
+                let Some(AttributeValue::UnitRef(ty)) = variable_type(unit, entry) else {
+                    continue;
+                };
+                if let Some(layout) = value_layout(dwarf, unit, name, ty) {
+                    out.push(FrameVariable { layout, location });
+                }
+            }
+            gimli::DW_TAG_lexical_block if die_contains(dwarf, unit, entry, pc) => {
+                collect_variables(dwarf, unit, child, pc, frame_base, out)?;
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn value_layout(
+    dwarf: &Dwarf<Reader<'_>>,
+    unit: &Unit<Reader<'_>>,
+    name: String,
+    ty: UnitOffset,
+) -> Option<ValueLayout> {
+    let resolved = resolve_type(dwarf, unit, ty)?;
+    // Swift's scalars are structs wrapping a builtin; their one member is noise.
+    let expandable = matches!(
+        resolved.kind,
+        ValueKind::Aggregate | ValueKind::Pointer | ValueKind::Unknown
+    ) && has_children(unit, ty);
+    Some(ValueLayout {
+        name,
+        ty: resolved,
+        type_offset: ty.to_debug_info_offset(&unit.header)?.0,
+        expandable,
+    })
+}
+
+/// Follows typedefs and qualifiers to the type that defines the layout.
+fn strip_aliases(unit: &Unit<Reader<'_>>, offset: UnitOffset) -> Option<UnitOffset> {
+    let mut offset = offset;
+    for _ in 0..16 {
+        let entry = unit.entry(offset).ok()?;
+        match entry.tag() {
+            gimli::DW_TAG_typedef
+            | gimli::DW_TAG_const_type
+            | gimli::DW_TAG_volatile_type
+            | gimli::DW_TAG_restrict_type
+            | gimli::DW_TAG_atomic_type => match entry.attr_value(gimli::DW_AT_type).ok()?? {
+                AttributeValue::UnitRef(next) => offset = next,
+                _ => return None,
+            },
+            _ => return Some(offset),
+        }
+    }
+    None
+}
+
+/// A cheap look at whether [`children_of`] would find anything.
+fn has_children(unit: &Unit<Reader<'_>>, ty: UnitOffset) -> bool {
+    let Some(offset) = strip_aliases(unit, ty) else {
+        return false;
+    };
+    let Ok(entry) = unit.entry(offset) else {
+        return false;
+    };
+    match entry.tag() {
+        gimli::DW_TAG_pointer_type
+        | gimli::DW_TAG_reference_type
+        | gimli::DW_TAG_rvalue_reference_type => {
+            matches!(entry.attr_value(gimli::DW_AT_type), Ok(Some(_)))
+        }
+        gimli::DW_TAG_structure_type
+        | gimli::DW_TAG_class_type
+        | gimli::DW_TAG_union_type
+        | gimli::DW_TAG_array_type => entry.has_children(),
+        _ => false,
+    }
+}
+
+fn children_of(
+    dwarf: &Dwarf<Reader<'_>>,
+    unit: &Unit<Reader<'_>>,
+    ty: UnitOffset,
+) -> AnyResult<TypeChildren> {
+    let Some(offset) = strip_aliases(unit, ty) else {
+        return Ok(TypeChildren::None);
+    };
+    let entry = unit.entry(offset)?;
+    let tag = entry.tag();
+    let target = match entry.attr_value(gimli::DW_AT_type)? {
+        Some(AttributeValue::UnitRef(target)) => Some(target),
+        _ => None,
+    };
+    Ok(match tag {
+        gimli::DW_TAG_pointer_type
+        | gimli::DW_TAG_reference_type
+        | gimli::DW_TAG_rvalue_reference_type => target
+            .and_then(|target| value_layout(dwarf, unit, "*".into(), target))
+            .map_or(TypeChildren::None, TypeChildren::Pointee),
+        gimli::DW_TAG_array_type => {
+            let element =
+                target.and_then(|target| value_layout(dwarf, unit, String::new(), target));
+            let mut tree = unit.entries_tree(Some(offset))?;
+            let mut children = tree.root()?.children();
+            let mut count = None;
+            while let Some(child) = children.next()? {
+                let entry = child.entry();
+                if entry.tag() != gimli::DW_TAG_subrange_type {
+                    continue;
+                }
+                let attr = |name| entry.attr_value(name).ok().flatten()?.udata_value();
+                count = attr(gimli::DW_AT_count)
+                    .or_else(|| attr(gimli::DW_AT_upper_bound).map(|bound| bound + 1));
+                // Only the outermost dimension is expanded.
+                break;
+            }
+            match (element, count) {
+                (Some(element), Some(count)) => match element.ty.byte_size {
+                    Some(stride) if stride > 0 => TypeChildren::Elements {
+                        element,
+                        stride,
+                        count,
+                    },
+                    _ => TypeChildren::None,
+                },
+                _ => TypeChildren::None,
+            }
+        }
+        gimli::DW_TAG_structure_type | gimli::DW_TAG_class_type | gimli::DW_TAG_union_type => {
+            let mut members = Vec::new();
+            let mut tree = unit.entries_tree(Some(offset))?;
+            collect_members(dwarf, unit, tree.root()?, &mut members)?;
+            if members.is_empty() {
+                TypeChildren::None
+            } else {
+                TypeChildren::Members {
+                    members,
+                    indirect: tag == gimli::DW_TAG_class_type,
+                }
+            }
+        }
+        _ => TypeChildren::None,
+    })
+}
+
+/// Data members and base classes of a struct, class or union. An enum's
+/// `DW_TAG_variant_part` contributes its payload only when a single case has
+/// one, as `Optional` does, since the active case is not known.
+fn collect_members(
+    dwarf: &Dwarf<Reader<'_>>,
+    unit: &Unit<Reader<'_>>,
+    node: EntriesTreeNode<'_, '_, '_, Reader<'_>>,
+    out: &mut Vec<(u64, ValueLayout)>,
+) -> AnyResult<()> {
+    let mut children = node.children();
+    while let Some(child) = children.next()? {
+        let entry = child.entry();
+        match entry.tag() {
+            gimli::DW_TAG_member | gimli::DW_TAG_inheritance => {
+                let is_static = [gimli::DW_AT_external, gimli::DW_AT_declaration]
+                    .into_iter()
+                    .any(|attr| matches!(entry.attr_value(attr), Ok(Some(_))));
+                if is_static {
+                    continue;
+                }
+                let Some(AttributeValue::UnitRef(ty)) = entry.attr_value(gimli::DW_AT_type)? else {
+                    continue;
+                };
+                let offset = entry
+                    .attr_value(gimli::DW_AT_data_member_location)?
+                    .and_then(|value| value.udata_value())
+                    .unwrap_or(0);
+                let name = attr_string(dwarf, unit, entry, gimli::DW_AT_name)
+                    .or_else(|| resolve_type(dwarf, unit, ty).map(|ty| ty.name))
+                    .unwrap_or_default();
+                if let Some(layout) = value_layout(dwarf, unit, name, ty) {
+                    out.push((offset, layout));
+                }
+            }
+            gimli::DW_TAG_variant_part => {
+                let mut payloads = Vec::new();
+                let mut variants = child.children();
+                while let Some(variant) = variants.next()? {
+                    if variant.entry().tag() != gimli::DW_TAG_variant {
+                        continue;
+                    }
+                    let mut members = Vec::new();
+                    collect_members(dwarf, unit, variant, &mut members)?;
+                    if !members.is_empty() {
+                        payloads.push(members);
+                    }
+                }
+                if payloads.len() == 1 {
+                    out.append(&mut payloads[0]);
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn containing_function(
+    dwarf: &Dwarf<Reader<'_>>,
+    unit: &Unit<Reader<'_>>,
+    pc: u64,
+) -> AnyResult<Option<(UnitOffset, Option<VariableLocation>)>> {
+    let mut entries = unit.entries();
+    while let Some((_, entry)) = entries.next_dfs()? {
+        if entry.tag() != gimli::DW_TAG_subprogram || !die_contains(dwarf, unit, entry, pc) {
+            continue;
+        }
+        let frame_base = match entry.attr_value(gimli::DW_AT_frame_base)? {
+            Some(AttributeValue::Exprloc(expression)) => {
+                single_operation_location(expression, unit, None)
+            }
+            _ => None,
+        };
+        return Ok(Some((entry.offset(), frame_base)));
+    }
+    Ok(None)
+}
+
+fn die_contains(
+    dwarf: &Dwarf<Reader<'_>>,
+    unit: &Unit<Reader<'_>>,
+    entry: &DebuggingInformationEntry<'_, '_, Reader<'_>>,
+    pc: u64,
+) -> bool {
+    let Ok(mut ranges) = dwarf.die_ranges(unit, entry) else {
+        return false;
+    };
+    while let Ok(Some(range)) = ranges.next() {
+        if range.begin <= pc && pc < range.end {
+            return true;
+        }
+    }
+    false
+}
+
+/// Depth-first search through the scopes of a function that cover `pc`.
+fn find_variable(
+    dwarf: &Dwarf<Reader<'_>>,
+    unit: &Unit<Reader<'_>>,
+    node: EntriesTreeNode<'_, '_, '_, Reader<'_>>,
+    pc: u64,
+    name: &str,
+) -> AnyResult<Option<UnitOffset>> {
+    let mut children = node.children();
+    while let Some(child) = children.next()? {
+        let entry = child.entry();
+        match entry.tag() {
+            gimli::DW_TAG_variable | gimli::DW_TAG_formal_parameter
+                if function_name(dwarf, unit, entry).as_deref() == Some(name) =>
+            {
+                return Ok(Some(entry.offset()));
+            }
+            gimli::DW_TAG_lexical_block if die_contains(dwarf, unit, entry, pc) => {
+                if let Some(found) = find_variable(dwarf, unit, child, pc, name)? {
+                    return Ok(Some(found));
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(None)
+}
+
+fn variable_location(
+    dwarf: &Dwarf<Reader<'_>>,
+    unit: &Unit<Reader<'_>>,
+    entry: &DebuggingInformationEntry<'_, '_, Reader<'_>>,
+    pc: u64,
+    frame_base: Option<VariableLocation>,
+) -> AnyResult<Option<VariableLocation>> {
+    match entry.attr_value(gimli::DW_AT_location)? {
+        Some(AttributeValue::Exprloc(expression)) => {
+            Ok(single_operation_location(expression, unit, frame_base))
+        }
+        Some(AttributeValue::LocationListsRef(offset)) => {
+            let mut locations = dwarf.locations(unit, offset)?;
+            while let Some(location) = locations.next()? {
+                if location.range.begin <= pc && pc < location.range.end {
+                    return Ok(single_operation_location(location.data, unit, frame_base));
+                }
+            }
+            Ok(None)
+        }
+        _ => Ok(None),
+    }
+}
+
+fn single_operation_location(
+    expression: Expression<Reader<'_>>,
+    unit: &Unit<Reader<'_>>,
+    frame_base: Option<VariableLocation>,
+) -> Option<VariableLocation> {
+    let mut operations = expression.operations(unit.encoding());
+    let operation = operations.next().ok()??;
+    if !matches!(operations.next(), Ok(None)) {
+        return None;
+    }
+    match operation {
+        Operation::Register { register } => Some(VariableLocation::Register(register.0)),
+        Operation::RegisterOffset {
+            register, offset, ..
+        } => Some(VariableLocation::Memory {
+            register: register.0,
+            offset,
+        }),
+        // The frame base is the value of a register (`DW_OP_regN`) or of a
+        // register plus a bias (`DW_OP_bregN`); either way fbreg adds to it.
+        Operation::FrameOffset { offset } => match frame_base? {
+            VariableLocation::Register(register) => {
+                Some(VariableLocation::Memory { register, offset })
+            }
+            VariableLocation::Memory {
+                register,
+                offset: bias,
+            } => Some(VariableLocation::Memory {
+                register,
+                offset: bias + offset,
+            }),
+        },
+        _ => None,
+    }
+}
+
+fn variable_type<'a>(
+    unit: &Unit<Reader<'a>>,
+    entry: &DebuggingInformationEntry<'_, '_, Reader<'a>>,
+) -> Option<AttributeValue<Reader<'a>>> {
+    if let Some(ty) = entry.attr_value(gimli::DW_AT_type).ok()? {
+        return Some(ty);
+    }
+    let origin = unit.entry(origin_offset(entry)?).ok()?;
+    origin.attr_value(gimli::DW_AT_type).ok()?
+}
+
+/// Finds a data member of the struct `ty` (looking through typedefs and qualifiers).
+fn find_member(
+    dwarf: &Dwarf<Reader<'_>>,
+    unit: &Unit<Reader<'_>>,
+    ty: UnitOffset,
+    member: &str,
+) -> AnyResult<Option<(u64, UnitOffset)>> {
+    let mut ty = ty;
+    for _ in 0..16 {
+        let entry = unit.entry(ty)?;
+        match entry.tag() {
+            gimli::DW_TAG_typedef | gimli::DW_TAG_const_type | gimli::DW_TAG_volatile_type => {
+                match entry.attr_value(gimli::DW_AT_type)? {
+                    Some(AttributeValue::UnitRef(next)) => ty = next,
+                    _ => return Ok(None),
+                }
+            }
+            gimli::DW_TAG_structure_type | gimli::DW_TAG_union_type => break,
+            _ => return Ok(None),
+        }
+    }
+    let mut tree = unit.entries_tree(Some(ty))?;
+    let mut children = tree.root()?.children();
+    while let Some(child) = children.next()? {
+        let entry = child.entry();
+        if entry.tag() != gimli::DW_TAG_member
+            || attr_string(dwarf, unit, entry, gimli::DW_AT_name).as_deref() != Some(member)
+        {
+            continue;
+        }
+        let offset = entry
+            .attr_value(gimli::DW_AT_data_member_location)?
+            .and_then(|value| value.udata_value())
+            .unwrap_or(0);
+        return Ok(match entry.attr_value(gimli::DW_AT_type)? {
+            Some(AttributeValue::UnitRef(member_ty)) => Some((offset, member_ty)),
+            _ => None,
+        });
+    }
+    Ok(None)
+}
+
+fn function_name(
+    dwarf: &Dwarf<Reader<'_>>,
+    unit: &Unit<Reader<'_>>,
+    entry: &DebuggingInformationEntry<'_, '_, Reader<'_>>,
+) -> Option<String> {
+    if let Some(name) = attr_string(dwarf, unit, entry, gimli::DW_AT_name) {
+        return Some(name);
+    }
+    let origin = origin_offset(entry)?;
+    let origin = unit.entry(origin).ok()?;
+    attr_string(dwarf, unit, &origin, gimli::DW_AT_name)
+}
+
+fn function_return_type(
+    dwarf: &Dwarf<Reader<'_>>,
+    unit: &Unit<Reader<'_>>,
+    entry: &DebuggingInformationEntry<'_, '_, Reader<'_>>,
+) -> Option<ValueType> {
+    if let Some(AttributeValue::UnitRef(offset)) = entry.attr_value(gimli::DW_AT_type).ok()? {
+        return resolve_type(dwarf, unit, offset);
+    }
+    // Swift and C++ definitions frequently carry their signature on the declaration.
+    let origin = origin_offset(entry)?;
+    let origin = unit.entry(origin).ok()?;
+    match origin.attr_value(gimli::DW_AT_type).ok()? {
+        Some(AttributeValue::UnitRef(offset)) => resolve_type(dwarf, unit, offset),
+        _ => None,
+    }
+}
+
+fn origin_offset(entry: &DebuggingInformationEntry<'_, '_, Reader<'_>>) -> Option<UnitOffset> {
+    [gimli::DW_AT_specification, gimli::DW_AT_abstract_origin]
+        .into_iter()
+        .find_map(|attr| match entry.attr_value(attr).ok()? {
+            Some(AttributeValue::UnitRef(offset)) => Some(offset),
+            _ => None,
+        })
+}
+
+/// Follows typedef/qualifier chains until a type that determines the value layout.
+pub fn resolve_type(
+    dwarf: &Dwarf<Reader<'_>>,
+    unit: &Unit<Reader<'_>>,
+    offset: UnitOffset,
+) -> Option<ValueType> {
+    let mut offset = offset;
+    let mut alias = None;
+    for _ in 0..16 {
+        let entry = unit.entry(offset).ok()?;
+        let name = attr_string(dwarf, unit, &entry, gimli::DW_AT_name);
+        let byte_size = entry
+            .attr_value(gimli::DW_AT_byte_size)
+            .ok()
+            .flatten()
+            .and_then(|value| value.udata_value());
+        let tag = entry.tag();
+        let kind = match tag {
+            gimli::DW_TAG_base_type => {
+                match entry.attr_value(gimli::DW_AT_encoding).ok().flatten() {
+                    Some(AttributeValue::Encoding(encoding)) => base_type_kind(encoding),
+                    _ => ValueKind::Unknown,
+                }
+            }
+            gimli::DW_TAG_pointer_type
+            | gimli::DW_TAG_reference_type
+            | gimli::DW_TAG_rvalue_reference_type
+            | gimli::DW_TAG_class_type => ValueKind::Pointer,
+            gimli::DW_TAG_structure_type | gimli::DW_TAG_union_type => name
+                .as_deref()
+                .and_then(swift_scalar_kind)
+                .unwrap_or(ValueKind::Aggregate),
+            gimli::DW_TAG_enumeration_type => ValueKind::Unsigned,
+            gimli::DW_TAG_typedef
+            | gimli::DW_TAG_const_type
+            | gimli::DW_TAG_volatile_type
+            | gimli::DW_TAG_restrict_type
+            | gimli::DW_TAG_atomic_type => {
+                if alias.is_none() {
+                    alias = name;
+                }
+                match entry.attr_value(gimli::DW_AT_type).ok().flatten() {
+                    Some(AttributeValue::UnitRef(next)) => {
+                        offset = next;
+                        continue;
+                    }
+                    _ => return None,
+                }
+            }
+            _ => ValueKind::Unknown,
+        };
+        let name = alias
+            .or(name)
+            .unwrap_or_else(|| default_type_name(tag, kind).to_string());
+        return Some(ValueType {
+            name,
+            byte_size,
+            kind,
+        });
+    }
+    None
+}
+
+fn base_type_kind(encoding: gimli::DwAte) -> ValueKind {
+    match encoding {
+        gimli::DW_ATE_signed | gimli::DW_ATE_signed_char => ValueKind::Signed,
+        gimli::DW_ATE_unsigned | gimli::DW_ATE_unsigned_char | gimli::DW_ATE_UTF => {
+            ValueKind::Unsigned
+        }
+        gimli::DW_ATE_boolean => ValueKind::Bool,
+        gimli::DW_ATE_float => ValueKind::Float,
+        gimli::DW_ATE_address => ValueKind::Pointer,
+        _ => ValueKind::Unknown,
+    }
+}
+
+/// Swift exposes its scalars as single-field structs; classify the common ones by name.
+fn swift_scalar_kind(name: &str) -> Option<ValueKind> {
+    let name = name.strip_prefix("Swift.").unwrap_or(name);
+    match name {
+        "Int" | "Int8" | "Int16" | "Int32" | "Int64" => Some(ValueKind::Signed),
+        "UInt" | "UInt8" | "UInt16" | "UInt32" | "UInt64" => Some(ValueKind::Unsigned),
+        "Bool" => Some(ValueKind::Bool),
+        "Float" | "Double" | "CGFloat" => Some(ValueKind::Float),
+        "UnsafeRawPointer" | "UnsafeMutableRawPointer" | "OpaquePointer" => {
+            Some(ValueKind::Pointer)
+        }
+        _ => None,
+    }
+}
+
+fn default_type_name(tag: gimli::DwTag, kind: ValueKind) -> &'static str {
+    match (tag, kind) {
+        (gimli::DW_TAG_pointer_type, _) => "pointer",
+        (gimli::DW_TAG_reference_type, _) | (gimli::DW_TAG_rvalue_reference_type, _) => "reference",
+        (_, ValueKind::Aggregate) => "struct",
+        _ => "<unknown>",
+    }
+}
+
+fn attr_string(
+    dwarf: &Dwarf<Reader<'_>>,
+    unit: &Unit<Reader<'_>>,
+    entry: &DebuggingInformationEntry<'_, '_, Reader<'_>>,
+    attr: gimli::DwAt,
+) -> Option<String> {
+    let value = entry.attr_value(attr).ok()??;
+    let name = dwarf.attr_string(unit, value).ok()?;
+    Some(name.to_string_lossy().into_owned())
+}
+
+/// Renders a value returned in registers according to its DWARF type.
+///
+/// `integer` holds the bytes of the first integer return register (x0 on arm64)
+/// and `float` the bytes of the first floating-point return register, if read.
+pub fn format_register_value(ty: &ValueType, integer: &[u8], float: Option<&[u8]>) -> String {
+    let size = ty.byte_size.unwrap_or(8).clamp(1, 8) as usize;
+    let raw = le_u64(integer);
+    match ty.kind {
+        ValueKind::Signed => {
+            let shift = 64 - size * 8;
+            (((raw << shift) as i64) >> shift).to_string()
+        }
+        ValueKind::Unsigned => mask_to_size(raw, size).to_string(),
+        ValueKind::Bool => (raw & 0xff != 0).to_string(),
+        ValueKind::Float => {
+            let bits = float.map(le_u64).unwrap_or(raw);
+            if size == 4 {
+                f32::from_bits(bits as u32).to_string()
+            } else {
+                f64::from_bits(bits).to_string()
+            }
+        }
+        ValueKind::Pointer => format!("0x{raw:x}"),
+        ValueKind::Aggregate | ValueKind::Unknown => format!("0x{raw:x} (raw register)"),
+    }
+}
+
+/// Encodes `text` as the little-endian bytes of a value of type `ty`, the
+/// inverse of [`format_register_value`] for scalar types.
+pub fn encode_value(ty: &ValueType, text: &str) -> Result<Vec<u8>, String> {
+    let size = ty.byte_size.unwrap_or(8).clamp(1, 8) as usize;
+    let text = text.trim();
+    let invalid = || format!("`{text}` is not a valid {}", ty.name);
+    let bits = match ty.kind {
+        ValueKind::Signed => {
+            let value = parse_integer(text).ok_or_else(invalid)?;
+            let shift = 64 - size * 8;
+            if ((value << shift) >> shift) != value {
+                return Err(format!("{text} does not fit in {}", ty.name));
+            }
+            value as u64
+        }
+        ValueKind::Unsigned | ValueKind::Pointer => {
+            let value = parse_integer(text)
+                .and_then(|value| u64::try_from(value).ok())
+                .or_else(|| u64::from_str_radix(text.strip_prefix("0x")?, 16).ok())
+                .ok_or_else(invalid)?;
+            if mask_to_size(value, size) != value {
+                return Err(format!("{text} does not fit in {}", ty.name));
+            }
+            value
+        }
+        ValueKind::Bool => match text {
+            "true" | "1" | "YES" => 1,
+            "false" | "0" | "NO" => 0,
+            _ => return Err(invalid()),
+        },
+        ValueKind::Float => {
+            let value: f64 = text.parse().map_err(|_| invalid())?;
+            if size == 4 {
+                u64::from((value as f32).to_bits())
+            } else {
+                value.to_bits()
+            }
+        }
+        ValueKind::Aggregate | ValueKind::Unknown => {
+            return Err(format!("cannot assign to a value of type {}", ty.name));
+        }
+    };
+    Ok(bits.to_le_bytes()[..size].to_vec())
+}
+
+fn parse_integer(text: &str) -> Option<i64> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text),
+    };
+    let magnitude = match digits.strip_prefix("0x") {
+        Some(hex) => i64::from_str_radix(hex, 16).ok()?,
+        None => digits.parse().ok()?,
+    };
+    Some(if negative { -magnitude } else { magnitude })
+}
+
+/// arm64 register name for a DWARF register number, as debugserver names it.
+pub fn arm64_register_name(register: u16) -> Option<String> {
+    match register {
+        0..=28 => Some(format!("x{register}")),
+        29 => Some("fp".into()),
+        30 => Some("lr".into()),
+        31 => Some("sp".into()),
+        64..=95 => Some(format!("d{}", register - 64)),
+        _ => None,
+    }
+}
+
+fn mask_to_size(value: u64, size: usize) -> u64 {
+    if size >= 8 {
+        value
+    } else {
+        value & ((1u64 << (size * 8)) - 1)
+    }
+}
+
+pub(crate) fn load_section_vec(
+    file: &object::File<'_>,
+    id: SectionId,
+) -> Result<Vec<u8>, object::read::Error> {
+    if let Some(section) = file.section_by_name(id.name()) {
+        let data = section.uncompressed_data()?;
+        Ok(data.into_owned())
+    } else {
+        Ok(Vec::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use object::ObjectSymbol;
+
+    #[no_mangle]
+    #[inline(never)]
+    pub extern "C" fn dwarf_return_type_test_function(value: i32) -> i32 {
+        std::hint::black_box(value) - 1
+    }
+
+    #[test]
+    fn function_index_reports_return_type() {
+        assert_eq!(dwarf_return_type_test_function(1), 0);
+        let exe = std::env::current_exe().unwrap();
+        let index = match FunctionIndex::from_binary(&exe) {
+            Ok(index) => index,
+            Err(err) => {
+                eprintln!("skipping function_index_reports_return_type: {err}");
+                return;
+            }
+        };
+        let data = fs::read(&exe).unwrap();
+        let file = object::File::parse(&*data).unwrap();
+        let Some(address) = file
+            .symbols()
+            .find(|sym| {
+                sym.name()
+                    .map(|name| name.trim_start_matches('_') == "dwarf_return_type_test_function")
+                    .unwrap_or(false)
+            })
+            .map(|sym| sym.address())
+        else {
+            eprintln!("skipping function_index_reports_return_type: symbol missing");
+            return;
+        };
+        let Some(function) = index.lookup(address) else {
+            eprintln!("skipping function_index_reports_return_type: no DWARF for symbol");
+            return;
+        };
+        let ty = function.return_type.as_ref().expect("return type");
+        assert_eq!(ty.name, "i32");
+        assert_eq!(ty.kind, ValueKind::Signed);
+        assert_eq!(ty.byte_size, Some(4));
+    }
+
+    #[no_mangle]
+    #[inline(never)]
+    pub extern "C" fn dwarf_variable_test_function(value: i32) -> i32 {
+        std::hint::black_box(value) * 2
+    }
+
+    #[test]
+    fn locate_variable_finds_parameters() {
+        assert_eq!(dwarf_variable_test_function(2), 4);
+        let exe = std::env::current_exe().unwrap();
+        let data = fs::read(&exe).unwrap();
+        let file = object::File::parse(&*data).unwrap();
+        let Some(address) = file
+            .symbols()
+            .find(|sym| {
+                sym.name()
+                    .map(|name| name.trim_start_matches('_') == "dwarf_variable_test_function")
+                    .unwrap_or(false)
+            })
+            .map(|sym| sym.address())
+        else {
+            eprintln!("skipping locate_variable_finds_parameters: symbol missing");
+            return;
+        };
+        let Some(info) = locate_variable(&exe, address, "value").unwrap() else {
+            eprintln!("skipping locate_variable_finds_parameters: no simple location");
+            return;
+        };
+        assert_eq!(info.ty.kind, ValueKind::Signed);
+        assert_eq!(info.ty.byte_size, Some(4));
+        assert!(locate_variable(&exe, address, "missing").unwrap().is_none());
+        assert!(locate_variable(&exe, address, "value.field")
+            .unwrap()
+            .is_none());
+    }
+
+    #[repr(C)]
+    pub struct DwarfTestPoint {
+        x: i32,
+        y: i32,
+    }
+
+    #[no_mangle]
+    #[inline(never)]
+    pub extern "C" fn dwarf_children_test_function(
+        point: DwarfTestPoint,
+        values: &[u16; 3],
+        target: &DwarfTestPoint,
+    ) -> i32 {
+        std::hint::black_box(point.x + i32::from(values[2]) + target.y)
+    }
+
+    #[test]
+    fn frame_variables_expand_members_elements_and_pointees() {
+        let point = DwarfTestPoint { x: 1, y: 2 };
+        assert_eq!(
+            dwarf_children_test_function(DwarfTestPoint { x: 1, y: 0 }, &[0, 0, 3], &point),
+            6
+        );
+        let exe = std::env::current_exe().unwrap();
+        let data = fs::read(&exe).unwrap();
+        let file = object::File::parse(&*data).unwrap();
+        let Some(address) = file
+            .symbols()
+            .find(|sym| {
+                sym.name()
+                    .map(|name| name.trim_start_matches('_') == "dwarf_children_test_function")
+                    .unwrap_or(false)
+            })
+            .map(|sym| sym.address())
+        else {
+            eprintln!(
+                "skipping frame_variables_expand_members_elements_and_pointees: symbol missing"
+            );
+            return;
+        };
+        let variables = frame_variables(&exe, address).unwrap();
+        let layout = |name: &str| {
+            variables
+                .iter()
+                .find(|variable| variable.layout.name == name)
+                .map(|variable| variable.layout.clone())
+        };
+        let (Some(point), Some(values), Some(target)) =
+            (layout("point"), layout("values"), layout("target"))
+        else {
+            eprintln!("skipping frame_variables_expand_members_elements_and_pointees: no simple locations");
+            return;
+        };
+        assert!(point.expandable && values.expandable && target.expandable);
+        let parameters: Vec<String> = function_parameters(&exe, address)
+            .unwrap()
+            .into_iter()
+            .map(|parameter| parameter.name)
+            .collect();
+        assert_eq!(parameters, ["point", "values", "target"]);
+
+        let TypeChildren::Members { members, indirect } =
+            type_children(&exe, point.type_offset).unwrap()
+        else {
+            panic!("a struct has members");
+        };
+        assert!(!indirect);
+        let members: Vec<_> = members
+            .iter()
+            .map(|(offset, member)| (*offset, member.name.as_str(), member.ty.kind))
+            .collect();
+        assert_eq!(
+            members,
+            [(0, "x", ValueKind::Signed), (4, "y", ValueKind::Signed)]
+        );
+
+        let TypeChildren::Pointee(array) = type_children(&exe, values.type_offset).unwrap() else {
+            panic!("a reference has a pointee");
+        };
+        let TypeChildren::Elements {
+            element,
+            stride,
+            count,
+        } = type_children(&exe, array.type_offset).unwrap()
+        else {
+            panic!("an array has elements");
+        };
+        assert_eq!(
+            (element.ty.kind, stride, count),
+            (ValueKind::Unsigned, 2, 3)
+        );
+
+        let TypeChildren::Pointee(pointee) = type_children(&exe, target.type_offset).unwrap()
+        else {
+            panic!("a reference has a pointee");
+        };
+        assert_eq!(pointee.type_offset, point.type_offset);
+    }
+
+    #[test]
+    fn encode_value_checks_range_and_kind() {
+        let int8 = ValueType {
+            name: "Int8".into(),
+            byte_size: Some(1),
+            kind: ValueKind::Signed,
+        };
+        assert_eq!(encode_value(&int8, "-1").unwrap(), vec![0xff]);
+        assert!(encode_value(&int8, "300").is_err());
+        let double = ValueType {
+            name: "Double".into(),
+            byte_size: Some(8),
+            kind: ValueKind::Float,
+        };
+        assert_eq!(
+            encode_value(&double, "2.5").unwrap(),
+            2.5f64.to_bits().to_le_bytes().to_vec()
+        );
+        let aggregate = ValueType {
+            name: "Point".into(),
+            byte_size: Some(16),
+            kind: ValueKind::Aggregate,
+        };
+        assert!(encode_value(&aggregate, "1").is_err());
+        assert_eq!(arm64_register_name(29).as_deref(), Some("fp"));
+    }
+
+    #[test]
+    fn format_register_value_respects_kind_and_size() {
+        let int32 = ValueType {
+            name: "Int32".into(),
+            byte_size: Some(4),
+            kind: ValueKind::Signed,
+        };
+        let minus_one = 0xffff_ffffu64.to_le_bytes();
+        assert_eq!(format_register_value(&int32, &minus_one, None), "-1");
+
+        let boolean = ValueType {
+            name: "Bool".into(),
+            byte_size: Some(1),
+            kind: ValueKind::Bool,
+        };
+        assert_eq!(
+            format_register_value(&boolean, &1u64.to_le_bytes(), None),
+            "true"
+        );
+
+        let double = ValueType {
+            name: "Double".into(),
+            byte_size: Some(8),
+            kind: ValueKind::Float,
+        };
+        let bits = 2.5f64.to_bits().to_le_bytes();
+        assert_eq!(format_register_value(&double, &[0; 8], Some(&bits)), "2.5");
+    }
+}
@@ -0,0 +1,266 @@
+//! Exported-symbol lookup in images loaded in the target, backing `describe`.
+//!
+//! System libraries live in the dyld shared cache and have no file on the
+//! host to read symbols from, so the lookup walks the image's Mach-O header
+//! and export trie straight out of target memory. Only the trie nodes on the
+//! path to the symbol are visited. The same header walk gives the link-time
+//! `__TEXT` address that image slides are computed from.
+
+/// `LC_SEGMENT_64`.
+const LC_SEGMENT_64: u32 = 0x19;
+/// `LC_DYLD_INFO` and `LC_DYLD_INFO_ONLY`.
+const LC_DYLD_INFO: u32 = 0x22;
+const LC_DYLD_INFO_ONLY: u32 = 0x8000_0022;
+/// `LC_DYLD_EXPORTS_TRIE`.
+const LC_DYLD_EXPORTS_TRIE: u32 = 0x8000_0033;
+const MH_MAGIC_64: u32 = 0xfeed_facf;
+const EXPORT_SYMBOL_FLAGS_REEXPORT: u64 = 0x08;
+/// Larger tries are not worth pulling over the wire.
+const MAX_TRIE_SIZE: usize = 4 << 20;
+const MAX_LOAD_COMMANDS_SIZE: usize = 1 << 20;
+
+/// Where `symbol` (with its leading underscore, e.g. `_objc_msgSend`) lives
+/// in the image whose Mach-O header is at `header`, reading memory through
+/// `read`.
+pub fn find_export<R>(mut read: R, header: u64, symbol: &str) -> Option<u64>
+where
+    R: FnMut(u64, usize) -> Option<Vec<u8>>,
+{
+    let commands = load_commands(&mut read, header)?;
+    let mut text_vmaddr = None;
+    let mut linkedit = None;
+    let mut trie = None;
+    let mut offset = 0;
+    while offset + 8 <= commands.len() {
+        let command = &commands[offset..];
+        let size = u32_at(command, 4)? as usize;
+        if size < 8 {
+            return None;
+        }
+        match u32_at(command, 0)? {
+            LC_SEGMENT_64 => {
+                let name = command.get(8..24)?;
+                let name = &name[..name.iter().position(|&b| b == 0).unwrap_or(16)];
+                let vmaddr = u64_at(command, 24)?;
+                match name {
+                    b"__TEXT" => text_vmaddr = Some(vmaddr),
+                    b"__LINKEDIT" => linkedit = Some((vmaddr, u64_at(command, 40)?)),
+                    _ => {}
+                }
+            }
+            LC_DYLD_INFO | LC_DYLD_INFO_ONLY => {
+                let (off, size) = (u32_at(command, 40)?, u32_at(command, 44)?);
+                if size > 0 {
+                    trie = Some((off, size));
+                }
+            }
+            LC_DYLD_EXPORTS_TRIE => trie = Some((u32_at(command, 8)?, u32_at(command, 12)?)),
+            _ => {}
+        }
+        offset += size;
+    }
+    let slide = header.wrapping_sub(text_vmaddr?);
+    let (linkedit_vmaddr, linkedit_fileoff) = linkedit?;
+    let (trie_offset, trie_size) = trie?;
+    if trie_size as usize > MAX_TRIE_SIZE {
+        return None;
+    }
+    let trie_address = linkedit_vmaddr
+        .wrapping_add(slide)
+        .wrapping_add(u64::from(trie_offset).wrapping_sub(linkedit_fileoff));
+    let trie = read(trie_address, trie_size as usize)?;
+    lookup_trie(&trie, symbol).map(|offset| header.wrapping_add(offset))
+}
+
+/// Link-time address of the `__TEXT` segment of the image whose Mach-O header
+/// is at `header`; the image's slide is `header` minus it.
+pub fn text_vmaddr<R>(mut read: R, header: u64) -> Option<u64>
+where
+    R: FnMut(u64, usize) -> Option<Vec<u8>>,
+{
+    let commands = load_commands(&mut read, header)?;
+    let mut offset = 0;
+    while offset + 8 <= commands.len() {
+        let command = &commands[offset..];
+        let size = u32_at(command, 4)? as usize;
+        if size < 8 {
+            return None;
+        }
+        if u32_at(command, 0)? == LC_SEGMENT_64 && command.get(8..15)? == b"__TEXT\0" {
+            return u64_at(command, 24);
+        }
+        offset += size;
+    }
+    None
+}
+
+/// The load commands following the 64-bit Mach-O header at `header`.
+fn load_commands<R>(read: &mut R, header: u64) -> Option<Vec<u8>>
+where
+    R: FnMut(u64, usize) -> Option<Vec<u8>>,
+{
+    let mach_header = read(header, 32)?;
+    if u32_at(&mach_header, 0)? != MH_MAGIC_64 {
+        return None;
+    }
+    let commands_size = u32_at(&mach_header, 20)? as usize;
+    if commands_size > MAX_LOAD_COMMANDS_SIZE {
+        return None;
+    }
+    read(header + 32, commands_size)
+}
+
+/// Offset from the image header of `symbol` in an export trie.
+fn lookup_trie(trie: &[u8], symbol: &str) -> Option<u64> {
+    let mut remaining = symbol.as_bytes();
+    let mut node = 0usize;
+    // Each step consumes at least one byte of the symbol, which bounds the walk.
+    for _ in 0..=symbol.len() {
+        let mut cursor = node;
+        let terminal_size = read_uleb(trie, &mut cursor)? as usize;
+        if remaining.is_empty() {
+            if terminal_size == 0 {
+                return None;
+            }
+            let flags = read_uleb(trie, &mut cursor)?;
+            if flags & EXPORT_SYMBOL_FLAGS_REEXPORT != 0 {
+                return None;
+            }
+            // For `EXPORT_SYMBOL_FLAGS_STUB_AND_RESOLVER` this is the stub,
+            // which is callable; the resolver offset that follows is not.
+            return read_uleb(trie, &mut cursor);
+        }
+        cursor += terminal_size;
+        let children = *trie.get(cursor)?;
+        cursor += 1;
+        let mut next = None;
+        for _ in 0..children {
+            let edge_end = cursor + trie.get(cursor..)?.iter().position(|&b| b == 0)?;
+            let edge = &trie[cursor..edge_end];
+            cursor = edge_end + 1;
+            let child = read_uleb(trie, &mut cursor)? as usize;
+            if !edge.is_empty() && remaining.starts_with(edge) {
+                next = Some((child, edge.len()));
+                break;
+            }
+        }
+        let (child, consumed) = next?;
+        remaining = &remaining[consumed..];
+        node = child;
+    }
+    None
+}
+
+fn read_uleb(bytes: &[u8], cursor: &mut usize) -> Option<u64> {
+    let mut value = 0u64;
+    let mut shift = 0;
+    loop {
+        let byte = *bytes.get(*cursor)?;
+        *cursor += 1;
+        if shift < 64 {
+            value |= u64::from(byte & 0x7f) << shift;
+        }
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+        shift += 7;
+    }
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        bytes.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+fn u64_at(bytes: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(
+        bytes.get(offset..offset + 8)?.try_into().ok()?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: u64 = 0x10_0000;
+
+    fn segment(name: &str, vmaddr: u64, fileoff: u64) -> Vec<u8> {
+        let mut command = Vec::new();
+        command.extend(LC_SEGMENT_64.to_le_bytes());
+        command.extend(72u32.to_le_bytes());
+        let mut segname = [0u8; 16];
+        segname[..name.len()].copy_from_slice(name.as_bytes());
+        command.extend(segname);
+        command.extend(vmaddr.to_le_bytes());
+        command.extend(0x1000u64.to_le_bytes());
+        command.extend(fileoff.to_le_bytes());
+        command.resize(72, 0);
+        command
+    }
+
+    /// A dylib linked at 0x1000 and loaded at `BASE`, exporting
+    /// `_objc_msgSend` at +0x1840 and `_sel_registerName` at +0x20.
+    fn image() -> Vec<u8> {
+        #[rustfmt::skip]
+        let trie: Vec<u8> = [
+            // 0: no terminal, one child "_" -> 5
+            &[0x00, 0x01, b'_', 0x00, 0x05][..],
+            // 5: no terminal, children "objc_msgSend" -> 39, "sel_registerName" -> 44
+            &[0x00, 0x02], b"objc_msgSend\0", &[0x27], b"sel_registerName\0", &[0x2c],
+            // 39: flags 0, offset 0x1840, no children
+            &[0x03, 0x00, 0xc0, 0x30, 0x00],
+            // 44: flags 0, offset 0x20, no children
+            &[0x02, 0x00, 0x20, 0x00],
+        ]
+        .concat();
+        let mut commands = segment("__TEXT", 0x1000, 0);
+        commands.extend(segment("__LINKEDIT", 0x5000, 0x4000));
+        commands.extend(LC_DYLD_EXPORTS_TRIE.to_le_bytes());
+        commands.extend(16u32.to_le_bytes());
+        commands.extend(0x4000u32.to_le_bytes());
+        commands.extend((trie.len() as u32).to_le_bytes());
+
+        let mut image = Vec::new();
+        image.extend(MH_MAGIC_64.to_le_bytes());
+        image.resize(16, 0);
+        image.extend(3u32.to_le_bytes());
+        image.extend((commands.len() as u32).to_le_bytes());
+        image.resize(32, 0);
+        image.extend(commands);
+        image.resize(0x4000, 0);
+        image.extend(trie);
+        image
+    }
+
+    #[test]
+    fn finds_exports_through_the_trie() {
+        let image = image();
+        let read = |address: u64, length: usize| {
+            let start = address.checked_sub(BASE)? as usize;
+            image.get(start..start + length).map(<[u8]>::to_vec)
+        };
+        assert_eq!(
+            find_export(read, BASE, "_objc_msgSend"),
+            Some(BASE + 0x1840)
+        );
+        assert_eq!(
+            find_export(read, BASE, "_sel_registerName"),
+            Some(BASE + 0x20)
+        );
+        assert_eq!(find_export(read, BASE, "_objc_msg"), None);
+        assert_eq!(find_export(read, BASE, "_missing"), None);
+        assert_eq!(find_export(read, BASE + 8, "_objc_msgSend"), None);
+    }
+
+    #[test]
+    fn text_vmaddr_comes_from_the_text_segment() {
+        let image = image();
+        let read = |address: u64, length: usize| {
+            let start = address.checked_sub(BASE)? as usize;
+            image.get(start..start + length).map(<[u8]>::to_vec)
+        };
+        assert_eq!(text_vmaddr(read, BASE), Some(0x1000));
+        assert_eq!(text_vmaddr(read, BASE + 8), None);
+    }
+}
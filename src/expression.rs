@@ -0,0 +1,1070 @@
+//! Expressions `evaluate` answers without running code in the target.
+//!
+//! The grammar is the part Swift, Objective-C and C share: literals,
+//! variables (locals and `$convenience` ones), member access (`user.name`,
+//! `tuple.0`), subscripts (`items[2]`, `dict["key"]`), unary `-` and `!`,
+//! `* / %`, `+ -`, comparisons, `&&` and `||`, with C precedence and
+//! parentheses. Members and elements come from the variable tree; a pointer
+//! without children is dereferenced by reading target memory.
+//!
+//! Each [`Language`] parses its own dialect on top of that: Swift has `nil`,
+//! optional chaining (`?`, postfix `!`) and `.pointee`; the C family has `->`,
+//! unary `*` and `NULL`; Objective-C adds `YES`/`NO`, `nil` and `@"..."`, and
+//! Objective-C++ `nullptr`. Another language's spelling is a syntax error, or
+//! an ordinary name when it is a keyword.
+
+use crate::debug_session::{parse_address, Language, Variable};
+
+/// Where an expression's names and memory come from.
+pub trait Scope {
+    /// A local of the selected frame or a convenience variable.
+    fn variable(&self, name: &str) -> Option<Variable>;
+    /// The children behind a non-zero `variablesReference`.
+    fn children(&mut self, reference: i64) -> Vec<Variable>;
+    fn read_memory(&mut self, address: u64, length: usize) -> Result<Vec<u8>, String>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EvalError {
+    /// The text is not an expression this module understands.
+    Syntax(String),
+    /// It parsed, but could not be evaluated against the scope.
+    Failed(String),
+    /// It names a variable or child the scope does not have.
+    Unknown(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Int(i128),
+    Float(f64),
+    Bool(bool),
+    Str(String),
+    Nil,
+    Name(String),
+    Member(Box<Expr>, String),
+    Index(Box<Expr>, Box<Expr>),
+    Unary(UnaryOp, Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    Neg,
+    Not,
+    Deref,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Mul,
+    Div,
+    Rem,
+    Add,
+    Sub,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+    And,
+    Or,
+}
+
+impl BinaryOp {
+    fn precedence(self) -> u8 {
+        match self {
+            Self::Or => 1,
+            Self::And => 2,
+            Self::Eq | Self::Ne => 3,
+            Self::Lt | Self::Le | Self::Gt | Self::Ge => 4,
+            Self::Add | Self::Sub => 5,
+            Self::Mul | Self::Div | Self::Rem => 6,
+        }
+    }
+
+    fn from_token(token: &Token) -> Option<Self> {
+        let Token::Punct(punct) = token else {
+            return None;
+        };
+        Some(match *punct {
+            "*" => Self::Mul,
+            "/" => Self::Div,
+            "%" => Self::Rem,
+            "+" => Self::Add,
+            "-" => Self::Sub,
+            "<" => Self::Lt,
+            "<=" => Self::Le,
+            ">" => Self::Gt,
+            ">=" => Self::Ge,
+            "==" => Self::Eq,
+            "!=" => Self::Ne,
+            "&&" => Self::And,
+            "||" => Self::Or,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Int(i128),
+    Float(f64),
+    Str(String),
+    Ident(String),
+    Punct(&'static str),
+}
+
+/// Longest first, so `->` is not read as `-` then `>`.
+const PUNCTUATION: [&str; 21] = [
+    "->", "==", "!=", "<=", ">=", "&&", "||", "+", "-", "*", "/", "%", "<", ">", "!", "(", ")",
+    "[", "]", ".", "?",
+];
+
+fn tokenize(text: &str, language: Language) -> Result<Vec<Token>, EvalError> {
+    let mut tokens = Vec::new();
+    let mut rest = text.trim_start();
+    while let Some(c) = rest.chars().next() {
+        if c.is_ascii_digit() {
+            let end = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '.'))
+                .unwrap_or(rest.len());
+            let mut literal = &rest[..end];
+            // A dot starts a fraction only when a digit follows it and the
+            // number is not itself a member, as in `tuple.0.1`.
+            if let Some(dot) = literal.find('.') {
+                let fraction = literal[dot + 1..].chars().next();
+                let member = matches!(tokens.last(), Some(Token::Punct("." | "->")));
+                if member
+                    || literal.starts_with("0x")
+                    || !fraction.is_some_and(|c| c.is_ascii_digit())
+                {
+                    literal = &literal[..dot];
+                }
+            }
+            tokens.push(
+                number_token(literal)
+                    .ok_or_else(|| EvalError::Syntax(format!("`{literal}` is not a number")))?,
+            );
+            rest = &rest[literal.len()..];
+        } else if c.is_alphabetic() || c == '_' || c == '$' {
+            let end = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+                .unwrap_or(rest.len());
+            tokens.push(Token::Ident(rest[..end].to_string()));
+            rest = &rest[end..];
+        } else if c == '"' || (c == '@' && rest[1..].starts_with('"') && is_objc(language)) {
+            let body = &rest[rest.find('"').unwrap_or(0) + 1..];
+            let (mut text, mut escaped, mut end) = (String::new(), false, None);
+            for (index, c) in body.char_indices() {
+                match c {
+                    _ if escaped => {
+                        text.push(match c {
+                            'n' => '\n',
+                            't' => '\t',
+                            '0' => '\0',
+                            other => other,
+                        });
+                        escaped = false;
+                    }
+                    '\\' => escaped = true,
+                    '"' => {
+                        end = Some(index);
+                        break;
+                    }
+                    other => text.push(other),
+                }
+            }
+            let end = end.ok_or_else(|| EvalError::Syntax("unterminated string literal".into()))?;
+            tokens.push(Token::Str(text));
+            rest = &body[end + 1..];
+        } else {
+            let punct = PUNCTUATION
+                .iter()
+                .find(|punct| rest.starts_with(*punct))
+                .ok_or_else(|| EvalError::Syntax(format!("unexpected `{c}`")))?;
+            tokens.push(Token::Punct(punct));
+            rest = &rest[punct.len()..];
+        }
+        rest = rest.trim_start();
+    }
+    Ok(tokens)
+}
+
+fn number_token(literal: &str) -> Option<Token> {
+    let digits = literal.replace('_', "");
+    if let Some(hex) = digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        return i128::from_str_radix(hex, 16).ok().map(Token::Int);
+    }
+    if let Some(binary) = digits.strip_prefix("0b") {
+        return i128::from_str_radix(binary, 2).ok().map(Token::Int);
+    }
+    digits
+        .parse()
+        .map(Token::Int)
+        .ok()
+        .or_else(|| digits.parse().map(Token::Float).ok())
+}
+
+/// Parses `text` into an expression tree, in `language`'s dialect.
+pub fn parse(text: &str, language: Language) -> Result<Expr, EvalError> {
+    let tokens = tokenize(text, language)?;
+    let mut parser = Parser {
+        tokens,
+        next: 0,
+        language,
+    };
+    let expr = parser.binary(1)?;
+    match parser.tokens.get(parser.next) {
+        None => Ok(expr),
+        Some(token) => Err(EvalError::Syntax(format!(
+            "unexpected {} after the expression",
+            describe(token)
+        ))),
+    }
+}
+
+fn is_objc(language: Language) -> bool {
+    matches!(language, Language::ObjC | Language::ObjCPlusPlus)
+}
+
+/// The literal `name` spells in `language`, if it is one of its keywords.
+fn keyword(name: &str, language: Language) -> Option<Expr> {
+    match (name, language) {
+        ("true", _) => Some(Expr::Bool(true)),
+        ("false", _) => Some(Expr::Bool(false)),
+        ("YES", lang) if is_objc(lang) => Some(Expr::Bool(true)),
+        ("NO", lang) if is_objc(lang) => Some(Expr::Bool(false)),
+        ("nil", Language::Swift | Language::ObjC | Language::ObjCPlusPlus)
+        | ("Nil", Language::ObjC | Language::ObjCPlusPlus)
+        | ("NULL", Language::ObjC | Language::ObjCPlusPlus | Language::C)
+        | ("nullptr", Language::ObjCPlusPlus) => Some(Expr::Nil),
+        _ => None,
+    }
+}
+
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Int(value) => format!("`{value}`"),
+        Token::Float(value) => format!("`{value}`"),
+        Token::Str(text) => format!("{text:?}"),
+        Token::Ident(name) => format!("`{name}`"),
+        Token::Punct(punct) => format!("`{punct}`"),
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    next: usize,
+    language: Language,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.next)
+    }
+
+    fn bump(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.next).cloned();
+        self.next += 1;
+        token
+    }
+
+    fn expect(&mut self, punct: &str) -> Result<(), EvalError> {
+        if matches!(self.peek(), Some(Token::Punct(found)) if *found == punct) {
+            self.next += 1;
+            return Ok(());
+        }
+        Err(EvalError::Syntax(match self.peek() {
+            Some(token) => format!("expected `{punct}`, found {}", describe(token)),
+            None => format!("expected `{punct}`"),
+        }))
+    }
+
+    /// Operators binding at least as tightly as `min`, left to right.
+    fn binary(&mut self, min: u8) -> Result<Expr, EvalError> {
+        let mut lhs = self.unary()?;
+        while let Some(op) = self.peek().and_then(BinaryOp::from_token) {
+            if op.precedence() < min {
+                break;
+            }
+            self.next += 1;
+            let rhs = self.binary(op.precedence() + 1)?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Expr, EvalError> {
+        let op = match self.peek() {
+            Some(Token::Punct("-")) => UnaryOp::Neg,
+            Some(Token::Punct("!")) => UnaryOp::Not,
+            Some(Token::Punct("*")) if self.language == Language::Swift => {
+                return Err(EvalError::Syntax(
+                    "Swift has no unary `*`; dereference with `.pointee`".into(),
+                ))
+            }
+            Some(Token::Punct("*")) => UnaryOp::Deref,
+            Some(Token::Punct("+")) => {
+                self.next += 1;
+                return self.unary();
+            }
+            _ => return self.postfix(),
+        };
+        self.next += 1;
+        Ok(Expr::Unary(op, Box::new(self.unary()?)))
+    }
+
+    fn postfix(&mut self) -> Result<Expr, EvalError> {
+        let mut expr = self.primary()?;
+        loop {
+            match self.peek() {
+                Some(Token::Punct("->")) if self.language == Language::Swift => {
+                    return Err(EvalError::Syntax("`->` is not Swift; use `.`".into()))
+                }
+                Some(Token::Punct("." | "->")) => {
+                    self.next += 1;
+                    let member = match self.bump() {
+                        Some(Token::Ident(name)) => name,
+                        Some(Token::Int(index)) if index >= 0 => index.to_string(),
+                        Some(token) => {
+                            return Err(EvalError::Syntax(format!(
+                                "expected a member name, found {}",
+                                describe(&token)
+                            )))
+                        }
+                        None => return Err(EvalError::Syntax("expected a member name".into())),
+                    };
+                    expr = Expr::Member(Box::new(expr), member);
+                }
+                Some(Token::Punct("[")) => {
+                    self.next += 1;
+                    let index = self.binary(1)?;
+                    self.expect("]")?;
+                    expr = Expr::Index(Box::new(expr), Box::new(index));
+                }
+                // Optional chaining and force unwrapping look through the
+                // optional, which the variable tree has already done.
+                Some(Token::Punct("?" | "!")) if self.language == Language::Swift => self.next += 1,
+                _ => return Ok(expr),
+            }
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr, EvalError> {
+        match self.bump() {
+            Some(Token::Int(value)) => Ok(Expr::Int(value)),
+            Some(Token::Float(value)) => Ok(Expr::Float(value)),
+            Some(Token::Str(text)) => Ok(Expr::Str(text)),
+            Some(Token::Ident(name)) => {
+                Ok(keyword(&name, self.language).unwrap_or(Expr::Name(name)))
+            }
+            Some(Token::Punct("(")) => {
+                let expr = self.binary(1)?;
+                self.expect(")")?;
+                Ok(expr)
+            }
+            Some(token) => Err(EvalError::Syntax(format!(
+                "unexpected {}",
+                describe(&token)
+            ))),
+            None => Err(EvalError::Syntax("the expression ended early".into())),
+        }
+    }
+}
+
+/// Evaluates `text`, parsed in `language`'s dialect, against `scope`.
+/// Computed values are typed in `language`'s spelling (`Int`, `Double`,
+/// `Bool` or `long`, `double`, `bool`) unless both operands share a type.
+pub fn evaluate<S: Scope>(
+    text: &str,
+    language: Language,
+    scope: &mut S,
+) -> Result<Variable, EvalError> {
+    let expr = parse(text, language)?;
+    Evaluator { language, scope }.eval(&expr)
+}
+
+struct Evaluator<'a, S> {
+    language: Language,
+    scope: &'a mut S,
+}
+
+/// A value read as a number or boolean.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Number {
+    Int(i128),
+    Float(f64),
+}
+
+impl Number {
+    fn as_f64(self) -> f64 {
+        match self {
+            Self::Int(value) => value as f64,
+            Self::Float(value) => value,
+        }
+    }
+}
+
+fn failed(message: String) -> EvalError {
+    EvalError::Failed(message)
+}
+
+fn unknown(message: String) -> EvalError {
+    EvalError::Unknown(message)
+}
+
+impl<S: Scope> Evaluator<'_, S> {
+    fn eval(&mut self, expr: &Expr) -> Result<Variable, EvalError> {
+        match expr {
+            Expr::Int(value) => Ok(self.computed(value.to_string(), self.int_type())),
+            Expr::Float(value) => Ok(self.computed(render_float(*value), self.float_type())),
+            Expr::Bool(value) => Ok(self.computed(value.to_string(), self.bool_type())),
+            Expr::Str(text) => Ok(self.computed(format!("{text:?}"), self.string_type())),
+            Expr::Nil => Ok(self.computed("nil".into(), String::new())),
+            Expr::Name(name) => self
+                .scope
+                .variable(name)
+                .ok_or_else(|| unknown(format!("no variable `{name}` in the selected frame"))),
+            Expr::Member(base, member) => {
+                // `self.count` where the receiver's fields are listed as
+                // locals rather than under `self`.
+                if let Expr::Name(receiver) = base.as_ref() {
+                    if self
+                        .language
+                        .implicit_receivers()
+                        .contains(&receiver.as_str())
+                        && self.scope.variable(receiver).is_none()
+                    {
+                        return self.eval(&Expr::Name(member.clone()));
+                    }
+                }
+                let base = self.eval(base)?;
+                if self.language == Language::Swift
+                    && member == "pointee"
+                    && pointee_type(&base.ty).is_some()
+                {
+                    return self.deref(base);
+                }
+                self.child(&base, member, &format!("{}.{member}", base.name))
+            }
+            Expr::Index(base, index) => {
+                let base = self.eval(base)?;
+                let index = self.eval(index)?;
+                let key = match number(&index.value) {
+                    Some(Number::Int(index)) => index.to_string(),
+                    _ => unquote(&index.value).to_string(),
+                };
+                self.element(&base, &key)
+            }
+            Expr::Unary(UnaryOp::Deref, operand) => {
+                let pointer = self.eval(operand)?;
+                self.deref(pointer)
+            }
+            Expr::Unary(op, operand) => {
+                let operand = self.eval(operand)?;
+                match op {
+                    UnaryOp::Not => Ok(self.boolean(!self.truthy(&operand)?)),
+                    _ => match self.number(&operand)? {
+                        Number::Int(value) => Ok(self.computed(
+                            value
+                                .checked_neg()
+                                .ok_or_else(|| failed("integer overflow".into()))?
+                                .to_string(),
+                            operand.ty,
+                        )),
+                        Number::Float(value) => Ok(self.computed(render_float(-value), operand.ty)),
+                    },
+                }
+            }
+            Expr::Binary(op, lhs, rhs) => {
+                let lhs = self.eval(lhs)?;
+                // `&&` and `||` do not evaluate a right side they do not need.
+                match op {
+                    BinaryOp::And if !self.truthy(&lhs)? => return Ok(self.boolean(false)),
+                    BinaryOp::Or if self.truthy(&lhs)? => return Ok(self.boolean(true)),
+                    _ => {}
+                }
+                let rhs = self.eval(rhs)?;
+                self.binary(*op, lhs, rhs)
+            }
+        }
+    }
+
+    fn binary(&self, op: BinaryOp, lhs: Variable, rhs: Variable) -> Result<Variable, EvalError> {
+        match op {
+            BinaryOp::And | BinaryOp::Or => return Ok(self.boolean(self.truthy(&rhs)?)),
+            BinaryOp::Eq | BinaryOp::Ne => {
+                let equal = match (number(&lhs.value), number(&rhs.value)) {
+                    (Some(a), Some(b)) => a.as_f64() == b.as_f64(),
+                    _ if is_nil(&lhs.value) || is_nil(&rhs.value) => {
+                        is_nil(&lhs.value) == is_nil(&rhs.value)
+                    }
+                    _ => unquote(&lhs.value) == unquote(&rhs.value),
+                };
+                return Ok(self.boolean(equal == (op == BinaryOp::Eq)));
+            }
+            BinaryOp::Add if is_quoted(&lhs.value) || is_quoted(&rhs.value) => {
+                let text = format!("{}{}", unquote(&lhs.value), unquote(&rhs.value));
+                return Ok(self.computed(format!("{text:?}"), self.string_type()));
+            }
+            _ => {}
+        }
+        let (a, b) = (self.number(&lhs)?, self.number(&rhs)?);
+        let ordering = |test: fn(f64, f64) -> bool| Ok(self.boolean(test(a.as_f64(), b.as_f64())));
+        match op {
+            BinaryOp::Lt => return ordering(|a, b| a < b),
+            BinaryOp::Le => return ordering(|a, b| a <= b),
+            BinaryOp::Gt => return ordering(|a, b| a > b),
+            BinaryOp::Ge => return ordering(|a, b| a >= b),
+            _ => {}
+        }
+        let same_type = (lhs.ty == rhs.ty && !lhs.ty.is_empty()).then(|| lhs.ty.clone());
+        match (a, b) {
+            (Number::Int(a), Number::Int(b)) => {
+                let value = match op {
+                    BinaryOp::Add => a.checked_add(b),
+                    BinaryOp::Sub => a.checked_sub(b),
+                    BinaryOp::Mul => a.checked_mul(b),
+                    BinaryOp::Div | BinaryOp::Rem if b == 0 => {
+                        return Err(failed("division by zero".into()))
+                    }
+                    BinaryOp::Div => a.checked_div(b),
+                    _ => a.checked_rem(b),
+                }
+                .ok_or_else(|| failed("integer overflow".into()))?;
+                let ty = same_type.unwrap_or_else(|| self.int_type());
+                Ok(self.computed(value.to_string(), ty))
+            }
+            (a, b) => {
+                let (a, b) = (a.as_f64(), b.as_f64());
+                let value = match op {
+                    BinaryOp::Add => a + b,
+                    BinaryOp::Sub => a - b,
+                    BinaryOp::Mul => a * b,
+                    BinaryOp::Div => a / b,
+                    _ => a % b,
+                };
+                let ty = same_type.unwrap_or_else(|| self.float_type());
+                Ok(self.computed(render_float(value), ty))
+            }
+        }
+    }
+
+    /// `member` of `base`: a child of that name, or for pointers with no
+    /// such child, a child of what they point at.
+    fn child(&mut self, base: &Variable, member: &str, path: &str) -> Result<Variable, EvalError> {
+        let children = self.children(base);
+        if let Some(child) = children.iter().find(|child| child.name == member) {
+            return Ok(Variable {
+                name: path.to_string(),
+                ..child.clone()
+            });
+        }
+        if let [pointee] = children.as_slice() {
+            if pointee.variables_reference != 0 {
+                return self.child(pointee, member, path);
+            }
+        }
+        Err(unknown(format!("`{}` has no child `{member}`", base.name)))
+    }
+
+    /// The element at `key` (an index or a dictionary key) of `base`.
+    fn element(&mut self, base: &Variable, key: &str) -> Result<Variable, EvalError> {
+        let children = self.children(base);
+        let names = [
+            format!("[{key}]"),
+            key.to_string(),
+            format!("{key:?}"),
+            format!("[{key:?}]"),
+        ];
+        let found = children
+            .iter()
+            .find(|child| names.contains(&child.name))
+            .or_else(|| {
+                key.parse::<usize>()
+                    .ok()
+                    .and_then(|index| children.get(index))
+            });
+        match found {
+            Some(child) => Ok(Variable {
+                name: format!("{}[{key}]", base.name),
+                ..child.clone()
+            }),
+            None => Err(unknown(format!("`{}` has no child `[{key}]`", base.name))),
+        }
+    }
+
+    fn children(&mut self, variable: &Variable) -> Vec<Variable> {
+        match variable.variables_reference {
+            0 => Vec::new(),
+            reference => self.scope.children(reference),
+        }
+    }
+
+    /// What `pointer` points at: its only child when the variable tree
+    /// expands it, otherwise a scalar read from target memory.
+    fn deref(&mut self, pointer: Variable) -> Result<Variable, EvalError> {
+        let name = format!("*{}", pointer.name);
+        let pointee_type = pointee_type(&pointer.ty);
+        let children = self.children(&pointer);
+        if let [child] = children.as_slice() {
+            return Ok(Variable {
+                name,
+                ..child.clone()
+            });
+        }
+        if !children.is_empty() {
+            return Ok(Variable {
+                name,
+                ty: pointee_type.unwrap_or(&pointer.ty).to_string(),
+                ..pointer
+            });
+        }
+        let ty = pointee_type.ok_or_else(|| {
+            failed(format!(
+                "`{}` is a `{}`, not a pointer",
+                pointer.name, pointer.ty
+            ))
+        })?;
+        let address = pointer_address(&pointer.value)
+            .ok_or_else(|| failed(format!("`{}` holds no address", pointer.name)))?;
+        if address == 0 {
+            return Err(failed(format!("`{}` is a null pointer", pointer.name)));
+        }
+        let layout = scalar_layout(ty).ok_or_else(|| {
+            failed(format!(
+                "cannot dereference `{}`: the size of `{ty}` is unknown",
+                pointer.name
+            ))
+        })?;
+        let bytes = self
+            .scope
+            .read_memory(address, layout.size)
+            .map_err(failed)?;
+        Ok(Variable {
+            name,
+            ty: ty.to_string(),
+            value: layout.decode(&bytes),
+            variables_reference: 0,
+        })
+    }
+
+    fn number(&self, variable: &Variable) -> Result<Number, EvalError> {
+        number(&variable.value).ok_or_else(|| {
+            failed(format!(
+                "`{}` is `{}`, not a number",
+                variable.name, variable.value
+            ))
+        })
+    }
+
+    fn truthy(&self, variable: &Variable) -> Result<bool, EvalError> {
+        if is_nil(&variable.value) {
+            return Ok(false);
+        }
+        match number(&variable.value) {
+            Some(value) => Ok(value.as_f64() != 0.0),
+            None if variable.variables_reference != 0
+                || pointer_address(&variable.value).is_some() =>
+            {
+                Ok(true)
+            }
+            None => Err(failed(format!(
+                "`{}` is `{}`, not a condition",
+                variable.name, variable.value
+            ))),
+        }
+    }
+
+    fn computed(&self, value: String, ty: String) -> Variable {
+        Variable {
+            name: value.clone(),
+            ty,
+            value,
+            variables_reference: 0,
+        }
+    }
+
+    fn boolean(&self, value: bool) -> Variable {
+        self.computed(value.to_string(), self.bool_type())
+    }
+
+    fn int_type(&self) -> String {
+        match self.language {
+            Language::Swift => "Int",
+            _ => "long",
+        }
+        .into()
+    }
+
+    fn float_type(&self) -> String {
+        match self.language {
+            Language::Swift => "Double",
+            _ => "double",
+        }
+        .into()
+    }
+
+    fn bool_type(&self) -> String {
+        match self.language {
+            Language::Swift => "Bool",
+            Language::ObjC => "BOOL",
+            _ => "bool",
+        }
+        .into()
+    }
+
+    fn string_type(&self) -> String {
+        match self.language {
+            Language::Swift => "String",
+            Language::ObjC | Language::ObjCPlusPlus => "NSString *",
+            Language::C => "const char *",
+        }
+        .into()
+    }
+}
+
+/// Reads a rendered value as a number: integers in decimal or hex, floats,
+/// and `true`/`false`/`YES`/`NO` as 1 and 0.
+fn number(value: &str) -> Option<Number> {
+    let value = value.trim();
+    match value {
+        "true" | "YES" => return Some(Number::Int(1)),
+        "false" | "NO" => return Some(Number::Int(0)),
+        _ => {}
+    }
+    match number_token(value.strip_prefix('-').unwrap_or(value))? {
+        Token::Int(magnitude) if value.starts_with('-') => Some(Number::Int(-magnitude)),
+        Token::Int(value) => Some(Number::Int(value)),
+        Token::Float(magnitude) if value.starts_with('-') => Some(Number::Float(-magnitude)),
+        Token::Float(value) => Some(Number::Float(value)),
+        _ => None,
+    }
+}
+
+fn render_float(value: f64) -> String {
+    format!("{value:?}")
+}
+
+fn is_nil(value: &str) -> bool {
+    matches!(value.trim(), "nil" | "NULL" | "nullptr" | "none")
+}
+
+fn is_quoted(value: &str) -> bool {
+    let value = value.trim();
+    value.len() >= 2 && value.starts_with('"') && value.ends_with('"')
+}
+
+fn unquote(value: &str) -> &str {
+    let value = value.trim();
+    if is_quoted(value) {
+        &value[1..value.len() - 1]
+    } else {
+        value
+    }
+}
+
+/// The address a pointer value holds: a bare address or the `0x...` in
+/// `(int *) 0x16fdff0c8`.
+fn pointer_address(value: &str) -> Option<u64> {
+    parse_address(value).or_else(|| {
+        let hex = &value[value.find("0x")? + 2..];
+        let end = hex
+            .find(|c: char| !c.is_ascii_hexdigit())
+            .unwrap_or(hex.len());
+        u64::from_str_radix(&hex[..end], 16).ok()
+    })
+}
+
+/// `int` for `int *`, `Int32` for `UnsafePointer<Int32>`.
+fn pointee_type(ty: &str) -> Option<&str> {
+    let ty = ty.trim();
+    if let Some(inner) = ty.strip_suffix('*') {
+        return Some(inner.trim_end());
+    }
+    let ty = ty.strip_prefix("Swift.").unwrap_or(ty);
+    ["UnsafePointer<", "UnsafeMutablePointer<"]
+        .iter()
+        .find_map(|prefix| ty.strip_prefix(prefix)?.strip_suffix('>'))
+        .map(str::trim)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ScalarKind {
+    Signed,
+    Unsigned,
+    Float,
+    Bool,
+    Pointer,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ScalarLayout {
+    size: usize,
+    kind: ScalarKind,
+}
+
+impl ScalarLayout {
+    /// Renders little-endian `bytes` the way the variable tree would.
+    fn decode(self, bytes: &[u8]) -> String {
+        let mut raw = [0u8; 8];
+        let size = self.size.min(bytes.len()).min(8);
+        raw[..size].copy_from_slice(&bytes[..size]);
+        let unsigned = u64::from_le_bytes(raw);
+        match self.kind {
+            ScalarKind::Bool => (unsigned != 0).to_string(),
+            ScalarKind::Unsigned => unsigned.to_string(),
+            ScalarKind::Pointer => format!("0x{unsigned:x}"),
+            ScalarKind::Signed => {
+                let shift = 64 - 8 * size as u32;
+                (((unsigned << shift) as i64) >> shift).to_string()
+            }
+            ScalarKind::Float if size == 4 => render_float(f32::from_bits(unsigned as u32) as f64),
+            ScalarKind::Float => render_float(f64::from_bits(unsigned)),
+        }
+    }
+}
+
+/// Size and kind of the scalar types a pointer can be dereferenced to.
+fn scalar_layout(ty: &str) -> Option<ScalarLayout> {
+    let ty = ty.trim();
+    if ty.ends_with('*')
+        || ty.starts_with("UnsafePointer<")
+        || ty.starts_with("UnsafeMutablePointer<")
+    {
+        return Some(ScalarLayout {
+            size: 8,
+            kind: ScalarKind::Pointer,
+        });
+    }
+    let ty = ty.strip_prefix("Swift.").unwrap_or(ty);
+    let ty = ty.strip_prefix("const ").unwrap_or(ty);
+    let (size, kind) = match ty {
+        "bool" | "BOOL" | "Bool" | "_Bool" => (1, ScalarKind::Bool),
+        "char" | "signed char" | "int8_t" | "Int8" | "CChar" => (1, ScalarKind::Signed),
+        "unsigned char" | "uint8_t" | "UInt8" => (1, ScalarKind::Unsigned),
+        "short" | "int16_t" | "Int16" | "CShort" => (2, ScalarKind::Signed),
+        "unsigned short" | "uint16_t" | "UInt16" | "unichar" => (2, ScalarKind::Unsigned),
+        "int" | "int32_t" | "Int32" | "CInt" => (4, ScalarKind::Signed),
+        "unsigned int" | "unsigned" | "uint32_t" | "UInt32" | "CUnsignedInt" => {
+            (4, ScalarKind::Unsigned)
+        }
+        "long" | "long long" | "int64_t" | "Int64" | "Int" | "NSInteger" | "ssize_t" | "CLong"
+        | "intptr_t" => (8, ScalarKind::Signed),
+        "unsigned long" | "unsigned long long" | "uint64_t" | "UInt64" | "UInt" | "NSUInteger"
+        | "size_t" | "uintptr_t" => (8, ScalarKind::Unsigned),
+        "float" | "Float" | "Float32" | "CFloat" => (4, ScalarKind::Float),
+        "double" | "Double" | "Float64" | "CDouble" | "CGFloat" => (8, ScalarKind::Float),
+        _ => return None,
+    };
+    Some(ScalarLayout { size, kind })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn var(name: &str, ty: &str, value: &str, reference: i64) -> Variable {
+        Variable {
+            name: name.into(),
+            ty: ty.into(),
+            value: value.into(),
+            variables_reference: reference,
+        }
+    }
+
+    /// `user` with a `name` and an `address.city`, `items` of three ints,
+    /// `scores` keyed by name, `count`, `ratio`, and pointers into `memory`.
+    struct FakeScope {
+        locals: Vec<Variable>,
+        children: HashMap<i64, Vec<Variable>>,
+        memory: HashMap<u64, Vec<u8>>,
+    }
+
+    impl FakeScope {
+        fn new() -> Self {
+            let locals = vec![
+                var("user", "User", "User", 1),
+                var("items", "[Int]", "3 values", 3),
+                var("scores", "[String : Int]", "1 key/value pair", 4),
+                var("count", "Int", "7", 0),
+                var("ratio", "Double", "0.5", 0),
+                var("title", "String", "\"Cart\"", 0),
+                var("flag", "Bool", "false", 0),
+                var("total", "int *", "0x1000", 0),
+                var("empty", "int *", "0x0", 0),
+                var("node", "Node *", "0x2000", 5),
+            ];
+            let children = HashMap::from([
+                (
+                    1,
+                    vec![
+                        var("name", "String", "\"Ada\"", 0),
+                        var("address", "Address", "Address", 2),
+                    ],
+                ),
+                (2, vec![var("city", "String", "\"London\"", 0)]),
+                (
+                    3,
+                    vec![
+                        var("[0]", "Int", "10", 0),
+                        var("[1]", "Int", "20", 0),
+                        var("[2]", "Int", "30", 0),
+                    ],
+                ),
+                (4, vec![var("\"ada\"", "Int", "99", 0)]),
+                (
+                    5,
+                    vec![
+                        var("value", "int", "-4", 0),
+                        var("next", "Node *", "0x0", 0),
+                    ],
+                ),
+            ]);
+            let memory = HashMap::from([(0x1000, (-42i32).to_le_bytes().to_vec())]);
+            Self {
+                locals,
+                children,
+                memory,
+            }
+        }
+    }
+
+    impl Scope for FakeScope {
+        fn variable(&self, name: &str) -> Option<Variable> {
+            self.locals.iter().find(|local| local.name == name).cloned()
+        }
+
+        fn children(&mut self, reference: i64) -> Vec<Variable> {
+            self.children.get(&reference).cloned().unwrap_or_default()
+        }
+
+        fn read_memory(&mut self, address: u64, length: usize) -> Result<Vec<u8>, String> {
+            self.memory
+                .get(&address)
+                .map(|bytes| bytes[..length].to_vec())
+                .ok_or_else(|| format!("cannot read {length} bytes at 0x{address:x}"))
+        }
+    }
+
+    fn eval(text: &str) -> Result<String, EvalError> {
+        eval_in(text, Language::Swift)
+    }
+
+    fn eval_in(text: &str, language: Language) -> Result<String, EvalError> {
+        evaluate(text, language, &mut FakeScope::new()).map(|result| result.value)
+    }
+
+    #[test]
+    fn walks_members_subscripts_and_pointers() {
+        assert_eq!(eval("user.name").unwrap(), "\"Ada\"");
+        assert_eq!(eval("user.address.city").unwrap(), "\"London\"");
+        assert_eq!(eval("self.count").unwrap(), "7");
+        assert_eq!(eval("user?.address!.city").unwrap(), "\"London\"");
+        assert_eq!(eval("items[1]").unwrap(), "20");
+        assert_eq!(eval("items[count - 5]").unwrap(), "30");
+        assert_eq!(eval("scores[\"ada\"]").unwrap(), "99");
+        assert_eq!(eval("total.pointee").unwrap(), "-42");
+        assert_eq!(eval_in("*total", Language::C).unwrap(), "-42");
+        assert_eq!(eval_in("node->value", Language::C).unwrap(), "-4");
+        assert_eq!(eval_in("node->next", Language::ObjC).unwrap(), "0x0");
+
+        let result = evaluate("user.address", Language::Swift, &mut FakeScope::new()).unwrap();
+        assert_eq!(result.name, "user.address");
+        assert_eq!(result.variables_reference, 2);
+    }
+
+    #[test]
+    fn computes_arithmetic_and_comparisons() {
+        assert_eq!(eval("count * 2 + items[0]").unwrap(), "24");
+        assert_eq!(eval("(count + 1) % 3").unwrap(), "2");
+        assert_eq!(eval("-count").unwrap(), "-7");
+        assert_eq!(eval("ratio * 3").unwrap(), "1.5");
+        assert_eq!(eval("count / 2").unwrap(), "3");
+        assert_eq!(eval("0x10 + 1").unwrap(), "17");
+        assert_eq!(eval("count > 5 && !flag").unwrap(), "true");
+        assert_eq!(eval("user.name == \"Ada\"").unwrap(), "true");
+        assert_eq!(eval("title + \"!\"").unwrap(), "\"Cart!\"");
+        assert_eq!(eval("node != nil").unwrap(), "true");
+        assert_eq!(
+            eval("flag || missing").unwrap_err(),
+            unknown("no variable `missing` in the selected frame".into())
+        );
+        assert_eq!(eval("count > 5 || missing").unwrap(), "true");
+
+        let sum = evaluate("count + count", Language::C, &mut FakeScope::new()).unwrap();
+        assert_eq!(sum.ty, "Int");
+        let mixed = evaluate("1 + 2", Language::C, &mut FakeScope::new()).unwrap();
+        assert_eq!(mixed.ty, "long");
+    }
+
+    #[test]
+    fn reports_what_went_wrong() {
+        assert_eq!(
+            eval("count / 0").unwrap_err(),
+            failed("division by zero".into())
+        );
+        assert_eq!(
+            eval("user.age").unwrap_err(),
+            unknown("`user` has no child `age`".into())
+        );
+        assert_eq!(
+            eval("items[5]").unwrap_err(),
+            unknown("`items` has no child `[5]`".into())
+        );
+        assert_eq!(
+            eval("empty.pointee").unwrap_err(),
+            failed("`empty` is a null pointer".into())
+        );
+        assert_eq!(
+            eval_in("*count", Language::C).unwrap_err(),
+            failed("`count` is a `Int`, not a pointer".into())
+        );
+        assert!(matches!(eval("count +"), Err(EvalError::Syntax(_))));
+        assert!(matches!(eval("items[0"), Err(EvalError::Syntax(_))));
+        assert!(matches!(eval("count count"), Err(EvalError::Syntax(_))));
+        assert!(matches!(eval("a # b"), Err(EvalError::Syntax(_))));
+    }
+
+    #[test]
+    fn parses_each_language_in_its_own_dialect() {
+        for (text, language, value) in [
+            ("node != nil", Language::Swift, "true"),
+            ("node != NULL", Language::C, "true"),
+            ("node != nullptr", Language::ObjCPlusPlus, "true"),
+            ("flag == NO", Language::ObjC, "true"),
+            ("title == @\"Cart\"", Language::ObjC, "true"),
+            ("user?.address!.city", Language::Swift, "\"London\""),
+        ] {
+            assert_eq!(eval_in(text, language).unwrap(), value, "{text}");
+        }
+
+        for (text, language) in [
+            ("node->value", Language::Swift),
+            ("*total", Language::Swift),
+            ("user?.name", Language::C),
+            ("title == @\"Cart\"", Language::Swift),
+        ] {
+            assert!(
+                matches!(eval_in(text, language), Err(EvalError::Syntax(_))),
+                "{text}"
+            );
+        }
+        // Another language's keywords are plain names.
+        assert_eq!(
+            eval_in("node != nil", Language::C).unwrap_err(),
+            unknown("no variable `nil` in the selected frame".into())
+        );
+        assert_eq!(
+            eval("flag == NO").unwrap_err(),
+            unknown("no variable `NO` in the selected frame".into())
+        );
+    }
+}
@@ -0,0 +1,110 @@
+//! Developer fault injection (`ios_llm_api --fault-inject`).
+//!
+//! A shared [`FaultInjector`] is armed through the admin endpoint and consulted
+//! by the gdb-remote client (disconnects, slow packets) and the bridge
+//! controller (devicectl failures), so retry logic can be exercised without
+//! unplugging a device.
+
+use std::{
+    sync::atomic::{AtomicU32, AtomicU64, Ordering},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+/// Faults to inject. Counters are consumed one per injected failure.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FaultPlan {
+    /// Drop the debugserver connection on the next N packets.
+    #[serde(default)]
+    pub disconnects: u32,
+    /// Delay every outgoing packet by this many milliseconds.
+    #[serde(default)]
+    pub packet_delay_ms: u64,
+    /// Fail the next N bridge (devicectl) launches.
+    #[serde(default)]
+    pub devicectl_failures: u32,
+}
+
+#[derive(Debug, Default)]
+pub struct FaultInjector {
+    disconnects: AtomicU32,
+    packet_delay_ms: AtomicU64,
+    devicectl_failures: AtomicU32,
+}
+
+impl FaultInjector {
+    /// Replaces the armed faults with `plan`; an empty plan disarms everything.
+    pub fn configure(&self, plan: &FaultPlan) {
+        self.disconnects.store(plan.disconnects, Ordering::SeqCst);
+        self.packet_delay_ms
+            .store(plan.packet_delay_ms, Ordering::SeqCst);
+        self.devicectl_failures
+            .store(plan.devicectl_failures, Ordering::SeqCst);
+    }
+
+    /// Faults still armed.
+    pub fn plan(&self) -> FaultPlan {
+        FaultPlan {
+            disconnects: self.disconnects.load(Ordering::SeqCst),
+            packet_delay_ms: self.packet_delay_ms.load(Ordering::SeqCst),
+            devicectl_failures: self.devicectl_failures.load(Ordering::SeqCst),
+        }
+    }
+
+    pub fn packet_delay(&self) -> Option<Duration> {
+        match self.packet_delay_ms.load(Ordering::SeqCst) {
+            0 => None,
+            millis => Some(Duration::from_millis(millis)),
+        }
+    }
+
+    /// Consumes one armed disconnect, if any.
+    pub fn take_disconnect(&self) -> bool {
+        take(&self.disconnects)
+    }
+
+    /// Consumes one armed devicectl failure, if any.
+    pub fn take_devicectl_failure(&self) -> bool {
+        take(&self.devicectl_failures)
+    }
+}
+
+fn take(counter: &AtomicU32) -> bool {
+    counter
+        .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |left| {
+            left.checked_sub(1)
+        })
+        .is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counters_are_consumed_once_each() {
+        let faults = FaultInjector::default();
+        assert!(!faults.take_disconnect());
+        faults.configure(&FaultPlan {
+            disconnects: 2,
+            packet_delay_ms: 150,
+            devicectl_failures: 1,
+        });
+        assert!(faults.take_disconnect());
+        assert!(faults.take_devicectl_failure());
+        assert!(!faults.take_devicectl_failure());
+        assert_eq!(
+            faults.plan(),
+            FaultPlan {
+                disconnects: 1,
+                packet_delay_ms: 150,
+                devicectl_failures: 0,
+            }
+        );
+        assert_eq!(faults.packet_delay(), Some(Duration::from_millis(150)));
+        faults.configure(&FaultPlan::default());
+        assert_eq!(faults.packet_delay(), None);
+        assert!(!faults.take_disconnect());
+    }
+}
@@ -0,0 +1,536 @@
+//! Summaries of Swift standard-library and Foundation values.
+//!
+//! A [`FormatterRegistry`] maps a type's base name (`String`, `Array`,
+//! `Optional`, ...) to a provider that renders a value of it from its bytes,
+//! reading whatever storage the value points at through a memory reader:
+//! `"hello" (String, 5 chars)` rather than two raw words. Type names are
+//! matched after demangling and with generic sugar expanded, so `$sSaySiGD`,
+//! `[Int]` and `Swift.Array<Swift.Int>` all reach the `Array` provider.
+//!
+//! The layouts are those of the Swift 5 runtime on 64-bit Apple platforms.
+//! Values that don't fit them (bridged Objective-C storage, tagged pointers)
+//! get no summary, and callers fall back to the raw value.
+
+use std::collections::HashMap;
+
+use crate::memory::POINTER_MASK;
+use crate::swift_demangle;
+
+/// Reads `length` bytes of target memory at an address.
+pub type MemoryReader<'a> = dyn FnMut(u64, usize) -> Option<Vec<u8>> + 'a;
+
+/// Renders a value from its bytes. Receives the registry, for generic
+/// arguments that need their own summary, and the type's generic arguments.
+pub type SummaryProvider =
+    fn(&FormatterRegistry, &[String], &[u8], &mut MemoryReader<'_>) -> Option<String>;
+
+/// Longest string contents read for a summary.
+const MAX_SUMMARY_BYTES: usize = 1024;
+/// Counts above this mean the storage pointer was not what it seemed.
+const MAX_PLAUSIBLE_COUNT: u64 = 1 << 40;
+
+#[derive(Default)]
+pub struct FormatterRegistry {
+    providers: HashMap<String, SummaryProvider>,
+}
+
+impl FormatterRegistry {
+    /// A registry with the standard-library and Foundation providers.
+    pub fn swift() -> Self {
+        let mut registry = Self::default();
+        registry.register("String", string_summary);
+        registry.register("Array", array_summary);
+        registry.register("ContiguousArray", array_summary);
+        registry.register("Dictionary", dictionary_summary);
+        registry.register("Set", set_summary);
+        registry.register("Optional", optional_summary);
+        registry.register("Data", data_summary);
+        registry.register("URL", url_summary);
+        registry
+    }
+
+    /// Adds or replaces the provider for the base type name `name`.
+    pub fn register(&mut self, name: &str, provider: SummaryProvider) {
+        self.providers.insert(name.to_string(), provider);
+    }
+
+    /// Whether a provider is registered for `type_name`.
+    pub fn handles(&self, type_name: &str) -> bool {
+        self.providers.contains_key(&parse_type(type_name).0)
+    }
+
+    /// Summary of a `type_name` value held in `bytes`.
+    pub fn summary(
+        &self,
+        type_name: &str,
+        bytes: &[u8],
+        read: &mut MemoryReader<'_>,
+    ) -> Option<String> {
+        let (base, args) = parse_type(type_name);
+        let provider = self.providers.get(&base)?;
+        provider(self, &args, bytes, read)
+    }
+}
+
+/// `name` as its base name and generic arguments, demangled, with sugar
+/// expanded and the `Swift.` and `Foundation.` modules dropped:
+/// `[String : Int]` is `Dictionary` of `String` and `Int`.
+fn parse_type(name: &str) -> (String, Vec<String>) {
+    let demangled = swift_demangle::is_mangled(name)
+        .then(|| swift_demangle::demangle(name))
+        .flatten();
+    let name = demangled.as_deref().unwrap_or(name).trim();
+    if let Some(wrapped) = name.strip_suffix('?') {
+        let wrapped = wrapped
+            .strip_prefix('(')
+            .and_then(|inner| inner.strip_suffix(')'))
+            .unwrap_or(wrapped);
+        return ("Optional".into(), vec![wrapped.to_string()]);
+    }
+    if let Some(inner) = name
+        .strip_prefix('[')
+        .and_then(|rest| rest.strip_suffix(']'))
+    {
+        return match split_top_level(inner, " : ").as_slice() {
+            [key, value] => ("Dictionary".into(), vec![key.clone(), value.clone()]),
+            _ => ("Array".into(), vec![inner.to_string()]),
+        };
+    }
+    let (base, args) = match name.split_once('<') {
+        Some((base, rest)) if rest.ends_with('>') => {
+            (base, split_top_level(&rest[..rest.len() - 1], ","))
+        }
+        _ => (name, Vec::new()),
+    };
+    let base = ["Swift.", "Foundation."]
+        .iter()
+        .find_map(|module| base.strip_prefix(module))
+        .unwrap_or(base);
+    (base.to_string(), args)
+}
+
+/// `text` split at `separator` where it is not nested in brackets.
+fn split_top_level(text: &str, separator: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut depth = 0i32;
+    let mut start = 0;
+    let mut i = 0;
+    while i < text.len() {
+        match text.as_bytes()[i] {
+            b'<' | b'[' | b'(' => depth += 1,
+            b'>' | b']' | b')' => depth -= 1,
+            _ if depth == 0 && text[i..].starts_with(separator) => {
+                parts.push(text[start..i].trim().to_string());
+                i += separator.len();
+                start = i;
+                continue;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    parts.push(text[start..].trim().to_string());
+    parts
+}
+
+fn word(bytes: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(
+        bytes.get(offset..offset + 8)?.try_into().ok()?,
+    ))
+}
+
+fn read_word(read: &mut MemoryReader<'_>, address: u64) -> Option<u64> {
+    word(&read(address, 8)?, 0)
+}
+
+fn plural(count: u64, singular: &str, plural: &str) -> String {
+    if count == 1 {
+        format!("1 {singular}")
+    } else {
+        format!("{count} {plural}")
+    }
+}
+
+/// The contents of a Swift `String`: its text, cut at
+/// [`MAX_SUMMARY_BYTES`], and its length in UTF-8 bytes.
+fn read_string(bytes: &[u8], read: &mut MemoryReader<'_>) -> Option<(String, usize)> {
+    let count_and_flags = word(bytes, 0)?;
+    let object = word(bytes, 8)?;
+    let discriminator = object >> 60;
+    // Small strings keep up to 15 UTF-8 bytes in the value itself, with the
+    // count in the low nibble of the top byte.
+    if discriminator & 0b0010 != 0 {
+        let count = ((object >> 56) & 0xf) as usize;
+        let text = std::str::from_utf8(bytes.get(..count)?).ok()?;
+        return Some((text.to_string(), count));
+    }
+    // Bridged and foreign strings live in Objective-C objects.
+    if discriminator & 0b0101 != 0 {
+        return None;
+    }
+    let count = (count_and_flags & 0x0000_ffff_ffff_ffff) as usize;
+    let length = count.min(MAX_SUMMARY_BYTES);
+    let raw = if length == 0 {
+        Vec::new()
+    } else {
+        // Native storage and literals are both addressed 32 bytes before
+        // their first character.
+        read((object & 0x0fff_ffff_ffff_ffff) + 32, length)?
+    };
+    Some((String::from_utf8_lossy(&raw).into_owned(), count))
+}
+
+fn string_summary(
+    _: &FormatterRegistry,
+    _: &[String],
+    bytes: &[u8],
+    read: &mut MemoryReader<'_>,
+) -> Option<String> {
+    let (text, count) = read_string(bytes, read)?;
+    if count > MAX_SUMMARY_BYTES {
+        return Some(format!("{text:?}… (String, {count} bytes)"));
+    }
+    let chars = text.chars().count() as u64;
+    Some(format!(
+        "{text:?} (String, {})",
+        plural(chars, "char", "chars")
+    ))
+}
+
+/// `count` of a native array, dictionary or set: the storage object's first
+/// field after its isa and reference count.
+fn storage_count(bytes: &[u8], read: &mut MemoryReader<'_>) -> Option<u64> {
+    let storage = word(bytes, 0)? & POINTER_MASK;
+    if storage == 0 {
+        return None;
+    }
+    let count = read_word(read, storage + 16)?;
+    (count <= MAX_PLAUSIBLE_COUNT).then_some(count)
+}
+
+fn array_summary(
+    _: &FormatterRegistry,
+    _: &[String],
+    bytes: &[u8],
+    read: &mut MemoryReader<'_>,
+) -> Option<String> {
+    let count = storage_count(bytes, read)?;
+    Some(format!("[{}]", plural(count, "element", "elements")))
+}
+
+fn set_summary(
+    _: &FormatterRegistry,
+    _: &[String],
+    bytes: &[u8],
+    read: &mut MemoryReader<'_>,
+) -> Option<String> {
+    let count = storage_count(bytes, read)?;
+    Some(format!("[{}]", plural(count, "member", "members")))
+}
+
+fn dictionary_summary(
+    _: &FormatterRegistry,
+    _: &[String],
+    bytes: &[u8],
+    read: &mut MemoryReader<'_>,
+) -> Option<String> {
+    let count = storage_count(bytes, read)?;
+    Some(format!(
+        "[{}]",
+        plural(count, "key/value pair", "key/value pairs")
+    ))
+}
+
+/// Size of the payload of the scalar types `Optional` adds a tag byte to.
+fn tagged_payload_size(name: &str) -> Option<usize> {
+    match name.strip_prefix("Swift.").unwrap_or(name) {
+        "Int" | "UInt" | "Int64" | "UInt64" | "Double" => Some(8),
+        "Int32" | "UInt32" | "Float" => Some(4),
+        "Int16" | "UInt16" => Some(2),
+        "Int8" | "UInt8" => Some(1),
+        _ => None,
+    }
+}
+
+fn scalar(name: &str, bytes: &[u8]) -> Option<String> {
+    let name = name.strip_prefix("Swift.").unwrap_or(name);
+    let mut padded = [0u8; 8];
+    let size = bytes.len().min(8);
+    padded[..size].copy_from_slice(&bytes[..size]);
+    let raw = u64::from_le_bytes(padded);
+    let shift = 64 - size * 8;
+    Some(match name {
+        "Bool" => (raw & 1 == 1).to_string(),
+        "Double" => f64::from_bits(raw).to_string(),
+        "Float" => f32::from_bits(raw as u32).to_string(),
+        "Int" | "Int64" | "Int32" | "Int16" | "Int8" => {
+            (((raw << shift) as i64) >> shift).to_string()
+        }
+        "UInt" | "UInt64" | "UInt32" | "UInt16" | "UInt8" => raw.to_string(),
+        _ => return None,
+    })
+}
+
+/// `nil`, or the wrapped value. Scalars carry a trailing tag byte that is
+/// set for `nil`; `Bool` uses the spare value 2; the other types use the
+/// all-zero bit pattern, which no valid instance has.
+fn optional_summary(
+    registry: &FormatterRegistry,
+    args: &[String],
+    bytes: &[u8],
+    read: &mut MemoryReader<'_>,
+) -> Option<String> {
+    let wrapped = args.first()?;
+    let (payload, is_nil) = match tagged_payload_size(wrapped) {
+        Some(size) => (
+            bytes.get(..size)?,
+            bytes.get(size).is_some_and(|tag| *tag != 0),
+        ),
+        None if parse_type(wrapped).0 == "Bool" => (bytes, bytes.first() == Some(&2)),
+        None => (bytes, bytes.iter().all(|byte| *byte == 0)),
+    };
+    if is_nil {
+        return Some("nil".into());
+    }
+    registry
+        .summary(wrapped, payload, read)
+        .or_else(|| scalar(wrapped, payload))
+}
+
+/// `Data` is an enum of inline bytes, a slice of shared storage, a large
+/// slice and the empty value; the case is in the top two bits of its second
+/// word.
+fn data_summary(
+    _: &FormatterRegistry,
+    _: &[String],
+    bytes: &[u8],
+    read: &mut MemoryReader<'_>,
+) -> Option<String> {
+    let count = match word(bytes, 8)? >> 62 {
+        0 => u64::from(*bytes.get(14)?),
+        1 => {
+            let lower = i32::from_le_bytes(bytes.get(0..4)?.try_into().ok()?);
+            let upper = i32::from_le_bytes(bytes.get(4..8)?.try_into().ok()?);
+            u64::try_from(upper.checked_sub(lower)?).ok()?
+        }
+        2 => {
+            let range = word(bytes, 0)? & POINTER_MASK;
+            let lower = read_word(read, range + 16)?;
+            let upper = read_word(read, range + 24)?;
+            upper.checked_sub(lower)?
+        }
+        _ => 0,
+    };
+    (count <= MAX_PLAUSIBLE_COUNT).then(|| plural(count, "byte", "bytes"))
+}
+
+/// `URL` wraps an `NSURL`, whose `CFURL` layout keeps the string it was
+/// created from right after the runtime header, flags and encoding.
+fn url_summary(
+    _: &FormatterRegistry,
+    _: &[String],
+    bytes: &[u8],
+    read: &mut MemoryReader<'_>,
+) -> Option<String> {
+    let url = word(bytes, 0)? & POINTER_MASK;
+    if url == 0 {
+        return None;
+    }
+    let string = read_word(read, url + 24)?;
+    Some(format!("{:?}", read_cf_string(string, read)?))
+}
+
+/// `CFString` info bits, in the byte after the isa.
+const CF_STRING_MUTABLE: u8 = 0x01;
+const CF_STRING_HAS_LENGTH_BYTE: u8 = 0x04;
+const CF_STRING_UNICODE: u8 = 0x10;
+const CF_STRING_CONTENTS_MASK: u8 = 0x60;
+
+/// The text of a `CFString` (or toll-free bridged `NSString`) at `address`.
+/// Tagged pointer strings have no storage to read and give `None`.
+fn read_cf_string(address: u64, read: &mut MemoryReader<'_>) -> Option<String> {
+    if address >> 63 == 1 || address & POINTER_MASK == 0 {
+        return None;
+    }
+    let address = address & POINTER_MASK;
+    let header = read(address, 32)?;
+    let info = *header.get(8)?;
+    let explicit_length =
+        info & (CF_STRING_MUTABLE | CF_STRING_HAS_LENGTH_BYTE) != CF_STRING_HAS_LENGTH_BYTE;
+    let (contents, length) = match (info & CF_STRING_CONTENTS_MASK == 0, explicit_length) {
+        (true, true) => (address + 24, Some(word(&header, 16)?)),
+        (true, false) => (address + 16, None),
+        (false, true) => (word(&header, 16)?, Some(word(&header, 24)?)),
+        (false, false) => (word(&header, 16)?, None),
+    };
+    let (contents, length) = match length {
+        Some(length) => (contents, length),
+        None => (contents + 1, u64::from(*read(contents, 1)?.first()?)),
+    };
+    let unicode = info & CF_STRING_UNICODE != 0;
+    let width = if unicode { 2 } else { 1 };
+    let length = usize::try_from(length).ok()?.min(MAX_SUMMARY_BYTES / width);
+    if length == 0 {
+        return Some(String::new());
+    }
+    let raw = read(contents, length * width)?;
+    if unicode {
+        let units: Vec<u16> = raw
+            .chunks_exact(2)
+            .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+            .collect();
+        Some(String::from_utf16_lossy(&units))
+    } else {
+        Some(raw.iter().map(|byte| char::from(*byte)).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A reader over `(address, bytes)` blocks.
+    fn memory(blocks: Vec<(u64, Vec<u8>)>) -> impl FnMut(u64, usize) -> Option<Vec<u8>> {
+        move |address, length| {
+            blocks.iter().find_map(|(start, bytes)| {
+                let offset = usize::try_from(address.checked_sub(*start)?).ok()?;
+                Some(bytes.get(offset..offset + length)?.to_vec())
+            })
+        }
+    }
+
+    fn words(values: &[u64]) -> Vec<u8> {
+        values
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect()
+    }
+
+    #[test]
+    fn type_names_are_demangled_and_desugared() {
+        assert_eq!(
+            parse_type("$sSaySiGD"),
+            ("Array".into(), vec!["Int".into()])
+        );
+        assert_eq!(
+            parse_type("[String : [Int]]"),
+            ("Dictionary".into(), vec!["String".into(), "[Int]".into()])
+        );
+        assert_eq!(
+            parse_type("Swift.Optional<Swift.String>"),
+            ("Optional".into(), vec!["Swift.String".into()])
+        );
+        assert_eq!(parse_type("Int?"), ("Optional".into(), vec!["Int".into()]));
+        assert_eq!(parse_type("Foundation.URL"), ("URL".into(), Vec::new()));
+        let registry = FormatterRegistry::swift();
+        assert!(registry.handles("Set<Int>"));
+        assert!(!registry.handles("MyApp.Cart"));
+    }
+
+    #[test]
+    fn strings_summarize_small_and_native_storage() {
+        let registry = FormatterRegistry::swift();
+        let mut small = b"hello".to_vec();
+        small.resize(15, 0);
+        small.push(0xe5);
+        let mut read = memory(Vec::new());
+        assert_eq!(
+            registry.summary("String", &small, &mut read).as_deref(),
+            Some("\"hello\" (String, 5 chars)")
+        );
+
+        let text = "héllo, world!!!!";
+        let native = words(&[text.len() as u64, 0x1000 - 32]);
+        let mut read = memory(vec![(0x1000, text.as_bytes().to_vec())]);
+        assert_eq!(
+            registry
+                .summary("Swift.String", &native, &mut read)
+                .as_deref(),
+            Some("\"héllo, world!!!!\" (String, 16 chars)")
+        );
+        let bridged = words(&[5, 0x4000_0000_0000_1000]);
+        assert_eq!(registry.summary("String", &bridged, &mut read), None);
+    }
+
+    #[test]
+    fn collections_data_and_optionals_summarize() {
+        let registry = FormatterRegistry::swift();
+        let mut read = memory(vec![
+            (0x2000, words(&[0xdead, 2, 3, 4])),
+            (0x3000, words(&[0xbeef, 2, 1, 2])),
+            (0x4000, words(&[0, 2, 10, 25])),
+        ]);
+        let array = words(&[0x2000]);
+        assert_eq!(
+            registry.summary("[Int]", &array, &mut read).as_deref(),
+            Some("[3 elements]")
+        );
+        let dictionary = words(&[0x3000]);
+        assert_eq!(
+            registry
+                .summary("[String : Int]", &dictionary, &mut read)
+                .as_deref(),
+            Some("[1 key/value pair]")
+        );
+        assert_eq!(
+            registry
+                .summary("Set<Int>", &dictionary, &mut read)
+                .as_deref(),
+            Some("[1 member]")
+        );
+
+        let mut inline = vec![0u8; 16];
+        inline[14] = 5;
+        assert_eq!(
+            registry.summary("Data", &inline, &mut read).as_deref(),
+            Some("5 bytes")
+        );
+        let large = words(&[0x4000, 0x8000_0000_0000_5000]);
+        assert_eq!(
+            registry
+                .summary("Foundation.Data", &large, &mut read)
+                .as_deref(),
+            Some("15 bytes")
+        );
+
+        let mut some = 42i64.to_le_bytes().to_vec();
+        some.push(0);
+        assert_eq!(
+            registry.summary("Int?", &some, &mut read).as_deref(),
+            Some("42")
+        );
+        some[8] = 1;
+        assert_eq!(
+            registry.summary("Int?", &some, &mut read).as_deref(),
+            Some("nil")
+        );
+        assert_eq!(
+            registry.summary("[Int]?", &array, &mut read).as_deref(),
+            Some("[3 elements]")
+        );
+        assert_eq!(
+            registry
+                .summary("Optional<String>", &[0; 16], &mut read)
+                .as_deref(),
+            Some("nil")
+        );
+    }
+
+    #[test]
+    fn urls_read_their_cf_string() {
+        let registry = FormatterRegistry::swift();
+        let url = "https://example.com";
+        let mut cf_string = words(&[0xc1a55, 0, url.len() as u64]);
+        cf_string.extend_from_slice(url.as_bytes());
+        let mut read = memory(vec![
+            (0x5000, words(&[0xc1a55, 0, 0, 0x6000])),
+            (0x6000, cf_string),
+        ]);
+        assert_eq!(
+            registry
+                .summary("URL", &words(&[0x5000]), &mut read)
+                .as_deref(),
+            Some("\"https://example.com\"")
+        );
+    }
+}
@@ -1,22 +1,59 @@
 use std::{
     fmt,
     io::{self, Read, Write},
-    net::TcpStream,
-    time::Duration,
+    net::{Shutdown, TcpStream},
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
 };
 
+use serde_json::Value;
 use thiserror::Error;
 
+use crate::{
+    fault::FaultInjector,
+    protocol_trace::{Direction, ProtocolTrace},
+};
+
+/// Largest payload requested per `m` packet; debugserver rejects very large reads.
+pub const MAX_MEMORY_CHUNK: usize = 0x400;
+
+/// Times a packet is sent again after a NAK, or asked for again after it
+/// arrived corrupted, before the exchange is given up on.
+pub const MAX_RETRANSMITS: u32 = 3;
+
+/// Errors of the link itself (framing, checksums, acks) start with this,
+/// unlike the stub refusing a well-formed command.
+pub const PROTOCOL_ERROR: &str = "protocol error";
+
 #[derive(Debug, Error)]
 pub enum GdbRemoteError {
     #[error("I/O: {0}")]
     Io(#[from] io::Error),
     #[error("remote rejected packet: {0}")]
     Remote(String),
-    #[error("invalid packet checksum")]
+    #[error("{PROTOCOL_ERROR}: invalid packet checksum")]
     BadChecksum,
+    /// A broken exchange: a stray byte instead of an ack, or a packet the
+    /// peer kept refusing.
+    #[error("{PROTOCOL_ERROR}: {0}")]
+    Protocol(String),
     #[error("unexpected reply: {0}")]
     UnexpectedReply(String),
+    #[error("peer does not speak gdb-remote: {0}")]
+    Handshake(String),
+    #[error("process {0}")]
+    Exited(ProcessExit),
+    #[error("deadline exceeded")]
+    DeadlineExceeded,
+}
+
+impl GdbRemoteError {
+    /// Whether the link failed rather than the command: the stub may never
+    /// have seen the packet, so the connection is suspect.
+    pub fn is_protocol(&self) -> bool {
+        matches!(self, Self::BadChecksum | Self::Protocol(_))
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -24,6 +61,90 @@ pub struct StopReply {
     pub signal: u8,
     pub thread_id: Option<u64>,
     pub reason: StopReason,
+    /// Mach exception behind the stop, from debugserver's `metype`/`medata`.
+    pub exception: Option<MachException>,
+}
+
+/// A Mach exception as debugserver reports it: the `exception_type_t` and
+/// its code words, usually a code and a subcode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MachException {
+    pub exception_type: u32,
+    pub data: Vec<u64>,
+}
+
+impl MachException {
+    pub const BAD_ACCESS: u32 = 1;
+
+    /// `EXC_BAD_ACCESS`, `EXC_BREAKPOINT`, ... for the types `<mach/exception_types.h>` defines.
+    pub fn name(&self) -> Option<&'static str> {
+        Some(match self.exception_type {
+            1 => "EXC_BAD_ACCESS",
+            2 => "EXC_BAD_INSTRUCTION",
+            3 => "EXC_ARITHMETIC",
+            4 => "EXC_EMULATION",
+            5 => "EXC_SOFTWARE",
+            6 => "EXC_BREAKPOINT",
+            7 => "EXC_SYSCALL",
+            8 => "EXC_MACH_SYSCALL",
+            9 => "EXC_RPC_ALERT",
+            10 => "EXC_CRASH",
+            11 => "EXC_RESOURCE",
+            12 => "EXC_GUARD",
+            13 => "EXC_CORPSE_NOTIFY",
+            _ => return None,
+        })
+    }
+
+    pub fn code(&self) -> Option<u64> {
+        self.data.first().copied()
+    }
+
+    pub fn subcode(&self) -> Option<u64> {
+        self.data.get(1).copied()
+    }
+
+    /// The address that could not be accessed, for `EXC_BAD_ACCESS`.
+    pub fn fault_address(&self) -> Option<u64> {
+        (self.exception_type == Self::BAD_ACCESS)
+            .then(|| self.subcode())
+            .flatten()
+    }
+}
+
+/// Reads like LLDB's stop description: `EXC_BAD_ACCESS (code=1, address=0x0)`.
+impl fmt::Display for MachException {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name() {
+            Some(name) => f.write_str(name)?,
+            None => write!(f, "exception {}", self.exception_type)?,
+        }
+        let Some(code) = self.code() else {
+            return Ok(());
+        };
+        write!(f, " (code={code}")?;
+        match (self.fault_address(), self.subcode()) {
+            (Some(address), _) => write!(f, ", address={address:#x})"),
+            (None, Some(subcode)) => write!(f, ", subcode={subcode:#x})"),
+            (None, None) => f.write_str(")"),
+        }
+    }
+}
+
+/// How the inferior ended, from a `W`/`X` reply.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProcessExit {
+    Exited(u8),
+    Signaled(u8),
+}
+
+impl fmt::Display for ProcessExit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Exited(code) => write!(f, "exited with status {code}"),
+            Self::Signaled(signal) => write!(f, "terminated by signal {signal}"),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -34,10 +155,95 @@ pub enum StopReason {
     Unknown(String),
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegisterInfo {
+    pub number: u32,
+    pub name: String,
+    pub alt_name: Option<String>,
+    pub generic: Option<String>,
+    pub bitsize: u32,
+    /// Byte offset of the register within the `g` packet.
+    pub offset: Option<usize>,
+    pub set: Option<String>,
+}
+
+impl RegisterInfo {
+    pub fn matches(&self, name: &str) -> bool {
+        self.name == name
+            || self.alt_name.as_deref() == Some(name)
+            || self.generic.as_deref() == Some(name)
+    }
+}
+
+/// Kernel scheduling state of a thread, from `qThreadExtraInfo`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThreadRunState {
+    Running,
+    /// Waiting on a lock, I/O or a Mach message.
+    Blocked,
+    Suspended,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RemoteThread {
+    pub id: u64,
+    pub name: Option<String>,
+    /// GCD dispatch queue label.
+    pub queue: Option<String>,
+    pub run_state: Option<ThreadRunState>,
+}
+
+/// An image loaded in the inferior, as reported by dyld.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoadedImage {
+    pub path: String,
+    pub load_address: u64,
+    pub uuid: Option<String>,
+    /// Link-time address of `__TEXT`, when known, for computing the slide.
+    pub text_vmaddr: Option<u64>,
+}
+
+/// One `qMemoryRegionInfo` reply. Unmapped gaps have empty `permissions`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MemoryRegion {
+    pub start: u64,
+    pub size: u64,
+    /// Some of `r`, `w` and `x`.
+    pub permissions: String,
+    /// File mapped into the region.
+    pub name: Option<String>,
+    /// debugserver's classification, e.g. `stack` or `heap`.
+    pub region_type: Option<String>,
+}
+
+/// Sends `^C` on a client's connection from another thread, while the client
+/// itself is busy waiting for a stop.
+#[derive(Debug)]
+pub struct Interrupter(TcpStream);
+
+impl Interrupter {
+    pub fn interrupt(&self) -> Result<(), GdbRemoteError> {
+        let mut stream = &self.0;
+        stream.write_all(&[0x03])?;
+        stream.flush()?;
+        Ok(())
+    }
+}
+
 pub struct GdbRemoteClient {
     stream: TcpStream,
     pub port: u16,
     pub no_ack_mode: bool,
+    register_infos: Option<Vec<RegisterInfo>>,
+    binary_writes: bool,
+    faults: Option<Arc<FaultInjector>>,
+    trace: Option<Arc<ProtocolTrace>>,
+    /// Inferior stdout/stderr from `O` packets, not yet taken.
+    console_output: Vec<u8>,
+    /// Packets are refused once this passes; see [`set_deadline`](Self::set_deadline).
+    deadline: Option<Instant>,
+    /// How the inferior ended, once a stop wait saw it exit.
+    exit: Option<ProcessExit>,
 }
 
 impl fmt::Debug for GdbRemoteClient {
@@ -51,6 +257,15 @@ impl fmt::Debug for GdbRemoteClient {
 
 impl GdbRemoteClient {
     pub fn connect(port: u16) -> Result<Self, GdbRemoteError> {
+        Self::connect_traced(port, None)
+    }
+
+    /// Like [`connect`](Self::connect), recording every packet (including the
+    /// handshake) to `trace`.
+    pub fn connect_traced(
+        port: u16,
+        trace: Option<Arc<ProtocolTrace>>,
+    ) -> Result<Self, GdbRemoteError> {
         let stream = TcpStream::connect(("127.0.0.1", port))?;
         stream.set_read_timeout(Some(Duration::from_millis(200)))?;
         stream.set_write_timeout(Some(Duration::from_millis(200)))?;
@@ -58,6 +273,13 @@ impl GdbRemoteClient {
             stream,
             port,
             no_ack_mode: false,
+            register_infos: None,
+            binary_writes: true,
+            faults: None,
+            trace,
+            console_output: Vec::new(),
+            deadline: None,
+            exit: None,
         };
         client.handshake()?;
         Ok(client)
@@ -77,16 +299,17 @@ impl GdbRemoteClient {
             Err(err) => return Err(err.into()),
         }
 
-        // Announce capabilities.
-        let _ = self.send_packet("qSupported:multiprocess+;qRelocInsn+");
-        if let Ok(reply) = self.read_packet() {
-            if reply.contains("QStartNoAckMode+") {
-                if let Ok(resp) = self.send_packet("QStartNoAckMode") {
-                    if resp.as_deref() == Some("OK") {
-                        self.no_ack_mode = true;
-                    }
-                }
-            }
+        // Announce capabilities. A bridge that accepted the TCP connection
+        // before debugserver attached stays silent here, so this doubles as
+        // the readiness check.
+        let reply = self
+            .send_packet("qSupported:multiprocess+;qRelocInsn+")
+            .map_err(|err| GdbRemoteError::Handshake(format!("no reply to qSupported ({err})")))?
+            .unwrap_or_default();
+        if reply.contains("QStartNoAckMode+")
+            && self.send_packet("QStartNoAckMode")?.as_deref() == Some("OK")
+        {
+            self.no_ack_mode = true;
         }
 
         // Query current stop reason to synchronize state.
@@ -95,11 +318,33 @@ impl GdbRemoteClient {
         Ok(())
     }
 
+    /// Fails every packet sent or awaited after `deadline` with
+    /// [`GdbRemoteError::DeadlineExceeded`], so a bounded operation gives up
+    /// at the next packet boundary instead of running on.
+    pub fn set_deadline(&mut self, deadline: Option<Instant>) {
+        self.deadline = deadline;
+    }
+
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    fn check_deadline(&self) -> Result<(), GdbRemoteError> {
+        match self.deadline {
+            Some(deadline) if Instant::now() >= deadline => Err(GdbRemoteError::DeadlineExceeded),
+            _ => Ok(()),
+        }
+    }
+
+    /// Routes every later packet through `faults` (see `--fault-inject`).
+    pub fn set_fault_injector(&mut self, faults: Option<Arc<FaultInjector>>) {
+        self.faults = faults;
+    }
+
     pub fn set_software_breakpoint(&mut self, address: u64) -> Result<(), GdbRemoteError> {
         self.expect_ok(&format!("Z0,{address:x},1"))
     }
 
-    #[allow(dead_code)]
     pub fn clear_software_breakpoint(&mut self, address: u64) -> Result<(), GdbRemoteError> {
         self.expect_ok(&format!("z0,{address:x},1"))
     }
@@ -108,19 +353,382 @@ impl GdbRemoteClient {
         self.expect_ok("vCont;c")
     }
 
-    pub fn step_thread(&mut self, _thread_id: i64) -> Result<(), GdbRemoteError> {
-        self.expect_ok("vCont;s")
+    /// Resumes only `thread_ids`; threads without a `vCont` action stay stopped.
+    pub fn continue_threads(&mut self, thread_ids: &[u64]) -> Result<(), GdbRemoteError> {
+        let actions: String = thread_ids.iter().map(|id| format!(";c:{id:x}")).collect();
+        self.expect_ok(&format!("vCont{actions}"))
+    }
+
+    /// Single-steps `thread_id`. `others` continue meanwhile: every other
+    /// thread when `None`, only the listed ones otherwise, so `Some(&[])`
+    /// keeps them stopped.
+    pub fn step_thread(
+        &mut self,
+        thread_id: u64,
+        others: Option<&[u64]>,
+    ) -> Result<(), GdbRemoteError> {
+        let packet = format!("vCont;s:{thread_id:x}{}", other_actions(thread_id, others));
+        self.expect_ok(&packet)
+    }
+
+    /// Selects the thread used by subsequent register (`g`/`p`/`P`) packets.
+    pub fn select_thread(&mut self, thread_id: u64) -> Result<(), GdbRemoteError> {
+        self.expect_ok(&format!("Hg{thread_id:x}"))
+    }
+
+    /// Enumerates the target's registers via `qRegisterInfo`, caching the result.
+    pub fn register_infos(&mut self) -> Result<&[RegisterInfo], GdbRemoteError> {
+        if self.register_infos.is_none() {
+            let mut infos = Vec::new();
+            for number in 0u32.. {
+                let reply = self.send_packet(&format!("qRegisterInfo{number:x}"))?;
+                match reply
+                    .as_deref()
+                    .and_then(|reply| parse_register_info(number, reply))
+                {
+                    Some(info) => infos.push(info),
+                    None => break,
+                }
+            }
+            self.register_infos = Some(infos);
+        }
+        Ok(self.register_infos.as_deref().unwrap_or_default())
+    }
+
+    /// Looks a register up by name, alternate name, or generic role (`pc`, `sp`, `ra`, `arg1`).
+    pub fn find_register(&mut self, name: &str) -> Result<Option<RegisterInfo>, GdbRemoteError> {
+        Ok(self
+            .register_infos()?
+            .iter()
+            .find(|info| info.matches(name))
+            .cloned())
     }
 
+    /// Reads a single register of the currently selected thread as raw target-order bytes.
+    pub fn read_register(&mut self, number: u32) -> Result<Vec<u8>, GdbRemoteError> {
+        let reply = self
+            .send_packet(&format!("p{number:x}"))?
+            .unwrap_or_default();
+        if reply.starts_with('E') || reply.is_empty() {
+            return Err(GdbRemoteError::Remote(reply));
+        }
+        decode_hex(&reply)
+    }
+
+    /// Reads the selected thread's register file in one `g` packet, in target byte order.
+    pub fn read_all_registers(&mut self) -> Result<Vec<u8>, GdbRemoteError> {
+        let reply = self.send_packet("g")?.unwrap_or_default();
+        if reply.starts_with('E') || reply.is_empty() {
+            return Err(GdbRemoteError::Remote(reply));
+        }
+        decode_hex(&reply)
+    }
+
+    /// Restores the selected thread's whole register file, as read by
+    /// [`read_all_registers`](Self::read_all_registers), in one `G` packet.
+    pub fn write_all_registers(&mut self, bytes: &[u8]) -> Result<(), GdbRemoteError> {
+        let hex: String = bytes.iter().map(|byte| format!("{byte:02x}")).collect();
+        self.expect_ok(&format!("G{hex}"))
+    }
+
+    /// Writes a single register of the currently selected thread; `bytes` are in target order.
+    pub fn write_register(&mut self, number: u32, bytes: &[u8]) -> Result<(), GdbRemoteError> {
+        let hex: String = bytes.iter().map(|byte| format!("{byte:02x}")).collect();
+        self.expect_ok(&format!("P{number:x}={hex}"))
+    }
+
+    /// Reads target memory, splitting the request into `m` packets of at most
+    /// [`MAX_MEMORY_CHUNK`] bytes.
+    pub fn read_memory(&mut self, address: u64, length: usize) -> Result<Vec<u8>, GdbRemoteError> {
+        let mut bytes = Vec::with_capacity(length);
+        while bytes.len() < length {
+            let chunk = (length - bytes.len()).min(MAX_MEMORY_CHUNK);
+            let chunk_address = address.wrapping_add(bytes.len() as u64);
+            let reply = self
+                .send_packet(&format!("m{chunk_address:x},{chunk:x}"))?
+                .unwrap_or_default();
+            if reply.starts_with('E') {
+                return Err(GdbRemoteError::Remote(reply));
+            }
+            let data = decode_hex(&reply)?;
+            if data.is_empty() {
+                break;
+            }
+            bytes.extend_from_slice(&data);
+        }
+        Ok(bytes)
+    }
+
+    /// Writes target memory with binary `X` packets, falling back to hex `M`
+    /// packets when the stub does not understand `X`.
+    pub fn write_memory(&mut self, address: u64, bytes: &[u8]) -> Result<(), GdbRemoteError> {
+        for (idx, chunk) in bytes.chunks(MAX_MEMORY_CHUNK).enumerate() {
+            let chunk_address = address.wrapping_add((idx * MAX_MEMORY_CHUNK) as u64);
+            if self.binary_writes {
+                let mut payload = format!("X{chunk_address:x},{:x}:", chunk.len()).into_bytes();
+                payload.extend(escape_binary(chunk));
+                match self.send_packet_bytes(&payload)?.as_deref() {
+                    Some("OK") => continue,
+                    Some("") | None => self.binary_writes = false,
+                    Some(reply) => return Err(GdbRemoteError::Remote(reply.to_string())),
+                }
+            }
+            let hex: String = chunk.iter().map(|byte| format!("{byte:02x}")).collect();
+            self.expect_ok(&format!("M{chunk_address:x},{:x}:{hex}", chunk.len()))?;
+        }
+        Ok(())
+    }
+
+    /// The region containing `address`, or the unmapped gap around it
+    /// (`qMemoryRegionInfo`).
+    pub fn memory_region_info(&mut self, address: u64) -> Result<MemoryRegion, GdbRemoteError> {
+        let reply = self
+            .send_packet(&format!("qMemoryRegionInfo:{address:x}"))?
+            .unwrap_or_default();
+        if reply.is_empty() {
+            return Err(GdbRemoteError::UnexpectedReply(
+                "stub does not support qMemoryRegionInfo".into(),
+            ));
+        }
+        if reply.starts_with('E') {
+            return Err(GdbRemoteError::Remote(reply));
+        }
+        parse_memory_region(&reply).ok_or(GdbRemoteError::UnexpectedReply(reply))
+    }
+
+    /// Writes `data` to the inferior's stdin (`I` packets). Only reaches the
+    /// process when debugserver launched it and owns its stdio.
+    pub fn write_stdin(&mut self, data: &[u8]) -> Result<(), GdbRemoteError> {
+        for chunk in data.chunks(MAX_MEMORY_CHUNK) {
+            let hex: String = chunk.iter().map(|byte| format!("{byte:02x}")).collect();
+            self.expect_ok(&format!("I{hex}"))?;
+        }
+        Ok(())
+    }
+
+    /// Console output (`O` packets) received while waiting for stops.
+    pub fn take_console_output(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.console_output)
+    }
+
+    /// Lists loaded images via debugserver's dyld all-image-infos
+    /// (`jGetLoadedDynamicLibrariesInfos`), falling back to the standard
+    /// `qXfer:libraries:read` library list.
+    pub fn loaded_images(&mut self) -> Result<Vec<LoadedImage>, GdbRemoteError> {
+        let request =
+            escape_binary(br#"jGetLoadedDynamicLibrariesInfos:{"fetch_all_solibs":true}"#);
+        if let Some(reply) = self.send_packet_bytes(&request)? {
+            let reply = unescape_binary(&reply);
+            if reply.starts_with('{') {
+                return parse_dyld_images(&reply);
+            }
+        }
+        let mut xml = String::new();
+        loop {
+            let reply = self
+                .send_packet(&format!(
+                    "qXfer:libraries:read::{:x},{:x}",
+                    xml.len(),
+                    0xfff
+                ))?
+                .unwrap_or_default();
+            let reply = unescape_binary(&reply);
+            match reply.split_at_checked(1) {
+                Some(("m", chunk)) => xml.push_str(chunk),
+                Some(("l", chunk)) => {
+                    xml.push_str(chunk);
+                    break;
+                }
+                _ => return Err(GdbRemoteError::Remote(reply)),
+            }
+        }
+        Ok(parse_library_list(&xml))
+    }
+
+    /// The inferior's pid, from `qProcessInfo`.
+    pub fn process_id(&mut self) -> Result<u64, GdbRemoteError> {
+        let reply = self.send_packet("qProcessInfo")?.unwrap_or_default();
+        if reply.starts_with('E') {
+            return Err(GdbRemoteError::Remote(reply));
+        }
+        let pid = reply
+            .split(';')
+            .find_map(|pair| pair.strip_prefix("pid:"))
+            .and_then(|pid| u64::from_str_radix(pid, 16).ok());
+        pid.ok_or(GdbRemoteError::UnexpectedReply(reply))
+    }
+
+    /// Address of dyld's `dyld_all_image_infos`, from `qShlibInfoAddr`.
+    pub fn shlib_info_address(&mut self) -> Result<u64, GdbRemoteError> {
+        let reply = self.send_packet("qShlibInfoAddr")?.unwrap_or_default();
+        if reply.is_empty() || reply.starts_with('E') {
+            return Err(GdbRemoteError::Remote(reply));
+        }
+        u64::from_str_radix(&reply, 16).map_err(|_| GdbRemoteError::UnexpectedReply(reply))
+    }
+
+    /// Kills the inferior (`k`). Stubs that drop the connection instead of
+    /// reporting the exit yield `None`.
+    pub fn kill(&mut self) -> Result<Option<ProcessExit>, GdbRemoteError> {
+        match self.send_packet("k") {
+            Ok(reply) => Ok(reply.as_deref().and_then(parse_exit_reply)),
+            Err(GdbRemoteError::Io(err))
+                if matches!(
+                    err.kind(),
+                    io::ErrorKind::UnexpectedEof | io::ErrorKind::ConnectionReset
+                ) =>
+            {
+                Ok(None)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Detaches (`D`); debugserver removes its breakpoints and the process
+    /// keeps running.
+    pub fn detach(&mut self) -> Result<(), GdbRemoteError> {
+        self.expect_ok("D")
+    }
+
+    /// Lists threads (`qfThreadInfo`/`qsThreadInfo`), taking names and queue
+    /// labels from debugserver's `jThreadsInfo` and run states from
+    /// `qThreadExtraInfo` where the stub supports them.
+    pub fn threads(&mut self) -> Result<Vec<RemoteThread>, GdbRemoteError> {
+        let mut threads: Vec<RemoteThread> = self
+            .thread_ids()?
+            .into_iter()
+            .map(|id| RemoteThread {
+                id,
+                ..RemoteThread::default()
+            })
+            .collect();
+
+        if let Some(reply) = self.send_packet("jThreadsInfo")? {
+            let reply = unescape_binary(&reply);
+            if let Ok(Value::Array(infos)) = serde_json::from_str::<Value>(&reply) {
+                for info in infos {
+                    let Some(thread) = info
+                        .get("tid")
+                        .and_then(Value::as_u64)
+                        .and_then(|tid| threads.iter_mut().find(|thread| thread.id == tid))
+                    else {
+                        continue;
+                    };
+                    let text = |key: &str| {
+                        info.get(key)
+                            .and_then(Value::as_str)
+                            .filter(|text| !text.is_empty())
+                            .map(str::to_string)
+                    };
+                    thread.name = text("name");
+                    thread.queue = text("qname");
+                }
+            }
+        }
+
+        for thread in &mut threads {
+            let reply = self.send_packet(&format!("qThreadExtraInfo,{:x}", thread.id))?;
+            thread.run_state = reply
+                .as_deref()
+                .and_then(|hex| decode_hex(hex).ok())
+                .and_then(|bytes| parse_run_state(&String::from_utf8_lossy(&bytes)));
+        }
+        Ok(threads)
+    }
+
+    /// Resumes with `signal` delivered to `thread_id`; the other threads
+    /// continue, or only `others` when given.
+    pub fn continue_with_signal(
+        &mut self,
+        thread_id: u64,
+        signal: u8,
+        others: Option<&[u64]>,
+    ) -> Result<(), GdbRemoteError> {
+        let packet = format!(
+            "vCont;C{signal:02x}:{thread_id:x}{}",
+            other_actions(thread_id, others)
+        );
+        self.expect_ok(&packet)
+    }
+
+    /// Ids of the inferior's threads (`qfThreadInfo`/`qsThreadInfo`).
+    pub fn thread_ids(&mut self) -> Result<Vec<u64>, GdbRemoteError> {
+        let mut ids = Vec::new();
+        let mut reply = self.send_packet("qfThreadInfo")?.unwrap_or_default();
+        while let Some(chunk) = reply.strip_prefix('m') {
+            ids.extend(
+                chunk
+                    .split(',')
+                    .filter_map(|id| u64::from_str_radix(id, 16).ok()),
+            );
+            reply = self.send_packet("qsThreadInfo")?.unwrap_or_default();
+        }
+        if reply != "l" {
+            return Err(GdbRemoteError::Remote(reply));
+        }
+        Ok(ids)
+    }
+
+    /// Waits for the next stop; an exit (`W`/`X`) is reported as
+    /// [`GdbRemoteError::Exited`].
     pub fn wait_for_stop(&mut self) -> Result<StopReply, GdbRemoteError> {
         loop {
             let packet = self.read_packet()?;
             if let Some(reply) = parse_stop_reply(&packet) {
                 return Ok(reply);
             }
+            if let Some(exit) = parse_exit_reply(&packet) {
+                self.exit = Some(exit);
+                return Err(GdbRemoteError::Exited(exit));
+            }
+            if let Some(output) = parse_console_output(&packet) {
+                self.console_output.extend(output);
+            }
+        }
+    }
+
+    /// Like [`wait_for_stop`](Self::wait_for_stop), but keeps waiting through
+    /// read timeouts until `until`, returning `None` if the target is still
+    /// running then.
+    pub fn wait_for_stop_until(
+        &mut self,
+        until: Instant,
+    ) -> Result<Option<StopReply>, GdbRemoteError> {
+        loop {
+            match self.wait_for_stop() {
+                Err(GdbRemoteError::Io(err))
+                    if err.kind() == io::ErrorKind::WouldBlock
+                        || err.kind() == io::ErrorKind::TimedOut =>
+                {
+                    if Instant::now() >= until {
+                        return Ok(None);
+                    }
+                }
+                result => return result.map(Some),
+            }
         }
     }
 
+    /// Stops a running target by sending the out-of-band `^C` byte; the stop
+    /// reply still has to be awaited.
+    pub fn interrupt(&mut self) -> Result<(), GdbRemoteError> {
+        self.stream.write_all(&[0x03])?;
+        self.stream.flush()?;
+        Ok(())
+    }
+
+    /// How the inferior ended, if [`wait_for_stop`](Self::wait_for_stop) has
+    /// reported its exit since the last call.
+    pub fn take_exit(&mut self) -> Option<ProcessExit> {
+        self.exit.take()
+    }
+
+    /// A handle that can [`interrupt`](Self::interrupt) this connection
+    /// without borrowing the client.
+    pub fn interrupter(&self) -> Result<Interrupter, GdbRemoteError> {
+        Ok(Interrupter(self.stream.try_clone()?))
+    }
+
     fn expect_ok(&mut self, payload: &str) -> Result<(), GdbRemoteError> {
         let reply = self.send_packet(payload)?;
         match reply.as_deref() {
@@ -131,30 +739,65 @@ impl GdbRemoteClient {
     }
 
     fn send_packet(&mut self, payload: &str) -> Result<Option<String>, GdbRemoteError> {
-        let mut packet = String::with_capacity(payload.len() + 4);
-        packet.push('$');
-        packet.push_str(payload);
-        packet.push('#');
-        let checksum = payload.bytes().fold(0u8, |acc, b| acc.wrapping_add(b));
-        packet.push_str(&format!("{:02x}", checksum));
-        self.stream.write_all(packet.as_bytes())?;
-        self.stream.flush()?;
+        self.send_packet_bytes(payload.as_bytes())
+    }
 
-        if !self.no_ack_mode {
+    fn send_packet_bytes(&mut self, payload: &[u8]) -> Result<Option<String>, GdbRemoteError> {
+        self.check_deadline()?;
+        if let Some(faults) = &self.faults {
+            if let Some(delay) = faults.packet_delay() {
+                thread::sleep(delay);
+            }
+            if faults.take_disconnect() {
+                let _ = self.stream.shutdown(Shutdown::Both);
+                return Err(GdbRemoteError::Io(io::Error::new(
+                    io::ErrorKind::ConnectionReset,
+                    "injected debugserver disconnect",
+                )));
+            }
+        }
+        let mut packet = Vec::with_capacity(payload.len() + 4);
+        packet.push(b'$');
+        packet.extend_from_slice(payload);
+        packet.push(b'#');
+        let checksum = payload.iter().fold(0u8, |acc, b| acc.wrapping_add(*b));
+        packet.extend_from_slice(format!("{:02x}", checksum).as_bytes());
+        // Without no-ack mode the stub acks every packet, and NAKs one that
+        // reached it garbled so it is sent again.
+        let mut naks = 0;
+        loop {
+            self.stream.write_all(&packet)?;
+            self.stream.flush()?;
+            if let Some(trace) = &self.trace {
+                trace.record(Direction::Send, payload);
+            }
+            if self.no_ack_mode {
+                break;
+            }
             let mut ack = [0u8; 1];
             self.stream.read_exact(&mut ack)?;
-            if ack[0] != b'+' {
-                return Err(GdbRemoteError::UnexpectedReply(format!(
-                    "expected ack '+', got {:?}",
-                    ack[0] as char
-                )));
+            match ack[0] {
+                b'+' => break,
+                b'-' if naks < MAX_RETRANSMITS => naks += 1,
+                b'-' => {
+                    return Err(GdbRemoteError::Protocol(format!(
+                        "packet refused {} times",
+                        naks + 1
+                    )))
+                }
+                other => {
+                    return Err(GdbRemoteError::Protocol(format!(
+                        "expected ack '+', got {:?}",
+                        other as char
+                    )))
+                }
             }
         }
 
-        if payload.starts_with('v')
-            || payload.starts_with('c')
-            || payload.starts_with('s')
-            || payload == "?"
+        if payload.starts_with(b"v")
+            || payload.starts_with(b"c")
+            || payload.starts_with(b"s")
+            || payload == b"?"
         {
             Ok(None)
         } else {
@@ -162,7 +805,32 @@ impl GdbRemoteClient {
         }
     }
 
+    /// The next packet, acked. One that arrives corrupted is NAKed to have
+    /// it sent again, which no-ack mode has no way to ask for.
     fn read_packet(&mut self) -> Result<String, GdbRemoteError> {
+        self.check_deadline()?;
+        let mut naks = 0;
+        loop {
+            let (data, intact) = self.read_frame()?;
+            if intact {
+                if !self.no_ack_mode {
+                    self.stream.write_all(b"+")?;
+                }
+                if let Some(trace) = &self.trace {
+                    trace.record(Direction::Recv, &data);
+                }
+                return Ok(String::from_utf8_lossy(&data).into_owned());
+            }
+            if self.no_ack_mode || naks == MAX_RETRANSMITS {
+                return Err(GdbRemoteError::BadChecksum);
+            }
+            naks += 1;
+            self.stream.write_all(b"-")?;
+        }
+    }
+
+    /// The payload of the next `$...#xx` frame, and whether its checksum matches.
+    fn read_frame(&mut self) -> Result<(Vec<u8>, bool), GdbRemoteError> {
         let mut start = [0u8; 1];
         loop {
             self.stream.read_exact(&mut start)?;
@@ -183,16 +851,231 @@ impl GdbRemoteClient {
         }
         let mut checksum_bytes = [0u8; 2];
         self.stream.read_exact(&mut checksum_bytes)?;
-        let sent = u8::from_str_radix(std::str::from_utf8(&checksum_bytes).unwrap_or("00"), 16)
-            .map_err(|_| GdbRemoteError::BadChecksum)?;
+        let sent = std::str::from_utf8(&checksum_bytes)
+            .ok()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
         let computed = data.iter().copied().fold(0u8, |acc, b| acc.wrapping_add(b));
-        if sent != computed {
-            return Err(GdbRemoteError::BadChecksum);
+        Ok((data, sent == Some(computed)))
+    }
+}
+
+/// The `vCont` actions continuing `others` (every thread when `None`) beside
+/// the one `thread_id` was given.
+fn other_actions(thread_id: u64, others: Option<&[u64]>) -> String {
+    match others {
+        Some(ids) => ids
+            .iter()
+            .filter(|id| **id != thread_id)
+            .map(|id| format!(";c:{id:x}"))
+            .collect(),
+        None => ";c".into(),
+    }
+}
+
+fn parse_register_info(number: u32, reply: &str) -> Option<RegisterInfo> {
+    if reply.is_empty() || reply.starts_with('E') {
+        return None;
+    }
+    let mut info = RegisterInfo {
+        number,
+        name: String::new(),
+        alt_name: None,
+        generic: None,
+        bitsize: 0,
+        offset: None,
+        set: None,
+    };
+    for pair in reply.split(';') {
+        let Some((key, value)) = pair.split_once(':') else {
+            continue;
+        };
+        match key {
+            "name" => info.name = value.to_string(),
+            "alt-name" => info.alt_name = Some(value.to_string()),
+            "generic" => info.generic = Some(value.to_string()),
+            "bitsize" => info.bitsize = value.parse().unwrap_or_default(),
+            "offset" => info.offset = value.parse().ok(),
+            "set" => info.set = Some(value.to_string()),
+            _ => {}
+        }
+    }
+    if info.name.is_empty() {
+        None
+    } else {
+        Some(info)
+    }
+}
+
+/// Escapes `#`, `$`, `}` and `*` for binary packets (`}` followed by byte ^ 0x20).
+fn escape_binary(bytes: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(bytes.len());
+    for byte in bytes {
+        if matches!(byte, b'#' | b'$' | b'}' | b'*') {
+            out.push(b'}');
+            out.push(byte ^ 0x20);
+        } else {
+            out.push(*byte);
+        }
+    }
+    out
+}
+
+/// Reverses [`escape_binary`] and expands `*` run-length encoding in a reply.
+fn unescape_binary(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut out: Vec<u8> = Vec::with_capacity(bytes.len());
+    let mut idx = 0;
+    while idx < bytes.len() {
+        match bytes[idx] {
+            b'}' if idx + 1 < bytes.len() => {
+                out.push(bytes[idx + 1] ^ 0x20);
+                idx += 2;
+            }
+            b'*' if idx + 1 < bytes.len() && !out.is_empty() => {
+                let repeat = bytes[idx + 1].saturating_sub(29) as usize;
+                let last = out[out.len() - 1];
+                out.extend(std::iter::repeat_n(last, repeat));
+                idx += 2;
+            }
+            byte => {
+                out.push(byte);
+                idx += 1;
+            }
         }
-        if !self.no_ack_mode {
-            self.stream.write_all(b"+")?;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn parse_dyld_images(reply: &str) -> Result<Vec<LoadedImage>, GdbRemoteError> {
+    let value: Value = serde_json::from_str(reply)
+        .map_err(|err| GdbRemoteError::UnexpectedReply(format!("image infos: {err}")))?;
+    let images = value
+        .get("images")
+        .and_then(Value::as_array)
+        .ok_or_else(|| GdbRemoteError::UnexpectedReply("image infos without `images`".into()))?;
+    Ok(images
+        .iter()
+        .filter_map(|image| {
+            Some(LoadedImage {
+                path: image.get("pathname")?.as_str()?.to_string(),
+                load_address: image.get("load_address")?.as_u64()?,
+                uuid: image
+                    .get("uuid")
+                    .and_then(Value::as_str)
+                    .map(str::to_string),
+                text_vmaddr: image
+                    .pointer("/segments")
+                    .and_then(Value::as_array)
+                    .and_then(|segments| {
+                        segments.iter().find(|segment| {
+                            segment.get("name").and_then(Value::as_str) == Some("__TEXT")
+                        })
+                    })
+                    .and_then(|segment| segment.get("vmaddr"))
+                    .and_then(Value::as_u64),
+            })
+        })
+        .collect())
+}
+
+/// Reads `<library name="..."><segment address="0x..."/></library>` entries.
+fn parse_library_list(xml: &str) -> Vec<LoadedImage> {
+    let attribute = |element: &str, name: &str| -> Option<String> {
+        let start = element.find(&format!("{name}=\""))? + name.len() + 2;
+        let end = element[start..].find('"')? + start;
+        Some(element[start..end].to_string())
+    };
+    xml.split("<library ")
+        .skip(1)
+        .filter_map(|element| {
+            let address = attribute(element, "address")?;
+            Some(LoadedImage {
+                path: attribute(element, "name")?,
+                load_address: u64::from_str_radix(address.trim_start_matches("0x"), 16).ok()?,
+                uuid: None,
+                text_vmaddr: None,
+            })
+        })
+        .collect()
+}
+
+fn parse_memory_region(reply: &str) -> Option<MemoryRegion> {
+    let mut region = MemoryRegion::default();
+    let (mut start, mut size) = (None, None);
+    for field in reply.split(';') {
+        let Some((key, value)) = field.split_once(':') else {
+            continue;
+        };
+        match key {
+            "start" => start = u64::from_str_radix(value, 16).ok(),
+            "size" => size = u64::from_str_radix(value, 16).ok(),
+            "permissions" => region.permissions = value.to_string(),
+            "name" => {
+                region.name = decode_hex(value)
+                    .ok()
+                    .map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+            }
+            "type" => region.region_type = Some(value.to_string()),
+            _ => {}
         }
-        Ok(String::from_utf8_lossy(&data).into_owned())
+    }
+    region.start = start?;
+    region.size = size?;
+    Some(region)
+}
+
+/// Bytes of an `O<hex>` console output packet.
+fn parse_console_output(packet: &str) -> Option<Vec<u8>> {
+    let hex = packet
+        .strip_prefix('O')
+        .filter(|hex| !hex.is_empty() && *hex != "K")?;
+    decode_hex(hex).ok()
+}
+
+pub fn decode_hex(text: &str) -> Result<Vec<u8>, GdbRemoteError> {
+    if !text.len().is_multiple_of(2) {
+        return Err(GdbRemoteError::UnexpectedReply(text.to_string()));
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|idx| {
+            text.get(idx..idx + 2)
+                .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+                .ok_or_else(|| GdbRemoteError::UnexpectedReply(text.to_string()))
+        })
+        .collect()
+}
+
+/// Reads the Mach run state out of debugserver's thread description, e.g.
+/// `... state="waiting" ... suspend_count=0`.
+fn parse_run_state(info: &str) -> Option<ThreadRunState> {
+    let info = info.to_ascii_lowercase();
+    let suspended = info
+        .split_once("suspend_count=")
+        .and_then(|(_, rest)| {
+            rest.split(|ch: char| !ch.is_ascii_digit())
+                .next()?
+                .parse::<u32>()
+                .ok()
+        })
+        .is_some_and(|count| count > 0);
+    if suspended || info.contains("stopped") || info.contains("halted") {
+        Some(ThreadRunState::Suspended)
+    } else if info.contains("waiting") || info.contains("uninterruptible") {
+        Some(ThreadRunState::Blocked)
+    } else if info.contains("running") {
+        Some(ThreadRunState::Running)
+    } else {
+        None
+    }
+}
+
+fn parse_exit_reply(reply: &str) -> Option<ProcessExit> {
+    let code = u8::from_str_radix(reply.get(1..3)?, 16).ok()?;
+    match reply.as_bytes().first()? {
+        b'W' => Some(ProcessExit::Exited(code)),
+        b'X' => Some(ProcessExit::Signaled(code)),
+        _ => None,
     }
 }
 
@@ -206,17 +1089,24 @@ fn parse_stop_reply(reply: &str) -> Option<StopReply> {
             signal: sig,
             thread_id: None,
             reason: StopReason::Signal,
+            exception: None,
         });
     }
     if reply.starts_with('T') {
         let sig = u8::from_str_radix(&reply[1..3], 16).ok()?;
         let mut reason = StopReason::Unknown("signal".into());
         let mut thread_id = None;
+        let mut exception_type = None;
+        let mut data = Vec::new();
         for part in reply[3..].split(';') {
             if let Some(rest) = part.strip_prefix("thread:") {
                 if let Ok(id) = u64::from_str_radix(rest, 16) {
                     thread_id = Some(id);
                 }
+            } else if let Some(rest) = part.strip_prefix("metype:") {
+                exception_type = u32::from_str_radix(rest, 16).ok();
+            } else if let Some(rest) = part.strip_prefix("medata:") {
+                data.extend(u64::from_str_radix(rest, 16).ok());
             } else if let Some(rest) = part.strip_prefix("reason:") {
                 reason = match rest {
                     "breakpoint" => StopReason::Breakpoint,
@@ -229,6 +1119,10 @@ fn parse_stop_reply(reply: &str) -> Option<StopReply> {
             signal: sig,
             thread_id,
             reason,
+            exception: exception_type.map(|exception_type| MachException {
+                exception_type,
+                data,
+            }),
         });
     }
     None
@@ -250,12 +1144,413 @@ mod tests {
         assert_eq!(String::from_utf8(buf.clone()).unwrap(), "$Z0,1000,1#d4");
     }
 
+    /// Answers each packet from `reply`, acking unless no-ack mode was agreed.
+    fn fake_stub(reply: fn(&str) -> Option<&'static str>) -> u16 {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut no_ack = false;
+            let mut byte = [0u8; 1];
+            while stream.read_exact(&mut byte).is_ok() {
+                if byte[0] != b'$' {
+                    continue;
+                }
+                let mut payload = Vec::new();
+                while stream.read_exact(&mut byte).is_ok() && byte[0] != b'#' {
+                    payload.push(byte[0]);
+                }
+                let mut checksum = [0u8; 2];
+                let _ = stream.read_exact(&mut checksum);
+                let payload = String::from_utf8_lossy(&payload).into_owned();
+                if !no_ack {
+                    let _ = stream.write_all(b"+");
+                }
+                if let Some(body) = reply(&payload) {
+                    let sum = body.bytes().fold(0u8, |acc, b| acc.wrapping_add(b));
+                    let _ = write!(stream, "${body}#{sum:02x}");
+                }
+                no_ack |= payload == "QStartNoAckMode";
+            }
+        });
+        port
+    }
+
+    #[test]
+    fn connect_negotiates_no_ack_mode() {
+        let port = fake_stub(|payload| match payload {
+            p if p.starts_with("qSupported") => Some("PacketSize=20000;QStartNoAckMode+"),
+            "QStartNoAckMode" => Some("OK"),
+            "?" => Some("S05"),
+            _ => Some(""),
+        });
+        let client = GdbRemoteClient::connect(port).unwrap();
+        assert!(client.no_ack_mode);
+    }
+
+    /// Reads bytes up to and including the next frame from `stream`,
+    /// returning its payload.
+    fn read_stub_frame(stream: &mut TcpStream) -> String {
+        let mut frame = Vec::new();
+        let mut byte = [0u8; 1];
+        while stream.read_exact(&mut byte).is_ok() && byte[0] != b'$' {}
+        while stream.read_exact(&mut byte).is_ok() && byte[0] != b'#' {
+            frame.push(byte[0]);
+        }
+        let mut checksum = [0u8; 2];
+        stream.read_exact(&mut checksum).unwrap();
+        String::from_utf8(frame).unwrap()
+    }
+
+    fn frame(body: &str) -> String {
+        let sum = body.bytes().fold(0u8, |acc, b| acc.wrapping_add(b));
+        format!("${body}#{sum:02x}")
+    }
+
+    #[test]
+    fn naks_are_answered_with_retransmissions_both_ways() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut ack = [0u8; 1];
+            for reply in ["PacketSize=20000", "S05"] {
+                read_stub_frame(&mut stream);
+                write!(stream, "+{}", frame(reply)).unwrap();
+                stream.read_exact(&mut ack).unwrap();
+            }
+            // The first qProcessInfo is NAKed, the retransmission answered
+            // with a corrupted reply and then, once NAKed, an intact one.
+            assert_eq!(read_stub_frame(&mut stream), "qProcessInfo");
+            stream.write_all(b"-").unwrap();
+            assert_eq!(read_stub_frame(&mut stream), "qProcessInfo");
+            write!(stream, "+$pid:1f4;#00").unwrap();
+            stream.read_exact(&mut ack).unwrap();
+            assert_eq!(ack[0], b'-');
+            write!(stream, "{}", frame("pid:1f4;")).unwrap();
+            stream.read_exact(&mut ack).unwrap();
+            assert_eq!(ack[0], b'+');
+            // A reply that never arrives intact gives up.
+            assert_eq!(read_stub_frame(&mut stream), "qShlibInfoAddr");
+            stream.write_all(b"+").unwrap();
+            loop {
+                write!(stream, "$1000#00").unwrap();
+                if stream.read_exact(&mut ack).is_err() || ack[0] != b'-' {
+                    break;
+                }
+            }
+        });
+        let mut client = GdbRemoteClient::connect(port).unwrap();
+        assert!(!client.no_ack_mode);
+        assert_eq!(client.process_id().unwrap(), 500);
+        let err = client.shlib_info_address().unwrap_err();
+        assert!(err.is_protocol(), "{err}");
+        assert!(err.to_string().starts_with(PROTOCOL_ERROR), "{err}");
+        assert!(!GdbRemoteError::Remote("E01".into()).is_protocol());
+    }
+
+    #[test]
+    fn detach_sends_d_packet() {
+        let port = fake_stub(|payload| match payload {
+            p if p.starts_with("qSupported") => Some("PacketSize=20000"),
+            "?" => Some("S05"),
+            "D" => Some("OK"),
+            _ => Some("E01"),
+        });
+        let mut client = GdbRemoteClient::connect(port).unwrap();
+        client.detach().unwrap();
+    }
+
+    #[test]
+    fn process_id_is_read_from_process_info() {
+        let port = fake_stub(|payload| match payload {
+            p if p.starts_with("qSupported") => Some("PacketSize=20000"),
+            "?" => Some("S05"),
+            "qProcessInfo" => Some("pid:1f4;parent-pid:1;real-uid:1f5;ptrsize:8;"),
+            _ => Some(""),
+        });
+        let mut client = GdbRemoteClient::connect(port).unwrap();
+        assert_eq!(client.process_id().unwrap(), 500);
+    }
+
+    #[test]
+    fn shlib_info_address_is_read_from_its_query() {
+        let port = fake_stub(|payload| match payload {
+            p if p.starts_with("qSupported") => Some("PacketSize=20000"),
+            "?" => Some("S05"),
+            "qShlibInfoAddr" => Some("1fee8a0c0"),
+            _ => Some(""),
+        });
+        let mut client = GdbRemoteClient::connect(port).unwrap();
+        assert_eq!(client.shlib_info_address().unwrap(), 0x1_fee8_a0c0);
+    }
+
+    #[test]
+    fn traced_connect_records_handshake() {
+        let port = fake_stub(|payload| match payload {
+            p if p.starts_with("qSupported") => Some("PacketSize=20000"),
+            "?" => Some("S05"),
+            _ => Some(""),
+        });
+        let path = std::env::temp_dir().join(format!("gdb-trace-{port}.log"));
+        let trace = Arc::new(ProtocolTrace::create(&path, "test").unwrap());
+        GdbRemoteClient::connect_traced(port, Some(trace.clone())).unwrap();
+        std::fs::remove_file(&path).ok();
+        let packets: Vec<_> = trace
+            .tail(4)
+            .into_iter()
+            .map(|entry| (entry.direction, entry.packet))
+            .collect();
+        assert_eq!(
+            packets[..2],
+            [
+                (
+                    Direction::Send,
+                    "qSupported:multiprocess+;qRelocInsn+".to_string()
+                ),
+                (Direction::Recv, "PacketSize=20000".to_string()),
+            ]
+        );
+        assert!(packets.contains(&(Direction::Send, "?".to_string())));
+    }
+
+    #[test]
+    fn threads_merge_names_queues_and_run_states() {
+        let port = fake_stub(|payload| match payload {
+            p if p.starts_with("qSupported") => Some("PacketSize=20000"),
+            "?" => Some("S05"),
+            "qfThreadInfo" => Some("m1a,1b"),
+            "qsThreadInfo" => Some("l"),
+            // Binary reply: each `}` is escaped as `}]`.
+            "jThreadsInfo" => Some(
+                r#"[{"tid":26,"name":"main","qname":"com.apple.main-thread"}],{"tid":27,"name":""}]]"#,
+            ),
+            // `state="waiting" suspend_count=0`
+            "qThreadExtraInfo,1a" => {
+                Some("73746174653d2277616974696e67222073757370656e645f636f756e743d30")
+            }
+            // `state="running" suspend_count=1`
+            "qThreadExtraInfo,1b" => {
+                Some("73746174653d2272756e6e696e67222073757370656e645f636f756e743d31")
+            }
+            _ => Some(""),
+        });
+        let mut client = GdbRemoteClient::connect(port).unwrap();
+        let threads = client.threads().unwrap();
+        assert_eq!(
+            threads,
+            [
+                RemoteThread {
+                    id: 0x1a,
+                    name: Some("main".into()),
+                    queue: Some("com.apple.main-thread".into()),
+                    run_state: Some(ThreadRunState::Blocked),
+                },
+                RemoteThread {
+                    id: 0x1b,
+                    run_state: Some(ThreadRunState::Suspended),
+                    ..RemoteThread::default()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn continue_threads_sends_one_action_per_thread() {
+        let port = fake_stub(|payload| match payload {
+            p if p.starts_with("qSupported") => Some("PacketSize=20000"),
+            "?" => Some("S05"),
+            p if p.starts_with("vCont") => None,
+            _ => Some("E01"),
+        });
+        let path = std::env::temp_dir().join(format!("gdb-trace-{port}.log"));
+        let trace = Arc::new(ProtocolTrace::create(&path, "test").unwrap());
+        let mut client = GdbRemoteClient::connect_traced(port, Some(trace.clone())).unwrap();
+        client.continue_threads(&[0x1a, 0x2f]).unwrap();
+        std::fs::remove_file(&path).ok();
+        let last = trace.tail(1).pop().unwrap();
+        assert_eq!(
+            (last.direction, last.packet.as_str()),
+            (Direction::Send, "vCont;c:1a;c:2f")
+        );
+    }
+
+    #[test]
+    fn step_thread_names_the_thread_and_what_the_others_do() {
+        let port = fake_stub(|payload| match payload {
+            p if p.starts_with("qSupported") => Some("PacketSize=20000"),
+            "?" => Some("S05"),
+            p if p.starts_with("vCont") => None,
+            _ => Some("E01"),
+        });
+        let path = std::env::temp_dir().join(format!("gdb-trace-{port}.log"));
+        let trace = Arc::new(ProtocolTrace::create(&path, "test").unwrap());
+        let mut client = GdbRemoteClient::connect_traced(port, Some(trace.clone())).unwrap();
+        client.step_thread(0x1a, Some(&[])).unwrap();
+        client.step_thread(0x1a, None).unwrap();
+        client.step_thread(0x1a, Some(&[0x1a, 0x2f])).unwrap();
+        std::fs::remove_file(&path).ok();
+        let sent: Vec<String> = trace
+            .tail(3)
+            .into_iter()
+            .map(|packet| packet.packet)
+            .collect();
+        assert_eq!(sent, ["vCont;s:1a", "vCont;s:1a;c", "vCont;s:1a;c:2f"]);
+    }
+
+    #[test]
+    fn signal_is_delivered_to_one_thread_and_exit_ends_the_wait() {
+        let port = fake_stub(|payload| match payload {
+            p if p.starts_with("qSupported") => Some("PacketSize=20000"),
+            "?" => Some("S05"),
+            "vCont;C1e:1a;c" => Some("X0f"),
+            _ => Some("E01"),
+        });
+        let mut client = GdbRemoteClient::connect(port).unwrap();
+        client.continue_with_signal(0x1a, 30, None).unwrap();
+        let err = client.wait_for_stop().unwrap_err();
+        assert!(
+            matches!(err, GdbRemoteError::Exited(ProcessExit::Signaled(15))),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn stdin_is_sent_as_hex_and_console_output_decodes() {
+        let port = fake_stub(|payload| match payload {
+            p if p.starts_with("qSupported") => Some("PacketSize=20000"),
+            "?" => Some("S05"),
+            "I68690a" => Some("OK"),
+            _ => Some("E01"),
+        });
+        let mut client = GdbRemoteClient::connect(port).unwrap();
+        client.write_stdin(b"hi\n").unwrap();
+        assert!(client.write_stdin(b"x").is_err());
+        assert_eq!(parse_console_output("O6f6b0a"), Some(b"ok\n".to_vec()));
+        assert_eq!(parse_console_output("OK"), None);
+    }
+
+    #[test]
+    fn packets_past_the_deadline_are_refused() {
+        let port = fake_stub(|payload| match payload {
+            p if p.starts_with("qSupported") => Some("PacketSize=20000"),
+            "?" => Some("S05"),
+            "m1000,4" => Some("01020304"),
+            _ => Some("E01"),
+        });
+        let mut client = GdbRemoteClient::connect(port).unwrap();
+        client.set_deadline(Some(Instant::now()));
+        assert!(matches!(
+            client.read_memory(0x1000, 4),
+            Err(GdbRemoteError::DeadlineExceeded)
+        ));
+        client.set_deadline(None);
+        assert_eq!(client.read_memory(0x1000, 4).unwrap(), [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn memory_region_info_parses_fields() {
+        let port = fake_stub(|payload| match payload {
+            p if p.starts_with("qSupported") => Some("PacketSize=20000"),
+            "?" => Some("S05"),
+            "qMemoryRegionInfo:16fdff000" => {
+                Some("start:16fd80000;size:80000;permissions:rw;type:stack;name:2f7573722f6c6962;")
+            }
+            _ => Some("E01"),
+        });
+        let mut client = GdbRemoteClient::connect(port).unwrap();
+        let region = client.memory_region_info(0x1_6fdf_f000).unwrap();
+        assert_eq!(
+            region,
+            MemoryRegion {
+                start: 0x1_6fd8_0000,
+                size: 0x80000,
+                permissions: "rw".into(),
+                name: Some("/usr/lib".into()),
+                region_type: Some("stack".into()),
+            }
+        );
+        assert!(client.memory_region_info(0).is_err());
+    }
+
+    #[test]
+    fn connect_rejects_silent_peer() {
+        let port = fake_stub(|_| None);
+        let err = GdbRemoteClient::connect(port).unwrap_err();
+        assert!(matches!(err, GdbRemoteError::Handshake(_)), "{err}");
+    }
+
+    #[test]
+    fn parse_exit_reply_distinguishes_exit_and_signal() {
+        assert_eq!(parse_exit_reply("W00"), Some(ProcessExit::Exited(0)));
+        assert_eq!(
+            parse_exit_reply("X09;description:6b696c6c6564"),
+            Some(ProcessExit::Signaled(9))
+        );
+        assert_eq!(parse_exit_reply("OK"), None);
+    }
+
     #[test]
     fn parse_stop_reply_signal() {
         let reply = parse_stop_reply("S05").unwrap();
         assert_eq!(reply.signal, 0x05);
     }
 
+    #[test]
+    fn parse_register_info_reads_fields() {
+        let info = parse_register_info(
+            0x20,
+            "name:pc;alt-name:pc;bitsize:64;offset:256;encoding:uint;format:hex;set:General Purpose Registers;gcc:32;dwarf:32;generic:pc;",
+        )
+        .unwrap();
+        assert_eq!(info.number, 0x20);
+        assert_eq!(info.name, "pc");
+        assert_eq!(info.bitsize, 64);
+        assert_eq!(info.offset, Some(256));
+        assert!(info.matches("pc"));
+        assert!(parse_register_info(0x99, "E45").is_none());
+    }
+
+    #[test]
+    fn escape_binary_escapes_reserved_bytes() {
+        assert_eq!(
+            escape_binary(b"a#b$c}d*"),
+            b"a}\x03b}\x04c}]d}\x0a".to_vec()
+        );
+    }
+
+    #[test]
+    fn parse_dyld_images_reads_text_segment() {
+        let reply = r#"{"images":[{"load_address":4295000064,"pathname":"/private/var/App.app/App","uuid":"8B5A9D3C-1F2E-4A6B-9C0D-112233445566","segments":[{"name":"__PAGEZERO","vmaddr":0},{"name":"__TEXT","vmaddr":4294967296}]}]}"#;
+        let images = parse_dyld_images(reply).unwrap();
+        assert_eq!(
+            images,
+            vec![LoadedImage {
+                path: "/private/var/App.app/App".into(),
+                load_address: 0x1_0000_8000,
+                uuid: Some("8B5A9D3C-1F2E-4A6B-9C0D-112233445566".into()),
+                text_vmaddr: Some(0x1_0000_0000),
+            }]
+        );
+        assert_eq!(unescape_binary("{\"a\":1}]"), "{\"a\":1}");
+        assert_eq!(unescape_binary("0* "), "0000");
+    }
+
+    #[test]
+    fn parse_library_list_reads_segments() {
+        let xml = r#"<library-list><library name="/usr/lib/libc.dylib"><segment address="0x180000000"/></library></library-list>"#;
+        let images = parse_library_list(xml);
+        assert_eq!(images.len(), 1);
+        assert_eq!(images[0].path, "/usr/lib/libc.dylib");
+        assert_eq!(images[0].load_address, 0x1_8000_0000);
+    }
+
+    #[test]
+    fn decode_hex_rejects_odd_lengths() {
+        assert_eq!(decode_hex("0010ff").unwrap(), vec![0x00, 0x10, 0xff]);
+        assert!(decode_hex("abc").is_err());
+    }
+
     #[test]
     fn parse_stop_reply_thread() {
         let reply = parse_stop_reply("T05thread:1;reason:breakpoint;").unwrap();
@@ -263,4 +1558,34 @@ mod tests {
         assert!(matches!(reply.reason, StopReason::Breakpoint));
         assert_eq!(reply.thread_id, Some(1));
     }
+
+    #[test]
+    fn parse_stop_reply_reads_the_mach_exception() {
+        let reply = parse_stop_reply(
+            "T0bthread:1a03;metype:1;mecount:2;medata:1;medata:10;reason:exception;",
+        )
+        .unwrap();
+        assert_eq!(reply.signal, 11);
+        let exception = reply.exception.unwrap();
+        assert_eq!(exception.name(), Some("EXC_BAD_ACCESS"));
+        assert_eq!(exception.fault_address(), Some(0x10));
+        assert_eq!(
+            exception.to_string(),
+            "EXC_BAD_ACCESS (code=1, address=0x10)"
+        );
+
+        let trap = MachException {
+            exception_type: 6,
+            data: vec![1, 0x1_0000_4f2c],
+        };
+        assert_eq!(trap.fault_address(), None);
+        assert_eq!(
+            trap.to_string(),
+            "EXC_BREAKPOINT (code=1, subcode=0x100004f2c)"
+        );
+        assert!(parse_stop_reply("T05thread:1;")
+            .unwrap()
+            .exception
+            .is_none());
+    }
 }
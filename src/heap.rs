@@ -0,0 +1,256 @@
+//! Object identification from raw memory, backing `heap_info`.
+//!
+//! Without running code in the target there is no `malloc_size` or
+//! `object_getClassName`, so the class is read straight out of the
+//! Objective-C runtime's structures: isa → class → `class_rw_t` →
+//! `class_ro_t` → name and instance size. Swift classes share that layout on
+//! Apple platforms, so Swift objects resolve the same way. The block size is
+//! the malloc size class the instance size falls into, which is what
+//! `malloc_size` would report for an object allocated by the runtime.
+
+use serde::Serialize;
+
+use crate::memory::{read_u64, POINTER_MASK};
+
+/// Pointer bits of an arm64 non-pointer isa (`ISA_MASK`); arm64e keeps more.
+const NONPOINTER_ISA_MASKS: [u64; 2] = [0x0000_000f_ffff_fff8, 0x007f_ffff_ffff_fff8];
+/// `FAST_DATA_MASK`: the `class_rw_t` pointer inside a class's `bits`.
+const FAST_DATA_MASK: u64 = 0x0000_7fff_ffff_fff8;
+/// `FAST_IS_SWIFT_LEGACY | FAST_IS_SWIFT_STABLE`.
+const FAST_IS_SWIFT: u64 = 0b11;
+/// `RW_REALIZED`: `data` is a `class_rw_t` rather than the `class_ro_t`.
+const RW_REALIZED: u32 = 1 << 31;
+/// Longest class name read before giving up on the pointer.
+const MAX_CLASS_NAME_LEN: usize = 256;
+/// Anything larger is not a plausible instance size.
+const MAX_INSTANCE_SIZE: u32 = 1 << 24;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ObjectClass {
+    /// Runtime name; Swift classes keep their `_TtC` mangling here.
+    pub name: String,
+    /// `Module.Type` for Swift classes whose name could be demangled.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub swift_name: Option<String>,
+    pub address: String,
+    /// `objc` or `swift`.
+    pub language: &'static str,
+    pub instance_size: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct HeapObject {
+    /// The object's first word as stored, before masking.
+    pub isa: String,
+    pub class: ObjectClass,
+    pub block_size: u64,
+}
+
+/// Objective-C tagged pointers (`NSNumber`, short `NSString`s) carry their
+/// value in the pointer itself; arm64 marks them with the top bit.
+pub fn is_tagged_pointer(address: u64) -> bool {
+    address >> 63 == 1
+}
+
+/// Reads the object at `address` through `read`, returning `None` when its
+/// first word does not lead to a plausible class.
+pub fn identify<R>(mut read: R, address: u64) -> Option<HeapObject>
+where
+    R: FnMut(u64, usize) -> Option<Vec<u8>>,
+{
+    let isa = read_u64(&mut read, address)?;
+    if isa == 0 {
+        return None;
+    }
+    let mut candidates: Vec<u64> = if isa & 1 == 1 {
+        NONPOINTER_ISA_MASKS.iter().map(|mask| isa & mask).collect()
+    } else {
+        vec![isa & POINTER_MASK]
+    };
+    candidates.dedup();
+    let class = candidates
+        .into_iter()
+        .find_map(|class| read_class(&mut read, class))?;
+    Some(HeapObject {
+        isa: format!("0x{isa:x}"),
+        block_size: malloc_block_size(class.instance_size.into()),
+        class,
+    })
+}
+
+/// The block malloc hands out for a `size`-byte allocation by the runtime:
+/// instances are at least 16 bytes and rounded to 16, tiny blocks (up to
+/// 1008 bytes) come in 16-byte quanta, small ones (up to 127 KiB) in 512-byte
+/// quanta, and anything larger in 16 KiB pages.
+pub fn malloc_block_size(size: u64) -> u64 {
+    let size = size.max(16);
+    let quantum = match size {
+        0..=1008 => 16,
+        1009..=130_048 => 512,
+        _ => 16 * 1024,
+    };
+    size.div_ceil(quantum) * quantum
+}
+
+/// `Module.Type` from an Objective-C-visible Swift class name such as
+/// `_TtC5MyApp4Cart` or `_TtCC5MyApp4Cart4Item`; generic and other
+/// mangled forms are left alone.
+pub fn demangle_class_name(name: &str) -> Option<String> {
+    let rest = name.strip_prefix("_Tt")?;
+    let nesting = rest.len() - rest.trim_start_matches('C').len();
+    if nesting == 0 {
+        return None;
+    }
+    let mut rest = &rest[nesting..];
+    let mut parts = Vec::with_capacity(nesting + 1);
+    if let Some(stdlib) = rest.strip_prefix('s') {
+        parts.push("Swift");
+        rest = stdlib;
+    }
+    while !rest.is_empty() {
+        let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        let len: usize = rest[..digits].parse().ok()?;
+        let ident = rest.get(digits..digits + len)?;
+        parts.push(ident);
+        rest = &rest[digits + len..];
+    }
+    (parts.len() == nesting + 1).then(|| parts.join("."))
+}
+
+/// The class at `class`, when it leads to a plausible `class_ro_t`.
+pub(crate) fn read_class<R>(read: &mut R, class: u64) -> Option<ObjectClass>
+where
+    R: FnMut(u64, usize) -> Option<Vec<u8>>,
+{
+    let (bits, ro) = class_ro(read, class)?;
+    // flags, instanceStart, instanceSize, reserved, ivarLayout, name.
+    let header = read(ro, 32)?;
+    let instance_size = u32::from_le_bytes(header.get(8..12)?.try_into().ok()?);
+    let name_ptr = u64::from_le_bytes(header.get(24..32)?.try_into().ok()?) & 0x0000_7fff_ffff_ffff;
+    if instance_size == 0 || instance_size > MAX_INSTANCE_SIZE {
+        return None;
+    }
+    let name = read_c_string(read, name_ptr, MAX_CLASS_NAME_LEN)?;
+    let swift = bits & FAST_IS_SWIFT != 0 || name.starts_with("_Tt");
+    Some(ObjectClass {
+        swift_name: demangle_class_name(&name),
+        name,
+        address: format!("0x{class:x}"),
+        language: if swift { "swift" } else { "objc" },
+        instance_size,
+    })
+}
+
+/// The `bits` word of the class at `class` and the address of its
+/// `class_ro_t`, found through the `class_rw_t` (and its extension) once the
+/// class is realized.
+pub(crate) fn class_ro<R>(read: &mut R, class: u64) -> Option<(u64, u64)>
+where
+    R: FnMut(u64, usize) -> Option<Vec<u8>>,
+{
+    if class == 0 {
+        return None;
+    }
+    // isa, superclass, cache (two words), bits.
+    let bits = read_u64(read, class + 32)?;
+    let data = bits & FAST_DATA_MASK;
+    if data == 0 {
+        return None;
+    }
+    let flags = u32::from_le_bytes(read(data, 4)?.get(..4)?.try_into().ok()?);
+    let ro = if flags & RW_REALIZED != 0 {
+        let ro_or_ext = read_u64(read, data + 8)?;
+        if ro_or_ext & 1 == 1 {
+            read_u64(read, ro_or_ext & POINTER_MASK)? & POINTER_MASK
+        } else {
+            ro_or_ext & POINTER_MASK
+        }
+    } else {
+        data
+    };
+    (ro != 0).then_some((bits, ro))
+}
+
+/// A NUL-terminated printable ASCII string of at most `max_len` bytes, read
+/// in small chunks so a string near the end of a mapping does not fail the
+/// whole read.
+pub(crate) fn read_c_string<R>(read: &mut R, address: u64, max_len: usize) -> Option<String>
+where
+    R: FnMut(u64, usize) -> Option<Vec<u8>>,
+{
+    const CHUNK: usize = 64;
+    let mut name = Vec::new();
+    while name.len() < max_len {
+        let chunk = read(address + name.len() as u64, CHUNK)?;
+        match chunk.iter().position(|&byte| byte == 0) {
+            Some(end) => {
+                name.extend_from_slice(&chunk[..end]);
+                let valid = !name.is_empty() && name.iter().all(u8::is_ascii_graphic);
+                return valid.then(|| String::from_utf8(name).ok()).flatten();
+            }
+            None if chunk.is_empty() => return None,
+            None => name.extend_from_slice(&chunk),
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::testing::Memory;
+
+    #[test]
+    fn identifies_swift_and_objc_objects() {
+        let mut memory = Memory::default();
+        // A Swift object with a non-pointer isa and a realized class.
+        memory.words(0x1_0000, &[0x0100_01a0_0000_2001, 2]);
+        memory.words(0x2000, &[0, 0, 0, 0, 0x3000 | 0b10]);
+        memory.words(0x3000, &[u64::from(RW_REALIZED), 0x4000]);
+        memory.words(0x4000, &[0, 40, 0, 0x5000]);
+        memory.put(0x5000, b"_TtC5MyApp4Cart\0");
+        let object = identify(|address, len| memory.read(address, len), 0x1_0000).unwrap();
+        assert_eq!(object.class.name, "_TtC5MyApp4Cart");
+        assert_eq!(object.class.swift_name.as_deref(), Some("MyApp.Cart"));
+        assert_eq!(object.class.language, "swift");
+        assert_eq!(object.class.address, "0x2000");
+        assert_eq!((object.class.instance_size, object.block_size), (40, 48));
+
+        // An Objective-C object whose class is not realized yet.
+        memory.words(0x1_1000, &[0x6000]);
+        memory.words(0x6000, &[0, 0, 0, 0, 0x7000]);
+        memory.words(0x7000, &[0, 16, 0, 0x8000]);
+        memory.put(0x8000, b"NSObject\0");
+        let object = identify(|address, len| memory.read(address, len), 0x1_1000).unwrap();
+        assert_eq!(object.class.name, "NSObject");
+        assert_eq!(object.class.language, "objc");
+        assert_eq!(object.block_size, 16);
+
+        // A plain buffer of text does not look like an object.
+        memory.put(0x1_2000, b"hello, world");
+        assert_eq!(
+            identify(|address, len| memory.read(address, len), 0x1_2000),
+            None
+        );
+    }
+
+    #[test]
+    fn sizes_tagged_pointers_and_names() {
+        assert_eq!(malloc_block_size(1), 16);
+        assert_eq!(malloc_block_size(1008), 1008);
+        assert_eq!(malloc_block_size(1009), 1024);
+        assert_eq!(malloc_block_size(200_000), 212_992);
+        assert!(is_tagged_pointer(0x8000_0000_0000_0a27));
+        assert!(!is_tagged_pointer(0x0000_0001_0203_0400));
+        assert_eq!(
+            demangle_class_name("_TtCC5MyApp4Cart4Item").as_deref(),
+            Some("MyApp.Cart.Item")
+        );
+        assert_eq!(
+            demangle_class_name("_TtCs12_SwiftObject").as_deref(),
+            Some("Swift._SwiftObject")
+        );
+        assert_eq!(demangle_class_name("_TtGC5MyApp3BoxSi_"), None);
+        assert_eq!(demangle_class_name("NSObject"), None);
+    }
+}
@@ -1,7 +1,29 @@
+pub mod artifacts;
 pub mod backend;
+pub mod breakpoint_file;
+pub mod crash;
+pub mod deadlock;
 pub mod debug_session;
+pub mod disasm;
+pub mod dwarf;
+pub mod exports;
+pub mod expression;
+pub mod fault;
+pub mod formatters;
 pub mod gdb_remote;
+pub mod heap;
+pub mod memory;
+pub mod mock;
+pub mod objc;
+pub mod protocol_trace;
+pub mod redaction;
+pub mod response_format;
+pub mod swift_demangle;
+pub mod swift_tasks;
 pub mod symbols;
+pub mod truncation;
+pub mod value_format;
+pub mod verbosity;
 
 use serde_json::{json, Value};
 use zed_extension_api::{
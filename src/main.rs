@@ -1,37 +1,123 @@
-use swiftscope::{
-    backend::{Backend, BackendStopEvent},
-    debug_session::init_backend,
-};
+use clap::Parser;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_json::{json, Value};
-use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::{
+    collections::BTreeMap,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    net::{SocketAddr, TcpListener},
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc, Mutex,
+    },
+    thread,
+};
+use swiftscope::{
+    backend::ModuleInfo,
+    debug_session::{
+        backend_from_program, init_backend, DebugEvent, DebugSession, DebugSessionError,
+        SessionStop,
+    },
+    gdb_remote::Interrupter,
+};
+
+#[derive(Debug, Parser)]
+#[command(about = "Debug Adapter Protocol server for iOS debug sessions")]
+struct Args {
+    /// Serve DAP clients one at a time on this address (e.g. 127.0.0.1:4711)
+    /// instead of over stdin/stdout.
+    #[arg(long)]
+    listen: Option<SocketAddr>,
+}
 
 fn main() -> io::Result<()> {
     let _ = env_logger::builder().format_timestamp(None).try_init();
-    let stdin = io::stdin();
-    let stdout = io::stdout();
-    let mut reader = BufReader::new(stdin.lock());
-    let writer = BufWriter::new(stdout.lock());
-    let backend = init_backend()?;
-    let mut session = Session::new(backend, writer);
-
-    while let Some(message) = read_dap_message(&mut reader)? {
-        let envelope: DapEnvelope = match serde_json::from_str(&message) {
-            Ok(payload) => payload,
+    let args = Args::parse();
+    let Some(addr) = args.listen else {
+        return serve(BufReader::new(io::stdin()), BufWriter::new(io::stdout()));
+    };
+    let listener = TcpListener::bind(addr)?;
+    eprintln!("DAP server listening on {}", listener.local_addr()?);
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let reader = BufReader::new(stream.try_clone()?);
+        if let Err(err) = serve(reader, BufWriter::new(stream)) {
+            eprintln!("DAP client failed: {err}");
+        }
+    }
+    Ok(())
+}
+
+/// Runs one client until `disconnect` or the end of its input. Requests are
+/// read on their own thread so that `pause` can interrupt a `continue` that
+/// is still waiting for the target.
+fn serve<R, W>(mut reader: R, writer: W) -> io::Result<()>
+where
+    R: BufRead + Send + 'static,
+    W: Write,
+{
+    let pauser = Arc::new(Pauser::default());
+    let (requests_tx, requests) = mpsc::channel();
+    let reader_pauser = pauser.clone();
+    thread::spawn(move || loop {
+        let message = match read_dap_message(&mut reader) {
+            Ok(Some(message)) => message,
+            Ok(None) => break,
             Err(err) => {
-                eprintln!("Failed to parse DAP message: {err}");
-                continue;
+                eprintln!("Failed to read DAP message: {err}");
+                break;
             }
         };
+        match serde_json::from_str(&message) {
+            Ok(DapEnvelope::Request(request)) => {
+                if request.command == "pause" {
+                    reader_pauser.pause();
+                }
+                if requests_tx.send(request).is_err() {
+                    break;
+                }
+            }
+            Ok(DapEnvelope::Other) => {}
+            Err(err) => eprintln!("Failed to parse DAP message: {err}"),
+        }
+    });
 
-        if let DapEnvelope::Request(request) = envelope {
-            if !session.handle_request(request)? {
-                break;
+    let mut session = Session::new(writer, pauser);
+    for request in requests {
+        if !session.handle_request(request)? {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Stops the target for `pause` from the reader thread.
+#[derive(Default)]
+struct Pauser {
+    interrupter: Mutex<Option<Interrupter>>,
+    requested: AtomicBool,
+}
+
+impl Pauser {
+    fn arm(&self, interrupter: Option<Interrupter>) {
+        if let Ok(mut slot) = self.interrupter.lock() {
+            *slot = interrupter;
+        }
+    }
+
+    fn pause(&self) {
+        self.requested.store(true, Ordering::SeqCst);
+        if let Ok(slot) = self.interrupter.lock() {
+            if let Some(Err(err)) = slot.as_ref().map(Interrupter::interrupt) {
+                eprintln!("Failed to interrupt the target: {err}");
             }
         }
     }
 
-    Ok(())
+    /// Whether a `pause` arrived since the last call.
+    fn take_requested(&self) -> bool {
+        self.requested.swap(false, Ordering::SeqCst)
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -57,6 +143,9 @@ struct LaunchArguments {
     debugserver_port: u16,
     program: String,
     cwd: Option<String>,
+    /// Build path prefix to local path prefix.
+    #[serde(rename = "sourceMap", default)]
+    source_map: BTreeMap<String, String>,
 }
 
 #[derive(Deserialize)]
@@ -65,6 +154,8 @@ struct AttachArguments {
     debugserver_port: u16,
     program: Option<String>,
     cwd: Option<String>,
+    #[serde(rename = "sourceMap", default)]
+    source_map: BTreeMap<String, String>,
 }
 
 #[derive(Deserialize)]
@@ -88,6 +179,11 @@ struct SourceBreakpoint {
 struct StackTraceArguments {
     #[serde(rename = "threadId")]
     thread_id: i64,
+    #[serde(rename = "startFrame", default)]
+    start_frame: usize,
+    /// 0 means all of them.
+    #[serde(default)]
+    levels: usize,
 }
 
 #[derive(Deserialize)]
@@ -105,22 +201,42 @@ struct ThreadArguments {
 #[derive(Deserialize)]
 struct ScopesArguments {
     #[serde(rename = "frameId")]
-    _frame_id: i64,
+    frame_id: i64,
+}
+
+#[derive(Deserialize)]
+struct EvaluateArguments {
+    expression: String,
+    #[serde(rename = "frameId")]
+    frame_id: Option<i64>,
 }
 
+/// DAP messages the session's sinks produced while a request ran, written
+/// out after its response.
+type Outbox = Arc<Mutex<Vec<(&'static str, Value)>>>;
+
 struct Session<W: Write> {
     next_seq: i64,
     initialized: bool,
-    backend: Backend,
+    /// Set by `launch` or `attach`.
+    debug: Option<DebugSession>,
+    outbox: Outbox,
+    /// Thread and frame index behind each frame id handed out since the
+    /// target last resumed; frame id `n` is entry `n - 1`.
+    frames: Vec<(i64, usize)>,
+    pauser: Arc<Pauser>,
     writer: W,
 }
 
 impl<W: Write> Session<W> {
-    fn new(backend: Backend, writer: W) -> Self {
+    fn new(writer: W, pauser: Arc<Pauser>) -> Self {
         Self {
             next_seq: 1,
             initialized: false,
-            backend,
+            debug: None,
+            outbox: Outbox::default(),
+            frames: Vec::new(),
+            pauser,
             writer,
         }
     }
@@ -132,25 +248,42 @@ impl<W: Write> Session<W> {
             arguments,
         } = request;
         let command_str = command.as_str();
-        match command_str {
+        let keep_going = match command_str {
             "initialize" => self.handle_initialize(seq, command_str),
             "launch" => self.handle_launch(seq, command_str, arguments),
             "attach" => self.handle_attach(seq, command_str, arguments),
             "setBreakpoints" => self.handle_set_breakpoints(seq, command_str, arguments),
-            "configurationDone" => self.handle_simple_ok(seq, command_str, Value::Null),
+            "setExceptionBreakpoints" => {
+                self.handle_simple_ok(seq, command_str, json!({ "breakpoints": [] }))
+            }
+            "configurationDone" => self.handle_configuration_done(seq, command_str),
             "threads" => self.handle_threads(seq, command_str),
+            "modules" => self.handle_modules(seq, command_str),
             "stackTrace" => self.handle_stack_trace(seq, command_str, arguments),
             "scopes" => self.handle_scopes(seq, command_str, arguments),
             "variables" => self.handle_variables(seq, command_str, arguments),
-            "continue" => self.handle_continue(seq, command_str, arguments),
-            "next" => self.handle_next(seq, command_str, arguments),
-            "stepIn" => self.handle_step_in(seq, command_str, arguments),
+            "evaluate" => self.handle_evaluate(seq, command_str, arguments),
+            "continue" => self.handle_resume(
+                seq,
+                command_str,
+                arguments,
+                DebugSession::continue_execution,
+            ),
+            "next" => self.handle_resume(seq, command_str, arguments, DebugSession::next),
+            "stepIn" => self.handle_resume(seq, command_str, arguments, DebugSession::step_in),
+            "stepOut" => self.handle_resume(seq, command_str, arguments, DebugSession::step_out),
+            // The reader thread already interrupted the target; the stop
+            // arrives on the `continue` that was waiting for it.
+            "pause" => self.handle_simple_ok(seq, command_str, Value::Null),
+            "terminate" => self.handle_terminate(seq, command_str),
             "disconnect" => self.handle_disconnect(seq, command_str),
             _ => {
                 self.send_error_response(seq, command_str, format!("Unknown command: {command}"))?;
                 Ok(true)
             }
-        }
+        }?;
+        self.flush_outbox()?;
+        Ok(keep_going)
     }
 
     fn handle_initialize(&mut self, seq: i64, command: &str) -> io::Result<bool> {
@@ -161,6 +294,9 @@ impl<W: Write> Session<W> {
             true,
             Some(json!({
                 "supportsConfigurationDoneRequest": true,
+                "supportsEvaluateForHovers": true,
+                "supportsModulesRequest": true,
+                "supportsTerminateRequest": true,
             })),
             None,
         )?;
@@ -177,7 +313,7 @@ impl<W: Write> Session<W> {
             }
         };
 
-        if let Err(err) = self.backend.connect_debugserver(args.debugserver_port) {
+        if let Err(err) = self.open(Some(&args.program), args.debugserver_port, &args.source_map) {
             self.send_error_response(seq, command, err)?;
             return Ok(true);
         }
@@ -202,7 +338,11 @@ impl<W: Write> Session<W> {
             }
         };
 
-        if let Err(err) = self.backend.connect_debugserver(args.debugserver_port) {
+        if let Err(err) = self.open(
+            args.program.as_deref(),
+            args.debugserver_port,
+            &args.source_map,
+        ) {
             self.send_error_response(seq, command, err)?;
             return Ok(true);
         }
@@ -218,6 +358,52 @@ impl<W: Write> Session<W> {
         )
     }
 
+    /// Loads `program` (or the one `IOS_LLDB_DAP_CONFIG` names) and connects
+    /// to debugserver on `port`.
+    fn open(
+        &mut self,
+        program: Option<&str>,
+        port: u16,
+        source_map: &BTreeMap<String, String>,
+    ) -> Result<(), String> {
+        let backend = match program {
+            Some(program) => backend_from_program(Path::new(program), None, None),
+            None => init_backend(),
+        }
+        .map_err(|err| err.to_string())?;
+        let mut debug = DebugSession::new(backend);
+        for (from, to) in source_map {
+            debug.add_source_map(from, to);
+        }
+        let outbox = self.outbox.clone();
+        debug.set_log_sink(Box::new(move |message| {
+            if let Ok(mut outbox) = outbox.lock() {
+                outbox.push((
+                    "output",
+                    json!({ "category": "console", "output": format!("{message}\n") }),
+                ));
+            }
+        }));
+        let (outbox, pauser) = (self.outbox.clone(), self.pauser.clone());
+        debug.set_event_sink(Box::new(move |event| {
+            if let Ok(mut outbox) = outbox.lock() {
+                outbox.extend(dap_events(event, &pauser));
+            }
+        }));
+        debug
+            .connect_debugserver(port)
+            .map_err(|err| err.to_string())?;
+        self.pauser.arm(debug.interrupter());
+        self.debug = Some(debug);
+        Ok(())
+    }
+
+    fn debug(&mut self) -> Result<&mut DebugSession, String> {
+        self.debug
+            .as_mut()
+            .ok_or_else(|| "no debug session; send launch or attach first".to_string())
+    }
+
     fn handle_set_breakpoints(
         &mut self,
         seq: i64,
@@ -237,18 +423,30 @@ impl<W: Write> Session<W> {
             return Ok(true);
         };
 
-        let lines: Vec<i64> = args.breakpoints.iter().map(|bp| bp.line).collect();
-        if let Err(err) = self.backend.update_breakpoints(&path, &lines) {
-            self.send_error_response(seq, command, err)?;
-            return Ok(true);
-        }
-
-        let breakpoints: Vec<_> = args
+        let lines: Vec<u32> = args
             .breakpoints
+            .iter()
+            .map(|bp| bp.line.max(0) as u32)
+            .collect();
+        let result = self.debug().and_then(|debug| {
+            debug
+                .replace_file_breakpoints(&path, &lines)
+                .map_err(|err| err.to_string())
+        });
+        let breakpoints = match result {
+            Ok(breakpoints) => breakpoints,
+            Err(err) => {
+                self.send_error_response(seq, command, err)?;
+                return Ok(true);
+            }
+        };
+
+        let breakpoints: Vec<_> = breakpoints
             .into_iter()
             .map(|bp| {
                 json!({
-                    "verified": true,
+                    "id": bp.id,
+                    "verified": bp.verified,
                     "line": bp.line,
                 })
             })
@@ -257,8 +455,61 @@ impl<W: Write> Session<W> {
         self.handle_simple_ok(seq, command, json!({ "breakpoints": breakpoints }))
     }
 
+    /// debugserver holds the target stopped until it is continued, so the
+    /// client is told it stopped on entry.
+    fn handle_configuration_done(&mut self, seq: i64, command: &str) -> io::Result<bool> {
+        self.handle_simple_ok(seq, command, Value::Null)?;
+        if let Some(debug) = self.debug.as_ref().filter(|debug| debug.is_connected()) {
+            let thread_id = debug.selected_thread();
+            self.emit_event(
+                "stopped",
+                json!({ "reason": "entry", "threadId": thread_id, "allThreadsStopped": true }),
+            )?;
+        }
+        Ok(true)
+    }
+
     fn handle_threads(&mut self, seq: i64, command: &str) -> io::Result<bool> {
-        self.handle_simple_ok(seq, command, json!({ "threads": self.backend.threads() }))
+        let threads: Vec<Value> = match self.debug() {
+            Ok(debug) => debug
+                .threads()
+                .into_iter()
+                .map(|thread| {
+                    let name = if thread.name.is_empty() {
+                        format!("Thread {}", thread.id)
+                    } else {
+                        thread.name
+                    };
+                    json!({ "id": thread.id, "name": name })
+                })
+                .collect(),
+            Err(err) => {
+                self.send_error_response(seq, command, err)?;
+                return Ok(true);
+            }
+        };
+        self.handle_simple_ok(seq, command, json!({ "threads": threads }))
+    }
+
+    fn handle_modules(&mut self, seq: i64, command: &str) -> io::Result<bool> {
+        let result = self
+            .debug()
+            .and_then(|debug| debug.modules().map_err(|err| err.to_string()));
+        match result {
+            Ok(modules) => {
+                let modules: Vec<Value> = modules.iter().map(dap_module).collect();
+                let total = modules.len();
+                self.handle_simple_ok(
+                    seq,
+                    command,
+                    json!({ "modules": modules, "totalModules": total }),
+                )
+            }
+            Err(err) => {
+                self.send_error_response(seq, command, err)?;
+                Ok(true)
+            }
+        }
     }
 
     fn handle_stack_trace(
@@ -274,89 +525,161 @@ impl<W: Write> Session<W> {
                 return Ok(true);
             }
         };
-        let frames = self.backend.stack_trace(args.thread_id);
+        let debug = match self.debug() {
+            Ok(debug) => debug,
+            Err(err) => {
+                self.send_error_response(seq, command, err)?;
+                return Ok(true);
+            }
+        };
+        let frames = debug.stacktrace_for(args.thread_id);
+        let total = frames.len();
+        let levels = if args.levels == 0 { total } else { args.levels };
+        let mut stack_frames = Vec::new();
+        for frame in frames.into_iter().skip(args.start_frame).take(levels) {
+            let path = debug
+                .local_source_path(&frame.file)
+                .map(|path| path.display().to_string())
+                .unwrap_or_else(|| frame.file.clone());
+            let source = (frame.file != "<unknown>").then(|| {
+                let name = path.rsplit(['/', '\\']).next().unwrap_or(&path).to_string();
+                json!({ "name": name, "path": path })
+            });
+            stack_frames.push((frame, source));
+        }
+        let body: Vec<Value> = stack_frames
+            .into_iter()
+            .map(|(frame, source)| {
+                let id = self.frame_id(args.thread_id, frame.frame_index);
+                json!({
+                    "id": id,
+                    "name": frame.function,
+                    "line": frame.line,
+                    "column": 1,
+                    "source": source,
+                    "presentationHint": if frame.frame_index == 0 { "normal" } else { "subtle" },
+                })
+            })
+            .collect();
         self.handle_simple_ok(
             seq,
             command,
             json!({
-                "stackFrames": frames,
-                "totalFrames": 2,
+                "stackFrames": body,
+                "totalFrames": total,
             }),
         )
     }
 
-    fn handle_scopes(&mut self, seq: i64, command: &str, arguments: Value) -> io::Result<bool> {
-        let _args: ScopesArguments = match parse_arguments(arguments) {
-            Ok(args) => args,
-            Err(err) => {
-                self.send_error_response(seq, command, err)?;
-                return Ok(true);
+    fn frame_id(&mut self, thread_id: i64, index: usize) -> i64 {
+        let position = match self
+            .frames
+            .iter()
+            .position(|&frame| frame == (thread_id, index))
+        {
+            Some(position) => position,
+            None => {
+                self.frames.push((thread_id, index));
+                self.frames.len() - 1
             }
         };
+        position as i64 + 1
+    }
 
-        self.handle_simple_ok(seq, command, json!({ "scopes": self.backend.scopes() }))
+    /// Makes the frame behind `frame_id` the one `scopes` and `evaluate`
+    /// look at.
+    fn select_frame(&mut self, frame_id: i64) -> Result<(), String> {
+        let &(thread_id, index) = usize::try_from(frame_id - 1)
+            .ok()
+            .and_then(|position| self.frames.get(position))
+            .ok_or_else(|| format!("unknown frame id {frame_id}"))?;
+        let debug = self.debug()?;
+        debug.select_thread(thread_id);
+        debug.select_frame(index).map_err(|err| err.to_string())?;
+        Ok(())
     }
 
-    fn handle_variables(&mut self, seq: i64, command: &str, arguments: Value) -> io::Result<bool> {
-        let args: VariablesArguments = match parse_arguments(arguments) {
+    fn handle_scopes(&mut self, seq: i64, command: &str, arguments: Value) -> io::Result<bool> {
+        let args: ScopesArguments = match parse_arguments(arguments) {
             Ok(args) => args,
             Err(err) => {
                 self.send_error_response(seq, command, err)?;
                 return Ok(true);
             }
         };
-        self.handle_simple_ok(
-            seq,
-            command,
-            json!({ "variables": self.backend.variables(args.variables_reference) }),
-        )
+        let scopes = self
+            .select_frame(args.frame_id)
+            .and_then(|()| self.debug().map(|debug| debug.scopes()));
+        match scopes {
+            Ok(scopes) => self.handle_simple_ok(seq, command, json!({ "scopes": scopes })),
+            Err(err) => {
+                self.send_error_response(seq, command, err)?;
+                Ok(true)
+            }
+        }
     }
 
-    fn handle_continue(&mut self, seq: i64, command: &str, arguments: Value) -> io::Result<bool> {
-        let args: ThreadArguments = match parse_arguments(arguments) {
+    fn handle_variables(&mut self, seq: i64, command: &str, arguments: Value) -> io::Result<bool> {
+        let args: VariablesArguments = match parse_arguments(arguments) {
             Ok(args) => args,
             Err(err) => {
                 self.send_error_response(seq, command, err)?;
                 return Ok(true);
             }
         };
-        let stop_event = match self.backend.r#continue(args.thread_id) {
-            Ok(event) => event,
+        let variables = match self.debug() {
+            Ok(debug) => debug.variables_for_reference(args.variables_reference),
             Err(err) => {
                 self.send_error_response(seq, command, err)?;
                 return Ok(true);
             }
         };
-        self.handle_simple_ok(seq, command, json!({ "allThreadsContinued": true }))?;
-        if let Some(event) = stop_event {
-            self.emit_stop_event(event)?;
-        }
-        Ok(true)
+        self.handle_simple_ok(seq, command, json!({ "variables": variables }))
     }
 
-    fn handle_next(&mut self, seq: i64, command: &str, arguments: Value) -> io::Result<bool> {
-        let args: ThreadArguments = match parse_arguments(arguments) {
+    fn handle_evaluate(&mut self, seq: i64, command: &str, arguments: Value) -> io::Result<bool> {
+        let args: EvaluateArguments = match parse_arguments(arguments) {
             Ok(args) => args,
             Err(err) => {
                 self.send_error_response(seq, command, err)?;
                 return Ok(true);
             }
         };
-        let stop_event = match self.backend.step_over(args.thread_id) {
-            Ok(event) => event,
+        let result = args
+            .frame_id
+            .map_or(Ok(()), |frame_id| self.select_frame(frame_id))
+            .and_then(|()| self.debug())
+            .and_then(|debug| {
+                debug
+                    .evaluate(&args.expression)
+                    .map_err(|err| err.to_string())
+            });
+        match result {
+            Ok(value) => self.handle_simple_ok(
+                seq,
+                command,
+                json!({
+                    "result": value.result,
+                    "type": value.ty,
+                    "variablesReference": value.variables_reference,
+                }),
+            ),
             Err(err) => {
                 self.send_error_response(seq, command, err)?;
-                return Ok(true);
+                Ok(true)
             }
-        };
-        self.handle_simple_ok(seq, command, Value::Null)?;
-        if let Some(event) = stop_event {
-            self.emit_stop_event(event)?;
         }
-        Ok(true)
     }
 
-    fn handle_step_in(&mut self, seq: i64, command: &str, arguments: Value) -> io::Result<bool> {
+    /// `continue` and the steps. The response goes out before the target
+    /// runs; the stop is reported by the `stopped` event that follows.
+    fn handle_resume(
+        &mut self,
+        seq: i64,
+        command: &str,
+        arguments: Value,
+        resume: fn(&mut DebugSession) -> Result<Option<SessionStop>, DebugSessionError>,
+    ) -> io::Result<bool> {
         let args: ThreadArguments = match parse_arguments(arguments) {
             Ok(args) => args,
             Err(err) => {
@@ -364,24 +687,54 @@ impl<W: Write> Session<W> {
                 return Ok(true);
             }
         };
-        let stop_event = match self.backend.step_in(args.thread_id) {
-            Ok(event) => event,
+        let debug = match self.debug() {
+            Ok(debug) if debug.is_connected() => debug,
+            Ok(_) => {
+                self.send_error_response(seq, command, "the target is not connected".into())?;
+                return Ok(true);
+            }
             Err(err) => {
                 self.send_error_response(seq, command, err)?;
                 return Ok(true);
             }
         };
-        self.handle_simple_ok(seq, command, Value::Null)?;
-        if let Some(event) = stop_event {
-            self.emit_stop_event(event)?;
+        debug.select_thread(args.thread_id);
+        self.frames.clear();
+        let body = if command == "continue" {
+            json!({ "allThreadsContinued": true })
+        } else {
+            Value::Null
+        };
+        self.handle_simple_ok(seq, command, body)?;
+        self.flush_outbox()?;
+        if let Some(Err(err)) = self.debug.as_mut().map(resume) {
+            self.emit_event(
+                "output",
+                json!({ "category": "important", "output": format!("{command} failed: {err}\n") }),
+            )?;
         }
         Ok(true)
     }
 
+    fn handle_terminate(&mut self, seq: i64, command: &str) -> io::Result<bool> {
+        let result = self
+            .debug()
+            .and_then(|debug| debug.terminate().map_err(|err| err.to_string()));
+        match result {
+            Ok(_) => self.handle_simple_ok(seq, command, Value::Null),
+            Err(err) => {
+                self.send_error_response(seq, command, err)?;
+                Ok(true)
+            }
+        }
+    }
+
     fn handle_disconnect(&mut self, seq: i64, command: &str) -> io::Result<bool> {
-        if let Err(err) = self.backend.disconnect() {
-            self.send_error_response(seq, command, err)?;
-            return Ok(true);
+        if let Some(debug) = self.debug.as_mut().filter(|debug| debug.is_connected()) {
+            if let Err(err) = debug.disconnect() {
+                self.send_error_response(seq, command, err.to_string())?;
+                return Ok(true);
+            }
         }
         self.handle_simple_ok(seq, command, Value::Null)?;
         Ok(false)
@@ -432,15 +785,15 @@ impl<W: Write> Session<W> {
         write_dap_message(&mut self.writer, &event)
     }
 
-    fn emit_stop_event(&mut self, event: BackendStopEvent) -> io::Result<()> {
-        self.emit_event(
-            "stopped",
-            json!({
-                "reason": event.reason,
-                "description": event.description,
-                "threadId": event.thread_id
-            }),
-        )
+    fn flush_outbox(&mut self) -> io::Result<()> {
+        let events = match self.outbox.lock() {
+            Ok(mut outbox) => std::mem::take(&mut *outbox),
+            Err(_) => return Ok(()),
+        };
+        for (event, body) in events {
+            self.emit_event(event, body)?;
+        }
+        Ok(())
     }
 
     fn next_seq(&mut self) -> i64 {
@@ -450,6 +803,83 @@ impl<W: Write> Session<W> {
     }
 }
 
+/// The DAP events for a session event. A stop caused by `pause` is
+/// reported as one rather than as the signal that delivered it.
+fn dap_events(event: DebugEvent, pauser: &Pauser) -> Vec<(&'static str, Value)> {
+    match event {
+        DebugEvent::Continued { thread_id } => vec![(
+            "continued",
+            json!({ "threadId": thread_id, "allThreadsContinued": true }),
+        )],
+        DebugEvent::Stopped {
+            reason,
+            description,
+            thread_id,
+            exception,
+            ..
+        } => {
+            let reason = match reason.as_str() {
+                "signal" if pauser.take_requested() => "pause",
+                "signal" => "exception",
+                "breakpoint" => "breakpoint",
+                "main_thread_checker" => "exception",
+                _ => "step",
+            };
+            let mut body = json!({
+                "reason": reason,
+                "description": description,
+                "threadId": thread_id,
+                "allThreadsStopped": true,
+            });
+            if let Some(text) = exception.and_then(|e| e.exception.or(e.signal_name)) {
+                body["text"] = Value::String(text);
+            }
+            vec![("stopped", body)]
+        }
+        DebugEvent::BreakpointHit { .. } => Vec::new(),
+        // Already on the console through its `[trace]` log line.
+        DebugEvent::FunctionCalled(_) => Vec::new(),
+        DebugEvent::BreakpointResolved { id, line, .. } => vec![(
+            "breakpoint",
+            json!({ "reason": "changed", "breakpoint": { "id": id, "verified": true, "line": line } }),
+        )],
+        DebugEvent::MainThreadViolation(violation) => vec![(
+            "output",
+            json!({ "category": "important", "output": format!("{}\n", violation.message) }),
+        )],
+        DebugEvent::ThreadCreated { thread_id, .. } => vec![(
+            "thread",
+            json!({ "reason": "started", "threadId": thread_id }),
+        )],
+        DebugEvent::ThreadExited { thread_id } => vec![(
+            "thread",
+            json!({ "reason": "exited", "threadId": thread_id }),
+        )],
+        DebugEvent::ModuleLoaded(module) => vec![(
+            "module",
+            json!({ "reason": "new", "module": dap_module(&module) }),
+        )],
+        DebugEvent::ModuleUnloaded { path, .. } => {
+            let name = path.rsplit('/').next().unwrap_or(&path).to_string();
+            vec![(
+                "module",
+                json!({ "reason": "removed", "module": { "id": path, "name": name } }),
+            )]
+        }
+        DebugEvent::ProcessExited { status } => {
+            let code = match (status.exit_code, status.signal) {
+                (Some(code), _) => i64::from(code),
+                (None, Some(signal)) => 128 + i64::from(signal),
+                (None, None) => 0,
+            };
+            vec![
+                ("exited", json!({ "exitCode": code })),
+                ("terminated", Value::Null),
+            ]
+        }
+    }
+}
+
 #[derive(Serialize)]
 struct Response<'a> {
     seq: i64,
@@ -521,12 +951,28 @@ fn read_dap_message<R: BufRead>(reader: &mut R) -> io::Result<Option<String>> {
 fn write_dap_message<W: Write, T: Serialize>(writer: &mut W, payload: &T) -> io::Result<()> {
     let json = serde_json::to_string(payload)
         .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))?;
-    let header = format!("Content-Length: {}\r\n\r\n", json.as_bytes().len());
+    let header = format!("Content-Length: {}\r\n\r\n", json.len());
     writer.write_all(header.as_bytes())?;
     writer.write_all(json.as_bytes())?;
     writer.flush()
 }
 
+/// A DAP `Module`, identified by its path.
+fn dap_module(module: &ModuleInfo) -> Value {
+    let name = module.path.rsplit('/').next().unwrap_or(&module.path);
+    json!({
+        "id": module.path,
+        "name": name,
+        "path": module.path,
+        "addressRange": module.load_address,
+        "symbolStatus": if module.symbols.is_some() {
+            "Symbols loaded."
+        } else {
+            "Symbols not found."
+        },
+    })
+}
+
 fn parse_arguments<T: DeserializeOwned>(value: Value) -> Result<T, String> {
     serde_json::from_value(value).map_err(|err| err.to_string())
 }
@@ -535,7 +981,10 @@ fn parse_arguments<T: DeserializeOwned>(value: Value) -> Result<T, String> {
 mod tests {
     use super::*;
     use addr2line::Loader;
-    use swiftscope::symbols::{Image, SymbolContext};
+    use swiftscope::{
+        backend::{Backend, ExitStatus},
+        symbols::{Image, SymbolContext},
+    };
 
     #[derive(Serialize)]
     struct DummyResponse<'a> {
@@ -598,7 +1047,7 @@ mod tests {
 
     #[test]
     fn session_handles_initialize_request() {
-        let mut session = Session::new(test_backend(), Vec::new());
+        let mut session = Session::new(Vec::new(), Arc::default());
         let request = RawRequest {
             seq: 1,
             command: "initialize".into(),
@@ -619,7 +1068,7 @@ mod tests {
 
     #[test]
     fn session_handles_unknown_command() {
-        let mut session = Session::new(test_backend(), Vec::new());
+        let mut session = Session::new(Vec::new(), Arc::default());
         let request = RawRequest {
             seq: 1,
             command: "bogus".into(),
@@ -637,13 +1086,135 @@ mod tests {
         );
     }
 
+    #[test]
+    fn requests_before_launch_fail_with_a_hint() {
+        let mut session = Session::new(Vec::new(), Arc::default());
+        let request = RawRequest {
+            seq: 1,
+            command: "threads".into(),
+            arguments: Value::Null,
+        };
+        assert!(session.handle_request(request).unwrap());
+        let output = String::from_utf8(session.writer.clone()).unwrap();
+        assert!(
+            output.contains("send launch or attach first"),
+            "threads before launch should explain why it failed: {output}"
+        );
+    }
+
+    #[test]
+    fn set_breakpoints_replaces_the_file_set() {
+        let mut session = Session::new(Vec::new(), Arc::default());
+        session.debug = Some(DebugSession::new(test_backend()));
+        let request = |seq, lines: &[i64]| RawRequest {
+            seq,
+            command: "setBreakpoints".into(),
+            arguments: json!({
+                "source": { "path": "src/main.rs" },
+                "breakpoints": lines.iter().map(|line| json!({ "line": line })).collect::<Vec<_>>(),
+            }),
+        };
+        session.handle_request(request(1, &[10, 20])).unwrap();
+        session.handle_request(request(2, &[20])).unwrap();
+        let output = String::from_utf8(session.writer.clone()).unwrap();
+        let last = output.rsplit("\r\n\r\n").next().unwrap();
+        let response: Value = serde_json::from_str(last).unwrap();
+        assert_eq!(response["success"], true, "{last}");
+        let lines: Vec<_> = response["body"]["breakpoints"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|bp| bp["line"].as_u64().unwrap())
+            .collect();
+        // Line 20 is a `use` line; the breakpoint moves to the next code.
+        assert_eq!(lines.len(), 1, "{last}");
+        assert!(lines[0] > 20, "{last}");
+    }
+
+    #[test]
+    fn frame_ids_map_back_to_thread_and_index() {
+        let mut session = Session::new(Vec::new(), Arc::default());
+        assert_eq!(session.frame_id(3, 0), 1);
+        assert_eq!(session.frame_id(3, 1), 2);
+        assert_eq!(session.frame_id(3, 0), 1);
+        assert_eq!(session.frames, vec![(3, 0), (3, 1)]);
+        assert!(session
+            .select_frame(9)
+            .unwrap_err()
+            .contains("unknown frame id 9"));
+    }
+
+    #[test]
+    fn session_events_map_to_dap_events() {
+        let pauser = Pauser::default();
+        let stop = |reason: &str| DebugEvent::Stopped {
+            reason: reason.into(),
+            description: String::new(),
+            thread_id: 1,
+            exception: None,
+            diagnosis: None,
+            function: None,
+            file: None,
+            line: None,
+            summary: None,
+        };
+        let reason = |events: Vec<(&str, Value)>| events[0].1["reason"].clone();
+        assert_eq!(
+            reason(dap_events(stop("breakpoint"), &pauser)),
+            "breakpoint"
+        );
+        assert_eq!(reason(dap_events(stop("signal"), &pauser)), "exception");
+        pauser.pause();
+        assert_eq!(reason(dap_events(stop("signal"), &pauser)), "pause");
+        assert_eq!(reason(dap_events(stop("signal"), &pauser)), "exception");
+
+        let exited = dap_events(
+            DebugEvent::ProcessExited {
+                status: ExitStatus {
+                    exit_code: Some(3),
+                    signal: None,
+                },
+            },
+            &pauser,
+        );
+        let names: Vec<_> = exited.iter().map(|(event, _)| *event).collect();
+        assert_eq!(names, ["exited", "terminated"]);
+        assert_eq!(exited[0].1["exitCode"], 3);
+
+        let resolved = dap_events(
+            DebugEvent::BreakpointResolved {
+                id: 4,
+                file: "App.swift".into(),
+                line: 12,
+            },
+            &pauser,
+        );
+        assert_eq!(resolved[0].0, "breakpoint");
+        assert_eq!(
+            resolved[0].1["breakpoint"],
+            json!({ "id": 4, "verified": true, "line": 12 })
+        );
+
+        let unloaded = dap_events(
+            DebugEvent::ModuleUnloaded {
+                path: "/usr/lib/libPlugin.dylib".into(),
+                load_address: "0x104000000".into(),
+            },
+            &pauser,
+        );
+        assert_eq!(unloaded[0].1["reason"], "removed");
+        assert_eq!(unloaded[0].1["module"]["name"], "libPlugin.dylib");
+    }
+
     fn test_backend() -> Backend {
         let exe = std::env::current_exe().unwrap();
         let loader = Loader::new(&exe).unwrap();
         let image = Image {
             name: "test".into(),
-            path: exe.into(),
+            path: exe,
             uuid: None,
+            dsym: None,
+            slice: None,
             vmaddr_text: 0,
             slide: 0,
             dwarf: loader,
@@ -0,0 +1,116 @@
+//! Decoding arm64 target memory: stripping pointer authentication bits and
+//! reading little-endian words, shared by the modules that walk runtime
+//! structures ([`crate::heap`], [`crate::objc`], [`crate::swift_tasks`],
+//! [`crate::formatters`]).
+
+/// Strips pointer-authentication and tag bits from a data pointer.
+pub const POINTER_MASK: u64 = 0x0000_7fff_ffff_fff8;
+/// Strips pointer-authentication bits from a code pointer.
+pub const CODE_POINTER_MASK: u64 = 0x0000_7fff_ffff_fffc;
+
+/// The first eight bytes of `bytes` as a little-endian `u64`; missing bytes
+/// read as zero.
+pub fn le_u64(bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .take(8)
+        .enumerate()
+        .fold(0u64, |acc, (idx, byte)| {
+            acc | (u64::from(*byte) << (idx * 8))
+        })
+}
+
+/// The first four bytes of `bytes` as a little-endian `u32`; missing bytes
+/// read as zero.
+pub fn le_u32(bytes: &[u8]) -> u32 {
+    le_u64(&bytes[..bytes.len().min(4)]) as u32
+}
+
+/// Reads the `u64` at `address`, or `None` when fewer than eight bytes
+/// could be read.
+pub(crate) fn read_u64<R>(read: &mut R, address: u64) -> Option<u64>
+where
+    R: FnMut(u64, usize) -> Option<Vec<u8>>,
+{
+    read(address, 8)
+        .filter(|bytes| bytes.len() >= 8)
+        .map(|bytes| le_u64(&bytes))
+}
+
+/// Sparse target memory for tests of the modules that read it.
+#[cfg(test)]
+pub(crate) mod testing {
+    use std::collections::BTreeMap;
+
+    /// Sparse little-endian memory. Reads stop short at the first byte never
+    /// written, unless it was made [`zero_filled`](Memory::zero_filled).
+    #[derive(Default)]
+    pub(crate) struct Memory {
+        bytes: BTreeMap<u64, u8>,
+        zero_filled: bool,
+    }
+
+    impl Memory {
+        /// Memory whose unwritten bytes read as zero, except in the first
+        /// page, which stays unreadable like the null page.
+        pub(crate) fn zero_filled() -> Self {
+            Self {
+                zero_filled: true,
+                ..Self::default()
+            }
+        }
+
+        pub(crate) fn put(&mut self, address: u64, bytes: &[u8]) {
+            for (address, byte) in (address..).zip(bytes) {
+                self.bytes.insert(address, *byte);
+            }
+        }
+
+        pub(crate) fn words(&mut self, address: u64, words: &[u64]) {
+            let bytes: Vec<u8> = words.iter().flat_map(|word| word.to_le_bytes()).collect();
+            self.put(address, &bytes);
+        }
+
+        pub(crate) fn set(&mut self, address: u64, value: u64) {
+            self.words(address, &[value]);
+        }
+
+        pub(crate) fn read(&self, address: u64, len: usize) -> Option<Vec<u8>> {
+            if self.zero_filled {
+                return (address >= 0x1000).then(|| {
+                    (address..address + len as u64)
+                        .map(|address| self.bytes.get(&address).copied().unwrap_or(0))
+                        .collect()
+                });
+            }
+            self.bytes.get(&address)?;
+            Some(
+                (address..address + len as u64)
+                    .map_while(|address| self.bytes.get(&address).copied())
+                    .collect(),
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn le_readers_tolerate_short_input() {
+        assert_eq!(le_u64(&[1, 2, 3, 4, 5, 6, 7, 8, 9]), 0x0807_0605_0403_0201);
+        assert_eq!(le_u64(&[0x34, 0x12]), 0x1234);
+        assert_eq!(le_u32(&[1, 0, 0, 0, 0xff]), 1);
+
+        let mut memory = testing::Memory::default();
+        memory.set(0x2000, 0xfeed_0000_0000_2008);
+        let mut read = |address, len| memory.read(address, len);
+        assert_eq!(
+            read_u64(&mut read, 0x2000).map(|p| p & POINTER_MASK),
+            Some(0x2008)
+        );
+        assert_eq!(read_u64(&mut read, 0x2004), None);
+        assert_eq!(read_u64(&mut read, 0x3000), None);
+    }
+}
@@ -0,0 +1,191 @@
+//! Scripted target for offline development (`ios_llm_api --mock FIXTURE`).
+//!
+//! A [`MockFixture`] describes a stopped process: its threads, their frames
+//! and locals, and the stops each resume runs into. [`crate::backend::Backend::new_mock`]
+//! serves it in place of debugserver, so agents and tests get the same
+//! answers on every run without a device.
+
+use std::{fs, path::Path};
+
+use anyhow::{Context as AnyhowContext, Result as AnyResult};
+use serde::{Deserialize, Serialize};
+
+use crate::gdb_remote::ProcessExit;
+
+/// `SIGTRAP`, the signal debugserver reports breakpoint and step stops with.
+const SIGTRAP: u8 = 5;
+
+/// A fixture file: the threads at attach time and the stops to report, in order.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MockFixture {
+    #[serde(default)]
+    pub threads: Vec<MockThread>,
+    #[serde(default)]
+    pub stops: Vec<MockStop>,
+    /// Status the process exits with on the resume after the last stop.
+    #[serde(default)]
+    pub exit_code: u8,
+}
+
+impl MockFixture {
+    pub fn load(path: &Path) -> AnyResult<Self> {
+        let raw = fs::read_to_string(path)
+            .with_context(|| format!("failed to read mock fixture {}", path.display()))?;
+        serde_json::from_str(&raw)
+            .with_context(|| format!("invalid mock fixture {}", path.display()))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MockThread {
+    pub id: i64,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub queue: Option<String>,
+    /// Innermost first.
+    #[serde(default)]
+    pub frames: Vec<MockFrame>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MockFrame {
+    pub function: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    #[serde(default)]
+    pub line: u32,
+    #[serde(default)]
+    pub locals: Vec<MockVariable>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MockVariable {
+    pub name: String,
+    #[serde(rename = "type", default)]
+    pub ty: String,
+    pub value: String,
+}
+
+/// One scripted stop. `threads`, when set, replaces the process's threads
+/// from this stop on, which is how locals change between stops.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MockStop {
+    pub reason: MockStopReason,
+    /// Defaults to what debugserver's stop of the same kind is described as.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Defaults to the thread that was resumed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thread_id: Option<i64>,
+    /// Defaults to `SIGTRAP`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signal: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub threads: Option<Vec<MockThread>>,
+}
+
+impl MockStop {
+    pub fn signal(&self) -> u8 {
+        self.signal.unwrap_or(SIGTRAP)
+    }
+
+    pub fn description(&self) -> String {
+        self.description
+            .clone()
+            .unwrap_or_else(|| match self.reason {
+                MockStopReason::Breakpoint => "Breakpoint hit".into(),
+                MockStopReason::Step => "Step completed".into(),
+                MockStopReason::Signal => format!("Signal {}", self.signal()),
+            })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MockStopReason {
+    Breakpoint,
+    Step,
+    Signal,
+}
+
+impl MockStopReason {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Breakpoint => "breakpoint",
+            Self::Step => "step",
+            Self::Signal => "signal",
+        }
+    }
+}
+
+/// A [`MockFixture`] being played back. Attaching starts the script over.
+#[derive(Debug)]
+pub struct MockTarget {
+    fixture: MockFixture,
+    threads: Vec<MockThread>,
+    next_stop: usize,
+    attached: bool,
+    exit: Option<ProcessExit>,
+}
+
+impl MockTarget {
+    pub fn new(fixture: MockFixture) -> Self {
+        Self {
+            threads: fixture.threads.clone(),
+            fixture,
+            next_stop: 0,
+            attached: false,
+            exit: None,
+        }
+    }
+
+    pub fn attach(&mut self) {
+        self.threads = self.fixture.threads.clone();
+        self.next_stop = 0;
+        self.attached = true;
+        self.exit = None;
+    }
+
+    pub fn detach(&mut self) {
+        self.attached = false;
+    }
+
+    pub fn is_attached(&self) -> bool {
+        self.attached
+    }
+
+    pub fn threads(&self) -> &[MockThread] {
+        &self.threads
+    }
+
+    /// Frames of `thread_id`, innermost first; empty for unknown threads.
+    pub fn frames(&self, thread_id: i64) -> &[MockFrame] {
+        self.threads
+            .iter()
+            .find(|thread| thread.id == thread_id)
+            .map(|thread| thread.frames.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// The next scripted stop, or how the process exited once there are none
+    /// left; the process is then detached, as debugserver drops it.
+    pub fn resume(&mut self) -> Result<MockStop, ProcessExit> {
+        let Some(stop) = self.fixture.stops.get(self.next_stop).cloned() else {
+            let exit = ProcessExit::Exited(self.fixture.exit_code);
+            self.exit = Some(exit);
+            self.attached = false;
+            return Err(exit);
+        };
+        self.next_stop += 1;
+        if let Some(threads) = &stop.threads {
+            self.threads = threads.clone();
+        }
+        Ok(stop)
+    }
+
+    /// How the process exited, if a resume ran past the last stop since the last call.
+    pub fn take_exit(&mut self) -> Option<ProcessExit> {
+        self.exit.take()
+    }
+}
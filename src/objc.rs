@@ -0,0 +1,396 @@
+//! Objective-C runtime metadata read from memory, backing `objc_classes`
+//! and `objc_class`.
+//!
+//! The runtime keeps `gdb_objc_realized_classes` for debuggers: a name →
+//! class hash table of the classes realized outside the shared cache, which
+//! covers the app's own. A class's `class_ro_t` (found as in [`crate::heap`])
+//! holds its ivar and property lists and its instance methods; the
+//! metaclass's holds the class methods. Methods added by categories live in
+//! the runtime's extended class data and are not listed.
+
+use serde::Serialize;
+
+use crate::heap::{self, ObjectClass};
+use crate::memory::{le_u32, le_u64, read_u64, CODE_POINTER_MASK, POINTER_MASK};
+
+/// Strips pointer-authentication bits from a pointer to a string or an
+/// `int32_t`, which need not be word-aligned.
+const ADDRESS_MASK: u64 = 0x0000_7fff_ffff_ffff;
+/// `NX_MAPNOTAKEY`: an empty bucket.
+const EMPTY_BUCKET: u64 = u64::MAX;
+/// Buckets read from the class table.
+const MAX_BUCKETS: u32 = 1 << 20;
+/// `class_ro_t` fields: `baseMethods`, `ivars` and `baseProperties`.
+const RO_METHODS: u64 = 32;
+const RO_IVARS: u64 = 48;
+const RO_PROPERTIES: u64 = 64;
+/// `entsize_list_tt` flags: the entry size is what is left.
+const LIST_FLAGS_MASK: u32 = 0xffff_0003;
+/// Methods stored as three 32-bit offsets instead of three pointers.
+const SMALL_METHOD_LIST: u32 = 0x8000_0000;
+/// Small method names relative to the shared cache's selector base rather
+/// than to a selector reference.
+const DIRECT_SELECTORS: u32 = 0x4000_0000;
+/// Entries read from one method, ivar or property list.
+const MAX_LIST_ENTRIES: u32 = 4096;
+/// Superclasses followed before assuming a cycle.
+const MAX_SUPERCLASSES: usize = 64;
+/// Longest selector, type encoding or property attribute string read.
+const MAX_STRING_LEN: usize = 4096;
+
+/// A class in the runtime's class table.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ClassEntry {
+    pub name: String,
+    /// `Module.Type` for Swift classes whose name could be demangled.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub swift_name: Option<String>,
+    pub address: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Ivar {
+    pub name: String,
+    /// Objective-C type encoding.
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub type_encoding: Option<String>,
+    /// Byte offset in the instance, as slid by the runtime.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<i32>,
+    pub size: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Property {
+    pub name: String,
+    /// `T@"NSString",C,N,V_title` and the like.
+    pub attributes: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Method {
+    /// Missing for shared-cache methods whose selectors are stored as
+    /// offsets from the cache's selector base.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub selector: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub types: Option<String>,
+    pub imp: String,
+}
+
+/// A class's layout and interface, as reported by `objc_class`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ClassDump {
+    #[serde(flatten)]
+    pub class: ObjectClass,
+    /// Names of the superclasses, nearest first.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub superclasses: Vec<String>,
+    pub ivars: Vec<Ivar>,
+    pub properties: Vec<Property>,
+    pub instance_methods: Vec<Method>,
+    pub class_methods: Vec<Method>,
+}
+
+/// The classes in the `NXMapTable` at `table`, the value of
+/// `gdb_objc_realized_classes`, sorted by name.
+pub fn realized_classes<R>(mut read: R, table: u64) -> Option<Vec<ClassEntry>>
+where
+    R: FnMut(u64, usize) -> Option<Vec<u8>>,
+{
+    // prototype, count, nbBucketsMinusOne, buckets.
+    let header = read(table & POINTER_MASK, 24).filter(|header| header.len() == 24)?;
+    let buckets = le_u32(&header[12..]).checked_add(1)?;
+    if buckets > MAX_BUCKETS {
+        return None;
+    }
+    let base = le_u64(&header[16..]) & POINTER_MASK;
+    let pairs = read(base, buckets as usize * 16)?;
+    let mut classes: Vec<ClassEntry> = pairs
+        .chunks_exact(16)
+        .filter(|pair| le_u64(pair) != EMPTY_BUCKET && le_u64(pair) != 0)
+        .filter_map(|pair| {
+            let name = heap::read_c_string(&mut read, le_u64(pair) & ADDRESS_MASK, MAX_STRING_LEN)?;
+            Some(ClassEntry {
+                swift_name: heap::demangle_class_name(&name),
+                name,
+                address: format!("0x{:x}", le_u64(&pair[8..]) & POINTER_MASK),
+            })
+        })
+        .collect();
+    classes.sort_by(|a, b| a.name.cmp(&b.name));
+    Some(classes)
+}
+
+/// The class at `class` with its superclasses, ivars, properties and base
+/// methods; `None` when it does not look like a class.
+pub fn dump_class<R>(mut read: R, class: u64) -> Option<ClassDump>
+where
+    R: FnMut(u64, usize) -> Option<Vec<u8>>,
+{
+    let info = heap::read_class(&mut read, class)?;
+    let (_, ro) = heap::class_ro(&mut read, class)?;
+    let mut superclasses = Vec::new();
+    let mut superclass = read_u64(&mut read, class + 8).unwrap_or(0) & POINTER_MASK;
+    while superclass != 0 && superclasses.len() < MAX_SUPERCLASSES {
+        let Some(parent) = heap::read_class(&mut read, superclass) else {
+            break;
+        };
+        superclasses.push(parent.swift_name.unwrap_or(parent.name));
+        superclass = read_u64(&mut read, superclass + 8).unwrap_or(0) & POINTER_MASK;
+    }
+    let metaclass = read_u64(&mut read, class).unwrap_or(0) & POINTER_MASK;
+    let class_methods = heap::class_ro(&mut read, metaclass)
+        .map(|(_, meta_ro)| methods(&mut read, meta_ro))
+        .unwrap_or_default();
+    Some(ClassDump {
+        class: info,
+        superclasses,
+        ivars: ivars(&mut read, ro),
+        properties: properties(&mut read, ro),
+        instance_methods: methods(&mut read, ro),
+        class_methods,
+    })
+}
+
+/// The entries of the `entsize_list_tt` at `ro + field`: the list's flags
+/// and each entry's address and bytes.
+fn list_entries<R>(read: &mut R, ro: u64, field: u64) -> (u32, Vec<(u64, Vec<u8>)>)
+where
+    R: FnMut(u64, usize) -> Option<Vec<u8>>,
+{
+    let list = read_u64(read, ro + field).unwrap_or(0) & POINTER_MASK;
+    let Some(header) = read(list, 8).filter(|header| list != 0 && header.len() == 8) else {
+        return (0, Vec::new());
+    };
+    let entsize_and_flags = le_u32(&header);
+    let entsize = (entsize_and_flags & !LIST_FLAGS_MASK) as usize;
+    let count = le_u32(&header[4..]).min(MAX_LIST_ENTRIES) as usize;
+    if entsize == 0 {
+        return (entsize_and_flags, Vec::new());
+    }
+    let first = list + 8;
+    let entries = read(first, entsize * count)
+        .unwrap_or_default()
+        .chunks_exact(entsize)
+        .enumerate()
+        .map(|(index, entry)| (first + (index * entsize) as u64, entry.to_vec()))
+        .collect();
+    (entsize_and_flags, entries)
+}
+
+fn ivars<R>(read: &mut R, ro: u64) -> Vec<Ivar>
+where
+    R: FnMut(u64, usize) -> Option<Vec<u8>>,
+{
+    // offset pointer, name, type, alignment, size.
+    let (_, entries) = list_entries(read, ro, RO_IVARS);
+    entries
+        .into_iter()
+        .filter(|(_, entry)| entry.len() >= 32)
+        .filter_map(|(_, entry)| {
+            let offset_ptr = le_u64(&entry) & ADDRESS_MASK;
+            Some(Ivar {
+                name: string_at(read, le_u64(&entry[8..]))?,
+                type_encoding: string_at(read, le_u64(&entry[16..])),
+                offset: read(offset_ptr, 4)
+                    .filter(|bytes| offset_ptr != 0 && bytes.len() == 4)
+                    .map(|bytes| le_u32(&bytes) as i32),
+                size: le_u32(&entry[28..]),
+            })
+        })
+        .collect()
+}
+
+fn properties<R>(read: &mut R, ro: u64) -> Vec<Property>
+where
+    R: FnMut(u64, usize) -> Option<Vec<u8>>,
+{
+    let (_, entries) = list_entries(read, ro, RO_PROPERTIES);
+    entries
+        .into_iter()
+        .filter(|(_, entry)| entry.len() >= 16)
+        .filter_map(|(_, entry)| {
+            Some(Property {
+                name: string_at(read, le_u64(&entry))?,
+                attributes: string_at(read, le_u64(&entry[8..])).unwrap_or_default(),
+            })
+        })
+        .collect()
+}
+
+fn methods<R>(read: &mut R, ro: u64) -> Vec<Method>
+where
+    R: FnMut(u64, usize) -> Option<Vec<u8>>,
+{
+    let (flags, entries) = list_entries(read, ro, RO_METHODS);
+    let small = flags & SMALL_METHOD_LIST != 0;
+    entries
+        .into_iter()
+        .filter_map(|(address, entry)| {
+            if !small {
+                // name (a selector is its C string), types, imp.
+                return (entry.len() >= 24).then(|| Method {
+                    selector: string_at(read, le_u64(&entry)),
+                    types: string_at(read, le_u64(&entry[8..])),
+                    imp: format!("0x{:x}", le_u64(&entry[16..]) & CODE_POINTER_MASK),
+                });
+            }
+            if entry.len() < 12 {
+                return None;
+            }
+            // Each field is an offset from its own address.
+            let relative = |field: u64| {
+                let offset = le_u32(&entry[field as usize..]) as i32;
+                (address + field).wrapping_add_signed(offset.into())
+            };
+            let selector = if flags & DIRECT_SELECTORS != 0 {
+                None
+            } else {
+                read_u64(read, relative(0)).and_then(|selector| string_at(read, selector))
+            };
+            Some(Method {
+                selector,
+                types: string_at(read, relative(4)),
+                imp: format!("0x{:x}", relative(8)),
+            })
+        })
+        .collect()
+}
+
+fn string_at<R>(read: &mut R, address: u64) -> Option<String>
+where
+    R: FnMut(u64, usize) -> Option<Vec<u8>>,
+{
+    let address = address & ADDRESS_MASK;
+    (address != 0)
+        .then(|| heap::read_c_string(read, address, MAX_STRING_LEN))
+        .flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::testing::Memory;
+
+    /// Writes an unrealized class whose `class_ro_t` is at `ro`.
+    fn put_class(
+        memory: &mut Memory,
+        class: u64,
+        isa: u64,
+        superclass: u64,
+        ro: u64,
+        ro_fields: [u64; 3],
+    ) {
+        memory.words(class, &[isa, superclass, 0, 0, ro]);
+        let [methods, ivars, properties] = ro_fields;
+        memory.words(
+            ro,
+            &[0, 16, 0, ro + 0x100, methods, 0, ivars, 0, properties],
+        );
+    }
+
+    #[test]
+    fn lists_realized_classes_from_the_runtime_table() {
+        let mut memory = Memory::default();
+        memory.words(0x1000, &[0, 3 << 32 | 2, 0x2000]);
+        memory.words(
+            0x2000,
+            &[u64::MAX, 0, 0x3000, 0x4000, u64::MAX, 0, 0x3100, 0x5000],
+        );
+        memory.put(0x3000, b"_TtC5MyApp4Cart\0");
+        memory.put(0x3100, b"AppDelegate\0");
+        let classes = realized_classes(|a, l| memory.read(a, l), 0x1000).unwrap();
+        assert_eq!(
+            classes,
+            [
+                ClassEntry {
+                    name: "AppDelegate".into(),
+                    swift_name: None,
+                    address: "0x5000".into(),
+                },
+                ClassEntry {
+                    name: "_TtC5MyApp4Cart".into(),
+                    swift_name: Some("MyApp.Cart".into()),
+                    address: "0x4000".into(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn dumps_ivars_properties_and_methods() {
+        let mut memory = Memory::default();
+        // AppDelegate : NSObject, with metaclass 0x1800.
+        put_class(
+            &mut memory,
+            0x1000,
+            0x1800,
+            0x2000,
+            0x1100,
+            [0x6000, 0x7000, 0x8000],
+        );
+        memory.put(0x1200, b"AppDelegate\0");
+        put_class(&mut memory, 0x2000, 0, 0, 0x2100, [0, 0, 0]);
+        memory.put(0x2200, b"NSObject\0");
+        put_class(&mut memory, 0x1800, 0, 0, 0x1900, [0x6100, 0, 0]);
+        memory.put(0x1a00, b"AppDelegate\0");
+        // One big method, `window` returning an object.
+        memory.words(0x6000, &[24 | 1 << 32, 0x9000, 0x9100, 0x1_0000_4000]);
+        // One small method: selector reference, types and imp, relative.
+        let small = |name: i32, types: i32, imp: i32| {
+            let mut entry = (SMALL_METHOD_LIST | 12).to_le_bytes().to_vec();
+            entry.extend(1u32.to_le_bytes());
+            for offset in [name, types, imp] {
+                entry.extend(offset.to_le_bytes());
+            }
+            entry
+        };
+        memory.put(
+            0x6100,
+            &small(0x6300 - 0x6108, 0x9600 - 0x610c, 0x6000 - 0x6110),
+        );
+        memory.words(0x6300, &[0x9200]);
+        // `_window` at offset 8, and a `window` property.
+        memory.words(0x7000, &[32 | 1 << 32, 0x7100, 0x9300, 0x9400, 8 << 32 | 3]);
+        memory.words(0x7100, &[8]);
+        memory.words(0x8000, &[16 | 1 << 32, 0x9000, 0x9500]);
+        memory.put(0x9000, b"window\0");
+        memory.put(0x9100, b"@16@0:8\0");
+        memory.put(0x9200, b"shared\0");
+        memory.put(0x9300, b"_window\0");
+        memory.put(0x9400, b"@\"UIWindow\"\0");
+        memory.put(0x9500, b"T@\"UIWindow\",&,N,V_window\0");
+        memory.put(0x9600, b"@8@0:8\0");
+
+        let dump = dump_class(|a, l| memory.read(a, l), 0x1000).unwrap();
+        assert_eq!(dump.class.name, "AppDelegate");
+        assert_eq!(dump.superclasses, ["NSObject"]);
+        assert_eq!(
+            dump.ivars,
+            [Ivar {
+                name: "_window".into(),
+                type_encoding: Some("@\"UIWindow\"".into()),
+                offset: Some(8),
+                size: 8,
+            }]
+        );
+        assert_eq!(dump.properties[0].attributes, "T@\"UIWindow\",&,N,V_window");
+        assert_eq!(
+            dump.instance_methods,
+            [Method {
+                selector: Some("window".into()),
+                types: Some("@16@0:8".into()),
+                imp: "0x100004000".into(),
+            }]
+        );
+        assert_eq!(
+            dump.class_methods,
+            [Method {
+                selector: Some("shared".into()),
+                types: Some("@8@0:8".into()),
+                imp: "0x6000".into(),
+            }]
+        );
+    }
+}
@@ -0,0 +1,172 @@
+//! gdb-remote packet trace (`ios_llm_api --protocol-trace FILE`).
+//!
+//! Every packet the client sends or receives is appended to the trace file as
+//! `<unix millis> <session> <-|-> <payload>` and kept in a bounded in-memory
+//! tail of its session for `GET /protocol-trace/tail`, so a misbehaving
+//! device or iOS version can be diagnosed from the exact exchange.
+
+use std::{
+    collections::VecDeque,
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    path::Path,
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::Serialize;
+
+/// Entries kept for the tail endpoint.
+const TAIL_CAPACITY: usize = 2000;
+/// Longer payloads (memory reads, library lists) are cut to this many bytes.
+const MAX_PAYLOAD_LEN: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    Send,
+    Recv,
+}
+
+impl Direction {
+    fn arrow(self) -> &'static str {
+        match self {
+            Self::Send => "->",
+            Self::Recv => "<-",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TraceEntry {
+    pub timestamp_ms: u64,
+    pub direction: Direction,
+    pub packet: String,
+}
+
+/// The packets of one session. Sessions share the trace file, where each
+/// line names its session, but each keeps its own tail.
+#[derive(Debug)]
+pub struct ProtocolTrace {
+    file: Arc<Mutex<File>>,
+    session: String,
+    tail: Mutex<VecDeque<TraceEntry>>,
+}
+
+impl ProtocolTrace {
+    /// Appends `session`'s packets to `path`, creating it if needed.
+    pub fn create(path: &Path, session: &str) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self::with_file(Arc::new(Mutex::new(file)), session))
+    }
+
+    /// A trace of `session` appending to the same file, with a tail of its own.
+    pub fn for_session(&self, session: &str) -> Self {
+        Self::with_file(self.file.clone(), session)
+    }
+
+    fn with_file(file: Arc<Mutex<File>>, session: &str) -> Self {
+        Self {
+            file,
+            session: session.to_string(),
+            tail: Mutex::new(VecDeque::with_capacity(TAIL_CAPACITY)),
+        }
+    }
+
+    pub fn record(&self, direction: Direction, payload: &[u8]) {
+        let entry = TraceEntry {
+            timestamp_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_millis() as u64)
+                .unwrap_or_default(),
+            direction,
+            packet: printable(payload),
+        };
+        if let Ok(mut file) = self.file.lock() {
+            // Tracing must never fail a debugger command.
+            let _ = writeln!(
+                file,
+                "{} {} {} {}",
+                entry.timestamp_ms,
+                self.session,
+                direction.arrow(),
+                entry.packet
+            );
+        }
+        if let Ok(mut tail) = self.tail.lock() {
+            if tail.len() == TAIL_CAPACITY {
+                tail.pop_front();
+            }
+            tail.push_back(entry);
+        }
+    }
+
+    /// The last `count` packets, oldest first.
+    pub fn tail(&self, count: usize) -> Vec<TraceEntry> {
+        let Ok(tail) = self.tail.lock() else {
+            return Vec::new();
+        };
+        tail.iter()
+            .skip(tail.len().saturating_sub(count))
+            .cloned()
+            .collect()
+    }
+}
+
+fn printable(payload: &[u8]) -> String {
+    let shown = &payload[..payload.len().min(MAX_PAYLOAD_LEN)];
+    let mut text: String = shown
+        .iter()
+        .map(|&byte| {
+            if byte.is_ascii_graphic() || byte == b' ' {
+                (byte as char).to_string()
+            } else {
+                format!("\\x{byte:02x}")
+            }
+        })
+        .collect();
+    if payload.len() > MAX_PAYLOAD_LEN {
+        text.push_str(&format!("... ({} bytes)", payload.len()));
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_to_file_and_bounded_tail() {
+        let path = std::env::temp_dir().join(format!("protocol-trace-{}.log", std::process::id()));
+        let trace = ProtocolTrace::create(&path, "default").unwrap();
+        let other = trace.for_session("2");
+        trace.record(Direction::Send, b"qSupported:multiprocess+");
+        other.record(Direction::Send, b"m1000,8");
+        trace.record(Direction::Recv, b"OK\x01");
+        trace.record(Direction::Recv, &[b'm'; MAX_PAYLOAD_LEN + 5]);
+
+        let tail = trace.tail(2);
+        assert_eq!(tail.len(), 2);
+        assert_eq!(tail[0].direction, Direction::Recv);
+        assert_eq!(tail[0].packet, "OK\\x01");
+        assert!(tail[1]
+            .packet
+            .ends_with(&format!("... ({} bytes)", MAX_PAYLOAD_LEN + 5)));
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        let first = text.lines().next().unwrap();
+        assert!(
+            first.ends_with(" default -> qSupported:multiprocess+"),
+            "{first}"
+        );
+        assert!(text.lines().nth(1).unwrap().ends_with(" 2 -> m1000,8"));
+        assert_eq!(text.lines().count(), 4);
+        assert_eq!(
+            trace.tail(10).len(),
+            3,
+            "the other session's packet is not in the tail"
+        );
+        assert_eq!(other.tail(10).len(), 1);
+    }
+}
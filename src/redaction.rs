@@ -0,0 +1,204 @@
+//! Redaction of sensitive values before they leave the API.
+//!
+//! Two kinds of rule apply. Field names (`password`, `token`, ...) match
+//! case-insensitively anywhere in a variable or key name: the `value`,
+//! `result` or `summary` of an object whose `name` matches is replaced, as
+//! is a matching key's own value, and `name=value` / `"name": value` pairs
+//! inside free text lose their value. Patterns are regular expressions whose
+//! matches are replaced in every string. Both come with defaults so secrets in
+//! app memory are not shipped to a model unless redaction is turned off.
+
+use std::borrow::Cow;
+
+use regex::{Regex, RegexBuilder};
+use serde_json::Value;
+
+pub const REDACTED: &str = "<redacted>";
+
+pub const DEFAULT_FIELDS: &[&str] = &[
+    "password",
+    "passwd",
+    "secret",
+    "token",
+    "apikey",
+    "api_key",
+    "authorization",
+    "cookie",
+    "credential",
+    "private_key",
+];
+
+pub const DEFAULT_PATTERNS: &[&str] = &[
+    // Email addresses.
+    r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}",
+    // JSON Web Tokens.
+    r"eyJ[A-Za-z0-9_-]{5,}\.[A-Za-z0-9_-]{5,}\.[A-Za-z0-9_-]+",
+    // Bearer credentials.
+    r"(?i)bearer\s+[A-Za-z0-9._~+/=-]{8,}",
+    // AWS access key ids.
+    r"AKIA[0-9A-Z]{16}",
+    // PEM private keys.
+    r"-----BEGIN [A-Z ]*PRIVATE KEY-----[\s\S]*?-----END [A-Z ]*PRIVATE KEY-----",
+];
+
+/// Keys holding the value of an object named by its `name` key.
+const VALUE_KEYS: &[&str] = &["value", "result", "summary"];
+
+#[derive(Debug, Default)]
+pub struct Redactor {
+    /// Lowercased field-name fragments.
+    fields: Vec<String>,
+    /// `name=value` assignments whose name contains one of `fields`.
+    assignment: Option<Regex>,
+    patterns: Vec<Regex>,
+}
+
+impl Redactor {
+    /// Rules from `fields` and `patterns`, plus the defaults unless
+    /// `defaults` is false.
+    pub fn new(fields: &[String], patterns: &[String], defaults: bool) -> Result<Self, String> {
+        let defaults = defaults.then_some(()).into_iter();
+        let fields: Vec<String> = defaults
+            .clone()
+            .flat_map(|_| DEFAULT_FIELDS.iter().map(|field| field.to_string()))
+            .chain(fields.iter().map(|field| field.to_lowercase()))
+            .filter(|field| !field.is_empty())
+            .collect();
+        let patterns = defaults
+            .flat_map(|_| DEFAULT_PATTERNS.iter().copied())
+            .chain(patterns.iter().map(String::as_str))
+            .map(|pattern| {
+                Regex::new(pattern)
+                    .map_err(|err| format!("invalid redaction pattern `{pattern}`: {err}"))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let assignment = if fields.is_empty() {
+            None
+        } else {
+            let names = fields
+                .iter()
+                .map(|field| regex::escape(field))
+                .collect::<Vec<_>>()
+                .join("|");
+            let pattern = format!(
+                r#"\b([A-Za-z0-9_.]*(?:{names})[A-Za-z0-9_]*"?\s*[=:]\s*)("[^"]*"|[^\s,;)}}\]]+)"#
+            );
+            Some(
+                RegexBuilder::new(&pattern)
+                    .case_insensitive(true)
+                    .build()
+                    .map_err(|err| format!("invalid redaction field: {err}"))?,
+            )
+        };
+        Ok(Self {
+            fields,
+            assignment,
+            patterns,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty() && self.patterns.is_empty()
+    }
+
+    /// Whether a variable or key called `name` holds sensitive data.
+    pub fn is_sensitive(&self, name: &str) -> bool {
+        let name = name.to_lowercase();
+        self.fields
+            .iter()
+            .any(|field| name.contains(field.as_str()))
+    }
+
+    /// `text` with pattern matches and sensitive assignments replaced.
+    pub fn redact_text<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let mut text = Cow::Borrowed(text);
+        for pattern in &self.patterns {
+            if let Cow::Owned(replaced) = pattern.replace_all(&text, REDACTED) {
+                text = Cow::Owned(replaced);
+            }
+        }
+        if let Some(assignment) = &self.assignment {
+            if let Cow::Owned(replaced) = assignment.replace_all(&text, format!("${{1}}{REDACTED}"))
+            {
+                text = Cow::Owned(replaced);
+            }
+        }
+        text
+    }
+
+    /// Redacts every string in `value` in place.
+    pub fn apply(&self, value: &mut Value) {
+        if self.is_empty() {
+            return;
+        }
+        match value {
+            Value::Object(map) => {
+                let named = map
+                    .get("name")
+                    .and_then(Value::as_str)
+                    .is_some_and(|name| self.is_sensitive(name));
+                for (key, field) in map.iter_mut() {
+                    let scalar = field.is_string() || field.is_number();
+                    if scalar
+                        && ((named && VALUE_KEYS.contains(&key.as_str())) || self.is_sensitive(key))
+                    {
+                        *field = Value::String(REDACTED.into());
+                    } else {
+                        self.apply(field);
+                    }
+                }
+            }
+            Value::Array(items) => items.iter_mut().for_each(|item| self.apply(item)),
+            Value::String(text) => {
+                if let Cow::Owned(redacted) = self.redact_text(text) {
+                    *text = redacted;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn redacts_named_values_patterns_and_assignments() {
+        let redactor = Redactor::new(&["ssn".into()], &[r"\d{3}-\d{4}".into()], true).unwrap();
+        let mut value = json!({
+            "locals": [
+                { "name": "authToken", "type": "String", "value": "abc123" },
+                { "name": "userSSN", "type": "String", "value": "078-05-1120" },
+                { "name": "count", "type": "Int", "value": "3" },
+                { "name": "contact", "type": "String", "value": "jane@example.com" }
+            ],
+            "env": { "API_KEY": "k", "HOME": "/Users/jane" },
+            "summary": "count=3, password=\"hunter2\" phone 555-1234"
+        });
+        redactor.apply(&mut value);
+        assert_eq!(value["locals"][0]["value"], REDACTED);
+        assert_eq!(value["locals"][1]["value"], REDACTED);
+        assert_eq!(value["locals"][2]["value"], "3");
+        assert_eq!(value["locals"][3]["value"], REDACTED);
+        assert_eq!(value["env"]["API_KEY"], REDACTED);
+        assert_eq!(value["env"]["HOME"], "/Users/jane");
+        assert_eq!(
+            value["summary"],
+            format!("count=3, password={REDACTED} phone {REDACTED}")
+        );
+        assert_eq!(
+            redactor.redact_text(r#"[log] {"token":"t0k3n","id":1}"#),
+            format!(r#"[log] {{"token":{REDACTED},"id":1}}"#)
+        );
+        assert!(matches!(redactor.redact_text("plain"), Cow::Borrowed(_)));
+
+        let off = Redactor::new(&[], &[], false).unwrap();
+        assert!(off.is_empty());
+        let mut untouched = json!({ "name": "password", "value": "x" });
+        off.apply(&mut untouched);
+        assert_eq!(untouched["value"], "x");
+        assert!(Redactor::new(&[], &["(".into()], false).is_err());
+    }
+}
@@ -0,0 +1,290 @@
+//! Output profiles for command responses.
+//!
+//! `json` leaves a response as it is. `compact` drops `null` fields and
+//! renames the keys in [`SHORT_KEYS`] (`function` becomes `fn`, `line`
+//! becomes `l`, ...). `markdown` renders the response as text meant to be
+//! pasted into a prompt: scalar fields as a list, arrays of records as
+//! tables, source listings as code blocks and errors as a single line.
+
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResponseFormat {
+    #[default]
+    Json,
+    Compact,
+    Markdown,
+}
+
+impl FromStr for ResponseFormat {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text {
+            "json" => Ok(Self::Json),
+            "compact" => Ok(Self::Compact),
+            "markdown" => Ok(Self::Markdown),
+            other => Err(format!(
+                "unknown response format `{other}` (expected json, compact or markdown)"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ResponseFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Json => "json",
+            Self::Compact => "compact",
+            Self::Markdown => "markdown",
+        })
+    }
+}
+
+/// Keys `compact` renames, and their short forms.
+pub const SHORT_KEYS: [(&str, &str); 20] = [
+    ("stacktrace", "st"),
+    ("frames", "fr"),
+    ("frame_index", "i"),
+    ("function", "fn"),
+    ("file", "f"),
+    ("line", "l"),
+    ("threads", "th"),
+    ("thread_id", "tid"),
+    ("locals", "loc"),
+    ("variables", "vars"),
+    ("variablesReference", "ref"),
+    ("children", "ch"),
+    ("name", "n"),
+    ("type", "t"),
+    ("value", "v"),
+    ("expression", "e"),
+    ("result", "r"),
+    ("reason", "rsn"),
+    ("description", "d"),
+    ("address", "a"),
+];
+
+/// `value` in `format`: a JSON value for `json` and `compact`, a string for
+/// `markdown`.
+pub fn render(value: Value, format: ResponseFormat) -> Value {
+    match format {
+        ResponseFormat::Json => value,
+        ResponseFormat::Compact => compact(value),
+        ResponseFormat::Markdown => Value::String(markdown(&value)),
+    }
+}
+
+fn compact(value: Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .filter(|(_, field)| !field.is_null())
+                .map(|(key, field)| {
+                    let key = SHORT_KEYS
+                        .iter()
+                        .find(|(long, _)| *long == key)
+                        .map_or(key, |(_, short)| short.to_string());
+                    (key, compact(field))
+                })
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.into_iter().map(compact).collect()),
+        other => other,
+    }
+}
+
+/// Renders a response as markdown.
+pub fn markdown(value: &Value) -> String {
+    let Value::Object(map) = value else {
+        return block(value);
+    };
+    if map.get("ok") == Some(&Value::Bool(false)) {
+        if let Some(error) = map.get("error").and_then(Value::as_object) {
+            return error_line(error);
+        }
+    }
+    let (scalars, nested): (Vec<_>, Vec<_>) = map
+        .iter()
+        .filter(|(key, field)| *key != "ok" && !field.is_null())
+        .partition(|(_, field)| !field.is_object() && !field.is_array());
+    let mut sections = Vec::new();
+    if !scalars.is_empty() {
+        sections.push(
+            scalars
+                .iter()
+                .map(|(key, field)| format!("- **{key}**: {}", inline(field)))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        );
+    }
+    for (key, field) in nested {
+        sections.push(format!("### {key}\n\n{}", block(field)));
+    }
+    let mut text = sections.join("\n\n");
+    text.push('\n');
+    text
+}
+
+fn error_line(error: &Map<String, Value>) -> String {
+    let code = error.get("code").and_then(Value::as_str).unwrap_or("ERROR");
+    let message = error.get("message").map(inline).unwrap_or_default();
+    let mut text = format!("**Error** `{code}`: {message}\n");
+    if let Some(hint) = error.get("hint").and_then(Value::as_str) {
+        text.push_str(&format!("\nHint: {hint}\n"));
+    }
+    text
+}
+
+/// A value on lines of its own: source listings as code, arrays of objects
+/// as tables, other arrays and objects as lists.
+fn block(value: &Value) -> String {
+    match value {
+        Value::Object(map) => {
+            if let Some(code) = source_listing(map) {
+                return code;
+            }
+            if map.is_empty() {
+                return "_none_".into();
+            }
+            map.iter()
+                .map(|(key, field)| match field {
+                    Value::Object(inner) => match source_listing(inner) {
+                        Some(code) => format!("- **{key}**:\n\n{code}"),
+                        None => format!("- **{key}**: {}", inline(field)),
+                    },
+                    _ => format!("- **{key}**: {}", inline(field)),
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        }
+        Value::Array(items) if items.is_empty() => "_none_".into(),
+        Value::Array(items) if items.iter().all(Value::is_object) => table(items),
+        Value::Array(items) => items
+            .iter()
+            .map(|item| format!("- {}", inline(item)))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        other => inline(other),
+    }
+}
+
+/// A `{ path, lines: [{ number, text, current? }] }` listing as a code
+/// block with `>` on the current line.
+fn source_listing(map: &Map<String, Value>) -> Option<String> {
+    let lines = map.get("lines")?.as_array()?;
+    let mut code = format!(
+        "`{}`\n\n```\n",
+        map.get("path").and_then(Value::as_str).unwrap_or("")
+    );
+    for line in lines {
+        let number = line.get("number")?.as_u64()?;
+        let text = line.get("text")?.as_str()?;
+        let marker = if line.get("current") == Some(&Value::Bool(true)) {
+            '>'
+        } else {
+            ' '
+        };
+        code.push_str(&format!("{marker}{number:>5}  {text}\n"));
+    }
+    code.push_str("```");
+    Some(code)
+}
+
+/// Records as a table whose columns are every key any of them has, in
+/// first-seen order.
+fn table(rows: &[Value]) -> String {
+    let mut columns: Vec<&str> = Vec::new();
+    for row in rows.iter().filter_map(Value::as_object) {
+        for key in row.keys() {
+            if !columns.contains(&key.as_str()) {
+                columns.push(key);
+            }
+        }
+    }
+    let mut text = format!("| {} |\n|", columns.join(" | "));
+    text.push_str(&" --- |".repeat(columns.len()));
+    for row in rows {
+        let cells: Vec<String> = columns
+            .iter()
+            .map(|column| match row.get(*column) {
+                None | Some(Value::Null) => String::new(),
+                Some(field) => inline(field).replace('|', "\\|"),
+            })
+            .collect();
+        text.push_str(&format!("\n| {} |", cells.join(" | ")));
+    }
+    text
+}
+
+/// A value on one line: strings as they are, anything nested as JSON.
+fn inline(value: &Value) -> String {
+    let text = match value {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    };
+    text.replace('\n', " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn compact_shortens_keys_and_drops_nulls() {
+        let value = json!({
+            "ok": true,
+            "stacktrace": [{ "frame_index": 0, "function": "main", "file": "a.swift", "line": 3 }],
+            "summary": null,
+        });
+        assert_eq!(
+            render(value, ResponseFormat::Compact),
+            json!({ "ok": true, "st": [{ "i": 0, "fn": "main", "f": "a.swift", "l": 3 }] })
+        );
+        assert_eq!("compact".parse(), Ok(ResponseFormat::Compact));
+        assert!("yaml".parse::<ResponseFormat>().is_err());
+    }
+
+    #[test]
+    fn markdown_renders_tables_source_and_errors() {
+        let value = json!({
+            "ok": true,
+            "breakpoint_id": 2,
+            "stacktrace": [
+                { "function": "main", "line": 3 },
+                { "function": "start|up", "file": "dyld" },
+            ],
+            "source": {
+                "path": "a.swift",
+                "line": 3,
+                "lines": [
+                    { "number": 2, "text": "let x = 1" },
+                    { "number": 3, "text": "print(x)", "current": true },
+                ],
+            },
+        });
+        assert_eq!(
+            markdown(&value),
+            "- **breakpoint_id**: 2\n\n\
+             ### source\n\n\
+             `a.swift`\n\n```\n     2  let x = 1\n>    3  print(x)\n```\n\n\
+             ### stacktrace\n\n\
+             | function | line | file |\n| --- | --- | --- |\n\
+             | main | 3 |  |\n| start\\|up |  | dyld |\n"
+        );
+
+        let error = json!({
+            "ok": false,
+            "error": { "code": "NOT_STOPPED", "message": "target is running", "hint": "pause first" },
+        });
+        assert_eq!(
+            markdown(&error),
+            "**Error** `NOT_STOPPED`: target is running\n\nHint: pause first\n"
+        );
+    }
+}
@@ -0,0 +1,1441 @@
+//! Demangler for Swift 5 symbol names (`$s...`).
+//!
+//! Mangled names are postfix: operands come first and each operator
+//! character pops what it applies to, so demangling pushes nodes on a stack
+//! the way the Swift runtime's own demangler does. The common symbols of an
+//! app are covered: functions, initializers, closures, properties and their
+//! accessors, subscripts, extensions, generic signatures, the type metadata
+//! symbols and a few thunks. Anything else makes [`demangle`] return `None`
+//! so callers can keep the raw name.
+//!
+//! Names print the way LLDB shows them, except that types from the standard
+//! library and imported C/Objective-C modules leave out their module
+//! (`[String]`, `Int?`, `NSObject`) and private declarations leave out
+//! their file discriminator.
+
+/// The human-readable form of a Swift symbol, or `None` when `symbol` is not
+/// one or uses a construct this demangler doesn't know.
+pub fn demangle(symbol: &str) -> Option<String> {
+    let (mangled, suffix) = match symbol.find('.') {
+        Some(dot) => (&symbol[..dot], Some(&symbol[dot..])),
+        None => (symbol, None),
+    };
+    let body = ["_$s", "$s", "_$S", "$S"]
+        .iter()
+        .find_map(|prefix| mangled.strip_prefix(prefix))?;
+    let mut text = Demangler::new(body).demangle()?;
+    if let Some(suffix) = suffix {
+        text.push_str(&format!(" with unmangled suffix {suffix:?}"));
+    }
+    Some(text)
+}
+
+/// Whether `symbol` looks like a Swift 5 mangled name.
+pub fn is_mangled(symbol: &str) -> bool {
+    ["_$s", "$s", "_$S", "$S"]
+        .iter()
+        .any(|prefix| symbol.starts_with(prefix))
+}
+
+/// The most words an identifier list can hold for word substitutions.
+const MAX_WORDS: usize = 26;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NominalKind {
+    Class,
+    Struct,
+    Enum,
+    Protocol,
+    TypeAlias,
+}
+
+#[derive(Debug, Clone)]
+struct TupleElement {
+    label: Option<String>,
+    variadic: bool,
+    ty: Node,
+}
+
+#[derive(Debug, Clone)]
+enum Requirement {
+    Conforms(Node, Node),
+    SameType(Node, Node),
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Module(String),
+    Identifier(String),
+    LocalName(String, u64),
+    PrivateName(String),
+
+    // Markers operators pop.
+    EmptyList,
+    FirstElement,
+    Variadic,
+    Throws,
+    Async,
+    Signature(Vec<u64>, Vec<Requirement>),
+    Requirement(Box<Requirement>),
+    /// `partial apply forwarder for `, `@objc ` and the like.
+    Attribute(String),
+
+    // Types.
+    Nominal(NominalKind, Box<Node>, Box<Node>),
+    BoundGeneric(Box<Node>, Vec<Node>),
+    Tuple(Vec<TupleElement>),
+    FunctionType {
+        params: Box<Node>,
+        result: Box<Node>,
+        throws: bool,
+        is_async: bool,
+    },
+    GenericType(Box<Node>, Box<Node>),
+    GenericParam(u64, u64),
+    Member(Box<Node>, String),
+    Existential(Vec<Node>),
+    Metatype(Box<Node>),
+    Modified(&'static str, Box<Node>),
+
+    // Declarations.
+    Function {
+        context: Box<Node>,
+        name: Box<Node>,
+        labels: Option<Vec<Option<String>>>,
+        ty: Box<Node>,
+    },
+    Variable {
+        context: Box<Node>,
+        name: Box<Node>,
+        ty: Box<Node>,
+    },
+    Subscript {
+        context: Box<Node>,
+        labels: Option<Vec<Option<String>>>,
+        ty: Box<Node>,
+    },
+    Accessor(&'static str, Box<Node>),
+    Static(Box<Node>),
+    Initializer {
+        context: Box<Node>,
+        name: &'static str,
+        labels: Option<Vec<Option<String>>>,
+        ty: Option<Box<Node>>,
+    },
+    Special(&'static str, Box<Node>),
+    Closure {
+        context: Box<Node>,
+        implicit: bool,
+        index: u64,
+        ty: Option<Box<Node>>,
+    },
+    DefaultArgument(Box<Node>, u64),
+    Extension(String, Box<Node>),
+
+    /// `type metadata for T` and the other symbols describing a type or
+    /// declaration.
+    Described(&'static str, Box<Node>),
+}
+
+impl Node {
+    fn is_type(&self) -> bool {
+        matches!(
+            self,
+            Node::Nominal(..)
+                | Node::BoundGeneric(..)
+                | Node::Tuple(_)
+                | Node::FunctionType { .. }
+                | Node::GenericType(..)
+                | Node::GenericParam(..)
+                | Node::Member(..)
+                | Node::Existential(_)
+                | Node::Metatype(_)
+                | Node::Modified(..)
+        )
+    }
+
+    fn is_decl_name(&self) -> bool {
+        matches!(
+            self,
+            Node::Identifier(_) | Node::LocalName(..) | Node::PrivateName(_)
+        )
+    }
+
+    fn is_entity(&self) -> bool {
+        matches!(
+            self,
+            Node::Function { .. }
+                | Node::Variable { .. }
+                | Node::Subscript { .. }
+                | Node::Accessor(..)
+                | Node::Initializer { .. }
+                | Node::Special(..)
+                | Node::Closure { .. }
+                | Node::DefaultArgument(..)
+        )
+    }
+
+    fn is_context(&self) -> bool {
+        self.is_entity()
+            || matches!(
+                self,
+                Node::Module(_) | Node::Extension(..) | Node::Nominal(..) | Node::BoundGeneric(..)
+            )
+    }
+}
+
+struct Demangler<'a> {
+    text: &'a [u8],
+    pos: usize,
+    stack: Vec<Node>,
+    substitutions: Vec<Node>,
+    words: Vec<String>,
+}
+
+impl<'a> Demangler<'a> {
+    fn new(text: &'a str) -> Self {
+        Self {
+            text: text.as_bytes(),
+            pos: 0,
+            stack: Vec::new(),
+            substitutions: Vec::new(),
+            words: Vec::new(),
+        }
+    }
+
+    fn demangle(mut self) -> Option<String> {
+        while self.pos < self.text.len() {
+            let node = self.operator()?;
+            self.stack.push(node);
+        }
+        let mut attributes = Vec::new();
+        while let Some(Node::Attribute(attribute)) =
+            self.stack.pop_if(|node| matches!(node, Node::Attribute(_)))
+        {
+            attributes.push(attribute);
+        }
+        let [node] = self.stack.as_slice() else {
+            return None;
+        };
+        let mut text: String = attributes.concat();
+        text.push_str(&match node {
+            node if node.is_type() => type_name(node),
+            node => describe(node),
+        });
+        Some(text)
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.text.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<u8> {
+        let c = self.peek()?;
+        self.pos += 1;
+        Some(c)
+    }
+
+    fn next_if(&mut self, c: u8) -> bool {
+        let matched = self.peek() == Some(c);
+        if matched {
+            self.pos += 1;
+        }
+        matched
+    }
+
+    fn natural(&mut self) -> Option<u64> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        std::str::from_utf8(&self.text[start..self.pos])
+            .ok()?
+            .parse()
+            .ok()
+    }
+
+    /// `_` for 0, or a number `n` and `_` for `n + 1`.
+    fn index(&mut self) -> Option<u64> {
+        if self.next_if(b'_') {
+            return Some(0);
+        }
+        let n = self.natural()?;
+        self.next_if(b'_').then_some(n + 1)
+    }
+
+    fn pop(&mut self, accept: impl Fn(&Node) -> bool) -> Option<Node> {
+        if accept(self.stack.last()?) {
+            self.stack.pop()
+        } else {
+            None
+        }
+    }
+
+    fn pop_type(&mut self) -> Option<Node> {
+        self.pop(Node::is_type)
+    }
+
+    fn pop_context(&mut self) -> Option<Node> {
+        match self.stack.pop()? {
+            Node::Identifier(name) => Some(Node::Module(name)),
+            node if node.is_context() => Some(node),
+            _ => None,
+        }
+    }
+
+    fn pop_module(&mut self) -> Option<String> {
+        match self.stack.pop()? {
+            Node::Identifier(name) | Node::Module(name) => Some(name),
+            _ => None,
+        }
+    }
+
+    fn pop_protocol(&mut self) -> Option<Node> {
+        if let Some(protocol) =
+            self.pop(|node| matches!(node, Node::Nominal(NominalKind::Protocol, ..)))
+        {
+            return Some(protocol);
+        }
+        let name = self.pop(Node::is_decl_name)?;
+        let context = self.pop_context()?;
+        Some(Node::Nominal(
+            NominalKind::Protocol,
+            Box::new(context),
+            Box::new(name),
+        ))
+    }
+
+    fn operator(&mut self) -> Option<Node> {
+        let c = self.next()?;
+        match c {
+            b'0'..=b'9' => {
+                self.pos -= 1;
+                self.identifier()
+            }
+            b'A' => self.multi_substitution(),
+            b'C' => self.nominal(NominalKind::Class),
+            b'V' => self.nominal(NominalKind::Struct),
+            b'O' => self.nominal(NominalKind::Enum),
+            b'P' => self.nominal(NominalKind::Protocol),
+            b'a' => self.nominal(NominalKind::TypeAlias),
+            b'D' => self.pop_type(),
+            b'E' => {
+                let module = self.pop_module()?;
+                let extended = self.pop_type()?;
+                Some(Node::Extension(module, Box::new(extended)))
+            }
+            b'F' => self.plain_function(),
+            b'G' => self.bound_generic(),
+            b'K' => Some(Node::Throws),
+            b'L' => self.local_name(),
+            b'M' => self.metadata(),
+            b'N' => Some(Node::Described(
+                "type metadata for ",
+                Box::new(self.pop_type()?),
+            )),
+            b'Q' => self.associated_type(),
+            b'R' => self.requirement(),
+            b'S' => self.standard_substitution(),
+            b'T' => self.thunk(),
+            b'W' => self.witness(),
+            b'X' => match self.next()? {
+                b'E' => self.function_type(),
+                _ => None,
+            },
+            b'Y' => match self.next()? {
+                b'a' => Some(Node::Async),
+                _ => None,
+            },
+            b'Z' => Some(Node::Static(Box::new(self.pop(Node::is_entity)?))),
+            b'_' => Some(Node::FirstElement),
+            b'c' => self.function_type(),
+            b'd' => Some(Node::Variadic),
+            b'f' => self.function_entity(),
+            b'h' => Some(Node::Modified("__shared ", Box::new(self.pop_type()?))),
+            b'i' => self.subscript(),
+            b'l' => self.generic_signature(false),
+            b'm' => Some(Node::Metatype(Box::new(self.pop_type()?))),
+            b'n' => Some(Node::Modified("__owned ", Box::new(self.pop_type()?))),
+            b'p' => self.existential(),
+            b'q' => self
+                .generic_param_index()
+                .map(|(d, i)| Node::GenericParam(d, i)),
+            b'r' => self.generic_signature(true),
+            b's' => Some(Node::Module("Swift".into())),
+            b't' => self.tuple(),
+            b'u' => {
+                let signature = self.pop(|node| matches!(node, Node::Signature(..)))?;
+                let ty = self.pop_type()?;
+                Some(Node::GenericType(Box::new(signature), Box::new(ty)))
+            }
+            b'v' => {
+                let ty = self.pop_type()?;
+                let name = self.pop(Node::is_decl_name)?;
+                let context = self.pop_context()?;
+                let variable = Node::Variable {
+                    context: Box::new(context),
+                    name: Box::new(name),
+                    ty: Box::new(ty),
+                };
+                self.accessor(variable)
+            }
+            b'x' => Some(Node::GenericParam(0, 0)),
+            b'y' => Some(Node::EmptyList),
+            b'z' => Some(Node::Modified("inout ", Box::new(self.pop_type()?))),
+            _ => None,
+        }
+    }
+
+    fn identifier(&mut self) -> Option<Node> {
+        let mut word_substitutions = false;
+        if self.next_if(b'0') {
+            // `00` introduces a punycoded identifier.
+            if self.peek() == Some(b'0') {
+                return None;
+            }
+            word_substitutions = true;
+        }
+        let mut identifier = String::new();
+        loop {
+            while word_substitutions && self.peek().is_some_and(|c| c.is_ascii_alphabetic()) {
+                let c = self.next()?;
+                let index = if c.is_ascii_lowercase() {
+                    c - b'a'
+                } else {
+                    word_substitutions = false;
+                    c - b'A'
+                };
+                identifier.push_str(self.words.get(index as usize)?);
+            }
+            if self.next_if(b'0') {
+                break;
+            }
+            let len = self.natural()? as usize;
+            if len == 0 {
+                return None;
+            }
+            let slice = std::str::from_utf8(self.text.get(self.pos..self.pos + len)?).ok()?;
+            self.pos += len;
+            identifier.push_str(slice);
+            self.record_words(slice);
+            if !word_substitutions {
+                break;
+            }
+        }
+        let node = Node::Identifier(identifier);
+        self.substitutions.push(node.clone());
+        Some(node)
+    }
+
+    /// Adds the words of `slice` (runs that start at a letter and end before
+    /// an underscore or a lower-to-upper case change) to the word list.
+    fn record_words(&mut self, slice: &str) {
+        let bytes = slice.as_bytes();
+        let mut start = None;
+        for i in 0..=bytes.len() {
+            let c = bytes.get(i).copied().unwrap_or(0);
+            if let Some(begin) = start {
+                let prev: u8 = bytes[i - 1];
+                let word_end =
+                    c == b'_' || c == 0 || (!prev.is_ascii_uppercase() && c.is_ascii_uppercase());
+                if word_end {
+                    if i - begin >= 2 && self.words.len() < MAX_WORDS {
+                        self.words.push(slice[begin..i].to_string());
+                    }
+                    start = None;
+                }
+            }
+            if start.is_none() && !c.is_ascii_digit() && c != b'_' && c != 0 {
+                start = Some(i);
+            }
+        }
+    }
+
+    fn multi_substitution(&mut self) -> Option<Node> {
+        let mut repeat: Option<u64> = None;
+        loop {
+            let c = self.next()?;
+            if c.is_ascii_lowercase() {
+                let node = self.substitutions.get((c - b'a') as usize)?.clone();
+                for _ in 0..repeat.unwrap_or(1) {
+                    self.stack.push(node.clone());
+                }
+                repeat = None;
+            } else if c.is_ascii_uppercase() {
+                let node = self.substitutions.get((c - b'A') as usize)?.clone();
+                for _ in 1..repeat.unwrap_or(1) {
+                    self.stack.push(node.clone());
+                }
+                return Some(node);
+            } else if c == b'_' {
+                let index = repeat.map_or(26, |n| n + 27) as usize;
+                return self.substitutions.get(index).cloned();
+            } else {
+                self.pos -= 1;
+                repeat = Some(self.natural()?);
+            }
+        }
+    }
+
+    fn nominal(&mut self, kind: NominalKind) -> Option<Node> {
+        let name = self.pop(Node::is_decl_name)?;
+        let context = self.pop_context()?;
+        let node = Node::Nominal(kind, Box::new(context), Box::new(name));
+        self.substitutions.push(node.clone());
+        Some(node)
+    }
+
+    fn bound_generic(&mut self) -> Option<Node> {
+        let mut lists = Vec::new();
+        loop {
+            let mut list = Vec::new();
+            while let Some(ty) = self.pop_type() {
+                list.push(ty);
+            }
+            list.reverse();
+            lists.push(list);
+            if self.pop(|node| matches!(node, Node::EmptyList)).is_some() {
+                break;
+            }
+            self.pop(|node| matches!(node, Node::FirstElement))?;
+        }
+        let nominal = self.pop(|node| matches!(node, Node::Nominal(..)))?;
+        let node = bind_generic_args(nominal, &lists);
+        self.substitutions.push(node.clone());
+        Some(node)
+    }
+
+    fn standard_substitution(&mut self) -> Option<Node> {
+        match self.peek()? {
+            b'o' => {
+                self.pos += 1;
+                return Some(Node::Module("__C".into()));
+            }
+            b'C' => {
+                self.pos += 1;
+                return Some(Node::Module("__C_Synthesized".into()));
+            }
+            b'g' => {
+                self.pos += 1;
+                let wrapped = self.pop_type()?;
+                let node = Node::BoundGeneric(
+                    Box::new(stdlib(NominalKind::Enum, "Optional")),
+                    vec![wrapped],
+                );
+                self.substitutions.push(node.clone());
+                return Some(node);
+            }
+            _ => {}
+        }
+        let repeat = if self.peek()?.is_ascii_digit() {
+            self.natural()?
+        } else {
+            1
+        };
+        let concurrency = self.next_if(b'c');
+        let (kind, name) = standard_type(self.next()?, concurrency)?;
+        let node = stdlib(kind, name);
+        for _ in 1..repeat {
+            self.stack.push(node.clone());
+        }
+        Some(node)
+    }
+
+    fn local_name(&mut self) -> Option<Node> {
+        if self.next_if(b'L') {
+            self.pop(|node| matches!(node, Node::Identifier(_)))?;
+            return match self.pop(Node::is_decl_name)? {
+                Node::Identifier(name) => Some(Node::PrivateName(name)),
+                _ => None,
+            };
+        }
+        let index = self.index()?;
+        match self.pop(Node::is_decl_name)? {
+            Node::Identifier(name) => Some(Node::LocalName(name, index)),
+            _ => None,
+        }
+    }
+
+    fn metadata(&mut self) -> Option<Node> {
+        let description = match self.next()? {
+            b'a' => "type metadata accessor for ",
+            b'f' => "full type metadata for ",
+            b'l' => "lazy cache variable for type metadata for ",
+            b'm' => "metaclass for ",
+            b'n' => "nominal type descriptor for ",
+            b'o' => "class metadata base offset for ",
+            b'u' => "method lookup function for ",
+            b'p' => {
+                let protocol = self.pop_protocol()?;
+                return Some(Node::Described(
+                    "protocol descriptor for ",
+                    Box::new(protocol),
+                ));
+            }
+            _ => return None,
+        };
+        Some(Node::Described(description, Box::new(self.pop_type()?)))
+    }
+
+    fn witness(&mut self) -> Option<Node> {
+        match self.next()? {
+            b'V' => Some(Node::Described(
+                "value witness table for ",
+                Box::new(self.pop_type()?),
+            )),
+            b'v' => {
+                let description = match self.next()? {
+                    b'd' => "direct field offset for ",
+                    b'i' => "indirect field offset for ",
+                    _ => return None,
+                };
+                Some(Node::Described(
+                    description,
+                    Box::new(self.pop(Node::is_entity)?),
+                ))
+            }
+            _ => None,
+        }
+    }
+
+    fn thunk(&mut self) -> Option<Node> {
+        let attribute = match self.next()? {
+            b'A' => "partial apply forwarder for ",
+            b'O' => "@nonobjc ",
+            b'j' => "dispatch thunk of ",
+            b'm' => "merged ",
+            b'o' => "@objc ",
+            b'q' => "method descriptor for ",
+            // Async functions are split into partial functions at each
+            // suspension point; these are what continuations resume into.
+            b'Q' => {
+                return Some(Node::Attribute(format!(
+                    "({}) await resume partial function for ",
+                    self.index()?
+                )))
+            }
+            b'Y' => {
+                return Some(Node::Attribute(format!(
+                    "({}) suspend resume partial function for ",
+                    self.index()?
+                )))
+            }
+            _ => return None,
+        };
+        Some(Node::Attribute(attribute.to_string()))
+    }
+
+    fn function_type(&mut self) -> Option<Node> {
+        let throws = self.pop(|node| matches!(node, Node::Throws)).is_some();
+        let is_async = self.pop(|node| matches!(node, Node::Async)).is_some();
+        let params = self.function_params()?;
+        let result = self.function_params()?;
+        Some(Node::FunctionType {
+            params: Box::new(params),
+            result: Box::new(result),
+            throws,
+            is_async,
+        })
+    }
+
+    fn function_params(&mut self) -> Option<Node> {
+        if self.pop(|node| matches!(node, Node::EmptyList)).is_some() {
+            return Some(Node::Tuple(Vec::new()));
+        }
+        self.pop_type()
+    }
+
+    /// The argument labels of a function whose type is `ty`: `None` when
+    /// there are none, `_` entries as `None`.
+    fn labels(&mut self, ty: &Node) -> Option<Option<Vec<Option<String>>>> {
+        if self.pop(|node| matches!(node, Node::EmptyList)).is_some() {
+            return Some(None);
+        }
+        let count = match function_of(ty) {
+            Some(Node::FunctionType { params, .. }) => match params.as_ref() {
+                Node::Tuple(elements) => elements.len(),
+                _ => 1,
+            },
+            _ => 0,
+        };
+        if count == 0 {
+            return Some(None);
+        }
+        let mut labels = Vec::with_capacity(count);
+        for _ in 0..count {
+            labels.push(match self.stack.last() {
+                Some(Node::Identifier(label)) => {
+                    let label = label.clone();
+                    self.stack.pop();
+                    Some(label)
+                }
+                Some(Node::FirstElement) => {
+                    self.stack.pop();
+                    None
+                }
+                _ => return None,
+            });
+        }
+        labels.reverse();
+        Some(labels.iter().any(Option::is_some).then_some(labels))
+    }
+
+    fn plain_function(&mut self) -> Option<Node> {
+        let signature = self.pop(|node| matches!(node, Node::Signature(..)));
+        let mut ty = self.function_type()?;
+        let labels = self.labels(&ty)?;
+        if let Some(signature) = signature {
+            ty = Node::GenericType(Box::new(signature), Box::new(ty));
+        }
+        let name = self.pop(Node::is_decl_name)?;
+        let context = self.pop_context()?;
+        Some(Node::Function {
+            context: Box::new(context),
+            name: Box::new(name),
+            labels,
+            ty: Box::new(ty),
+        })
+    }
+
+    fn function_entity(&mut self) -> Option<Node> {
+        let c = self.next()?;
+        match c {
+            b'C' | b'c' => {
+                self.pop(|node| matches!(node, Node::PrivateName(_)));
+                let ty = self.pop_type()?;
+                let labels = self.labels(&ty)?;
+                let context = self.pop_context()?;
+                Some(Node::Initializer {
+                    context: Box::new(context),
+                    name: if c == b'C' {
+                        "__allocating_init"
+                    } else {
+                        "init"
+                    },
+                    labels,
+                    ty: Some(Box::new(ty)),
+                })
+            }
+            b'D' => Some(Node::Special(
+                "__deallocating_deinit",
+                Box::new(self.pop_context()?),
+            )),
+            b'd' => Some(Node::Special("deinit", Box::new(self.pop_context()?))),
+            b'E' => Some(Node::Special(
+                "__ivar_destroyer",
+                Box::new(self.pop_context()?),
+            )),
+            b'e' => Some(Node::Special(
+                "__ivar_initializer",
+                Box::new(self.pop_context()?),
+            )),
+            b'U' | b'u' => {
+                let index = self.index()?;
+                let ty = self.pop_type();
+                let context = self.pop_context()?;
+                Some(Node::Closure {
+                    context: Box::new(context),
+                    implicit: c == b'u',
+                    index,
+                    ty: ty.map(Box::new),
+                })
+            }
+            b'A' => {
+                let index = self.index()?;
+                Some(Node::DefaultArgument(Box::new(self.pop_context()?), index))
+            }
+            _ => None,
+        }
+    }
+
+    fn subscript(&mut self) -> Option<Node> {
+        self.pop(|node| matches!(node, Node::PrivateName(_)));
+        let ty = self.pop_type()?;
+        let labels = self.labels(&ty)?;
+        let context = self.pop_context()?;
+        self.accessor(Node::Subscript {
+            context: Box::new(context),
+            labels,
+            ty: Box::new(ty),
+        })
+    }
+
+    fn accessor(&mut self, entity: Node) -> Option<Node> {
+        let kind = match self.next()? {
+            b'p' => return Some(entity),
+            b'g' | b'G' => "getter",
+            b's' => "setter",
+            b'M' => "modify",
+            b'r' => "read",
+            b'w' => "willset",
+            b'W' => "didset",
+            b'm' => "materializeForSet",
+            b'i' => "init",
+            b'a' if self.next_if(b'u') => "unsafeMutableAddressor",
+            b'l' if self.next_if(b'u') => "unsafeAddressor",
+            _ => return None,
+        };
+        Some(Node::Accessor(kind, Box::new(entity)))
+    }
+
+    fn tuple(&mut self) -> Option<Node> {
+        let mut elements = Vec::new();
+        if self.pop(|node| matches!(node, Node::EmptyList)).is_none() {
+            loop {
+                let first = self
+                    .pop(|node| matches!(node, Node::FirstElement))
+                    .is_some();
+                let variadic = self.pop(|node| matches!(node, Node::Variadic)).is_some();
+                let label = match self.stack.last() {
+                    Some(Node::Identifier(label)) => {
+                        let label = label.clone();
+                        self.stack.pop();
+                        Some(label)
+                    }
+                    _ => None,
+                };
+                let ty = self.pop_type()?;
+                elements.push(TupleElement {
+                    label,
+                    variadic,
+                    ty,
+                });
+                if first {
+                    break;
+                }
+            }
+            elements.reverse();
+        }
+        Some(Node::Tuple(elements))
+    }
+
+    fn existential(&mut self) -> Option<Node> {
+        let mut protocols = Vec::new();
+        if self.pop(|node| matches!(node, Node::EmptyList)).is_none() {
+            loop {
+                let first = self
+                    .pop(|node| matches!(node, Node::FirstElement))
+                    .is_some();
+                protocols.push(self.pop_protocol()?);
+                if first {
+                    break;
+                }
+            }
+            protocols.reverse();
+        }
+        Some(Node::Existential(protocols))
+    }
+
+    fn generic_param_index(&mut self) -> Option<(u64, u64)> {
+        if self.next_if(b'd') {
+            let depth = self.index()? + 1;
+            let index = self.index()?;
+            return Some((depth, index));
+        }
+        if self.next_if(b'z') {
+            return Some((0, 0));
+        }
+        Some((0, self.index()? + 1))
+    }
+
+    fn generic_signature(&mut self, counted: bool) -> Option<Node> {
+        let mut counts = Vec::new();
+        if counted {
+            while !self.next_if(b'l') {
+                counts.push(if self.next_if(b'z') {
+                    0
+                } else {
+                    self.index()? + 1
+                });
+            }
+        } else {
+            counts.push(1);
+        }
+        let mut requirements = Vec::new();
+        while let Some(Node::Requirement(requirement)) =
+            self.pop(|node| matches!(node, Node::Requirement(_)))
+        {
+            requirements.push(*requirement);
+        }
+        requirements.reverse();
+        Some(Node::Signature(counts, requirements))
+    }
+
+    fn requirement(&mut self) -> Option<Node> {
+        let (same_type, subject) = match self.next()? {
+            b'b' | b's' => {
+                let same_type = self.text[self.pos - 1] == b's';
+                let (depth, index) = self.generic_param_index()?;
+                (same_type, Node::GenericParam(depth, index))
+            }
+            b'p' | b't' => {
+                let same_type = self.text[self.pos - 1] == b't';
+                let subject = self.associated_type_of(|this| {
+                    let (depth, index) = this.generic_param_index()?;
+                    Some(Node::GenericParam(depth, index))
+                })?;
+                (same_type, subject)
+            }
+            b'Q' | b'S' => {
+                let same_type = self.text[self.pos - 1] == b'S';
+                (same_type, self.pop_type()?)
+            }
+            _ => {
+                self.pos -= 1;
+                let (depth, index) = self.generic_param_index()?;
+                (false, Node::GenericParam(depth, index))
+            }
+        };
+        let requirement = if same_type {
+            Requirement::SameType(subject, self.pop_type()?)
+        } else {
+            let constraint = match self.pop_protocol() {
+                Some(protocol) => protocol,
+                None => self.pop_type()?,
+            };
+            Requirement::Conforms(subject, constraint)
+        };
+        Some(Node::Requirement(Box::new(requirement)))
+    }
+
+    fn associated_type(&mut self) -> Option<Node> {
+        match self.next()? {
+            b'z' => self.associated_type_of(|_| Some(Node::GenericParam(0, 0))),
+            b'y' => self.associated_type_of(|this| {
+                let (depth, index) = this.generic_param_index()?;
+                Some(Node::GenericParam(depth, index))
+            }),
+            _ => None,
+        }
+    }
+
+    /// `Base.Name` for an associated type name on the stack, with `base`
+    /// reading the base type from the text.
+    fn associated_type_of(&mut self, base: impl FnOnce(&mut Self) -> Option<Node>) -> Option<Node> {
+        self.pop(|node| matches!(node, Node::Nominal(NominalKind::Protocol, ..)));
+        let Node::Identifier(name) = self.pop(|node| matches!(node, Node::Identifier(_)))? else {
+            return None;
+        };
+        let node = Node::Member(Box::new(base(self)?), name);
+        self.substitutions.push(node.clone());
+        Some(node)
+    }
+}
+
+/// `nominal` with the innermost argument list in `lists` applied to it and
+/// the outer ones to its enclosing types.
+fn bind_generic_args(nominal: Node, lists: &[Vec<Node>]) -> Node {
+    let Some((args, outer)) = lists.split_first() else {
+        return nominal;
+    };
+    let nominal = match nominal {
+        Node::Nominal(kind, context, name) if !outer.is_empty() => {
+            let context = match *context {
+                context @ Node::Nominal(..) => bind_generic_args(context, outer),
+                context => context,
+            };
+            Node::Nominal(kind, Box::new(context), name)
+        }
+        nominal => nominal,
+    };
+    if args.is_empty() {
+        nominal
+    } else {
+        Node::BoundGeneric(Box::new(nominal), args.clone())
+    }
+}
+
+fn stdlib(kind: NominalKind, name: &str) -> Node {
+    Node::Nominal(
+        kind,
+        Box::new(Node::Module("Swift".into())),
+        Box::new(Node::Identifier(name.into())),
+    )
+}
+
+/// The standard-library type an `S` substitution letter stands for.
+fn standard_type(c: u8, concurrency: bool) -> Option<(NominalKind, &'static str)> {
+    use NominalKind::{Class, Protocol, Struct};
+    if concurrency {
+        return Some(match c {
+            b'A' => (Protocol, "Actor"),
+            b'C' => (Struct, "CheckedContinuation"),
+            b'c' => (Struct, "UnsafeContinuation"),
+            b'E' => (Struct, "CancellationError"),
+            b'F' => (Protocol, "Executor"),
+            b'f' => (Protocol, "SerialExecutor"),
+            b'G' => (Struct, "TaskGroup"),
+            b'g' => (Struct, "ThrowingTaskGroup"),
+            b'I' => (Protocol, "AsyncIteratorProtocol"),
+            b'i' => (Protocol, "AsyncSequence"),
+            b'M' => (Class, "MainActor"),
+            b'P' => (Struct, "TaskPriority"),
+            b'S' => (Struct, "AsyncStream"),
+            b's' => (Struct, "AsyncThrowingStream"),
+            b'T' => (Struct, "Task"),
+            b't' => (Struct, "UnsafeCurrentTask"),
+            _ => return None,
+        });
+    }
+    Some(match c {
+        b'A' => (Struct, "AutoreleasingUnsafeMutablePointer"),
+        b'a' => (Struct, "Array"),
+        b'B' => (Protocol, "BinaryFloatingPoint"),
+        b'b' => (Struct, "Bool"),
+        b'D' => (Struct, "Dictionary"),
+        b'd' => (Struct, "Double"),
+        b'E' => (Protocol, "Encodable"),
+        b'e' => (Protocol, "Decodable"),
+        b'F' => (Protocol, "FloatingPoint"),
+        b'f' => (Struct, "Float"),
+        b'G' => (Protocol, "RandomNumberGenerator"),
+        b'H' => (Protocol, "Hashable"),
+        b'h' => (Struct, "Set"),
+        b'I' => (Struct, "DefaultIndices"),
+        b'i' => (Struct, "Int"),
+        b'J' => (Struct, "Character"),
+        b'j' => (Protocol, "Numeric"),
+        b'K' => (Protocol, "BidirectionalCollection"),
+        b'k' => (Protocol, "RandomAccessCollection"),
+        b'L' => (Protocol, "Comparable"),
+        b'l' => (Protocol, "Collection"),
+        b'M' => (Protocol, "MutableCollection"),
+        b'm' => (Protocol, "RangeReplaceableCollection"),
+        b'N' => (Struct, "ClosedRange"),
+        b'n' => (Struct, "Range"),
+        b'O' => (Struct, "ObjectIdentifier"),
+        b'P' => (Struct, "UnsafePointer"),
+        b'p' => (Struct, "UnsafeMutablePointer"),
+        b'Q' => (Protocol, "Equatable"),
+        b'q' => (NominalKind::Enum, "Optional"),
+        b'R' => (Struct, "UnsafeBufferPointer"),
+        b'r' => (Struct, "UnsafeMutableBufferPointer"),
+        b'S' => (Struct, "String"),
+        b's' => (Struct, "Substring"),
+        b'T' => (Protocol, "Sequence"),
+        b't' => (Protocol, "IteratorProtocol"),
+        b'U' => (Protocol, "UnsignedInteger"),
+        b'u' => (Struct, "UInt"),
+        b'V' => (Struct, "UnsafeRawPointer"),
+        b'v' => (Struct, "UnsafeMutableRawPointer"),
+        b'W' => (Struct, "UnsafeRawBufferPointer"),
+        b'w' => (Struct, "UnsafeMutableRawBufferPointer"),
+        b'X' => (Protocol, "RangeExpression"),
+        b'x' => (Protocol, "Strideable"),
+        b'Y' => (Protocol, "RawRepresentable"),
+        b'y' => (Protocol, "StringProtocol"),
+        b'Z' => (Protocol, "SignedInteger"),
+        b'z' => (Protocol, "BinaryInteger"),
+        _ => return None,
+    })
+}
+
+/// The function type inside `ty`, looking through a generic signature.
+fn function_of(ty: &Node) -> Option<&Node> {
+    match ty {
+        Node::GenericType(_, inner) => function_of(inner),
+        Node::FunctionType { .. } => Some(ty),
+        _ => None,
+    }
+}
+
+fn decl_name(node: &Node) -> String {
+    match node {
+        Node::Identifier(name) | Node::PrivateName(name) => name.clone(),
+        Node::LocalName(name, index) => format!("({name} #{})", index + 1),
+        other => describe(other),
+    }
+}
+
+fn is_implicit_module(module: &str) -> bool {
+    matches!(module, "Swift" | "__C" | "__C_Synthesized")
+}
+
+/// `node` as the context of a declaration: fully qualified.
+fn context_name(node: &Node) -> String {
+    match node {
+        Node::Module(module) => module.clone(),
+        Node::Nominal(_, context, name) => {
+            format!("{}.{}", context_name(context), decl_name(name))
+        }
+        Node::Extension(module, extended) => {
+            format!("(extension in {module}):{}", context_name(extended))
+        }
+        node if node.is_type() => type_name(node),
+        node => describe(node),
+    }
+}
+
+/// `node` in a type position, with generic sugar and without the module of
+/// standard-library and imported types.
+fn type_name(node: &Node) -> String {
+    match node {
+        Node::Nominal(_, context, name) => match context.as_ref() {
+            Node::Module(module) if is_implicit_module(module) => decl_name(name),
+            context => format!("{}.{}", context_name(context), decl_name(name)),
+        },
+        Node::BoundGeneric(base, args) => {
+            let stdlib_name = match base.as_ref() {
+                Node::Nominal(_, context, name) if matches!(context.as_ref(), Node::Module(module) if module == "Swift") => {
+                    Some(decl_name(name))
+                }
+                _ => None,
+            };
+            match (stdlib_name.as_deref(), args.as_slice()) {
+                (Some("Array"), [element]) => format!("[{}]", type_name(element)),
+                (Some("Dictionary"), [key, value]) => {
+                    format!("[{} : {}]", type_name(key), type_name(value))
+                }
+                (Some("Optional"), [wrapped]) => match wrapped {
+                    Node::FunctionType { .. } | Node::Existential(_) => {
+                        format!("({})?", type_name(wrapped))
+                    }
+                    _ => format!("{}?", type_name(wrapped)),
+                },
+                _ => format!(
+                    "{}<{}>",
+                    type_name(base),
+                    args.iter().map(type_name).collect::<Vec<_>>().join(", ")
+                ),
+            }
+        }
+        Node::Tuple(elements) => format!(
+            "({})",
+            elements
+                .iter()
+                .map(|element| {
+                    let mut text = match &element.label {
+                        Some(label) => format!("{label}: {}", type_name(&element.ty)),
+                        None => type_name(&element.ty),
+                    };
+                    if element.variadic {
+                        text.push_str("...");
+                    }
+                    text
+                })
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        Node::FunctionType {
+            params,
+            result,
+            throws,
+            is_async,
+        } => {
+            let params = match params.as_ref() {
+                tuple @ Node::Tuple(_) => type_name(tuple),
+                single => format!("({})", type_name(single)),
+            };
+            let mut effects = String::new();
+            if *is_async {
+                effects.push_str(" async");
+            }
+            if *throws {
+                effects.push_str(" throws");
+            }
+            format!("{params}{effects} -> {}", type_name(result))
+        }
+        Node::GenericType(signature, ty) => {
+            format!("{} {}", signature_text(signature), type_name(ty))
+        }
+        Node::GenericParam(depth, index) => generic_param_name(*depth, *index),
+        Node::Member(base, name) => format!("{}.{name}", type_name(base)),
+        Node::Existential(protocols) => match protocols.as_slice() {
+            [] => "Any".into(),
+            protocols => protocols
+                .iter()
+                .map(type_name)
+                .collect::<Vec<_>>()
+                .join(" & "),
+        },
+        Node::Metatype(instance) => match instance.as_ref() {
+            Node::Existential(_) | Node::FunctionType { .. } => {
+                format!("({}).Type", type_name(instance))
+            }
+            instance => format!("{}.Type", type_name(instance)),
+        },
+        Node::Modified(modifier, ty) => format!("{modifier}{}", type_name(ty)),
+        other => describe(other),
+    }
+}
+
+fn generic_param_name(depth: u64, mut index: u64) -> String {
+    let mut name = String::new();
+    loop {
+        name.push((b'A' + (index % 26) as u8) as char);
+        index /= 26;
+        if index == 0 {
+            break;
+        }
+    }
+    if depth != 0 {
+        name.push_str(&depth.to_string());
+    }
+    name
+}
+
+fn signature_text(signature: &Node) -> String {
+    let Node::Signature(counts, requirements) = signature else {
+        return String::new();
+    };
+    let params: Vec<String> = counts
+        .iter()
+        .enumerate()
+        .flat_map(|(depth, count)| {
+            (0..*count).map(move |index| generic_param_name(depth as u64, index))
+        })
+        .collect();
+    let requirements: Vec<String> = requirements
+        .iter()
+        .map(|requirement| match requirement {
+            Requirement::Conforms(subject, constraint) => {
+                format!("{}: {}", type_name(subject), type_name(constraint))
+            }
+            Requirement::SameType(subject, other) => {
+                format!("{} == {}", type_name(subject), type_name(other))
+            }
+        })
+        .collect();
+    if requirements.is_empty() {
+        format!("<{}>", params.join(", "))
+    } else {
+        format!("<{} where {}>", params.join(", "), requirements.join(", "))
+    }
+}
+
+/// The parameter list and result of a function type, with `labels` merged
+/// into the parameters: `<A>(x: Int, _: String) throws -> Bool`.
+fn signature(ty: &Node, labels: Option<&[Option<String>]>) -> String {
+    let (generics, function) = match ty {
+        Node::GenericType(signature, inner) => (signature_text(signature), inner.as_ref()),
+        other => (String::new(), other),
+    };
+    let Node::FunctionType {
+        params,
+        result,
+        throws,
+        is_async,
+    } = function
+    else {
+        return format!("{generics} : {}", type_name(function));
+    };
+    let params: Vec<String> = match params.as_ref() {
+        Node::Tuple(elements) => elements
+            .iter()
+            .map(|element| {
+                let mut text = type_name(&element.ty);
+                if element.variadic {
+                    text.push_str("...");
+                }
+                text
+            })
+            .collect(),
+        single => vec![type_name(single)],
+    };
+    let params: Vec<String> = match labels {
+        Some(labels) => params
+            .into_iter()
+            .zip(labels)
+            .map(|(param, label)| format!("{}: {param}", label.as_deref().unwrap_or("_")))
+            .collect(),
+        None => params,
+    };
+    let mut effects = String::new();
+    if *is_async {
+        effects.push_str(" async");
+    }
+    if *throws {
+        effects.push_str(" throws");
+    }
+    format!(
+        "{generics}({}){effects} -> {}",
+        params.join(", "),
+        type_name(result)
+    )
+}
+
+/// A declaration or other top-level node, fully qualified.
+fn describe(node: &Node) -> String {
+    match node {
+        Node::Function {
+            context,
+            name,
+            labels,
+            ty,
+        } => format!(
+            "{}.{}{}",
+            context_name(context),
+            decl_name(name),
+            signature(ty, labels.as_deref())
+        ),
+        Node::Variable { context, name, ty } => format!(
+            "{}.{} : {}",
+            context_name(context),
+            decl_name(name),
+            type_name(ty)
+        ),
+        Node::Subscript {
+            context,
+            labels,
+            ty,
+        } => format!(
+            "{}.subscript{}",
+            context_name(context),
+            signature(ty, labels.as_deref())
+        ),
+        Node::Accessor(kind, entity) => match entity.as_ref() {
+            Node::Variable { context, name, ty } => format!(
+                "{}.{}.{kind} : {}",
+                context_name(context),
+                decl_name(name),
+                type_name(ty)
+            ),
+            Node::Subscript { context, ty, .. } => format!(
+                "{}.subscript.{kind} : {}",
+                context_name(context),
+                type_name(ty)
+            ),
+            other => format!("{}.{kind}", describe(other)),
+        },
+        Node::Static(entity) => format!("static {}", describe(entity)),
+        Node::Initializer {
+            context,
+            name,
+            labels,
+            ty,
+        } => {
+            let signature = ty
+                .as_deref()
+                .map(|ty| signature(ty, labels.as_deref()))
+                .unwrap_or_default();
+            format!("{}.{name}{signature}", context_name(context))
+        }
+        Node::Special(name, context) => format!("{}.{name}", context_name(context)),
+        Node::Closure {
+            context,
+            implicit,
+            index,
+            ty,
+        } => {
+            let kind = if *implicit {
+                "implicit closure"
+            } else {
+                "closure"
+            };
+            let ty = ty
+                .as_deref()
+                .map(|ty| format!(" {}", type_name(ty)))
+                .unwrap_or_default();
+            format!("{kind} #{}{ty} in {}", index + 1, context_name(context))
+        }
+        Node::DefaultArgument(context, index) => {
+            format!("default argument {index} of {}", context_name(context))
+        }
+        Node::Described(description, subject) => {
+            let subject = if subject.is_type() {
+                context_name(subject)
+            } else {
+                describe(subject)
+            };
+            format!("{description}{subject}")
+        }
+        Node::Module(_) | Node::Extension(..) | Node::Nominal(..) => context_name(node),
+        node if node.is_type() => type_name(node),
+        Node::Identifier(name) => name.clone(),
+        _ => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn demangles_methods_initializers_and_accessors() {
+        for (mangled, expected) in [
+            (
+                "$s5MyApp14ViewControllerC11viewDidLoadyyF",
+                "MyApp.ViewController.viewDidLoad() -> ()",
+            ),
+            (
+                "_$s4main3foo1x1yS2i_SStF",
+                "main.foo(x: Int, y: String) -> Int",
+            ),
+            ("$s4main3fooyySiF", "main.foo(Int) -> ()"),
+            ("$s4main3foo_1xySi_SitF", "main.foo(_: Int, x: Int) -> ()"),
+            (
+                "$s5MyApp4CartV5itemsSaySSGvg",
+                "MyApp.Cart.items.getter : [String]",
+            ),
+            (
+                "$s5MyApp4CartV6sharedACvgZ",
+                "static MyApp.Cart.shared.getter : MyApp.Cart",
+            ),
+            (
+                "$s5MyApp4CartV5itemsACSaySSG_tcfC",
+                "MyApp.Cart.__allocating_init(items: [String]) -> MyApp.Cart",
+            ),
+            (
+                "$s5MyApp5StoreC4load2idSDySSSiGSgSi_tYaKF",
+                "MyApp.Store.load(id: Int) async throws -> [String : Int]?",
+            ),
+            (
+                "$s5MyApp4CartVySSSicig",
+                "MyApp.Cart.subscript.getter : (Int) -> String",
+            ),
+            ("$s5MyApp4CartVMa", "type metadata accessor for MyApp.Cart"),
+            (
+                "$s5MyApp4loadyyYaFTQ0_",
+                "(1) await resume partial function for MyApp.load() async -> ()",
+            ),
+            (
+                "$sSS5MyAppE7shoutedSSvg",
+                "(extension in MyApp):Swift.String.shouted.getter : String",
+            ),
+        ] {
+            assert_eq!(demangle(mangled).as_deref(), Some(expected), "{mangled}");
+        }
+    }
+
+    #[test]
+    fn demangles_closures_generics_and_substitutions() {
+        for (mangled, expected) in [
+            (
+                "$s5MyApp14ViewControllerC11viewDidLoadyyFyycfU_",
+                "closure #1 () -> () in MyApp.ViewController.viewDidLoad() -> ()",
+            ),
+            (
+                "$s4main3fooyyxSHRzlF",
+                "main.foo<A where A: Hashable>(A) -> ()",
+            ),
+            (
+                "$s4main4sortySayxGACSLRzlF",
+                "main.sort<A where A: Comparable>([A]) -> [A]",
+            ),
+            ("$s4main3bar1fyyyXEF", "main.bar(f: () -> ()) -> ()"),
+            (
+                "$s4main8CartItemC0bC4ViewV5countSivg",
+                "main.CartItem.CartItemView.count.getter : Int",
+            ),
+            ("$s4main3logyys5Error_pF", "main.log(Error) -> ()"),
+        ] {
+            assert_eq!(demangle(mangled).as_deref(), Some(expected), "{mangled}");
+        }
+    }
+
+    #[test]
+    fn keeps_suffixes_and_rejects_other_symbols() {
+        assert_eq!(
+            demangle("$s5MyApp14ViewControllerC11viewDidLoadyyFTA.1").as_deref(),
+            Some(
+                "partial apply forwarder for MyApp.ViewController.viewDidLoad() -> () \
+                 with unmangled suffix \".1\""
+            )
+        );
+        assert_eq!(demangle("_main"), None);
+        assert_eq!(demangle("_ZN4core3fmt5write17h0123456789abcdefE"), None);
+        assert_eq!(demangle("$s5MyApp14ViewControllerC11viewDidLoadyyF!"), None);
+        assert!(is_mangled("$s4main3fooyySiF"));
+        assert!(!is_mangled("main"));
+    }
+}
@@ -0,0 +1,594 @@
+//! Swift concurrency task inspection, backing `tasks`.
+//!
+//! Async functions do not keep their callers on the thread's stack. Each
+//! suspension point returns to the executor, and the chain of callers lives
+//! in heap-allocated `AsyncContext`s instead, which is why an ordinary stack
+//! trace dead-ends in `swift_task_switch` or the executor's job loop. An
+//! async function's frame record saves the frame pointer with bit 60 set and
+//! keeps the function's context in the word below it; every context starts
+//! with its caller's context and the continuation to resume the caller at,
+//! so following those two words rebuilds the async backtrace.
+//!
+//! Tasks are `AsyncTask` heap objects. The task running on a thread is found
+//! by walking back from the root of its context chain, which the runtime
+//! allocates in the same block as the task. Suspended tasks are reached from
+//! there as child tasks (`async let`, task groups), or directly from a task
+//! handle's address.
+//!
+//! A default actor keeps its status inline: a state, the priority it runs
+//! at, the port of the thread draining it and the head of its queue of
+//! waiting jobs. Actors with a custom executor (`MainActor` among them)
+//! have none of that. Offsets are those of the Swift 5.9+ runtime on 64-bit
+//! targets, as laid out in `RemoteInspection/RuntimeInternals.h`.
+
+use std::collections::HashSet;
+
+use serde::Serialize;
+
+use crate::memory::{le_u32, le_u64, read_u64, CODE_POINTER_MASK, POINTER_MASK};
+
+/// Set in the frame pointer an async function saves in its frame record.
+const ASYNC_FRAME_FLAG: u64 = 1 << 60;
+/// Frame records walked on a thread before giving up on finding an async one.
+const MAX_FRAMES: usize = 512;
+/// Continuations followed in one async backtrace.
+const MAX_ASYNC_FRAMES: usize = 256;
+/// Tasks reported, counting children.
+const MAX_TASKS: usize = 1024;
+/// How far before its root context a task may start: the task, its
+/// fragments and the future's result storage.
+const MAX_TASK_PREFIX: u64 = 1024;
+
+/// `Job::Flags`, `Job::Id` and `Job::ResumeTask`.
+const JOB_FLAGS: usize = 32;
+const JOB_ID: usize = 36;
+const JOB_RESUME_TASK: usize = 56;
+/// `AsyncTask::ResumeContext`.
+const TASK_RESUME_CONTEXT: usize = 64;
+/// `ActiveTaskStatus::Flags` and `ActiveTaskStatus::Record`.
+const TASK_STATUS_FLAGS: usize = 96;
+const TASK_STATUS_RECORD: usize = 104;
+/// `sizeof(AsyncTask)`; the optional fragments follow, child fragment first.
+const TASK_SIZE: usize = 192;
+/// `ChildFragment::Parent` and `ChildFragment::NextChild`.
+const CHILD_PARENT: usize = TASK_SIZE;
+const CHILD_NEXT: usize = TASK_SIZE + 8;
+
+/// `JobFlags`: the job kind (0 for tasks) and priority, then task flags.
+const JOB_KIND_MASK: u32 = 0xff;
+const JOB_PRIORITY_SHIFT: u32 = 8;
+const TASK_IS_CHILD: u32 = 1 << 24;
+const TASK_IS_FUTURE: u32 = 1 << 25;
+const TASK_IS_GROUP_CHILD: u32 = 1 << 26;
+const TASK_IS_ASYNC_LET: u32 = 1 << 28;
+/// Bits `JobFlags` defines; anything else means the word is not a task's.
+const KNOWN_JOB_FLAGS: u32 = 0x3f00_ffff;
+
+/// `ActiveTaskStatus` flags.
+const STATUS_CANCELLED: u32 = 0x100;
+const STATUS_RUNNING: u32 = 0x800;
+const STATUS_ENQUEUED: u32 = 0x1000;
+const STATUS_COMPLETE: u32 = 0x2000;
+
+/// `TaskStatusRecordKind`s that lead to child tasks.
+const CHILD_TASK_RECORD: u8 = 1;
+const TASK_GROUP_RECORD: u8 = 2;
+
+/// `Job::SchedulerPrivate[NextWaitingTaskIndex]`, linking queued jobs.
+const JOB_NEXT: u64 = 16;
+/// Class metadata's nominal type descriptor, after the Objective-C fields
+/// and Swift class header.
+const CLASS_DESCRIPTION: u64 = 64;
+/// `ContextDescriptorKind::Class` and the `Class_IsActor` and
+/// `Class_IsDefaultActor` type context flags.
+const DESCRIPTOR_KIND_MASK: u32 = 0x1f;
+const DESCRIPTOR_KIND_CLASS: u32 = 16;
+const DESCRIPTOR_IS_ACTOR: u32 = 1 << 23;
+const DESCRIPTOR_IS_DEFAULT_ACTOR: u32 = 1 << 24;
+/// `DefaultActorImpl::Status`, after the object header and job storage:
+/// flags, drain lock and first queued job.
+const ACTOR_STATUS_FLAGS: u64 = 80;
+/// `ActorFlagConstants`.
+const ACTOR_STATE_MASK: u32 = 0x7;
+const ACTOR_DISTRIBUTED_REMOTE: u32 = 0x8;
+const ACTOR_PRIORITY_ESCALATED: u32 = 0x10;
+const ACTOR_PRIORITY_SHIFT: u32 = 8;
+/// Low bits of the drain lock that are flags rather than the thread port.
+const DRAIN_LOCK_FLAGS: u32 = 0x3;
+/// Jobs read from an actor's queue.
+const MAX_QUEUED_JOBS: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskState {
+    /// Executing on a thread.
+    Running,
+    /// Ready and waiting for its executor.
+    Enqueued,
+    /// Awaiting something; it resumes at the first continuation.
+    Suspended,
+    Completed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActorState {
+    /// Nothing running on the actor and nothing queued.
+    Idle,
+    /// Jobs are queued and the actor is waiting for a thread.
+    Scheduled,
+    /// A thread is executing a job on the actor, which holds its isolation.
+    Running,
+    /// Released and waiting for the last job to finish before deallocation.
+    Deallocating,
+}
+
+/// A Swift actor's status.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Actor {
+    /// Runs on the shared default executor; the status fields below are
+    /// only known for default actors.
+    pub default_actor: bool,
+    pub state: Option<ActorState>,
+    /// The highest priority of the jobs run or queued on the actor.
+    pub priority: Option<u8>,
+    pub escalated: bool,
+    pub distributed_remote: bool,
+    /// Mach port of the thread draining a running actor.
+    pub thread_port: Option<u32>,
+    /// Tasks queued to run on the actor, in queue order.
+    pub waiting: Vec<Task>,
+}
+
+/// The registers a thread's async frames are found from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ThreadRegisters {
+    pub thread_id: i64,
+    pub pc: u64,
+    pub fp: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Task {
+    /// `None` for an async chain running on a thread whose task could not
+    /// be located; the task fields are then unknown.
+    pub address: Option<u64>,
+    pub id: Option<u32>,
+    pub state: TaskState,
+    pub priority: Option<u8>,
+    pub cancelled: bool,
+    /// `future`, `child`, `group_child` and `async_let`, as they apply.
+    pub flags: Vec<&'static str>,
+    pub parent: Option<u64>,
+    pub thread_id: Option<i64>,
+    /// Code addresses of the async backtrace, innermost first: where the
+    /// task is executing or will resume, then each caller's continuation.
+    pub continuations: Vec<u64>,
+}
+
+/// Every task reachable from `threads` and `handles`, and their children.
+/// Tasks running on a thread come first, in thread order, and carry the
+/// backtrace of their live frames; an address in `handles` that is not a
+/// task is an error.
+pub fn collect<R>(
+    mut read: R,
+    threads: &[ThreadRegisters],
+    handles: &[u64],
+) -> Result<Vec<Task>, String>
+where
+    R: FnMut(u64, usize) -> Option<Vec<u8>>,
+{
+    let mut tasks = Vec::new();
+    let mut pending = Vec::new();
+    let mut seen = HashSet::new();
+    for thread in threads {
+        let Some((pc, context)) = async_frame(&mut read, thread.pc, thread.fp) else {
+            continue;
+        };
+        let (continuations, root) = async_backtrace(&mut read, pc, context);
+        let found = task_owning(&mut read, root)
+            .filter(|address| seen.insert(*address))
+            .and_then(|address| read_task(&mut read, address).map(|task| (address, task)));
+        let mut task = match found {
+            Some((address, (task, children))) => {
+                pending.extend(children.into_iter().map(|child| (child, Some(address))));
+                task
+            }
+            None => Task {
+                address: None,
+                id: None,
+                state: TaskState::Running,
+                priority: None,
+                cancelled: false,
+                flags: Vec::new(),
+                parent: None,
+                thread_id: None,
+                continuations: Vec::new(),
+            },
+        };
+        task.state = TaskState::Running;
+        task.thread_id = Some(thread.thread_id);
+        task.continuations = continuations;
+        tasks.push(task);
+    }
+    for &handle in handles {
+        let handle = handle & POINTER_MASK;
+        if !is_task(&mut read, handle) {
+            return Err(format!("0x{handle:x} is not a Swift task"));
+        }
+        pending.push((handle, None));
+    }
+    pending.reverse();
+    while let Some((address, parent)) = pending.pop() {
+        if tasks.len() >= MAX_TASKS {
+            break;
+        }
+        if !seen.insert(address) {
+            continue;
+        }
+        let Some((mut task, children)) = read_task(&mut read, address) else {
+            continue;
+        };
+        task.parent = task.parent.or(parent);
+        pending.extend(
+            children
+                .into_iter()
+                .rev()
+                .map(|child| (child, Some(address))),
+        );
+        tasks.push(task);
+    }
+    Ok(tasks)
+}
+
+/// The status of the actor at `object`, an instance of the Swift class at
+/// `class`; `None` when the class is not an actor.
+pub fn read_actor<R>(mut read: R, object: u64, class: u64) -> Option<Actor>
+where
+    R: FnMut(u64, usize) -> Option<Vec<u8>>,
+{
+    let descriptor = read_u64(&mut read, class + CLASS_DESCRIPTION)? & POINTER_MASK;
+    let flags = read(descriptor, 4)
+        .filter(|bytes| bytes.len() == 4)
+        .map(|bytes| le_u32(&bytes))?;
+    if flags & DESCRIPTOR_KIND_MASK != DESCRIPTOR_KIND_CLASS || flags & DESCRIPTOR_IS_ACTOR == 0 {
+        return None;
+    }
+    let mut actor = Actor {
+        default_actor: flags & DESCRIPTOR_IS_DEFAULT_ACTOR != 0,
+        state: None,
+        priority: None,
+        escalated: false,
+        distributed_remote: false,
+        thread_port: None,
+        waiting: Vec::new(),
+    };
+    if !actor.default_actor {
+        return Some(actor);
+    }
+    let Some(status) = read(object + ACTOR_STATUS_FLAGS, 16).filter(|bytes| bytes.len() == 16)
+    else {
+        return Some(actor);
+    };
+    let status_flags = le_u32(&status);
+    actor.state = Some(match status_flags & ACTOR_STATE_MASK {
+        0 => ActorState::Idle,
+        1 => ActorState::Scheduled,
+        2 => ActorState::Running,
+        _ => ActorState::Deallocating,
+    });
+    actor.priority = Some((status_flags >> ACTOR_PRIORITY_SHIFT) as u8);
+    actor.escalated = status_flags & ACTOR_PRIORITY_ESCALATED != 0;
+    actor.distributed_remote = status_flags & ACTOR_DISTRIBUTED_REMOTE != 0;
+    actor.thread_port = Some(le_u32(&status[4..]) & !DRAIN_LOCK_FLAGS)
+        .filter(|port| *port != 0 && actor.state == Some(ActorState::Running));
+    let mut job = le_u64(&status[8..]) & POINTER_MASK;
+    let mut seen = HashSet::new();
+    while job != 0 && seen.len() < MAX_QUEUED_JOBS && seen.insert(job) {
+        if let Some((task, _)) = read_task(&mut read, job) {
+            actor.waiting.push(task);
+        }
+        job = read_u64(&mut read, job + JOB_NEXT).unwrap_or(0) & POINTER_MASK;
+    }
+    Some(actor)
+}
+
+/// The innermost async frame on a thread stopped at `pc` with frame pointer
+/// `fp`: the pc inside that async function and the function's context.
+pub fn async_frame<R>(read: &mut R, pc: u64, fp: u64) -> Option<(u64, u64)>
+where
+    R: FnMut(u64, usize) -> Option<Vec<u8>>,
+{
+    let (mut pc, mut fp) = (pc, fp & POINTER_MASK);
+    for _ in 0..MAX_FRAMES {
+        if fp == 0 {
+            return None;
+        }
+        let saved = read_u64(read, fp)?;
+        if saved & ASYNC_FRAME_FLAG != 0 {
+            let context = read_u64(read, fp.checked_sub(8)?)? & POINTER_MASK;
+            return (context != 0).then_some((pc, context));
+        }
+        // Callers' frames sit higher on the stack; anything else is a
+        // corrupt or terminating chain.
+        if saved & POINTER_MASK <= fp {
+            return None;
+        }
+        pc = read_u64(read, fp + 8)? & CODE_POINTER_MASK;
+        fp = saved & POINTER_MASK;
+    }
+    None
+}
+
+/// `first` followed by the continuation each context from `context` up
+/// resumes its caller at, and the root of that context chain.
+pub fn async_backtrace<R>(read: &mut R, first: u64, context: u64) -> (Vec<u64>, u64)
+where
+    R: FnMut(u64, usize) -> Option<Vec<u8>>,
+{
+    let mut continuations = vec![first];
+    let mut context = context;
+    let mut seen = HashSet::new();
+    while continuations.len() < MAX_ASYNC_FRAMES && seen.insert(context) {
+        let Some(words) = read(context, 16).filter(|words| words.len() == 16) else {
+            break;
+        };
+        let resume = le_u64(&words[8..]) & CODE_POINTER_MASK;
+        if resume == 0 {
+            break;
+        }
+        continuations.push(resume);
+        let parent = le_u64(&words) & POINTER_MASK;
+        if parent == 0 {
+            break;
+        }
+        context = parent;
+    }
+    (continuations, context)
+}
+
+/// The task whose allocation holds `root`, the outermost context of a chain.
+fn task_owning<R>(read: &mut R, root: u64) -> Option<u64>
+where
+    R: FnMut(u64, usize) -> Option<Vec<u8>>,
+{
+    let lowest = root.saturating_sub(MAX_TASK_PREFIX);
+    let mut candidate = root.checked_sub(TASK_SIZE as u64)? & !0xf;
+    while candidate >= lowest && candidate > 0 {
+        if is_task(read, candidate) {
+            return Some(candidate);
+        }
+        candidate -= 16;
+    }
+    None
+}
+
+/// Whether `address` holds a task's header: an object whose job flags are
+/// a future task's, with an id assigned.
+fn is_task<R>(read: &mut R, address: u64) -> bool
+where
+    R: FnMut(u64, usize) -> Option<Vec<u8>>,
+{
+    let Some(header) = read(address, JOB_RESUME_TASK).filter(|h| h.len() == JOB_RESUME_TASK) else {
+        return false;
+    };
+    let flags = le_u32(&header[JOB_FLAGS..]);
+    le_u64(&header) & POINTER_MASK != 0
+        && le_u64(&header[8..]) != 0
+        && flags & JOB_KIND_MASK == 0
+        && flags & !KNOWN_JOB_FLAGS == 0
+        && flags & TASK_IS_FUTURE != 0
+        && le_u32(&header[JOB_ID..]) != 0
+}
+
+/// The task at `address` and its child tasks. A task that is not running
+/// gets the backtrace it will resume with.
+fn read_task<R>(read: &mut R, address: u64) -> Option<(Task, Vec<u64>)>
+where
+    R: FnMut(u64, usize) -> Option<Vec<u8>>,
+{
+    let bytes = read(address, CHILD_NEXT + 8)
+        .or_else(|| read(address, TASK_SIZE))
+        .filter(|bytes| bytes.len() >= TASK_SIZE)?;
+    let job_flags = le_u32(&bytes[JOB_FLAGS..]);
+    if job_flags & JOB_KIND_MASK != 0 {
+        return None;
+    }
+    let status = le_u32(&bytes[TASK_STATUS_FLAGS..]);
+    let state = if status & STATUS_COMPLETE != 0 {
+        TaskState::Completed
+    } else if status & STATUS_RUNNING != 0 {
+        TaskState::Running
+    } else if status & STATUS_ENQUEUED != 0 {
+        TaskState::Enqueued
+    } else {
+        TaskState::Suspended
+    };
+    let resume = le_u64(&bytes[JOB_RESUME_TASK..]) & CODE_POINTER_MASK;
+    let continuations = match state {
+        TaskState::Suspended | TaskState::Enqueued if resume != 0 => {
+            let context = le_u64(&bytes[TASK_RESUME_CONTEXT..]) & POINTER_MASK;
+            async_backtrace(read, resume, context).0
+        }
+        _ => Vec::new(),
+    };
+    let flags = [
+        (TASK_IS_FUTURE, "future"),
+        (TASK_IS_CHILD, "child"),
+        (TASK_IS_GROUP_CHILD, "group_child"),
+        (TASK_IS_ASYNC_LET, "async_let"),
+    ]
+    .into_iter()
+    .filter(|(bit, _)| job_flags & bit != 0)
+    .map(|(_, name)| name)
+    .collect();
+    let parent = (job_flags & TASK_IS_CHILD != 0 && bytes.len() >= CHILD_NEXT)
+        .then(|| le_u64(&bytes[CHILD_PARENT..]) & POINTER_MASK)
+        .filter(|parent| *parent != 0);
+    let task = Task {
+        address: Some(address),
+        id: Some(le_u32(&bytes[JOB_ID..])),
+        state,
+        priority: Some((job_flags >> JOB_PRIORITY_SHIFT) as u8),
+        cancelled: status & STATUS_CANCELLED != 0,
+        flags,
+        parent,
+        thread_id: None,
+        continuations,
+    };
+    let children = child_tasks(read, le_u64(&bytes[TASK_STATUS_RECORD..]));
+    Some((task, children))
+}
+
+/// Children linked from the status records starting at `record`: each
+/// child-task and task-group record points at its first child, and children
+/// are chained through their child fragments.
+fn child_tasks<R>(read: &mut R, record: u64) -> Vec<u64>
+where
+    R: FnMut(u64, usize) -> Option<Vec<u8>>,
+{
+    let mut children = Vec::new();
+    let mut record = record & POINTER_MASK;
+    let mut seen = HashSet::new();
+    while record != 0 && children.len() < MAX_TASKS && seen.insert(record) {
+        let Some(words) = read(record, 24).filter(|words| words.len() == 24) else {
+            break;
+        };
+        if matches!(words[0], CHILD_TASK_RECORD | TASK_GROUP_RECORD) {
+            let mut child = le_u64(&words[16..]) & POINTER_MASK;
+            while child != 0 && children.len() < MAX_TASKS && !children.contains(&child) {
+                children.push(child);
+                child = read_u64(read, child + CHILD_NEXT as u64).unwrap_or(0) & POINTER_MASK;
+            }
+        }
+        record = le_u64(&words[8..]) & POINTER_MASK;
+    }
+    children
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::testing::Memory;
+
+    /// Writes the header of a task at `address`.
+    fn put_task(memory: &mut Memory, address: u64, id: u32, job_flags: u32, status: u32) {
+        memory.set(address, 0x1_0000_8000);
+        memory.set(address + 8, 3);
+        memory.set(
+            address + JOB_FLAGS as u64,
+            u64::from(id) << 32 | u64::from(job_flags),
+        );
+        memory.set(address + TASK_STATUS_FLAGS as u64, u64::from(status));
+    }
+
+    #[test]
+    fn running_task_is_found_from_its_thread() {
+        let mut memory = Memory::zero_filled();
+        // Sync frame (the executor) → async frame with context 0x5000.
+        memory.set(0x7000, 0x7100);
+        memory.set(0x7008, 0x1_0000_2000);
+        memory.set(0x7100, 0x7200 | ASYNC_FRAME_FLAG);
+        memory.set(0x70f8, 0x5000);
+        // 0x5000 → root 0x4100 (resume 0x2100) → none (resume 0x2200).
+        memory.set(0x5000, 0x4100);
+        memory.set(0x5008, 0x1_0000_2100);
+        memory.set(0x4108, 0x1_0000_2200);
+        put_task(
+            &mut memory,
+            0x4000,
+            7,
+            TASK_IS_FUTURE | 0x19 << 8,
+            STATUS_RUNNING | 0x19,
+        );
+
+        let threads = [ThreadRegisters {
+            thread_id: 3,
+            pc: 0x1_0000_1000,
+            fp: 0x7000,
+        }];
+        let tasks = collect(|a, l| memory.read(a, l), &threads, &[]).unwrap();
+        assert_eq!(tasks.len(), 1);
+        let task = &tasks[0];
+        assert_eq!(task.address, Some(0x4000));
+        assert_eq!(task.id, Some(7));
+        assert_eq!(task.state, TaskState::Running);
+        assert_eq!(task.priority, Some(0x19));
+        assert_eq!(task.thread_id, Some(3));
+        assert_eq!(task.flags, ["future"]);
+        assert_eq!(
+            task.continuations,
+            [0x1_0000_2000, 0x1_0000_2100, 0x1_0000_2200]
+        );
+    }
+
+    #[test]
+    fn default_actor_reports_its_status_and_queue() {
+        let mut memory = Memory::zero_filled();
+        let flags = DESCRIPTOR_KIND_CLASS | DESCRIPTOR_IS_ACTOR | DESCRIPTOR_IS_DEFAULT_ACTOR;
+        memory.set(0x3000 + CLASS_DESCRIPTION, 0x3800);
+        memory.set(0x3800, u64::from(flags));
+        // Running at priority 0x15, drained by port 0x2303, one task queued.
+        memory.set(0x2000 + ACTOR_STATUS_FLAGS, 0x2303 << 32 | 0x15 << 8 | 2);
+        memory.set(0x2000 + ACTOR_STATUS_FLAGS + 8, 0x4000);
+        put_task(
+            &mut memory,
+            0x4000,
+            9,
+            TASK_IS_FUTURE | 0x19 << 8,
+            STATUS_ENQUEUED | 0x19,
+        );
+
+        let actor = read_actor(|a, l| memory.read(a, l), 0x2000, 0x3000).unwrap();
+        assert!(actor.default_actor);
+        assert_eq!(actor.state, Some(ActorState::Running));
+        assert_eq!(actor.priority, Some(0x15));
+        assert_eq!(actor.thread_port, Some(0x2300));
+        assert_eq!(actor.waiting.len(), 1);
+        assert_eq!(actor.waiting[0].id, Some(9));
+        assert_eq!(actor.waiting[0].state, TaskState::Enqueued);
+
+        // Custom executors keep no inline status; plain classes are no actor.
+        memory.set(
+            0x3800,
+            u64::from(DESCRIPTOR_KIND_CLASS | DESCRIPTOR_IS_ACTOR),
+        );
+        let actor = read_actor(|a, l| memory.read(a, l), 0x2000, 0x3000).unwrap();
+        assert!(!actor.default_actor);
+        assert_eq!(actor.state, None);
+        memory.set(0x3800, u64::from(DESCRIPTOR_KIND_CLASS));
+        assert!(read_actor(|a, l| memory.read(a, l), 0x2000, 0x3000).is_none());
+    }
+
+    #[test]
+    fn suspended_children_resume_through_their_contexts() {
+        let mut memory = Memory::zero_filled();
+        put_task(&mut memory, 0x4000, 1, TASK_IS_FUTURE, 0);
+        // A task group record listing two children.
+        memory.set(0x4000 + TASK_STATUS_RECORD as u64, 0x6000);
+        memory.set(0x6000, u64::from(TASK_GROUP_RECORD));
+        memory.set(0x6010, 0x8000);
+        let child_flags = TASK_IS_FUTURE | TASK_IS_CHILD | TASK_IS_GROUP_CHILD;
+        put_task(&mut memory, 0x8000, 2, child_flags, STATUS_CANCELLED);
+        memory.set(0x8000 + CHILD_PARENT as u64, 0x4000);
+        memory.set(0x8000 + CHILD_NEXT as u64, 0x9000);
+        memory.set(0x8000 + JOB_RESUME_TASK as u64, 0x1_0000_3000);
+        memory.set(0x8000 + TASK_RESUME_CONTEXT as u64, 0x5000);
+        memory.set(0x5008, 0x1_0000_3100);
+        put_task(&mut memory, 0x9000, 3, child_flags, STATUS_COMPLETE);
+
+        let tasks = collect(|a, l| memory.read(a, l), &[], &[0x4000]).unwrap();
+        let ids: Vec<_> = tasks.iter().map(|task| task.id.unwrap()).collect();
+        assert_eq!(ids, [1, 2, 3]);
+        assert_eq!(tasks[0].state, TaskState::Suspended);
+        assert_eq!(tasks[1].parent, Some(0x4000));
+        assert!(tasks[1].cancelled);
+        assert_eq!(tasks[1].flags, ["future", "child", "group_child"]);
+        assert_eq!(tasks[1].continuations, [0x1_0000_3000, 0x1_0000_3100]);
+        assert_eq!(tasks[2].state, TaskState::Completed);
+        assert!(tasks[2].continuations.is_empty());
+
+        let err = collect(|a, l| memory.read(a, l), &[], &[0x5000]).unwrap_err();
+        assert_eq!(err, "0x5000 is not a Swift task");
+    }
+}
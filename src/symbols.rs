@@ -1,22 +1,63 @@
 use std::{
+    borrow::Cow,
     fs,
+    hash::{DefaultHasher, Hash, Hasher},
     path::{Path, PathBuf},
+    process::Command,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::{Duration, SystemTime},
 };
 
 use addr2line::{Frame, Loader, LoaderReader, Location};
 use anyhow::{anyhow, Context, Result};
 use object::{
-    read::{macho, ReadRef},
-    BinaryFormat, File as ObjectFile, Object, ObjectSegment,
+    macho::{
+        CPU_SUBTYPE_ARM64E, CPU_SUBTYPE_MASK, CPU_TYPE_ARM64, CPU_TYPE_ARM64_32, CPU_TYPE_X86_64,
+    },
+    read::{
+        macho::{self, FatArch},
+        ReadRef,
+    },
+    BinaryFormat, File as ObjectFile, FileKind, Object, ObjectSegment, ObjectSymbol,
 };
 
-type LoaderFrame<'a> = Frame<'a, LoaderReader<'a>>;
+use crate::swift_demangle;
+
+/// One function of an address's inlining chain.
+pub type LoaderFrame<'a> = Frame<'a, LoaderReader<'a>>;
+
+/// Slices of a universal binary picked, in order, when no architecture is
+/// asked for: those current iOS devices run.
+const PREFERRED_ARCHES: &[&str] = &["arm64e", "arm64"];
+
+/// Virtual address bits of an arm64 user-space pointer. The bits above hold
+/// the authentication code of a signed arm64e pointer, such as a return
+/// address a function saved.
+const ADDRESS_MASK: u64 = 0x0000_7fff_ffff_ffff;
+
+/// The architecture taken out of a universal (fat) binary.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Slice {
+    /// `arm64`, `arm64e`, `x86_64`, ...
+    pub arch: String,
+    /// Thin copy of the slice, read in place of the universal file, which
+    /// addr2line cannot.
+    pub binary: PathBuf,
+}
 
 #[allow(dead_code)]
 pub struct Image {
     pub name: String,
     pub path: PathBuf,
     pub uuid: Option<[u8; 16]>,
+    /// DWARF file of the dSYM matching `uuid`, read instead of `path` for
+    /// line tables, variables and symbols.
+    pub dsym: Option<PathBuf>,
+    /// Set when `path` is a universal binary.
+    pub slice: Option<Slice>,
     pub vmaddr_text: u64,
     pub slide: i64,
     pub dwarf: Loader,
@@ -26,11 +67,43 @@ pub struct SymbolContext {
     pub main: Image,
 }
 
+impl Image {
+    /// The file debug information is read from: the dSYM when one matched,
+    /// else the binary itself (its slice, for a universal one).
+    pub fn debug_path(&self) -> &Path {
+        self.dsym
+            .as_deref()
+            .or(self.slice.as_ref().map(|slice| slice.binary.as_path()))
+            .unwrap_or(&self.path)
+    }
+}
+
 impl SymbolContext {
-    pub fn new(app_path: &Path) -> Result<Self> {
+    /// Loads `app_path`, with its debug information from `dsym` (a `.dSYM`
+    /// bundle or the DWARF file inside one) or, without it, from a dSYM found
+    /// by [`find_dsym`]. Of a universal binary the `arch` slice is loaded,
+    /// or without one the first of [`PREFERRED_ARCHES`] it has.
+    pub fn new(app_path: &Path, dsym: Option<&Path>, arch: Option<&str>) -> Result<Self> {
         let data = fs::read(app_path)
             .with_context(|| format!("failed to read Mach-O {}", app_path.display()))?;
-        let file = ObjectFile::parse(&*data)
+        let slices = macho_slices(&data)
+            .map_err(|err| anyhow!("failed to parse Mach-O {}: {err}", app_path.display()))?;
+        let (macho, arch) = match &slices {
+            Some(slices) => {
+                let (arch, bytes) = pick_slice(slices, arch).ok_or_else(|| {
+                    let arches: Vec<&str> = slices.iter().map(|(arch, _)| arch.as_str()).collect();
+                    anyhow!(
+                        "{} has no {} slice; it has {}",
+                        app_path.display(),
+                        arch.unwrap_or("usable"),
+                        arches.join(", ")
+                    )
+                })?;
+                (*bytes, Some(arch.clone()))
+            }
+            None => (&data[..], None),
+        };
+        let file = ObjectFile::parse(macho)
             .map_err(|err| anyhow!("failed to parse Mach-O {}: {err}", app_path.display()))?;
         if file.format() != BinaryFormat::MachO {
             return Err(anyhow!(
@@ -42,8 +115,25 @@ impl SymbolContext {
 
         let vmaddr_text = find_text_vmaddr(&file)?;
         let uuid = extract_macho_uuid(&file)?;
-        let dwarf = Loader::new(app_path)
-            .map_err(|err| anyhow!("failed to load DWARF from {}: {err}", app_path.display()))?;
+        let slice = match arch {
+            Some(arch) => Some(Slice {
+                binary: write_slice(app_path, &arch, macho)?,
+                arch,
+            }),
+            None => None,
+        };
+        let dsym = match (dsym, uuid) {
+            (Some(dsym), uuid) => Some(resolve_dsym(dsym, uuid)?),
+            (None, Some(uuid)) => find_dsym(app_path, uuid),
+            (None, None) => None,
+        };
+        let dsym = dsym.map(|dsym| thin_dsym(&dsym, uuid)).transpose()?;
+        let debug_path = dsym
+            .as_deref()
+            .or(slice.as_ref().map(|slice| slice.binary.as_path()))
+            .unwrap_or(app_path);
+        let dwarf = Loader::new(debug_path)
+            .map_err(|err| anyhow!("failed to load DWARF from {}: {err}", debug_path.display()))?;
         let name = app_path
             .file_name()
             .map(|n| n.to_string_lossy().into_owned())
@@ -54,6 +144,8 @@ impl SymbolContext {
                 name,
                 path: app_path.to_path_buf(),
                 uuid,
+                dsym,
+                slice,
                 vmaddr_text,
                 slide: 0,
                 dwarf,
@@ -66,7 +158,11 @@ impl SymbolContext {
         self.main.slide = slide;
     }
 
+    /// The address in the binary of `remote_pc`, an address in the
+    /// inferior. Pointer authentication bits are stripped first, so signed
+    /// return addresses symbolicate too.
     pub fn translate_remote_pc(&self, remote_pc: u64) -> u64 {
+        let remote_pc = remote_pc & ADDRESS_MASK;
         if self.main.slide >= 0 {
             remote_pc.wrapping_sub(self.main.slide as u64)
         } else {
@@ -108,12 +204,442 @@ impl SymbolContext {
         Ok(frames)
     }
 
+    /// Demangled name of a symbolized frame's function, falling back to the raw name.
+    pub fn function_name(frame: &LoaderFrame<'_>) -> Option<String> {
+        frame.function.as_ref().and_then(|name| {
+            let raw = name.raw_name().ok();
+            raw.as_deref()
+                .and_then(swift_demangle::demangle)
+                .or_else(|| name.demangle().ok().map(|cow| cow.into_owned()))
+                .or_else(|| raw.map(|cow| cow.into_owned()))
+        })
+    }
+
+    /// Raw linkage name of a symbolized frame's function, when it differs
+    /// from [`Self::function_name`].
+    pub fn mangled_function_name(frame: &LoaderFrame<'_>) -> Option<String> {
+        let raw = frame.function.as_ref()?.raw_name().ok()?.into_owned();
+        (Self::function_name(frame).as_ref() != Some(&raw)).then_some(raw)
+    }
+
+    /// Name of the symbol-table entry covering `remote_pc`.
+    pub fn symbol_name(&self, remote_pc: u64) -> Option<String> {
+        let probe = self.translate_remote_pc(remote_pc);
+        self.main.dwarf.find_symbol(probe).map(str::to_string)
+    }
+
+    /// Resolves a symbol of the main image to its address in the inferior.
+    /// The Mach-O leading underscore is optional.
+    pub fn symbol_address(&self, name: &str) -> Result<Option<u64>> {
+        Ok(self
+            .lookup_symbols(name)?
+            .first()
+            .map(|symbol| symbol.address))
+    }
+
+    /// Every symbol of the main image whose raw name (leading underscore
+    /// optional) or demangled name is `name`, at its address in the inferior.
+    /// Swift symbols also match on their name without the signature, so
+    /// `MyApp.ViewController.viewDidLoad` finds
+    /// `MyApp.ViewController.viewDidLoad() -> ()`.
+    pub fn lookup_symbols(&self, name: &str) -> Result<Vec<SymbolMatch>> {
+        let path = self.main.debug_path();
+        let data = fs::read(path).with_context(|| format!("failed to read {path:?}"))?;
+        let file = ObjectFile::parse(&*data).context("failed to parse binary for symbols")?;
+        let mut matches: Vec<SymbolMatch> = file
+            .symbols()
+            .filter(|symbol| symbol.is_definition())
+            .filter_map(|symbol| {
+                let raw = symbol.name().ok()?;
+                let matched = raw == name
+                    || raw.strip_prefix('_') == Some(name)
+                    || swift_demangle::demangle(raw)
+                        .is_some_and(|demangled| swift_name_matches(&demangled, name))
+                    || addr2line::demangle_auto(Cow::Borrowed(raw), None) == name;
+                matched.then(|| SymbolMatch {
+                    name: raw.to_string(),
+                    address: self.local_to_remote(symbol.address()),
+                })
+            })
+            .collect();
+        matches.sort_by_key(|symbol| symbol.address);
+        matches.dedup_by_key(|symbol| symbol.address);
+        Ok(matches)
+    }
+
     #[cfg(any(test, feature = "cli"))]
     pub fn for_testing(main: Image) -> Self {
         Self { main }
     }
 }
 
+/// `raw` demangled as a Swift symbol, or else as a C++ or Rust one; `raw`
+/// itself when it is neither.
+pub fn demangle_symbol(raw: &str) -> String {
+    swift_demangle::demangle(raw)
+        .unwrap_or_else(|| addr2line::demangle_auto(Cow::Borrowed(raw), None).into_owned())
+}
+
+/// Whether the demangled Swift symbol `demangled` is `name`, or is `name`
+/// followed by its signature or type.
+fn swift_name_matches(demangled: &str, name: &str) -> bool {
+    demangled == name
+        || demangled
+            .strip_prefix(name)
+            .is_some_and(|rest| rest.starts_with('(') || rest.starts_with(" : "))
+}
+
+/// The DWARF file of `dsym` whose UUID is `uuid`. `dsym` is a `.dSYM`
+/// bundle, which holds one file per architecture, or one of those files.
+pub fn resolve_dsym(dsym: &Path, uuid: Option<[u8; 16]>) -> Result<PathBuf> {
+    let files = dsym_dwarf_files(dsym);
+    let Some(uuid) = uuid else {
+        return files
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow!("no DWARF files in {}", dsym.display()));
+    };
+    let mut found = Vec::new();
+    for file in files {
+        let uuids = file_uuids(&file);
+        if uuids.contains(&uuid) {
+            return Ok(file);
+        }
+        found.extend(uuids.iter().map(format_uuid));
+    }
+    Err(anyhow!(
+        "{} does not match the binary (UUID {}); it holds {}",
+        dsym.display(),
+        format_uuid(&uuid),
+        if found.is_empty() {
+            "no Mach-O DWARF files".to_string()
+        } else {
+            found.join(", ")
+        }
+    ))
+}
+
+/// Looks for the dSYM of the binary at `binary` whose UUID is `uuid`: next
+/// to the binary and its `.app` the way Xcode lays out build products, then
+/// through Spotlight, which indexes the UUIDs of every dSYM on the Mac.
+pub fn find_dsym(binary: &Path, uuid: [u8; 16]) -> Option<PathBuf> {
+    dsym_candidates(binary)
+        .into_iter()
+        .chain(spotlight_dsyms(uuid))
+        .flat_map(|bundle| dsym_dwarf_files(&bundle))
+        .find(|file| file_uuids(file).contains(&uuid))
+}
+
+/// `App.dSYM` beside `App`, and for a binary inside `App.app`, `App.app.dSYM`
+/// and then every other dSYM in the directory holding the bundle.
+fn dsym_candidates(binary: &Path) -> Vec<PathBuf> {
+    let mut candidates = Vec::new();
+    if let Some(name) = binary.file_name() {
+        let mut bundle = name.to_os_string();
+        bundle.push(".dSYM");
+        candidates.push(binary.with_file_name(bundle));
+    }
+    let app = binary
+        .ancestors()
+        .find(|dir| dir.extension().is_some_and(|ext| ext == "app"));
+    if let Some(app) = app {
+        let mut bundle = app.as_os_str().to_os_string();
+        bundle.push(".dSYM");
+        candidates.push(PathBuf::from(bundle));
+        if let Ok(entries) = app.parent().map_or(Ok(Vec::new()), |dir| {
+            fs::read_dir(dir).map(|entries| entries.flatten().map(|e| e.path()).collect())
+        }) {
+            let mut siblings: Vec<PathBuf> = entries
+                .into_iter()
+                .filter(|path| path.extension().is_some_and(|ext| ext == "dSYM"))
+                .filter(|path| !candidates.contains(path))
+                .collect();
+            siblings.sort();
+            candidates.extend(siblings);
+        }
+    }
+    candidates
+}
+
+/// dSYM bundles Spotlight knows for `uuid`; none where `mdfind` is missing.
+fn spotlight_dsyms(uuid: [u8; 16]) -> Vec<PathBuf> {
+    let query = format!("com_apple_xcode_dsym_uuids == {}", format_uuid(&uuid));
+    let Ok(output) = Command::new("mdfind").arg(query).output() else {
+        return Vec::new();
+    };
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(PathBuf::from)
+        .collect()
+}
+
+/// The files under `Contents/Resources/DWARF` of a bundle, or `path` itself
+/// when it is a file.
+fn dsym_dwarf_files(path: &Path) -> Vec<PathBuf> {
+    if path.is_file() {
+        return vec![path.to_path_buf()];
+    }
+    let Ok(entries) = fs::read_dir(path.join("Contents/Resources/DWARF")) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .collect();
+    files.sort();
+    files
+}
+
+/// UUIDs of the Mach-O at `path`, one per slice of a universal file.
+fn file_uuids(path: &Path) -> Vec<[u8; 16]> {
+    let Ok(data) = fs::read(path) else {
+        return Vec::new();
+    };
+    let uuid = |macho: &[u8]| extract_macho_uuid(&ObjectFile::parse(macho).ok()?).ok()?;
+    match macho_slices(&data) {
+        Ok(Some(slices)) => slices.iter().filter_map(|(_, bytes)| uuid(bytes)).collect(),
+        _ => uuid(&data).into_iter().collect(),
+    }
+}
+
+/// An architecture of a universal Mach-O and the bytes of its slice.
+pub type ArchSlice<'data> = (String, &'data [u8]);
+
+/// The slices of a universal Mach-O with their architectures, or `None`
+/// when `data` is not universal.
+pub fn macho_slices(data: &[u8]) -> Result<Option<Vec<ArchSlice<'_>>>> {
+    match FileKind::parse(data) {
+        Ok(FileKind::MachOFat32) => {
+            fat_slices(&macho::MachOFatFile32::parse(data)?, data).map(Some)
+        }
+        Ok(FileKind::MachOFat64) => {
+            fat_slices(&macho::MachOFatFile64::parse(data)?, data).map(Some)
+        }
+        _ => Ok(None),
+    }
+}
+
+fn fat_slices<'data, Fat: FatArch>(
+    fat: &macho::MachOFatFile<'data, Fat>,
+    data: &'data [u8],
+) -> Result<Vec<ArchSlice<'data>>> {
+    fat.arches()
+        .iter()
+        .map(|slice| {
+            Ok((
+                arch_name(slice.cputype(), slice.cpusubtype()),
+                slice.data(data)?,
+            ))
+        })
+        .collect()
+}
+
+/// The slice for `arch`, or without one the first of [`PREFERRED_ARCHES`]
+/// there is, else the first.
+fn pick_slice<'a, 'data>(
+    slices: &'a [ArchSlice<'data>],
+    arch: Option<&str>,
+) -> Option<&'a ArchSlice<'data>> {
+    match arch {
+        Some(arch) => slices.iter().find(|(name, _)| name == arch),
+        None => PREFERRED_ARCHES
+            .iter()
+            .find_map(|preferred| slices.iter().find(|(name, _)| name == preferred))
+            .or(slices.first()),
+    }
+}
+
+/// `data` itself, or the slice of a universal binary [`SymbolContext::new`]
+/// loads when no architecture is asked for.
+pub fn preferred_slice(data: &[u8]) -> &[u8] {
+    match macho_slices(data) {
+        Ok(Some(slices)) => pick_slice(&slices, None).map_or(data, |(_, bytes)| bytes),
+        _ => data,
+    }
+}
+
+/// `arm64`, `arm64e`, ... for a Mach-O CPU type and subtype; the raw
+/// numbers for others.
+pub fn arch_name(cputype: u32, cpusubtype: u32) -> String {
+    match (cputype, cpusubtype & !CPU_SUBTYPE_MASK) {
+        (CPU_TYPE_ARM64, CPU_SUBTYPE_ARM64E) => "arm64e".into(),
+        (CPU_TYPE_ARM64, _) => "arm64".into(),
+        (CPU_TYPE_ARM64_32, _) => "arm64_32".into(),
+        (CPU_TYPE_X86_64, _) => "x86_64".into(),
+        (cputype, subtype) => format!("{cputype:#x}/{subtype:#x}"),
+    }
+}
+
+/// Slices in [`slice_dir`] last used longer ago than this are removed when
+/// another build of the same file is written.
+const SLICE_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Writes `bytes`, the `arch` slice of the universal file `source`, to a
+/// file addr2line can load, in [`slice_dir`]. Files are named by a hash of
+/// their contents, so a copy left by an earlier run of the same build is
+/// reused (only if it holds exactly `bytes`), and a new one is renamed into
+/// place so a reader never sees it half written. Copies of other builds of
+/// `source` unused for [`SLICE_MAX_AGE`] are removed.
+fn write_slice(source: &Path, arch: &str, bytes: &[u8]) -> Result<PathBuf> {
+    let dir = slice_dir()?;
+    let name = source
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mut hasher = DefaultHasher::new();
+    bytes.hash(&mut hasher);
+    let file_name = format!("{name}-{:016x}.{arch}", hasher.finish());
+    let path = dir.join(&file_name);
+    if fs::read(&path).is_ok_and(|cached| cached == bytes) {
+        // Marks the copy as in use, so pruning leaves it alone.
+        let _ = fs::File::options()
+            .write(true)
+            .open(&path)
+            .and_then(|file| file.set_modified(SystemTime::now()));
+        return Ok(path);
+    }
+    static WRITES: AtomicUsize = AtomicUsize::new(0);
+    let write = WRITES.fetch_add(1, Ordering::Relaxed);
+    let partial = dir.join(format!("{file_name}.{}.{write}", std::process::id()));
+    fs::write(&partial, bytes)
+        .and_then(|()| fs::rename(&partial, &path))
+        .with_context(|| format!("failed to write the {arch} slice of {}", source.display()))?;
+    prune_slices(&dir, &name, arch, &file_name);
+    Ok(path)
+}
+
+/// Removes the copies of other builds of `name`'s `arch` slice that have
+/// not been used for [`SLICE_MAX_AGE`].
+fn prune_slices(dir: &Path, name: &str, arch: &str, keep: &str) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let suffix = format!(".{arch}");
+    for entry in entries.flatten() {
+        let file_name = entry.file_name().to_string_lossy().into_owned();
+        let stale = file_name != keep
+            && file_name
+                .strip_prefix(name)
+                .and_then(|rest| rest.strip_prefix('-'))
+                .is_some_and(|rest| rest.ends_with(&suffix))
+            && entry
+                .metadata()
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                .and_then(|modified| modified.elapsed().ok())
+                .is_some_and(|age| age > SLICE_MAX_AGE);
+        if stale {
+            let _ = fs::remove_file(entry.path());
+        }
+    }
+}
+
+/// Directory the slices of universal files are written to, shared by this
+/// user's runs. It must be a real directory only its owner can use, so
+/// nothing another user left there can be loaded in place of a slice.
+fn slice_dir() -> Result<PathBuf> {
+    static DIR: Mutex<Option<PathBuf>> = Mutex::new(None);
+    let mut slot = DIR.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Some(dir) = &*slot {
+        return Ok(dir.clone());
+    }
+    let user = std::env::var("USER")
+        .ok()
+        .filter(|user| !user.is_empty() && !user.contains(std::path::is_separator))
+        .unwrap_or_else(|| "unknown".into());
+    let dir = std::env::temp_dir().join(format!("swiftscope-slices-{user}"));
+    let mut builder = fs::DirBuilder::new();
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    match builder.create(&dir) {
+        Ok(()) => {}
+        Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => {}
+        Err(err) => return Err(err).with_context(|| format!("failed to create {}", dir.display())),
+    }
+    check_private_dir(&dir)?;
+    *slot = Some(dir.clone());
+    Ok(dir)
+}
+
+/// Fails unless `dir` is a directory (not a link to one) owned by this
+/// process's user and closed to everyone else.
+fn check_private_dir(dir: &Path) -> Result<()> {
+    let metadata = fs::symlink_metadata(dir)
+        .with_context(|| format!("failed to inspect {}", dir.display()))?;
+    if !metadata.is_dir() {
+        return Err(anyhow!("{} is not a directory", dir.display()));
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        let refuse = || {
+            anyhow!(
+                "{} must belong to this user with mode 0700; remove it and retry",
+                dir.display()
+            )
+        };
+        if metadata.mode() & 0o077 != 0 {
+            return Err(refuse());
+        }
+        // The owner of a file this process creates is its own user.
+        let probe = dir.join(format!(".owner-{}", std::process::id()));
+        let owner = fs::File::options()
+            .write(true)
+            .create_new(true)
+            .open(&probe)
+            .and_then(|file| file.metadata())
+            .map(|probe| probe.uid());
+        let _ = fs::remove_file(&probe);
+        if owner.map_err(|_| refuse())? != metadata.uid() {
+            return Err(refuse());
+        }
+    }
+    Ok(())
+}
+
+/// `dsym` itself, or for a universal dSYM the thin copy of its slice whose
+/// UUID is `uuid` (without one, of its preferred slice).
+fn thin_dsym(dsym: &Path, uuid: Option<[u8; 16]>) -> Result<PathBuf> {
+    let data = fs::read(dsym).with_context(|| format!("failed to read {}", dsym.display()))?;
+    let Some(slices) = macho_slices(&data)? else {
+        return Ok(dsym.to_path_buf());
+    };
+    let slice = match uuid {
+        Some(uuid) => slices.iter().find(|(_, bytes)| {
+            ObjectFile::parse(*bytes)
+                .ok()
+                .and_then(|file| extract_macho_uuid(&file).ok()?)
+                == Some(uuid)
+        }),
+        None => pick_slice(&slices, None),
+    };
+    let (arch, bytes) =
+        slice.ok_or_else(|| anyhow!("no slice of {} matches the binary", dsym.display()))?;
+    write_slice(dsym, arch, bytes)
+}
+
+/// Renders a Mach-O UUID the way dyld reports it (`8B5A9D3C-1F2E-...`).
+pub fn format_uuid(uuid: &[u8; 16]) -> String {
+    let hex: String = uuid.iter().map(|byte| format!("{byte:02X}")).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+/// A symbol returned by [`SymbolContext::lookup_symbols`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymbolMatch {
+    pub name: String,
+    pub address: u64,
+}
+
 pub fn find_text_vmaddr(file: &ObjectFile<'_>) -> Result<u64> {
     if file.format() != BinaryFormat::MachO {
         return Err(anyhow!("expected Mach-O format"));
@@ -196,6 +722,32 @@ mod tests {
         assert_eq!(uuid, TEST_UUID);
     }
 
+    #[test]
+    fn dsyms_are_found_beside_the_app_and_matched_by_uuid() {
+        let root = std::env::temp_dir().join(format!("swiftscope-dsym-{}", std::process::id()));
+        let app = root.join("Debug-iphoneos/MyApp.app");
+        let dwarf = root.join("Debug-iphoneos/MyApp.app.dSYM/Contents/Resources/DWARF");
+        fs::create_dir_all(&app).unwrap();
+        fs::create_dir_all(&dwarf).unwrap();
+        let binary = app.join("MyApp");
+        fs::write(&binary, build_test_macho(0x1000, TEST_UUID)).unwrap();
+        fs::write(dwarf.join("MyApp"), build_test_macho(0x1000, TEST_UUID)).unwrap();
+
+        assert_eq!(find_dsym(&binary, TEST_UUID), Some(dwarf.join("MyApp")));
+        let bundle = dwarf.ancestors().nth(3).unwrap();
+        assert_eq!(
+            resolve_dsym(bundle, Some(TEST_UUID)).unwrap(),
+            dwarf.join("MyApp")
+        );
+        let mut other = TEST_UUID;
+        other[0] = 0xff;
+        assert_eq!(find_dsym(&binary, other), None);
+        let err = resolve_dsym(bundle, Some(other)).unwrap_err().to_string();
+        assert!(err.contains("does not match"), "{err}");
+        assert!(err.contains(&format_uuid(&TEST_UUID)), "{err}");
+        fs::remove_dir_all(&root).unwrap();
+    }
+
     #[test]
     fn translate_remote_pc_applies_slide() {
         let Some(dummy_loader) = test_loader() else {
@@ -206,6 +758,8 @@ mod tests {
             name: "test".into(),
             path: PathBuf::from("/tmp/test"),
             uuid: None,
+            dsym: None,
+            slice: None,
             vmaddr_text: 0x1000,
             slide: 0,
             dwarf: dummy_loader,
@@ -213,6 +767,8 @@ mod tests {
         ctx.set_slide(0x4000);
         let translated = ctx.translate_remote_pc(0x9000);
         assert_eq!(translated, 0x5000);
+        // A return address signed with pointer authentication.
+        assert_eq!(ctx.translate_remote_pc(0x002d_0000_0000_9000), 0x5000);
     }
 
     #[test]
@@ -225,6 +781,8 @@ mod tests {
             name: "test".into(),
             path: PathBuf::from("/tmp/test"),
             uuid: None,
+            dsym: None,
+            slice: None,
             vmaddr_text: 0x0,
             slide: 0x2000,
             dwarf: dummy_loader,
@@ -239,7 +797,7 @@ mod tests {
     #[test]
     fn symbolize_frames_handles_missing_or_real_debug_info() {
         let exe = std::env::current_exe().unwrap();
-        let ctx = match SymbolContext::new(&exe) {
+        let ctx = match SymbolContext::new(&exe, None, None) {
             Ok(ctx) => ctx,
             Err(err) => {
                 eprintln!("skipping symbolize_frames_handles_missing_or_real_debug_info: {err}");
@@ -253,10 +811,133 @@ mod tests {
         );
     }
 
+    #[test]
+    fn swift_symbols_match_with_or_without_their_signature() {
+        let demangled = demangle_symbol("$s5MyApp14ViewControllerC11viewDidLoadyyF");
+        assert_eq!(demangled, "MyApp.ViewController.viewDidLoad() -> ()");
+        assert!(swift_name_matches(
+            &demangled,
+            "MyApp.ViewController.viewDidLoad"
+        ));
+        assert!(swift_name_matches(&demangled, &demangled));
+        assert!(!swift_name_matches(&demangled, "MyApp.ViewController.view"));
+        assert!(swift_name_matches(
+            "MyApp.Cart.items.getter : [String]",
+            "MyApp.Cart.items.getter"
+        ));
+        assert_eq!(demangle_symbol("_main"), "_main");
+    }
+
+    #[test]
+    fn universal_binaries_load_the_device_slice() {
+        let root = std::env::temp_dir().join(format!("swiftscope-fat-{}", std::process::id()));
+        let dwarf = root.join("MyApp.dSYM/Contents/Resources/DWARF");
+        fs::create_dir_all(&dwarf).unwrap();
+        let mut arm64e_uuid = TEST_UUID;
+        arm64e_uuid[0] = 0xee;
+        let arm64 = build_test_macho(0x1000, TEST_UUID);
+        let mut arm64e = build_test_macho(0x2000, arm64e_uuid);
+        arm64e[8..12].copy_from_slice(&CPU_SUBTYPE_ARM64E.to_le_bytes());
+        let binary = root.join("MyApp");
+        fs::write(&binary, build_fat(&[&arm64, &arm64e])).unwrap();
+        fs::write(dwarf.join("MyApp"), build_fat(&[&arm64e, &arm64])).unwrap();
+
+        let data = fs::read(&binary).unwrap();
+        let arches: Vec<String> = macho_slices(&data)
+            .unwrap()
+            .unwrap()
+            .into_iter()
+            .map(|(arch, _)| arch)
+            .collect();
+        assert_eq!(arches, ["arm64", "arm64e"]);
+        assert_eq!(preferred_slice(&data), &arm64e[..]);
+        assert!(macho_slices(&arm64).unwrap().is_none());
+
+        let ctx = SymbolContext::new(&binary, None, None).unwrap();
+        let slice = ctx.main.slice.clone().unwrap();
+        assert_eq!(slice.arch, "arm64e");
+        assert_eq!(fs::read(&slice.binary).unwrap(), arm64e);
+        assert_eq!(ctx.main.uuid, Some(arm64e_uuid));
+        assert_eq!(ctx.main.vmaddr_text, 0x2000);
+        let dsym = ctx.main.dsym.clone().unwrap();
+        assert_eq!(fs::read(&dsym).unwrap(), arm64e, "the dSYM is thinned too");
+        assert_eq!(ctx.main.debug_path(), dsym);
+
+        let ctx = SymbolContext::new(&binary, None, Some("arm64")).unwrap();
+        assert_eq!(ctx.main.uuid, Some(TEST_UUID));
+        assert_eq!(fs::read(ctx.main.debug_path()).unwrap(), arm64);
+        let err = SymbolContext::new(&binary, None, Some("x86_64"))
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(
+            err.contains("no x86_64 slice; it has arm64, arm64e"),
+            "{err}"
+        );
+        for path in [slice.binary, dsym, ctx.main.dsym.unwrap()] {
+            fs::remove_file(path).ok();
+        }
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn slices_are_cached_privately_by_content() {
+        let source = Path::new("/nowhere/SliceTest");
+        let first = write_slice(source, "arm64", b"first build").unwrap();
+        let dir = first.parent().unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(dir).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o700);
+        }
+        // The same bytes reuse the copy; another build of the same size
+        // gets its own.
+        assert_eq!(write_slice(source, "arm64", b"first build").unwrap(), first);
+        let second = write_slice(source, "arm64", b"other build").unwrap();
+        assert_ne!(second, first);
+        assert_eq!(fs::read(&second).unwrap(), b"other build");
+
+        // A copy of another build unused for a day goes when one is written.
+        let stale = SystemTime::now() - SLICE_MAX_AGE - Duration::from_secs(60);
+        fs::File::options()
+            .write(true)
+            .open(&first)
+            .unwrap()
+            .set_modified(stale)
+            .unwrap();
+        let third = write_slice(source, "arm64", b"third build").unwrap();
+        assert!(!first.exists());
+        assert!(second.exists());
+        for path in [second, third] {
+            fs::remove_file(path).unwrap();
+        }
+    }
+
+    /// A universal binary of `slices`, each at a 16 KiB boundary.
+    fn build_fat(slices: &[&[u8]]) -> Vec<u8> {
+        const ALIGN: usize = 0x4000;
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&0xcafe_babeu32.to_be_bytes());
+        buf.extend_from_slice(&(slices.len() as u32).to_be_bytes());
+        for (index, slice) in slices.iter().enumerate() {
+            let cputype = u32::from_le_bytes(slice[4..8].try_into().unwrap());
+            let cpusubtype = u32::from_le_bytes(slice[8..12].try_into().unwrap());
+            buf.extend_from_slice(&cputype.to_be_bytes());
+            buf.extend_from_slice(&cpusubtype.to_be_bytes());
+            buf.extend_from_slice(&(((index + 1) * ALIGN) as u32).to_be_bytes());
+            buf.extend_from_slice(&(slice.len() as u32).to_be_bytes());
+            buf.extend_from_slice(&14u32.to_be_bytes()); // 2^14 alignment
+        }
+        for slice in slices {
+            buf.resize(buf.len().next_multiple_of(ALIGN), 0);
+            buf.extend_from_slice(slice);
+        }
+        buf
+    }
+
     fn build_test_macho(vmaddr: u64, uuid: [u8; 16]) -> Vec<u8> {
-        let mut commands = Vec::new();
-        commands.push(build_segment_command(vmaddr));
-        commands.push(build_uuid_command(uuid));
+        let commands = vec![build_segment_command(vmaddr), build_uuid_command(uuid)];
         build_header(&commands)
     }
 
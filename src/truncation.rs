@@ -0,0 +1,260 @@
+//! Size limits that keep responses inside an agent's context budget.
+//!
+//! Strings longer than `max_string_len` bytes are cut there and end in
+//! `…(+1234 more bytes)`; arrays keep their first `max_children` items (or
+//! `max_frames` for `stacktrace` and `frames` arrays) followed by a
+//! `…(+12 more items)` string. `code`, `action` and `request_id` fields are
+//! never cut. Unset limits leave values untouched.
+//!
+//! `all_stacktraces` lists each thread's frames as indices into a shared
+//! `frames` table, which is not cut itself: the threads' index lists are, the
+//! threads are marked `truncated`, and the table is rebuilt from the frames
+//! still referenced, renumbered in order.
+
+use std::collections::HashMap;
+
+use serde_json::{Map, Value};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Limits {
+    pub max_string_len: Option<usize>,
+    pub max_children: Option<usize>,
+    pub max_frames: Option<usize>,
+}
+
+impl Limits {
+    /// These limits, with the unset ones taken from `defaults`.
+    pub fn or(self, defaults: Limits) -> Limits {
+        Limits {
+            max_string_len: self.max_string_len.or(defaults.max_string_len),
+            max_children: self.max_children.or(defaults.max_children),
+            max_frames: self.max_frames.or(defaults.max_frames),
+        }
+    }
+
+    pub fn is_unlimited(&self) -> bool {
+        *self == Limits::default()
+    }
+}
+
+/// Arrays whose items are stack frames.
+const FRAME_KEYS: [&str; 3] = ["stacktrace", "frames", "stackFrames"];
+
+/// Fields clients match on, never cut.
+const KEPT_KEYS: [&str; 3] = ["code", "action", "request_id"];
+
+/// Truncates `value` in place according to `limits`.
+pub fn truncate(value: &mut Value, limits: &Limits) {
+    if limits.is_unlimited() {
+        return;
+    }
+    truncate_value(value, limits, limits.max_children);
+}
+
+fn truncate_value(value: &mut Value, limits: &Limits, max_items: Option<usize>) {
+    match value {
+        Value::String(text) => {
+            if let Some(max) = limits.max_string_len {
+                truncate_string(text, max);
+            }
+        }
+        Value::Array(items) => {
+            for item in items.iter_mut() {
+                truncate_value(item, limits, limits.max_children);
+            }
+            if let Some(max) = max_items.filter(|max| items.len() > *max) {
+                let dropped = items.len() - max;
+                items.truncate(max);
+                items.push(Value::String(format!("…(+{dropped} more items)")));
+            }
+        }
+        Value::Object(map) => {
+            let shared_frames = has_shared_frames(map);
+            for (key, field) in map.iter_mut() {
+                if KEPT_KEYS.contains(&key.as_str()) || (shared_frames && key == "frames") {
+                    continue;
+                }
+                let max_items = if FRAME_KEYS.contains(&key.as_str()) {
+                    limits.max_frames.or(limits.max_children)
+                } else {
+                    limits.max_children
+                };
+                truncate_value(field, limits, max_items);
+            }
+            if shared_frames {
+                rebuild_shared_frames(map, limits);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Whether `map` holds a `frames` table that its `threads` refer to by index.
+fn has_shared_frames(map: &Map<String, Value>) -> bool {
+    let (Some(Value::Array(_)), Some(Value::Array(threads))) =
+        (map.get("frames"), map.get("threads"))
+    else {
+        return false;
+    };
+    threads.iter().filter_map(Value::as_object).all(|thread| {
+        thread
+            .get("frames")
+            .and_then(Value::as_array)
+            .is_some_and(|indices| indices.iter().all(Value::is_u64))
+    })
+}
+
+/// Replaces the `frames` table with the frames the (already cut) threads
+/// still refer to, and renumbers their indices to match.
+fn rebuild_shared_frames(map: &mut Map<String, Value>, limits: &Limits) {
+    let Some(Value::Array(table)) = map.remove("frames") else {
+        return;
+    };
+    let mut kept = Vec::new();
+    let mut renumbered = HashMap::new();
+    if let Some(Value::Array(threads)) = map.get_mut("threads") {
+        for thread in threads.iter_mut().filter_map(Value::as_object_mut) {
+            let Some(Value::Array(indices)) = thread.get_mut("frames") else {
+                continue;
+            };
+            // An index list is cut with a marker; the thread's flag says so instead.
+            let cut = indices.last().is_some_and(Value::is_string);
+            if cut {
+                indices.pop();
+            }
+            for index in indices.iter_mut() {
+                let Some(old) = index.as_u64() else {
+                    continue;
+                };
+                let new = *renumbered.entry(old).or_insert_with(|| {
+                    kept.push(table.get(old as usize).cloned().unwrap_or(Value::Null));
+                    kept.len() - 1
+                });
+                *index = Value::from(new);
+            }
+            if cut {
+                thread.insert("truncated".into(), Value::Bool(true));
+            }
+        }
+    }
+    for frame in &mut kept {
+        truncate_value(frame, limits, limits.max_children);
+    }
+    map.insert("frames".into(), Value::Array(kept));
+}
+
+fn truncate_string(text: &mut String, max: usize) {
+    if text.len() <= max {
+        return;
+    }
+    let mut end = max;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    let dropped = text.len() - end;
+    text.truncate(end);
+    text.push_str(&format!("…(+{dropped} more bytes)"));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn long_strings_and_arrays_get_markers() {
+        let mut value = json!({
+            "ok": true,
+            "action": "describe_everything",
+            "summary": "x".repeat(1300),
+            "children": [1, 2, 3, 4],
+            "stacktrace": [{ "function": "a" }, { "function": "b" }, { "function": "c" }],
+        });
+        let limits = Limits {
+            max_string_len: Some(64),
+            max_children: Some(3),
+            max_frames: Some(1),
+        };
+        truncate(&mut value, &limits);
+        assert_eq!(
+            value["summary"],
+            format!("{}…(+1236 more bytes)", "x".repeat(64))
+        );
+        assert_eq!(value["children"], json!([1, 2, 3, "…(+1 more items)"]));
+        assert_eq!(
+            value["stacktrace"],
+            json!([{ "function": "a" }, "…(+2 more items)"])
+        );
+        assert_eq!(value["ok"], true);
+        assert_eq!(value["action"], "describe_everything");
+    }
+
+    #[test]
+    fn cuts_fall_on_char_boundaries_and_defaults_fill_gaps() {
+        let mut text = "héllo".to_string();
+        truncate_string(&mut text, 2);
+        assert_eq!(text, "h…(+5 more bytes)");
+
+        let request = Limits {
+            max_children: Some(1),
+            ..Limits::default()
+        };
+        let defaults = Limits {
+            max_children: Some(5),
+            max_string_len: Some(10),
+            ..Limits::default()
+        };
+        let merged = request.or(defaults);
+        assert_eq!(merged.max_children, Some(1));
+        assert_eq!(merged.max_string_len, Some(10));
+
+        let mut untouched = json!({ "items": [1, 2, 3] });
+        truncate(&mut untouched, &Limits::default());
+        assert_eq!(untouched, json!({ "items": [1, 2, 3] }));
+    }
+
+    #[test]
+    fn shared_frame_tables_are_rebuilt_not_cut() {
+        let frame = |name: &str| json!({ "function": name });
+        let mut value = json!({
+            "ok": true,
+            "frames": [frame("a"), frame("b"), frame("c"), frame("d"), frame("e")],
+            "threads": [
+                { "id": 1, "frames": [3, 4, 2], "truncated": false },
+                { "id": 2, "frames": [0, 1, 2], "truncated": false },
+            ],
+        });
+        let limits = Limits {
+            max_frames: Some(2),
+            ..Limits::default()
+        };
+        truncate(&mut value, &limits);
+        assert_eq!(
+            value["threads"],
+            json!([
+                { "id": 1, "frames": [0, 1], "truncated": true },
+                { "id": 2, "frames": [2, 3], "truncated": true },
+            ])
+        );
+        assert_eq!(
+            value["frames"],
+            json!([frame("d"), frame("e"), frame("a"), frame("b")])
+        );
+
+        // Frames of threads that were cut away are dropped from the table.
+        let mut value = json!({
+            "frames": [frame("a"), frame("b")],
+            "threads": [{ "id": 1, "frames": [1] }, { "id": 2, "frames": [0] }],
+        });
+        let limits = Limits {
+            max_children: Some(1),
+            ..Limits::default()
+        };
+        truncate(&mut value, &limits);
+        assert_eq!(
+            value["threads"],
+            json!([{ "id": 1, "frames": [0] }, "…(+1 more items)"])
+        );
+        assert_eq!(value["frames"], json!([frame("b")]));
+    }
+}
@@ -0,0 +1,176 @@
+//! Number formatting for variable and evaluation values.
+//!
+//! Values arrive as text; [`apply`] rewrites the `value`/`result` of every
+//! object whose `type` names an integer or floating-point type, so an agent
+//! can ask for hex integers or fixed float precision instead of reparsing
+//! strings. Anything that does not parse as a number is left alone.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::verbosity::shorten_type;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IntegerFormat {
+    #[default]
+    Decimal,
+    Hex,
+}
+
+/// Formatting options; unset fields fall back to the session's options.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValueFormat {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub integers: Option<IntegerFormat>,
+    /// Digits after the decimal point for floating-point values.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub float_precision: Option<usize>,
+    /// Leave values exactly as the debugger rendered them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw: Option<bool>,
+}
+
+impl ValueFormat {
+    /// `self`, with unset fields taken from `base`.
+    pub fn or(self, base: Self) -> Self {
+        Self {
+            integers: self.integers.or(base.integers),
+            float_precision: self.float_precision.or(base.float_precision),
+            raw: self.raw.or(base.raw),
+        }
+    }
+
+    fn is_noop(&self) -> bool {
+        self.raw == Some(true)
+            || (self.integers.unwrap_or_default() == IntegerFormat::Decimal
+                && self.float_precision.is_none())
+    }
+}
+
+/// Rewrites numeric values in `value` in place according to `format`.
+pub fn apply(value: &mut Value, format: &ValueFormat) {
+    if format.is_noop() {
+        return;
+    }
+    match value {
+        Value::Object(map) => {
+            let ty = map.get("type").and_then(Value::as_str).map(shorten_type);
+            if let Some(ty) = ty {
+                for key in ["value", "result"] {
+                    if let Some(Value::String(text)) = map.get_mut(key) {
+                        if let Some(formatted) = format_number(&ty, text, format) {
+                            *text = formatted;
+                        }
+                    }
+                }
+            }
+            map.values_mut().for_each(|field| apply(field, format));
+        }
+        Value::Array(items) => items.iter_mut().for_each(|item| apply(item, format)),
+        _ => {}
+    }
+}
+
+fn format_number(ty: &str, text: &str, format: &ValueFormat) -> Option<String> {
+    let text = text.trim();
+    if is_integer_type(ty) {
+        if format.integers? != IntegerFormat::Hex {
+            return None;
+        }
+        let value: i128 = text.parse().ok()?;
+        return Some(if value < 0 {
+            format!("-0x{:x}", value.unsigned_abs())
+        } else {
+            format!("0x{value:x}")
+        });
+    }
+    if is_float_type(ty) {
+        let value: f64 = text.parse().ok()?;
+        return Some(format!("{value:.*}", format.float_precision?));
+    }
+    None
+}
+
+fn is_integer_type(ty: &str) -> bool {
+    let ty = ty.trim();
+    let bare = ty
+        .trim_start_matches("unsigned ")
+        .trim_start_matches("signed ");
+    matches!(
+        bare,
+        "Int"
+            | "UInt"
+            | "int"
+            | "short"
+            | "long"
+            | "long long"
+            | "long int"
+            | "short int"
+            | "size_t"
+            | "ssize_t"
+            | "NSInteger"
+            | "NSUInteger"
+            | "intptr_t"
+            | "uintptr_t"
+    ) || ["Int", "UInt", "int", "uint"].iter().any(|prefix| {
+        bare.strip_prefix(prefix)
+            .map(|bits| bits.trim_end_matches("_t"))
+            .is_some_and(|bits| matches!(bits, "8" | "16" | "32" | "64"))
+    })
+}
+
+fn is_float_type(ty: &str) -> bool {
+    matches!(
+        ty.trim(),
+        "Float"
+            | "Double"
+            | "Float32"
+            | "Float64"
+            | "Float80"
+            | "CGFloat"
+            | "TimeInterval"
+            | "float"
+            | "double"
+            | "long double"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn hex_and_precision_apply_by_type() {
+        let mut value = json!({
+            "locals": [
+                { "name": "mask", "type": "Swift.UInt32", "value": "255" },
+                { "name": "delta", "type": "int32_t", "value": "-16" },
+                { "name": "ratio", "type": "Swift.Double", "value": "0.3333333333" },
+                { "name": "title", "type": "Swift.String", "value": "42" }
+            ],
+            "result": "7",
+            "type": "long"
+        });
+        let format = ValueFormat {
+            integers: Some(IntegerFormat::Hex),
+            float_precision: Some(2),
+            raw: None,
+        };
+        apply(&mut value, &format);
+        assert_eq!(value["locals"][0]["value"], "0xff");
+        assert_eq!(value["locals"][1]["value"], "-0x10");
+        assert_eq!(value["locals"][2]["value"], "0.33");
+        assert_eq!(value["locals"][3]["value"], "42");
+        assert_eq!(value["result"], "0x7");
+
+        let raw = ValueFormat {
+            raw: Some(true),
+            ..ValueFormat::default()
+        };
+        let mut untouched = json!({ "type": "Int", "value": "10" });
+        apply(&mut untouched, &raw.or(format));
+        assert_eq!(untouched["value"], "10");
+    }
+}
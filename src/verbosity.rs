@@ -0,0 +1,186 @@
+//! Response shaping for token-frugal clients.
+//!
+//! `full` leaves payloads untouched. `normal` drops `null` fields. `minimal`
+//! also drops empty strings, arrays and objects, reduces source and image
+//! paths to their file name, and strips module qualifiers from the types of
+//! values (`Swift.Array<Swift.Int>` becomes `Array<Int>`). Only the records
+//! those fields belong to are rewritten: a `file` next to a `line` (frames,
+//! breakpoints, symbolicated addresses), a `path` next to `lines` (source
+//! listings) or `load_address` (modules), and a `type` next to a `value` or
+//! `result` (variables and evaluation results). Any other `file`, `path` or
+//! `type`, such as an artifact's path or an event's type, is left alone.
+
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Verbosity {
+    Minimal,
+    #[default]
+    Normal,
+    Full,
+}
+
+impl FromStr for Verbosity {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text {
+            "minimal" => Ok(Self::Minimal),
+            "normal" => Ok(Self::Normal),
+            "full" => Ok(Self::Full),
+            other => Err(format!(
+                "unknown verbosity `{other}` (expected minimal, normal or full)"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Verbosity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Minimal => "minimal",
+            Self::Normal => "normal",
+            Self::Full => "full",
+        })
+    }
+}
+
+/// Rewrites `value` in place according to `verbosity`.
+pub fn shape(value: &mut Value, verbosity: Verbosity) {
+    if verbosity == Verbosity::Full {
+        return;
+    }
+    match value {
+        Value::Object(map) => {
+            // Records are recognised before their empty fields are dropped.
+            if verbosity == Verbosity::Minimal {
+                shorten_record(map);
+            }
+            map.retain(|_, field| !is_droppable(field, verbosity));
+            for field in map.values_mut() {
+                shape(field, verbosity);
+            }
+            // Children may have become empty once their own fields were dropped.
+            map.retain(|_, field| !is_droppable(field, verbosity));
+        }
+        Value::Array(items) => items.iter_mut().for_each(|item| shape(item, verbosity)),
+        _ => {}
+    }
+}
+
+/// Shortens the path and type fields of the envelope records that carry them.
+fn shorten_record(map: &mut Map<String, Value>) {
+    let has = |key: &str| map.contains_key(key);
+    let source_file = has("line");
+    let image_or_listing = has("lines") || has("load_address");
+    let typed_value = has("value") || has("result");
+    for (key, field) in map.iter_mut() {
+        let Value::String(text) = field else {
+            continue;
+        };
+        match key.as_str() {
+            "file" if source_file => *text = file_name(text).to_string(),
+            "path" if image_or_listing => *text = file_name(text).to_string(),
+            "type" if typed_value => *text = shorten_type(text),
+            _ => {}
+        }
+    }
+}
+
+fn is_droppable(value: &Value, verbosity: Verbosity) -> bool {
+    match value {
+        Value::Null => true,
+        Value::String(text) => verbosity == Verbosity::Minimal && text.is_empty(),
+        Value::Array(items) => verbosity == Verbosity::Minimal && items.is_empty(),
+        Value::Object(map) => verbosity == Verbosity::Minimal && map.is_empty(),
+        _ => false,
+    }
+}
+
+fn file_name(path: &str) -> &str {
+    path.rsplit(['/', '\\']).next().unwrap_or(path)
+}
+
+/// Drops every `Module.` qualifier from the identifiers in a type name.
+pub fn shorten_type(ty: &str) -> String {
+    let mut out = String::with_capacity(ty.len());
+    let mut segment = String::new();
+    for ch in ty.chars() {
+        if ch.is_alphanumeric() || ch == '_' {
+            segment.push(ch);
+        } else if ch == '.' && !segment.is_empty() {
+            segment.clear();
+        } else {
+            out.push_str(&segment);
+            segment.clear();
+            out.push(ch);
+        }
+    }
+    out.push_str(&segment);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn minimal_strips_paths_types_and_empty_fields() {
+        let mut value = json!({
+            "ok": true,
+            "stacktrace": [{ "file": "/Users/dev/App/View.swift", "line": 3, "hint": null }],
+            "variable": { "type": "Swift.Array<MyApp.User>", "value": "", "children": [] },
+            "deadlocked": false
+        });
+        shape(&mut value, Verbosity::Minimal);
+        assert_eq!(
+            value,
+            json!({
+                "ok": true,
+                "stacktrace": [{ "file": "View.swift", "line": 3 }],
+                "variable": { "type": "Array<User>" },
+                "deadlocked": false
+            })
+        );
+    }
+
+    #[test]
+    fn minimal_leaves_fields_outside_known_records_alone() {
+        let original = json!({
+            "artifact": { "id": "a1", "path": "/tmp/artifacts/session-2/a1.bin", "type": "memory" },
+            "event": { "type": "Swift.Stopped", "file": "/Users/dev/App/notes.txt" },
+            "schema": { "type": "object", "properties": { "path": { "type": "string" } } }
+        });
+        let mut value = original.clone();
+        shape(&mut value, Verbosity::Minimal);
+        assert_eq!(value, original);
+
+        let mut records = json!({
+            "modules": [{ "path": "/usr/lib/libobjc.A.dylib", "load_address": "0x1000" }],
+            "source": { "path": "/Users/dev/App/View.swift", "line": 3, "lines": [{ "number": 3 }] },
+            "result": "3", "type": "Swift.Int"
+        });
+        shape(&mut records, Verbosity::Minimal);
+        assert_eq!(records["modules"][0]["path"], "libobjc.A.dylib");
+        assert_eq!(records["source"]["path"], "View.swift");
+        assert_eq!(records["type"], "Int");
+    }
+
+    #[test]
+    fn normal_only_drops_nulls_and_full_keeps_everything() {
+        let original = json!({ "function": null, "frames": [], "file": "/a/b.swift" });
+        let mut normal = original.clone();
+        shape(&mut normal, Verbosity::Normal);
+        assert_eq!(normal, json!({ "frames": [], "file": "/a/b.swift" }));
+        let mut full = original.clone();
+        shape(&mut full, Verbosity::Full);
+        assert_eq!(full, original);
+        assert_eq!("minimal".parse(), Ok(Verbosity::Minimal));
+        assert!("loud".parse::<Verbosity>().is_err());
+    }
+}
@@ -1,3 +1,6 @@
+//! Drives the adapter end to end; it symbolizes its own test binary, so it needs Mach-O.
+#![cfg(target_os = "macos")]
+
 use std::{
     env,
     io::Write,